@@ -0,0 +1,216 @@
+//! Harness driving the built `todo_backend` wasm through PocketIC.
+//!
+//! The tests need the PocketIC server binary (`POCKET_IC_BIN`) and the canister built with the
+//! `testing` feature (`TODO_BACKEND_WASM`), so they are ignored by default. Run them with
+//! `just integration-test`.
+
+use std::time::Duration;
+
+use candid::{utils::ArgumentEncoder, CandidType, Deserialize, Principal};
+use pocket_ic::{query_candid_as, update_candid_as, PocketIc};
+
+/// Path of the wasm used when `TODO_BACKEND_WASM` is not set, relative to the workspace root.
+const DEFAULT_WASM: &str = "target/wasm32-unknown-unknown/release/todo_backend.wasm";
+
+/// Cycles given to the canister, enough for every test.
+const INITIAL_CYCLES: u128 = 2_000_000_000_000;
+
+/// Number of nanoseconds in a day.
+pub const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Mirrors the `Priority` variant of the canister.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+/// Mirrors the fields of the canister's `Todo` record the tests look at.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Todo {
+    pub id: u64,
+    pub description: String,
+    pub priority: Priority,
+    pub is_completed: bool,
+    pub escalated: bool,
+}
+
+/// Mirrors the fields of the canister's `CreatedTodo` record the tests look at.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CreatedTodo {
+    pub id: u64,
+}
+
+/// Mirrors the `Paginator` record of the canister.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Paginator {
+    pub page: u32,
+    pub limit: Option<u32>,
+}
+
+/// Mirrors the `Preferences` record of the canister, leaving out the settings the tests do not change.
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct Preferences {
+    pub escalate_after_days: Option<u32>,
+    pub archive_after_days: Option<u32>,
+}
+
+/// Mirrors the `Error` variant of the canister.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum Error {
+    InvalidInput(String),
+    NotFound,
+    Unauthorized(Option<String>),
+    AlreadyExists(Option<String>),
+    DependencyNotMet(Option<String>),
+    RateLimited(Option<String>),
+    QuotaExceeded(Option<String>),
+    Conflict(Option<String>),
+    Maintenance(Option<String>),
+}
+
+/// A PocketIC instance running a freshly installed `todo_backend` canister.
+pub struct TestEnv {
+    /// The PocketIC instance.
+    pub pic: PocketIc,
+    /// The id of the canister.
+    pub canister: Principal,
+    /// The controller of the canister.
+    pub controller: Principal,
+    /// The wasm module the canister was installed from.
+    wasm: Vec<u8>,
+}
+
+impl TestEnv {
+    /// Creates a PocketIC instance and installs the canister in it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the wasm cannot be read.
+    pub fn new() -> Self {
+        let path = std::env::var("TODO_BACKEND_WASM")
+            .unwrap_or_else(|_| format!("{}/../../{DEFAULT_WASM}", env!("CARGO_MANIFEST_DIR")));
+        let wasm = std::fs::read(&path)
+            .unwrap_or_else(|error| panic!("cannot read the canister wasm at {path}: {error}"));
+        let pic = PocketIc::new();
+        let controller = user(0);
+        let canister = pic.create_canister_with_settings(Some(controller), None);
+        pic.add_cycles(canister, INITIAL_CYCLES);
+        pic.install_canister(canister, wasm.clone(), Vec::new(), Some(controller));
+        Self {
+            pic,
+            canister,
+            controller,
+            wasm,
+        }
+    }
+
+    /// Upgrades the canister to the same wasm, running its pre- and post-upgrade hooks.
+    pub fn upgrade(&self) {
+        self.pic
+            .upgrade_canister(
+                self.canister,
+                self.wasm.clone(),
+                Vec::new(),
+                Some(self.controller),
+            )
+            .expect("upgrade failed");
+    }
+
+    /// Calls an update method of the canister.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - The principal making the call.
+    /// * `method` - The name of the method.
+    /// * `args` - The arguments of the method.
+    pub fn update<T>(&self, caller: Principal, method: &str, args: impl ArgumentEncoder) -> T
+    where
+        T: CandidType + for<'de> Deserialize<'de>,
+    {
+        let (result,) = update_candid_as(&self.pic, self.canister, caller, method, args)
+            .unwrap_or_else(|error| panic!("{method} failed: {error:?}"));
+        result
+    }
+
+    /// Calls a query method of the canister.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - The principal making the call.
+    /// * `method` - The name of the method.
+    /// * `args` - The arguments of the method.
+    pub fn query<T>(&self, caller: Principal, method: &str, args: impl ArgumentEncoder) -> T
+    where
+        T: CandidType + for<'de> Deserialize<'de>,
+    {
+        let (result,) = query_candid_as(&self.pic, self.canister, caller, method, args)
+            .unwrap_or_else(|error| panic!("{method} failed: {error:?}"));
+        result
+    }
+
+    /// Moves the logical clock of the canister forward, without running its timers.
+    ///
+    /// # Arguments
+    ///
+    /// * `nanos` - The amount of time, in nanoseconds.
+    pub fn advance_clock(&self, nanos: u64) {
+        let result: Result<(), Error> = self.update(self.controller, "advance_time", (nanos,));
+        result.expect("advance_time failed");
+    }
+
+    /// Moves the time of the PocketIC instance forward and executes rounds, so that due timers fire.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - The amount of time.
+    pub fn advance_replica_time(&self, duration: Duration) {
+        self.pic.advance_time(duration);
+        for _ in 0..3 {
+            self.pic.tick();
+        }
+    }
+
+    /// Adds a Todo item on behalf of a principal.
+    ///
+    /// # Returns
+    ///
+    /// The id of the new Todo item.
+    pub fn add(&self, caller: Principal, description: &str) -> u64 {
+        let result: Result<CreatedTodo, Error> =
+            self.update(caller, "add_todo_item", (description, None::<Priority>));
+        result.expect("add_todo_item failed").id
+    }
+
+    /// Removes the sample Todo items a principal receives on its first call.
+    pub fn clear_samples(&self, caller: Principal) {
+        let result: Result<u64, Error> = self.update(caller, "clear_samples", ());
+        result.expect("clear_samples failed");
+    }
+
+    /// Lists the Todo items of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - The principal.
+    /// * `paginator` - The page to fetch, or None for the default page.
+    pub fn list(&self, caller: Principal, paginator: Option<Paginator>) -> Vec<Todo> {
+        self.query(caller, "list_todo_items", (paginator,))
+    }
+}
+
+impl Default for TestEnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns a distinct, non-anonymous principal for the given index.
+///
+/// # Arguments
+///
+/// * `index` - The index of the principal.
+pub fn user(index: u8) -> Principal {
+    Principal::from_slice(&[0xAB, index, 0x01])
+}
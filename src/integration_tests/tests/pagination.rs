@@ -0,0 +1,33 @@
+use std::collections::HashSet;
+
+use integration_tests::{user, Paginator, TestEnv};
+
+#[test]
+#[ignore = "requires the PocketIC server and the testing wasm; run with `just integration-test`"]
+fn test_pages_cover_every_todo_once() {
+    let env = TestEnv::new();
+    let alice = user(1);
+    env.clear_samples(alice);
+    let ids: HashSet<u64> = (0..25)
+        .map(|index| env.add(alice, &format!("Task {index}")))
+        .collect();
+
+    let pages: Vec<Vec<u64>> = (1..=4)
+        .map(|page| {
+            let paginator = Paginator {
+                page,
+                limit: Some(10),
+            };
+            env.list(alice, Some(paginator))
+                .into_iter()
+                .map(|todo| todo.id)
+                .collect()
+        })
+        .collect();
+
+    assert_eq!(
+        pages.iter().map(Vec::len).collect::<Vec<_>>(),
+        vec![10, 10, 5, 0]
+    );
+    assert_eq!(pages.concat().into_iter().collect::<HashSet<_>>(), ids);
+}
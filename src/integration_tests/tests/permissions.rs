@@ -0,0 +1,36 @@
+use integration_tests::{user, Error, Paginator, TestEnv, Todo};
+
+#[test]
+#[ignore = "requires the PocketIC server and the testing wasm; run with `just integration-test`"]
+fn test_todos_are_private() {
+    let env = TestEnv::new();
+    let (alice, bob) = (user(1), user(2));
+    let id = env.add(alice, "Alice's secret");
+
+    let result: Result<Todo, Error> = env.query(bob, "get_todo_item", (id,));
+    assert_eq!(result.map(|todo| todo.id), Err(Error::NotFound));
+    assert!(env
+        .list(bob, None)
+        .iter()
+        .all(|todo| todo.description != "Alice's secret"));
+}
+
+#[test]
+#[ignore = "requires the PocketIC server and the testing wasm; run with `just integration-test`"]
+fn test_admin_endpoints_require_controller() {
+    let env = TestEnv::new();
+    let alice = user(1);
+
+    let seeded: Result<u64, Error> = env.update(alice, "seed_fake_data", (1u32, 1u32));
+    assert!(matches!(seeded, Err(Error::Unauthorized(_))));
+    let advanced: Result<(), Error> = env.update(alice, "advance_time", (1u64,));
+    assert!(matches!(advanced, Err(Error::Unauthorized(_))));
+    let maintained: Result<(), Error> = env.update(alice, "run_maintenance_now", ());
+    assert!(matches!(maintained, Err(Error::Unauthorized(_))));
+
+    let seeded: Result<u64, Error> = env.update(env.controller, "seed_fake_data", (1u32, 1u32));
+    assert_eq!(seeded, Ok(1));
+    let users: Result<Vec<candid::Reserved>, Error> =
+        env.query(alice, "list_users", (None::<Paginator>,));
+    assert!(matches!(users, Err(Error::Unauthorized(_))));
+}
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use integration_tests::{user, Error, Preferences, Priority, TestEnv, Todo, NANOS_PER_DAY};
+
+/// Opts a principal into escalation and adds a Todo item of medium priority.
+fn stale_todo(env: &TestEnv) -> (candid::Principal, u64) {
+    let alice = user(1);
+    let preferences = Preferences {
+        escalate_after_days: Some(1),
+        ..Preferences::default()
+    };
+    let result: Result<(), Error> = env.update(alice, "set_preferences", (preferences,));
+    assert_eq!(result, Ok(()));
+    let id = env.add(alice, "Renew the passport");
+    (alice, id)
+}
+
+fn get(env: &TestEnv, caller: candid::Principal, id: u64) -> Todo {
+    let result: Result<Todo, Error> = env.query(caller, "get_todo_item", (id,));
+    result.expect("missing Todo item")
+}
+
+#[test]
+#[ignore = "requires the PocketIC server and the testing wasm; run with `just integration-test`"]
+fn test_maintenance_escalates_stale_todos() {
+    let env = TestEnv::new();
+    let (alice, id) = stale_todo(&env);
+
+    env.advance_clock(NANOS_PER_DAY / 2);
+    let result: Result<(), Error> = env.update(env.controller, "run_maintenance_now", ());
+    assert_eq!(result, Ok(()));
+    assert_eq!(get(&env, alice, id).priority, Priority::Medium);
+
+    env.advance_clock(2 * NANOS_PER_DAY);
+    let result: Result<(), Error> = env.update(env.controller, "run_maintenance_now", ());
+    assert_eq!(result, Ok(()));
+    let todo = get(&env, alice, id);
+    assert_eq!(todo.priority, Priority::High);
+    assert!(todo.escalated);
+    let unread: u64 = env.query(alice, "get_unread_notification_count", ());
+    assert!(unread >= 1);
+}
+
+#[test]
+#[ignore = "requires the PocketIC server and the testing wasm; run with `just integration-test`"]
+fn test_hourly_timer_runs_maintenance() {
+    let env = TestEnv::new();
+    let (alice, id) = stale_todo(&env);
+
+    env.advance_clock(2 * NANOS_PER_DAY);
+    assert_eq!(get(&env, alice, id).priority, Priority::Medium);
+    env.advance_replica_time(Duration::from_secs(60 * 60 + 1));
+    assert_eq!(get(&env, alice, id).priority, Priority::High);
+}
+
+/// Mirrors the fields of the canister's `CreateTodo` record the tests set.
+#[derive(candid::CandidType)]
+struct CreateTodo {
+    description: String,
+}
+
+#[test]
+#[ignore = "requires the PocketIC server and the testing wasm; run with `just integration-test`"]
+fn test_timer_creates_scheduled_todo() {
+    let env = TestEnv::new();
+    let alice = user(1);
+    env.clear_samples(alice);
+    let now = env
+        .pic
+        .get_time()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64;
+    let request = CreateTodo {
+        description: "Review the budget".to_string(),
+    };
+    let result: Result<u64, Error> =
+        env.update(alice, "schedule_todo", (request, now + 60_000_000_000));
+    assert!(result.is_ok());
+    assert!(env.list(alice, None).is_empty());
+
+    env.advance_replica_time(Duration::from_secs(61));
+    let todos = env.list(alice, None);
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0].description, "Review the budget");
+}
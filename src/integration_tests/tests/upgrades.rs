@@ -0,0 +1,22 @@
+use integration_tests::{user, Priority, TestEnv};
+
+#[test]
+#[ignore = "requires the PocketIC server and the testing wasm; run with `just integration-test`"]
+fn test_todos_survive_upgrade() {
+    let env = TestEnv::new();
+    let alice = user(1);
+    env.clear_samples(alice);
+    let id = env.add(alice, "Water the plants");
+    let result: Result<(), integration_tests::Error> =
+        env.update(alice, "modify_todo_priority", (id, Priority::High));
+    assert_eq!(result, Ok(()));
+
+    env.upgrade();
+
+    let todos = env.list(alice, None);
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0].id, id);
+    assert_eq!(todos[0].description, "Water the plants");
+    assert_eq!(todos[0].priority, Priority::High);
+    assert_ne!(env.add(alice, "Repot the fern"), id);
+}
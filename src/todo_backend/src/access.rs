@@ -0,0 +1,247 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::errors::Error;
+
+/// Maximum number of principals a principal may grant access to their account.
+pub(crate) const MAX_GRANTS: usize = 20;
+
+/// Type alias for the AccessStore, which is a StableBTreeMap with a tuple key of (owner, grantee) and value of AccountGrant.
+pub(crate) type AccessStore<M> = StableBTreeMap<(Principal, Principal), AccountGrant, M>;
+
+/// Type alias for the SharedAccountIndex, which is a StableBTreeMap keyed by (grantee, owner) for every
+/// grant in the AccessStore, so that the accounts shared with a principal are found without a full scan.
+pub(crate) type SharedAccountIndex<M> = StableBTreeMap<(Principal, Principal), (), M>;
+
+/// Represents what a principal granted access to an account may change besides reading it.
+/// Deleting is never granted, so an assistant cannot destroy data.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum AccountScope {
+    /// Adding Todo items.
+    Create,
+    /// Ticking off Todo items.
+    Complete,
+}
+
+/// Represents access to the whole account of a principal, granted to another principal.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct AccountGrant {
+    /// The principal the access is granted to.
+    pub(crate) grantee: Principal,
+    /// Time the access was granted, in nanoseconds since the Unix epoch.
+    pub(crate) granted_at: u64,
+    /// What the grantee may change; None or empty for read-only access.
+    pub(crate) scopes: Option<Vec<AccountScope>>,
+}
+
+impl Storable for AccountGrant {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Wrapper around the AccessStore to provide additional functionality.
+pub(crate) struct AccessStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<AccessStore<M>>,
+}
+
+impl<M: Memory> AccessStoreWrapper<'_, M> {
+    /// Grants a principal access to the account of another, replacing any earlier grant.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - The principal owning the account.
+    /// * `grantee` - The principal the access is granted to.
+    /// * `scopes` - What the grantee may change besides reading the account.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if the grantee is the owner or anonymous, or too many grants exist.
+    pub(crate) fn grant(
+        &self,
+        owner: Principal,
+        grantee: Principal,
+        scopes: Vec<AccountScope>,
+        now: u64,
+    ) -> Result<(), Error> {
+        if grantee == owner || grantee == Principal::anonymous() {
+            return Err(Error::InvalidInput(
+                "Access can only be granted to another, authenticated principal".to_string(),
+            ));
+        }
+        let grants = self.list(owner);
+        if grants.len() >= MAX_GRANTS && !grants.iter().any(|grant| grant.grantee == grantee) {
+            return Err(Error::QuotaExceeded(Some(format!(
+                "At most {MAX_GRANTS} principals can be granted access"
+            ))));
+        }
+        self.store.borrow_mut().insert(
+            (owner, grantee),
+            AccountGrant {
+                grantee,
+                granted_at: now,
+                scopes: Some(scopes),
+            },
+        );
+        Ok(())
+    }
+
+    /// Revokes the access of a principal to the account of another.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - The principal owning the account.
+    /// * `grantee` - The principal the access was granted to.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if no access was granted.
+    pub(crate) fn revoke(&self, owner: Principal, grantee: Principal) -> Result<(), Error> {
+        self.store
+            .borrow_mut()
+            .remove(&(owner, grantee))
+            .map(|_| ())
+            .ok_or(Error::NotFound)
+    }
+
+    /// Lists the grants to the account of a principal, by grantee.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - The principal owning the account.
+    pub(crate) fn list(&self, owner: Principal) -> Vec<AccountGrant> {
+        self.store
+            .borrow()
+            .range((owner, Principal::management_canister())..)
+            .take_while(|((account, _), _)| *account == owner)
+            .map(|(_, grant)| grant)
+            .collect()
+    }
+
+    /// Returns the access granted to a principal for the account of another.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - The principal owning the account.
+    /// * `grantee` - The principal the access may have been granted to.
+    ///
+    /// # Returns
+    ///
+    /// The grant, or None if no access was granted.
+    pub(crate) fn get(&self, owner: Principal, grantee: Principal) -> Option<AccountGrant> {
+        self.store.borrow().get(&(owner, grantee))
+    }
+}
+
+/// Wrapper around the SharedAccountIndex to provide additional functionality.
+pub(crate) struct SharedAccountIndexWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<SharedAccountIndex<M>>,
+}
+
+impl<M: Memory> SharedAccountIndexWrapper<'_, M> {
+    /// Records that an account was shared with a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - The principal owning the account.
+    /// * `grantee` - The principal the access was granted to.
+    pub(crate) fn insert(&self, owner: Principal, grantee: Principal) {
+        self.store.borrow_mut().insert((grantee, owner), ());
+    }
+
+    /// Records that an account is no longer shared with a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - The principal owning the account.
+    /// * `grantee` - The principal the access was granted to.
+    pub(crate) fn remove(&self, owner: Principal, grantee: Principal) {
+        self.store.borrow_mut().remove(&(grantee, owner));
+    }
+
+    /// Lists the owners of the accounts shared with a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `grantee` - The principal the access was granted to.
+    pub(crate) fn owners(&self, grantee: Principal) -> Vec<Principal> {
+        self.store
+            .borrow()
+            .range((grantee, Principal::management_canister())..)
+            .take_while(|((shared_with, _), _)| *shared_with == grantee)
+            .map(|((_, owner), _)| owner)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_grant_and_revoke() {
+        let store = RefCell::new(AccessStore::new(DefaultMemoryImpl::default()));
+        let wrapper = AccessStoreWrapper { store: &store };
+        let owner = Principal::from_slice(&[1]);
+        let coach = Principal::from_slice(&[2]);
+        let stranger = Principal::from_slice(&[3]);
+
+        assert_eq!(wrapper.get(owner, coach), None);
+        assert!(wrapper.grant(owner, coach, Vec::new(), 5).is_ok());
+        assert!(wrapper.get(owner, coach).is_some());
+        assert_eq!(wrapper.get(coach, owner), None);
+        assert_eq!(wrapper.get(owner, stranger), None);
+        assert!(matches!(
+            wrapper.grant(owner, owner, Vec::new(), 5),
+            Err(Error::InvalidInput(_))
+        ));
+        assert_eq!(
+            wrapper.list(owner),
+            vec![AccountGrant {
+                grantee: coach,
+                granted_at: 5,
+                scopes: Some(Vec::new())
+            }]
+        );
+
+        let assistant = Principal::from_slice(&[5]);
+        assert!(wrapper
+            .grant(owner, assistant, vec![AccountScope::Complete], 6)
+            .is_ok());
+        assert_eq!(
+            wrapper.get(owner, assistant).and_then(|grant| grant.scopes),
+            Some(vec![AccountScope::Complete])
+        );
+
+        assert!(wrapper.revoke(owner, coach).is_ok());
+        assert_eq!(wrapper.get(owner, coach), None);
+        assert!(matches!(wrapper.revoke(owner, coach), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn test_lists_accounts_shared_with_a_principal() {
+        let store = RefCell::new(SharedAccountIndex::new(DefaultMemoryImpl::default()));
+        let wrapper = SharedAccountIndexWrapper { store: &store };
+        let coach = Principal::from_slice(&[2]);
+        let (first, second) = (Principal::from_slice(&[1]), Principal::from_slice(&[3]));
+        wrapper.insert(first, coach);
+        wrapper.insert(second, coach);
+        wrapper.insert(coach, first);
+
+        assert_eq!(wrapper.owners(coach), vec![first, second]);
+        wrapper.remove(first, coach);
+        assert_eq!(wrapper.owners(coach), vec![second]);
+        assert_eq!(wrapper.owners(second), Vec::<Principal>::new());
+    }
+}
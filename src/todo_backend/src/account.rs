@@ -0,0 +1,292 @@
+use candid::{CandidType, Deserialize, Principal};
+
+use crate::{
+    access::{AccountGrant, MAX_GRANTS},
+    board::Column,
+    checklists::{Checklist, MAX_CHECKLISTS},
+    errors::Error,
+    goals::{Goal, MAX_GOALS},
+    labels::{Label, MAX_LABELS},
+    namespaces::{Namespace, MAX_NAMESPACES},
+    preferences::Preferences,
+    renumbering::{renumbered, Renumbering},
+    todo::{Todo, TodoId},
+    validation,
+};
+
+/// Version of the account bundle layout written by this canister. Bumped whenever a field changes in a
+/// way older canisters cannot read, so that imports of newer bundles are refused instead of losing data.
+pub(crate) const BUNDLE_VERSION: u32 = 1;
+
+/// Represents everything a principal keeps in the canister, so that the account can move to another
+/// deployment. Derived data such as statistics, indexes and histories is rebuilt on import.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct AccountBundle {
+    /// Version of the bundle layout.
+    pub(crate) version: u32,
+    /// Time the bundle was exported, in nanoseconds since the Unix epoch.
+    pub(crate) exported_at: u64,
+    /// The Todo items, with their tags and projects.
+    pub(crate) todos: Vec<Todo>,
+    /// The archived Todo items.
+    pub(crate) archived: Vec<Todo>,
+    /// The preferences.
+    pub(crate) preferences: Preferences,
+    /// The labels.
+    pub(crate) labels: Vec<Label>,
+    /// The goals.
+    pub(crate) goals: Vec<Goal>,
+    /// The saved checklists.
+    pub(crate) checklists: Vec<Checklist>,
+    /// The columns of the project boards, with their cards.
+    pub(crate) columns: Vec<Column>,
+    /// The namespaces.
+    pub(crate) namespaces: Vec<Namespace>,
+    /// The principals the account is shared with.
+    pub(crate) grants: Vec<AccountGrant>,
+}
+
+/// Checks an account bundle before it is restored, so that a restore cannot fail halfway.
+///
+/// # Arguments
+///
+/// * `principal` - The principal the bundle is restored for.
+/// * `bundle` - The bundle.
+///
+/// # Returns
+///
+/// A Result containing the bundle with its text sanitized, or an Error if its version is not supported,
+/// it holds more than the limits allow or any of its contents is invalid.
+pub(crate) fn validate(
+    principal: Principal,
+    bundle: AccountBundle,
+) -> Result<AccountBundle, Error> {
+    if bundle.version != BUNDLE_VERSION {
+        return Err(Error::InvalidInput(format!(
+            "Bundle version {} is not supported; expected version {BUNDLE_VERSION}",
+            bundle.version
+        )));
+    }
+    let limits = [
+        ("labels", bundle.labels.len(), MAX_LABELS),
+        ("goals", bundle.goals.len(), MAX_GOALS),
+        ("checklists", bundle.checklists.len(), MAX_CHECKLISTS),
+        ("namespaces", bundle.namespaces.len(), MAX_NAMESPACES),
+        ("grants", bundle.grants.len(), MAX_GRANTS),
+    ];
+    if let Some((name, _, limit)) = limits.iter().find(|(_, count, limit)| count > limit) {
+        return Err(Error::QuotaExceeded(Some(format!(
+            "A bundle can hold at most {limit} {name}"
+        ))));
+    }
+    bundle.preferences.validate()?;
+    if bundle
+        .grants
+        .iter()
+        .any(|grant| grant.grantee == principal || grant.grantee == Principal::anonymous())
+    {
+        return Err(Error::InvalidInput(
+            "Access can only be granted to another, authenticated principal".to_string(),
+        ));
+    }
+    let labels = bundle
+        .labels
+        .into_iter()
+        .map(|label| {
+            Ok(Label {
+                name: validation::label_name(&label.name)?,
+                color: validation::color(&label.color)?,
+                ..label
+            })
+        })
+        .collect::<Result<_, Error>>()?;
+    let goals = bundle
+        .goals
+        .into_iter()
+        .map(|goal| {
+            Ok(Goal {
+                title: validation::goal_title(&goal.title)?,
+                target: validation::goal_target(goal.target)?,
+                ..goal
+            })
+        })
+        .collect::<Result<_, Error>>()?;
+    let checklists = bundle
+        .checklists
+        .into_iter()
+        .map(|checklist| {
+            Ok(Checklist {
+                name: validation::checklist_name(&checklist.name)?,
+                items: validation::checklist_items(checklist.items)?,
+                ..checklist
+            })
+        })
+        .collect::<Result<_, Error>>()?;
+    let columns = bundle
+        .columns
+        .into_iter()
+        .map(|column| {
+            Ok(Column {
+                project: validation::project(&column.project)?,
+                name: validation::column_name(&column.name)?,
+                ..column
+            })
+        })
+        .collect::<Result<_, Error>>()?;
+    let namespaces = bundle
+        .namespaces
+        .into_iter()
+        .map(|namespace| {
+            Ok(Namespace {
+                name: validation::namespace(&namespace.name)?,
+                ..namespace
+            })
+        })
+        .collect::<Result<_, Error>>()?;
+    Ok(AccountBundle {
+        todos: bundle
+            .todos
+            .into_iter()
+            .map(todo)
+            .collect::<Result<_, _>>()?,
+        archived: bundle
+            .archived
+            .into_iter()
+            .map(todo)
+            .collect::<Result<_, _>>()?,
+        labels,
+        goals,
+        checklists,
+        columns,
+        namespaces,
+        ..bundle
+    })
+}
+
+/// Sanitizes the text fields of a Todo item of a bundle.
+fn todo(todo: Todo) -> Result<Todo, Error> {
+    Ok(Todo {
+        description: validation::description(&todo.description)?,
+        notes: todo.notes.as_deref().map(validation::notes).transpose()?,
+        tags: validation::tags(todo.tags)?,
+        project: todo
+            .project
+            .as_deref()
+            .map(validation::project)
+            .transpose()?,
+        namespace: todo
+            .namespace
+            .as_deref()
+            .map(validation::namespace)
+            .transpose()?,
+        ..todo
+    })
+}
+
+/// Gives the Todo items of a bundle fresh identifiers, since identifiers are unique across the canister,
+/// and points the cards on the boards to them. Cards of Todo items missing from the bundle are dropped.
+///
+/// # Arguments
+///
+/// * `bundle` - The validated bundle.
+/// * `next_id` - Hands out the next unused identifier for a Todo item.
+///
+/// # Returns
+///
+/// The bundle with renumbered Todo items.
+pub(crate) fn renumber(
+    mut bundle: AccountBundle,
+    mut next_id: impl FnMut() -> TodoId,
+) -> AccountBundle {
+    let mut ids = Renumbering::new();
+    for todo in bundle.todos.iter_mut().chain(bundle.archived.iter_mut()) {
+        let id = next_id();
+        ids.insert(todo.id, id);
+        todo.id = id;
+    }
+    for column in &mut bundle.columns {
+        column.cards.retain(|card| ids.contains_key(card));
+        for card in &mut column.cards {
+            *card = renumbered(&ids, *card);
+        }
+    }
+    bundle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::todo::Priority;
+
+    fn bundle() -> AccountBundle {
+        AccountBundle {
+            version: BUNDLE_VERSION,
+            exported_at: 0,
+            todos: vec![
+                Todo::new(7, " Ship ".to_string(), Priority::High),
+                Todo::new(9, "Test".to_string(), Priority::Low),
+            ],
+            archived: vec![Todo::new(3, "Plan".to_string(), Priority::Low)],
+            preferences: Preferences::default(),
+            labels: Vec::new(),
+            goals: Vec::new(),
+            checklists: Vec::new(),
+            columns: vec![Column {
+                id: 1,
+                project: "launch".to_string(),
+                name: "Doing".to_string(),
+                position: 0,
+                cards: vec![9, 4, 7],
+            }],
+            namespaces: Vec::new(),
+            grants: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_validate_and_renumber_bundle() {
+        let principal = Principal::anonymous();
+        assert!(matches!(
+            validate(
+                principal,
+                AccountBundle {
+                    version: BUNDLE_VERSION + 1,
+                    ..bundle()
+                }
+            ),
+            Err(Error::InvalidInput(_))
+        ));
+        let grant = AccountGrant {
+            grantee: principal,
+            granted_at: 0,
+            scopes: None,
+        };
+        assert!(validate(
+            principal,
+            AccountBundle {
+                grants: vec![grant],
+                ..bundle()
+            }
+        )
+        .is_err());
+
+        let bundle = validate(principal, bundle()).unwrap();
+        assert_eq!(bundle.todos[0].description, "Ship");
+        let mut next = 100;
+        let bundle = renumber(bundle, || {
+            next += 1;
+            next
+        });
+        assert_eq!(
+            bundle
+                .todos
+                .iter()
+                .chain(&bundle.archived)
+                .map(|todo| todo.id)
+                .collect::<Vec<_>>(),
+            vec![101, 102, 103]
+        );
+        assert_eq!(bundle.columns[0].cards, vec![102, 101]);
+    }
+}
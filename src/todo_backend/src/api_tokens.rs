@@ -0,0 +1,280 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+use sha2::{Digest, Sha256};
+
+use crate::{errors::Error, http::HttpRequest};
+
+/// Prefix of the routes served to clients authenticating with an API token.
+pub(crate) const API_PREFIX: &str = "/api/";
+
+/// Path of the route listing and creating Todo items.
+pub(crate) const TODOS_PATH: &str = "/api/todos";
+
+/// Maximum number of API tokens a principal may hold at once.
+pub(crate) const MAX_API_TOKENS: usize = 20;
+
+/// Type alias for the identifier of an API token, unique per principal.
+pub(crate) type ApiTokenId = u64;
+
+/// Type alias for the ApiTokenStore, which is a StableBTreeMap with a tuple key of (Principal, ApiTokenId) and value of ApiToken.
+pub(crate) type ApiTokenStore<M> = StableBTreeMap<(Principal, ApiTokenId), ApiToken, M>;
+
+/// Represents what a request authenticated with an API token may do.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ApiScope {
+    /// Reading Todo items.
+    Read,
+    /// Creating Todo items.
+    Write,
+}
+
+/// Represents an API token of a principal. Only the hash of its secret is kept.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ApiToken {
+    /// The identifier of the token.
+    pub(crate) id: ApiTokenId,
+    /// The SHA-256 hash of the secret part of the token.
+    pub(crate) secret_hash: Vec<u8>,
+    /// What requests authenticated with the token may do.
+    pub(crate) scopes: Vec<ApiScope>,
+    /// Creation time, in nanoseconds since the Unix epoch.
+    pub(crate) created_at: u64,
+    /// Time the token stops working, in nanoseconds since the Unix epoch, or None if it does not expire.
+    pub(crate) expires_at: Option<u64>,
+}
+
+impl Storable for ApiToken {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Represents an API token as shown to its owner, without its secret.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ApiTokenInfo {
+    /// The identifier of the token.
+    pub(crate) id: ApiTokenId,
+    /// What requests authenticated with the token may do.
+    pub(crate) scopes: Vec<ApiScope>,
+    /// Creation time, in nanoseconds since the Unix epoch.
+    pub(crate) created_at: u64,
+    /// Time the token stops working, in nanoseconds since the Unix epoch, or None if it does not expire.
+    pub(crate) expires_at: Option<u64>,
+}
+
+/// Represents the principal a request acts as and what it may do.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ApiGrant {
+    /// The principal the token was issued to.
+    pub(crate) principal: Principal,
+    /// What the request may do.
+    pub(crate) scopes: Vec<ApiScope>,
+}
+
+/// Wrapper around the ApiTokenStore to provide additional functionality.
+pub(crate) struct ApiTokenStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<ApiTokenStore<M>>,
+}
+
+impl<M: Memory> ApiTokenStoreWrapper<'_, M> {
+    /// Issues an API token for a principal.
+    /// The token names the principal and its identifier, so it is looked up without scanning the store.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `scopes` - What requests authenticated with the token may do.
+    /// * `expires_at` - Time the token stops working, in nanoseconds since the Unix epoch, or None if it does not expire.
+    /// * `random` - Random bytes making up the secret part of the token.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the API token, or an Error if the input is invalid or too many tokens exist.
+    pub(crate) fn issue(
+        &self,
+        principal: Principal,
+        mut scopes: Vec<ApiScope>,
+        expires_at: Option<u64>,
+        random: &[u8],
+        now: u64,
+    ) -> Result<String, Error> {
+        scopes.sort_by_key(|scope| *scope as u8);
+        scopes.dedup();
+        if scopes.is_empty() {
+            return Err(Error::InvalidInput(
+                "An API token needs at least one scope".to_string(),
+            ));
+        }
+        if expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(Error::InvalidInput(
+                "Expiry must lie in the future".to_string(),
+            ));
+        }
+        if self.list(principal).len() >= MAX_API_TOKENS {
+            return Err(Error::QuotaExceeded(Some(format!(
+                "At most {MAX_API_TOKENS} API tokens can exist at once"
+            ))));
+        }
+        let id = self
+            .store
+            .borrow()
+            .iter_upper_bound(&(principal, ApiTokenId::MAX))
+            .next()
+            .filter(|((owner, _), _)| *owner == principal)
+            .map_or(1, |((_, id), _)| id + 1);
+        let secret: String = random.iter().map(|byte| format!("{byte:02x}")).collect();
+        let token = ApiToken {
+            id,
+            secret_hash: Sha256::digest(&secret).to_vec(),
+            scopes,
+            created_at: now,
+            expires_at,
+        };
+        self.store.borrow_mut().insert((principal, id), token);
+        Ok(format!("{principal}.{id}.{secret}"))
+    }
+
+    /// Lists the API tokens of a principal, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn list(&self, principal: Principal) -> Vec<ApiTokenInfo> {
+        self.store
+            .borrow()
+            .range((principal, ApiTokenId::MIN)..=(principal, ApiTokenId::MAX))
+            .map(|(_, token)| ApiTokenInfo {
+                id: token.id,
+                scopes: token.scopes,
+                created_at: token.created_at,
+                expires_at: token.expires_at,
+            })
+            .collect()
+    }
+
+    /// Revokes an API token.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The identifier of the token.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if the token is not found.
+    pub(crate) fn revoke(&self, principal: Principal, id: ApiTokenId) -> Result<(), Error> {
+        self.store
+            .borrow_mut()
+            .remove(&(principal, id))
+            .map(|_| ())
+            .ok_or(Error::NotFound)
+    }
+
+    /// Finds the principal and scopes an API token grants.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The API token sent with the request.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the grant, or None if the token is unknown, revoked or expired.
+    pub(crate) fn authenticate(&self, token: &str, now: u64) -> Option<ApiGrant> {
+        let (principal, rest) = token.split_once('.')?;
+        let (id, secret) = rest.split_once('.')?;
+        let principal = Principal::from_text(principal).ok()?;
+        let stored = self.store.borrow().get(&(principal, id.parse().ok()?))?;
+        let valid = stored.secret_hash == Sha256::digest(secret).as_slice()
+            && stored.expires_at.is_none_or(|expires_at| now < expires_at);
+        valid.then_some(ApiGrant {
+            principal,
+            scopes: stored.scopes,
+        })
+    }
+}
+
+/// Returns the token a request carries in its `Authorization: Bearer <token>` header.
+///
+/// # Arguments
+///
+/// * `request` - The HTTP request.
+pub(crate) fn bearer_token(request: &HttpRequest) -> Option<&str> {
+    let (scheme, token) = request.header("Authorization")?.trim().split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("Bearer")
+        .then_some(token.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_issue_and_authenticate() {
+        let store = RefCell::new(ApiTokenStore::new(DefaultMemoryImpl::default()));
+        let wrapper = ApiTokenStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+
+        let token = wrapper
+            .issue(
+                principal,
+                vec![ApiScope::Read],
+                Some(100),
+                &[0xab, 0x01],
+                10,
+            )
+            .unwrap();
+        assert_eq!(token, format!("{principal}.1.ab01"));
+        assert_eq!(
+            wrapper.authenticate(&token, 50),
+            Some(ApiGrant {
+                principal,
+                scopes: vec![ApiScope::Read]
+            })
+        );
+        assert_eq!(wrapper.authenticate(&token, 100), None);
+        assert_eq!(
+            wrapper.authenticate(&format!("{principal}.1.ab02"), 50),
+            None
+        );
+        assert!(!wrapper.list(principal)[0].scopes.is_empty());
+
+        assert!(matches!(
+            wrapper.issue(principal, vec![], None, &[0], 10),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            wrapper.issue(principal, vec![ApiScope::Write], Some(10), &[0], 10),
+            Err(Error::InvalidInput(_))
+        ));
+
+        assert!(wrapper.revoke(principal, 1).is_ok());
+        assert_eq!(wrapper.authenticate(&token, 50), None);
+        assert!(matches!(wrapper.revoke(principal, 1), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        let request = |value: &str| HttpRequest {
+            method: "GET".to_string(),
+            url: TODOS_PATH.to_string(),
+            headers: vec![("authorization".to_string(), value.to_string())],
+            body: Vec::new(),
+        };
+        assert_eq!(bearer_token(&request("Bearer abc")), Some("abc"));
+        assert_eq!(bearer_token(&request("bearer  abc ")), Some("abc"));
+        assert_eq!(bearer_token(&request("Basic abc")), None);
+    }
+}
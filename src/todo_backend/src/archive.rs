@@ -0,0 +1,242 @@
+use std::cell::RefCell;
+
+use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::{Memory, StableBTreeMap};
+
+use crate::{
+    goals::GoalId,
+    labels::LabelId,
+    paginator::Paginator,
+    renumbering::{renumbered, rewrite_entries, Renumbering},
+    todo::{Todo, TodoId, TodoRecord},
+};
+
+/// Type alias for the ArchiveStore, which is a StableBTreeMap with a tuple key of (Principal, TodoId) and value of TodoRecord.
+pub(crate) type ArchiveStore<M> = StableBTreeMap<(Principal, TodoId), TodoRecord, M>;
+
+/// Represents what a purge removed.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct PurgeSummary {
+    /// Number of archived Todo items deleted for good.
+    pub(crate) archived_todos: u32,
+    /// Number of previous versions of Todo items dropped.
+    pub(crate) revisions: u32,
+}
+
+/// Wrapper around the ArchiveStore to provide additional functionality.
+pub(crate) struct ArchiveStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<ArchiveStore<M>>,
+}
+
+impl<M: Memory> ArchiveStoreWrapper<'_, M> {
+    /// Adds Todo items of a principal to the archive.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `todos` - The Todo items to be archived.
+    pub(crate) fn archive(&self, principal: Principal, todos: Vec<Todo>) {
+        let mut store = self.store.borrow_mut();
+        for todo in todos {
+            store.insert((principal, todo.id), todo.into());
+        }
+    }
+
+    /// Lists archived Todo items of a principal with pagination.
+    /// Records that cannot be decoded are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `paginator` - The paginator for controlling the list output.
+    ///
+    /// # Returns
+    ///
+    /// A vector of archived Todo items.
+    pub(crate) fn list(&self, principal: Principal, paginator: Paginator) -> Vec<Todo> {
+        self.store
+            .borrow()
+            .range((principal, TodoId::MIN)..)
+            .take_while(|((p, _), _)| p == &principal)
+            .filter_map(|(_, record)| record.into_todo())
+            .skip(paginator.skip())
+            .take(paginator.limit())
+            .collect()
+    }
+
+    /// Removes the archived Todo items of a principal completed before a given time.
+    /// Records that cannot be decoded are kept for inspection.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `completed_before` - The time, in nanoseconds since the Unix epoch, before which Todo items were completed.
+    ///
+    /// # Returns
+    ///
+    /// A vector of the removed Todo items.
+    pub(crate) fn purge(&self, principal: Principal, completed_before: u64) -> Vec<Todo> {
+        let todos: Vec<Todo> = self
+            .store
+            .borrow()
+            .range((principal, TodoId::MIN)..=(principal, TodoId::MAX))
+            .filter_map(|(_, record)| record.into_todo())
+            .filter(|todo| todo.completed_at.is_some_and(|completed_at| completed_at < completed_before))
+            .collect();
+        let mut store = self.store.borrow_mut();
+        for todo in &todos {
+            store.remove(&(principal, todo.id));
+        }
+        todos
+    }
+
+    /// Removes a Todo item from the archive.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the archived Todo item if found, otherwise None.
+    pub(crate) fn take(&self, principal: Principal, id: TodoId) -> Option<Todo> {
+        self.store
+            .borrow_mut()
+            .remove(&(principal, id))?
+            .into_todo()
+    }
+
+    /// Detaches a deleted label from the archived Todo items of a principal, so that a later label
+    /// reusing its identifier does not show up on them.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `label_id` - The unique identifier for the label.
+    pub(crate) fn remove_label(&self, principal: Principal, label_id: LabelId) {
+        let todos: Vec<Todo> = self
+            .store
+            .borrow()
+            .range((principal, TodoId::MIN)..=(principal, TodoId::MAX))
+            .filter_map(|(_, record)| record.into_todo())
+            .filter(|todo| todo.label_ids.contains(&label_id))
+            .collect();
+        let mut store = self.store.borrow_mut();
+        for mut todo in todos {
+            todo.remove_label(label_id);
+            store.insert((principal, todo.id), todo.into());
+        }
+    }
+
+    /// Lists every archived Todo item of a principal. Records that cannot be decoded are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn all(&self, principal: Principal) -> Vec<Todo> {
+        self.store
+            .borrow()
+            .range((principal, TodoId::MIN)..=(principal, TodoId::MAX))
+            .filter_map(|(_, record)| record.into_todo())
+            .collect()
+    }
+
+    /// Detaches the archived Todo items of a principal from a deleted goal, so that a later goal
+    /// reusing its identifier does not count them.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `goal_id` - The unique identifier for the goal.
+    pub(crate) fn remove_goal(&self, principal: Principal, goal_id: GoalId) {
+        let todos: Vec<Todo> = self
+            .all(principal)
+            .into_iter()
+            .filter(|todo| todo.goal_id == Some(goal_id))
+            .collect();
+        let mut store = self.store.borrow_mut();
+        for mut todo in todos {
+            todo.goal_id = None;
+            store.insert((principal, todo.id), todo.into());
+        }
+    }
+
+    /// Lists the identifiers of all archived records of a principal, including records that cannot be decoded.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn ids(&self, principal: Principal) -> Vec<TodoId> {
+        self.store
+            .borrow()
+            .range((principal, TodoId::MIN)..=(principal, TodoId::MAX))
+            .map(|((_, id), _)| id)
+            .collect()
+    }
+
+    /// Moves the archived records of a principal to their new identifiers.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        rewrite_entries(
+            self.store,
+            (principal, TodoId::MIN)..=(principal, TodoId::MAX),
+            |(principal, id), record| {
+                let id = renumbered(ids, id);
+                ((principal, id), record.with_id(id))
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::Priority;
+
+    #[test]
+    fn test_archive_and_take() {
+        let store = RefCell::new(ArchiveStore::new(DefaultMemoryImpl::default()));
+        let wrapper = ArchiveStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let todos = vec![
+            Todo::new(1, "First".to_string(), Priority::Low),
+            Todo::new(2, "Second".to_string(), Priority::Low),
+        ];
+
+        wrapper.archive(principal, todos);
+        assert_eq!(wrapper.list(principal, Paginator::default()).len(), 2);
+        assert_eq!(wrapper.take(principal, 1).unwrap().description, "First");
+        assert_eq!(wrapper.take(principal, 1), None);
+        assert_eq!(
+            wrapper.list(Principal::management_canister(), Paginator::default()),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_purge() {
+        let store = RefCell::new(ArchiveStore::new(DefaultMemoryImpl::default()));
+        let wrapper = ArchiveStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let todos = (1..=3)
+            .map(|id| {
+                let mut todo = Todo::new(id, "Done".to_string(), Priority::Low);
+                todo.set_completed(true, id * 10);
+                todo
+            })
+            .collect();
+        wrapper.archive(principal, todos);
+
+        let purged = wrapper.purge(principal, 30);
+        assert_eq!(purged.iter().map(|todo| todo.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(wrapper.list(principal, Paginator::default()).len(), 1);
+    }
+}
@@ -0,0 +1,418 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{
+    errors::Error,
+    renumbering::{renumbered, rewrite_entries, Renumbering},
+    todo::TodoId,
+};
+
+/// Maximum size of an uploaded chunk, in bytes, which keeps every call below the ingress message limit.
+const MAX_CHUNK_BYTES: usize = 1_000_000;
+
+/// Maximum size of a single attachment, in bytes.
+const MAX_ATTACHMENT_BYTES: u64 = 10_000_000;
+
+/// Maximum total size of the attachments of a principal, in bytes, including unfinished uploads.
+pub(crate) const MAX_BYTES_PER_PRINCIPAL: u64 = 50_000_000;
+
+/// Type alias for the unique identifier of an attachment, unique per principal.
+pub(crate) type AttachmentId = u64;
+
+/// Type alias for the AttachmentStore, which is a StableBTreeMap with a tuple key of (Principal, AttachmentId) and value of Attachment.
+pub(crate) type AttachmentStore<M> = StableBTreeMap<(Principal, AttachmentId), Attachment, M>;
+
+/// Type alias for the ChunkStore, which is a StableBTreeMap with a tuple key of (Principal, AttachmentId, chunk index) and value of the chunk bytes.
+pub(crate) type ChunkStore<M> = StableBTreeMap<(Principal, AttachmentId, u32), Vec<u8>, M>;
+
+/// Represents a file attached to a Todo item, such as a receipt or a screenshot.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Attachment {
+    /// The unique identifier of the attachment, which also serves as the upload handle.
+    pub(crate) id: AttachmentId,
+    /// The unique identifier of the Todo item the file is attached to.
+    pub(crate) todo_id: TodoId,
+    /// The file name.
+    pub(crate) name: String,
+    /// The MIME type of the file, e.g. `image/png`.
+    pub(crate) mime: String,
+    /// The size of the file, in bytes, as announced when the upload began.
+    pub(crate) size: u64,
+    /// The number of bytes uploaded so far.
+    pub(crate) received: u64,
+    /// The number of chunks uploaded so far.
+    pub(crate) chunk_count: u32,
+    /// Time the upload began, in nanoseconds since the Unix epoch.
+    pub(crate) created_at: u64,
+    /// Whether the upload is finished, after which chunks can be read but not added.
+    pub(crate) complete: bool,
+}
+
+impl Storable for Attachment {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Wrapper around the AttachmentStore to provide additional functionality.
+pub(crate) struct AttachmentStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<AttachmentStore<M>>,
+}
+
+impl<M: Memory> AttachmentStoreWrapper<'_, M> {
+    /// Begins the upload of an attachment, reserving its size in the principal's quota.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `todo_id` - The unique identifier for the Todo item.
+    /// * `name` - The validated file name.
+    /// * `mime` - The validated MIME type.
+    /// * `size` - The size of the file, in bytes.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the identifier of the attachment, or an Error if the file is empty or too large,
+    /// or the quota of the principal is exhausted.
+    pub(crate) fn begin(
+        &self,
+        principal: Principal,
+        todo_id: TodoId,
+        name: String,
+        mime: String,
+        size: u64,
+        now: u64,
+    ) -> Result<AttachmentId, Error> {
+        if !(1..=MAX_ATTACHMENT_BYTES).contains(&size) {
+            return Err(Error::InvalidInput(format!(
+                "Attachments must hold 1 to {MAX_ATTACHMENT_BYTES} bytes"
+            )));
+        }
+        if self.used_bytes(principal) + size > MAX_BYTES_PER_PRINCIPAL {
+            return Err(Error::QuotaExceeded(Some(format!(
+                "Attachments cannot exceed {MAX_BYTES_PER_PRINCIPAL} bytes in total"
+            ))));
+        }
+        let id = self
+            .store
+            .borrow()
+            .iter_upper_bound(&(principal, AttachmentId::MAX))
+            .next()
+            .filter(|((owner, _), _)| *owner == principal)
+            .map_or(1, |((_, id), _)| id + 1);
+        let attachment = Attachment {
+            id,
+            todo_id,
+            name,
+            mime,
+            size,
+            received: 0,
+            chunk_count: 0,
+            created_at: now,
+            complete: false,
+        };
+        self.store.borrow_mut().insert((principal, id), attachment);
+        Ok(id)
+    }
+
+    /// Retrieves an attachment of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the attachment.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the attachment, or None if it does not exist.
+    pub(crate) fn get(&self, principal: Principal, id: AttachmentId) -> Option<Attachment> {
+        self.store.borrow().get(&(principal, id))
+    }
+
+    /// Records the next chunk of an unfinished upload.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the attachment.
+    /// * `length` - The size of the chunk, in bytes.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the index the chunk is to be stored under, or an Error if the attachment is
+    /// not found, already finished, or the chunk is empty or exceeds the announced size.
+    pub(crate) fn add_chunk(
+        &self,
+        principal: Principal,
+        id: AttachmentId,
+        length: usize,
+    ) -> Result<u32, Error> {
+        let mut attachment = self.get(principal, id).ok_or(Error::NotFound)?;
+        if attachment.complete {
+            return Err(Error::Conflict(Some(
+                "Upload is already finished".to_string(),
+            )));
+        }
+        if !(1..=MAX_CHUNK_BYTES).contains(&length) {
+            return Err(Error::InvalidInput(format!(
+                "Chunks must hold 1 to {MAX_CHUNK_BYTES} bytes"
+            )));
+        }
+        if attachment.received + length as u64 > attachment.size {
+            return Err(Error::InvalidInput(
+                "Chunk exceeds the announced size".to_string(),
+            ));
+        }
+        let index = attachment.chunk_count;
+        attachment.received += length as u64;
+        attachment.chunk_count += 1;
+        self.store.borrow_mut().insert((principal, id), attachment);
+        Ok(index)
+    }
+
+    /// Finishes an upload once all announced bytes have been received.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the attachment.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the finished attachment, or an Error if it is not found or bytes are missing.
+    pub(crate) fn finish(
+        &self,
+        principal: Principal,
+        id: AttachmentId,
+    ) -> Result<Attachment, Error> {
+        let mut attachment = self.get(principal, id).ok_or(Error::NotFound)?;
+        if attachment.received != attachment.size {
+            return Err(Error::InvalidInput(format!(
+                "Received {} of {} bytes",
+                attachment.received, attachment.size
+            )));
+        }
+        attachment.complete = true;
+        self.store
+            .borrow_mut()
+            .insert((principal, id), attachment.clone());
+        Ok(attachment)
+    }
+
+    /// Lists the attachments of a Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `todo_id` - The unique identifier for the Todo item.
+    ///
+    /// # Returns
+    ///
+    /// A vector of attachments, including unfinished uploads, in the order they were begun.
+    pub(crate) fn list(&self, principal: Principal, todo_id: TodoId) -> Vec<Attachment> {
+        self.all(principal)
+            .into_iter()
+            .filter(|attachment| attachment.todo_id == todo_id)
+            .collect()
+    }
+
+    /// Removes an attachment of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the attachment.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the removed attachment, or None if it did not exist.
+    pub(crate) fn remove(&self, principal: Principal, id: AttachmentId) -> Option<Attachment> {
+        self.store.borrow_mut().remove(&(principal, id))
+    }
+
+    /// Returns the total size of the attachments of a principal, in bytes, including unfinished uploads.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn used_bytes(&self, principal: Principal) -> u64 {
+        self.all(principal)
+            .iter()
+            .map(|attachment| attachment.size)
+            .sum()
+    }
+
+    /// Lists every attachment of a principal, in the order they were begun.
+    pub(crate) fn all(&self, principal: Principal) -> Vec<Attachment> {
+        self.store
+            .borrow()
+            .range((principal, AttachmentId::MIN)..)
+            .take_while(|((owner, _), _)| *owner == principal)
+            .map(|(_, attachment)| attachment)
+            .collect()
+    }
+
+    /// Points the attachments of a principal to the new identifiers of their Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        rewrite_entries(
+            self.store,
+            (principal, AttachmentId::MIN)..=(principal, AttachmentId::MAX),
+            |key, mut attachment| {
+                attachment.todo_id = renumbered(ids, attachment.todo_id);
+                (key, attachment)
+            },
+        );
+    }
+}
+
+/// Wrapper around the ChunkStore to provide additional functionality.
+pub(crate) struct ChunkStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<ChunkStore<M>>,
+}
+
+impl<M: Memory> ChunkStoreWrapper<'_, M> {
+    /// Stores a chunk of an attachment.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the attachment.
+    /// * `index` - The position of the chunk within the attachment.
+    /// * `bytes` - The content of the chunk.
+    pub(crate) fn put(&self, principal: Principal, id: AttachmentId, index: u32, bytes: Vec<u8>) {
+        self.store
+            .borrow_mut()
+            .insert((principal, id, index), bytes);
+    }
+
+    /// Retrieves a chunk of an attachment.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the attachment.
+    /// * `index` - The position of the chunk within the attachment.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the content of the chunk, or None if it does not exist.
+    pub(crate) fn get(
+        &self,
+        principal: Principal,
+        id: AttachmentId,
+        index: u32,
+    ) -> Option<Vec<u8>> {
+        self.store.borrow().get(&(principal, id, index))
+    }
+
+    /// Reads the whole content of an attachment by joining its chunks in order.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the attachment.
+    pub(crate) fn read(&self, principal: Principal, id: AttachmentId) -> Vec<u8> {
+        self.store
+            .borrow()
+            .range((principal, id, 0)..=(principal, id, u32::MAX))
+            .flat_map(|(_, bytes)| bytes)
+            .collect()
+    }
+
+    /// Removes every chunk of an attachment.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the attachment.
+    pub(crate) fn remove_all(&self, principal: Principal, id: AttachmentId) {
+        let mut store = self.store.borrow_mut();
+        let keys: Vec<_> = store
+            .range((principal, id, 0)..=(principal, id, u32::MAX))
+            .map(|(key, _)| key)
+            .collect();
+        for key in keys {
+            store.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_chunked_upload() {
+        let store = RefCell::new(AttachmentStore::new(DefaultMemoryImpl::default()));
+        let wrapper = AttachmentStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+
+        let id = wrapper
+            .begin(
+                principal,
+                1,
+                "receipt.pdf".to_string(),
+                "application/pdf".to_string(),
+                5,
+                0,
+            )
+            .unwrap();
+        assert_eq!(wrapper.add_chunk(principal, id, 3).unwrap(), 0);
+        assert!(matches!(
+            wrapper.finish(principal, id),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            wrapper.add_chunk(principal, id, 3),
+            Err(Error::InvalidInput(_))
+        ));
+        assert_eq!(wrapper.add_chunk(principal, id, 2).unwrap(), 1);
+        assert!(wrapper.finish(principal, id).unwrap().complete);
+        assert!(matches!(
+            wrapper.add_chunk(principal, id, 1),
+            Err(Error::Conflict(_))
+        ));
+        assert_eq!(wrapper.list(principal, 1).len(), 1);
+        assert!(wrapper.list(principal, 2).is_empty());
+    }
+
+    #[test]
+    fn test_quota() {
+        let store = RefCell::new(AttachmentStore::new(DefaultMemoryImpl::default()));
+        let wrapper = AttachmentStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let begin = || {
+            wrapper.begin(
+                principal,
+                1,
+                "a".to_string(),
+                "text/plain".to_string(),
+                MAX_ATTACHMENT_BYTES,
+                0,
+            )
+        };
+
+        for _ in 0..MAX_BYTES_PER_PRINCIPAL / MAX_ATTACHMENT_BYTES {
+            begin().unwrap();
+        }
+        assert!(matches!(begin(), Err(Error::QuotaExceeded(_))));
+        wrapper.remove(principal, 1);
+        assert!(begin().is_ok());
+    }
+}
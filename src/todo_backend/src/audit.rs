@@ -0,0 +1,293 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    paginator::Paginator,
+    store::TodoChange,
+    todo::{LenientTodo, Todo, TodoId},
+};
+
+/// Type alias for the AuditStore, which is a StableBTreeMap with a tuple key of (Principal, sequence number) and value of AuditEntry.
+pub(crate) type AuditStore<M> = StableBTreeMap<(Principal, u64), AuditEntry, M>;
+
+/// Represents a single mutation of a Todo item in the audit log of its owner.
+///
+/// Entries are chained: the hash of each entry covers the hash of the previous entry of the
+/// same owner, so that altering or removing an entry invalidates every later hash.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct AuditEntry {
+    /// Position of the entry in the audit log of the owner, starting at 0.
+    pub(crate) sequence: u64,
+    /// Time of the mutation, in nanoseconds since the Unix epoch.
+    pub(crate) timestamp: u64,
+    /// The principal that performed the mutation.
+    pub(crate) caller: Principal,
+    /// The unique identifier of the mutated Todo item.
+    pub(crate) todo_id: TodoId,
+    /// The Todo item before the mutation, or None if it was created.
+    pub(crate) before: Option<Todo>,
+    /// The Todo item after the mutation, or None if it was removed.
+    pub(crate) after: Option<Todo>,
+    /// SHA-256 hash of the previous entry's hash followed by the Candid encoding of
+    /// `(sequence, timestamp, caller, todo_id, before, after)`.
+    pub(crate) hash: Vec<u8>,
+}
+
+impl AuditEntry {
+    /// Computes the chained hash of the entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `previous_hash` - The hash of the previous entry, or an empty slice for the first entry.
+    ///
+    /// # Returns
+    ///
+    /// The SHA-256 hash of the entry.
+    fn compute_hash(&self, previous_hash: &[u8]) -> Vec<u8> {
+        let content = Encode!(
+            &self.sequence,
+            &self.timestamp,
+            &self.caller,
+            &self.todo_id,
+            &self.before,
+            &self.after
+        )
+        .unwrap();
+        let mut hasher = Sha256::new();
+        hasher.update(previous_hash);
+        hasher.update(content);
+        hasher.finalize().to_vec()
+    }
+}
+
+/// Lenient view of a stored audit entry, used when its Todo items no longer match the current schema.
+#[derive(CandidType, Deserialize)]
+struct LenientAuditEntry {
+    sequence: u64,
+    timestamp: u64,
+    caller: Principal,
+    todo_id: TodoId,
+    before: Option<LenientTodo>,
+    after: Option<LenientTodo>,
+    hash: Vec<u8>,
+}
+
+impl LenientAuditEntry {
+    /// Converts the lenient view into an audit entry, filling in defaults for missing fields.
+    ///
+    /// # Returns
+    ///
+    /// The audit entry, or None if a Todo item lacks its identifier or description.
+    fn into_entry(self) -> Option<AuditEntry> {
+        let into_todo = |todo: Option<LenientTodo>| {
+            todo.map(|todo| todo.into_todo().ok_or(())).transpose().ok()
+        };
+        Some(AuditEntry {
+            sequence: self.sequence,
+            timestamp: self.timestamp,
+            caller: self.caller,
+            todo_id: self.todo_id,
+            before: into_todo(self.before)?,
+            after: into_todo(self.after)?,
+            hash: self.hash,
+        })
+    }
+}
+
+impl Storable for AuditEntry {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    /// Decodes an audit entry, with defaults for the fields its Todo items were stored without.
+    /// Always decoded leniently: a strict decode would turn a Todo item of an older shape into None.
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), LenientAuditEntry)
+            .ok()
+            .and_then(LenientAuditEntry::into_entry)
+            .expect("Corrupt audit entry")
+    }
+}
+
+/// Wrapper around the AuditStore to provide additional functionality.
+pub(crate) struct AuditStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<AuditStore<M>>,
+}
+
+impl<M: Memory> AuditStoreWrapper<'_, M> {
+    /// Appends a change to a Todo item to the audit log of its owner.
+    ///
+    /// # Arguments
+    ///
+    /// * `change` - The change to a Todo item.
+    /// * `caller` - The principal that performed the change.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    pub(crate) fn record_change(&self, change: &TodoChange, caller: Principal, now: u64) {
+        let Some(todo_id) = change
+            .after
+            .as_ref()
+            .or(change.before.as_ref())
+            .map(|todo| todo.id)
+        else {
+            return;
+        };
+        let previous = self.last_entry(change.principal);
+        let mut entry = AuditEntry {
+            sequence: previous.as_ref().map_or(0, |entry| entry.sequence + 1),
+            timestamp: now,
+            caller,
+            todo_id,
+            before: change.before.clone(),
+            after: change.after.clone(),
+            hash: Vec::new(),
+        };
+        entry.hash = entry.compute_hash(previous.as_ref().map_or(&[], |entry| &entry.hash));
+        self.store
+            .borrow_mut()
+            .insert((change.principal, entry.sequence), entry);
+    }
+
+    /// Lists the audit log of a principal with pagination, newest entries first.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `paginator` - The paginator for controlling the list output.
+    ///
+    /// # Returns
+    ///
+    /// A vector of audit entries.
+    pub(crate) fn list_entries(
+        &self,
+        principal: Principal,
+        paginator: Paginator,
+    ) -> Vec<AuditEntry> {
+        let Some(last) = self.last_entry(principal) else {
+            return Vec::new();
+        };
+        let Some(end) = (last.sequence + 1).checked_sub(paginator.skip() as u64) else {
+            return Vec::new();
+        };
+        let start = end.saturating_sub(paginator.limit() as u64);
+        let mut entries: Vec<AuditEntry> = self
+            .store
+            .borrow()
+            .range((principal, start)..(principal, end))
+            .map(|(_, entry)| entry)
+            .collect();
+        entries.reverse();
+        entries
+    }
+
+    /// Returns the latest audit entry of a principal.
+    fn last_entry(&self, principal: Principal) -> Option<AuditEntry> {
+        self.store
+            .borrow()
+            .iter_upper_bound(&(principal, u64::MAX))
+            .next()
+            .filter(|((owner, _), _)| *owner == principal)
+            .map(|(_, entry)| entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::Priority;
+
+    fn change(principal: Principal, before: Option<Todo>, after: Option<Todo>) -> TodoChange {
+        TodoChange {
+            principal,
+            before,
+            after,
+        }
+    }
+
+    #[test]
+    fn test_entries_are_chained() {
+        let store = RefCell::new(AuditStore::new(DefaultMemoryImpl::default()));
+        let wrapper = AuditStoreWrapper { store: &store };
+        let owner = Principal::anonymous();
+        let todo = Todo::new(1, "Buy milk".to_string(), Priority::Medium);
+
+        wrapper.record_change(&change(owner, None, Some(todo.clone())), owner, 10);
+        wrapper.record_change(&change(owner, Some(todo), None), owner, 20);
+
+        let entries = wrapper.list_entries(owner, Paginator::default());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].sequence, 1);
+        assert_eq!(entries[0].timestamp, 20);
+        assert_eq!(entries[1].hash, entries[1].compute_hash(&[]));
+        assert_eq!(entries[0].hash, entries[0].compute_hash(&entries[1].hash));
+    }
+
+    #[test]
+    fn test_list_entries_paginates_newest_first() {
+        let store = RefCell::new(AuditStore::new(DefaultMemoryImpl::default()));
+        let wrapper = AuditStoreWrapper { store: &store };
+        let owner = Principal::anonymous();
+        let other = Principal::management_canister();
+        for id in 1..=7 {
+            let todo = Todo::new(id, "Task".to_string(), Priority::Low);
+            wrapper.record_change(&change(owner, None, Some(todo.clone())), owner, id);
+            wrapper.record_change(&change(other, None, Some(todo)), other, id);
+        }
+
+        let first_page = wrapper.list_entries(owner, Paginator::default());
+        let sequences: Vec<u64> = first_page.iter().map(|entry| entry.sequence).collect();
+        assert_eq!(sequences, vec![6, 5, 4, 3, 2]);
+        assert!(first_page.iter().all(|entry| entry.caller == owner));
+    }
+
+    #[test]
+    fn test_decodes_entry_of_older_todo_shape() {
+        #[derive(CandidType)]
+        struct OldTodo {
+            id: TodoId,
+            description: String,
+            is_completed: bool,
+            priority: Priority,
+        }
+        #[derive(CandidType)]
+        struct OldEntry {
+            sequence: u64,
+            timestamp: u64,
+            caller: Principal,
+            todo_id: TodoId,
+            before: Option<OldTodo>,
+            after: Option<OldTodo>,
+            hash: Vec<u8>,
+        }
+        let old = OldEntry {
+            sequence: 0,
+            timestamp: 5,
+            caller: Principal::anonymous(),
+            todo_id: 1,
+            before: None,
+            after: Some(OldTodo {
+                id: 1,
+                description: "Old".to_string(),
+                is_completed: false,
+                priority: Priority::Low,
+            }),
+            hash: vec![1, 2],
+        };
+
+        let entry = AuditEntry::from_bytes(Cow::Owned(Encode!(&old).unwrap()));
+
+        assert_eq!(entry.before, None);
+        assert_eq!(
+            entry.after.map(|todo| todo.description),
+            Some("Old".to_string())
+        );
+        assert_eq!(entry.hash, vec![1, 2]);
+    }
+}
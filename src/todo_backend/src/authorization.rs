@@ -0,0 +1,276 @@
+use candid::Principal;
+
+use crate::{
+    access::{AccountGrant, AccountScope},
+    errors::Error,
+    governance::GovernanceConfig,
+};
+
+/// Represents what an endpoint acts on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Resource {
+    /// The account of a principal: their Todo items and everything attached to them.
+    Account(Principal),
+    /// The canister as a whole: its configuration and the data of every principal.
+    Canister,
+}
+
+/// Represents what an endpoint does to a resource.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Action {
+    /// Reading an account.
+    Read,
+    /// Adding Todo items to an account.
+    Create,
+    /// Ticking off Todo items in an account.
+    Complete,
+    /// Looking up Todo items of an account on behalf of a linked Todo item; trusted peer canisters may.
+    Reference,
+    /// Any other change to an account, including granting access to it; only its owner may.
+    Manage,
+    /// Configuring the canister or reading across accounts; admins may.
+    Administer,
+    /// A destructive operation on the canister; once a governance canister is configured, only an adopted proposal may.
+    Govern,
+    /// Operations reserved to the controllers of the canister, such as generating test data.
+    Control,
+}
+
+/// Represents what is known about a caller when authorizing them.
+pub(crate) struct Context {
+    /// The access granted to the caller for the account acted on, if any.
+    pub(crate) grant: Option<AccountGrant>,
+    /// Who administers the canister.
+    pub(crate) governance: GovernanceConfig,
+    /// Whether the caller is a controller of the canister.
+    pub(crate) is_controller: bool,
+    /// Whether the caller is a trusted peer canister.
+    pub(crate) is_peer: bool,
+}
+
+/// Checks that a caller may perform an action on a resource. Every permission check goes through this
+/// function, so that the rules live in one place and new endpoints pick them up.
+///
+/// # Arguments
+///
+/// * `caller` - The caller.
+/// * `resource` - What the caller acts on.
+/// * `action` - What the caller does.
+/// * `context` - What is known about the caller.
+///
+/// # Returns
+///
+/// A Result indicating success or an Unauthorized Error.
+pub(crate) fn check(
+    caller: Principal,
+    resource: Resource,
+    action: Action,
+    context: &Context,
+) -> Result<(), Error> {
+    match (resource, action) {
+        (Resource::Account(owner), _) if owner == caller => Ok(()),
+        (Resource::Account(_), Action::Read) if context.grant.is_some() => Ok(()),
+        (Resource::Account(_), Action::Read) => Err(Error::Unauthorized(Some(
+            "No access was granted to this account".to_string(),
+        ))),
+        (Resource::Account(_), Action::Reference) if context.is_peer => Ok(()),
+        (Resource::Account(_), Action::Reference) => Err(Error::Unauthorized(Some(
+            "Caller is not a peer canister".to_string(),
+        ))),
+        (Resource::Account(_), Action::Create) => ensure_scope(context, AccountScope::Create),
+        (Resource::Account(_), Action::Complete) => ensure_scope(context, AccountScope::Complete),
+        (Resource::Account(_), _) => Err(Error::Unauthorized(Some(
+            "Only the owner of the account may do this".to_string(),
+        ))),
+        (Resource::Canister, Action::Administer) => context
+            .governance
+            .ensure_admin(caller, context.is_controller),
+        (Resource::Canister, Action::Govern) => context
+            .governance
+            .ensure_proposal(caller, context.is_controller),
+        (Resource::Canister, Action::Control) if context.is_controller => Ok(()),
+        (Resource::Canister, Action::Control) => Err(Error::Unauthorized(Some(
+            "Caller is not a controller".to_string(),
+        ))),
+        (Resource::Canister, _) => Err(Error::Unauthorized(Some(format!(
+            "{action:?} does not apply to the canister"
+        )))),
+    }
+}
+
+/// Checks that the access granted to a caller covers a kind of change.
+fn ensure_scope(context: &Context, scope: AccountScope) -> Result<(), Error> {
+    let granted = context
+        .grant
+        .as_ref()
+        .and_then(|grant| grant.scopes.as_ref())
+        .is_some_and(|scopes| scopes.contains(&scope));
+    if granted {
+        return Ok(());
+    }
+    Err(Error::Unauthorized(Some(format!(
+        "No {scope:?} access was granted to this account"
+    ))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCOUNT_ACTIONS: [Action; 5] = [
+        Action::Read,
+        Action::Create,
+        Action::Complete,
+        Action::Reference,
+        Action::Manage,
+    ];
+    const CANISTER_ACTIONS: [Action; 3] = [Action::Administer, Action::Govern, Action::Control];
+
+    fn context(
+        scopes: Option<Vec<AccountScope>>,
+        governance: Option<Principal>,
+        is_controller: bool,
+    ) -> Context {
+        Context {
+            grant: scopes.map(|scopes| AccountGrant {
+                grantee: Principal::from_slice(&[2]),
+                granted_at: 0,
+                scopes: Some(scopes),
+            }),
+            governance: GovernanceConfig { governance },
+            is_controller,
+            is_peer: false,
+        }
+    }
+
+    /// Lists the actions on a resource a caller may perform.
+    fn allowed(
+        caller: Principal,
+        resource: Resource,
+        actions: &[Action],
+        context: &Context,
+    ) -> Vec<Action> {
+        actions
+            .iter()
+            .copied()
+            .filter(|action| check(caller, resource, *action, context).is_ok())
+            .collect()
+    }
+
+    #[test]
+    fn test_account_matrix() {
+        let owner = Principal::from_slice(&[1]);
+        let other = Principal::from_slice(&[2]);
+        let account = Resource::Account(owner);
+
+        let cases = [
+            // Owner, even without any grant or admin role.
+            (owner, context(None, None, false), ACCOUNT_ACTIONS.to_vec()),
+            // Stranger, even a controller.
+            (other, context(None, None, true), vec![]),
+            // Viewer.
+            (
+                other,
+                context(Some(vec![]), None, false),
+                vec![Action::Read],
+            ),
+            // Assistant allowed to add Todo items.
+            (
+                other,
+                context(Some(vec![AccountScope::Create]), None, false),
+                vec![Action::Read, Action::Create],
+            ),
+            // Assistant allowed to tick off Todo items.
+            (
+                other,
+                context(Some(vec![AccountScope::Complete]), None, false),
+                vec![Action::Read, Action::Complete],
+            ),
+            // Assistant allowed both, who still may not manage the account.
+            (
+                other,
+                context(
+                    Some(vec![AccountScope::Create, AccountScope::Complete]),
+                    None,
+                    false,
+                ),
+                vec![Action::Read, Action::Create, Action::Complete],
+            ),
+        ];
+        for (caller, context, expected) in cases {
+            assert_eq!(
+                allowed(caller, account, &ACCOUNT_ACTIONS, &context),
+                expected
+            );
+        }
+        assert!(matches!(
+            check(
+                other,
+                account,
+                Action::Manage,
+                &context(Some(vec![]), None, false)
+            ),
+            Err(Error::Unauthorized(_))
+        ));
+
+        // Peer canister, which may only look up references.
+        let peer = Context {
+            is_peer: true,
+            ..context(None, None, false)
+        };
+        assert_eq!(
+            allowed(other, account, &ACCOUNT_ACTIONS, &peer),
+            vec![Action::Reference]
+        );
+    }
+
+    #[test]
+    fn test_canister_matrix() {
+        let caller = Principal::from_slice(&[1]);
+        let governance = Principal::from_slice(&[9]);
+
+        let cases = [
+            // Controller without governance.
+            (caller, context(None, None, true), CANISTER_ACTIONS.to_vec()),
+            // Controller once governance is configured: destructive operations need a proposal.
+            (
+                caller,
+                context(None, Some(governance), true),
+                vec![Action::Administer, Action::Control],
+            ),
+            // The governance canister.
+            (
+                governance,
+                context(None, Some(governance), false),
+                vec![Action::Administer, Action::Govern],
+            ),
+            // Anybody else, even with a grant.
+            (
+                caller,
+                context(Some(vec![AccountScope::Create]), Some(governance), false),
+                vec![],
+            ),
+        ];
+        for (caller, context, expected) in cases {
+            assert_eq!(
+                allowed(caller, Resource::Canister, &CANISTER_ACTIONS, &context),
+                expected
+            );
+        }
+        // Account actions never apply to the canister, and canister roles never open an account.
+        assert!(allowed(
+            caller,
+            Resource::Canister,
+            &ACCOUNT_ACTIONS,
+            &context(None, None, true)
+        )
+        .is_empty());
+        assert!(check(
+            governance,
+            Resource::Account(caller),
+            Action::Read,
+            &context(None, Some(governance), true)
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! Benchmarks run by `canbench` against stores in heap memory, so that changes to indexing and
+//! serialization can be compared with a baseline. No baseline has been recorded yet: run
+//! `canbench --persist` in `src/todo_backend` (or `just bench-baseline`) to write `canbench_results.yml`.
+
+use std::cell::RefCell;
+
+use canbench_rs::{bench, bench_fn, BenchResult};
+use candid::Principal;
+use ic_stable_structures::VectorMemory;
+
+use crate::{
+    budget::InstructionGuard,
+    paginator::Paginator,
+    search, seed,
+    store::{SortOrder, TodoStore, TodoStoreWrapper},
+    todo::TodoId,
+};
+
+/// Fixed time the benchmarks run at, so that generated due dates do not vary between runs.
+const NOW: u64 = 1_700_000_000_000_000_000;
+
+/// Number of Todo items in the small store.
+const SMALL: u32 = 10_000;
+
+/// Number of Todo items in the large store.
+const LARGE: u32 = 100_000;
+
+/// Number of Todo items listed per page, the largest page size allowed.
+const PAGE_SIZE: u32 = 100;
+
+/// Number of Todo items added by a batch.
+const BATCH: u32 = 100;
+
+/// Creates a store in heap memory holding `count` generated Todo items of a single principal.
+///
+/// # Arguments
+///
+/// * `count` - The number of Todo items.
+///
+/// # Returns
+///
+/// The store and the principal owning the Todo items.
+fn filled_store(count: u32) -> (RefCell<TodoStore<VectorMemory>>, Principal) {
+    let store = RefCell::new(TodoStore::new(VectorMemory::default()));
+    let principal = seed::fake_principal(0);
+    let wrapper = TodoStoreWrapper { store: &store };
+    for index in 0..count {
+        let todo = seed::fake_todo(0, index, NOW);
+        wrapper
+            .create_todo(principal, TodoId::from(index), todo.request, NOW)
+            .unwrap();
+    }
+    (store, principal)
+}
+
+/// Measures adding one Todo item to a store holding `count` Todo items.
+fn add(count: u32) -> BenchResult {
+    let (store, principal) = filled_store(count);
+    let wrapper = TodoStoreWrapper { store: &store };
+    let request = seed::fake_todo(1, 0, NOW).request;
+    bench_fn(|| {
+        wrapper
+            .create_todo(principal, TodoId::from(count), request, NOW)
+            .unwrap()
+    })
+}
+
+/// Measures adding a batch of Todo items to a store holding `count` Todo items, either at once or one by one.
+fn add_batch(count: u32, batched: bool) -> BenchResult {
+    let (store, principal) = filled_store(count);
+    let wrapper = TodoStoreWrapper { store: &store };
+    let requests: Vec<_> = (0..BATCH)
+        .map(|index| {
+            let todo = seed::fake_todo(1, index, NOW);
+            (TodoId::from(count + index), todo.request)
+        })
+        .collect();
+    bench_fn(|| {
+        if batched {
+            wrapper.create_many(principal, requests, NOW).unwrap();
+            return;
+        }
+        for (id, request) in requests {
+            wrapper.create_todo(principal, id, request, NOW).unwrap();
+        }
+    })
+}
+
+/// Measures listing the last page of a store holding `count` Todo items.
+fn list(count: u32, sort: SortOrder) -> BenchResult {
+    let (store, principal) = filled_store(count);
+    let wrapper = TodoStoreWrapper { store: &store };
+    let paginator = Paginator::new(count / PAGE_SIZE, Some(PAGE_SIZE));
+    let guard = InstructionGuard::with_counter(u64::MAX, || 0);
+    bench_fn(|| wrapper.list_todos(principal, paginator, sort, &guard))
+}
+
+/// Measures finding the Todo items whose description contains a word in a store holding `count` Todo items.
+/// No search index exists yet, so `search_todos` scans this way until its page is full.
+fn search(count: u32) -> BenchResult {
+    let (store, principal) = filled_store(count);
+    let wrapper = TodoStoreWrapper { store: &store };
+    let words = search::words("report");
+    bench_fn(|| {
+        wrapper
+            .all_todos(principal)
+            .into_iter()
+            .filter(|todo| search::matches(todo, &words))
+            .count()
+    })
+}
+
+/// Measures toggling the completion of one Todo item in a store holding `count` Todo items.
+fn toggle(count: u32) -> BenchResult {
+    let (store, principal) = filled_store(count);
+    let wrapper = TodoStoreWrapper { store: &store };
+    bench_fn(|| {
+        wrapper
+            .toggle_todo_complete(principal, TodoId::from(count / 2), NOW)
+            .unwrap()
+    })
+}
+
+#[bench(raw)]
+fn add_todo_10k() -> BenchResult {
+    add(SMALL)
+}
+
+#[bench(raw)]
+fn add_todo_100k() -> BenchResult {
+    add(LARGE)
+}
+
+#[bench(raw)]
+fn add_todos_one_by_one_10k() -> BenchResult {
+    add_batch(SMALL, false)
+}
+
+#[bench(raw)]
+fn add_todos_batched_10k() -> BenchResult {
+    add_batch(SMALL, true)
+}
+
+#[bench(raw)]
+fn list_todos_by_creation_10k() -> BenchResult {
+    list(SMALL, SortOrder::Created)
+}
+
+#[bench(raw)]
+fn list_todos_by_creation_100k() -> BenchResult {
+    list(LARGE, SortOrder::Created)
+}
+
+#[bench(raw)]
+fn list_todos_by_due_date_10k() -> BenchResult {
+    list(SMALL, SortOrder::DueDate)
+}
+
+#[bench(raw)]
+fn list_todos_by_due_date_100k() -> BenchResult {
+    list(LARGE, SortOrder::DueDate)
+}
+
+#[bench(raw)]
+fn search_todos_10k() -> BenchResult {
+    search(SMALL)
+}
+
+#[bench(raw)]
+fn search_todos_100k() -> BenchResult {
+    search(LARGE)
+}
+
+#[bench(raw)]
+fn toggle_todo_10k() -> BenchResult {
+    toggle(SMALL)
+}
+
+#[bench(raw)]
+fn toggle_todo_100k() -> BenchResult {
+    toggle(LARGE)
+}
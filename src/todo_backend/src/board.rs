@@ -0,0 +1,296 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{
+    errors::Error,
+    renumbering::{renumbered, rewrite_entries, Renumbering},
+    store::TodoChange,
+    todo::{Todo, TodoId},
+};
+
+/// Type alias for the unique identifier of a board column.
+pub(crate) type ColumnId = u64;
+
+/// Type alias for the BoardStore, which is a StableBTreeMap with a tuple key of (Principal, ColumnId) and value of Column.
+pub(crate) type BoardStore<M> = StableBTreeMap<(Principal, ColumnId), Column, M>;
+
+/// Represents a column on the board of a project.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Column {
+    /// Unique identifier for the column.
+    pub(crate) id: ColumnId,
+    /// The project whose board the column belongs to.
+    pub(crate) project: String,
+    /// Name of the column.
+    pub(crate) name: String,
+    /// Position of the column on the board, from left to right.
+    pub(crate) position: u32,
+    /// Todo items placed in the column, from top to bottom.
+    pub(crate) cards: Vec<TodoId>,
+}
+
+impl Storable for Column {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Represents a column of a board together with its cards.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct BoardColumn {
+    /// Unique identifier for the column.
+    pub(crate) id: ColumnId,
+    /// Name of the column.
+    pub(crate) name: String,
+    /// Todo items placed in the column, from top to bottom.
+    pub(crate) cards: Vec<Todo>,
+}
+
+/// Represents the board of a project.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Board {
+    /// The project the board belongs to.
+    pub(crate) project: String,
+    /// Columns of the board, from left to right.
+    pub(crate) columns: Vec<BoardColumn>,
+}
+
+/// Wrapper around the BoardStore to provide additional functionality.
+pub(crate) struct BoardStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<BoardStore<M>>,
+}
+
+impl<M: Memory> BoardStoreWrapper<'_, M> {
+    /// Adds a column to the right end of the board of a project.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `project` - The project whose board the column belongs to.
+    /// * `name` - The name of the column.
+    ///
+    /// # Returns
+    ///
+    /// The unique identifier for the new column.
+    pub(crate) fn create_column(
+        &self,
+        principal: Principal,
+        project: String,
+        name: String,
+    ) -> ColumnId {
+        let id = self
+            .store
+            .borrow()
+            .iter_upper_bound(&(principal, ColumnId::MAX))
+            .next()
+            .filter(|((owner, _), _)| *owner == principal)
+            .map_or(1, |((_, id), _)| id + 1);
+        let position = self.columns(principal, &project).len() as u32;
+        let column = Column {
+            id,
+            project,
+            name,
+            position,
+            cards: Vec::new(),
+        };
+        self.store.borrow_mut().insert((principal, id), column);
+        id
+    }
+
+    /// Retrieves a column.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the column.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the column if found, otherwise None.
+    pub(crate) fn get_column(&self, principal: Principal, id: ColumnId) -> Option<Column> {
+        self.store.borrow().get(&(principal, id))
+    }
+
+    /// Lists the columns of the board of a project, from left to right.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `project` - The project whose board is listed.
+    ///
+    /// # Returns
+    ///
+    /// A vector of columns.
+    pub(crate) fn columns(&self, principal: Principal, project: &str) -> Vec<Column> {
+        let mut columns: Vec<Column> = self
+            .store
+            .borrow()
+            .range((principal, ColumnId::MIN)..)
+            .take_while(|((p, _), _)| p == &principal)
+            .map(|(_, column)| column)
+            .filter(|column| column.project == project)
+            .collect();
+        columns.sort_by_key(|column| column.position);
+        columns
+    }
+
+    /// Lists the columns of every board of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn all_columns(&self, principal: Principal) -> Vec<Column> {
+        self.store
+            .borrow()
+            .range((principal, ColumnId::MIN)..=(principal, ColumnId::MAX))
+            .map(|(_, column)| column)
+            .collect()
+    }
+
+    /// Puts back the columns of a principal from an account bundle, keeping their identifiers.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `columns` - The validated columns.
+    pub(crate) fn restore(&self, principal: Principal, columns: Vec<Column>) {
+        let mut store = self.store.borrow_mut();
+        for column in columns {
+            store.insert((principal, column.id), column);
+        }
+    }
+
+    /// Places a card at a position within a column, moving it if it is already there.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the column.
+    /// * `todo_id` - The unique identifier for the Todo item.
+    /// * `position` - The position from the top; positions past the end place the card last.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if the column is not found.
+    pub(crate) fn place_card(
+        &self,
+        principal: Principal,
+        id: ColumnId,
+        todo_id: TodoId,
+        position: u32,
+    ) -> Result<(), Error> {
+        let mut column = self.get_column(principal, id).ok_or(Error::NotFound)?;
+        column.cards.retain(|card| *card != todo_id);
+        let position = (position as usize).min(column.cards.len());
+        column.cards.insert(position, todo_id);
+        self.store.borrow_mut().insert((principal, id), column);
+        Ok(())
+    }
+
+    /// Removes the card of a Todo item from its column when it leaves the column or is removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `change` - The change to a Todo item.
+    pub(crate) fn record_change(&self, change: &TodoChange) {
+        let Some(before) = &change.before else {
+            return;
+        };
+        let Some(column_id) = before.column_id else {
+            return;
+        };
+        if change.after.as_ref().and_then(|after| after.column_id) == Some(column_id) {
+            return;
+        }
+        if let Some(mut column) = self.get_column(change.principal, column_id) {
+            column.cards.retain(|card| *card != before.id);
+            self.store
+                .borrow_mut()
+                .insert((change.principal, column_id), column);
+        }
+    }
+
+    /// Points the cards on the boards of a principal to the new identifiers of their Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        rewrite_entries(
+            self.store,
+            (principal, ColumnId::MIN)..=(principal, ColumnId::MAX),
+            |key, mut column| {
+                for card in &mut column.cards {
+                    *card = renumbered(ids, *card);
+                }
+                (key, column)
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::Priority;
+
+    #[test]
+    fn test_columns_and_cards() {
+        let store = RefCell::new(BoardStore::new(DefaultMemoryImpl::default()));
+        let wrapper = BoardStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let todo = wrapper.create_column(principal, "home".to_string(), "To do".to_string());
+        let done = wrapper.create_column(principal, "home".to_string(), "Done".to_string());
+        wrapper.create_column(principal, "work".to_string(), "Backlog".to_string());
+
+        let names: Vec<String> = wrapper
+            .columns(principal, "home")
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, vec!["To do", "Done"]);
+
+        wrapper.place_card(principal, todo, 1, 0).unwrap();
+        wrapper.place_card(principal, todo, 2, 0).unwrap();
+        wrapper.place_card(principal, todo, 1, 0).unwrap();
+        wrapper.place_card(principal, todo, 3, 99).unwrap();
+        assert_eq!(
+            wrapper.get_column(principal, todo).unwrap().cards,
+            vec![1, 2, 3]
+        );
+        assert!(matches!(
+            wrapper.place_card(principal, 99, 1, 0),
+            Err(Error::NotFound)
+        ));
+
+        let before = Todo {
+            column_id: Some(todo),
+            ..Todo::new(2, "Card".to_string(), Priority::Low)
+        };
+        let after = Todo {
+            column_id: Some(done),
+            ..before.clone()
+        };
+        wrapper.record_change(&TodoChange {
+            principal,
+            before: Some(before),
+            after: Some(after),
+        });
+        assert_eq!(
+            wrapper.get_column(principal, todo).unwrap().cards,
+            vec![1, 3]
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use std::borrow::Cow;
+
+use ic_stable_structures::{storable::Bound, Storable};
+
+/// A string with an upper bound on its encoded size, so that it can be part of a tuple key in a stable map.
+///
+/// Callers must validate the length of the string before storing it; `MAX_BYTES` is a size in bytes,
+/// so a limit of `n` characters needs `4 * n` bytes.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct BoundedString<const MAX_BYTES: u32>(pub(crate) String);
+
+impl<const MAX_BYTES: u32> From<String> for BoundedString<MAX_BYTES> {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl<const MAX_BYTES: u32> From<&str> for BoundedString<MAX_BYTES> {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl<const MAX_BYTES: u32> Storable for BoundedString<MAX_BYTES> {
+    const BOUND: Bound = Bound::Bounded {
+        max_size: MAX_BYTES,
+        is_fixed_size: false,
+    };
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        assert!(self.0.len() <= MAX_BYTES as usize, "String exceeds {MAX_BYTES} bytes");
+        Cow::Borrowed(self.0.as_bytes())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self(String::from_utf8(bytes.into_owned()).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use candid::Principal;
+    use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap};
+
+    #[test]
+    fn test_tuple_key_round_trip() {
+        let mut map: StableBTreeMap<(Principal, BoundedString<16>), u64, _> =
+            StableBTreeMap::new(DefaultMemoryImpl::default());
+        let principal = Principal::anonymous();
+        map.insert((principal, "b".into()), 2);
+        map.insert((principal, "a".into()), 1);
+
+        let keys: Vec<String> = map.iter().map(|((_, key), _)| key.0).collect();
+        assert_eq!(keys, vec!["a", "b"]);
+        assert_eq!(map.get(&(principal, "b".into())), Some(2));
+    }
+}
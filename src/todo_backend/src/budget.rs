@@ -0,0 +1,121 @@
+use std::borrow::Cow;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_stable_structures::{storable::Bound, Storable};
+
+use crate::errors::Error;
+
+/// Fewest instructions a call may be budgeted, so that every call still makes progress.
+const MIN_INSTRUCTIONS: u64 = 100_000_000;
+
+/// Most instructions a call may be budgeted, below the limit of a query call with room left to reply.
+const MAX_INSTRUCTIONS: u64 = 4_000_000_000;
+
+/// Represents how many instructions a long-running call may execute before it stops and returns a continuation token.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) struct InstructionBudget {
+    /// Number of instructions, counted from the start of the call.
+    pub(crate) instructions: u64,
+}
+
+impl Default for InstructionBudget {
+    fn default() -> Self {
+        Self {
+            instructions: 2_000_000_000,
+        }
+    }
+}
+
+impl Storable for InstructionBudget {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl InstructionBudget {
+    /// Creates an instruction budget.
+    ///
+    /// # Arguments
+    ///
+    /// * `instructions` - Number of instructions a call may execute.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the budget, or an Error if it is too small or too large.
+    pub(crate) fn new(instructions: u64) -> Result<Self, Error> {
+        if !(MIN_INSTRUCTIONS..=MAX_INSTRUCTIONS).contains(&instructions) {
+            return Err(Error::InvalidInput(format!(
+                "Instruction budget must be between {MIN_INSTRUCTIONS} and {MAX_INSTRUCTIONS}"
+            )));
+        }
+        Ok(Self { instructions })
+    }
+
+    /// Starts guarding the current call against exceeding the budget.
+    pub(crate) fn guard(&self) -> InstructionGuard {
+        InstructionGuard {
+            limit: self.instructions,
+            counter: instructions,
+        }
+    }
+}
+
+/// Tells long loops when the current call spent its instruction budget, so that they stop and
+/// return a continuation token instead of trapping at the instruction limit.
+pub(crate) struct InstructionGuard {
+    limit: u64,
+    counter: fn() -> u64,
+}
+
+impl InstructionGuard {
+    /// Creates a guard reading the instructions from another counter.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - Number of instructions after which the guard is exhausted.
+    /// * `counter` - Returns the instructions executed so far.
+    #[cfg(any(test, feature = "canbench-rs"))]
+    pub(crate) fn with_counter(limit: u64, counter: fn() -> u64) -> Self {
+        Self { limit, counter }
+    }
+
+    /// Returns whether the call spent its budget. Loops check this after each step, so that they always make progress.
+    pub(crate) fn exhausted(&self) -> bool {
+        (self.counter)() >= self.limit
+    }
+}
+
+/// Returns the number of instructions the current message executed so far.
+fn instructions() -> u64 {
+    // Counter 0 restarts with every message, which is what the instruction limit applies to.
+    ic_cdk::api::performance_counter(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_and_guard() {
+        assert!(matches!(
+            InstructionBudget::new(MIN_INSTRUCTIONS - 1),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(InstructionBudget::new(MAX_INSTRUCTIONS + 1).is_err());
+        assert_eq!(
+            InstructionBudget::new(MIN_INSTRUCTIONS)
+                .unwrap()
+                .instructions,
+            MIN_INSTRUCTIONS
+        );
+
+        assert!(!InstructionGuard::with_counter(10, || 9).exhausted());
+        assert!(InstructionGuard::with_counter(10, || 10).exhausted());
+    }
+}
@@ -0,0 +1,121 @@
+use candid::{CandidType, Deserialize};
+
+use crate::{account::BUNDLE_VERSION, migration::SCHEMA_VERSION};
+
+/// Versions of the public interface the canister serves.
+const API_VERSIONS: &[u32] = &[1];
+
+/// The Candid interface, embedded as the public `candid:service` metadata of the module so that tooling reads it
+/// without calling the canister.
+#[cfg_attr(target_arch = "wasm32", link_section = "icp:public candid:service")]
+#[used]
+static CANDID_SERVICE: [u8; include_bytes!("../todo_backend.did").len()] =
+    *include_bytes!("../todo_backend.did");
+
+/// The schema version, embedded in decimal as public metadata of the module.
+#[cfg_attr(
+    target_arch = "wasm32",
+    link_section = "icp:public todo:schema_version"
+)]
+#[used]
+static SCHEMA_VERSION_SECTION: [u8; decimal_length(SCHEMA_VERSION)] = decimal(SCHEMA_VERSION);
+
+/// Represents the build of the running canister, so that deployed frontends can detect that they are out of date.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct BuildInfo {
+    /// The version of the `todo_backend` crate.
+    pub(crate) version: String,
+    /// The git commit the canister was built from, if it was set in `GIT_COMMIT` at build time.
+    pub(crate) git_commit: Option<String>,
+    /// The version of the layout of the stored data.
+    pub(crate) schema_version: u32,
+    /// The versions of the public interface the canister serves.
+    pub(crate) api_versions: Vec<u32>,
+    /// The Candid description of the public interface.
+    pub(crate) candid_interface: String,
+}
+
+/// Describes the running build.
+///
+/// # Arguments
+///
+/// * `candid_interface` - The Candid description of the public interface.
+pub(crate) fn current(candid_interface: String) -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: option_env!("GIT_COMMIT")
+            .filter(|commit| !commit.is_empty())
+            .map(str::to_string),
+        schema_version: SCHEMA_VERSION,
+        api_versions: API_VERSIONS.to_vec(),
+        candid_interface,
+    }
+}
+
+/// Represents the interface and data layouts the canister speaks, for tooling and other canisters that
+/// introspect it at runtime.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Schema {
+    /// The version of the layout of the stored data.
+    pub(crate) schema_version: u32,
+    /// The version of the account bundle layout written by `export_account`.
+    pub(crate) bundle_version: u32,
+    /// The versions of the public interface the canister serves.
+    pub(crate) api_versions: Vec<u32>,
+    /// The Candid description of the public interface.
+    pub(crate) candid_interface: String,
+}
+
+/// Describes the interface and data layouts of the running build.
+///
+/// # Arguments
+///
+/// * `candid_interface` - The Candid description of the public interface.
+pub(crate) fn schema(candid_interface: String) -> Schema {
+    Schema {
+        schema_version: SCHEMA_VERSION,
+        bundle_version: BUNDLE_VERSION,
+        api_versions: API_VERSIONS.to_vec(),
+        candid_interface,
+    }
+}
+
+/// Returns the number of decimal digits of a number.
+const fn decimal_length(mut value: u32) -> usize {
+    let mut length = 1;
+    while value >= 10 {
+        value /= 10;
+        length += 1;
+    }
+    length
+}
+
+/// Writes a number in decimal, for metadata that is read as text.
+const fn decimal<const N: usize>(mut value: u32) -> [u8; N] {
+    let mut digits = [b'0'; N];
+    let mut position = N;
+    while position > 0 {
+        position -= 1;
+        digits[position] = b'0' + (value % 10) as u8;
+        value /= 10;
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_sections() {
+        assert_eq!(decimal::<{ decimal_length(0) }>(0), *b"0");
+        assert_eq!(decimal::<{ decimal_length(1203) }>(1203), *b"1203");
+        assert_eq!(
+            SCHEMA_VERSION_SECTION,
+            *SCHEMA_VERSION.to_string().as_bytes()
+        );
+        assert!(std::str::from_utf8(&CANDID_SERVICE)
+            .unwrap()
+            .contains("get_schema"));
+    }
+}
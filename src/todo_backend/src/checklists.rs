@@ -0,0 +1,179 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::errors::Error;
+
+/// Maximum number of checklists a principal may save.
+pub(crate) const MAX_CHECKLISTS: usize = 50;
+
+/// Maximum number of items in a checklist, and of subtasks in a Todo item.
+pub(crate) const MAX_CHECKLIST_ITEMS: usize = 100;
+
+/// Type alias for the unique identifier of a checklist, unique per principal.
+pub(crate) type ChecklistId = u64;
+
+/// Type alias for the ChecklistStore, which is a StableBTreeMap with a tuple key of (Principal, ChecklistId) and value of Checklist.
+pub(crate) type ChecklistStore<M> = StableBTreeMap<(Principal, ChecklistId), Checklist, M>;
+
+/// Represents a saved list of subtasks that can be added to any Todo item, such as the steps of a release.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Checklist {
+    /// Unique identifier for the checklist.
+    pub(crate) id: ChecklistId,
+    /// Name of the checklist.
+    pub(crate) name: String,
+    /// Titles of the subtasks the checklist adds, in order.
+    pub(crate) items: Vec<String>,
+}
+
+impl Storable for Checklist {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Wrapper around the ChecklistStore to provide additional functionality.
+pub(crate) struct ChecklistStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<ChecklistStore<M>>,
+}
+
+impl<M: Memory> ChecklistStoreWrapper<'_, M> {
+    /// Saves a checklist.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `name` - The validated name of the checklist.
+    /// * `items` - The validated titles of the subtasks of the checklist.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the unique identifier for the new checklist, or an Error if too many checklists exist.
+    pub(crate) fn create(
+        &self,
+        principal: Principal,
+        name: String,
+        items: Vec<String>,
+    ) -> Result<ChecklistId, Error> {
+        if self.list(principal).len() >= MAX_CHECKLISTS {
+            return Err(Error::QuotaExceeded(Some(format!(
+                "At most {MAX_CHECKLISTS} checklists can be saved"
+            ))));
+        }
+        let id = self
+            .store
+            .borrow()
+            .iter_upper_bound(&(principal, ChecklistId::MAX))
+            .next()
+            .filter(|((owner, _), _)| *owner == principal)
+            .map_or(1, |((_, id), _)| id + 1);
+        let checklist = Checklist { id, name, items };
+        self.store.borrow_mut().insert((principal, id), checklist);
+        Ok(id)
+    }
+
+    /// Retrieves a checklist.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the checklist.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the checklist if found, otherwise None.
+    pub(crate) fn get(&self, principal: Principal, id: ChecklistId) -> Option<Checklist> {
+        self.store.borrow().get(&(principal, id))
+    }
+
+    /// Lists the checklists of a principal in the order they were saved.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn list(&self, principal: Principal) -> Vec<Checklist> {
+        self.store
+            .borrow()
+            .range((principal, ChecklistId::MIN)..=(principal, ChecklistId::MAX))
+            .map(|(_, checklist)| checklist)
+            .collect()
+    }
+
+    /// Puts back the checklists of a principal from an account bundle, keeping their identifiers.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `checklists` - The validated checklists.
+    pub(crate) fn restore(&self, principal: Principal, checklists: Vec<Checklist>) {
+        let mut store = self.store.borrow_mut();
+        for checklist in checklists {
+            store.insert((principal, checklist.id), checklist);
+        }
+    }
+
+    /// Deletes a checklist; subtasks it added to Todo items are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the checklist.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if the checklist is not found.
+    pub(crate) fn delete(&self, principal: Principal, id: ChecklistId) -> Result<(), Error> {
+        self.store
+            .borrow_mut()
+            .remove(&(principal, id))
+            .map(|_| ())
+            .ok_or(Error::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_checklists() {
+        let store = RefCell::new(ChecklistStore::new(DefaultMemoryImpl::default()));
+        let wrapper = ChecklistStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let other = Principal::management_canister();
+
+        let items = vec!["Tag".to_string(), "Publish".to_string()];
+        let release = wrapper
+            .create(principal, "Release".to_string(), items.clone())
+            .unwrap();
+        let trip = wrapper
+            .create(principal, "Trip".to_string(), vec!["Pack".to_string()])
+            .unwrap();
+        assert_eq!((release, trip), (1, 2));
+        assert_eq!(
+            wrapper
+                .create(other, "Other".to_string(), Vec::new())
+                .unwrap(),
+            1
+        );
+        assert_eq!(wrapper.get(principal, release).unwrap().items, items);
+
+        assert!(wrapper.delete(principal, release).is_ok());
+        assert!(matches!(
+            wrapper.delete(principal, release),
+            Err(Error::NotFound)
+        ));
+        assert_eq!(wrapper.list(principal).len(), 1);
+        assert_eq!(wrapper.list(other).len(), 1);
+    }
+}
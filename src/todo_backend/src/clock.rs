@@ -0,0 +1,29 @@
+#[cfg(feature = "testing")]
+use std::cell::Cell;
+
+#[cfg(feature = "testing")]
+thread_local! {
+    /// Amount of time the clock was moved forward by, in nanoseconds.
+    /// Kept on the heap, so an upgrade resets it.
+    static OFFSET: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Returns the current time, in nanoseconds since the Unix epoch.
+///
+/// With the `testing` feature, the time includes the amount the clock was moved forward by.
+pub(crate) fn now() -> u64 {
+    #[cfg(feature = "testing")]
+    return ic_cdk::api::time() + OFFSET.with(Cell::get);
+    #[cfg(not(feature = "testing"))]
+    ic_cdk::api::time()
+}
+
+/// Moves the clock forward, so that time-based logic can be exercised deterministically.
+///
+/// # Arguments
+///
+/// * `nanos` - The amount of time, in nanoseconds.
+#[cfg(feature = "testing")]
+pub(crate) fn advance(nanos: u64) {
+    OFFSET.with(|offset| offset.set(offset.get() + nanos));
+}
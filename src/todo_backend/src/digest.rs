@@ -0,0 +1,135 @@
+use candid::{CandidType, Deserialize};
+
+use crate::{stats::local_day_of, todo::Todo};
+
+/// Maximum number of Todo items suggested as focus for the day.
+const FOCUS_LIMIT: usize = 5;
+
+/// Represents the overview of a principal's Todo items at the start of a day.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct DailyDigest {
+    /// Open Todo items whose due date lies before today.
+    pub(crate) overdue: Vec<Todo>,
+    /// Open Todo items due today.
+    pub(crate) due_today: Vec<Todo>,
+    /// Todo items completed yesterday.
+    pub(crate) completed_yesterday: Vec<Todo>,
+    /// Open Todo items suggested to work on today, most urgent first.
+    pub(crate) suggested_focus: Vec<Todo>,
+}
+
+/// Assembles the daily digest from the Todo items of a principal.
+///
+/// Days are delimited in the principal's time zone. Focus suggestions favour overdue items,
+/// then items due today, then higher priorities and earlier due dates.
+///
+/// # Arguments
+///
+/// * `todos` - All Todo items of the principal.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+/// * `utc_offset` - The offset of the principal's time zone from UTC, in nanoseconds.
+///
+/// # Returns
+///
+/// The daily digest.
+pub(crate) fn build(todos: Vec<Todo>, now: u64, utc_offset: i64) -> DailyDigest {
+    let day_of = |timestamp| local_day_of(timestamp, utc_offset);
+    let today = day_of(now);
+    let mut digest = DailyDigest {
+        overdue: Vec::new(),
+        due_today: Vec::new(),
+        completed_yesterday: Vec::new(),
+        suggested_focus: Vec::new(),
+    };
+    let mut open = Vec::new();
+    for todo in todos {
+        if todo.is_completed {
+            if todo.completed_at.map(day_of) == Some(today.saturating_sub(1)) {
+                digest.completed_yesterday.push(todo);
+            }
+            continue;
+        }
+        match todo.due_date {
+            Some(due_date) if day_of(due_date) < today => digest.overdue.push(todo.clone()),
+            Some(due_date) if day_of(due_date) == today => digest.due_today.push(todo.clone()),
+            _ => {}
+        }
+        open.push(todo);
+    }
+    open.sort_by_key(|todo| {
+        let urgency = match todo.due_date {
+            Some(due_date) if day_of(due_date) <= today => 0,
+            _ => 1,
+        };
+        (
+            urgency,
+            todo.priority.rank(),
+            todo.due_date.unwrap_or(u64::MAX),
+            todo.id,
+        )
+    });
+    open.truncate(FOCUS_LIMIT);
+    digest.suggested_focus = open;
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        stats::{NANOS_PER_DAY, NANOS_PER_MINUTE},
+        todo::Priority,
+    };
+
+    fn todo(id: u64, priority: Priority, due_date: Option<u64>) -> Todo {
+        Todo {
+            due_date,
+            ..Todo::new(id, format!("Task {id}"), priority)
+        }
+    }
+
+    fn ids(todos: &[Todo]) -> Vec<u64> {
+        todos.iter().map(|todo| todo.id).collect()
+    }
+
+    #[test]
+    fn test_build_digest() {
+        let now = 10 * NANOS_PER_DAY + 1;
+        let mut completed = todo(1, Priority::Low, None);
+        completed.set_completed(true, 9 * NANOS_PER_DAY);
+        let todos = vec![
+            completed,
+            todo(2, Priority::Low, Some(9 * NANOS_PER_DAY)),
+            todo(3, Priority::Medium, Some(10 * NANOS_PER_DAY + 5)),
+            todo(4, Priority::High, None),
+            todo(5, Priority::High, Some(12 * NANOS_PER_DAY)),
+            todo(6, Priority::Low, None),
+            todo(7, Priority::Medium, None),
+        ];
+
+        let digest = build(todos, now, 0);
+        assert_eq!(ids(&digest.overdue), vec![2]);
+        assert_eq!(ids(&digest.due_today), vec![3]);
+        assert_eq!(ids(&digest.completed_yesterday), vec![1]);
+        assert_eq!(ids(&digest.suggested_focus), vec![3, 2, 5, 4, 7]);
+    }
+
+    #[test]
+    fn test_build_digest_in_local_time() {
+        let now = 10 * NANOS_PER_DAY + 60 * NANOS_PER_MINUTE;
+        let due_late_yesterday = todo(
+            1,
+            Priority::Low,
+            Some(10 * NANOS_PER_DAY - 60 * NANOS_PER_MINUTE),
+        );
+
+        let digest = build(vec![due_late_yesterday.clone()], now, 0);
+        assert_eq!(ids(&digest.overdue), vec![1]);
+
+        let utc_offset = 2 * 60 * NANOS_PER_MINUTE as i64;
+        let digest = build(vec![due_late_yesterday], now, utc_offset);
+        assert!(digest.overdue.is_empty());
+        assert_eq!(ids(&digest.due_today), vec![1]);
+    }
+}
@@ -0,0 +1,489 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use candid::Principal;
+use ic_http_certification::{
+    DefaultCelBuilder, DefaultFullCelExpression, DefaultResponseCertification,
+    DefaultResponseOnlyCelExpression, HttpCertification, HttpCertificationPath,
+    HttpCertificationTree, HttpCertificationTreeEntry, CERTIFICATE_EXPRESSION_HEADER_NAME,
+    CERTIFICATE_HEADER_NAME,
+};
+use ic_stable_structures::{Memory, StableBTreeMap};
+
+use crate::{
+    attachments::{Attachment, AttachmentId},
+    http::HttpResponse,
+    todo::Todo,
+};
+
+/// Path of the route serving the Todo items of a principal as JSON.
+const EXPORT_PATH: &str = "/export.json";
+
+/// Prefix of the routes serving attachments, followed by the identifier of the attachment.
+const ATTACHMENTS_PREFIX: &str = "/attachments/";
+
+/// Query parameter carrying the download token.
+pub(crate) const TOKEN_PARAMETER: &str = "token";
+
+/// Maximum size of an attachment served over HTTP, in bytes, which keeps the certified response
+/// below the query response limit. Larger attachments are downloaded with `get_attachment_chunk`.
+pub(crate) const MAX_DOWNLOAD_BYTES: u64 = 2_000_000;
+
+/// Type alias for the DownloadTokenStore, which is a StableBTreeMap keyed by Principal with the secret of its download token.
+pub(crate) type DownloadTokenStore<M> = StableBTreeMap<Principal, String, M>;
+
+thread_local! {
+    /// Certifications of the downloads currently served.
+    /// Kept on the heap, so they are rebuilt after an upgrade.
+    pub(crate) static CERTIFIER: RefCell<Certifier> = RefCell::new(Certifier::new());
+}
+
+/// Represents a file served through the HTTP gateway.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Download {
+    /// The Todo items of the principal, as JSON.
+    Export,
+    /// An attachment of the principal.
+    Attachment(AttachmentId),
+}
+
+impl Download {
+    /// Parses the path of a request URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path, without the query string.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the download, or None if the path does not serve one.
+    pub(crate) fn parse(path: &str) -> Option<Self> {
+        let download = if path == EXPORT_PATH {
+            Download::Export
+        } else {
+            Download::Attachment(path.strip_prefix(ATTACHMENTS_PREFIX)?.parse().ok()?)
+        };
+        // Only the canonical path is certified, so `/attachments/007` is not an alias of `/attachments/7`.
+        (download.path() == path).then_some(download)
+    }
+
+    /// Returns the path the download is served at.
+    fn path(self) -> String {
+        match self {
+            Download::Export => EXPORT_PATH.to_string(),
+            Download::Attachment(id) => format!("{ATTACHMENTS_PREFIX}{id}"),
+        }
+    }
+}
+
+/// Builds the response serving the Todo items of a principal.
+///
+/// # Arguments
+///
+/// * `todos` - All Todo items of the principal.
+pub(crate) fn export_response(todos: &[Todo]) -> HttpResponse {
+    response(
+        200,
+        "application/json",
+        "attachment; filename=\"todos.json\"".to_string(),
+        serde_json::to_vec(todos).unwrap_or_default(),
+    )
+}
+
+/// Builds the response serving an attachment.
+///
+/// # Arguments
+///
+/// * `attachment` - The finished attachment.
+/// * `body` - The content of the attachment.
+pub(crate) fn attachment_response(attachment: &Attachment, body: Vec<u8>) -> HttpResponse {
+    response(
+        200,
+        &attachment.mime,
+        format!(
+            "inline; filename*=UTF-8''{}",
+            percent_encode(&attachment.name)
+        ),
+        body,
+    )
+}
+
+/// Builds the response served for every path, token or attachment that cannot be downloaded,
+/// which does not tell apart a wrong token from a missing file.
+fn not_found() -> HttpResponse {
+    let mut response = HttpResponse::text(404, "Not found");
+    response.headers.push((
+        CERTIFICATE_EXPRESSION_HEADER_NAME.to_string(),
+        fallback_expression().to_string(),
+    ));
+    response
+}
+
+/// Builds a download response, which is private to the holder of the token and certified with all its headers.
+fn response(
+    status_code: u16,
+    content_type: &str,
+    disposition: String,
+    body: Vec<u8>,
+) -> HttpResponse {
+    HttpResponse {
+        status_code,
+        headers: vec![
+            ("Content-Type".to_string(), content_type.to_string()),
+            ("Content-Disposition".to_string(), disposition),
+            ("Cache-Control".to_string(), "private, no-store".to_string()),
+            (
+                CERTIFICATE_EXPRESSION_HEADER_NAME.to_string(),
+                download_expression().to_string(),
+            ),
+        ],
+        body,
+        upgrade: None,
+    }
+}
+
+/// Returns the certificate expression of downloads, which certifies the token of the request and
+/// every header of the response.
+fn download_expression() -> DefaultFullCelExpression<'static> {
+    DefaultCelBuilder::full_certification()
+        .with_request_query_parameters(vec![TOKEN_PARAMETER])
+        .with_response_certification(DefaultResponseCertification::response_header_exclusions(
+            vec![],
+        ))
+        .build()
+}
+
+/// Returns the certificate expression of the `Not found` response, which certifies every header of the response.
+fn fallback_expression() -> DefaultResponseOnlyCelExpression<'static> {
+    DefaultCelBuilder::response_only_certification()
+        .with_response_certification(DefaultResponseCertification::response_header_exclusions(
+            vec![],
+        ))
+        .build()
+}
+
+/// Encodes a file name for the `filename*` parameter of the `Content-Disposition` header.
+fn percent_encode(name: &str) -> String {
+    name.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Converts a response to the type the certification library hashes.
+fn to_certified(response: &HttpResponse) -> ic_http_certification::HttpResponse {
+    ic_http_certification::HttpResponse {
+        status_code: response.status_code,
+        headers: response.headers.clone(),
+        body: response.body.clone(),
+        upgrade: response.upgrade,
+    }
+}
+
+/// Keeps the HTTP certification tree whose root hash is the certified data of the canister.
+///
+/// Each download is certified together with the token it is requested with, so a response
+/// only verifies for the URL of its owner.
+pub(crate) struct Certifier {
+    /// The certification tree.
+    tree: HttpCertificationTree,
+    /// The certifications in the tree, by principal and download.
+    certified: BTreeMap<(Principal, Download), HttpCertification>,
+    /// The certification of the response served for any other path.
+    fallback: HttpCertification,
+}
+
+impl Certifier {
+    /// Creates a certification tree holding only the certified `Not found` response.
+    pub(crate) fn new() -> Self {
+        let fallback = HttpCertification::response_only(
+            &fallback_expression(),
+            &to_certified(&not_found()),
+            None,
+        )
+        .expect("the fallback response carries its certificate expression");
+        let mut tree = HttpCertificationTree::default();
+        tree.insert(&HttpCertificationTreeEntry::new(
+            HttpCertificationPath::wildcard(""),
+            fallback,
+        ));
+        Self {
+            tree,
+            certified: BTreeMap::new(),
+            fallback,
+        }
+    }
+
+    /// Returns the root hash of the tree, to be set as the certified data of the canister.
+    pub(crate) fn root_hash(&self) -> [u8; 32] {
+        self.tree.root_hash()
+    }
+
+    /// Certifies the response of a download requested with a token, replacing its previous certification.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the download.
+    /// * `download` - The download.
+    /// * `token` - The download token of the principal.
+    /// * `response` - The response served for the download.
+    pub(crate) fn certify(
+        &mut self,
+        principal: Principal,
+        download: Download,
+        token: &str,
+        response: &HttpResponse,
+    ) {
+        self.uncertify(principal, download);
+        let path = download.path();
+        let request = ic_http_certification::HttpRequest {
+            method: "GET".to_string(),
+            url: format!("{path}?{TOKEN_PARAMETER}={token}"),
+            headers: Vec::new(),
+            body: Vec::new(),
+        };
+        let Ok(certification) = HttpCertification::full(
+            &download_expression(),
+            &request,
+            &to_certified(response),
+            None,
+        ) else {
+            return;
+        };
+        self.tree.insert(&HttpCertificationTreeEntry::new(
+            HttpCertificationPath::exact(path),
+            certification,
+        ));
+        self.certified.insert((principal, download), certification);
+    }
+
+    /// Returns whether the response of a download is certified.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the download.
+    /// * `download` - The download.
+    pub(crate) fn is_certified(&self, principal: Principal, download: Download) -> bool {
+        self.certified.contains_key(&(principal, download))
+    }
+
+    /// Removes the certification of a download, after which it is served as `Not found`.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the download.
+    /// * `download` - The download.
+    pub(crate) fn uncertify(&mut self, principal: Principal, download: Download) {
+        if let Some(certification) = self.certified.remove(&(principal, download)) {
+            self.tree.delete(&HttpCertificationTreeEntry::new(
+                HttpCertificationPath::exact(download.path()),
+                certification,
+            ));
+        }
+    }
+
+    /// Removes the certifications of every download of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn uncertify_all(&mut self, principal: Principal) {
+        let downloads: Vec<_> = self
+            .certified
+            .keys()
+            .filter(|(owner, _)| *owner == principal)
+            .map(|(_, download)| *download)
+            .collect();
+        for download in downloads {
+            self.uncertify(principal, download);
+        }
+    }
+
+    /// Attaches the `IC-Certificate` header to the response of a request, so the gateway can verify it.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The request URL.
+    /// * `served` - The principal and download that were resolved from the request, with their response,
+    ///   or None if the request does not resolve to a download.
+    /// * `certificate` - The data certificate of the canister.
+    ///
+    /// # Returns
+    ///
+    /// The certified response, which is `Not found` unless the download is certified.
+    pub(crate) fn serve(
+        &self,
+        url: &str,
+        served: Option<(Principal, Download, HttpResponse)>,
+        certificate: &[u8],
+    ) -> HttpResponse {
+        let (path, certification, mut response) =
+            match served.and_then(|(principal, download, response)| {
+                let certification = *self.certified.get(&(principal, download))?;
+                Some((
+                    HttpCertificationPath::exact(download.path()),
+                    certification,
+                    response,
+                ))
+            }) {
+                Some(served) => served,
+                None => (
+                    HttpCertificationPath::wildcard(""),
+                    self.fallback,
+                    not_found(),
+                ),
+            };
+        let request_path = url.split('?').next().unwrap_or_default();
+        let entry = HttpCertificationTreeEntry::new(&path, certification);
+        let Ok(witness) = self.tree.witness(&entry, request_path) else {
+            return response;
+        };
+        let (Ok(tree), Ok(expr_path)) = (
+            serde_cbor::to_vec(&witness),
+            serde_cbor::to_vec(&path.to_expr_path()),
+        ) else {
+            return response;
+        };
+        response.headers.push((
+            CERTIFICATE_HEADER_NAME.to_string(),
+            format!(
+                "certificate=:{}:, tree=:{}:, expr_path=:{}:, version=2",
+                STANDARD.encode(certificate),
+                STANDARD.encode(tree),
+                STANDARD.encode(expr_path),
+            ),
+        ));
+        response
+    }
+}
+
+/// Wrapper around the DownloadTokenStore to provide additional functionality.
+pub(crate) struct DownloadTokenStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<DownloadTokenStore<M>>,
+}
+
+impl<M: Memory> DownloadTokenStoreWrapper<'_, M> {
+    /// Issues a download token for a principal, replacing its previous token.
+    /// The token names the principal, so it is looked up without scanning the store.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `random` - Random bytes making up the secret part of the token.
+    ///
+    /// # Returns
+    ///
+    /// The download token.
+    pub(crate) fn issue(&self, principal: Principal, random: &[u8]) -> String {
+        let secret: String = random.iter().map(|byte| format!("{byte:02x}")).collect();
+        self.store.borrow_mut().insert(principal, secret.clone());
+        format!("{principal}.{secret}")
+    }
+
+    /// Revokes the download token of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn revoke(&self, principal: Principal) {
+        self.store.borrow_mut().remove(&principal);
+    }
+
+    /// Finds the principal a download token was issued to.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The download token sent with the request.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the principal, or None if the token is unknown or revoked.
+    pub(crate) fn find(&self, token: &str) -> Option<Principal> {
+        let (principal, secret) = token.split_once('.')?;
+        let principal = Principal::from_text(principal).ok()?;
+        (self.store.borrow().get(&principal)? == secret).then_some(principal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::Priority;
+
+    #[test]
+    fn test_parse_download() {
+        assert_eq!(Download::parse("/export.json"), Some(Download::Export));
+        assert_eq!(
+            Download::parse("/attachments/7"),
+            Some(Download::Attachment(7))
+        );
+        assert_eq!(Download::parse("/attachments/007"), None);
+        assert_eq!(Download::parse("/attachments/"), None);
+        assert_eq!(Download::parse("/index.html"), None);
+    }
+
+    #[test]
+    fn test_issue_and_find_token() {
+        let store = RefCell::new(DownloadTokenStore::new(DefaultMemoryImpl::default()));
+        let wrapper = DownloadTokenStoreWrapper { store: &store };
+        let principal = Principal::from_slice(&[1, 2, 3]);
+
+        let token = wrapper.issue(principal, &[0xab, 0xcd]);
+        assert!(token.ends_with(".abcd"));
+        assert_eq!(wrapper.find(&token), Some(principal));
+        assert_eq!(wrapper.find(&format!("{principal}.abce")), None);
+        assert_eq!(wrapper.find("abcd"), None);
+
+        let rotated = wrapper.issue(principal, &[0x01]);
+        assert_eq!(wrapper.find(&token), None);
+        assert_eq!(wrapper.find(&rotated), Some(principal));
+        wrapper.revoke(principal);
+        assert_eq!(wrapper.find(&rotated), None);
+    }
+
+    #[test]
+    fn test_certify_and_serve() {
+        let mut certifier = Certifier::new();
+        let principal = Principal::from_slice(&[1, 2, 3]);
+        let empty = certifier.root_hash();
+        let response = export_response(&[Todo::new(1, "Task".to_string(), Priority::High)]);
+
+        assert!(!certifier.is_certified(principal, Download::Export));
+        certifier.certify(principal, Download::Export, "token", &response);
+        assert!(certifier.is_certified(principal, Download::Export));
+        assert_ne!(certifier.root_hash(), empty);
+        let served = certifier.serve(
+            "/export.json?token=token",
+            Some((principal, Download::Export, response.clone())),
+            b"certificate",
+        );
+        assert_eq!(served.status_code, 200);
+        assert_eq!(served.body, response.body);
+        assert!(served
+            .headers
+            .iter()
+            .any(|(name, value)| name == CERTIFICATE_HEADER_NAME
+                && value.starts_with("certificate=:Y2VydGlmaWNhdGU=:")
+                && value.ends_with("version=2")));
+
+        certifier.uncertify_all(principal);
+        assert_eq!(certifier.root_hash(), empty);
+        let served = certifier.serve(
+            "/export.json?token=token",
+            Some((principal, Download::Export, response)),
+            b"certificate",
+        );
+        assert_eq!(served.status_code, 404);
+        assert!(served
+            .headers
+            .iter()
+            .any(|(name, _)| name == CERTIFICATE_HEADER_NAME));
+    }
+}
@@ -0,0 +1,401 @@
+use std::{cell::RefCell, ops::Bound};
+
+use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::{Memory, StableBTreeMap, StableCell, Storable};
+
+use crate::{
+    budget::InstructionGuard,
+    memory::{
+        ACCESS_STORE, API_TOKEN_STORE, ARCHIVE_STORE, ATTACHMENT_STORE, AUDIT_STORE, BOARD_STORE,
+        CHECKLIST_STORE, CHUNK_STORE, COMPLETION_INDEX, DOWNLOAD_TOKEN_STORE, DUE_INDEX, EMAIL_CHANNEL_STORE,
+        EMAIL_LOG_STORE, EVENT_STORE, EXTERNAL_ID_STORE, FEATURE_FLAG_STORE, GEO_INDEX, GOAL_STORE,
+        GOVERNANCE_CONFIG, HABIT_STORE, HISTORY_STORE, IMPORT_STORE, INSTRUCTION_BUDGET,
+        INTEGRATION_STORE, JOB_STORE, LABEL_STORE, LAST_INTEGRATION_ID, LAST_TODO_ID, MAINTENANCE_MODE, MODIFIED_INDEX,
+        MY_DAY_STORE, NAMESPACE_STORE, NOTIFICATION_STORE, PEER_STORE, POMODORO_STORE,
+        PREFERENCE_STORE, PRIORITY_HISTORY_STORE, RANK_INDEX, RELATION_STORE, REVIEW_LOG_STORE,
+        SCHEDULE_STORE, SCORE_STORE, SHARED_ACCOUNT_INDEX, STAGED_TODO_STORE, STATS_STORE, STORED_SCHEMA_VERSION, TAG_STORE, TAG_TREND_STORE,
+        TELEGRAM_CHAT_INDEX, TELEGRAM_STORE, TODO_COUNT_STORE, TODO_STORE, USAGE_STORE, USER_STORE,
+    },
+    todo::TodoId,
+};
+
+/// Maximum number of entries in a segment.
+pub(crate) const MAX_SEGMENT_ENTRIES: u32 = 10_000;
+
+/// Number of bytes of keys and values after which a segment is closed, well below the limit of a reply.
+const MAX_SEGMENT_BYTES: usize = 1_500_000;
+
+/// Identifiers of the dumped stores, in the order they are dumped. A store is identified by the
+/// number of the memory it lives in, so the identifiers stay stable as stores are added.
+pub(crate) const STORES: std::ops::RangeInclusive<u8> = 2..=55;
+
+/// Represents the position of an entry in the dump: the store it belongs to and its raw key.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct SegmentKey {
+    /// The identifier of the store.
+    pub(crate) store: u8,
+    /// The key, encoded as the store encodes it; empty for stores holding a single value.
+    pub(crate) key: Vec<u8>,
+}
+
+/// Represents an entry of a store, encoded as the store encodes it.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct SegmentEntry {
+    /// The identifier of the store.
+    pub(crate) store: u8,
+    /// The key; empty for stores holding a single value.
+    pub(crate) key: Vec<u8>,
+    /// The value.
+    pub(crate) value: Vec<u8>,
+}
+
+/// Represents a slice of the dump of the canister state.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Segment {
+    /// The entries, in dump order.
+    pub(crate) entries: Vec<SegmentEntry>,
+    /// The position to continue after, or None if the dump is complete.
+    pub(crate) next: Option<SegmentKey>,
+}
+
+/// Collects entries into a segment until it is full or the instruction budget is spent.
+pub(crate) struct SegmentBuilder {
+    entries: Vec<SegmentEntry>,
+    limit: usize,
+    bytes: usize,
+    guard: InstructionGuard,
+}
+
+impl SegmentBuilder {
+    /// Creates an empty segment.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - The maximum number of entries.
+    /// * `guard` - The instruction budget of the call.
+    pub(crate) fn new(limit: u32, guard: InstructionGuard) -> Self {
+        Self {
+            entries: Vec::new(),
+            limit: limit.clamp(1, MAX_SEGMENT_ENTRIES) as usize,
+            bytes: 0,
+            guard,
+        }
+    }
+
+    /// Adds an entry unless the segment is full. The first entry is always added, however large.
+    ///
+    /// # Returns
+    ///
+    /// Whether the entry was added.
+    fn push(&mut self, entry: SegmentEntry) -> bool {
+        let bytes = entry.key.len() + entry.value.len();
+        let full = self.entries.len() >= self.limit
+            || (!self.entries.is_empty()
+                && (self.bytes + bytes > MAX_SEGMENT_BYTES || self.guard.exhausted()));
+        if full {
+            return false;
+        }
+        self.bytes += bytes;
+        self.entries.push(entry);
+        true
+    }
+
+    /// Closes the segment.
+    ///
+    /// # Arguments
+    ///
+    /// * `complete` - Whether every store was dumped up to its last entry.
+    pub(crate) fn finish(self, complete: bool) -> Segment {
+        let next = self
+            .entries
+            .last()
+            .filter(|_| !complete)
+            .map(|entry| SegmentKey {
+                store: entry.store,
+                key: entry.key.clone(),
+            });
+        Segment {
+            entries: self.entries,
+            next,
+        }
+    }
+}
+
+/// A stable store whose entries can be dumped and restored in their encoded form.
+pub(crate) trait Dump {
+    /// Adds the entries following a key to a segment.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The identifier of the store.
+    /// * `after` - The key to continue after, or None to start with the first entry.
+    /// * `segment` - The segment.
+    ///
+    /// # Returns
+    ///
+    /// Whether the last entry was added, or false if the segment filled up first.
+    fn export(&self, store: u8, after: Option<&[u8]>, segment: &mut SegmentBuilder) -> bool;
+
+    /// Stores an entry, replacing any entry with the same key. Malformed entries trap, undoing the whole import.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The encoded key.
+    /// * `value` - The encoded value.
+    fn import(&self, key: &[u8], value: &[u8]);
+}
+
+impl<K, V, M> Dump for RefCell<StableBTreeMap<K, V, M>>
+where
+    K: Storable + Ord + Clone,
+    V: Storable,
+    M: Memory,
+{
+    fn export(&self, store: u8, after: Option<&[u8]>, segment: &mut SegmentBuilder) -> bool {
+        let start = after.map_or(Bound::Unbounded, |key| {
+            Bound::Excluded(K::from_bytes(key.to_vec().into()))
+        });
+        self.borrow()
+            .range((start, Bound::Unbounded))
+            .all(|(key, value)| {
+                segment.push(SegmentEntry {
+                    store,
+                    key: key.to_bytes().into_owned(),
+                    value: value.to_bytes().into_owned(),
+                })
+            })
+    }
+
+    fn import(&self, key: &[u8], value: &[u8]) {
+        self.borrow_mut().insert(
+            K::from_bytes(key.to_vec().into()),
+            V::from_bytes(value.to_vec().into()),
+        );
+    }
+}
+
+impl<T, M> Dump for RefCell<StableCell<T, M>>
+where
+    T: Storable,
+    M: Memory,
+{
+    fn export(&self, store: u8, after: Option<&[u8]>, segment: &mut SegmentBuilder) -> bool {
+        after.is_some()
+            || segment.push(SegmentEntry {
+                store,
+                key: Vec::new(),
+                value: self.borrow().get().to_bytes().into_owned(),
+            })
+    }
+
+    fn import(&self, _key: &[u8], value: &[u8]) {
+        self.borrow_mut()
+            .set(T::from_bytes(value.to_vec().into()))
+            .unwrap();
+    }
+}
+
+/// Runs a function on a store of the canister.
+///
+/// # Arguments
+///
+/// * `store` - The identifier of the store.
+/// * `f` - The function.
+///
+/// # Returns
+///
+/// An Option containing the result of the function, or None if no store has the identifier.
+pub(crate) fn with_store<R>(store: u8, f: impl FnOnce(&dyn Dump) -> R) -> Option<R> {
+    let result = match store {
+        2 => LAST_TODO_ID.with(|store| f(store)),
+        3 => TODO_STORE.with(|store| f(store)),
+        4 => EXTERNAL_ID_STORE.with(|store| f(store)),
+        5 => USER_STORE.with(|store| f(store)),
+        6 => STATS_STORE.with(|store| f(store)),
+        7 => AUDIT_STORE.with(|store| f(store)),
+        8 => HISTORY_STORE.with(|store| f(store)),
+        9 => POMODORO_STORE.with(|store| f(store)),
+        10 => SCORE_STORE.with(|store| f(store)),
+        11 => PREFERENCE_STORE.with(|store| f(store)),
+        12 => ARCHIVE_STORE.with(|store| f(store)),
+        13 => BOARD_STORE.with(|store| f(store)),
+        14 => MY_DAY_STORE.with(|store| f(store)),
+        15 => NOTIFICATION_STORE.with(|store| f(store)),
+        16 => EMAIL_CHANNEL_STORE.with(|store| f(store)),
+        17 => EMAIL_LOG_STORE.with(|store| f(store)),
+        18 => TELEGRAM_STORE.with(|store| f(store)),
+        19 => ATTACHMENT_STORE.with(|store| f(store)),
+        20 => CHUNK_STORE.with(|store| f(store)),
+        21 => DOWNLOAD_TOKEN_STORE.with(|store| f(store)),
+        22 => TAG_STORE.with(|store| f(store)),
+        23 => MODIFIED_INDEX.with(|store| f(store)),
+        24 => RANK_INDEX.with(|store| f(store)),
+        25 => COMPLETION_INDEX.with(|store| f(store)),
+        26 => SCHEDULE_STORE.with(|store| f(store)),
+        27 => LABEL_STORE.with(|store| f(store)),
+        28 => API_TOKEN_STORE.with(|store| f(store)),
+        29 => INTEGRATION_STORE.with(|store| f(store)),
+        30 => PEER_STORE.with(|store| f(store)),
+        31 => GOVERNANCE_CONFIG.with(|store| f(store)),
+        32 => MAINTENANCE_MODE.with(|store| f(store)),
+        33 => USAGE_STORE.with(|store| f(store)),
+        34 => FEATURE_FLAG_STORE.with(|store| f(store)),
+        35 => REVIEW_LOG_STORE.with(|store| f(store)),
+        36 => GOAL_STORE.with(|store| f(store)),
+        37 => HABIT_STORE.with(|store| f(store)),
+        38 => GEO_INDEX.with(|store| f(store)),
+        39 => RELATION_STORE.with(|store| f(store)),
+        40 => CHECKLIST_STORE.with(|store| f(store)),
+        41 => ACCESS_STORE.with(|store| f(store)),
+        42 => EVENT_STORE.with(|store| f(store)),
+        43 => INSTRUCTION_BUDGET.with(|store| f(store)),
+        44 => JOB_STORE.with(|store| f(store)),
+        45 => NAMESPACE_STORE.with(|store| f(store)),
+        46 => PRIORITY_HISTORY_STORE.with(|store| f(store)),
+        47 => IMPORT_STORE.with(|store| f(store)),
+        48 => STAGED_TODO_STORE.with(|store| f(store)),
+        49 => TAG_TREND_STORE.with(|store| f(store)),
+        50 => DUE_INDEX.with(|store| f(store)),
+        51 => SHARED_ACCOUNT_INDEX.with(|store| f(store)),
+        52 => TELEGRAM_CHAT_INDEX.with(|store| f(store)),
+        53 => STORED_SCHEMA_VERSION.with(|store| f(store)),
+        54 => LAST_INTEGRATION_ID.with(|store| f(store)),
+        55 => TODO_COUNT_STORE.with(|store| f(store)),
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Returns the highest Todo identifier taken or handed out by dumped entries: the counter of identifiers,
+/// and the identifiers of the Todo items and archived Todo items.
+///
+/// # Arguments
+///
+/// * `entries` - The entries, as dumped.
+///
+/// # Returns
+///
+/// The highest identifier, or 0 if the entries hold none.
+pub(crate) fn highest_todo_id(entries: &[SegmentEntry]) -> TodoId {
+    entries
+        .iter()
+        .filter_map(|entry| match entry.store {
+            2 => Some(TodoId::from_bytes(entry.value.as_slice().into())),
+            3 | 12 => Some(<(Principal, TodoId)>::from_bytes(entry.key.as_slice().into()).1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or_default()
+}
+
+/// Dumps the entries of the canister state following a position.
+///
+/// # Arguments
+///
+/// * `after` - The position to continue after, or None to start from the beginning.
+/// * `limit` - The maximum number of entries.
+/// * `guard` - The instruction budget of the call.
+///
+/// # Returns
+///
+/// The segment.
+pub(crate) fn export_segment(
+    after: Option<SegmentKey>,
+    limit: u32,
+    guard: InstructionGuard,
+) -> Segment {
+    let mut segment = SegmentBuilder::new(limit, guard);
+    let first = after.as_ref().map_or(*STORES.start(), |after| after.store);
+    let mut after = after.map(|after| after.key);
+    for store in first.max(*STORES.start())..=*STORES.end() {
+        let complete = with_store(store, |dump| {
+            dump.export(store, after.as_deref(), &mut segment)
+        })
+        .unwrap_or(true);
+        if !complete {
+            return segment.finish(false);
+        }
+        after = None;
+    }
+    segment.finish(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    fn unlimited() -> InstructionGuard {
+        InstructionGuard::with_counter(u64::MAX, || 0)
+    }
+
+    #[test]
+    fn test_export_and_import_in_segments() {
+        let source = RefCell::new(StableBTreeMap::<u64, u64, _>::new(
+            DefaultMemoryImpl::default(),
+        ));
+        let target = RefCell::new(StableBTreeMap::<u64, u64, _>::new(
+            DefaultMemoryImpl::default(),
+        ));
+        for key in 1..=5 {
+            source.borrow_mut().insert(key, key * 10);
+        }
+
+        let mut after: Option<Vec<u8>> = None;
+        let mut segments = 0;
+        loop {
+            let mut builder = SegmentBuilder::new(2, unlimited());
+            let complete = source.export(3, after.as_deref(), &mut builder);
+            let segment = builder.finish(complete);
+            for entry in &segment.entries {
+                target.import(&entry.key, &entry.value);
+            }
+            segments += 1;
+            match segment.next {
+                Some(next) => after = Some(next.key),
+                None => break,
+            }
+        }
+        assert_eq!(segments, 3);
+
+        // A spent budget closes every segment after its first entry.
+        let mut builder = SegmentBuilder::new(10, InstructionGuard::with_counter(0, || 1));
+        assert!(!source.export(3, None, &mut builder));
+        assert_eq!(builder.finish(false).entries.len(), 1);
+        assert_eq!(
+            target.borrow().iter().collect::<Vec<_>>(),
+            source.borrow().iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_highest_todo_id() {
+        let entry = |store, key: Vec<u8>, value: Vec<u8>| SegmentEntry { store, key, value };
+        let todo_key = |id: TodoId| (Principal::anonymous(), id).to_bytes().into_owned();
+        let entries = vec![
+            entry(2, Vec::new(), 7u64.to_bytes().into_owned()),
+            entry(3, todo_key(5), Vec::new()),
+            entry(12, todo_key(9), Vec::new()),
+            entry(8, todo_key(50), Vec::new()),
+        ];
+        assert_eq!(highest_todo_id(&entries), 9);
+        assert_eq!(highest_todo_id(&entries[..2]), 7);
+        assert_eq!(highest_todo_id(&[]), 0);
+    }
+
+    #[test]
+    fn test_cell_is_dumped_once() {
+        let cell = RefCell::new(StableCell::init(DefaultMemoryImpl::default(), 7u64).unwrap());
+        let mut builder = SegmentBuilder::new(10, unlimited());
+        assert!(cell.export(2, None, &mut builder));
+        assert!(cell.export(2, Some(&[]), &mut builder));
+        let segment = builder.finish(true);
+        assert_eq!(segment.entries.len(), 1);
+        assert_eq!(segment.next, None);
+
+        let restored = RefCell::new(StableCell::init(DefaultMemoryImpl::default(), 0u64).unwrap());
+        restored.import(&[], &segment.entries[0].value);
+        assert_eq!(*restored.borrow().get(), 7);
+    }
+}
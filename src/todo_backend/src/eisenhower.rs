@@ -0,0 +1,111 @@
+use candid::{CandidType, Deserialize};
+
+use crate::{
+    stats::{local_day_of, Day},
+    todo::{Priority, Todo},
+};
+
+/// Number of local days ahead within which a due date makes a Todo item urgent.
+const URGENT_WITHIN_DAYS: Day = 2;
+
+/// Represents the open Todo items of a principal sorted into the quadrants of the Eisenhower matrix.
+///
+/// Importance is derived from the priority and urgency from the due date, so that no extra fields need to be kept up to date.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct EisenhowerMatrix {
+    /// Urgent and important Todo items.
+    pub(crate) do_first: Vec<Todo>,
+    /// Important Todo items that are not urgent.
+    pub(crate) schedule: Vec<Todo>,
+    /// Urgent Todo items that are not important.
+    pub(crate) delegate: Vec<Todo>,
+    /// Todo items that are neither urgent nor important.
+    pub(crate) eliminate: Vec<Todo>,
+}
+
+/// Returns whether a Todo item is important, which is the case for high priority items.
+fn is_important(todo: &Todo) -> bool {
+    todo.priority == Priority::High
+}
+
+/// Sorts the open Todo items of a principal into the quadrants of the Eisenhower matrix.
+///
+/// A Todo item is urgent if it is overdue or due within the next two local days.
+/// Each quadrant lists the soonest due date first; Todo items without a due date come last.
+///
+/// # Arguments
+///
+/// * `todos` - All Todo items of the principal.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+/// * `utc_offset` - The offset of the principal's time zone from UTC, in nanoseconds.
+///
+/// # Returns
+///
+/// The Eisenhower matrix.
+pub(crate) fn build(todos: Vec<Todo>, now: u64, utc_offset: i64) -> EisenhowerMatrix {
+    let today = local_day_of(now, utc_offset);
+    let mut open: Vec<Todo> = todos
+        .into_iter()
+        .filter(|todo| !todo.is_completed)
+        .collect();
+    open.sort_by_key(|todo| (todo.due_date.unwrap_or(u64::MAX), todo.id));
+    let mut matrix = EisenhowerMatrix {
+        do_first: Vec::new(),
+        schedule: Vec::new(),
+        delegate: Vec::new(),
+        eliminate: Vec::new(),
+    };
+    for todo in open {
+        let is_urgent = todo.due_date.is_some_and(|due_date| {
+            local_day_of(due_date, utc_offset) <= today + URGENT_WITHIN_DAYS
+        });
+        let quadrant = match (is_urgent, is_important(&todo)) {
+            (true, true) => &mut matrix.do_first,
+            (false, true) => &mut matrix.schedule,
+            (true, false) => &mut matrix.delegate,
+            (false, false) => &mut matrix.eliminate,
+        };
+        quadrant.push(todo);
+    }
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::stats::NANOS_PER_DAY;
+
+    fn todo(id: u64, priority: Priority, due_date: Option<u64>) -> Todo {
+        Todo {
+            due_date,
+            ..Todo::new(id, format!("Task {id}"), priority)
+        }
+    }
+
+    fn ids(todos: &[Todo]) -> Vec<u64> {
+        todos.iter().map(|todo| todo.id).collect()
+    }
+
+    #[test]
+    fn test_build_matrix() {
+        let now = 10 * NANOS_PER_DAY;
+        let mut completed = todo(1, Priority::High, Some(now));
+        completed.set_completed(true, now);
+        let todos = vec![
+            completed,
+            todo(2, Priority::High, Some(12 * NANOS_PER_DAY)),
+            todo(3, Priority::High, Some(5 * NANOS_PER_DAY)),
+            todo(4, Priority::High, Some(13 * NANOS_PER_DAY)),
+            todo(5, Priority::Medium, Some(11 * NANOS_PER_DAY)),
+            todo(6, Priority::High, None),
+            todo(7, Priority::Low, None),
+        ];
+
+        let matrix = build(todos, now, 0);
+        assert_eq!(ids(&matrix.do_first), vec![3, 2]);
+        assert_eq!(ids(&matrix.schedule), vec![4, 6]);
+        assert_eq!(ids(&matrix.delegate), vec![5]);
+        assert_eq!(ids(&matrix.eliminate), vec![7]);
+    }
+}
@@ -0,0 +1,468 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk::api::management_canister::http_request::{
+    CanisterHttpRequestArgument, HttpHeader, HttpMethod, HttpResponse,
+};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{
+    messages::{local_time, Language, Message},
+    paginator::Paginator,
+    renumbering::{renumbered, rewrite_entries, Renumbering},
+    todo::{Todo, TodoId},
+};
+
+/// Maximum number of deliveries kept in the log of each principal.
+const MAX_DELIVERIES: usize = 100;
+
+/// Maximum size of the response accepted from an email endpoint, in bytes.
+const MAX_RESPONSE_BYTES: u64 = 2_048;
+
+/// Type alias for the identifier of an email delivery, unique per principal.
+pub(crate) type DeliveryId = u64;
+
+/// Type alias for the EmailChannelStore, which is a StableBTreeMap keyed by Principal with a value of EmailChannel.
+pub(crate) type EmailChannelStore<M> = StableBTreeMap<Principal, EmailChannel, M>;
+
+/// Type alias for the EmailLogStore, which is a StableBTreeMap with a tuple key of (Principal, DeliveryId) and value of EmailDelivery.
+pub(crate) type EmailLogStore<M> = StableBTreeMap<(Principal, DeliveryId), EmailDelivery, M>;
+
+/// Represents the Mailgun-compatible service used to send a principal's reminder emails.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct EmailChannel {
+    /// The HTTPS URL messages are posted to, e.g. `https://api.mailgun.net/v3/<domain>/messages`.
+    pub(crate) endpoint: String,
+    /// The API key of the service, sent as the password of the `api` user.
+    pub(crate) api_key: String,
+    /// The sender address.
+    pub(crate) from: String,
+    /// The recipient address.
+    pub(crate) to: String,
+}
+
+impl Storable for EmailChannel {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Represents the state of an email delivery.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) enum DeliveryStatus {
+    /// The request to the email endpoint is in flight.
+    Pending,
+    /// The email endpoint accepted the message.
+    Sent,
+    /// The message could not be delivered, with the reason.
+    Failed(String),
+}
+
+/// Represents a reminder email sent for a Todo item.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct EmailDelivery {
+    /// The unique identifier of the delivery.
+    pub(crate) id: DeliveryId,
+    /// The unique identifier of the Todo item the reminder is about.
+    pub(crate) todo_id: TodoId,
+    /// The due date the reminder is about, in nanoseconds since the Unix epoch.
+    pub(crate) due_date: u64,
+    /// Time the delivery was attempted, in nanoseconds since the Unix epoch.
+    pub(crate) sent_at: u64,
+    /// The state of the delivery.
+    pub(crate) status: DeliveryStatus,
+}
+
+impl Storable for EmailDelivery {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Represents the content of a reminder email.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct EmailMessage {
+    /// The subject line.
+    pub(crate) subject: String,
+    /// The plain text body.
+    pub(crate) text: String,
+}
+
+/// Renders the reminder email for a Todo item that is due soon.
+///
+/// # Arguments
+///
+/// * `todo` - The Todo item with a due date.
+/// * `due_date` - The due date of the Todo item, in nanoseconds since the Unix epoch.
+/// * `utc_offset` - The offset of the recipient's time zone from UTC, in nanoseconds.
+/// * `language` - The language of the recipient.
+///
+/// # Returns
+///
+/// The email message.
+pub(crate) fn reminder_message(
+    todo: &Todo,
+    due_date: u64,
+    utc_offset: i64,
+    language: Language,
+) -> EmailMessage {
+    let mut text = Message::ReminderText {
+        description: &todo.description,
+        due: &local_time(due_date, utc_offset, language),
+        priority: todo.priority,
+    }
+    .render(language);
+    if let Some(notes) = &todo.notes {
+        text.push('\n');
+        text.push_str(notes);
+        text.push('\n');
+    }
+    EmailMessage {
+        subject: Message::ReminderSubject {
+            description: &todo.description,
+        }
+        .render(language),
+        text,
+    }
+}
+
+/// Builds the HTTPS outcall posting a message to an email endpoint.
+///
+/// Every replica of the subnet sends the request, so it carries an idempotency key
+/// that lets the endpoint drop the duplicates. The caller attaches the transform,
+/// which needs the canister id.
+///
+/// # Arguments
+///
+/// * `channel` - The email channel of the recipient.
+/// * `message` - The email message.
+/// * `idempotency_key` - A key unique to the delivery.
+///
+/// # Returns
+///
+/// The HTTP request argument for the management canister.
+pub(crate) fn request_for(
+    channel: &EmailChannel,
+    message: &EmailMessage,
+    idempotency_key: String,
+) -> CanisterHttpRequestArgument {
+    let credentials = STANDARD.encode(format!("api:{}", channel.api_key));
+    let body = form_encode(&[
+        ("from", &channel.from),
+        ("to", &channel.to),
+        ("subject", &message.subject),
+        ("text", &message.text),
+    ]);
+    CanisterHttpRequestArgument {
+        url: channel.endpoint.clone(),
+        max_response_bytes: Some(MAX_RESPONSE_BYTES),
+        method: HttpMethod::POST,
+        headers: vec![
+            HttpHeader {
+                name: "Authorization".to_string(),
+                value: format!("Basic {credentials}"),
+            },
+            HttpHeader {
+                name: "Content-Type".to_string(),
+                value: "application/x-www-form-urlencoded".to_string(),
+            },
+            HttpHeader {
+                name: "Idempotency-Key".to_string(),
+                value: idempotency_key,
+            },
+        ],
+        body: Some(body.into_bytes()),
+        transform: None,
+    }
+}
+
+/// Returns the delivery status matching a response of an email endpoint.
+///
+/// # Arguments
+///
+/// * `response` - The response, after transformation.
+pub(crate) fn delivery_status(response: &HttpResponse) -> DeliveryStatus {
+    if response.status >= 200u16 && response.status < 300u16 {
+        DeliveryStatus::Sent
+    } else {
+        DeliveryStatus::Failed(format!(
+            "Endpoint responded with status {}",
+            response.status
+        ))
+    }
+}
+
+/// Encodes key-value pairs as an `application/x-www-form-urlencoded` body.
+///
+/// # Arguments
+///
+/// * `pairs` - The key-value pairs.
+fn form_encode(pairs: &[(&str, &String)]) -> String {
+    let encode = |text: &str| {
+        text.bytes()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'*' => {
+                    (byte as char).to_string()
+                }
+                b' ' => "+".to_string(),
+                _ => format!("%{byte:02X}"),
+            })
+            .collect::<String>()
+    };
+    pairs
+        .iter()
+        .map(|(key, value)| format!("{}={}", encode(key), encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Wrapper around the EmailChannelStore to provide additional functionality.
+pub(crate) struct EmailChannelStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<EmailChannelStore<M>>,
+}
+
+impl<M: Memory> EmailChannelStoreWrapper<'_, M> {
+    /// Sets or removes the email channel of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `channel` - The validated email channel, or None to stop sending emails.
+    pub(crate) fn set(&self, principal: Principal, channel: Option<EmailChannel>) {
+        let mut store = self.store.borrow_mut();
+        match channel {
+            Some(channel) => store.insert(principal, channel),
+            None => store.remove(&principal),
+        };
+    }
+
+    /// Lists every principal with an email channel.
+    ///
+    /// # Returns
+    ///
+    /// A vector of principals and their email channels.
+    pub(crate) fn all(&self) -> Vec<(Principal, EmailChannel)> {
+        self.store.borrow().iter().collect()
+    }
+}
+
+/// Wrapper around the EmailLogStore to provide additional functionality.
+pub(crate) struct EmailLogStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<EmailLogStore<M>>,
+}
+
+impl<M: Memory> EmailLogStoreWrapper<'_, M> {
+    /// Records a pending delivery, dropping the oldest ones beyond the limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `todo_id` - The unique identifier of the Todo item the reminder is about.
+    /// * `due_date` - The due date the reminder is about.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// The identifier of the new delivery.
+    pub(crate) fn start(
+        &self,
+        principal: Principal,
+        todo_id: TodoId,
+        due_date: u64,
+        now: u64,
+    ) -> DeliveryId {
+        let id = self
+            .store
+            .borrow()
+            .iter_upper_bound(&(principal, DeliveryId::MAX))
+            .next()
+            .filter(|((owner, _), _)| *owner == principal)
+            .map_or(1, |((_, id), _)| id + 1);
+        let delivery = EmailDelivery {
+            id,
+            todo_id,
+            due_date,
+            sent_at: now,
+            status: DeliveryStatus::Pending,
+        };
+        let mut store = self.store.borrow_mut();
+        store.insert((principal, id), delivery);
+        let expired = id.saturating_sub(MAX_DELIVERIES as DeliveryId);
+        let expired: Vec<_> = store
+            .range((principal, 0)..=(principal, expired))
+            .map(|(key, _)| key)
+            .collect();
+        for key in expired {
+            store.remove(&key);
+        }
+        id
+    }
+
+    /// Records the outcome of a delivery.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier of the delivery.
+    /// * `status` - The outcome of the delivery.
+    pub(crate) fn finish(&self, principal: Principal, id: DeliveryId, status: DeliveryStatus) {
+        let mut store = self.store.borrow_mut();
+        if let Some(delivery) = store.get(&(principal, id)) {
+            store.insert((principal, id), EmailDelivery { status, ..delivery });
+        }
+    }
+
+    /// Returns whether a reminder about a due date of a Todo item was already attempted.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `todo_id` - The unique identifier of the Todo item.
+    /// * `due_date` - The due date.
+    pub(crate) fn contains(&self, principal: Principal, todo_id: TodoId, due_date: u64) -> bool {
+        self.store
+            .borrow()
+            .range((principal, 0)..=(principal, DeliveryId::MAX))
+            .any(|(_, delivery)| delivery.todo_id == todo_id && delivery.due_date == due_date)
+    }
+
+    /// Lists the deliveries of a principal, newest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `paginator` - The paginator for controlling the list output.
+    ///
+    /// # Returns
+    ///
+    /// A vector of deliveries.
+    pub(crate) fn list(&self, principal: Principal, paginator: Paginator) -> Vec<EmailDelivery> {
+        let mut deliveries: Vec<EmailDelivery> = self
+            .store
+            .borrow()
+            .range((principal, 0)..=(principal, DeliveryId::MAX))
+            .map(|(_, delivery)| delivery)
+            .collect();
+        deliveries.reverse();
+        deliveries
+            .into_iter()
+            .skip(paginator.skip())
+            .take(paginator.limit())
+            .collect()
+    }
+
+    /// Points the delivery log of a principal to the new identifiers of their Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        rewrite_entries(
+            self.store,
+            (principal, DeliveryId::MIN)..=(principal, DeliveryId::MAX),
+            |key, mut delivery| {
+                delivery.todo_id = renumbered(ids, delivery.todo_id);
+                (key, delivery)
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use candid::Nat;
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::{
+        stats::{days_from_civil, NANOS_PER_DAY, NANOS_PER_MINUTE},
+        todo::Priority,
+    };
+
+    #[test]
+    fn test_reminder_message() {
+        let due_date = days_from_civil(2024, 3, 1) as u64 * NANOS_PER_DAY + 90 * NANOS_PER_MINUTE;
+        let todo = Todo {
+            notes: Some("Bring the receipts".to_string()),
+            ..Todo::new(1, "File taxes".to_string(), Priority::High)
+        };
+
+        let message = reminder_message(
+            &todo,
+            due_date,
+            -120 * NANOS_PER_MINUTE as i64,
+            Language::English,
+        );
+        assert_eq!(message.subject, "Reminder: File taxes");
+        assert_eq!(
+            message.text,
+            "\"File taxes\" is due on 2024-02-29 at 23:30 (UTC-02:00).\nPriority: High\n\nBring the receipts\n"
+        );
+    }
+
+    #[test]
+    fn test_request_for() {
+        let channel = EmailChannel {
+            endpoint: "https://api.mailgun.net/v3/example.com/messages".to_string(),
+            api_key: "key".to_string(),
+            from: "todo@example.com".to_string(),
+            to: "me+todo@example.com".to_string(),
+        };
+        let message = EmailMessage {
+            subject: "Reminder: Buy milk".to_string(),
+            text: "Due".to_string(),
+        };
+
+        let request = request_for(&channel, &message, "1".to_string());
+        assert_eq!(request.headers[0].value, "Basic YXBpOmtleQ==");
+        assert_eq!(
+            String::from_utf8(request.body.unwrap()).unwrap(),
+            "from=todo%40example.com&to=me%2Btodo%40example.com&subject=Reminder%3A+Buy+milk&text=Due"
+        );
+    }
+
+    #[test]
+    fn test_delivery_status() {
+        let response = |status: u16| HttpResponse {
+            status: Nat::from(status),
+            headers: Vec::new(),
+            body: Vec::new(),
+        };
+        assert_eq!(delivery_status(&response(200)), DeliveryStatus::Sent);
+        assert!(matches!(
+            delivery_status(&response(401)),
+            DeliveryStatus::Failed(_)
+        ));
+    }
+
+    #[test]
+    fn test_delivery_log() {
+        let store = RefCell::new(EmailLogStore::new(DefaultMemoryImpl::default()));
+        let wrapper = EmailLogStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+
+        let id = wrapper.start(principal, 1, 100, 10);
+        assert!(wrapper.contains(principal, 1, 100));
+        assert!(!wrapper.contains(principal, 1, 200));
+        wrapper.finish(principal, id, DeliveryStatus::Sent);
+
+        let deliveries = wrapper.list(principal, Paginator::default());
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].status, DeliveryStatus::Sent);
+    }
+}
@@ -1,8 +1,12 @@
-use candid::CandidType;
+use candid::{CandidType, Deserialize};
 use thiserror::Error;
 
 /// Represents the different types of errors that can occur in the application.
-#[derive(CandidType, Debug, Error)]
+///
+/// Every variant has a stable numeric code (see [`Error::code`]) so that clients can
+/// branch on errors without parsing messages. Variants other than `NotFound` and
+/// `InvalidInput` carry an optional detail describing the specific failure.
+#[derive(CandidType, Deserialize, Debug, Error)]
 pub(crate) enum Error {
     /// Error indicating that the requested item was not found.
     #[error("Item not found")]
@@ -12,4 +16,93 @@ pub(crate) enum Error {
     /// The invalid input is included as a string.
     #[error("Invalid input: {0}")]
     InvalidInput(String),
+
+    /// Error indicating that the caller is not allowed to perform the operation.
+    #[error("Unauthorized{}", detail_suffix(.0))]
+    Unauthorized(Option<String>),
+
+    /// Error indicating that the caller has exhausted one of their quotas.
+    #[error("Quota exceeded{}", detail_suffix(.0))]
+    QuotaExceeded(Option<String>),
+
+    /// Error indicating that the item to be created already exists.
+    #[error("Item already exists{}", detail_suffix(.0))]
+    AlreadyExists(Option<String>),
+
+    /// Error indicating that the operation conflicts with the current state of the item.
+    #[error("Conflict{}", detail_suffix(.0))]
+    Conflict(Option<String>),
+
+    /// Error indicating that the caller has sent too many requests.
+    #[error("Rate limited{}", detail_suffix(.0))]
+    RateLimited(Option<String>),
+
+    /// Error indicating that an item the operation depends on is not in the required state.
+    #[error("Dependency not met{}", detail_suffix(.0))]
+    DependencyNotMet(Option<String>),
+
+    /// Error indicating that the canister rejects changes while it is under maintenance.
+    /// The detail is the message explaining the maintenance, if one was given.
+    #[error("Under maintenance{}", detail_suffix(.0))]
+    Maintenance(Option<String>),
+}
+
+impl Error {
+    /// Returns the stable numeric code of the error, modelled after HTTP status codes.
+    pub(crate) fn code(&self) -> u16 {
+        match self {
+            Error::InvalidInput(_) => 400,
+            Error::Unauthorized(_) => 403,
+            Error::NotFound => 404,
+            Error::AlreadyExists(_) => 409,
+            Error::Conflict(_) => 412,
+            Error::DependencyNotMet(_) => 424,
+            Error::RateLimited(_) => 429,
+            Error::Maintenance(_) => 503,
+            Error::QuotaExceeded(_) => 507,
+        }
+    }
+
+    /// Returns one instance of every error variant, without details.
+    pub(crate) fn variants() -> Vec<Error> {
+        vec![
+            Error::NotFound,
+            Error::InvalidInput(String::new()),
+            Error::Unauthorized(None),
+            Error::QuotaExceeded(None),
+            Error::AlreadyExists(None),
+            Error::Conflict(None),
+            Error::RateLimited(None),
+            Error::DependencyNotMet(None),
+            Error::Maintenance(None),
+        ]
+    }
+}
+
+/// Formats an optional error detail as a message suffix.
+fn detail_suffix(detail: &Option<String>) -> String {
+    detail.as_ref().map(|detail| format!(": {detail}")).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_are_unique() {
+        let errors = Error::variants();
+        let mut codes: Vec<u16> = errors.iter().map(Error::code).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+    }
+
+    #[test]
+    fn test_display_includes_detail() {
+        assert_eq!(Error::Unauthorized(None).to_string(), "Unauthorized");
+        assert_eq!(
+            Error::QuotaExceeded(Some("100 items".to_string())).to_string(),
+            "Quota exceeded: 100 items"
+        );
+    }
 }
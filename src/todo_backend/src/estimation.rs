@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+
+use candid::{CandidType, Deserialize};
+
+use crate::{
+    stats::NANOS_PER_MINUTE,
+    todo::{Todo, TodoId},
+};
+
+/// Represents how the estimates of completed Todo items compare to the time they took.
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct EstimationGroup {
+    /// The tag or project the Todo items share; empty for all Todo items.
+    pub(crate) name: String,
+    /// Number of Todo items.
+    pub(crate) items: u32,
+    /// Number of the Todo items whose time was tracked in focus sessions rather than taken from when they
+    /// were created and completed.
+    pub(crate) tracked_items: u32,
+    /// Total estimated time, in minutes.
+    pub(crate) estimated_minutes: u64,
+    /// Total time taken, in minutes.
+    pub(crate) actual_minutes: u64,
+    /// Time taken as a share of the estimated time, in percent: above 100 means the Todo items took
+    /// longer than estimated.
+    pub(crate) actual_percent: u64,
+}
+
+impl EstimationGroup {
+    /// Adds a Todo item to the group.
+    fn add(&mut self, estimated: u64, actual: u64, tracked: bool) {
+        self.items += 1;
+        self.tracked_items += u32::from(tracked);
+        self.estimated_minutes += estimated;
+        self.actual_minutes += actual;
+        self.actual_percent = self.actual_minutes * 100 / self.estimated_minutes.max(1);
+    }
+}
+
+/// Represents the accuracy of a principal's estimates, overall and per tag and project.
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct EstimationReport {
+    /// Every estimated Todo item.
+    pub(crate) total: EstimationGroup,
+    /// The estimated Todo items per tag, by tag.
+    pub(crate) by_tag: Vec<EstimationGroup>,
+    /// The estimated Todo items per project, by project.
+    pub(crate) by_project: Vec<EstimationGroup>,
+}
+
+/// Compares the estimates of completed Todo items to the time they took: the focus time tracked on
+/// them, or else the time from their creation to their completion. Todo items without an estimate,
+/// not completed, or of unknown creation time and without tracked time are left out.
+///
+/// # Arguments
+///
+/// * `todos` - The Todo items.
+/// * `tracked` - The minutes of focus time tracked on each Todo item.
+///
+/// # Returns
+///
+/// The estimation report.
+pub(crate) fn report(
+    todos: impl IntoIterator<Item = Todo>,
+    tracked: &BTreeMap<TodoId, u64>,
+) -> EstimationReport {
+    let mut total = EstimationGroup::default();
+    let mut by_tag: BTreeMap<String, EstimationGroup> = BTreeMap::new();
+    let mut by_project: BTreeMap<String, EstimationGroup> = BTreeMap::new();
+    for todo in todos {
+        let (Some(estimated), Some(completed_at)) = (todo.estimated_minutes, todo.completed_at)
+        else {
+            continue;
+        };
+        let (actual, tracked) = match tracked.get(&todo.id) {
+            Some(&minutes) if minutes > 0 => (minutes, true),
+            _ if todo.created_at > 0 && completed_at >= todo.created_at => {
+                ((completed_at - todo.created_at) / NANOS_PER_MINUTE, false)
+            }
+            _ => continue,
+        };
+        let estimated = u64::from(estimated);
+        total.add(estimated, actual, tracked);
+        for tag in todo.tags {
+            group(&mut by_tag, tag).add(estimated, actual, tracked);
+        }
+        if let Some(project) = todo.project {
+            group(&mut by_project, project).add(estimated, actual, tracked);
+        }
+    }
+    EstimationReport {
+        total,
+        by_tag: by_tag.into_values().collect(),
+        by_project: by_project.into_values().collect(),
+    }
+}
+
+/// Returns the group of a tag or project, creating it if missing.
+fn group(groups: &mut BTreeMap<String, EstimationGroup>, name: String) -> &mut EstimationGroup {
+    groups
+        .entry(name.clone())
+        .or_insert_with(|| EstimationGroup {
+            name,
+            ..EstimationGroup::default()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::todo::Priority;
+
+    #[test]
+    fn test_report_compares_estimates() {
+        let todo = |id, estimate, minutes: u64, tag: &str| Todo {
+            estimated_minutes: estimate,
+            created_at: 1,
+            completed_at: Some(1 + minutes * NANOS_PER_MINUTE),
+            tags: vec![tag.to_string()],
+            project: Some("launch".to_string()),
+            ..Todo::new(id, "Ship".to_string(), Priority::Medium)
+        };
+        let open = Todo {
+            completed_at: None,
+            ..todo(4, Some(10), 0, "web")
+        };
+        let todos = vec![
+            todo(1, Some(30), 60, "web"),
+            todo(2, Some(60), 999, "api"),
+            todo(3, None, 10, "web"),
+            open,
+        ];
+        let tracked = BTreeMap::from([(2, 30)]);
+
+        let report = report(todos, &tracked);
+        assert_eq!(
+            report.total,
+            EstimationGroup {
+                name: String::new(),
+                items: 2,
+                tracked_items: 1,
+                estimated_minutes: 90,
+                actual_minutes: 90,
+                actual_percent: 100
+            }
+        );
+        assert_eq!(
+            report
+                .by_tag
+                .iter()
+                .map(|group| (group.name.as_str(), group.actual_percent))
+                .collect::<Vec<_>>(),
+            vec![("api", 50), ("web", 200)]
+        );
+        assert_eq!(report.by_project.len(), 1);
+        assert_eq!(report.by_project[0].items, 2);
+    }
+}
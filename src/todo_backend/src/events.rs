@@ -0,0 +1,364 @@
+use std::{borrow::Cow, cell::RefCell, collections::BTreeMap};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{
+    renumbering::{renumbered, Renumbering},
+    store::TodoChange,
+    todo::{LenientTodo, Todo, TodoId},
+};
+
+/// Number of events a principal's log may gain after its snapshot before it is compacted into a new snapshot of the Todo items.
+pub(crate) const MAX_EVENTS: u64 = 10_000;
+
+/// Maximum number of events returned in one call.
+pub(crate) const MAX_EVENTS_PER_PAGE: u32 = 500;
+
+/// Type alias for the position of an event in the log of a principal, increasing with every event.
+pub(crate) type Sequence = u64;
+
+/// Type alias for the EventStore, which is a StableBTreeMap with a tuple key of (Principal, Sequence) and value of TodoEvent.
+/// The Todo store is the projection of these logs: replaying the log of a principal yields their Todo items.
+pub(crate) type EventStore<M> = StableBTreeMap<(Principal, Sequence), TodoEvent, M>;
+
+/// Represents what happened to the Todo items of a principal.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) enum TodoEventKind {
+    /// The projection starts over from no Todo items; the given number of events after it are a snapshot of the Todo items.
+    Reset(u64),
+    /// A Todo item was created, changed or put back, and now reads as given.
+    Put(Box<Todo>),
+    /// A Todo item was deleted or archived.
+    Removed(TodoId),
+    /// The Todo items were renumbered, from old identifiers to new ones.
+    Renumbered(Vec<(TodoId, TodoId)>),
+}
+
+/// Represents an event in the log of a principal.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct TodoEvent {
+    /// The position of the event in the log.
+    pub(crate) sequence: Sequence,
+    /// Time of the event, in nanoseconds since the Unix epoch.
+    pub(crate) at: u64,
+    /// What happened.
+    pub(crate) kind: TodoEventKind,
+}
+
+/// Lenient view of what happened, used when a stored Todo item no longer matches the current schema.
+#[derive(CandidType, Deserialize)]
+enum LenientEventKind {
+    Reset(u64),
+    Put(Box<LenientTodo>),
+    Removed(TodoId),
+    Renumbered(Vec<(TodoId, TodoId)>),
+}
+
+/// Lenient view of a stored event.
+#[derive(CandidType, Deserialize)]
+struct LenientEvent {
+    sequence: Sequence,
+    at: u64,
+    kind: LenientEventKind,
+}
+
+impl LenientEvent {
+    /// Converts the lenient view into an event, filling in defaults for missing fields.
+    ///
+    /// # Returns
+    ///
+    /// The event, or None if a Todo item lacks its identifier or description.
+    fn into_event(self) -> Option<TodoEvent> {
+        let kind = match self.kind {
+            LenientEventKind::Reset(count) => TodoEventKind::Reset(count),
+            LenientEventKind::Put(todo) => TodoEventKind::Put(Box::new(todo.into_todo()?)),
+            LenientEventKind::Removed(id) => TodoEventKind::Removed(id),
+            LenientEventKind::Renumbered(pairs) => TodoEventKind::Renumbered(pairs),
+        };
+        Some(TodoEvent {
+            sequence: self.sequence,
+            at: self.at,
+            kind,
+        })
+    }
+}
+
+impl Storable for TodoEvent {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    /// Decodes an event, with defaults for the fields its Todo item was stored without.
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self)
+            .ok()
+            .or_else(|| Decode!(bytes.as_ref(), LenientEvent).ok()?.into_event())
+            .expect("Corrupt event record")
+    }
+}
+
+/// Replays events into the Todo items they describe.
+///
+/// # Arguments
+///
+/// * `events` - The events, oldest first.
+///
+/// # Returns
+///
+/// The Todo items by identifier.
+pub(crate) fn project(events: impl IntoIterator<Item = TodoEvent>) -> BTreeMap<TodoId, Todo> {
+    let mut todos = BTreeMap::new();
+    for event in events {
+        match event.kind {
+            TodoEventKind::Reset(_) => todos.clear(),
+            TodoEventKind::Put(todo) => {
+                todos.insert(todo.id, *todo);
+            }
+            TodoEventKind::Removed(id) => {
+                todos.remove(&id);
+            }
+            TodoEventKind::Renumbered(pairs) => {
+                let ids: Renumbering = pairs.into_iter().collect();
+                todos = todos
+                    .into_values()
+                    .map(|mut todo| {
+                        todo.id = renumbered(&ids, todo.id);
+                        (todo.id, todo)
+                    })
+                    .collect();
+            }
+        }
+    }
+    todos
+}
+
+/// Wrapper around the EventStore to provide additional functionality.
+pub(crate) struct EventStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<EventStore<M>>,
+}
+
+impl<M: Memory> EventStoreWrapper<'_, M> {
+    /// Appends the event describing a change to a Todo item, unless the change left it as it was.
+    ///
+    /// # Arguments
+    ///
+    /// * `change` - The change to a Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    pub(crate) fn record_change(&self, change: &TodoChange, now: u64) {
+        match (&change.before, &change.after) {
+            (before, Some(after)) if before.as_ref() != Some(after) => self.append(
+                change.principal,
+                TodoEventKind::Put(Box::new(after.clone())),
+                now,
+            ),
+            (Some(before), None) => {
+                self.append(change.principal, TodoEventKind::Removed(before.id), now)
+            }
+            _ => {}
+        }
+    }
+
+    /// Appends an event to the log of a principal, compacting the log once it gained `MAX_EVENTS` events after its snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `kind` - What happened.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    pub(crate) fn append(&self, principal: Principal, kind: TodoEventKind, now: u64) {
+        let (first, next) = self.bounds(principal);
+        let snapshot = match self.store.borrow().get(&(principal, first)) {
+            Some(TodoEvent {
+                kind: TodoEventKind::Reset(len),
+                ..
+            }) => len + 1,
+            _ => 0,
+        };
+        self.store.borrow_mut().insert(
+            (principal, next),
+            TodoEvent {
+                sequence: next,
+                at: now,
+                kind,
+            },
+        );
+        if (next + 1 - first).saturating_sub(snapshot) > MAX_EVENTS {
+            self.compact(principal, now);
+        }
+    }
+
+    /// Lists the events of a principal after a position, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `after` - The position of the last event already seen, or None to start from the oldest event kept.
+    /// * `limit` - The maximum number of events.
+    pub(crate) fn list(
+        &self,
+        principal: Principal,
+        after: Option<Sequence>,
+        limit: usize,
+    ) -> Vec<TodoEvent> {
+        let from = after.map_or(Sequence::MIN, |after| after.saturating_add(1));
+        self.store
+            .borrow()
+            .range((principal, from)..=(principal, Sequence::MAX))
+            .take(limit)
+            .map(|(_, event)| event)
+            .collect()
+    }
+
+    /// Replays the log of a principal into their Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn project(&self, principal: Principal) -> BTreeMap<TodoId, Todo> {
+        project(self.list(principal, None, usize::MAX))
+    }
+
+    /// Replaces the log of a principal with a snapshot of their Todo items, keeping the positions increasing.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    pub(crate) fn compact(&self, principal: Principal, now: u64) {
+        let todos = self.project(principal);
+        let (first, next) = self.bounds(principal);
+        let mut store = self.store.borrow_mut();
+        for sequence in first..next {
+            store.remove(&(principal, sequence));
+        }
+        let kinds = std::iter::once(TodoEventKind::Reset(todos.len() as u64)).chain(
+            todos
+                .into_values()
+                .map(|todo| TodoEventKind::Put(Box::new(todo))),
+        );
+        for (sequence, kind) in (next..).zip(kinds) {
+            store.insert(
+                (principal, sequence),
+                TodoEvent {
+                    sequence,
+                    at: now,
+                    kind,
+                },
+            );
+        }
+    }
+
+    /// Returns whether no events are logged for any principal.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.store.borrow().is_empty()
+    }
+
+    /// Returns the position of the oldest event kept for a principal and the position of the next event.
+    fn bounds(&self, principal: Principal) -> (Sequence, Sequence) {
+        let store = self.store.borrow();
+        let mut events = store.range((principal, Sequence::MIN)..=(principal, Sequence::MAX));
+        let first = events.next().map_or(1, |((_, sequence), _)| sequence);
+        let next = store
+            .iter_upper_bound(&(principal, Sequence::MAX))
+            .next()
+            .filter(|((owner, _), _)| *owner == principal)
+            .map_or(1, |((_, sequence), _)| sequence + 1);
+        (first, next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::Priority;
+
+    fn todo(id: TodoId, description: &str) -> Todo {
+        Todo::new(id, description.to_string(), Priority::Medium)
+    }
+
+    #[test]
+    fn test_replay_and_compact() {
+        let store = RefCell::new(EventStore::new(DefaultMemoryImpl::default()));
+        let wrapper = EventStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let change = |before: Option<Todo>, after: Option<Todo>| TodoChange {
+            principal,
+            before,
+            after,
+        };
+
+        wrapper.record_change(&change(None, Some(todo(3, "Write"))), 1);
+        wrapper.record_change(&change(None, Some(todo(5, "Read"))), 2);
+        wrapper.record_change(&change(Some(todo(5, "Read")), Some(todo(5, "Read"))), 2);
+        wrapper.record_change(&change(Some(todo(3, "Write")), Some(todo(3, "Edit"))), 3);
+        wrapper.record_change(&change(Some(todo(5, "Read")), None), 4);
+        wrapper.append(principal, TodoEventKind::Renumbered(vec![(3, 1)]), 5);
+        assert_eq!(
+            wrapper.project(principal),
+            BTreeMap::from([(1, todo(1, "Edit"))])
+        );
+        assert_eq!(wrapper.list(principal, Some(3), 10).len(), 2);
+
+        wrapper.compact(principal, 6);
+        let events = wrapper.list(principal, None, 10);
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| event.sequence)
+                .collect::<Vec<_>>(),
+            vec![6, 7]
+        );
+        assert_eq!(events[0].kind, TodoEventKind::Reset(1));
+        assert_eq!(
+            wrapper.project(principal),
+            BTreeMap::from([(1, todo(1, "Edit"))])
+        );
+        assert!(wrapper.project(Principal::management_canister()).is_empty());
+    }
+
+    #[test]
+    fn test_decodes_event_of_older_todo_shape() {
+        #[derive(CandidType)]
+        struct OldTodo {
+            id: TodoId,
+            description: String,
+            is_completed: bool,
+            priority: Priority,
+        }
+        #[derive(CandidType)]
+        enum OldEventKind {
+            Put(Box<OldTodo>),
+        }
+        #[derive(CandidType)]
+        struct OldEvent {
+            sequence: Sequence,
+            at: u64,
+            kind: OldEventKind,
+        }
+        let old = OldEvent {
+            sequence: 4,
+            at: 9,
+            kind: OldEventKind::Put(Box::new(OldTodo {
+                id: 2,
+                description: "Old".to_string(),
+                is_completed: true,
+                priority: Priority::Medium,
+            })),
+        };
+
+        let event = TodoEvent::from_bytes(Cow::Owned(Encode!(&old).unwrap()));
+
+        assert_eq!(event.sequence, 4);
+        let TodoEventKind::Put(todo) = event.kind else {
+            panic!("Expected a Put event");
+        };
+        assert_eq!((todo.id, todo.description.as_str()), (2, "Old"));
+        assert!(todo.is_completed);
+    }
+}
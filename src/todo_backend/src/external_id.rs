@@ -0,0 +1,91 @@
+use std::cell::RefCell;
+
+use candid::Principal;
+use ic_stable_structures::{Memory, StableBTreeMap};
+
+use crate::{
+    bounded_string::BoundedString,
+    renumbering::{renumbered, Renumbering},
+    todo::TodoId,
+    validation::MAX_EXTERNAL_ID_LENGTH,
+};
+
+/// Type alias for an identifier assigned to a Todo item by an external system.
+pub(crate) type ExternalId = BoundedString<{ 4 * MAX_EXTERNAL_ID_LENGTH as u32 }>;
+
+/// Type alias for the ExternalIdStore, which maps a (Principal, ExternalId) pair to the Todo item it was synced into.
+pub(crate) type ExternalIdStore<M> = StableBTreeMap<(Principal, ExternalId), TodoId, M>;
+
+/// Wrapper around the ExternalIdStore to provide additional functionality.
+pub(crate) struct ExternalIdStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<ExternalIdStore<M>>,
+}
+
+impl<M: Memory> ExternalIdStoreWrapper<'_, M> {
+    /// Retrieves the Todo item an external id is mapped to.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `external_id` - The identifier assigned by the external system.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the Todo identifier if the external id is mapped, otherwise None.
+    pub(crate) fn get(&self, principal: Principal, external_id: &str) -> Option<TodoId> {
+        self.store.borrow().get(&(principal, external_id.into()))
+    }
+
+    /// Maps an external id to a Todo item, replacing any previous mapping.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `external_id` - The identifier assigned by the external system.
+    /// * `id` - The unique identifier for the Todo item.
+    pub(crate) fn set(&self, principal: Principal, external_id: String, id: TodoId) {
+        self.store.borrow_mut().insert((principal, external_id.into()), id);
+    }
+
+    /// Points the external ids of a principal to the new identifiers of their Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        let mappings: Vec<_> = self
+            .store
+            .borrow()
+            .range((principal, ExternalId::from(""))..)
+            .take_while(|((p, _), _)| *p == principal)
+            .filter(|(_, id)| ids.contains_key(id))
+            .collect();
+        let mut store = self.store.borrow_mut();
+        for (key, id) in mappings {
+            store.insert(key, renumbered(ids, id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_mapping_is_scoped_per_principal() {
+        let store = RefCell::new(ExternalIdStore::new(DefaultMemoryImpl::default()));
+        let wrapper = ExternalIdStoreWrapper { store: &store };
+        let alice = Principal::anonymous();
+        let bob = Principal::management_canister();
+
+        wrapper.set(alice, "issue-1".to_string(), 1);
+        assert_eq!(wrapper.get(alice, "issue-1"), Some(1));
+        assert_eq!(wrapper.get(bob, "issue-1"), None);
+
+        wrapper.set(alice, "issue-1".to_string(), 2);
+        assert_eq!(wrapper.get(alice, "issue-1"), Some(2));
+    }
+}
@@ -0,0 +1,227 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::errors::Error;
+
+/// Maximum number of principals a feature may be turned on or off for individually.
+pub(crate) const MAX_OVERRIDES: usize = 1_000;
+
+/// Type alias for the FeatureFlagStore, which is a StableBTreeMap keyed by the number of a feature with value of FeatureFlag.
+pub(crate) type FeatureFlagStore<M> = StableBTreeMap<u8, FeatureFlag, M>;
+
+/// Represents a subsystem that can be turned on or off without deploying the canister again.
+/// Flags are stored by the position of the variant, so new features are appended.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Feature {
+    /// Todo items created from events posted by external services.
+    Webhooks,
+    /// Points, levels, streaks and the leaderboard.
+    Gamification,
+    /// Access to the API with API tokens.
+    ApiTokens,
+}
+
+impl Feature {
+    /// Every feature, in the order they are listed.
+    pub(crate) const ALL: [Feature; 3] =
+        [Feature::Webhooks, Feature::Gamification, Feature::ApiTokens];
+
+    /// Returns whether the feature is on for principals without an override before an admin sets it.
+    /// Features that shipped before the flags existed stay on.
+    fn default_enabled(self) -> bool {
+        match self {
+            Feature::Webhooks | Feature::Gamification | Feature::ApiTokens => true,
+        }
+    }
+
+    /// Returns the name of the feature, as used in error messages.
+    fn name(self) -> &'static str {
+        match self {
+            Feature::Webhooks => "webhooks",
+            Feature::Gamification => "gamification",
+            Feature::ApiTokens => "API tokens",
+        }
+    }
+}
+
+/// Represents whether a feature is on, for everyone and for individual principals.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct FeatureFlag {
+    /// The feature.
+    pub(crate) feature: Feature,
+    /// Whether the feature is on for principals without an override.
+    pub(crate) enabled: bool,
+    /// Principals the feature is turned on or off for regardless of the global setting.
+    pub(crate) overrides: Vec<(Principal, bool)>,
+}
+
+impl Storable for FeatureFlag {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl FeatureFlag {
+    /// Returns whether the feature is on for a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    fn is_enabled_for(&self, principal: Principal) -> bool {
+        self.overrides
+            .iter()
+            .find(|(overridden, _)| *overridden == principal)
+            .map_or(self.enabled, |(_, enabled)| *enabled)
+    }
+}
+
+/// Wrapper around the FeatureFlagStore to provide additional functionality.
+pub(crate) struct FeatureFlagStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<FeatureFlagStore<M>>,
+}
+
+impl<M: Memory> FeatureFlagStoreWrapper<'_, M> {
+    /// Retrieves the flag of a feature, falling back to its default.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - The feature.
+    pub(crate) fn get(&self, feature: Feature) -> FeatureFlag {
+        self.store
+            .borrow()
+            .get(&(feature as u8))
+            .unwrap_or_else(|| FeatureFlag {
+                feature,
+                enabled: feature.default_enabled(),
+                overrides: Vec::new(),
+            })
+    }
+
+    /// Lists the flags of every feature.
+    pub(crate) fn list(&self) -> Vec<FeatureFlag> {
+        Feature::ALL
+            .into_iter()
+            .map(|feature| self.get(feature))
+            .collect()
+    }
+
+    /// Returns whether a feature is on for a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - The feature.
+    /// * `principal` - The principal identifier.
+    pub(crate) fn is_enabled(&self, feature: Feature, principal: Principal) -> bool {
+        self.get(feature).is_enabled_for(principal)
+    }
+
+    /// Checks that a feature is on for a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - The feature.
+    /// * `principal` - The principal identifier.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or a DependencyNotMet Error if the feature is off.
+    pub(crate) fn ensure_enabled(
+        &self,
+        feature: Feature,
+        principal: Principal,
+    ) -> Result<(), Error> {
+        if self.is_enabled(feature, principal) {
+            Ok(())
+        } else {
+            Err(Error::DependencyNotMet(Some(format!(
+                "The {} feature is turned off",
+                feature.name()
+            ))))
+        }
+    }
+
+    /// Turns a feature on or off for principals without an override.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - The feature.
+    /// * `enabled` - Whether the feature is on.
+    pub(crate) fn set(&self, feature: Feature, enabled: bool) {
+        let mut flag = self.get(feature);
+        flag.enabled = enabled;
+        self.store.borrow_mut().insert(feature as u8, flag);
+    }
+
+    /// Turns a feature on or off for a single principal, or makes the principal follow the global setting again.
+    ///
+    /// # Arguments
+    ///
+    /// * `feature` - The feature.
+    /// * `principal` - The principal identifier.
+    /// * `enabled` - Whether the feature is on for the principal, or None to remove the override.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if too many principals have an override.
+    pub(crate) fn set_override(
+        &self,
+        feature: Feature,
+        principal: Principal,
+        enabled: Option<bool>,
+    ) -> Result<(), Error> {
+        let mut flag = self.get(feature);
+        flag.overrides
+            .retain(|(overridden, _)| *overridden != principal);
+        if let Some(enabled) = enabled {
+            if flag.overrides.len() >= MAX_OVERRIDES {
+                return Err(Error::QuotaExceeded(Some(format!(
+                    "A feature can be overridden for at most {MAX_OVERRIDES} principals"
+                ))));
+            }
+            flag.overrides.push((principal, enabled));
+        }
+        self.store.borrow_mut().insert(feature as u8, flag);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_flags_and_overrides() {
+        let store = RefCell::new(FeatureFlagStore::new(DefaultMemoryImpl::default()));
+        let wrapper = FeatureFlagStoreWrapper { store: &store };
+        let tester = Principal::anonymous();
+        let other = Principal::management_canister();
+
+        assert!(wrapper.is_enabled(Feature::Webhooks, other));
+        wrapper.set(Feature::Webhooks, false);
+        assert!(wrapper
+            .set_override(Feature::Webhooks, tester, Some(true))
+            .is_ok());
+        assert!(wrapper.ensure_enabled(Feature::Webhooks, tester).is_ok());
+        assert!(matches!(
+            wrapper.ensure_enabled(Feature::Webhooks, other),
+            Err(Error::DependencyNotMet(_))
+        ));
+        assert!(wrapper.is_enabled(Feature::Gamification, other));
+
+        assert!(wrapper
+            .set_override(Feature::Webhooks, tester, None)
+            .is_ok());
+        assert!(!wrapper.is_enabled(Feature::Webhooks, tester));
+        assert_eq!(wrapper.list().len(), Feature::ALL.len());
+    }
+}
@@ -0,0 +1,306 @@
+use std::cell::RefCell;
+
+use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::{Memory, StableBTreeMap};
+use serde::Serialize;
+
+use crate::{
+    renumbering::{renumbered, rewrite_entries, Renumbering},
+    store::TodoChange,
+    todo::{Todo, TodoId},
+};
+
+/// Number of bits of the geohash cells Todo items are indexed under, alternating between longitude and
+/// latitude as in a geohash of six characters; a cell spans about 1.2 by 0.6 kilometres at the equator.
+const CELL_BITS: u32 = 30;
+
+/// Mean radius of the Earth, in metres.
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+/// Length of a degree of latitude, in metres.
+const METRES_PER_DEGREE: f64 = EARTH_RADIUS_M * std::f64::consts::PI / 180.0;
+
+/// Largest radius a proximity search may cover, in metres.
+pub(crate) const MAX_RADIUS_M: u32 = 50_000;
+
+/// Type alias for the GeoIndex, which is a StableBTreeMap keyed by (Principal, geohash cell, TodoId) for every
+/// open Todo item with a location in the Todo store. Cells sharing a geohash prefix are adjacent in the index.
+pub(crate) type GeoIndex<M> = StableBTreeMap<(Principal, u32, TodoId), (), M>;
+
+/// Represents a place on Earth.
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub(crate) struct GeoPoint {
+    /// Latitude, in degrees from -90 to 90.
+    pub(crate) lat: f64,
+    /// Longitude, in degrees from -180 to 180.
+    pub(crate) lon: f64,
+    /// Optional name of the place, such as `Pharmacy`.
+    pub(crate) label: Option<String>,
+}
+
+/// Represents a Todo item found near a place.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct NearbyTodo {
+    /// The Todo item.
+    pub(crate) todo: Todo,
+    /// Distance between the place and the location of the Todo item, in metres.
+    pub(crate) distance_m: u32,
+}
+
+impl GeoPoint {
+    /// Returns the great-circle distance to another point, in metres.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The other point.
+    pub(crate) fn distance_m(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let half_dlat = (lat2 - lat1) / 2.0;
+        let half_dlon = (other.lon - self.lon).to_radians() / 2.0;
+        let a = half_dlat.sin().powi(2) + lat1.cos() * lat2.cos() * half_dlon.sin().powi(2);
+        2.0 * EARTH_RADIUS_M * a.sqrt().min(1.0).asin()
+    }
+}
+
+/// Returns the geohash of a point as a number of bits, alternating between longitude and latitude
+/// starting with longitude.
+///
+/// # Arguments
+///
+/// * `lat` - The latitude, in degrees.
+/// * `lon` - The longitude, in degrees.
+/// * `bits` - The number of bits, at most 32.
+fn geohash(lat: f64, lon: f64, bits: u32) -> u32 {
+    let (mut lat_range, mut lon_range) = ((-90.0, 90.0), (-180.0, 180.0));
+    let mut hash = 0;
+    for bit in 0..bits {
+        let (value, range) = if bit % 2 == 0 {
+            (lon, &mut lon_range)
+        } else {
+            (lat, &mut lat_range)
+        };
+        let middle = (range.0 + range.1) / 2.0;
+        hash <<= 1;
+        if value >= middle {
+            hash |= 1;
+            range.0 = middle;
+        } else {
+            range.1 = middle;
+        }
+    }
+    hash
+}
+
+/// Returns the height and width of a geohash cell, in degrees of latitude and longitude.
+///
+/// # Arguments
+///
+/// * `bits` - The number of bits of the geohash.
+fn cell_size(bits: u32) -> (f64, f64) {
+    let lat_bits = bits / 2;
+    let lon_bits = bits - lat_bits;
+    (
+        180.0 / f64::from(1u32 << lat_bits),
+        360.0 / f64::from(1u32 << lon_bits),
+    )
+}
+
+/// Lists the geohash prefixes covering every point within a radius of a center: the cell of the center and its
+/// eight neighbours, at the finest precision whose cells are at least as large as the radius.
+///
+/// # Arguments
+///
+/// * `center` - The center.
+/// * `radius_m` - The radius, in metres.
+///
+/// # Returns
+///
+/// The number of bits of the prefixes and the distinct prefixes.
+fn covering_prefixes(center: &GeoPoint, radius_m: f64) -> (u32, Vec<u32>) {
+    // Longitude degrees shrink towards the poles; clamping keeps the cells finite near them.
+    let lon_scale = center.lat.to_radians().cos().max(0.01);
+    let bits = (0..=CELL_BITS)
+        .rev()
+        .find(|bits| {
+            let (height, width) = cell_size(*bits);
+            height * METRES_PER_DEGREE >= radius_m
+                && width * METRES_PER_DEGREE * lon_scale >= radius_m
+        })
+        .unwrap_or(0);
+    let (height, width) = cell_size(bits);
+    let mut prefixes = Vec::new();
+    for dlat in [-1.0, 0.0, 1.0] {
+        for dlon in [-1.0, 0.0, 1.0] {
+            let lat = (center.lat + dlat * height).clamp(-90.0, 90.0);
+            let lon = (center.lon + dlon * width + 540.0).rem_euclid(360.0) - 180.0;
+            let prefix = geohash(lat, lon, bits);
+            if !prefixes.contains(&prefix) {
+                prefixes.push(prefix);
+            }
+        }
+    }
+    (bits, prefixes)
+}
+
+/// Returns the cell an open Todo item with a location is indexed under.
+///
+/// # Arguments
+///
+/// * `todo` - The Todo item.
+fn cell_of(todo: &Todo) -> Option<u32> {
+    let location = todo.location.as_ref().filter(|_| !todo.is_completed)?;
+    Some(geohash(location.lat, location.lon, CELL_BITS))
+}
+
+/// Wrapper around the GeoIndex to provide additional functionality.
+pub(crate) struct GeoIndexWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<GeoIndex<M>>,
+}
+
+impl<M: Memory> GeoIndexWrapper<'_, M> {
+    /// Moves a Todo item to the cell of its new location after a change.
+    ///
+    /// # Arguments
+    ///
+    /// * `change` - The change to a Todo item.
+    pub(crate) fn record_change(&self, change: &TodoChange) {
+        if let Some(before) = &change.before {
+            self.remove(change.principal, before);
+        }
+        if let Some(after) = &change.after {
+            self.insert(change.principal, after);
+        }
+    }
+
+    /// Adds a Todo item to the index if it is open and has a location.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the Todo item.
+    /// * `todo` - The Todo item.
+    pub(crate) fn insert(&self, principal: Principal, todo: &Todo) {
+        if let Some(cell) = cell_of(todo) {
+            self.store
+                .borrow_mut()
+                .insert((principal, cell, todo.id), ());
+        }
+    }
+
+    /// Removes a Todo item from the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the Todo item.
+    /// * `todo` - The Todo item as it was last indexed.
+    pub(crate) fn remove(&self, principal: Principal, todo: &Todo) {
+        if let Some(cell) = cell_of(todo) {
+            self.store.borrow_mut().remove(&(principal, cell, todo.id));
+        }
+    }
+
+    /// Lists the open Todo items of a principal that may lie within a radius of a place. The candidates
+    /// cover the radius but may lie further away, so callers check the distance of each.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `center` - The place.
+    /// * `radius_m` - The radius, in metres.
+    ///
+    /// # Returns
+    ///
+    /// A vector of Todo item identifiers.
+    pub(crate) fn candidates(
+        &self,
+        principal: Principal,
+        center: &GeoPoint,
+        radius_m: u32,
+    ) -> Vec<TodoId> {
+        let (bits, prefixes) = covering_prefixes(center, f64::from(radius_m));
+        let shift = CELL_BITS - bits;
+        let store = self.store.borrow();
+        let mut ids = Vec::new();
+        for prefix in prefixes {
+            let first = prefix << shift;
+            let last = first | ((1u64 << shift) - 1) as u32;
+            ids.extend(
+                store
+                    .range((principal, first, TodoId::MIN)..=(principal, last, TodoId::MAX))
+                    .map(|((_, _, id), _)| id),
+            );
+        }
+        ids
+    }
+
+    /// Moves the index entries of a principal to the new identifiers of their Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        rewrite_entries(
+            self.store,
+            (principal, u32::MIN, TodoId::MIN)..=(principal, u32::MAX, TodoId::MAX),
+            |(principal, cell, id), ()| ((principal, cell, renumbered(ids, id)), ()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::Priority;
+
+    fn point(lat: f64, lon: f64) -> GeoPoint {
+        GeoPoint {
+            lat,
+            lon,
+            label: None,
+        }
+    }
+
+    #[test]
+    fn test_geohash_and_distance() {
+        // The geohash of this point starts with "u4pruy", i.e. 11010 00100 10101 10111 11010 11110.
+        assert_eq!(
+            geohash(57.64911, 10.40744, 30),
+            0b11010_00100_10101_10111_11010_11110
+        );
+        let distance = point(52.5200, 13.4050).distance_m(&point(48.1351, 11.5820));
+        assert!((distance - 504_000.0).abs() < 2_000.0);
+    }
+
+    #[test]
+    fn test_candidates() {
+        let store = RefCell::new(GeoIndex::new(DefaultMemoryImpl::default()));
+        let wrapper = GeoIndexWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let todo = |id, location: GeoPoint| Todo {
+            location: Some(location),
+            ..Todo::new(id, format!("Task {id}"), Priority::Medium)
+        };
+
+        let home = point(52.5200, 13.4050);
+        // About 300 metres north and east of home.
+        wrapper.insert(principal, &todo(1, point(52.5227, 13.4050)));
+        wrapper.insert(principal, &todo(2, point(52.5200, 13.4090)));
+        wrapper.insert(principal, &todo(3, point(48.1351, 11.5820)));
+        let mut done = todo(4, point(52.5200, 13.4050));
+        done.set_completed(true, 1);
+        wrapper.insert(principal, &done);
+
+        let mut near = wrapper.candidates(principal, &home, 1_000);
+        near.sort_unstable();
+        assert_eq!(near, vec![1, 2]);
+        let mut far = wrapper.candidates(principal, &home, MAX_RADIUS_M);
+        far.sort_unstable();
+        assert_eq!(far, vec![1, 2]);
+
+        wrapper.remove(principal, &todo(2, point(52.5200, 13.4090)));
+        assert_eq!(wrapper.candidates(principal, &home, 1_000), vec![1]);
+    }
+}
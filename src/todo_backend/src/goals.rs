@@ -0,0 +1,269 @@
+use std::{borrow::Cow, cell::RefCell, collections::BTreeMap};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{errors::Error, todo::Todo};
+
+/// Maximum number of goals a principal may pursue at once.
+pub(crate) const MAX_GOALS: usize = 50;
+
+/// Maximum number of completed Todo items a goal may target.
+pub(crate) const MAX_TARGET_COUNT: u32 = 10_000;
+
+/// Type alias for the unique identifier of a goal, unique per principal.
+pub(crate) type GoalId = u64;
+
+/// Type alias for the GoalStore, which is a StableBTreeMap with a tuple key of (Principal, GoalId) and value of Goal.
+pub(crate) type GoalStore<M> = StableBTreeMap<(Principal, GoalId), Goal, M>;
+
+/// Represents what reaching a goal takes.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum GoalTarget {
+    /// A number of Todo items attached to the goal are completed.
+    Count(u32),
+    /// Every Todo item attached to the goal is completed by a date, in nanoseconds since the Unix epoch.
+    Date(u64),
+}
+
+/// Represents a longer-term objective Todo items are attached to.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Goal {
+    /// Unique identifier for the goal.
+    pub(crate) id: GoalId,
+    /// Title of the goal.
+    pub(crate) title: String,
+    /// What reaching the goal takes.
+    pub(crate) target: GoalTarget,
+    /// Timestamp of when the goal was set, in nanoseconds since the Unix epoch.
+    pub(crate) created_at: u64,
+}
+
+impl Storable for Goal {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Represents how far a goal got.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct GoalProgress {
+    /// The goal.
+    pub(crate) goal: Goal,
+    /// Number of Todo items attached to the goal, including archived ones.
+    pub(crate) total: u32,
+    /// Number of those Todo items that are completed.
+    pub(crate) completed: u32,
+    /// Share of the goal reached, from 0 to 100: completions against the target count, or the
+    /// completed share of the attached Todo items for goals with a target date.
+    pub(crate) percent: u8,
+}
+
+/// Computes the progress of goals from the Todo items attached to them.
+///
+/// # Arguments
+///
+/// * `goals` - The goals of a principal.
+/// * `todos` - The Todo items of the principal, including archived ones.
+///
+/// # Returns
+///
+/// The progress of every goal, in the order of the goals.
+pub(crate) fn progress(goals: Vec<Goal>, todos: &[Todo]) -> Vec<GoalProgress> {
+    let mut counts: BTreeMap<GoalId, (u32, u32)> = BTreeMap::new();
+    for todo in todos {
+        if let Some(goal_id) = todo.goal_id {
+            let (total, completed) = counts.entry(goal_id).or_default();
+            *total += 1;
+            *completed += u32::from(todo.is_completed);
+        }
+    }
+    goals
+        .into_iter()
+        .map(|goal| {
+            let (total, completed) = counts.get(&goal.id).copied().unwrap_or_default();
+            let reached = match goal.target {
+                GoalTarget::Count(count) => (completed, count),
+                GoalTarget::Date(_) => (completed, total),
+            };
+            let percent = match reached {
+                (_, 0) => 0,
+                (done, of) => (u64::from(done.min(of)) * 100 / u64::from(of)) as u8,
+            };
+            GoalProgress {
+                goal,
+                total,
+                completed,
+                percent,
+            }
+        })
+        .collect()
+}
+
+/// Wrapper around the GoalStore to provide additional functionality.
+pub(crate) struct GoalStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<GoalStore<M>>,
+}
+
+impl<M: Memory> GoalStoreWrapper<'_, M> {
+    /// Sets a goal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `title` - The validated title of the goal.
+    /// * `target` - The validated target of the goal.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the unique identifier for the new goal, or an Error if too many goals exist.
+    pub(crate) fn create(
+        &self,
+        principal: Principal,
+        title: String,
+        target: GoalTarget,
+        now: u64,
+    ) -> Result<GoalId, Error> {
+        if self.list(principal).len() >= MAX_GOALS {
+            return Err(Error::QuotaExceeded(Some(format!(
+                "At most {MAX_GOALS} goals can be set"
+            ))));
+        }
+        let id = self
+            .store
+            .borrow()
+            .iter_upper_bound(&(principal, GoalId::MAX))
+            .next()
+            .filter(|((owner, _), _)| *owner == principal)
+            .map_or(1, |((_, id), _)| id + 1);
+        let goal = Goal {
+            id,
+            title,
+            target,
+            created_at: now,
+        };
+        self.store.borrow_mut().insert((principal, id), goal);
+        Ok(id)
+    }
+
+    /// Retrieves a goal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the goal.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the goal if found, otherwise None.
+    pub(crate) fn get(&self, principal: Principal, id: GoalId) -> Option<Goal> {
+        self.store.borrow().get(&(principal, id))
+    }
+
+    /// Lists the goals of a principal in the order they were set.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn list(&self, principal: Principal) -> Vec<Goal> {
+        self.store
+            .borrow()
+            .range((principal, GoalId::MIN)..=(principal, GoalId::MAX))
+            .map(|(_, goal)| goal)
+            .collect()
+    }
+
+    /// Puts back the goals of a principal from an account bundle, keeping their identifiers.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `goals` - The validated goals.
+    pub(crate) fn restore(&self, principal: Principal, goals: Vec<Goal>) {
+        let mut store = self.store.borrow_mut();
+        for goal in goals {
+            store.insert((principal, goal.id), goal);
+        }
+    }
+
+    /// Deletes a goal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the goal.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if the goal is not found.
+    pub(crate) fn delete(&self, principal: Principal, id: GoalId) -> Result<(), Error> {
+        self.store
+            .borrow_mut()
+            .remove(&(principal, id))
+            .map(|_| ())
+            .ok_or(Error::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::Priority;
+
+    #[test]
+    fn test_goals_and_progress() {
+        let store = RefCell::new(GoalStore::new(DefaultMemoryImpl::default()));
+        let wrapper = GoalStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+
+        let read = wrapper
+            .create(principal, "Read".to_string(), GoalTarget::Count(4), 1)
+            .unwrap();
+        let ship = wrapper
+            .create(principal, "Ship".to_string(), GoalTarget::Date(100), 2)
+            .unwrap();
+        let idle = wrapper
+            .create(principal, "Idle".to_string(), GoalTarget::Count(1), 3)
+            .unwrap();
+        assert_eq!((read, ship, idle), (1, 2, 3));
+
+        let todo = |id, goal_id, completed| {
+            let mut todo = Todo {
+                goal_id: Some(goal_id),
+                ..Todo::new(id, format!("Task {id}"), Priority::Medium)
+            };
+            todo.set_completed(completed, 0);
+            todo
+        };
+        let todos = vec![
+            todo(1, read, true),
+            todo(2, read, false),
+            todo(3, ship, true),
+            todo(4, ship, false),
+            todo(5, ship, false),
+            todo(6, ship, true),
+        ];
+        let progress: Vec<_> = progress(wrapper.list(principal), &todos)
+            .into_iter()
+            .map(|progress| (progress.total, progress.completed, progress.percent))
+            .collect();
+        assert_eq!(progress, vec![(2, 1, 25), (4, 2, 50), (0, 0, 0)]);
+
+        assert!(wrapper.delete(principal, ship).is_ok());
+        assert!(matches!(
+            wrapper.delete(principal, ship),
+            Err(Error::NotFound)
+        ));
+        assert_eq!(wrapper.list(principal).len(), 2);
+    }
+}
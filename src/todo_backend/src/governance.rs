@@ -0,0 +1,112 @@
+use std::borrow::Cow;
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Storable};
+
+use crate::errors::Error;
+
+/// Represents the arguments the canister is installed or upgraded with.
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub(crate) struct InitArgs {
+    /// Optional governance canister, such as the governance canister of an SNS, that becomes the admin of the canister.
+    pub(crate) governance: Option<Principal>,
+}
+
+/// Represents who administers the canister.
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct GovernanceConfig {
+    /// The governance canister that is the admin, or None if the controllers are.
+    pub(crate) governance: Option<Principal>,
+}
+
+impl Storable for GovernanceConfig {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl GovernanceConfig {
+    /// Checks that a caller is an admin of the canister.
+    /// Once a governance canister is configured, it is the admin alongside the controllers.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - The caller.
+    /// * `is_controller` - Whether the caller is a controller of the canister.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Unauthorized Error.
+    pub(crate) fn ensure_admin(&self, caller: Principal, is_controller: bool) -> Result<(), Error> {
+        if is_controller || self.governance == Some(caller) {
+            Ok(())
+        } else {
+            Err(Error::Unauthorized(Some(
+                "Caller is not an admin".to_string(),
+            )))
+        }
+    }
+
+    /// Checks that a caller may perform a destructive admin operation.
+    /// Once a governance canister is configured, only it may, which it does when executing an adopted proposal
+    /// after the proposal was checked by the matching `validate_` query; no single controller key suffices.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - The caller.
+    /// * `is_controller` - Whether the caller is a controller of the canister.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Unauthorized Error.
+    pub(crate) fn ensure_proposal(
+        &self,
+        caller: Principal,
+        is_controller: bool,
+    ) -> Result<(), Error> {
+        match self.governance {
+            Some(governance) if governance == caller => Ok(()),
+            Some(_) => Err(Error::Unauthorized(Some(
+                "The operation requires an adopted governance proposal".to_string(),
+            ))),
+            None if is_controller => Ok(()),
+            None => Err(Error::Unauthorized(Some(
+                "Caller is not a controller".to_string(),
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admin_checks() {
+        let governance = Principal::management_canister();
+        let controller = Principal::anonymous();
+        let governed = GovernanceConfig {
+            governance: Some(governance),
+        };
+        assert!(governed.ensure_admin(governance, false).is_ok());
+        assert!(governed.ensure_admin(controller, true).is_ok());
+        assert!(governed.ensure_proposal(governance, false).is_ok());
+        assert!(matches!(
+            governed.ensure_proposal(controller, true),
+            Err(Error::Unauthorized(_))
+        ));
+
+        let ungoverned = GovernanceConfig::default();
+        assert!(ungoverned.ensure_proposal(controller, true).is_ok());
+        assert!(matches!(
+            ungoverned.ensure_admin(governance, false),
+            Err(Error::Unauthorized(_))
+        ));
+    }
+}
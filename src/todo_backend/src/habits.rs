@@ -0,0 +1,198 @@
+use std::{cell::RefCell, collections::BTreeSet};
+
+use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::{Memory, StableBTreeMap};
+
+use crate::{
+    recurrence::Recurrence,
+    renumbering::{renumbered, rewrite_entries, Renumbering},
+    stats::Day,
+    todo::TodoId,
+};
+
+/// Type alias for the HabitStore, which is a StableBTreeMap with a tuple key of (Principal, TodoId, Day) marking the local days a habit was done on.
+pub(crate) type HabitStore<M> = StableBTreeMap<(Principal, TodoId, Day), (), M>;
+
+/// Represents an occurrence of a habit.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) struct HabitDay {
+    /// The local day of the occurrence, counted in days since the Unix epoch.
+    pub(crate) day: Day,
+    /// Whether the habit was done on the day.
+    pub(crate) done: bool,
+}
+
+/// Represents the record of a habit over a range of days.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct HabitHistory {
+    /// The days the habit was due or done on, oldest first.
+    pub(crate) days: Vec<HabitDay>,
+    /// Number of occurrences done in a row up to the latest one; an occurrence due today that is not done yet does not break it.
+    pub(crate) current_streak: u32,
+    /// Largest number of occurrences done in a row within the range.
+    pub(crate) longest_streak: u32,
+}
+
+/// Assembles the history of a habit from the days it was done on.
+///
+/// # Arguments
+///
+/// * `recurrence` - The rule naming the days the habit is due on.
+/// * `done` - The local days the habit was done on.
+/// * `from` - The first local day of the range.
+/// * `to` - The last local day of the range (inclusive).
+/// * `today` - The current local day of the owner.
+///
+/// # Returns
+///
+/// The habit history.
+pub(crate) fn history(
+    recurrence: &Recurrence,
+    done: &BTreeSet<Day>,
+    from: Day,
+    to: Day,
+    today: Day,
+) -> HabitHistory {
+    let days: Vec<HabitDay> = (from..=to.min(today))
+        .filter(|day| done.contains(day) || recurrence.falls_on(i64::from(*day)))
+        .map(|day| HabitDay {
+            day,
+            done: done.contains(&day),
+        })
+        .collect();
+    let mut longest_streak = 0;
+    let mut streak = 0;
+    for day in &days {
+        streak = if day.done { streak + 1 } else { 0 };
+        longest_streak = longest_streak.max(streak);
+    }
+    let pending_today = days
+        .last()
+        .is_some_and(|last| last.day == today && !last.done);
+    let current_streak = days
+        .iter()
+        .rev()
+        .skip(usize::from(pending_today))
+        .take_while(|day| day.done)
+        .count() as u32;
+    HabitHistory {
+        days,
+        current_streak,
+        longest_streak,
+    }
+}
+
+/// Wrapper around the HabitStore to provide additional functionality.
+pub(crate) struct HabitStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<HabitStore<M>>,
+}
+
+impl<M: Memory> HabitStoreWrapper<'_, M> {
+    /// Records that a habit was done on a day.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item tracked as a habit.
+    /// * `day` - The local day of the occurrence.
+    pub(crate) fn record(&self, principal: Principal, id: TodoId, day: Day) {
+        self.store.borrow_mut().insert((principal, id, day), ());
+    }
+
+    /// Lists the days a habit was done on within a range.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item tracked as a habit.
+    /// * `from` - The first local day of the range.
+    /// * `to` - The last local day of the range (inclusive).
+    pub(crate) fn days(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        from: Day,
+        to: Day,
+    ) -> BTreeSet<Day> {
+        self.store
+            .borrow()
+            .range((principal, id, from)..=(principal, id, to))
+            .map(|((_, _, day), _)| day)
+            .collect()
+    }
+
+    /// Removes the history of a habit whose Todo item was deleted.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    pub(crate) fn remove_all(&self, principal: Principal, id: TodoId) {
+        let days = self.days(principal, id, Day::MIN, Day::MAX);
+        let mut store = self.store.borrow_mut();
+        for day in days {
+            store.remove(&(principal, id, day));
+        }
+    }
+
+    /// Moves the histories of a principal's habits to the new identifiers of their Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        rewrite_entries(
+            self.store,
+            (principal, TodoId::MIN, Day::MIN)..=(principal, TodoId::MAX, Day::MAX),
+            |(principal, id, day), value| ((principal, renumbered(ids, id), day), value),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_history_and_streaks() {
+        let store = RefCell::new(HabitStore::new(DefaultMemoryImpl::default()));
+        let wrapper = HabitStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let daily = Recurrence::parse("every day").unwrap();
+
+        for day in [1, 2, 3, 5, 6, 7, 8] {
+            wrapper.record(principal, 1, day);
+        }
+        wrapper.record(principal, 2, 4);
+
+        let done = wrapper.days(principal, 1, 0, 9);
+        let daily_history = history(&daily, &done, 1, 20, 9);
+        assert_eq!(daily_history.days.len(), 9);
+        assert_eq!(daily_history.longest_streak, 4);
+        assert_eq!(daily_history.current_streak, 4);
+        assert_eq!(history(&daily, &done, 1, 9, 10).current_streak, 0);
+
+        // Day 3 was a Sunday, so Mondays fell on days 4 and 11.
+        let mondays = Recurrence::parse("every mon").unwrap();
+        let weekly_history = history(&mondays, &wrapper.days(principal, 2, 0, 20), 0, 20, 11);
+        assert_eq!(
+            weekly_history.days,
+            vec![
+                HabitDay { day: 4, done: true },
+                HabitDay {
+                    day: 11,
+                    done: false
+                }
+            ]
+        );
+        assert_eq!(weekly_history.current_streak, 1);
+
+        wrapper.renumber(principal, &Renumbering::from([(2, 1), (1, 2)]));
+        assert_eq!(wrapper.days(principal, 1, 0, 20), BTreeSet::from([4]));
+        wrapper.remove_all(principal, 2);
+        assert!(wrapper.days(principal, 2, 0, 20).is_empty());
+    }
+}
@@ -0,0 +1,379 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{
+    renumbering::{renumbered, rewrite_entries, Renumbering},
+    store::TodoChange,
+    todo::{LenientTodo, Todo, TodoId},
+};
+
+/// Maximum number of previous versions kept for each Todo item.
+pub(crate) const MAX_REVISIONS: usize = 20;
+
+/// Type alias for a revision number, increasing with every change of a Todo item.
+pub(crate) type Revision = u32;
+
+/// Type alias for the HistoryStore, which is a StableBTreeMap with a tuple key of (Principal, TodoId, Revision) and value of TodoRevision.
+pub(crate) type HistoryStore<M> = StableBTreeMap<(Principal, TodoId, Revision), TodoRevision, M>;
+
+/// Represents a previous version of a Todo item.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct TodoRevision {
+    /// The revision number, starting at 1 for the first change of the Todo item.
+    pub(crate) revision: Revision,
+    /// Time the version was replaced, in nanoseconds since the Unix epoch.
+    pub(crate) replaced_at: u64,
+    /// The Todo item as it was before the change.
+    pub(crate) todo: Todo,
+}
+
+/// Lenient view of a stored revision, used when its Todo item no longer matches the current schema.
+#[derive(CandidType, Deserialize)]
+struct LenientRevision {
+    revision: Revision,
+    replaced_at: u64,
+    todo: LenientTodo,
+}
+
+impl Storable for TodoRevision {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    /// Decodes a revision, with defaults for the fields its Todo item was stored without.
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self)
+            .ok()
+            .or_else(|| {
+                let lenient = Decode!(bytes.as_ref(), LenientRevision).ok()?;
+                Some(TodoRevision {
+                    revision: lenient.revision,
+                    replaced_at: lenient.replaced_at,
+                    todo: lenient.todo.into_todo()?,
+                })
+            })
+            .expect("Corrupt revision record")
+    }
+}
+
+/// Wrapper around the HistoryStore to provide additional functionality.
+pub(crate) struct HistoryStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<HistoryStore<M>>,
+}
+
+impl<M: Memory> HistoryStoreWrapper<'_, M> {
+    /// Keeps the previous version of a changed Todo item, dropping the oldest versions beyond the limit.
+    /// The history of a removed Todo item is discarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `change` - The change to a Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    /// * `max_revisions` - The number of previous versions the owner keeps for each Todo item.
+    pub(crate) fn record_change(&self, change: &TodoChange, now: u64, max_revisions: usize) {
+        match (&change.before, &change.after) {
+            (Some(before), Some(after)) if before != after => {
+                let revision = self.last_revision(change.principal, before.id) + 1;
+                let todo_revision = TodoRevision {
+                    revision,
+                    replaced_at: now,
+                    todo: before.clone(),
+                };
+                let mut store = self.store.borrow_mut();
+                store.insert((change.principal, before.id, revision), todo_revision);
+                let expired = revision.saturating_sub(max_revisions as Revision);
+                let expired: Vec<_> = store
+                    .range(
+                        (change.principal, before.id, 0)..=(change.principal, before.id, expired),
+                    )
+                    .map(|(key, _)| key)
+                    .collect();
+                for key in expired {
+                    store.remove(&key);
+                }
+            }
+            (Some(before), None) => {
+                let keys: Vec<_> = self
+                    .store
+                    .borrow()
+                    .range((change.principal, before.id, 0)..)
+                    .take_while(|((principal, id, _), _)| {
+                        *principal == change.principal && *id == before.id
+                    })
+                    .map(|(key, _)| key)
+                    .collect();
+                let mut store = self.store.borrow_mut();
+                for key in keys {
+                    store.remove(&key);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Lists the previous versions of a Todo item, newest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    ///
+    /// # Returns
+    ///
+    /// A vector of previous versions.
+    pub(crate) fn list_revisions(&self, principal: Principal, id: TodoId) -> Vec<TodoRevision> {
+        let mut revisions: Vec<TodoRevision> = self
+            .store
+            .borrow()
+            .range((principal, id, 0)..=(principal, id, Revision::MAX))
+            .map(|(_, revision)| revision)
+            .collect();
+        revisions.reverse();
+        revisions
+    }
+
+    /// Retrieves a previous version of a Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `revision` - The revision number.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the Todo item as it was before the given revision, otherwise None.
+    pub(crate) fn get_revision(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        revision: Revision,
+    ) -> Option<Todo> {
+        self.store
+            .borrow()
+            .get(&(principal, id, revision))
+            .map(|revision| revision.todo)
+    }
+
+    /// Drops the oldest versions of every Todo item of a principal beyond a limit,
+    /// such as after the principal lowered the number of versions to keep.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `max_revisions` - The number of previous versions to keep for each Todo item.
+    ///
+    /// # Returns
+    ///
+    /// The number of versions dropped.
+    pub(crate) fn trim(&self, principal: Principal, max_revisions: usize) -> u32 {
+        let mut kept = 0;
+        let mut current = None;
+        let expired: Vec<_> = self
+            .store
+            .borrow()
+            .range((principal, TodoId::MIN, 0)..=(principal, TodoId::MAX, Revision::MAX))
+            .rev()
+            .map(|(key, _)| key)
+            .filter(|(_, id, _)| {
+                if current != Some(*id) {
+                    current = Some(*id);
+                    kept = 0;
+                }
+                kept += 1;
+                kept > max_revisions
+            })
+            .collect();
+        let mut store = self.store.borrow_mut();
+        for key in &expired {
+            store.remove(key);
+        }
+        expired.len() as u32
+    }
+
+    /// Returns the number of the latest revision of a Todo item, or 0 if it was never changed.
+    fn last_revision(&self, principal: Principal, id: TodoId) -> Revision {
+        self.store
+            .borrow()
+            .iter_upper_bound(&(principal, id, Revision::MAX))
+            .next()
+            .filter(|((owner, todo_id, _), _)| *owner == principal && *todo_id == id)
+            .map_or(0, |(_, revision)| revision.revision)
+    }
+
+    /// Moves the previous versions of a principal's Todo items to their new identifiers.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        rewrite_entries(
+            self.store,
+            (principal, TodoId::MIN, 0)..=(principal, TodoId::MAX, Revision::MAX),
+            |(principal, id, revision), mut todo_revision| {
+                let id = renumbered(ids, id);
+                todo_revision.todo.id = id;
+                ((principal, id, revision), todo_revision)
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::Priority;
+
+    fn new_history_store() -> RefCell<HistoryStore<DefaultMemoryImpl>> {
+        RefCell::new(HistoryStore::new(DefaultMemoryImpl::default()))
+    }
+
+    #[test]
+    fn test_keeps_latest_revisions() {
+        let store = new_history_store();
+        let wrapper = HistoryStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let mut todo = Todo::new(1, "Version 0".to_string(), Priority::Medium);
+        for version in 1..=25 {
+            let before = todo.clone();
+            todo.description = format!("Version {version}");
+            let change = TodoChange {
+                principal,
+                before: Some(before),
+                after: Some(todo.clone()),
+            };
+            wrapper.record_change(&change, version, MAX_REVISIONS);
+        }
+
+        let revisions = wrapper.list_revisions(principal, 1);
+        assert_eq!(revisions.len(), MAX_REVISIONS);
+        assert_eq!(revisions[0].revision, 25);
+        assert_eq!(revisions[0].todo.description, "Version 24");
+        assert_eq!(revisions[MAX_REVISIONS - 1].revision, 6);
+        assert_eq!(wrapper.get_revision(principal, 1, 5), None);
+        assert_eq!(
+            wrapper.get_revision(principal, 1, 6).unwrap().description,
+            "Version 5"
+        );
+    }
+
+    #[test]
+    fn test_ignores_unchanged_items_and_discards_removed_items() {
+        let store = new_history_store();
+        let wrapper = HistoryStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let todo = Todo::new(1, "Buy milk".to_string(), Priority::Medium);
+        let renamed = Todo {
+            description: "Buy oat milk".to_string(),
+            ..todo.clone()
+        };
+
+        wrapper.record_change(
+            &TodoChange {
+                principal,
+                before: Some(todo.clone()),
+                after: Some(todo),
+            },
+            1,
+            MAX_REVISIONS,
+        );
+        assert!(wrapper.list_revisions(principal, 1).is_empty());
+
+        wrapper.record_change(
+            &TodoChange {
+                principal,
+                before: Some(Todo::new(1, "Buy milk".to_string(), Priority::Medium)),
+                after: Some(renamed.clone()),
+            },
+            2,
+            MAX_REVISIONS,
+        );
+        assert_eq!(wrapper.list_revisions(principal, 1).len(), 1);
+
+        wrapper.record_change(
+            &TodoChange {
+                principal,
+                before: Some(renamed),
+                after: None,
+            },
+            3,
+            MAX_REVISIONS,
+        );
+        assert!(wrapper.list_revisions(principal, 1).is_empty());
+    }
+
+    #[test]
+    fn test_trim() {
+        let store = new_history_store();
+        let wrapper = HistoryStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        for id in 1..=2 {
+            let mut todo = Todo::new(id, "Version 0".to_string(), Priority::Medium);
+            for version in 1..=5 {
+                let before = todo.clone();
+                todo.description = format!("Version {version}");
+                let change = TodoChange {
+                    principal,
+                    before: Some(before),
+                    after: Some(todo.clone()),
+                };
+                wrapper.record_change(&change, version, MAX_REVISIONS);
+            }
+        }
+
+        assert_eq!(wrapper.trim(principal, 2), 6);
+        for id in 1..=2 {
+            let revisions = wrapper.list_revisions(principal, id);
+            assert_eq!(
+                revisions.iter().map(|r| r.revision).collect::<Vec<_>>(),
+                vec![5, 4]
+            );
+        }
+        assert_eq!(wrapper.trim(principal, 2), 0);
+    }
+
+    #[test]
+    fn test_decodes_revision_of_older_todo_shape() {
+        #[derive(CandidType)]
+        struct OldTodo {
+            id: TodoId,
+            description: String,
+            is_completed: bool,
+            priority: Priority,
+            tags: Vec<String>,
+        }
+        #[derive(CandidType)]
+        struct OldRevision {
+            revision: Revision,
+            replaced_at: u64,
+            todo: OldTodo,
+        }
+        let old = OldRevision {
+            revision: 3,
+            replaced_at: 7,
+            todo: OldTodo {
+                id: 1,
+                description: "Old".to_string(),
+                is_completed: true,
+                priority: Priority::High,
+                tags: vec!["home".to_string()],
+            },
+        };
+
+        let revision = TodoRevision::from_bytes(Cow::Owned(Encode!(&old).unwrap()));
+
+        assert_eq!((revision.revision, revision.replaced_at), (3, 7));
+        assert_eq!(revision.todo.description, "Old");
+        assert!(revision.todo.is_completed);
+        assert_eq!(revision.todo.tags, vec!["home".to_string()]);
+        assert!(!revision.todo.escalated);
+    }
+}
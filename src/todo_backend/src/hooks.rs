@@ -0,0 +1,449 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    errors::Error,
+    todo::{CreateTodo, Priority},
+};
+
+/// Prefix of the routes receiving events from external services, followed by the key of the integration.
+pub(crate) const HOOKS_PREFIX: &str = "/hooks/";
+
+/// Header carrying the HMAC-SHA256 signature of the body, as `sha256=<hex>`.
+pub(crate) const SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+
+/// Header naming the kind of a GitHub event, such as `issues`.
+pub(crate) const GITHUB_EVENT_HEADER: &str = "X-GitHub-Event";
+
+/// Maximum number of integrations a principal may set up.
+pub(crate) const MAX_INTEGRATIONS: usize = 10;
+
+/// Number of bytes in a block of SHA-256, which HMAC pads its key to.
+const BLOCK_SIZE: usize = 64;
+
+/// Type alias for the identifier of an integration, unique per principal.
+pub(crate) type IntegrationId = u64;
+
+/// Type alias for the IntegrationStore, which is a StableBTreeMap with a tuple key of (Principal, IntegrationId) and value of Integration.
+pub(crate) type IntegrationStore<M> = StableBTreeMap<(Principal, IntegrationId), Integration, M>;
+
+/// Represents the external service an integration receives events from.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) enum IntegrationKind {
+    /// GitHub issue events; an issue assigned to `assignee`, or to anyone if None, becomes a Todo item.
+    GitHub { assignee: Option<String> },
+    /// A JSON body with the fields of a Todo item, as taken by `create_todo`.
+    Generic,
+}
+
+/// Represents how the Todo items created by an integration are filed.
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct IntegrationMapping {
+    /// Project of the Todo items, unless the event names one.
+    pub(crate) project: Option<String>,
+    /// Tags added to the Todo items.
+    pub(crate) tags: Vec<String>,
+    /// Priority of the Todo items, unless the event names one.
+    pub(crate) priority: Option<Priority>,
+}
+
+/// Represents an integration of a principal with an external service.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Integration {
+    /// The identifier of the integration.
+    pub(crate) id: IntegrationId,
+    /// The service events are received from.
+    pub(crate) kind: IntegrationKind,
+    /// How the created Todo items are filed.
+    pub(crate) mapping: IntegrationMapping,
+    /// The secret the service signs its events with.
+    pub(crate) secret: String,
+}
+
+impl Storable for Integration {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Represents an integration as shown to its owner, without its secret.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct IntegrationInfo {
+    /// The identifier of the integration.
+    pub(crate) id: IntegrationId,
+    /// The service events are received from.
+    pub(crate) kind: IntegrationKind,
+    /// How the created Todo items are filed.
+    pub(crate) mapping: IntegrationMapping,
+    /// The path the service posts its events to.
+    pub(crate) path: String,
+}
+
+/// Represents a newly set up integration.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct NewIntegration {
+    /// The integration.
+    pub(crate) integration: IntegrationInfo,
+    /// The secret to configure in the service, shown only once.
+    pub(crate) secret: String,
+}
+
+/// Represents a Todo item requested by an event.
+#[derive(Clone, Debug)]
+pub(crate) struct HookTodo {
+    /// The identifier of the item in the external service, so that repeated events create a single Todo item.
+    pub(crate) external_id: Option<String>,
+    /// The fields of the Todo item, filed according to the mapping.
+    pub(crate) request: CreateTodo,
+}
+
+/// Wrapper around the IntegrationStore to provide additional functionality.
+pub(crate) struct IntegrationStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<IntegrationStore<M>>,
+}
+
+impl<M: Memory> IntegrationStoreWrapper<'_, M> {
+    /// Sets up an integration for a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The identifier of the integration, never given to an integration before.
+    /// * `kind` - The service events are received from.
+    /// * `mapping` - The validated mapping of the created Todo items.
+    /// * `random` - Random bytes making up the secret.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the integration with its secret, or an Error if too many integrations exist.
+    pub(crate) fn create(
+        &self,
+        principal: Principal,
+        id: IntegrationId,
+        kind: IntegrationKind,
+        mapping: IntegrationMapping,
+        random: &[u8],
+    ) -> Result<NewIntegration, Error> {
+        if self.list(principal).len() >= MAX_INTEGRATIONS {
+            return Err(Error::QuotaExceeded(Some(format!(
+                "At most {MAX_INTEGRATIONS} integrations can be set up"
+            ))));
+        }
+        let integration = Integration {
+            id,
+            kind,
+            mapping,
+            secret: random.iter().map(|byte| format!("{byte:02x}")).collect(),
+        };
+        self.store
+            .borrow_mut()
+            .insert((principal, id), integration.clone());
+        Ok(NewIntegration {
+            secret: integration.secret.clone(),
+            integration: info(principal, integration),
+        })
+    }
+
+    /// Lists the integrations of a principal, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn list(&self, principal: Principal) -> Vec<IntegrationInfo> {
+        self.store
+            .borrow()
+            .range((principal, IntegrationId::MIN)..=(principal, IntegrationId::MAX))
+            .map(|(_, integration)| info(principal, integration))
+            .collect()
+    }
+
+    /// Removes an integration, after which events posted to it are rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The identifier of the integration.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if the integration is not found.
+    pub(crate) fn delete(&self, principal: Principal, id: IntegrationId) -> Result<(), Error> {
+        self.store
+            .borrow_mut()
+            .remove(&(principal, id))
+            .map(|_| ())
+            .ok_or(Error::NotFound)
+    }
+
+    /// Finds the integration events are posted to at a path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the request, such as `/hooks/<principal>.<id>`.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the principal and the integration, or None if the path names no integration.
+    pub(crate) fn find(&self, path: &str) -> Option<(Principal, Integration)> {
+        let (principal, id) = path.strip_prefix(HOOKS_PREFIX)?.split_once('.')?;
+        let principal = Principal::from_text(principal).ok()?;
+        let integration = self.store.borrow().get(&(principal, id.parse().ok()?))?;
+        Some((principal, integration))
+    }
+}
+
+/// Describes an integration without its secret.
+///
+/// # Arguments
+///
+/// * `principal` - The principal owning the integration.
+/// * `integration` - The integration.
+fn info(principal: Principal, integration: Integration) -> IntegrationInfo {
+    IntegrationInfo {
+        path: format!("{HOOKS_PREFIX}{principal}.{}", integration.id),
+        id: integration.id,
+        kind: integration.kind,
+        mapping: integration.mapping,
+    }
+}
+
+/// Computes the HMAC-SHA256 of a message.
+///
+/// # Arguments
+///
+/// * `key` - The secret key.
+/// * `message` - The message.
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let padded = |pad: u8| block.map(|byte| byte ^ pad);
+    let inner = Sha256::new()
+        .chain_update(padded(0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(padded(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+/// Checks the signature an external service sent with an event.
+///
+/// # Arguments
+///
+/// * `secret` - The secret of the integration.
+/// * `body` - The body of the request.
+/// * `signature` - The value of the signature header, as `sha256=<hex>`.
+///
+/// # Returns
+///
+/// Whether the body was signed with the secret.
+pub(crate) fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Some(hex) = signature.strip_prefix("sha256=") else {
+        return false;
+    };
+    let expected: String = hmac_sha256(secret.as_bytes(), body)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    // Compares every byte, so the time taken does not reveal how much of a forged signature is right.
+    expected.len() == hex.len()
+        && expected
+            .bytes()
+            .zip(hex.to_ascii_lowercase().bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+/// Turns an event into the Todo item it requests.
+///
+/// # Arguments
+///
+/// * `integration` - The integration the event was posted to.
+/// * `event` - The kind of event named in its headers, if any.
+/// * `body` - The JSON body of the event.
+///
+/// # Returns
+///
+/// A Result containing the requested Todo item, None if the event requests none, or an Error if the body is malformed.
+pub(crate) fn todo_for_event(
+    integration: &Integration,
+    event: Option<&str>,
+    body: &[u8],
+) -> Result<Option<HookTodo>, Error> {
+    let invalid = |error: serde_json::Error| Error::InvalidInput(format!("Invalid event: {error}"));
+    let todo = match &integration.kind {
+        IntegrationKind::GitHub { assignee } => {
+            if event != Some("issues") {
+                return Ok(None);
+            }
+            let payload: serde_json::Value = serde_json::from_slice(body).map_err(invalid)?;
+            let login = payload["assignee"]["login"].as_str();
+            let assigned = payload["action"] == "assigned"
+                && assignee.as_deref().is_none_or(|assignee| {
+                    login.is_some_and(|login| login.eq_ignore_ascii_case(assignee))
+                });
+            let issue = &payload["issue"];
+            let (Some(title), Some(number), Some(repository)) = (
+                issue["title"].as_str(),
+                issue["number"].as_u64(),
+                payload["repository"]["full_name"].as_str(),
+            ) else {
+                return Ok(None);
+            };
+            if !assigned {
+                return Ok(None);
+            }
+            HookTodo {
+                external_id: Some(format!("github:{repository}#{number}")),
+                request: CreateTodo {
+                    description: title.to_string(),
+                    notes: issue["html_url"].as_str().map(str::to_string),
+                    ..Default::default()
+                },
+            }
+        }
+        IntegrationKind::Generic => HookTodo {
+            external_id: None,
+            request: serde_json::from_slice(body).map_err(invalid)?,
+        },
+    };
+    Ok(Some(file(todo, &integration.mapping)))
+}
+
+/// Files a requested Todo item according to the mapping of its integration.
+///
+/// # Arguments
+///
+/// * `todo` - The requested Todo item.
+/// * `mapping` - The mapping of the integration.
+fn file(mut todo: HookTodo, mapping: &IntegrationMapping) -> HookTodo {
+    let request = &mut todo.request;
+    request.project = request.project.take().or_else(|| mapping.project.clone());
+    request.priority = request.priority.or(mapping.priority);
+    let mut tags = request.tags.take().unwrap_or_default();
+    for tag in &mapping.tags {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    request.tags = Some(tags);
+    todo
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    fn github(assignee: Option<&str>) -> Integration {
+        Integration {
+            id: 1,
+            kind: IntegrationKind::GitHub {
+                assignee: assignee.map(str::to_string),
+            },
+            mapping: IntegrationMapping {
+                project: Some("Work".to_string()),
+                tags: vec!["github".to_string()],
+                priority: None,
+            },
+            secret: "secret".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        // Test case 2 of RFC 4231.
+        let mac: String = hmac_sha256(b"Jefe", b"what do ya want for nothing?")
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        assert_eq!(
+            mac,
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        let signature = format!("sha256={mac}");
+        assert!(verify_signature(
+            "Jefe",
+            b"what do ya want for nothing?",
+            &signature
+        ));
+        assert!(!verify_signature(
+            "Jefe",
+            b"what do ya want for something?",
+            &signature
+        ));
+        assert!(!verify_signature(
+            "Jefe",
+            b"what do ya want for nothing?",
+            &mac
+        ));
+    }
+
+    #[test]
+    fn test_github_issue_assigned() {
+        let body = br#"{
+            "action": "assigned",
+            "assignee": {"login": "Octocat"},
+            "issue": {"number": 7, "title": "Fix login", "html_url": "https://github.com/o/r/issues/7"},
+            "repository": {"full_name": "o/r"}
+        }"#;
+        let todo = todo_for_event(&github(Some("octocat")), Some("issues"), body)
+            .unwrap()
+            .unwrap();
+        assert_eq!(todo.external_id.as_deref(), Some("github:o/r#7"));
+        assert_eq!(todo.request.description, "Fix login");
+        assert_eq!(todo.request.project.as_deref(), Some("Work"));
+        assert_eq!(todo.request.tags, Some(vec!["github".to_string()]));
+
+        assert!(todo_for_event(&github(Some("hubot")), Some("issues"), body)
+            .unwrap()
+            .is_none());
+        assert!(todo_for_event(&github(None), Some("ping"), b"{}")
+            .unwrap()
+            .is_none());
+        assert!(todo_for_event(&github(None), Some("issues"), b"not json").is_err());
+    }
+
+    #[test]
+    fn test_find() {
+        let store = RefCell::new(IntegrationStore::new(DefaultMemoryImpl::default()));
+        let wrapper = IntegrationStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let created = wrapper
+            .create(
+                principal,
+                1,
+                IntegrationKind::Generic,
+                IntegrationMapping::default(),
+                &[0xab],
+            )
+            .unwrap();
+        assert_eq!(created.secret, "ab");
+
+        let (owner, integration) = wrapper.find(&created.integration.path).unwrap();
+        assert_eq!(owner, principal);
+        assert_eq!(integration.id, created.integration.id);
+        assert!(wrapper
+            .find(&format!("{HOOKS_PREFIX}{principal}.2"))
+            .is_none());
+
+        assert!(wrapper.delete(principal, 1).is_ok());
+        assert!(wrapper.find(&created.integration.path).is_none());
+    }
+}
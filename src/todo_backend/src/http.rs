@@ -0,0 +1,123 @@
+use candid::{CandidType, Deserialize};
+use ic_cdk::api::management_canister::http_request::{self as outcall, TransformContext};
+
+/// Name of the query transforming the responses of HTTPS outcalls so that all replicas agree on them.
+const TRANSFORM_METHOD: &str = "transform_outcall_response";
+
+/// Represents an HTTP request received through the HTTP gateway.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub(crate) struct HttpRequest {
+    /// The HTTP method, e.g. `POST`.
+    pub(crate) method: String,
+    /// The request URL, consisting of the path and query string.
+    pub(crate) url: String,
+    /// The request headers.
+    pub(crate) headers: Vec<(String, String)>,
+    /// The request body.
+    pub(crate) body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// Returns the path of the request URL, without the query string.
+    pub(crate) fn path(&self) -> &str {
+        self.url.split('?').next().unwrap_or_default()
+    }
+
+    /// Returns the value of a query string parameter, taken as is without percent-decoding.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the parameter.
+    pub(crate) fn query_param(&self, name: &str) -> Option<&str> {
+        self.url
+            .split_once('?')?
+            .1
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the value of a request header, matching its name case-insensitively.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the header.
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Represents the response to an HTTP request received through the HTTP gateway.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct HttpResponse {
+    /// The HTTP status code.
+    pub(crate) status_code: u16,
+    /// The response headers.
+    pub(crate) headers: Vec<(String, String)>,
+    /// The response body.
+    pub(crate) body: Vec<u8>,
+    /// Whether the gateway should repeat the request as an update call.
+    pub(crate) upgrade: Option<bool>,
+}
+
+impl HttpResponse {
+    /// Creates a plain text response.
+    ///
+    /// # Arguments
+    ///
+    /// * `status_code` - The HTTP status code.
+    /// * `text` - The response body.
+    pub(crate) fn text(status_code: u16, text: &str) -> Self {
+        Self {
+            status_code,
+            headers: vec![("Content-Type".to_string(), "text/plain".to_string())],
+            body: text.as_bytes().to_vec(),
+            upgrade: None,
+        }
+    }
+
+    /// Creates a JSON response.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The serialized JSON body.
+    pub(crate) fn json(body: Vec<u8>) -> Self {
+        Self {
+            status_code: 200,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body,
+            upgrade: None,
+        }
+    }
+
+    /// Creates a response asking the gateway to repeat the request as an update call.
+    pub(crate) fn upgrade() -> Self {
+        Self {
+            upgrade: Some(true),
+            ..Self::text(200, "")
+        }
+    }
+}
+
+/// Returns the transform to attach to HTTPS outcalls whose response only matters for its status.
+/// Only available inside a canister, since it refers to the canister id.
+pub(crate) fn status_only_transform() -> TransformContext {
+    TransformContext::from_name(TRANSFORM_METHOD.to_string(), Vec::new())
+}
+
+/// Strips the response of an HTTPS outcall down to its status, so that the responses seen by the replicas agree.
+///
+/// # Arguments
+///
+/// * `response` - The raw response.
+pub(crate) fn strip_response(response: outcall::HttpResponse) -> outcall::HttpResponse {
+    outcall::HttpResponse {
+        status: response.status,
+        headers: Vec::new(),
+        body: Vec::new(),
+    }
+}
@@ -0,0 +1,377 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{errors::Error, todo::CreateTodo};
+
+/// Maximum number of Todo items a single import may stage, as many as `commit_import` creates within one message.
+pub(crate) const MAX_IMPORT_ITEMS: u32 = 10_000;
+
+/// Maximum number of Todo items sent in a single chunk.
+pub(crate) const MAX_CHUNK_ITEMS: usize = 1_000;
+
+/// Maximum number of imports a principal may have staging or committing at once.
+pub(crate) const MAX_OPEN_IMPORTS: usize = 3;
+
+/// Maximum number of committed imports kept per principal for their status; older ones are removed as new imports begin.
+pub(crate) const MAX_FINISHED_IMPORTS: usize = 10;
+
+/// Type alias for the handle of an import, unique per principal.
+pub(crate) type ImportHandle = u64;
+
+/// Type alias for the ImportStore, which is a StableBTreeMap with a tuple key of (Principal, ImportHandle) and value of Import.
+pub(crate) type ImportStore<M> = StableBTreeMap<(Principal, ImportHandle), Import, M>;
+
+/// Type alias for the StagedTodoStore, which is a StableBTreeMap with a tuple key of (Principal, ImportHandle, position)
+/// and value of CreateTodo, holding the Todo items of imports until they are committed.
+pub(crate) type StagedTodoStore<M> = StableBTreeMap<(Principal, ImportHandle, u32), CreateTodo, M>;
+
+/// Represents the stage an import is in.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ImportState {
+    /// Chunks are being staged; none of the Todo items are visible yet.
+    Staging,
+    /// Every Todo item was created, within a single message.
+    Committed,
+}
+
+/// Represents a bulk import of Todo items sent in chunks across many messages.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Import {
+    /// The handle of the import.
+    pub(crate) handle: ImportHandle,
+    /// The stage the import is in.
+    pub(crate) state: ImportState,
+    /// Number of Todo items staged.
+    pub(crate) items: u32,
+    /// Time the import began, in nanoseconds since the Unix epoch.
+    pub(crate) started_at: u64,
+    /// Time the Todo items were created, in nanoseconds since the Unix epoch, or None until then.
+    pub(crate) committed_at: Option<u64>,
+}
+
+impl Storable for Import {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl Storable for CreateTodo {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Wrapper around the ImportStore to provide additional functionality.
+pub(crate) struct ImportStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<ImportStore<M>>,
+}
+
+impl<M: Memory> ImportStoreWrapper<'_, M> {
+    /// Begins an import, removing the oldest committed imports of the principal beyond `MAX_FINISHED_IMPORTS`.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the import, or an Error if the principal has too many open imports.
+    pub(crate) fn begin(&self, principal: Principal, now: u64) -> Result<Import, Error> {
+        let imports = self.list(principal);
+        let (committed, open): (Vec<Import>, Vec<Import>) = imports
+            .iter()
+            .cloned()
+            .partition(|import| import.state == ImportState::Committed);
+        if open.len() >= MAX_OPEN_IMPORTS {
+            return Err(Error::QuotaExceeded(Some(format!(
+                "At most {MAX_OPEN_IMPORTS} imports can be open at once"
+            ))));
+        }
+        let mut store = self.store.borrow_mut();
+        for import in committed
+            .iter()
+            .take((committed.len() + 1).saturating_sub(MAX_FINISHED_IMPORTS))
+        {
+            store.remove(&(principal, import.handle));
+        }
+        let handle = imports.last().map_or(1, |import| import.handle + 1);
+        let import = Import {
+            handle,
+            state: ImportState::Staging,
+            items: 0,
+            started_at: now,
+            committed_at: None,
+        };
+        store.insert((principal, handle), import.clone());
+        Ok(import)
+    }
+
+    /// Retrieves an import.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `handle` - The handle of the import.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the import if found, otherwise None.
+    pub(crate) fn get(&self, principal: Principal, handle: ImportHandle) -> Option<Import> {
+        self.store.borrow().get(&(principal, handle))
+    }
+
+    /// Reserves the positions of a chunk of Todo items in an import that is staging.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `handle` - The handle of the import.
+    /// * `count` - Number of Todo items in the chunk.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the position of the first Todo item of the chunk, or an Error if the import is not found,
+    /// no longer staging or would exceed `MAX_IMPORT_ITEMS`.
+    pub(crate) fn reserve(
+        &self,
+        principal: Principal,
+        handle: ImportHandle,
+        count: u32,
+    ) -> Result<u32, Error> {
+        let mut import = self.staging(principal, handle)?;
+        let first = import.items;
+        import.items = first
+            .checked_add(count)
+            .filter(|items| *items <= MAX_IMPORT_ITEMS)
+            .ok_or_else(|| {
+                Error::QuotaExceeded(Some(format!(
+                    "An import can stage at most {MAX_IMPORT_ITEMS} Todo items"
+                )))
+            })?;
+        self.save(principal, import);
+        Ok(first)
+    }
+
+    /// Retrieves an import that is staging.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `handle` - The handle of the import.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the import, or an Error if it is not found or no longer staging.
+    pub(crate) fn staging(
+        &self,
+        principal: Principal,
+        handle: ImportHandle,
+    ) -> Result<Import, Error> {
+        let import = self.get(principal, handle).ok_or(Error::NotFound)?;
+        if import.state != ImportState::Staging {
+            return Err(Error::Conflict(Some(
+                "Import is already committed".to_string(),
+            )));
+        }
+        Ok(import)
+    }
+
+    /// Stores the progress of an import.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `import` - The import.
+    pub(crate) fn save(&self, principal: Principal, import: Import) {
+        self.store
+            .borrow_mut()
+            .insert((principal, import.handle), import);
+    }
+
+    /// Removes an import.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `handle` - The handle of the import.
+    pub(crate) fn remove(&self, principal: Principal, handle: ImportHandle) {
+        self.store.borrow_mut().remove(&(principal, handle));
+    }
+
+    /// Lists the imports of a principal, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn list(&self, principal: Principal) -> Vec<Import> {
+        self.store
+            .borrow()
+            .range((principal, ImportHandle::MIN)..=(principal, ImportHandle::MAX))
+            .map(|(_, import)| import)
+            .collect()
+    }
+}
+
+/// Wrapper around the StagedTodoStore to provide additional functionality.
+pub(crate) struct StagedTodoStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<StagedTodoStore<M>>,
+}
+
+impl<M: Memory> StagedTodoStoreWrapper<'_, M> {
+    /// Stages a chunk of Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `handle` - The handle of the import.
+    /// * `first` - The position of the first Todo item of the chunk.
+    /// * `items` - The validated Todo items.
+    pub(crate) fn put(
+        &self,
+        principal: Principal,
+        handle: ImportHandle,
+        first: u32,
+        items: Vec<CreateTodo>,
+    ) {
+        let mut store = self.store.borrow_mut();
+        for (position, item) in (first..).zip(items) {
+            store.insert((principal, handle, position), item);
+        }
+    }
+
+    /// Retrieves every staged Todo item of an import, keeping them staged.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `handle` - The handle of the import.
+    ///
+    /// # Returns
+    ///
+    /// A vector of the staged Todo items, in order of position.
+    pub(crate) fn all(&self, principal: Principal, handle: ImportHandle) -> Vec<CreateTodo> {
+        self.store
+            .borrow()
+            .range((principal, handle, u32::MIN)..=(principal, handle, u32::MAX))
+            .map(|(_, item)| item)
+            .collect()
+    }
+
+    /// Removes every staged Todo item of an import.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `handle` - The handle of the import.
+    pub(crate) fn remove_all(&self, principal: Principal, handle: ImportHandle) {
+        let keys: Vec<_> = self
+            .store
+            .borrow()
+            .range((principal, handle, u32::MIN)..=(principal, handle, u32::MAX))
+            .map(|(key, _)| key)
+            .collect();
+        let mut store = self.store.borrow_mut();
+        for key in keys {
+            store.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_stage_imports() {
+        let imports = RefCell::new(ImportStore::new(DefaultMemoryImpl::default()));
+        let imports = ImportStoreWrapper { store: &imports };
+        let staged = RefCell::new(StagedTodoStore::new(DefaultMemoryImpl::default()));
+        let staged = StagedTodoStoreWrapper { store: &staged };
+        let principal = Principal::anonymous();
+
+        let handle = imports.begin(principal, 5).unwrap().handle;
+        assert_eq!(handle, 1);
+        let item = |description: &str| CreateTodo {
+            description: description.to_string(),
+            ..CreateTodo::default()
+        };
+        assert_eq!(imports.reserve(principal, handle, 2).unwrap(), 0);
+        staged.put(principal, handle, 0, vec![item("a"), item("b")]);
+        assert_eq!(imports.reserve(principal, handle, 1).unwrap(), 2);
+        staged.put(principal, handle, 2, vec![item("c")]);
+        assert!(matches!(
+            imports.reserve(principal, handle, MAX_IMPORT_ITEMS),
+            Err(Error::QuotaExceeded(_))
+        ));
+        assert_eq!(imports.get(principal, handle).unwrap().items, 3);
+
+        let descriptions: Vec<_> = staged
+            .all(principal, handle)
+            .into_iter()
+            .map(|item| item.description)
+            .collect();
+        assert_eq!(descriptions, vec!["a", "b", "c"]);
+        imports.begin(principal, 6).unwrap();
+        imports.begin(principal, 7).unwrap();
+        assert!(matches!(
+            imports.begin(principal, 8),
+            Err(Error::QuotaExceeded(_))
+        ));
+
+        let mut import = imports.staging(principal, handle).unwrap();
+        import.state = ImportState::Committed;
+        imports.save(principal, import);
+        assert!(matches!(
+            imports.reserve(principal, handle, 1),
+            Err(Error::Conflict(_))
+        ));
+        assert!(imports.begin(principal, 8).is_ok());
+        staged.remove_all(principal, handle);
+        assert!(staged.store.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_decodes_import_stored_with_progress() {
+        #[derive(CandidType)]
+        struct PreviousImport {
+            handle: ImportHandle,
+            state: ImportState,
+            items: u32,
+            created: u32,
+            failed: Option<u32>,
+            started_at: u64,
+            committed_at: Option<u64>,
+        }
+        let bytes = Encode!(&PreviousImport {
+            handle: 2,
+            state: ImportState::Committed,
+            items: 10,
+            created: 10,
+            failed: None,
+            started_at: 5,
+            committed_at: Some(6),
+        })
+        .unwrap();
+
+        let import = Import::from_bytes(Cow::Owned(bytes));
+        assert_eq!(import.items, 10);
+        assert_eq!(import.committed_at, Some(6));
+    }
+}
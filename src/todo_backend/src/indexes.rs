@@ -0,0 +1,504 @@
+use std::{cell::RefCell, ops::RangeInclusive};
+
+use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::{Memory, StableBTreeMap, Storable};
+
+use crate::{
+    paginator::Paginator,
+    renumbering::{renumbered, rewrite_entries, Renumbering},
+    store::TodoChange,
+    todo::{Todo, TodoId},
+};
+
+/// Type alias for an index of Todo items, which is a StableBTreeMap keyed by (Principal, position, TodoId),
+/// where the position of a Todo item is derived from it by the index.
+pub(crate) type TodoIndex<P, M> = StableBTreeMap<(Principal, P, TodoId), (), M>;
+
+/// Type alias for the ModifiedIndex, which is a TodoIndex positioning every Todo item in the Todo store
+/// by the time it was last touched.
+pub(crate) type ModifiedIndex<M> = TodoIndex<u64, M>;
+
+/// Type alias for the CompletionIndex, which is a TodoIndex positioning every Todo item in the Todo store
+/// by whether it is completed.
+pub(crate) type CompletionIndex<M> = TodoIndex<bool, M>;
+
+/// Type alias for the rank of an open Todo item: the rank of its priority followed by its due date
+/// in big-endian bytes, so that byte order matches ranking order.
+pub(crate) type Rank = [u8; 9];
+
+/// Type alias for the RankIndex, which is a TodoIndex positioning every open Todo item in the Todo store
+/// by its Rank. Within a rank, older Todo items come first as identifiers only grow.
+pub(crate) type RankIndex<M> = TodoIndex<Rank, M>;
+
+/// Type alias for the DueIndex, which is a TodoIndex positioning every open Todo item in the Todo store
+/// by its due date. Todo items without a due date are positioned at `NO_DUE_DATE`.
+pub(crate) type DueIndex<M> = TodoIndex<u64, M>;
+
+/// Position of open Todo items without a due date in the DueIndex, after every due date.
+const NO_DUE_DATE: u64 = u64::MAX;
+
+/// Represents the position of a Todo item within an index, bounded by the lowest and highest positions.
+pub(crate) trait Position: Storable + Ord + Clone {
+    /// The lowest position.
+    const FIRST: Self;
+    /// The highest position.
+    const LAST: Self;
+}
+
+impl Position for u64 {
+    const FIRST: Self = u64::MIN;
+    const LAST: Self = u64::MAX;
+}
+
+impl Position for bool {
+    const FIRST: Self = false;
+    const LAST: Self = true;
+}
+
+impl Position for Rank {
+    const FIRST: Self = [0; 9];
+    const LAST: Self = [u8::MAX; 9];
+}
+
+/// Represents how many open Todo items of a principal fall due in each period.
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct DueSummary {
+    /// Number of Todo items whose due date has passed.
+    pub(crate) overdue: u32,
+    /// Number of Todo items due during the rest of today.
+    pub(crate) today: u32,
+    /// Number of Todo items due tomorrow.
+    pub(crate) tomorrow: u32,
+    /// Number of Todo items due after tomorrow, within the current week.
+    pub(crate) this_week: u32,
+    /// Number of Todo items due after the current week.
+    pub(crate) later: u32,
+    /// Number of Todo items without a due date.
+    pub(crate) no_date: u32,
+}
+
+/// Returns the rank of a Todo item, where higher priorities come first, then sooner due dates,
+/// with Todo items without a due date last.
+///
+/// # Arguments
+///
+/// * `todo` - The Todo item.
+fn rank_of(todo: &Todo) -> Rank {
+    let mut rank = [0; 9];
+    rank[0] = todo.priority.rank();
+    rank[1..].copy_from_slice(&todo.due_date.unwrap_or(u64::MAX).to_be_bytes());
+    rank
+}
+
+/// Wrapper around a TodoIndex to provide additional functionality.
+pub(crate) struct TodoIndexWrapper<'a, P: Position, M: Memory> {
+    pub store: &'a RefCell<TodoIndex<P, M>>,
+    /// Returns the position of a Todo item, or None if the index leaves it out.
+    pub position: fn(&Todo) -> Option<P>,
+}
+
+impl<P: Position, M: Memory> TodoIndexWrapper<'_, P, M> {
+    /// Moves a Todo item to its new position after a change.
+    ///
+    /// # Arguments
+    ///
+    /// * `change` - The change to a Todo item.
+    pub(crate) fn record_change(&self, change: &TodoChange) {
+        if let Some(before) = &change.before {
+            self.remove(change.principal, before);
+        }
+        if let Some(after) = &change.after {
+            self.insert(change.principal, after);
+        }
+    }
+
+    /// Adds a Todo item to the index, unless the index leaves it out.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the Todo item.
+    /// * `todo` - The Todo item.
+    pub(crate) fn insert(&self, principal: Principal, todo: &Todo) {
+        if let Some(position) = (self.position)(todo) {
+            self.store
+                .borrow_mut()
+                .insert((principal, position, todo.id), ());
+        }
+    }
+
+    /// Removes a Todo item from the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the Todo item.
+    /// * `todo` - The Todo item as it was last indexed.
+    pub(crate) fn remove(&self, principal: Principal, todo: &Todo) {
+        if let Some(position) = (self.position)(todo) {
+            self.store
+                .borrow_mut()
+                .remove(&(principal, position, todo.id));
+        }
+    }
+
+    /// Lists the Todo items of a principal within a range of positions in order of position, or in reverse
+    /// with a descending paginator.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `positions` - The positions of the Todo items.
+    /// * `paginator` - The paginator for controlling the list output.
+    ///
+    /// # Returns
+    ///
+    /// A vector of Todo item identifiers.
+    fn list_between(
+        &self,
+        principal: Principal,
+        positions: RangeInclusive<P>,
+        paginator: Paginator,
+    ) -> Vec<TodoId> {
+        let (first, last) = positions.into_inner();
+        let store = self.store.borrow();
+        let keys = store
+            .range((principal, first, TodoId::MIN)..=(principal, last, TodoId::MAX))
+            .map(|((_, _, id), _)| id);
+        let keys: Box<dyn Iterator<Item = _>> = if paginator.is_descending() {
+            Box::new(keys.rev())
+        } else {
+            Box::new(keys)
+        };
+        keys.skip(paginator.skip())
+            .take(paginator.limit())
+            .collect()
+    }
+
+    /// Moves the index entries of a principal to the new identifiers of their Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        rewrite_entries(
+            self.store,
+            (principal, P::FIRST, TodoId::MIN)..=(principal, P::LAST, TodoId::MAX),
+            |(principal, position, id), ()| ((principal, position, renumbered(ids, id)), ()),
+        );
+    }
+}
+
+impl<'a, M: Memory> TodoIndexWrapper<'a, u64, M> {
+    /// Wraps the ModifiedIndex, which positions every Todo item by the time it was last touched.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The ModifiedIndex.
+    pub(crate) fn modified(store: &'a RefCell<ModifiedIndex<M>>) -> Self {
+        Self {
+            store,
+            position: |todo| Some(todo.last_touched()),
+        }
+    }
+
+    /// Wraps the DueIndex, which positions every open Todo item by its due date.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The DueIndex.
+    pub(crate) fn due(store: &'a RefCell<DueIndex<M>>) -> Self {
+        Self {
+            store,
+            position: |todo| (!todo.is_completed).then_some(todo.due_date.unwrap_or(NO_DUE_DATE)),
+        }
+    }
+
+    /// Lists the Todo items of a principal created or updated after a given time, least recently
+    /// modified first, or most recently modified first with a descending paginator.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `since` - The time, in nanoseconds since the Unix epoch; Todo items last modified at it are left out.
+    /// * `paginator` - The paginator for controlling the list output.
+    ///
+    /// # Returns
+    ///
+    /// A vector of Todo item identifiers.
+    pub(crate) fn modified_since(
+        &self,
+        principal: Principal,
+        since: u64,
+        paginator: Paginator,
+    ) -> Vec<TodoId> {
+        let Some(from) = since.checked_add(1) else {
+            return Vec::new();
+        };
+        self.list_between(principal, from..=u64::MAX, paginator)
+    }
+
+    /// Counts the open Todo items of a principal by the period they fall due in, without reading them.
+    /// Each period is counted over its own range of due dates, so no due date is compared.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `boundaries` - The current time and the starts of tomorrow, of the day after tomorrow and of
+    ///   the next week, in nanoseconds since the Unix epoch and in ascending order.
+    ///
+    /// # Returns
+    ///
+    /// The number of open Todo items in each period.
+    pub(crate) fn summary(&self, principal: Principal, boundaries: [u64; 4]) -> DueSummary {
+        let [now, tomorrow, day_after_tomorrow, next_week] = boundaries;
+        let count = |from: u64, to: u64| {
+            self.store
+                .borrow()
+                .range((principal, from, TodoId::MIN)..(principal, to, TodoId::MIN))
+                .count() as u32
+        };
+        DueSummary {
+            overdue: count(u64::MIN, now),
+            today: count(now, tomorrow),
+            tomorrow: count(tomorrow, day_after_tomorrow),
+            this_week: count(day_after_tomorrow, next_week),
+            later: count(next_week, NO_DUE_DATE),
+            no_date: self
+                .store
+                .borrow()
+                .range(
+                    (principal, NO_DUE_DATE, TodoId::MIN)..=(principal, NO_DUE_DATE, TodoId::MAX),
+                )
+                .count() as u32,
+        }
+    }
+}
+
+impl<'a, M: Memory> TodoIndexWrapper<'a, bool, M> {
+    /// Wraps the CompletionIndex, which positions every Todo item by whether it is completed.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The CompletionIndex.
+    pub(crate) fn completion(store: &'a RefCell<CompletionIndex<M>>) -> Self {
+        Self {
+            store,
+            position: |todo| Some(todo.is_completed),
+        }
+    }
+
+    /// Lists the open or the completed Todo items of a principal in order of creation,
+    /// or in reverse with a descending paginator.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `completed` - Whether to list the completed rather than the open Todo items.
+    /// * `paginator` - The paginator for controlling the list output.
+    ///
+    /// # Returns
+    ///
+    /// A vector of Todo item identifiers.
+    pub(crate) fn list(
+        &self,
+        principal: Principal,
+        completed: bool,
+        paginator: Paginator,
+    ) -> Vec<TodoId> {
+        self.list_between(principal, completed..=completed, paginator)
+    }
+}
+
+impl<'a, M: Memory> TodoIndexWrapper<'a, Rank, M> {
+    /// Wraps the RankIndex, which positions every open Todo item by its Rank.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The RankIndex.
+    pub(crate) fn rank(store: &'a RefCell<RankIndex<M>>) -> Self {
+        Self {
+            store,
+            position: |todo| (!todo.is_completed).then(|| rank_of(todo)),
+        }
+    }
+
+    /// Lists the highest-ranked open Todo items of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `count` - The maximum number of Todo items to list.
+    ///
+    /// # Returns
+    ///
+    /// A vector of Todo item identifiers, highest priority first, then soonest due date, then oldest.
+    pub(crate) fn top(&self, principal: Principal, count: usize) -> Vec<TodoId> {
+        self.store
+            .borrow()
+            .range((principal, Rank::FIRST, TodoId::MIN)..=(principal, Rank::LAST, TodoId::MAX))
+            .map(|((_, _, id), _)| id)
+            .take(count)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::Priority;
+
+    #[test]
+    fn test_modified_since() {
+        let store = RefCell::new(ModifiedIndex::new(DefaultMemoryImpl::default()));
+        let wrapper = TodoIndexWrapper::modified(&store);
+        let principal = Principal::anonymous();
+        let todo = |id, updated_at| Todo {
+            created_at: 10,
+            updated_at,
+            ..Todo::new(id, "Task".to_string(), Priority::Low)
+        };
+        for id in 1..=3 {
+            wrapper.insert(principal, &todo(id, 10 * id));
+        }
+        wrapper.insert(Principal::management_canister(), &todo(4, 40));
+        wrapper.record_change(&TodoChange {
+            principal,
+            before: Some(todo(1, 10)),
+            after: Some(todo(1, 50)),
+        });
+        wrapper.record_change(&TodoChange {
+            principal,
+            before: Some(todo(3, 30)),
+            after: None,
+        });
+
+        assert_eq!(
+            wrapper.modified_since(principal, 10, Paginator::default()),
+            vec![2, 1]
+        );
+        assert_eq!(
+            wrapper.modified_since(principal, 0, Paginator::new(0, Some(1)).descending()),
+            vec![1]
+        );
+        assert!(wrapper
+            .modified_since(principal, 50, Paginator::default())
+            .is_empty());
+        assert!(wrapper
+            .modified_since(principal, u64::MAX, Paginator::default())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_lists_by_completion() {
+        let store = RefCell::new(CompletionIndex::new(DefaultMemoryImpl::default()));
+        let wrapper = TodoIndexWrapper::completion(&store);
+        let principal = Principal::anonymous();
+        for id in 1..=4 {
+            wrapper.insert(principal, &Todo::new(id, "Task".to_string(), Priority::Low));
+        }
+        wrapper.insert(
+            Principal::management_canister(),
+            &Todo::new(5, "Task".to_string(), Priority::Low),
+        );
+        let todo = Todo::new(2, "Task".to_string(), Priority::Low);
+        let mut completed = todo.clone();
+        completed.set_completed(true, 1);
+        wrapper.record_change(&TodoChange {
+            principal,
+            before: Some(todo),
+            after: Some(completed),
+        });
+
+        assert_eq!(
+            wrapper.list(principal, false, Paginator::default()),
+            vec![1, 3, 4]
+        );
+        assert_eq!(
+            wrapper.list(principal, false, Paginator::new(2, Some(2)).descending()),
+            vec![1]
+        );
+        assert_eq!(wrapper.list(principal, true, Paginator::default()), vec![2]);
+    }
+
+    #[test]
+    fn test_top_ranks_open_todos() {
+        let store = RefCell::new(RankIndex::new(DefaultMemoryImpl::default()));
+        let wrapper = TodoIndexWrapper::rank(&store);
+        let principal = Principal::anonymous();
+        let todo = |id, priority, due_date| Todo {
+            due_date,
+            ..Todo::new(id, "Task".to_string(), priority)
+        };
+        wrapper.insert(principal, &todo(1, Priority::Low, Some(5)));
+        wrapper.insert(principal, &todo(2, Priority::High, None));
+        wrapper.insert(principal, &todo(3, Priority::High, Some(u64::MAX - 1)));
+        wrapper.insert(principal, &todo(4, Priority::Medium, None));
+        wrapper.insert(principal, &todo(5, Priority::High, None));
+        wrapper.insert(
+            Principal::management_canister(),
+            &todo(6, Priority::High, Some(1)),
+        );
+        let mut completed = todo(4, Priority::Medium, None);
+        completed.set_completed(true, 1);
+        wrapper.record_change(&TodoChange {
+            principal,
+            before: Some(todo(4, Priority::Medium, None)),
+            after: Some(completed),
+        });
+
+        assert_eq!(wrapper.top(principal, 10), vec![3, 2, 5, 1]);
+        assert_eq!(wrapper.top(principal, 2), vec![3, 2]);
+    }
+
+    #[test]
+    fn test_summary_counts_open_todos_by_period() {
+        let store = RefCell::new(DueIndex::new(DefaultMemoryImpl::default()));
+        let wrapper = TodoIndexWrapper::due(&store);
+        let principal = Principal::anonymous();
+        let todo = |id, due_date| Todo {
+            due_date,
+            ..Todo::new(id, "Task".to_string(), Priority::Low)
+        };
+        let due_dates = [
+            Some(5),
+            Some(8),
+            Some(10),
+            Some(20),
+            Some(35),
+            Some(40),
+            None,
+            None,
+        ];
+        for (id, due_date) in (1..).zip(due_dates) {
+            wrapper.insert(principal, &todo(id, due_date));
+        }
+        wrapper.insert(Principal::management_canister(), &todo(9, Some(5)));
+        let mut completed = todo(1, Some(5));
+        completed.set_completed(true, 1);
+        wrapper.record_change(&TodoChange {
+            principal,
+            before: Some(todo(1, Some(5))),
+            after: Some(completed),
+        });
+        wrapper.record_change(&TodoChange {
+            principal,
+            before: Some(todo(7, None)),
+            after: Some(todo(7, Some(25))),
+        });
+
+        assert_eq!(
+            wrapper.summary(principal, [10, 20, 30, 40]),
+            DueSummary {
+                overdue: 1,
+                today: 1,
+                tomorrow: 2,
+                this_week: 1,
+                later: 1,
+                no_date: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::errors::Error;
+
+/// Maximum number of finished jobs kept for their status; older ones are removed as new jobs start.
+pub(crate) const MAX_FINISHED_JOBS: usize = 100;
+
+/// Type alias for the unique identifier of a job.
+pub(crate) type JobId = u64;
+
+/// Type alias for the position of the last item a job processed: a principal and, for jobs walking
+/// Todo items, the identifier of the Todo item.
+pub(crate) type JobCursor = (Principal, u64);
+
+/// Type alias for the JobStore, which is a StableBTreeMap with a key of JobId and value of Job.
+pub(crate) type JobStore<M> = StableBTreeMap<JobId, Job, M>;
+
+/// Represents a maintenance task that runs in slices across many messages.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum JobKind {
+    /// Adds every Todo item to the derived indexes, repairing entries missing from them.
+    Reindex,
+    /// Deletes the archived Todo items and previous versions that principals no longer keep.
+    Purge,
+    /// Sends the email reminders, Telegram announcements and weekly reviews that are due, a principal at a time.
+    /// A slice runs per maintenance round and makes a limited number of outcalls.
+    Deliver,
+}
+
+/// Represents whether a job is still running.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum JobState {
+    /// More slices follow.
+    Running,
+    /// Every item was processed.
+    Done,
+}
+
+/// Represents a job and its progress, which is kept across messages and upgrades.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Job {
+    /// Unique identifier for the job.
+    pub(crate) id: JobId,
+    /// The task the job performs.
+    pub(crate) kind: JobKind,
+    /// Whether the job is still running.
+    pub(crate) state: JobState,
+    /// The position of the last item processed, or None if the job has not processed any.
+    pub(crate) cursor: Option<JobCursor>,
+    /// Number of items processed.
+    pub(crate) processed: u64,
+    /// Number of slices run.
+    pub(crate) slices: u32,
+    /// Time the job started, in nanoseconds since the Unix epoch.
+    pub(crate) started_at: u64,
+    /// Time the job finished, in nanoseconds since the Unix epoch, or None while it is running.
+    pub(crate) finished_at: Option<u64>,
+}
+
+impl Storable for Job {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl Job {
+    /// Runs a slice of the job: processes items one at a time until none are left or the budget of the slice is spent.
+    ///
+    /// # Arguments
+    ///
+    /// * `exhausted` - Returns whether the slice spent its budget, such as its instructions; checked after each item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    /// * `step` - Processes the item following a position and returns the position of that item, or None if no item is left.
+    pub(crate) fn run_slice(
+        &mut self,
+        exhausted: impl Fn() -> bool,
+        now: u64,
+        mut step: impl FnMut(Option<JobCursor>) -> Option<JobCursor>,
+    ) {
+        if self.state == JobState::Done {
+            return;
+        }
+        self.slices += 1;
+        loop {
+            let Some(cursor) = step(self.cursor) else {
+                self.state = JobState::Done;
+                self.finished_at = Some(now);
+                return;
+            };
+            self.cursor = Some(cursor);
+            self.processed += 1;
+            if exhausted() {
+                return;
+            }
+        }
+    }
+}
+
+/// Wrapper around the JobStore to provide additional functionality.
+pub(crate) struct JobStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<JobStore<M>>,
+}
+
+impl<M: Memory> JobStoreWrapper<'_, M> {
+    /// Starts a job, removing the oldest finished jobs beyond `MAX_FINISHED_JOBS`.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The task the job performs.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the job, or an Error if a job of the same kind is running.
+    pub(crate) fn start(&self, kind: JobKind, now: u64) -> Result<Job, Error> {
+        if self.running().iter().any(|job| job.kind == kind) {
+            return Err(Error::Conflict(Some(format!(
+                "A {kind:?} job is already running"
+            ))));
+        }
+        let finished: Vec<JobId> = self
+            .store
+            .borrow()
+            .iter()
+            .filter(|(_, job)| job.state == JobState::Done)
+            .map(|(id, _)| id)
+            .collect();
+        let mut store = self.store.borrow_mut();
+        for id in finished
+            .iter()
+            .take((finished.len() + 1).saturating_sub(MAX_FINISHED_JOBS))
+        {
+            store.remove(id);
+        }
+        let id = store.last_key_value().map_or(1, |(id, _)| id + 1);
+        let job = Job {
+            id,
+            kind,
+            state: JobState::Running,
+            cursor: None,
+            processed: 0,
+            slices: 0,
+            started_at: now,
+            finished_at: None,
+        };
+        store.insert(id, job.clone());
+        Ok(job)
+    }
+
+    /// Retrieves a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The unique identifier for the job.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the job if found, otherwise None.
+    pub(crate) fn get(&self, id: JobId) -> Option<Job> {
+        self.store.borrow().get(&id)
+    }
+
+    /// Stores the progress of a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - The job.
+    pub(crate) fn save(&self, job: Job) {
+        self.store.borrow_mut().insert(job.id, job);
+    }
+
+    /// Lists the jobs that are still running, oldest first.
+    pub(crate) fn running(&self) -> Vec<Job> {
+        self.store
+            .borrow()
+            .iter()
+            .map(|(_, job)| job)
+            .filter(|job| job.state == JobState::Running)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_jobs_run_in_slices() {
+        let store = RefCell::new(JobStore::new(DefaultMemoryImpl::default()));
+        let wrapper = JobStoreWrapper { store: &store };
+
+        let mut job = wrapper.start(JobKind::Reindex, 5).unwrap();
+        assert_eq!(job.id, 1);
+        assert!(matches!(
+            wrapper.start(JobKind::Reindex, 6),
+            Err(Error::Conflict(_))
+        ));
+        assert_eq!(wrapper.start(JobKind::Purge, 6).unwrap().id, 2);
+
+        // Three items, processed one per slice because the budget is always spent.
+        let principal = Principal::anonymous();
+        let step = |cursor: Option<JobCursor>| {
+            let next = cursor.map_or(1, |(_, id)| id + 1);
+            (next <= 3).then_some((principal, next))
+        };
+        while job.state == JobState::Running {
+            job.run_slice(|| true, 9, step);
+            wrapper.save(job.clone());
+        }
+        let job = wrapper.get(1).unwrap();
+        assert_eq!((job.processed, job.slices), (3, 4));
+        assert_eq!(job.cursor, Some((principal, 3)));
+        assert_eq!(job.finished_at, Some(9));
+        assert_eq!(wrapper.running().len(), 1);
+        assert!(wrapper.start(JobKind::Reindex, 10).is_ok());
+    }
+}
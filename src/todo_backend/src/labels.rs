@@ -0,0 +1,227 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::errors::Error;
+
+/// Maximum number of labels a principal may define.
+pub(crate) const MAX_LABELS: usize = 100;
+
+/// Type alias for the unique identifier of a label, unique per principal.
+pub(crate) type LabelId = u64;
+
+/// Type alias for the LabelStore, which is a StableBTreeMap with a tuple key of (Principal, LabelId) and value of Label.
+pub(crate) type LabelStore<M> = StableBTreeMap<(Principal, LabelId), Label, M>;
+
+/// Represents a label Todo items refer to by its identifier, so that renaming it leaves them untouched.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Label {
+    /// Unique identifier for the label.
+    pub(crate) id: LabelId,
+    /// Name of the label, unique among the labels of a principal regardless of letter case.
+    pub(crate) name: String,
+    /// Color of the label, such as `#ff8800`.
+    pub(crate) color: String,
+}
+
+impl Storable for Label {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Wrapper around the LabelStore to provide additional functionality.
+pub(crate) struct LabelStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<LabelStore<M>>,
+}
+
+impl<M: Memory> LabelStoreWrapper<'_, M> {
+    /// Defines a label.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `name` - The validated name of the label.
+    /// * `color` - The validated color of the label.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the unique identifier for the new label, or an Error if the name is taken or too many labels exist.
+    pub(crate) fn create(
+        &self,
+        principal: Principal,
+        name: String,
+        color: String,
+    ) -> Result<LabelId, Error> {
+        let labels = self.list(principal);
+        if labels.len() >= MAX_LABELS {
+            return Err(Error::QuotaExceeded(Some(format!(
+                "At most {MAX_LABELS} labels can be defined"
+            ))));
+        }
+        ensure_unique(&labels, None, &name)?;
+        let id = self
+            .store
+            .borrow()
+            .iter_upper_bound(&(principal, LabelId::MAX))
+            .next()
+            .filter(|((owner, _), _)| *owner == principal)
+            .map_or(1, |((_, id), _)| id + 1);
+        let label = Label { id, name, color };
+        self.store.borrow_mut().insert((principal, id), label);
+        Ok(id)
+    }
+
+    /// Retrieves a label.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the label.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the label if found, otherwise None.
+    pub(crate) fn get(&self, principal: Principal, id: LabelId) -> Option<Label> {
+        self.store.borrow().get(&(principal, id))
+    }
+
+    /// Lists the labels of a principal in alphabetical order.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn list(&self, principal: Principal) -> Vec<Label> {
+        let mut labels: Vec<Label> = self
+            .store
+            .borrow()
+            .range((principal, LabelId::MIN)..=(principal, LabelId::MAX))
+            .map(|(_, label)| label)
+            .collect();
+        labels.sort_by_key(|label| label.name.to_lowercase());
+        labels
+    }
+
+    /// Puts back the labels of a principal from an account bundle, keeping their identifiers.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `labels` - The validated labels.
+    pub(crate) fn restore(&self, principal: Principal, labels: Vec<Label>) {
+        let mut store = self.store.borrow_mut();
+        for label in labels {
+            store.insert((principal, label.id), label);
+        }
+    }
+
+    /// Renames and recolors a label.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the label.
+    /// * `name` - The validated new name of the label.
+    /// * `color` - The validated new color of the label.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if the label is not found or the name is taken.
+    pub(crate) fn update(
+        &self,
+        principal: Principal,
+        id: LabelId,
+        name: String,
+        color: String,
+    ) -> Result<(), Error> {
+        if self.get(principal, id).is_none() {
+            return Err(Error::NotFound);
+        }
+        ensure_unique(&self.list(principal), Some(id), &name)?;
+        let label = Label { id, name, color };
+        self.store.borrow_mut().insert((principal, id), label);
+        Ok(())
+    }
+
+    /// Deletes a label.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the label.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if the label is not found.
+    pub(crate) fn delete(&self, principal: Principal, id: LabelId) -> Result<(), Error> {
+        self.store
+            .borrow_mut()
+            .remove(&(principal, id))
+            .map(|_| ())
+            .ok_or(Error::NotFound)
+    }
+}
+
+/// Checks that no other label carries a name, ignoring letter case.
+///
+/// # Arguments
+///
+/// * `labels` - The labels of the principal.
+/// * `id` - The label being renamed, if any, which may keep its name.
+/// * `name` - The name.
+fn ensure_unique(labels: &[Label], id: Option<LabelId>, name: &str) -> Result<(), Error> {
+    let taken = labels
+        .iter()
+        .any(|label| Some(label.id) != id && label.name.to_lowercase() == name.to_lowercase());
+    if taken {
+        return Err(Error::AlreadyExists(Some(format!(
+            "A label named \"{name}\" already exists"
+        ))));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_labels() {
+        let store = RefCell::new(LabelStore::new(DefaultMemoryImpl::default()));
+        let wrapper = LabelStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let create =
+            |name: &str| wrapper.create(principal, name.to_string(), "#ff8800".to_string());
+
+        assert_eq!(create("Work").unwrap(), 1);
+        assert_eq!(create("Errands").unwrap(), 2);
+        assert!(matches!(create("work"), Err(Error::AlreadyExists(_))));
+        assert!(matches!(
+            wrapper.update(principal, 2, "WORK".to_string(), "#000000".to_string()),
+            Err(Error::AlreadyExists(_))
+        ));
+        assert!(wrapper
+            .update(principal, 1, "Office".to_string(), "#000000".to_string())
+            .is_ok());
+        let names: Vec<_> = wrapper
+            .list(principal)
+            .into_iter()
+            .map(|label| label.name)
+            .collect();
+        assert_eq!(names, vec!["Errands", "Office"]);
+        assert_eq!(wrapper.get(principal, 1).unwrap().color, "#000000");
+
+        assert!(wrapper.delete(principal, 2).is_ok());
+        assert!(matches!(wrapper.delete(principal, 2), Err(Error::NotFound)));
+        assert!(wrapper.list(Principal::management_canister()).is_empty());
+    }
+}
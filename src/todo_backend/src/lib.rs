@@ -1,14 +1,165 @@
+mod access;
+mod account;
+mod api_tokens;
+mod archive;
+mod attachments;
+mod audit;
+mod authorization;
+#[cfg(feature = "canbench-rs")]
+mod benches;
+mod board;
+mod budget;
+mod bounded_string;
+mod build_info;
+mod checklists;
+mod clock;
+mod digest;
+mod downloads;
+mod dump;
+mod eisenhower;
+mod email;
 mod errors;
+mod estimation;
+mod events;
+mod external_id;
+mod features;
+mod geo;
+mod goals;
+mod governance;
+mod habits;
+mod history;
+mod hooks;
+mod http;
+mod imports;
+mod indexes;
+mod jobs;
+mod labels;
+mod link_preview;
+mod maintenance;
+mod links;
 mod memory;
+mod messages;
+mod migration;
+mod my_day;
+mod namespaces;
+mod notifications;
 mod paginator;
+mod planner;
+mod pomodoro;
+mod preferences;
+mod priority_history;
+mod quotas;
+mod recurrence;
+mod relations;
+mod renumbering;
+mod review;
+mod samples;
+mod scheduled;
+mod score;
+mod search;
+mod seed;
+mod stats;
 mod store;
+mod tag_trends;
+mod tags;
+mod telegram;
+mod throttle;
 mod todo;
+mod todo_counts;
+mod usage;
+mod users;
+mod validation;
+mod waiting;
 
+use std::{cell::Cell, collections::{BTreeMap, BTreeSet}, ops::Bound, time::Duration};
+
+use api_tokens::{ApiGrant, ApiScope, ApiTokenId, ApiTokenInfo, ApiTokenStoreWrapper};
+use archive::{ArchiveStoreWrapper, PurgeSummary};
+use access::{AccessStoreWrapper, AccountGrant, AccountScope, SharedAccountIndexWrapper};
+use account::{AccountBundle, BUNDLE_VERSION};
+use attachments::{Attachment, AttachmentId, AttachmentStoreWrapper, ChunkStoreWrapper};
+use audit::{AuditEntry, AuditStoreWrapper};
+use authorization::{Action, Context, Resource};
+use board::{Board, BoardColumn, BoardStoreWrapper, ColumnId};
+use budget::{InstructionBudget, InstructionGuard};
+use build_info::{BuildInfo, Schema};
+use candid::Principal;
+use checklists::{Checklist, ChecklistId, ChecklistStoreWrapper};
+use ic_cdk::api::management_canister::{
+    http_request::{self as outcall, TransformArgs},
+    main::raw_rand,
+};
+use digest::DailyDigest;
+use downloads::{Certifier, Download, DownloadTokenStoreWrapper, CERTIFIER};
+use dump::{Segment, SegmentEntry, SegmentKey};
+use eisenhower::EisenhowerMatrix;
+use email::{DeliveryStatus, EmailChannel, EmailChannelStoreWrapper, EmailDelivery, EmailLogStoreWrapper};
 use errors::Error;
-use memory::{LAST_TODO_ID, TODO_STORE};
-use paginator::Paginator;
-use store::TodoStoreWrapper;
-use todo::{Priority, Todo, TodoId};
+use estimation::EstimationReport;
+use events::{EventStoreWrapper, Sequence, TodoEvent, TodoEventKind, MAX_EVENTS_PER_PAGE};
+use external_id::ExternalIdStoreWrapper;
+use features::{Feature, FeatureFlag, FeatureFlagStoreWrapper};
+use geo::{GeoIndexWrapper, GeoPoint, NearbyTodo, MAX_RADIUS_M};
+use goals::{GoalId, GoalProgress, GoalStoreWrapper, GoalTarget};
+use governance::{GovernanceConfig, InitArgs};
+use habits::{HabitHistory, HabitStoreWrapper};
+use history::{HistoryStoreWrapper, Revision, TodoRevision};
+use hooks::{IntegrationId, IntegrationInfo, IntegrationKind, IntegrationMapping, IntegrationStoreWrapper, NewIntegration};
+use http::{HttpRequest, HttpResponse};
+use imports::{Import, ImportHandle, ImportState, ImportStoreWrapper, StagedTodoStoreWrapper, MAX_CHUNK_ITEMS};
+use indexes::{DueSummary, TodoIndexWrapper};
+use jobs::{Job, JobCursor, JobId, JobKind, JobState, JobStoreWrapper};
+use labels::{Label, LabelId, LabelStoreWrapper};
+use link_preview::{LinkPreview, FAILED_FETCHES};
+use maintenance::MaintenanceMode;
+use links::{LinkStatus, PeerStoreWrapper, ResolvedLink, TodoReference, MAX_RESOLVED_LINKS, REFERENCE_METHOD};
+use memory::{ACCESS_STORE, API_TOKEN_STORE, ARCHIVE_STORE, ATTACHMENT_STORE, AUDIT_STORE, BOARD_STORE, CHECKLIST_STORE, CHUNK_STORE, COMPLETION_INDEX, DOWNLOAD_TOKEN_STORE, DUE_INDEX, EMAIL_CHANNEL_STORE, EMAIL_LOG_STORE, EVENT_STORE, EXTERNAL_ID_STORE, FEATURE_FLAG_STORE, GEO_INDEX, GOAL_STORE, GOVERNANCE_CONFIG, HABIT_STORE, HISTORY_STORE, IMPORT_STORE, INSTRUCTION_BUDGET, INTEGRATION_STORE, JOB_STORE, LABEL_STORE, LAST_INTEGRATION_ID, LAST_TODO_ID, MAINTENANCE_MODE, MODIFIED_INDEX, MY_DAY_STORE, NAMESPACE_STORE, NOTIFICATION_STORE, PEER_STORE, POMODORO_STORE, PREFERENCE_STORE, PRIORITY_HISTORY_STORE, RANK_INDEX, RELATION_STORE, REVIEW_LOG_STORE, SCHEDULE_STORE, SCORE_STORE, SHARED_ACCOUNT_INDEX, STAGED_TODO_STORE, STATS_STORE, TAG_STORE, TAG_TREND_STORE, TELEGRAM_CHAT_INDEX, TELEGRAM_STORE, TODO_COUNT_STORE, TODO_STORE, USAGE_STORE, USER_STORE};
+use messages::{Language, Message};
+use my_day::MyDayStoreWrapper;
+use namespaces::{Namespace, NamespaceStoreWrapper};
+use notifications::{Notification, NotificationId, NotificationKind, NotificationStoreWrapper};
+use paginator::{Paginator, MAX_PAGE_SIZE};
+use preferences::{PreferenceStoreWrapper, Preferences};
+use planner::WeekPlan;
+use pomodoro::{PomodoroStoreWrapper, PomodoroSummary, SessionId};
+use priority_history::{PriorityChange, PriorityHistoryStoreWrapper};
+use recurrence::Recurrence;
+use relations::{RelatedTodo, RelationKind, RelationStoreWrapper};
+use review::{ReviewLogStoreWrapper, WeeklyReview};
+use quotas::{CreatedTodo, Quota, StagedChunk, Warning};
+use scheduled::{ScheduleId, ScheduleStoreWrapper, ScheduledTodo};
+use score::{LeaderboardEntry, MyScore, ScoreStoreWrapper};
+use stats::{day_of, local_day_of, local_day_start, CompletionHeatmap, Day, MyStats, StatsRange, StatsStoreWrapper, NANOS_PER_DAY};
+use search::{SearchHit, TodoSearch};
+use store::{BulkUpdate, CorruptTodo, SortKey, SortedPage, TodoChange, TodoFilter, TodoStoreWrapper, TodoVersion};
+use tag_trends::{week_of, week_start, TagTrendStoreWrapper, TagTrends, DEFAULT_TREND_WEEKS};
+use tags::{TagCloudEntry, TagNode, TagStoreWrapper};
+use telegram::{Command, TelegramChatIndexWrapper, TelegramLink, TelegramStoreWrapper};
+use throttle::{Caller, THROTTLE};
+use todo::{CreateTodo, Energy, PatchTodo, Priority, Todo, TodoId};
+use todo_counts::TodoCountStoreWrapper;
+use usage::{Meter, MyUsage, PrincipalUsage, UsageStoreWrapper, USAGE_RETENTION_DAYS};
+use users::{UserStats, UserStoreWrapper};
+use waiting::WaitingTodo;
+
+/// Type alias for the outcome of a batch call: the result for each selected Todo item, in order.
+type BatchOutcome = Vec<(TodoId, Result<(), Error>)>;
+
+/// Number of tags reported in the statistics of a principal.
+const BUSIEST_TAGS_LIMIT: usize = 5;
+
+/// Interval between two runs of the periodic maintenance jobs.
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How long before its due date a reminder is sent for a Todo item, in nanoseconds.
+const REMINDER_LEAD: u64 = NANOS_PER_DAY;
+
+/// Cycles attached to each HTTPS outcall sending a message; the unused part is refunded.
+const OUTCALL_CYCLES: u128 = 1_000_000_000;
+
+/// Number of outcalls sending messages after which a maintenance round stops delivering; the principals left
+/// are delivered to in the following rounds.
+const MAX_OUTCALLS_PER_ROUND: u32 = 100;
 
 /// Adds a new Todo item.
 ///
@@ -18,17 +169,269 @@ use todo::{Priority, Todo, TodoId};
 ///
 /// # Returns
 ///
-/// The unique identifier for the newly created Todo item.
+/// A Result containing the unique identifier for the newly created Todo item with the quotas the caller used at least
+/// 80% of, or an Error if the description is invalid, the caller keeps too many Todo items or, for principals rejecting
+/// duplicates, an open Todo item has the same description.
 #[ic_cdk::update]
-fn add_todo_item(description: String, priority: Option<Priority>) -> TodoId {
-    let principal = ic_cdk::caller();
+fn add_todo_item(description: String, priority: Option<Priority>) -> Result<CreatedTodo, Error> {
+    let (principal, _meter) = active_caller()?;
+    TODO_COUNT_STORE.with(|store| TodoCountStoreWrapper { store }.ensure_room(principal, 1))?;
+    ensure_not_duplicate(principal, &description)?;
     let id = generate_next_id();
-    let priority = priority.unwrap_or_default();
-    TODO_STORE.with(|store| TodoStoreWrapper{store}.add_todo(principal, id, description, priority));
-    id
+    let priority = priority.or(preferences_of(principal).default_priority).unwrap_or_default();
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.add_todo(principal, id, description, priority, now))
+        .map(record_change)?;
+    Ok(CreatedTodo { id, warnings: quota_warnings(principal, 0) })
+}
+
+/// Creates a Todo item with all of its fields in a single call.
+///
+/// # Arguments
+///
+/// * `request` - The fields of the new Todo item.
+///
+/// # Returns
+///
+/// A Result containing the unique identifier for the newly created Todo item with the quotas the caller used at least
+/// 80% of, or an Error if the input is invalid, the caller keeps too many Todo items or, for principals rejecting
+/// duplicates, an open Todo item has the same description.
+#[ic_cdk::update]
+fn create_todo(request: CreateTodo) -> Result<CreatedTodo, Error> {
+    let (principal, _meter) = active_caller()?;
+    let id = create_requested_todo(principal, request, clock::now())?;
+    Ok(CreatedTodo { id, warnings: quota_warnings(principal, 0) })
+}
+
+/// Finds an open Todo item of the caller with the same description, ignoring case and spacing,
+/// so that clients can flag a task that is about to be captured twice. The search stops once the call spent
+/// its instruction budget, so that large accounts cannot make it exceed the limit of a query.
+///
+/// # Arguments
+///
+/// * `description` - The description of the new Todo item.
+///
+/// # Returns
+///
+/// An Option containing the unique identifier for the open Todo item, or None if there is none among the Todo items searched.
+#[ic_cdk::query]
+fn find_duplicate_todo(description: String) -> Option<TodoId> {
+    let principal = ic_cdk::caller();
+    let guard = instruction_guard();
+    TODO_STORE.with(|store| TodoStoreWrapper { store }.find_duplicate(principal, &description, &guard))
+}
+
+/// Returns the identifier the next created Todo item gets unless another one is created first,
+/// so that clients can show an optimistic row while the creation is in flight.
+/// Identifiers are never handed out twice, so a row that ends up with another identifier is simply replaced.
+///
+/// # Returns
+///
+/// The next unique identifier for a Todo item.
+#[ic_cdk::query]
+fn peek_next_id() -> TodoId {
+    LAST_TODO_ID.with(|id| *id.borrow().get() + 1)
+}
+
+/// Begins a bulk import of Todo items too many to create in a single message. The items are then
+/// sent with `import_chunk` and created with `commit_import`; none of them are visible before.
+///
+/// # Returns
+///
+/// A Result containing the handle of the import, or an Error if the caller has too many open imports.
+#[ic_cdk::update]
+fn begin_import() -> Result<ImportHandle, Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    IMPORT_STORE.with(|store| ImportStoreWrapper { store }.begin(principal, now)).map(|import| import.handle)
+}
+
+/// Stages the next chunk of at most 1,000 Todo items of an import. The chunk is validated as a whole, against
+/// the items staged before it too, so that a rejected chunk can be fixed and sent again.
+///
+/// # Arguments
+///
+/// * `handle` - The handle returned by `begin_import`.
+/// * `items` - The fields of the Todo items.
+///
+/// # Returns
+///
+/// A Result containing the number of Todo items staged so far with the quotas the caller used at least 80% of,
+/// counting the staged Todo items, or an Error if the import is not found or already committed, an item is invalid,
+/// the import would exceed 10,000 Todo items or the caller 100,000 or, for callers rejecting duplicates, an item
+/// has the same description as an open Todo item or another staged item.
+#[ic_cdk::update]
+fn import_chunk(handle: ImportHandle, items: Vec<CreateTodo>) -> Result<StagedChunk, Error> {
+    let (principal, _meter) = active_caller()?;
+    if items.len() > MAX_CHUNK_ITEMS {
+        return Err(Error::InvalidInput(format!("A chunk can hold at most {MAX_CHUNK_ITEMS} Todo items")));
+    }
+    let import = IMPORT_STORE.with(|store| ImportStoreWrapper { store }.staging(principal, handle))?;
+    let items = validated_import_items(principal, items, import.items)?;
+    let count = items.len() as u32;
+    TODO_COUNT_STORE.with(|store| TodoCountStoreWrapper { store }.ensure_room(principal, u64::from(import.items + count)))?;
+    let earlier = STAGED_TODO_STORE.with(|store| StagedTodoStoreWrapper { store }.all(principal, handle));
+    ensure_no_duplicates(principal, &earlier, &items, import.items)?;
+    let first = IMPORT_STORE.with(|store| ImportStoreWrapper { store }.reserve(principal, handle, count))?;
+    STAGED_TODO_STORE.with(|store| StagedTodoStoreWrapper { store }.put(principal, handle, first, items));
+    let staged = first + count;
+    Ok(StagedChunk { staged, warnings: quota_warnings(principal, u64::from(staged)) })
+}
+
+/// Commits an import: every staged Todo item is created within this one message, so that either all of them
+/// become visible at once or, if any is refused, none does. The staged items are checked again, as the caller
+/// may have added Todo items or changed preferences since staging them; a refused commit leaves the import
+/// staging, so that it can be fixed with another import or abandoned.
+///
+/// # Arguments
+///
+/// * `handle` - The handle returned by `begin_import`.
+///
+/// # Returns
+///
+/// A Result containing the committed import, or an Error if it is not found or already committed, the caller
+/// would keep more than 100,000 Todo items or a staged item is refused, naming its position in the import.
+#[ic_cdk::update]
+fn commit_import(handle: ImportHandle) -> Result<Import, Error> {
+    let (principal, _meter) = active_caller()?;
+    let mut import = IMPORT_STORE.with(|store| ImportStoreWrapper { store }.staging(principal, handle))?;
+    let staged = STAGED_TODO_STORE.with(|store| StagedTodoStoreWrapper { store }.all(principal, handle));
+    TODO_COUNT_STORE.with(|store| TodoCountStoreWrapper { store }.ensure_room(principal, staged.len() as u64))?;
+    let items = validated_import_items(principal, staged, 0)?;
+    ensure_no_duplicates(principal, &[], &items, 0)?;
+    let now = clock::now();
+    let requests = items.into_iter().map(|request| (generate_next_id(), request)).collect();
+    // Every request was validated above, so the batch is stored as a whole.
+    TODO_STORE.with(|store| TodoStoreWrapper { store }.create_many(principal, requests, now))?.into_iter().for_each(record_change);
+    STAGED_TODO_STORE.with(|store| StagedTodoStoreWrapper { store }.remove_all(principal, handle));
+    import.state = ImportState::Committed;
+    import.committed_at = Some(now);
+    IMPORT_STORE.with(|store| ImportStoreWrapper { store }.save(principal, import.clone()));
+    Ok(import)
+}
+
+/// Abandons an import that is not committed, discarding its staged Todo items.
+///
+/// # Arguments
+///
+/// * `handle` - The handle returned by `begin_import`.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the import is not found or already committed.
+#[ic_cdk::update]
+fn abort_import(handle: ImportHandle) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    IMPORT_STORE.with(|store| ImportStoreWrapper { store }.staging(principal, handle))?;
+    STAGED_TODO_STORE.with(|store| StagedTodoStoreWrapper { store }.remove_all(principal, handle));
+    IMPORT_STORE.with(|store| ImportStoreWrapper { store }.remove(principal, handle));
+    Ok(())
+}
+
+/// Retrieves an import of the caller, telling how many Todo items it staged and whether it was committed.
+///
+/// # Arguments
+///
+/// * `handle` - The handle returned by `begin_import`.
+///
+/// # Returns
+///
+/// A Result containing the import, or an Error if it is not found.
+#[ic_cdk::query]
+fn get_import_status(handle: ImportHandle) -> Result<Import, Error> {
+    let principal = ic_cdk::caller();
+    IMPORT_STORE.with(|store| ImportStoreWrapper { store }.get(principal, handle)).ok_or(Error::NotFound)
+}
+
+/// Schedules a Todo item to be created at a later time, keeping it off the list until then.
+///
+/// # Arguments
+///
+/// * `request` - The fields of the Todo item.
+/// * `create_at` - Time the Todo item is created at, in nanoseconds since the Unix epoch.
+///
+/// # Returns
+///
+/// A Result containing the identifier of the scheduled creation, or an Error if the input is invalid,
+/// the time has passed or too many Todo items are scheduled.
+#[ic_cdk::update]
+fn schedule_todo(request: CreateTodo, create_at: u64) -> Result<ScheduleId, Error> {
+    let (principal, _meter) = active_caller()?;
+    let request = validation::create_request(with_default_priority(principal, request))?;
+    let now = clock::now();
+    if create_at <= now {
+        return Err(Error::InvalidInput("Creation time must lie in the future".to_string()));
+    }
+    let id = SCHEDULE_STORE.with(|store| ScheduleStoreWrapper { store }.schedule(principal, request, create_at))?;
+    arm_schedule_timer();
+    Ok(id)
+}
+
+/// Lists the Todo items the caller scheduled for later creation.
+///
+/// # Returns
+///
+/// A vector of scheduled Todo items, earliest first.
+#[ic_cdk::query]
+fn list_scheduled_todos() -> Vec<ScheduledTodo> {
+    let principal = ic_cdk::caller();
+    SCHEDULE_STORE.with(|store| ScheduleStoreWrapper { store }.list(principal))
 }
 
+/// Cancels the creation of a scheduled Todo item.
+///
+/// # Arguments
+///
+/// * `id` - The identifier of the scheduled creation.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the scheduled creation is not found.
+#[ic_cdk::update]
+fn cancel_scheduled_todo(id: ScheduleId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    SCHEDULE_STORE.with(|store| ScheduleStoreWrapper { store }.cancel(principal, id))?;
+    arm_schedule_timer();
+    Ok(())
+}
 
+/// Creates or updates a Todo item identified by an id assigned by an external system.
+///
+/// If the external id is already mapped to an existing Todo item of the caller, that item is
+/// updated with the fields of the request; otherwise a new Todo item is created and mapped.
+///
+/// # Arguments
+///
+/// * `external_id` - The identifier assigned by the external system.
+/// * `request` - The fields of the Todo item.
+///
+/// # Returns
+///
+/// A Result containing the unique identifier for the Todo item, or an Error if the input is invalid.
+#[ic_cdk::update]
+fn upsert_todo(external_id: String, request: CreateTodo) -> Result<TodoId, Error> {
+    let (principal, _meter) = active_caller()?;
+    let request = with_default_priority(principal, request);
+    let external_id = validation::external_id(&external_id)?;
+    let mapped_id = EXTERNAL_ID_STORE
+        .with(|store| ExternalIdStoreWrapper { store }.get(principal, &external_id));
+    TODO_STORE.with(|store| {
+        let wrapper = TodoStoreWrapper { store };
+        if let Some(id) = mapped_id.filter(|id| wrapper.get_todo(principal, *id).is_some()) {
+            wrapper
+                .replace_todo(principal, id, request, clock::now())
+                .map(record_change)?;
+            return Ok(id);
+        }
+        let id = generate_next_id();
+        wrapper
+            .create_todo(principal, id, request, clock::now())
+            .map(record_change)?;
+        EXTERNAL_ID_STORE.with(|store| ExternalIdStoreWrapper { store }.set(principal, external_id, id));
+        Ok(id)
+    })
+}
 
 /// Retrieves a Todo item.
 ///
@@ -43,11 +446,11 @@ fn add_todo_item(description: String, priority: Option<Priority>) -> TodoId {
 fn get_todo_item(id: TodoId) -> Result<Todo, Error> {
     let principal = ic_cdk::caller();
     TODO_STORE
-        .with(|store| TodoStoreWrapper{store}.get_todo(principal, id))
+        .with(|store| TodoStoreWrapper { store }.get_todo(principal, id))
         .ok_or(Error::NotFound)
 }
 
-/// Lists Todo items with pagination.
+/// Lists Todo items with pagination, in the order and page size set in the caller's preferences.
 ///
 /// # Arguments
 ///
@@ -58,9 +461,195 @@ fn get_todo_item(id: TodoId) -> Result<Todo, Error> {
 /// A vector of Todo items.
 #[ic_cdk::query]
 fn list_todo_items(paginator: Option<Paginator>) -> Vec<Todo> {
+    list_todos_of(ic_cdk::caller(), paginator)
+}
+
+/// Lists the Todo items of a principal with pagination, in the order and page size set in their preferences.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `paginator` - Optional paginator for controlling the list output.
+fn list_todos_of(principal: Principal, paginator: Option<Paginator>) -> Vec<Todo> {
+    let preferences = preferences_of(principal);
+    let paginator = paginator.unwrap_or_default().with_default_limit(preferences.default_page_size);
+    let sort = preferences.default_sort.unwrap_or_default();
+    let guard = instruction_guard();
+    TODO_STORE.with(|store| TodoStoreWrapper { store }.list_todos(principal, paginator, sort, &guard))
+}
+
+/// Lists Todo items with pagination, sorted by several keys, such as highest priority first,
+/// then soonest due date, then oldest first. Large accounts are ranked across several calls,
+/// each continuing after the `next` identifier of the previous one.
+///
+/// # Arguments
+///
+/// * `sort` - The sort keys, most significant first; at most 4. When empty, the caller's preferred order is used.
+/// * `paginator` - Optional paginator for controlling the list output.
+/// * `after` - The `next` identifier of the previous call to continue after, or None to start from the first Todo item.
+///
+/// # Returns
+///
+/// A Result containing the sorted page, or an Error if there are too many sort keys.
+#[ic_cdk::query]
+fn list_todo_items_sorted(
+    sort: Vec<SortKey>,
+    paginator: Option<Paginator>,
+    after: Option<TodoId>,
+) -> Result<SortedPage, Error> {
     let principal = ic_cdk::caller();
-    let paginator = paginator.unwrap_or_default();
-    TODO_STORE.with(|store| TodoStoreWrapper{store}.list_todos(principal, paginator))
+    let preferences = preferences_of(principal);
+    let paginator = paginator.unwrap_or_default().with_default_limit(preferences.default_page_size);
+    let sort = if sort.is_empty() { preferences.default_sort.unwrap_or_default().keys() } else { sort };
+    let guard = instruction_guard();
+    TODO_STORE.with(|store| TodoStoreWrapper { store }.list_todos_sorted(principal, paginator, &sort, after, &guard))
+}
+
+/// Lists the identifiers of the caller's Todo items that match a filter, for cheap sync checks.
+///
+/// # Arguments
+///
+/// * `filter` - Optional conditions the Todo items must meet.
+///
+/// # Returns
+///
+/// A Result containing the identifiers in ascending order, or an Error if the filter is invalid.
+#[ic_cdk::query]
+fn list_todo_ids(filter: Option<TodoFilter>) -> Result<Vec<TodoId>, Error> {
+    Ok(list_todo_versions(filter)?.into_iter().map(|version| version.id).collect())
+}
+
+/// Lists the identifiers and versions of the caller's Todo items that match a filter,
+/// so that clients can diff their cache against the canister without fetching full records.
+///
+/// # Arguments
+///
+/// * `filter` - Optional conditions the Todo items must meet.
+///
+/// # Returns
+///
+/// A Result containing the identifiers with versions in ascending order, or an Error if the filter is invalid.
+#[ic_cdk::query]
+fn list_todo_versions(filter: Option<TodoFilter>) -> Result<Vec<TodoVersion>, Error> {
+    let principal = ic_cdk::caller();
+    let filter = validation::filter(filter.unwrap_or_default())?;
+    let now = clock::now();
+    Ok(TODO_STORE.with(|store| TodoStoreWrapper { store }.todo_versions(principal, &filter, now)))
+}
+
+/// Lists the caller's Todo items created or updated after a given time, least recently modified first,
+/// so that polling clients only fetch what changed since their last refresh.
+/// Removed and archived Todo items are not listed; `list_todo_ids` tells which Todo items still exist.
+///
+/// # Arguments
+///
+/// * `since` - The time of the last refresh, in nanoseconds since the Unix epoch, such as the latest
+///   `updated_at` or `created_at` seen.
+/// * `paginator` - Optional paginator for controlling the list output; a descending paginator lists the
+///   most recently modified Todo items first.
+///
+/// # Returns
+///
+/// A vector of Todo items.
+#[ic_cdk::query]
+fn list_todos_modified_since(since: u64, paginator: Option<Paginator>) -> Vec<Todo> {
+    let principal = ic_cdk::caller();
+    let paginator = paginator.unwrap_or_default().with_default_limit(preferences_of(principal).default_page_size);
+    let ids = MODIFIED_INDEX.with(|store| TodoIndexWrapper::modified(store).modified_since(principal, since, paginator));
+    TODO_STORE.with(|store| {
+        let wrapper = TodoStoreWrapper { store };
+        ids.into_iter().filter_map(|id| wrapper.get_todo(principal, id)).collect()
+    })
+}
+
+/// Lists the events of the caller's Todo items after a position, oldest first, so that clients can keep a copy in sync
+/// by replaying them. A client whose position precedes the oldest event kept starts over from the oldest event, which
+/// is a `Reset` followed by a snapshot.
+///
+/// # Arguments
+///
+/// * `after` - The position of the last event the client applied, or None to start from the oldest event kept.
+/// * `limit` - The maximum number of events, at most 500.
+///
+/// # Returns
+///
+/// A vector of events.
+#[ic_cdk::query]
+fn list_todo_events(after: Option<Sequence>, limit: u32) -> Vec<TodoEvent> {
+    let principal = ic_cdk::caller();
+    let limit = limit.min(MAX_EVENTS_PER_PAGE) as usize;
+    EVENT_STORE.with(|store| {
+        let wrapper = EventStoreWrapper { store };
+        let oldest = wrapper.list(principal, None, 1).first().map(|event| event.sequence);
+        let after = after.filter(|after| oldest.is_some_and(|oldest| *after >= oldest));
+        wrapper.list(principal, after, limit)
+    })
+}
+
+/// Lists the caller's open Todo items in order of creation, with pagination.
+///
+/// # Arguments
+///
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A vector of open Todo items.
+#[ic_cdk::query]
+fn list_open_todos(paginator: Option<Paginator>) -> Vec<Todo> {
+    list_todos_by_completion(false, paginator)
+}
+
+/// Lists the caller's completed Todo items in order of creation, with pagination.
+/// Archived Todo items are not listed.
+///
+/// # Arguments
+///
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A vector of completed Todo items.
+#[ic_cdk::query]
+fn list_completed_todos(paginator: Option<Paginator>) -> Vec<Todo> {
+    list_todos_by_completion(true, paginator)
+}
+
+/// Lists either the open or the completed Todo items of the caller from the completion index.
+///
+/// # Arguments
+///
+/// * `completed` - Whether to list the completed rather than the open Todo items.
+/// * `paginator` - Optional paginator for controlling the list output.
+fn list_todos_by_completion(completed: bool, paginator: Option<Paginator>) -> Vec<Todo> {
+    let principal = ic_cdk::caller();
+    let paginator = paginator.unwrap_or_default().with_default_limit(preferences_of(principal).default_page_size);
+    let ids = COMPLETION_INDEX.with(|store| TodoIndexWrapper::completion(store).list(principal, completed, paginator));
+    TODO_STORE.with(|store| {
+        let wrapper = TodoStoreWrapper { store };
+        ids.into_iter().filter_map(|id| wrapper.get_todo(principal, id)).collect()
+    })
+}
+
+/// Lists the caller's most pressing open Todo items: highest priority first, then soonest due date,
+/// then oldest.
+///
+/// # Arguments
+///
+/// * `n` - The number of Todo items to list, at most 100.
+///
+/// # Returns
+///
+/// A vector of Todo items.
+#[ic_cdk::query]
+fn get_top_todos(n: u32) -> Vec<Todo> {
+    let principal = ic_cdk::caller();
+    let count = n.min(paginator::MAX_PAGE_SIZE) as usize;
+    let ids = RANK_INDEX.with(|store| TodoIndexWrapper::rank(store).top(principal, count));
+    TODO_STORE.with(|store| {
+        let wrapper = TodoStoreWrapper { store };
+        ids.into_iter().filter_map(|id| wrapper.get_todo(principal, id)).collect()
+    })
 }
 
 /// Updates the text of an existing Todo item.
@@ -72,100 +661,4474 @@ fn list_todo_items(paginator: Option<Paginator>) -> Vec<Todo> {
 ///
 /// # Returns
 ///
-/// A Result indicating success or an Error if the Todo item is not found or the input is invalid.
+/// A Result containing the quotas the caller used at least 80% of, or an Error if the Todo item is not found or the input is invalid.
 #[ic_cdk::update]
-fn update_todo_item(id: TodoId, text: String) -> Result<(), Error> {
-    let principal = ic_cdk::caller();
-    TODO_STORE.with(|store| TodoStoreWrapper{store}.update_todo(principal, id, text))
+fn update_todo_item(id: TodoId, text: String) -> Result<Vec<Warning>, Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    store::with_cached_reads(|| {
+        TODO_STORE
+            .with(|store| TodoStoreWrapper { store }.update_todo(principal, id, text, now))
+            .map(record_change)?;
+        Ok(quota_warnings(principal, 0))
+    })
 }
 
-/// Deletes a Todo item.
+/// Applies a partial update to a Todo item, changing only the provided fields.
 ///
 /// # Arguments
 ///
 /// * `id` - The unique identifier for the Todo item.
+/// * `patch` - The fields to be changed.
+///
+/// # Returns
+///
+/// A Result containing the quotas the caller used at least 80% of, or an Error if the Todo item is not found or the input is invalid.
 #[ic_cdk::update]
-fn delete_todo_item(id: TodoId) {
-    let principal = ic_cdk::caller();
-    TODO_STORE.with(|store| TodoStoreWrapper{store}.remove_todo(principal, id));
+fn patch_todo_item(id: TodoId, patch: PatchTodo) -> Result<Vec<Warning>, Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    store::with_cached_reads(|| {
+        TODO_STORE
+            .with(|store| TodoStoreWrapper { store }.patch_todo(principal, id, patch, now))
+            .map(record_change)?;
+        Ok(quota_warnings(principal, 0))
+    })
 }
 
-/// Marks a Todo item as complete.
+/// Applies a partial update to every Todo item of the caller that matches a filter,
+/// such as raising every overdue item tagged "work" to High priority.
+/// At most 500 Todo items are changed per call, and a call stops early once its instruction budget is spent;
+/// if Todo items remain, calling again with the returned `next` continues with them.
 ///
 /// # Arguments
 ///
-/// * `id` - The unique identifier for the Todo item.
+/// * `filter` - The conditions the Todo items must meet.
+/// * `patch` - The fields to be changed.
+/// * `after` - The `next` identifier returned by the previous call, or None to start from the first Todo item.
 ///
 /// # Returns
 ///
-/// A Result indicating success or an Error if the Todo item is not found.
+/// A Result containing the number of changed Todo items and where to continue, or an Error if the input is invalid.
 #[ic_cdk::update]
-fn toggle_todo_complete(id: TodoId) -> Result<(), Error> {
-    let principal = ic_cdk::caller();
-    TODO_STORE.with(|store| TodoStoreWrapper{store}.toggle_todo_complete(principal, id))
+fn bulk_update(filter: TodoFilter, patch: PatchTodo, after: Option<TodoId>) -> Result<BulkUpdate, Error> {
+    let (principal, _meter) = active_caller()?;
+    let filter = validation::filter(filter)?;
+    let now = clock::now();
+    let guard = instruction_guard();
+    let (outcome, changes) = TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.bulk_update(principal, &filter, patch, after, &guard, now))?;
+    changes.into_iter().for_each(record_change);
+    Ok(outcome)
 }
 
-/// Modifies the priority of a Todo item.
+/// Deletes a Todo item.
 ///
 /// # Arguments
 ///
 /// * `id` - The unique identifier for the Todo item.
-/// * `priority` - The new priority to be set.
 ///
 /// # Returns
 ///
 /// A Result indicating success or an Error if the Todo item is not found.
 #[ic_cdk::update]
-fn modify_todo_priority(id: TodoId, priority: Priority) -> Result<(), Error> {
-    let principal = ic_cdk::caller();
-    TODO_STORE.with(|store| TodoStoreWrapper { store }.modify_todo_priority(principal, id, priority))
+fn delete_todo_item(id: TodoId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let change = TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.remove_todo(principal, id))
+        .ok_or(Error::NotFound)?;
+    record_change(change);
+    Ok(())
 }
 
-/// Adds a tag to a Todo item.
+/// Applies the same partial update to a selection of Todo items, reporting the outcome of each item
+/// so that one invalid identifier does not abort the rest.
 ///
 /// # Arguments
 ///
-/// * `id` - The unique identifier for the Todo item.
-/// * `tag` - The tag to be added.
+/// * `ids` - The unique identifiers for the Todo items, at most 100.
+/// * `patch` - The fields to be changed.
 ///
 /// # Returns
 ///
-/// A Result indicating success or an Error if the Todo item is not found.
+/// A Result containing the outcome for each identifier in order, or an Error if too many are selected.
 #[ic_cdk::update]
-fn add_tag_to_todo_item(id: TodoId, tag: String) -> Result<(), Error> {
-    let principal = ic_cdk::caller();
-    TODO_STORE.with(|store| TodoStoreWrapper { store }.add_tag_to_todo(principal, id, tag))
+fn patch_todo_items(ids: Vec<TodoId>, patch: PatchTodo) -> Result<BatchOutcome, Error> {
+    validation::batch(&ids)?;
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    Ok(store::with_cached_reads(|| {
+        ids.into_iter()
+            .map(|id| {
+                let result = TODO_STORE
+                    .with(|store| TodoStoreWrapper { store }.patch_todo(principal, id, patch.clone(), now))
+                    .map(record_change);
+                (id, result)
+            })
+            .collect()
+    }))
 }
 
-/// Removes a tag from a Todo item.
+/// Deletes a selection of Todo items, reporting the outcome of each item
+/// so that one invalid identifier does not abort the rest.
 ///
 /// # Arguments
 ///
-/// * `id` - The unique identifier for the Todo item.
-/// * `tag` - The tag to be removed.
+/// * `ids` - The unique identifiers for the Todo items, at most 100.
 ///
 /// # Returns
 ///
-/// A Result indicating success or an Error if the Todo item is not found.
+/// A Result containing the outcome for each identifier in order, with a NotFound Error for unknown
+/// identifiers, or an Error if too many are selected.
 #[ic_cdk::update]
-fn remove_tag_from_todo_item(id: TodoId, tag: String) -> Result<(), Error> {
-    let principal = ic_cdk::caller();
-    TODO_STORE.with(|store| TodoStoreWrapper { store }.remove_tag_from_todo(principal, id, &tag))
+fn delete_todo_items(ids: Vec<TodoId>) -> Result<BatchOutcome, Error> {
+    validation::batch(&ids)?;
+    let (principal, _meter) = active_caller()?;
+    Ok(ids
+        .into_iter()
+        .map(|id| {
+            let result = TODO_STORE
+                .with(|store| TodoStoreWrapper { store }.remove_todo(principal, id))
+                .map(record_change)
+                .ok_or(Error::NotFound);
+            (id, result)
+        })
+        .collect())
 }
 
-/// Generates the next unique identifier for a Todo item.
+/// Adds the onboarding samples to the caller's Todo items. First-time callers receive them automatically.
 ///
 /// # Returns
 ///
-/// The next unique identifier for a Todo item.
-fn generate_next_id() -> TodoId {
-    LAST_TODO_ID.with(|id| {
-        let mut id = id.borrow_mut();
-        let new_id = *id.get() + 1;
-        id.set(new_id).unwrap()
-    })
+/// A Result containing the identifiers of the samples, or an Error if the caller still has samples.
+#[ic_cdk::update]
+fn bootstrap_samples() -> Result<Vec<TodoId>, Error> {
+    maintenance_mode().ensure_open()?;
+    let principal = ic_cdk::caller();
+    authorize(principal, Resource::Account(principal), Action::Manage)?;
+    let now = clock::now();
+    // The samples are added below, so a first-time caller must not receive them twice.
+    USER_STORE.with(|store| UserStoreWrapper { store }.touch(principal, now));
+    let todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+    if todos.iter().any(|todo| todo.sample) {
+        return Err(Error::AlreadyExists(Some("Samples are already present".to_string())));
+    }
+    Ok(add_samples(principal, now))
 }
 
+/// Removes every onboarding sample from the caller's Todo items, including samples they edited.
+///
+/// # Returns
+///
+/// A Result containing the number of removed samples.
+#[ic_cdk::update]
+fn clear_samples() -> Result<u64, Error> {
+    let (principal, _meter) = active_caller()?;
+    let changes = TODO_STORE.with(|store| TodoStoreWrapper { store }.remove_sample_todos(principal));
+    let removed = changes.len() as u64;
+    changes.into_iter().for_each(record_change);
+    Ok(removed)
+}
 
+/// Marks a Todo item as complete.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found.
+#[ic_cdk::update]
+fn toggle_todo_complete(id: TodoId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.toggle_todo_complete(principal, id, now))
+        .map(record_change)
+}
+
+/// Modifies the priority of a Todo item.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `priority` - The new priority to be set.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found.
+#[ic_cdk::update]
+fn modify_todo_priority(id: TodoId, priority: Priority) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.modify_todo_priority(principal, id, priority, now))
+        .map(record_change)
+}
+
+/// Adds a tag to a Todo item.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `tag` - The tag to be added.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found or the tag is invalid.
+#[ic_cdk::update]
+fn add_tag_to_todo_item(id: TodoId, tag: String) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.add_tag_to_todo(principal, id, tag, now))
+        .map(record_change)
+}
+
+/// Removes a tag from a Todo item.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `tag` - The tag to be removed.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found.
+#[ic_cdk::update]
+fn remove_tag_from_todo_item(id: TodoId, tag: String) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.remove_tag_from_todo(principal, id, &tag, now))
+        .map(record_change)
+}
+
+/// Sets or clears the place a Todo item is done at, so that it shows up when the caller is nearby.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `location` - The new location, or None to clear it.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found or the location is invalid.
+#[ic_cdk::update]
+fn set_location(id: TodoId, location: Option<GeoPoint>) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let location = location.map(validation::location).transpose()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.set_location(principal, id, location, now))
+        .map(record_change)
+}
+
+/// Lists the caller's open Todo items located within a radius of a place, such as where the caller is now.
+///
+/// # Arguments
+///
+/// * `point` - The place.
+/// * `radius_m` - The radius, in metres, at most 50 kilometres.
+///
+/// # Returns
+///
+/// A Result containing the Todo items with their distance, nearest first, or an Error if the place or radius is invalid.
+#[ic_cdk::query]
+fn list_todos_near(point: GeoPoint, radius_m: u32) -> Result<Vec<NearbyTodo>, Error> {
+    let principal = ic_cdk::caller();
+    let point = validation::location(point)?;
+    if radius_m > MAX_RADIUS_M {
+        return Err(Error::InvalidInput(format!("Radius cannot exceed {MAX_RADIUS_M} metres")));
+    }
+    let ids = GEO_INDEX.with(|store| GeoIndexWrapper { store }.candidates(principal, &point, radius_m));
+    let guard = instruction_guard();
+    let mut nearby: Vec<NearbyTodo> = TODO_STORE.with(|store| {
+        let wrapper = TodoStoreWrapper { store };
+        ids.into_iter()
+            .take_while(|_| !guard.exhausted())
+            .filter_map(|id| wrapper.get_todo(principal, id))
+            .filter_map(|todo| {
+                let distance_m = point.distance_m(todo.location.as_ref()?);
+                (distance_m <= f64::from(radius_m)).then_some(NearbyTodo { todo, distance_m: distance_m.round() as u32 })
+            })
+            .collect()
+    });
+    nearby.sort_by_key(|nearby| (nearby.distance_m, nearby.todo.id));
+    Ok(nearby)
+}
+
+/// Sets or clears the GTD context of a Todo item, such as `@home` or `@errands`.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `context` - The new context, or None to clear it; a missing leading `@` is added.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found or the context is invalid.
+#[ic_cdk::update]
+fn set_context(id: TodoId, context: Option<String>) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.set_context(principal, id, context, now))
+        .map(record_change)
+}
+
+/// Starts or stops tracking a recurring Todo item as a habit. A habit stays a single Todo item: checking in
+/// records the occurrence in its history and moves it on to the next one.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `habit` - Whether the Todo item is tracked as a habit.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found or does not recur.
+#[ic_cdk::update]
+fn set_habit(id: TodoId, habit: bool) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.set_habit(principal, id, habit, now))
+        .map(record_change)
+}
+
+/// Records the current occurrence of a habit as done and moves the habit on to its next occurrence.
+/// The current occurrence is the one it is due for, or today if it has no due date.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item tracked as a habit.
+///
+/// # Returns
+///
+/// A Result containing the local day recorded, or an Error if the Todo item is not found, not an open habit or its rule is not understood.
+#[ic_cdk::update]
+fn check_in_habit(id: TodoId) -> Result<Day, Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    store::with_cached_reads(|| {
+        let todo = TODO_STORE.with(|store| TodoStoreWrapper { store }.get_todo(principal, id)).ok_or(Error::NotFound)?;
+        let rule = todo.recurrence.as_deref().filter(|_| todo.habit && !todo.is_completed).ok_or_else(|| {
+            Error::Conflict(Some("Todo item is not an open habit".to_string()))
+        })?;
+        let recurrence = Recurrence::parse(rule)?;
+        let utc_offset = preferences_of(principal).utc_offset();
+        let day = local_day_of(todo.due_date.unwrap_or(now), utc_offset);
+        HABIT_STORE.with(|store| HabitStoreWrapper { store }.record(principal, id, day));
+        let due_date = recurrence.next_after(todo.due_date.map_or(now, |due_date| due_date.max(now)), utc_offset);
+        TODO_STORE
+            .with(|store| TodoStoreWrapper { store }.advance_habit(principal, id, due_date, now))
+            .map(record_change)?;
+        Ok(day)
+    })
+}
+
+/// Retrieves the days a habit was due or done on within a range, with its streaks.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item tracked as a habit.
+/// * `range` - Optional range, defaulting to the last 30 days; days are delimited in the caller's time zone.
+///
+/// # Returns
+///
+/// A Result containing the habit history, or an Error if the Todo item is not found, does not recur or the range is invalid.
+#[ic_cdk::query]
+fn get_habit_history(id: TodoId, range: Option<StatsRange>) -> Result<HabitHistory, Error> {
+    let principal = ic_cdk::caller();
+    let todo = TODO_STORE.with(|store| TodoStoreWrapper { store }.get_todo(principal, id)).ok_or(Error::NotFound)?;
+    let rule = todo.recurrence.as_deref().ok_or_else(|| {
+        Error::DependencyNotMet(Some("Todo item does not recur".to_string()))
+    })?;
+    let recurrence = Recurrence::parse(rule)?;
+    let utc_offset = preferences_of(principal).utc_offset();
+    let local = |timestamp: u64| timestamp.saturating_add_signed(utc_offset);
+    let now = clock::now();
+    let range = range.map(|range| StatsRange { from: local(range.from), to: local(range.to) });
+    let (from, to) = StatsRange::days(range, local(now))?;
+    let done = HABIT_STORE.with(|store| HabitStoreWrapper { store }.days(principal, id, from, to));
+    Ok(habits::history(&recurrence, &done, from, to, local_day_of(now, utc_offset)))
+}
+
+/// Sets or clears the rule by which a Todo item recurs. Completing a recurring Todo item creates its next
+/// occurrence, due at the next time the rule names in the caller's time zone, and moves the rule over to it.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `recurrence` - The new rule, such as `every day`, `every mon,wed 09:00` or `1st of month`, or None to stop recurring.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found or the rule is not understood.
+#[ic_cdk::update]
+fn set_recurrence(id: TodoId, recurrence: Option<String>) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.set_recurrence(principal, id, recurrence, now))
+        .map(record_change)
+}
+
+/// Turns the daily notification that a Todo item is overdue off or back on.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `muted` - Whether to stop notifying that the Todo item is overdue.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found.
+#[ic_cdk::update]
+fn mute_overdue_alerts(id: TodoId, muted: bool) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.set_overdue_muted(principal, id, muted, now))
+        .map(record_change)
+}
+
+/// Sets or clears the web page a Todo item refers to; a changed URL drops the cached link preview.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `url` - The new `https://` URL, or None to clear it.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found or the URL is invalid.
+#[ic_cdk::update]
+fn set_url(id: TodoId, url: Option<String>) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.set_url(principal, id, url, now))
+        .map(record_change)
+}
+
+/// Fetches the title and description of the page a Todo item refers to and caches them on the Todo item.
+/// A preview fetched less than a day ago is returned without fetching the page again, and a page that could
+/// not be fetched is not fetched again for an hour.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+///
+/// # Returns
+///
+/// A Result containing the preview, or an Error if the Todo item is not found, has no URL, the page could
+/// not be fetched or the caller fetched too many pages recently.
+#[ic_cdk::update]
+async fn fetch_link_preview(id: TodoId) -> Result<LinkPreview, Error> {
+    let (principal, _meter) = active_caller()?;
+    let todo = TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.get_todo(principal, id))
+        .ok_or(Error::NotFound)?;
+    let url = todo
+        .url
+        .ok_or_else(|| Error::DependencyNotMet(Some("The Todo item has no URL".to_string())))?;
+    if let Some(preview) = todo.link_preview.filter(|preview| preview.is_fresh(&url, clock::now())) {
+        return Ok(preview);
+    }
+    if FAILED_FETCHES.with(|failed| failed.borrow().is_failing(&url, clock::now())) {
+        return Err(Error::DependencyNotMet(Some("The page could not be fetched recently; try again later".to_string())));
+    }
+    throttle(Caller::Principal(principal))?;
+    let fetched = outcall::http_request(link_preview::fetch_request(&url), OUTCALL_CYCLES)
+        .await
+        .map_err(|(code, message)| Error::DependencyNotMet(Some(format!("{code:?}: {message}"))))
+        .and_then(|(response,)| {
+            link_preview::from_response(url.clone(), &response, clock::now()).ok_or_else(|| {
+                Error::DependencyNotMet(Some(format!("The page responded with status {}", response.status)))
+            })
+        });
+    let now = clock::now();
+    let preview = fetched.inspect_err(|_| FAILED_FETCHES.with(|failed| failed.borrow_mut().record(url, now)))?;
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.set_link_preview(principal, id, preview.clone(), now))
+        .map(record_change)?;
+    Ok(preview)
+}
+
+/// Lists the GTD contexts of the caller's open Todo items.
+///
+/// # Returns
+///
+/// A vector of contexts, in alphabetical order.
+#[ic_cdk::query]
+fn list_my_contexts() -> Vec<String> {
+    let principal = ic_cdk::caller();
+    TODO_STORE.with(|store| TodoStoreWrapper { store }.contexts(principal))
+}
+
+/// Lists the caller's open Todo items in a GTD context with pagination.
+///
+/// # Arguments
+///
+/// * `context` - The context, with or without its leading `@`.
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A Result containing the Todo items, or an Error if the context is invalid.
+#[ic_cdk::query]
+fn list_todos_in_context(context: String, paginator: Option<Paginator>) -> Result<Vec<Todo>, Error> {
+    let principal = ic_cdk::caller();
+    let context = validation::context(&context)?;
+    let page_size = preferences_of(principal).default_page_size;
+    let paginator = paginator.unwrap_or_default().with_default_limit(page_size);
+    Ok(TODO_STORE.with(|store| {
+        TodoStoreWrapper { store }.list_todos_in_context(principal, &context, paginator)
+    }))
+}
+
+/// Creates a namespace, a separate space of Todo items such as "work" or "personal".
+///
+/// # Arguments
+///
+/// * `name` - The name of the namespace.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the name is invalid, the namespace exists or too many namespaces exist.
+#[ic_cdk::update]
+fn create_namespace(name: String) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let name = validation::namespace(&name)?;
+    NAMESPACE_STORE.with(|store| NamespaceStoreWrapper { store }.create(principal, name, clock::now()))
+}
+
+/// Lists the caller's namespaces by name. Todo items outside of them belong to the default space.
+///
+/// # Returns
+///
+/// A vector of namespaces.
+#[ic_cdk::query]
+fn list_namespaces() -> Vec<Namespace> {
+    let principal = ic_cdk::caller();
+    NAMESPACE_STORE.with(|store| NamespaceStoreWrapper { store }.list(principal))
+}
+
+/// Deletes an empty namespace.
+///
+/// # Arguments
+///
+/// * `name` - The name of the namespace.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the namespace is not found or still holds Todo items.
+#[ic_cdk::update]
+fn delete_namespace(name: String) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    if TODO_STORE.with(|store| TodoStoreWrapper { store }.namespace_in_use(principal, &name)) {
+        return Err(Error::Conflict(Some("Namespace still holds Todo items".to_string())));
+    }
+    NAMESPACE_STORE.with(|store| NamespaceStoreWrapper { store }.delete(principal, &name))
+}
+
+/// Moves a Todo item to another namespace.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `namespace` - The namespace, or None for the default space.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item or the namespace is not found.
+#[ic_cdk::update]
+fn move_to_namespace(id: TodoId, namespace: Option<String>) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let namespace = existing_namespace(principal, namespace)?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.set_namespace(principal, id, namespace, now))
+        .map(record_change)
+}
+
+/// Lists the caller's Todo items in a namespace with pagination.
+///
+/// # Arguments
+///
+/// * `namespace` - The namespace, or None for the default space.
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A Result containing the Todo items, or an Error if the namespace is not found.
+#[ic_cdk::query]
+fn list_namespace_todos(namespace: Option<String>, paginator: Option<Paginator>) -> Result<Vec<Todo>, Error> {
+    let principal = ic_cdk::caller();
+    let namespace = existing_namespace(principal, namespace)?;
+    let page_size = preferences_of(principal).default_page_size;
+    let paginator = paginator.unwrap_or_default().with_default_limit(page_size);
+    Ok(TODO_STORE.with(|store| {
+        TodoStoreWrapper { store }.list_todos_in_namespace(principal, namespace.as_deref(), paginator)
+    }))
+}
+
+/// Sets or clears the energy level a Todo item takes.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `energy` - The new energy level, or None to clear it.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found.
+#[ic_cdk::update]
+fn set_energy(id: TodoId, energy: Option<Energy>) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.set_energy(principal, id, energy, now))
+        .map(record_change)
+}
+
+/// Sets or clears the estimate of how long a Todo item takes.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `minutes` - The estimate, in minutes, or None to clear it.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the estimate is invalid or the Todo item is not found.
+#[ic_cdk::update]
+fn set_estimate(id: TodoId, minutes: Option<u32>) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let minutes = minutes.map(validation::estimated_minutes).transpose()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.set_estimate(principal, id, minutes, now))
+        .map(record_change)
+}
+
+/// Lists the caller's open Todo items that fit the energy they have left, with pagination.
+///
+/// # Arguments
+///
+/// * `level` - The highest energy level to include; `Low` lists only low energy items.
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A vector of Todo items with an energy level, highest priority first, then soonest due date.
+#[ic_cdk::query]
+fn list_todos_by_energy(level: Energy, paginator: Option<Paginator>) -> Vec<Todo> {
+    let principal = ic_cdk::caller();
+    let page_size = preferences_of(principal).default_page_size;
+    let paginator = paginator.unwrap_or_default().with_default_limit(page_size);
+    TODO_STORE.with(|store| TodoStoreWrapper { store }.list_todos_by_energy(principal, level, paginator))
+}
+
+/// Marks a Todo item as delegated and waiting on someone, or clears the wait.
+/// The wait is timed from the moment the person waited on is set or changed.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `who` - The person or party waited on, or None if the Todo item is no longer waiting.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found or the name is invalid.
+#[ic_cdk::update]
+fn mark_waiting(id: TodoId, who: Option<String>) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.mark_waiting(principal, id, who, now))
+        .map(record_change)
+}
+
+/// Lists the caller's open Todo items that are waiting on someone, longest waiting first.
+/// Items waiting for at least the caller's follow-up period, 7 days by default, are flagged.
+///
+/// # Returns
+///
+/// A vector of waiting Todo items.
+#[ic_cdk::query]
+fn list_waiting() -> Vec<WaitingTodo> {
+    let principal = ic_cdk::caller();
+    let todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+    waiting::list(todos, clock::now(), preferences_of(principal).follow_up_after_days())
+}
+
+/// Begins uploading a file attached to a Todo item, reserving its size in the caller's attachment quota.
+/// The content is then sent with `upload_chunk` and the upload completed with `finish_upload`.
+///
+/// # Arguments
+///
+/// * `todo_id` - The unique identifier for the Todo item.
+/// * `name` - The file name.
+/// * `mime` - The MIME type of the file, e.g. `image/png`.
+/// * `size` - The size of the file, in bytes.
+///
+/// # Returns
+///
+/// A Result containing the identifier of the attachment, which serves as the upload handle, or an Error
+/// if the Todo item is not found, the metadata is invalid or the quota is exhausted.
+#[ic_cdk::update]
+fn begin_attachment_upload(todo_id: TodoId, name: String, mime: String, size: u64) -> Result<AttachmentId, Error> {
+    let (principal, _meter) = active_caller()?;
+    let name = validation::file_name(&name)?;
+    let mime = validation::mime_type(&mime)?;
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.get_todo(principal, todo_id))
+        .ok_or(Error::NotFound)?;
+    let now = clock::now();
+    ATTACHMENT_STORE.with(|store| {
+        AttachmentStoreWrapper { store }.begin(principal, todo_id, name, mime, size, now)
+    })
+}
+
+/// Appends the next chunk of at most 1 MB to an unfinished upload.
+///
+/// # Arguments
+///
+/// * `handle` - The identifier of the attachment returned by `begin_attachment_upload`.
+/// * `bytes` - The content of the chunk.
+///
+/// # Returns
+///
+/// A Result containing the index of the chunk, or an Error if the upload is not found or finished,
+/// or the chunk exceeds the announced size.
+#[ic_cdk::update]
+fn upload_chunk(handle: AttachmentId, bytes: Vec<u8>) -> Result<u32, Error> {
+    let (principal, _meter) = active_caller()?;
+    let index = ATTACHMENT_STORE
+        .with(|store| AttachmentStoreWrapper { store }.add_chunk(principal, handle, bytes.len()))?;
+    CHUNK_STORE.with(|store| ChunkStoreWrapper { store }.put(principal, handle, index, bytes));
+    Ok(index)
+}
+
+/// Completes an upload once all announced bytes have been received, making the file readable.
+///
+/// # Arguments
+///
+/// * `handle` - The identifier of the attachment returned by `begin_attachment_upload`.
+///
+/// # Returns
+///
+/// A Result containing the finished attachment, or an Error if it is not found or bytes are missing.
+#[ic_cdk::update]
+fn finish_upload(handle: AttachmentId) -> Result<Attachment, Error> {
+    let (principal, _meter) = active_caller()?;
+    let attachment = ATTACHMENT_STORE.with(|store| AttachmentStoreWrapper { store }.finish(principal, handle))?;
+    uncertify_download(principal, Download::Attachment(handle));
+    Ok(attachment)
+}
+
+/// Retrieves a chunk of a finished attachment; chunks are numbered from 0 to `chunk_count - 1`.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the attachment.
+/// * `index` - The position of the chunk.
+///
+/// # Returns
+///
+/// A Result containing the content of the chunk, or an Error if the attachment or chunk is not found
+/// or the upload is not finished.
+#[ic_cdk::query]
+fn get_attachment_chunk(id: AttachmentId, index: u32) -> Result<Vec<u8>, Error> {
+    let principal = ic_cdk::caller();
+    let attachment = ATTACHMENT_STORE
+        .with(|store| AttachmentStoreWrapper { store }.get(principal, id))
+        .ok_or(Error::NotFound)?;
+    if !attachment.complete {
+        return Err(Error::DependencyNotMet(Some("Upload is not finished".to_string())));
+    }
+    CHUNK_STORE
+        .with(|store| ChunkStoreWrapper { store }.get(principal, id, index))
+        .ok_or(Error::NotFound)
+}
+
+/// Lists the files attached to a Todo item, including unfinished uploads.
+///
+/// # Arguments
+///
+/// * `todo_id` - The unique identifier for the Todo item.
+///
+/// # Returns
+///
+/// A vector of attachments, in the order their uploads began.
+#[ic_cdk::query]
+fn list_attachments(todo_id: TodoId) -> Vec<Attachment> {
+    let principal = ic_cdk::caller();
+    ATTACHMENT_STORE.with(|store| AttachmentStoreWrapper { store }.list(principal, todo_id))
+}
+
+/// Deletes an attachment or abandons an upload, releasing its size from the caller's quota.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the attachment.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the attachment is not found.
+#[ic_cdk::update]
+fn delete_attachment(id: AttachmentId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    ATTACHMENT_STORE
+        .with(|store| AttachmentStoreWrapper { store }.remove(principal, id))
+        .ok_or(Error::NotFound)?;
+    CHUNK_STORE.with(|store| ChunkStoreWrapper { store }.remove_all(principal, id));
+    uncertify_download(principal, Download::Attachment(id));
+    Ok(())
+}
+
+/// Exports everything the caller keeps in the canister as a versioned bundle, so that the account
+/// can move to another deployment with `import_account`.
+///
+/// # Returns
+///
+/// The account bundle.
+#[ic_cdk::query]
+fn export_account() -> AccountBundle {
+    let principal = ic_cdk::caller();
+    AccountBundle {
+        version: BUNDLE_VERSION,
+        exported_at: clock::now(),
+        todos: TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal)),
+        archived: ARCHIVE_STORE.with(|store| ArchiveStoreWrapper { store }.all(principal)),
+        preferences: preferences_of(principal),
+        labels: LABEL_STORE.with(|store| LabelStoreWrapper { store }.list(principal)),
+        goals: GOAL_STORE.with(|store| GoalStoreWrapper { store }.list(principal)),
+        checklists: CHECKLIST_STORE.with(|store| ChecklistStoreWrapper { store }.list(principal)),
+        columns: BOARD_STORE.with(|store| BoardStoreWrapper { store }.all_columns(principal)),
+        namespaces: NAMESPACE_STORE.with(|store| NamespaceStoreWrapper { store }.list(principal)),
+        grants: ACCESS_STORE.with(|store| AccessStoreWrapper { store }.list(principal)),
+    }
+}
+
+/// Restores an account bundle exported by `export_account` into the caller's account, which must not hold
+/// anything yet besides the onboarding samples; the samples are removed. Todo items get fresh identifiers,
+/// while labels, goals, checklists and columns keep theirs.
+///
+/// # Arguments
+///
+/// * `bundle` - The account bundle.
+///
+/// # Returns
+///
+/// A Result containing the number of restored Todo items, or an Error if the bundle is invalid or of
+/// an unsupported version, the caller's account is not empty or the bundle holds more Todo items than the quota allows.
+#[ic_cdk::update]
+fn import_account(bundle: AccountBundle) -> Result<u64, Error> {
+    let (principal, _meter) = active_caller()?;
+    let bundle = account::validate(principal, bundle)?;
+    let todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+    let empty = todos.iter().all(|todo| todo.sample)
+        && ARCHIVE_STORE.with(|store| ArchiveStoreWrapper { store }.ids(principal)).is_empty()
+        && LABEL_STORE.with(|store| LabelStoreWrapper { store }.list(principal)).is_empty()
+        && GOAL_STORE.with(|store| GoalStoreWrapper { store }.list(principal)).is_empty()
+        && CHECKLIST_STORE.with(|store| ChecklistStoreWrapper { store }.list(principal)).is_empty()
+        && BOARD_STORE.with(|store| BoardStoreWrapper { store }.all_columns(principal)).is_empty()
+        && NAMESPACE_STORE.with(|store| NamespaceStoreWrapper { store }.list(principal)).is_empty()
+        && ACCESS_STORE.with(|store| AccessStoreWrapper { store }.list(principal)).is_empty();
+    if !empty {
+        return Err(Error::Conflict(Some("An account can only be imported into an empty account".to_string())));
+    }
+    TODO_STORE.with(|store| TodoStoreWrapper { store }.remove_sample_todos(principal)).into_iter().for_each(record_change);
+    let restored = (bundle.todos.len() + bundle.archived.len()) as u64;
+    TODO_COUNT_STORE.with(|store| TodoCountStoreWrapper { store }.ensure_room(principal, restored))?;
+    let bundle = account::renumber(bundle, generate_next_id);
+    PREFERENCE_STORE.with(|store| PreferenceStoreWrapper { store }.set(principal, bundle.preferences))?;
+    LABEL_STORE.with(|store| LabelStoreWrapper { store }.restore(principal, bundle.labels));
+    GOAL_STORE.with(|store| GoalStoreWrapper { store }.restore(principal, bundle.goals));
+    CHECKLIST_STORE.with(|store| ChecklistStoreWrapper { store }.restore(principal, bundle.checklists));
+    BOARD_STORE.with(|store| BoardStoreWrapper { store }.restore(principal, bundle.columns));
+    NAMESPACE_STORE.with(|store| NamespaceStoreWrapper { store }.restore(principal, bundle.namespaces));
+    for grant in bundle.grants {
+        let scopes = grant.scopes.unwrap_or_default();
+        grant_access(principal, grant.grantee, scopes, grant.granted_at)?;
+    }
+    TODO_STORE.with(|store| TodoStoreWrapper { store }.restore_from_bundle(principal, bundle.todos)).into_iter().for_each(record_change);
+    // Archived Todo items keep counting towards the tags, their trends and the quota, as they do once archived here.
+    for todo in &bundle.archived {
+        TAG_STORE.with(|store| TagStoreWrapper { store }.add(principal, todo));
+        TAG_TREND_STORE.with(|store| TagTrendStoreWrapper { store }.add(principal, todo));
+    }
+    TODO_COUNT_STORE.with(|store| TodoCountStoreWrapper { store }.add(principal, bundle.archived.len() as u64));
+    ARCHIVE_STORE.with(|store| ArchiveStoreWrapper { store }.archive(principal, bundle.archived));
+    Ok(restored)
+}
+
+/// Creates a download token for the caller, replacing the previous one.
+/// Browsers download the caller's Todo items from `/export.json?token=<token>` and finished
+/// attachments of up to 2 MB from `/attachments/<id>?token=<token>`, with certified responses.
+///
+/// # Returns
+///
+/// A Result containing the download token, or an Error if no randomness could be obtained.
+#[ic_cdk::update]
+async fn create_download_token() -> Result<String, Error> {
+    let (principal, _meter) = active_caller()?;
+    let (random,) = raw_rand()
+        .await
+        .map_err(|(_, message)| Error::DependencyNotMet(Some(message)))?;
+    let token = DOWNLOAD_TOKEN_STORE.with(|store| DownloadTokenStoreWrapper { store }.issue(principal, &random));
+    uncertify_downloads(principal);
+    Ok(token)
+}
+
+/// Revokes the caller's download token, after which its download URLs stop working.
+///
+/// # Returns
+///
+/// A Result indicating success.
+#[ic_cdk::update]
+fn revoke_download_token() -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    DOWNLOAD_TOKEN_STORE.with(|store| DownloadTokenStoreWrapper { store }.revoke(principal));
+    uncertify_downloads(principal);
+    Ok(())
+}
+
+/// Creates an API token for clients that cannot authenticate with the Internet Computer, such as CLI tools.
+/// Requests under `/api/` carrying `Authorization: Bearer <token>` act as the caller within the granted scopes:
+/// `GET /api/todos?limit=<n>&after=<id>` lists a page of the Todo items and `POST /api/todos` creates one from a JSON body.
+/// Only a hash of the token is kept, so it is shown once.
+///
+/// # Arguments
+///
+/// * `scopes` - What requests authenticated with the token may do.
+/// * `expires_at` - Time the token stops working, in nanoseconds since the Unix epoch, or None if it does not expire.
+///
+/// # Returns
+///
+/// A Result containing the API token, or an Error if the input is invalid, too many tokens exist or no randomness could be obtained.
+#[ic_cdk::update]
+async fn create_api_token(scopes: Vec<ApiScope>, expires_at: Option<u64>) -> Result<String, Error> {
+    let (principal, _meter) = active_caller()?;
+    ensure_feature(Feature::ApiTokens, principal)?;
+    let (random,) = raw_rand()
+        .await
+        .map_err(|(_, message)| Error::DependencyNotMet(Some(message)))?;
+    let now = clock::now();
+    API_TOKEN_STORE.with(|store| ApiTokenStoreWrapper { store }.issue(principal, scopes, expires_at, &random, now))
+}
+
+/// Lists the caller's API tokens without their secrets.
+///
+/// # Returns
+///
+/// A vector of API tokens, oldest first.
+#[ic_cdk::query]
+fn list_api_tokens() -> Vec<ApiTokenInfo> {
+    let principal = ic_cdk::caller();
+    API_TOKEN_STORE.with(|store| ApiTokenStoreWrapper { store }.list(principal))
+}
+
+/// Revokes one of the caller's API tokens, after which requests carrying it are rejected.
+///
+/// # Arguments
+///
+/// * `id` - The identifier of the token.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the token is not found.
+#[ic_cdk::update]
+fn revoke_api_token(id: ApiTokenId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    API_TOKEN_STORE.with(|store| ApiTokenStoreWrapper { store }.revoke(principal, id))
+}
+
+/// Sets up an integration through which an external service creates Todo items for the caller.
+/// The service posts its events to the returned path and signs each body with HMAC-SHA256 using the
+/// returned secret, sent as `X-Hub-Signature-256: sha256=<hex>` the way GitHub does.
+///
+/// # Arguments
+///
+/// * `kind` - The service events are received from.
+/// * `mapping` - The project, tags and priority the created Todo items are filed under.
+///
+/// # Returns
+///
+/// A Result containing the integration with its secret, or an Error if the mapping is invalid, too many
+/// integrations exist or no randomness could be obtained.
+#[ic_cdk::update]
+async fn create_integration(kind: IntegrationKind, mapping: IntegrationMapping) -> Result<NewIntegration, Error> {
+    let (principal, _meter) = active_caller()?;
+    ensure_feature(Feature::Webhooks, principal)?;
+    let mapping = validation::integration_mapping(mapping)?;
+    let (random,) = raw_rand()
+        .await
+        .map_err(|(_, message)| Error::DependencyNotMet(Some(message)))?;
+    let id = generate_next_integration_id();
+    INTEGRATION_STORE.with(|store| IntegrationStoreWrapper { store }.create(principal, id, kind, mapping, &random))
+}
+
+/// Lists the caller's integrations without their secrets.
+///
+/// # Returns
+///
+/// A vector of integrations, oldest first.
+#[ic_cdk::query]
+fn list_integrations() -> Vec<IntegrationInfo> {
+    let principal = ic_cdk::caller();
+    INTEGRATION_STORE.with(|store| IntegrationStoreWrapper { store }.list(principal))
+}
+
+/// Removes one of the caller's integrations, after which events posted to it are rejected.
+///
+/// # Arguments
+///
+/// * `id` - The identifier of the integration.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the integration is not found.
+#[ic_cdk::update]
+fn delete_integration(id: IntegrationId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    INTEGRATION_STORE.with(|store| IntegrationStoreWrapper { store }.delete(principal, id))
+}
+
+/// Links a Todo item to an item in another canister of the same deployment, or removes the link.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `linked` - The canister holding the linked Todo item and its identifier there, or None to remove the link.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found or links to itself.
+#[ic_cdk::update]
+fn link_todo(id: TodoId, linked: Option<(Principal, TodoId)>) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    if linked == Some((ic_cdk::id(), id)) {
+        return Err(Error::InvalidInput("A Todo item cannot link to itself".to_string()));
+    }
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.set_linked(principal, id, linked, now))
+        .map(record_change)
+}
+
+/// Relates one of the caller's Todo items to another, replacing any relation between them.
+///
+/// # Arguments
+///
+/// * `a` - The unique identifier for the Todo item the relation starts at.
+/// * `b` - The unique identifier for the Todo item the relation points at, as in "a DuplicateOf b".
+/// * `kind` - How the Todo items are related.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if a Todo item is not found, is related to itself or to too many others.
+#[ic_cdk::update]
+fn link_todos(a: TodoId, b: TodoId, kind: RelationKind) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    TODO_STORE.with(|store| {
+        let wrapper = TodoStoreWrapper { store };
+        [a, b].into_iter().try_for_each(|id| wrapper.get_todo(principal, id).map(|_| ()).ok_or(Error::NotFound))
+    })?;
+    RELATION_STORE.with(|store| RelationStoreWrapper { store }.link(principal, a, b, kind))
+}
+
+/// Removes the relation between two of the caller's Todo items.
+///
+/// # Arguments
+///
+/// * `a` - The unique identifier for one of the Todo items.
+/// * `b` - The unique identifier for the other Todo item.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo items are not related.
+#[ic_cdk::update]
+fn unlink_todos(a: TodoId, b: TodoId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    RELATION_STORE.with(|store| RelationStoreWrapper { store }.unlink(principal, a, b))
+}
+
+/// Lists the Todo items related to one of the caller's Todo items, in both directions.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+///
+/// # Returns
+///
+/// A Result containing the related Todo items by identifier, or an Error if the Todo item is not found.
+#[ic_cdk::query]
+fn get_related(id: TodoId) -> Result<Vec<RelatedTodo>, Error> {
+    let principal = ic_cdk::caller();
+    TODO_STORE.with(|store| TodoStoreWrapper { store }.get_todo(principal, id)).ok_or(Error::NotFound)?;
+    Ok(RELATION_STORE.with(|store| RelationStoreWrapper { store }.related(principal, id)))
+}
+
+/// Looks up the caller's Todo items that link to items in other canisters, asking each of those canisters in turn.
+/// A canister that cannot be reached or does not know the item leaves that link unavailable instead of failing the query.
+///
+/// # Returns
+///
+/// A vector of links with the outcome of each lookup, ordered by the identifier of the linking Todo item.
+#[ic_cdk::query(composite = true)]
+async fn resolve_linked_todos() -> Vec<ResolvedLink> {
+    let principal = ic_cdk::caller();
+    let own_canister = ic_cdk::id();
+    let links: Vec<(TodoId, Principal, TodoId)> = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal))
+        .into_iter()
+        .filter_map(|todo| todo.linked.map(|(canister, linked_id)| (todo.id, canister, linked_id)))
+        .collect();
+    let mut resolved = Vec::with_capacity(links.len());
+    for (index, (todo_id, canister, linked_id)) in links.into_iter().enumerate() {
+        let status = if index >= MAX_RESOLVED_LINKS {
+            LinkStatus::Unavailable(format!("Only {MAX_RESOLVED_LINKS} links are looked up at once"))
+        } else if canister == own_canister {
+            TODO_STORE
+                .with(|store| TodoStoreWrapper { store }.get_todo(principal, linked_id))
+                .map_or_else(|| LinkStatus::Unavailable(Error::NotFound.to_string()), |todo| LinkStatus::Resolved(todo.into()))
+        } else {
+            links::link_status(ic_cdk::call(canister, REFERENCE_METHOD, (principal, linked_id)).await)
+        };
+        resolved.push(ResolvedLink { todo_id, canister, linked_id, status });
+    }
+    resolved
+}
+
+/// Looks up a Todo item on behalf of one of its owner's Todo items in a peer canister.
+/// Only the peer canisters set by a controller may call this endpoint, and they only learn the description, status and due date.
+///
+/// # Arguments
+///
+/// * `owner` - The principal owning the Todo item, as vouched for by the peer canister.
+/// * `id` - The unique identifier for the Todo item.
+///
+/// # Returns
+///
+/// A Result containing the Todo item's reference, or an Error if the caller is not a peer or the item is not found.
+#[ic_cdk::query]
+fn get_todo_reference(owner: Principal, id: TodoId) -> Result<TodoReference, Error> {
+    authorize(ic_cdk::caller(), Resource::Account(owner), Action::Reference)?;
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.get_todo(owner, id))
+        .map(TodoReference::from)
+        .ok_or(Error::NotFound)
+}
+
+/// Replaces the peer canisters trusted to look up linked Todo items in this canister.
+/// Only admins of the canister may call this endpoint.
+///
+/// # Arguments
+///
+/// * `peers` - The principals of the peer canisters.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the caller is not an admin or too many peers are given.
+#[ic_cdk::update]
+fn set_peer_canisters(peers: Vec<Principal>) -> Result<(), Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Administer)?;
+    PEER_STORE.with(|store| PeerStoreWrapper { store }.replace(peers))
+}
+
+/// Lists the peer canisters trusted to look up linked Todo items in this canister.
+/// Only admins of the canister may call this endpoint.
+///
+/// # Returns
+///
+/// A Result containing the peer canisters, or an Error if the caller is not an admin.
+#[ic_cdk::query]
+fn list_peer_canisters() -> Result<Vec<Principal>, Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Administer)?;
+    Ok(PEER_STORE.with(|store| PeerStoreWrapper { store }.list()))
+}
+
+/// Lists the archived Todo items of the caller with pagination.
+///
+/// # Arguments
+///
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A vector of archived Todo items.
+#[ic_cdk::query]
+fn list_archived_todo_items(paginator: Option<Paginator>) -> Vec<Todo> {
+    let principal = ic_cdk::caller();
+    let page_size = preferences_of(principal).default_page_size;
+    let paginator = paginator.unwrap_or_default().with_default_limit(page_size);
+    ARCHIVE_STORE.with(|store| ArchiveStoreWrapper { store }.list(principal, paginator))
+}
+
+/// Moves an archived Todo item back to the active list.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not archived or its identifier is in use by an
+/// active Todo item, in which case it stays archived.
+#[ic_cdk::update]
+fn unarchive_todo_item(id: TodoId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let todo = ARCHIVE_STORE
+        .with(|store| ArchiveStoreWrapper { store }.take(principal, id))
+        .ok_or(Error::NotFound)?;
+    if let Err(error) = TODO_STORE.with(|store| TodoStoreWrapper { store }.put_back_todo(principal, todo.clone())) {
+        // The identifier is in use again; the Todo item stays archived rather than being lost.
+        ARCHIVE_STORE.with(|store| ArchiveStoreWrapper { store }.archive(principal, vec![todo]));
+        return Err(error);
+    }
+    index_todo(principal, &todo);
+    EVENT_STORE.with(|store| EventStoreWrapper { store }.append(principal, TodoEventKind::Put(Box::new(todo)), clock::now()));
+    Ok(())
+}
+
+/// Lists the previous versions of a Todo item, keeping the latest 20 changes or as many as the caller set in their preferences.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+///
+/// # Returns
+///
+/// A Result containing the previous versions, newest first, or an Error if the Todo item is not found.
+#[ic_cdk::query]
+fn get_todo_history(id: TodoId) -> Result<Vec<TodoRevision>, Error> {
+    let principal = ic_cdk::caller();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.get_todo(principal, id))
+        .ok_or(Error::NotFound)?;
+    Ok(HISTORY_STORE.with(|store| HistoryStoreWrapper { store }.list_revisions(principal, id)))
+}
+
+/// Restores a Todo item to the version it had before the given revision.
+/// The reverted version is itself kept in the history, so the revert can be undone.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `revision` - The revision number, as listed by `get_todo_history`.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item or revision is not found.
+#[ic_cdk::update]
+fn revert_todo(id: TodoId, revision: Revision) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let previous = HISTORY_STORE
+        .with(|store| HistoryStoreWrapper { store }.get_revision(principal, id, revision))
+        .ok_or(Error::NotFound)?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.restore_todo(principal, id, previous, now))
+        .map(record_change)
+}
+
+/// Defines a label that Todo items refer to by its identifier.
+///
+/// # Arguments
+///
+/// * `name` - The name of the label, unique among the caller's labels regardless of letter case.
+/// * `color` - The color of the label, such as `#ff8800`.
+///
+/// # Returns
+///
+/// A Result containing the unique identifier for the new label, or an Error if the input is invalid, the name is taken or too many labels exist.
+#[ic_cdk::update]
+fn create_label(name: String, color: String) -> Result<LabelId, Error> {
+    let (principal, _meter) = active_caller()?;
+    let name = validation::label_name(&name)?;
+    let color = validation::color(&color)?;
+    LABEL_STORE.with(|store| LabelStoreWrapper { store }.create(principal, name, color))
+}
+
+/// Renames and recolors a label; the Todo items carrying it are left untouched.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the label.
+/// * `name` - The new name of the label.
+/// * `color` - The new color of the label.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the label is not found, the input is invalid or the name is taken.
+#[ic_cdk::update]
+fn update_label(id: LabelId, name: String, color: String) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let name = validation::label_name(&name)?;
+    let color = validation::color(&color)?;
+    LABEL_STORE.with(|store| LabelStoreWrapper { store }.update(principal, id, name, color))
+}
+
+/// Deletes a label and detaches it from every Todo item carrying it, including archived ones.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the label.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the label is not found.
+#[ic_cdk::update]
+fn delete_label(id: LabelId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    LABEL_STORE.with(|store| LabelStoreWrapper { store }.delete(principal, id))?;
+    let changes = TODO_STORE.with(|store| TodoStoreWrapper { store }.remove_label_from_todos(principal, id, now));
+    for change in changes {
+        record_change(change);
+    }
+    ARCHIVE_STORE.with(|store| ArchiveStoreWrapper { store }.remove_label(principal, id));
+    Ok(())
+}
+
+/// Lists the caller's labels.
+///
+/// # Returns
+///
+/// A vector of labels in alphabetical order.
+#[ic_cdk::query]
+fn list_labels() -> Vec<Label> {
+    let principal = ic_cdk::caller();
+    LABEL_STORE.with(|store| LabelStoreWrapper { store }.list(principal))
+}
+
+/// Attaches a label to a Todo item.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `label_id` - The unique identifier for the label.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item or label is not found.
+#[ic_cdk::update]
+fn add_label_to_todo_item(id: TodoId, label_id: LabelId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    LABEL_STORE.with(|store| LabelStoreWrapper { store }.get(principal, label_id)).ok_or(Error::NotFound)?;
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.add_label_to_todo(principal, id, label_id, now))
+        .map(record_change)
+}
+
+/// Detaches a label from a Todo item.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `label_id` - The unique identifier for the label.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found.
+#[ic_cdk::update]
+fn remove_label_from_todo_item(id: TodoId, label_id: LabelId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.remove_label_from_todo(principal, id, label_id, now))
+        .map(record_change)
+}
+
+/// Saves a checklist of subtasks, such as the steps of a release, to add to Todo items later.
+///
+/// # Arguments
+///
+/// * `name` - The name of the checklist.
+/// * `items` - The titles of the subtasks, in order.
+///
+/// # Returns
+///
+/// A Result containing the unique identifier for the new checklist, or an Error if the input is invalid or too many checklists exist.
+#[ic_cdk::update]
+fn create_checklist(name: String, items: Vec<String>) -> Result<ChecklistId, Error> {
+    let (principal, _meter) = active_caller()?;
+    let name = validation::checklist_name(&name)?;
+    let items = validation::checklist_items(items)?;
+    CHECKLIST_STORE.with(|store| ChecklistStoreWrapper { store }.create(principal, name, items))
+}
+
+/// Deletes a checklist; the subtasks it added to Todo items are kept.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the checklist.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the checklist is not found.
+#[ic_cdk::update]
+fn delete_checklist(id: ChecklistId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    CHECKLIST_STORE.with(|store| ChecklistStoreWrapper { store }.delete(principal, id))
+}
+
+/// Lists the caller's checklists in the order they were saved.
+///
+/// # Returns
+///
+/// A vector of checklists.
+#[ic_cdk::query]
+fn list_checklists() -> Vec<Checklist> {
+    let principal = ic_cdk::caller();
+    CHECKLIST_STORE.with(|store| ChecklistStoreWrapper { store }.list(principal))
+}
+
+/// Appends the items of a checklist to a Todo item as open subtasks.
+///
+/// # Arguments
+///
+/// * `todo_id` - The unique identifier for the Todo item.
+/// * `checklist_id` - The unique identifier for the checklist.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item or checklist is not found, or the Todo item would have too many subtasks.
+#[ic_cdk::update]
+fn apply_checklist(todo_id: TodoId, checklist_id: ChecklistId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    let checklist = CHECKLIST_STORE.with(|store| ChecklistStoreWrapper { store }.get(principal, checklist_id)).ok_or(Error::NotFound)?;
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.add_subtasks(principal, todo_id, checklist.items, now))
+        .map(record_change)
+}
+
+/// Ticks off a subtask of a Todo item, or reopens it.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `index` - The position of the subtask, starting at 0.
+/// * `done` - Whether the subtask is done.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item or subtask is not found.
+#[ic_cdk::update]
+fn set_subtask_done(id: TodoId, index: u32, done: bool) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.set_subtask_done(principal, id, index, done, now))
+        .map(record_change)
+}
+
+/// Sets a longer-term goal that Todo items can be attached to.
+///
+/// # Arguments
+///
+/// * `title` - The title of the goal.
+/// * `target` - What reaching the goal takes: a number of completed Todo items or a date by which every attached Todo item is completed.
+///
+/// # Returns
+///
+/// A Result containing the unique identifier for the new goal, or an Error if the input is invalid or too many goals exist.
+#[ic_cdk::update]
+fn create_goal(title: String, target: GoalTarget) -> Result<GoalId, Error> {
+    let (principal, _meter) = active_caller()?;
+    let title = validation::goal_title(&title)?;
+    let target = validation::goal_target(target)?;
+    let now = clock::now();
+    GOAL_STORE.with(|store| GoalStoreWrapper { store }.create(principal, title, target, now))
+}
+
+/// Deletes a goal and detaches every Todo item from it, including archived ones.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the goal.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the goal is not found.
+#[ic_cdk::update]
+fn delete_goal(id: GoalId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    GOAL_STORE.with(|store| GoalStoreWrapper { store }.delete(principal, id))?;
+    let changes = TODO_STORE.with(|store| TodoStoreWrapper { store }.remove_goal_from_todos(principal, id, now));
+    for change in changes {
+        record_change(change);
+    }
+    ARCHIVE_STORE.with(|store| ArchiveStoreWrapper { store }.remove_goal(principal, id));
+    Ok(())
+}
+
+/// Attaches a Todo item to a goal, or detaches it.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `goal_id` - The unique identifier for the goal, or None to detach the Todo item.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item or goal is not found.
+#[ic_cdk::update]
+fn set_todo_goal(id: TodoId, goal_id: Option<GoalId>) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    if let Some(goal_id) = goal_id {
+        GOAL_STORE.with(|store| GoalStoreWrapper { store }.get(principal, goal_id)).ok_or(Error::NotFound)?;
+    }
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.set_goal(principal, id, goal_id, now))
+        .map(record_change)
+}
+
+/// Lists the caller's goals with their progress, computed from the completions of the attached Todo items,
+/// archived ones included.
+///
+/// # Returns
+///
+/// A vector of goals with their progress, in the order they were set.
+#[ic_cdk::query]
+fn list_goals_with_progress() -> Vec<GoalProgress> {
+    let principal = ic_cdk::caller();
+    let goals = GOAL_STORE.with(|store| GoalStoreWrapper { store }.list(principal));
+    let mut todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+    todos.extend(ARCHIVE_STORE.with(|store| ArchiveStoreWrapper { store }.all(principal)));
+    goals::progress(goals, &todos)
+}
+
+/// Adds a column to the right end of the board of a project.
+///
+/// # Arguments
+///
+/// * `project` - The project whose board the column belongs to.
+/// * `name` - The name of the column.
+///
+/// # Returns
+///
+/// A Result containing the unique identifier for the new column, or an Error if the input is invalid.
+#[ic_cdk::update]
+fn create_column(project: String, name: String) -> Result<ColumnId, Error> {
+    let (principal, _meter) = active_caller()?;
+    let project = validation::project(&project)?;
+    let name = validation::column_name(&name)?;
+    Ok(BOARD_STORE.with(|store| BoardStoreWrapper { store }.create_column(principal, project, name)))
+}
+
+/// Moves a Todo item to a position within a board column, adding it to the column's project.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+/// * `column_id` - The unique identifier for the column.
+/// * `position` - The position from the top; positions past the end place the Todo item last.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item or column is not found.
+#[ic_cdk::update]
+fn move_todo_to_column(id: TodoId, column_id: ColumnId, position: u32) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    BOARD_STORE.with(|store| {
+        let board = BoardStoreWrapper { store };
+        let column = board.get_column(principal, column_id).ok_or(Error::NotFound)?;
+        let project = column.project;
+        TODO_STORE
+            .with(|store| {
+                TodoStoreWrapper { store }.move_todo_to_column(principal, id, column_id, project, now)
+            })
+            .map(record_change)?;
+        board.place_card(principal, column_id, id, position)
+    })
+}
+
+/// Retrieves the board of a project with the Todo items placed in each column.
+///
+/// # Arguments
+///
+/// * `project` - The project whose board is retrieved.
+///
+/// # Returns
+///
+/// The board, with its columns from left to right and cards from top to bottom.
+#[ic_cdk::query]
+fn get_board(project: String) -> Board {
+    board_of(ic_cdk::caller(), project)
+}
+
+/// Assembles the board of a principal's project with the Todo items placed in each column.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `project` - The project whose board is assembled.
+fn board_of(principal: Principal, project: String) -> Board {
+    let columns = BOARD_STORE.with(|store| BoardStoreWrapper { store }.columns(principal, &project));
+    let columns = TODO_STORE.with(|store| {
+        let wrapper = TodoStoreWrapper { store };
+        columns
+            .into_iter()
+            .map(|column| BoardColumn {
+                id: column.id,
+                name: column.name,
+                cards: column
+                    .cards
+                    .iter()
+                    .filter_map(|id| wrapper.get_todo(principal, *id))
+                    .filter(|todo| todo.column_id == Some(column.id))
+                    .collect(),
+            })
+            .collect()
+    });
+    Board { project, columns }
+}
+
+/// Grants another principal read-only access to the caller's whole account, such as a coach reviewing the caller's Todo items.
+///
+/// # Arguments
+///
+/// * `principal` - The principal the access is granted to.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the principal is the caller or anonymous, or too many principals have access.
+#[ic_cdk::update]
+fn grant_account_viewer(principal: Principal) -> Result<(), Error> {
+    let (owner, _meter) = active_caller()?;
+    let now = clock::now();
+    grant_access(owner, principal, Vec::new(), now)
+}
+
+/// Grants another principal, such as a virtual assistant, access to the caller's whole account with some changes
+/// allowed: adding Todo items, ticking them off, or both. Deleting is never allowed.
+///
+/// # Arguments
+///
+/// * `principal` - The principal the access is granted to.
+/// * `scopes` - What the principal may change besides reading the account.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the principal is the caller or anonymous, or too many principals have access.
+#[ic_cdk::update]
+fn grant_account_assistant(principal: Principal, scopes: Vec<AccountScope>) -> Result<(), Error> {
+    let (owner, _meter) = active_caller()?;
+    let now = clock::now();
+    grant_access(owner, principal, scopes, now)
+}
+
+/// Revokes the access of another principal to the caller's account, whether read-only or with changes allowed.
+///
+/// # Arguments
+///
+/// * `principal` - The principal the access was granted to.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the principal has no access.
+#[ic_cdk::update]
+fn revoke_account_viewer(principal: Principal) -> Result<(), Error> {
+    let (owner, _meter) = active_caller()?;
+    ACCESS_STORE.with(|store| AccessStoreWrapper { store }.revoke(owner, principal))?;
+    SHARED_ACCOUNT_INDEX.with(|store| SharedAccountIndexWrapper { store }.remove(owner, principal));
+    Ok(())
+}
+
+/// Lists the principals granted access to the caller's account, with what they may change.
+///
+/// # Returns
+///
+/// A vector of grants, by principal.
+#[ic_cdk::query]
+fn list_account_viewers() -> Vec<AccountGrant> {
+    let owner = ic_cdk::caller();
+    ACCESS_STORE.with(|store| AccessStoreWrapper { store }.list(owner))
+}
+
+/// Retrieves a Todo item of an account the caller may view.
+///
+/// # Arguments
+///
+/// * `owner` - The principal owning the account.
+/// * `id` - The unique identifier for the Todo item.
+///
+/// # Returns
+///
+/// A Result containing the Todo item, or an Error if the caller has no access or the item is not found.
+#[ic_cdk::query]
+fn get_account_todo(owner: Principal, id: TodoId) -> Result<Todo, Error> {
+    let owner = viewable_account(owner)?;
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.get_todo(owner, id))
+        .ok_or(Error::NotFound)
+}
+
+/// Lists the Todo items of an account the caller may view, in the order and page size set by its owner.
+///
+/// # Arguments
+///
+/// * `owner` - The principal owning the account.
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A Result containing the Todo items, or an Error if the caller has no access.
+#[ic_cdk::query]
+fn list_account_todos(owner: Principal, paginator: Option<Paginator>) -> Result<Vec<Todo>, Error> {
+    Ok(list_todos_of(viewable_account(owner)?, paginator))
+}
+
+/// Retrieves the board of a project in an account the caller may view.
+///
+/// # Arguments
+///
+/// * `owner` - The principal owning the account.
+/// * `project` - The project whose board is retrieved.
+///
+/// # Returns
+///
+/// A Result containing the board, or an Error if the caller has no access.
+#[ic_cdk::query]
+fn get_account_board(owner: Principal, project: String) -> Result<Board, Error> {
+    Ok(board_of(viewable_account(owner)?, project))
+}
+
+/// Searches the caller's Todo items for words in their description or notes, and optionally those of every
+/// account shared with the caller, so that one search box covers all the Todo items, projects included,
+/// the caller can read. The caller's own Todo items come first, then those of each shared account.
+/// Accounts are only read up to the requested page, and the search stops once the call spent its instruction
+/// budget, so that a page may come back short for very large accounts.
+///
+/// # Arguments
+///
+/// * `search` - The words to search for, with optional conditions and whether shared accounts are included.
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A Result containing the matching Todo items with their owners, or an Error if the search is invalid.
+#[ic_cdk::query]
+fn search_todos(search: TodoSearch, paginator: Option<Paginator>) -> Result<Vec<SearchHit>, Error> {
+    let principal = ic_cdk::caller();
+    let words = search::words(&validation::search_text(&search.text)?);
+    let filter = validation::filter(search.filter.unwrap_or_default())?;
+    let mut owners = vec![principal];
+    if search.include_shared.unwrap_or(false) {
+        let shared = SHARED_ACCOUNT_INDEX.with(|store| SharedAccountIndexWrapper { store }.owners(principal));
+        owners.extend(shared.into_iter().filter(|owner| authorize(principal, Resource::Account(*owner), Action::Read).is_ok()));
+    }
+    let paginator = paginator.unwrap_or_default();
+    let needed = paginator.skip() + paginator.limit();
+    let now = clock::now();
+    let guard = instruction_guard();
+    let mut hits = Vec::new();
+    for owner in owners {
+        if hits.len() >= needed || guard.exhausted() {
+            break;
+        }
+        let todos = TODO_STORE.with(|store| {
+            TodoStoreWrapper { store }.find_todos(owner, needed - hits.len(), &guard, |todo| {
+                filter.matches(todo, now) && search::matches(todo, &words)
+            })
+        });
+        hits.extend(todos.into_iter().map(|todo| SearchHit { owner, todo }));
+    }
+    Ok(hits.into_iter().skip(paginator.skip()).collect())
+}
+
+/// Creates a Todo item in an account the caller was granted to add Todo items to.
+///
+/// # Arguments
+///
+/// * `owner` - The principal owning the account.
+/// * `request` - The fields of the new Todo item.
+///
+/// # Returns
+///
+/// A Result containing the unique identifier for the new Todo item, or an Error if the caller may not add Todo items
+/// to the account or the input is invalid.
+#[ic_cdk::update]
+fn create_account_todo(owner: Principal, request: CreateTodo) -> Result<TodoId, Error> {
+    let (actor, _meter) = active_caller()?;
+    authorize(actor, Resource::Account(owner), Action::Create)?;
+    let request = with_default_priority(owner, request);
+    let id = generate_next_id();
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.create_todo(owner, id, request, now))
+        .map(record_change)?;
+    Ok(id)
+}
+
+/// Marks a Todo item as complete in an account the caller was granted to tick off Todo items in.
+///
+/// # Arguments
+///
+/// * `owner` - The principal owning the account.
+/// * `id` - The unique identifier for the Todo item.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the caller may not tick off Todo items in the account or the item is not found.
+#[ic_cdk::update]
+fn complete_account_todo(owner: Principal, id: TodoId) -> Result<(), Error> {
+    let (actor, _meter) = active_caller()?;
+    authorize(actor, Resource::Account(owner), Action::Complete)?;
+    let now = clock::now();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.complete_todo(owner, id, now))
+        .map(record_change)
+}
+
+/// Grants a principal access to the account of another and records the account as shared with them.
+///
+/// # Arguments
+///
+/// * `owner` - The principal owning the account.
+/// * `grantee` - The principal the access is granted to.
+/// * `scopes` - What the grantee may change besides reading the account.
+/// * `granted_at` - The time the access is granted, in nanoseconds since the Unix epoch.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the grantee is the owner or anonymous, or too many grants exist.
+fn grant_access(owner: Principal, grantee: Principal, scopes: Vec<AccountScope>, granted_at: u64) -> Result<(), Error> {
+    ACCESS_STORE.with(|store| AccessStoreWrapper { store }.grant(owner, grantee, scopes, granted_at))?;
+    SHARED_ACCOUNT_INDEX.with(|store| SharedAccountIndexWrapper { store }.insert(owner, grantee));
+    Ok(())
+}
+
+/// Checks that the caller may read an account, being its owner or granted access to it.
+///
+/// # Arguments
+///
+/// * `owner` - The principal owning the account.
+///
+/// # Returns
+///
+/// A Result containing the owner, or an Unauthorized Error.
+fn viewable_account(owner: Principal) -> Result<Principal, Error> {
+    authorize(ic_cdk::caller(), Resource::Account(owner), Action::Read)?;
+    Ok(owner)
+}
+
+/// Starts a pomodoro session for a Todo item.
+///
+/// # Arguments
+///
+/// * `todo_id` - The unique identifier for the Todo item worked on.
+/// * `minutes` - The planned length of the session.
+///
+/// # Returns
+///
+/// A Result containing the identifier of the session, or an Error if the Todo item is not found or the length is invalid.
+#[ic_cdk::update]
+fn start_pomodoro(todo_id: TodoId, minutes: u32) -> Result<SessionId, Error> {
+    let (principal, _meter) = active_caller()?;
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.get_todo(principal, todo_id))
+        .ok_or(Error::NotFound)?;
+    let now = clock::now();
+    POMODORO_STORE.with(|store| PomodoroStoreWrapper { store }.start(principal, todo_id, minutes, now))
+}
+
+/// Completes a running pomodoro session.
+///
+/// # Arguments
+///
+/// * `session_id` - The unique identifier of the session.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the session is not found or already completed.
+#[ic_cdk::update]
+fn complete_pomodoro(session_id: SessionId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let now = clock::now();
+    POMODORO_STORE.with(|store| PomodoroStoreWrapper { store }.complete(principal, session_id, now))
+}
+
+/// Summarizes the pomodoro sessions the caller completed on a day.
+///
+/// # Arguments
+///
+/// * `day` - Optional day, counted in days since the Unix epoch, defaulting to today.
+///
+/// # Returns
+///
+/// The summary of the completed sessions.
+#[ic_cdk::query]
+fn get_pomodoro_summary(day: Option<Day>) -> PomodoroSummary {
+    let principal = ic_cdk::caller();
+    let day = day.unwrap_or_else(|| day_of(clock::now()));
+    POMODORO_STORE.with(|store| PomodoroStoreWrapper { store }.daily_summary(principal, day))
+}
+
+/// Retrieves the preferences of the caller.
+///
+/// # Returns
+///
+/// The preferences, or the defaults if the caller never set any.
+#[ic_cdk::query]
+fn get_preferences() -> Preferences {
+    let principal = ic_cdk::caller();
+    PREFERENCE_STORE.with(|store| PreferenceStoreWrapper { store }.get(principal))
+}
+
+/// Replaces the preferences of the caller.
+///
+/// # Arguments
+///
+/// * `preferences` - The new preferences.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if a setting is out of range.
+#[ic_cdk::update]
+fn set_preferences(preferences: Preferences) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    PREFERENCE_STORE.with(|store| PreferenceStoreWrapper { store }.set(principal, preferences))
+}
+
+/// Deletes the caller's archived Todo items and previous versions that their retention settings no longer keep,
+/// without waiting for the periodic maintenance.
+///
+/// # Returns
+///
+/// A Result containing what was removed.
+#[ic_cdk::update]
+fn purge_now() -> Result<PurgeSummary, Error> {
+    let (principal, _meter) = active_caller()?;
+    Ok(purge(principal, clock::now()))
+}
+
+/// Retrieves productivity statistics of the caller.
+///
+/// # Arguments
+///
+/// * `range` - Optional time range, defaulting to the last 30 days.
+///
+/// # Returns
+///
+/// A Result containing the statistics, or an Error if the range is invalid.
+#[ic_cdk::query]
+fn get_my_stats(range: Option<StatsRange>) -> Result<MyStats, Error> {
+    let principal = ic_cdk::caller();
+    let now = clock::now();
+    let (from, to) = StatsRange::days(range, now)?;
+    let (days, current_streak) = STATS_STORE.with(|store| {
+        let wrapper = StatsStoreWrapper { store };
+        (wrapper.daily_counts(principal, from, to), wrapper.current_streak(principal, day_of(now)))
+    });
+    let busiest_tags = TODO_STORE.with(|store| {
+        let (from, to) = (u64::from(from) * NANOS_PER_DAY, (u64::from(to) + 1) * NANOS_PER_DAY - 1);
+        TodoStoreWrapper { store }.busiest_tags(principal, from, to, BUSIEST_TAGS_LIMIT)
+    });
+    Ok(stats::summarize(days, current_streak, busiest_tags))
+}
+
+/// Compares the caller's estimates with the time the Todo items completed in a range took, overall and
+/// per tag and project. The time taken is the focus time tracked in pomodoro sessions, or else the
+/// time from creation to completion. Archived Todo items are included.
+///
+/// # Arguments
+///
+/// * `range` - Optional time range of the completions, defaulting to the last 30 days.
+///
+/// # Returns
+///
+/// A Result containing the report, or an Error if the range is invalid.
+#[ic_cdk::query]
+fn get_estimation_report(range: Option<StatsRange>) -> Result<EstimationReport, Error> {
+    let principal = ic_cdk::caller();
+    let (from, to) = StatsRange::days(range, clock::now())?;
+    let (from, to) = (u64::from(from) * NANOS_PER_DAY, (u64::from(to) + 1) * NANOS_PER_DAY - 1);
+    let mut todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+    todos.extend(ARCHIVE_STORE.with(|store| ArchiveStoreWrapper { store }.all(principal)));
+    todos.retain(|todo| todo.completed_at.is_some_and(|completed_at| (from..=to).contains(&completed_at)));
+    let tracked = POMODORO_STORE.with(|store| PomodoroStoreWrapper { store }.tracked_minutes(principal));
+    Ok(estimation::report(todos, &tracked))
+}
+
+/// Lists every tag the caller uses, with the number of open and completed Todo items carrying it.
+/// Archived Todo items are included.
+///
+/// # Returns
+///
+/// A vector of tags in alphabetical order.
+#[ic_cdk::query]
+fn get_tag_cloud() -> Vec<TagCloudEntry> {
+    let principal = ic_cdk::caller();
+    TAG_STORE.with(|store| TagStoreWrapper { store }.cloud(principal))
+}
+
+/// Lists the tags the caller uses nested by their levels, so that `work/projectX` is found below `work`.
+/// Levels no Todo item carries by themselves are included with zero counts.
+///
+/// # Returns
+///
+/// A vector of the top-level tags in alphabetical order.
+#[ic_cdk::query]
+fn get_tag_tree() -> Vec<TagNode> {
+    let principal = ic_cdk::caller();
+    TAG_STORE.with(|store| TagStoreWrapper { store }.tree(principal))
+}
+
+/// Retrieves how many Todo items carrying a tag the caller created and completed in each week of a range,
+/// so that they can see which areas generate or finish the most work. Weeks start on Monday, in UTC.
+///
+/// # Arguments
+///
+/// * `tag` - The tag.
+/// * `range` - Optional time range, defaulting to the last 12 weeks.
+///
+/// # Returns
+///
+/// A Result containing the trends, or an Error if the tag or range is invalid.
+#[ic_cdk::query]
+fn get_tag_trends(tag: String, range: Option<StatsRange>) -> Result<TagTrends, Error> {
+    let principal = ic_cdk::caller();
+    let tag = validation::tag(&tag)?;
+    let now = clock::now();
+    let range = range.unwrap_or_else(|| StatsRange {
+        from: week_start(week_of(day_of(now)).saturating_sub(DEFAULT_TREND_WEEKS - 1)),
+        to: now,
+    });
+    let (from, to) = StatsRange::days(Some(range), now)?;
+    Ok(TAG_TREND_STORE.with(|store| TagTrendStoreWrapper { store }.trends(principal, &tag, week_of(from), week_of(to))))
+}
+
+/// Assembles an overview of the caller's overdue items, items due today, yesterday's
+/// completions and a suggested focus list for today, with days delimited in the caller's time zone.
+///
+/// # Returns
+///
+/// The daily digest of the caller.
+#[ic_cdk::query]
+fn get_daily_digest() -> DailyDigest {
+    let principal = ic_cdk::caller();
+    let todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+    digest::build(todos, clock::now(), preferences_of(principal).utc_offset())
+}
+
+/// Sorts the caller's open Todo items into the four quadrants of the Eisenhower matrix.
+/// High priority items are important, and items overdue or due within two local days are urgent.
+///
+/// # Returns
+///
+/// The Eisenhower matrix of the caller.
+#[ic_cdk::query]
+fn get_eisenhower_matrix() -> EisenhowerMatrix {
+    let principal = ic_cdk::caller();
+    let todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+    eisenhower::build(todos, clock::now(), preferences_of(principal).utc_offset())
+}
+
+/// Looks back at a week of the caller's Todo items: what was completed, what was added, what was carried
+/// over from before and how far each project got, with days delimited in the caller's time zone.
+///
+/// # Arguments
+///
+/// * `week_start` - Optional first local day of the week, counted in days since the Unix epoch,
+///   defaulting to the start of the current week according to the caller's preferences.
+///
+/// # Returns
+///
+/// The weekly review of the caller.
+#[ic_cdk::query]
+fn get_weekly_review(week_start: Option<Day>) -> WeeklyReview {
+    let principal = ic_cdk::caller();
+    let preferences = preferences_of(principal);
+    let utc_offset = preferences.utc_offset();
+    let week_start = week_start.unwrap_or_else(|| {
+        let today = local_day_of(clock::now(), utc_offset);
+        preferences.week_start.unwrap_or_default().on_or_before(today)
+    });
+    let todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+    review::build(todos, week_start, utc_offset)
+}
+
+/// Lays out the caller's Todo items over seven days, grouped by the local day they are due,
+/// together with the open Todo items that have no due date.
+///
+/// # Arguments
+///
+/// * `start_day` - Optional first local day of the week, counted in days since the Unix epoch,
+///   defaulting to the start of the current week according to the caller's preferences.
+///
+/// # Returns
+///
+/// The week plan of the caller.
+#[ic_cdk::query]
+fn get_week_plan(start_day: Option<Day>) -> WeekPlan {
+    let principal = ic_cdk::caller();
+    let preferences = preferences_of(principal);
+    let utc_offset = preferences.utc_offset();
+    let start_day = start_day.unwrap_or_else(|| {
+        let today = local_day_of(clock::now(), utc_offset);
+        preferences.week_start.unwrap_or_default().on_or_before(today)
+    });
+    let todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+    planner::week_plan(todos, start_day, utc_offset)
+}
+
+/// Counts the caller's open Todo items by when they fall due, for the badges of a sidebar. Days and
+/// weeks are delimited in the caller's time zone and by their preferred first day of the week.
+///
+/// # Returns
+///
+/// The number of open Todo items that are overdue, due later today, tomorrow, later this week,
+/// after this week, or without a due date.
+#[ic_cdk::query]
+fn get_due_summary() -> DueSummary {
+    let principal = ic_cdk::caller();
+    let preferences = preferences_of(principal);
+    let utc_offset = preferences.utc_offset();
+    let now = clock::now();
+    let today = local_day_of(now, utc_offset);
+    let today_start = local_day_start(now, utc_offset);
+    let next_week = preferences.week_start.unwrap_or_default().on_or_before(today) + 7;
+    let boundaries = [
+        now,
+        today_start + NANOS_PER_DAY,
+        today_start + 2 * NANOS_PER_DAY,
+        today_start + u64::from(next_week - today).max(2) * NANOS_PER_DAY,
+    ];
+    DUE_INDEX.with(|store| TodoIndexWrapper::due(store).summary(principal, boundaries))
+}
+
+/// Adds a Todo item to the caller's focus list for today, which resets at local midnight.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the Todo item is not found or the list is full.
+#[ic_cdk::update]
+fn add_to_my_day(id: TodoId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.get_todo(principal, id))
+        .ok_or(Error::NotFound)?;
+    let today = local_today_of(principal);
+    if MY_DAY_STORE.with(|store| MyDayStoreWrapper { store }.add(principal, id, today)) {
+        Ok(())
+    } else {
+        Err(Error::QuotaExceeded(Some("My Day is full".to_string())))
+    }
+}
+
+/// Removes a Todo item from the caller's focus list for today.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+///
+/// # Returns
+///
+/// A Result indicating success; removing a Todo item that is not on the list also succeeds.
+#[ic_cdk::update]
+fn remove_from_my_day(id: TodoId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let today = local_today_of(principal);
+    MY_DAY_STORE.with(|store| MyDayStoreWrapper { store }.remove(principal, id, today));
+    Ok(())
+}
+
+/// Retrieves the caller's focus list for today.
+///
+/// # Returns
+///
+/// A vector of the selected Todo items, in the order they were added.
+#[ic_cdk::query]
+fn get_my_day() -> Vec<Todo> {
+    let principal = ic_cdk::caller();
+    let today = local_today_of(principal);
+    let ids = MY_DAY_STORE.with(|store| MyDayStoreWrapper { store }.get(principal, today));
+    TODO_STORE.with(|store| {
+        let wrapper = TodoStoreWrapper { store };
+        ids.into_iter().filter_map(|id| wrapper.get_todo(principal, id)).collect()
+    })
+}
+
+/// Sets the Mailgun-compatible service used to email the caller reminders about Todo items due soon.
+///
+/// # Arguments
+///
+/// * `channel` - The email channel, or None to stop sending emails.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the channel is invalid.
+#[ic_cdk::update]
+fn set_email_channel(channel: Option<EmailChannel>) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    let channel = channel.map(validation::email_channel).transpose()?;
+    EMAIL_CHANNEL_STORE.with(|store| EmailChannelStoreWrapper { store }.set(principal, channel));
+    Ok(())
+}
+
+/// Lists the reminder emails sent to the caller, newest first.
+///
+/// # Arguments
+///
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A vector of email deliveries.
+#[ic_cdk::query]
+fn get_email_deliveries(paginator: Option<Paginator>) -> Vec<EmailDelivery> {
+    let principal = ic_cdk::caller();
+    let page_size = preferences_of(principal).default_page_size;
+    let paginator = paginator.unwrap_or_default().with_default_limit(page_size);
+    EMAIL_LOG_STORE.with(|store| EmailLogStoreWrapper { store }.list(principal, paginator))
+}
+
+/// Links the caller to a Telegram chat, where they receive due date announcements and can
+/// add Todo items with `/add <description>`.
+///
+/// # Arguments
+///
+/// * `chat_id` - The identifier of the Telegram chat.
+/// * `token` - The token of the bot, whose webhook must point to `/telegram/webhook`.
+///
+/// # Returns
+///
+/// A Result containing the secret token to set on the webhook, or an Error if the token is invalid.
+#[ic_cdk::update]
+fn link_telegram(chat_id: i64, token: String) -> Result<String, Error> {
+    let (principal, _meter) = active_caller()?;
+    let token = validation::bot_token(&token)?;
+    let now = clock::now();
+    unlink_telegram_chat(principal);
+    TELEGRAM_CHAT_INDEX.with(|store| TelegramChatIndexWrapper { store }.insert(chat_id, principal));
+    Ok(TELEGRAM_STORE.with(|store| TelegramStoreWrapper { store }.link(principal, chat_id, token, now)))
+}
+
+/// Removes the caller's link to a Telegram chat.
+///
+/// # Returns
+///
+/// A Result indicating success.
+#[ic_cdk::update]
+fn unlink_telegram() -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    unlink_telegram_chat(principal);
+    Ok(())
+}
+
+/// Removes the link of a principal to a Telegram chat, if any, along with its entry in the chat index.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+fn unlink_telegram_chat(principal: Principal) {
+    if let Some(link) = TELEGRAM_STORE.with(|store| TelegramStoreWrapper { store }.get(principal)) {
+        TELEGRAM_CHAT_INDEX.with(|store| TelegramChatIndexWrapper { store }.remove(link.chat_id, principal));
+        TELEGRAM_STORE.with(|store| TelegramStoreWrapper { store }.unlink(principal));
+    }
+}
+
+/// Finds the principal a Telegram webhook update belongs to: the one linked to its chat by the bot whose secret
+/// token the update carries.
+///
+/// # Arguments
+///
+/// * `chat_id` - The identifier of the Telegram chat.
+/// * `secret` - The secret token sent with the update.
+///
+/// # Returns
+///
+/// An Option containing the principal, or None if no principal linked the chat with that bot.
+fn telegram_principal(chat_id: i64, secret: &str) -> Option<Principal> {
+    TELEGRAM_CHAT_INDEX
+        .with(|store| TelegramChatIndexWrapper { store }.principals(chat_id))
+        .into_iter()
+        .find(|principal| {
+            TELEGRAM_STORE
+                .with(|store| TelegramStoreWrapper { store }.get(*principal))
+                .is_some_and(|link| link.chat_id == chat_id && link.secret == secret)
+        })
+}
+
+/// Serves HTTP requests through the gateway, upgrading Telegram webhook updates to update calls
+/// and serving certified attachments to holders of a download token. Attachments not certified since they
+/// last changed are upgraded to update calls, which certify them for the following requests. Exports are
+/// built from every Todo item of their owner, so they are always upgraded, which counts them against the client.
+///
+/// # Arguments
+///
+/// * `request` - The HTTP request.
+///
+/// # Returns
+///
+/// The HTTP response.
+#[ic_cdk::query]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    if request.method == "POST" && request.path() == telegram::WEBHOOK_PATH {
+        // Updates are checked before being upgraded, so that only linked chats cost an update call.
+        return match telegram_update(&request) {
+            Ok(_) => HttpResponse::upgrade(),
+            Err(response) => response,
+        };
+    }
+    let upgraded = request.method == "POST" && request.path().starts_with(hooks::HOOKS_PREFIX);
+    // Responses of the API depend on the caller, so they are served by update calls instead of being certified.
+    if upgraded || request.path().starts_with(api_tokens::API_PREFIX) {
+        return HttpResponse::upgrade();
+    }
+    let requested = requested_download(&request);
+    if requested.is_some_and(|(principal, download, _)| {
+        download == Download::Export || !CERTIFIER.with(|certifier| certifier.borrow().is_certified(principal, download))
+    }) {
+        return HttpResponse::upgrade();
+    }
+    let served = requested.and_then(|(principal, download, _)| Some((principal, download, download_response(principal, download)?)));
+    let certificate = ic_cdk::api::data_certificate().unwrap_or_default();
+    CERTIFIER.with(|certifier| certifier.borrow().serve(&request.url, served, &certificate))
+}
+
+/// Handles Telegram webhook updates, adding a Todo item for the linked principal on `/add <description>`,
+/// events posted by external services to an integration, requests to the API authenticated with an API token,
+/// and downloads that are not certified yet.
+///
+/// # Arguments
+///
+/// * `request` - The HTTP request.
+///
+/// # Returns
+///
+/// The HTTP response, which carries the reply to the chat or the result of the API request.
+#[ic_cdk::update]
+fn http_request_update(request: HttpRequest) -> HttpResponse {
+    if let Err(error) = maintenance_mode().ensure_open() {
+        return HttpResponse::text(error.code(), &error.to_string());
+    }
+    if request.path().starts_with(api_tokens::API_PREFIX) {
+        return serve_api(&request);
+    }
+    if request.method == "POST" && request.path().starts_with(hooks::HOOKS_PREFIX) {
+        return receive_hook(&request);
+    }
+    if request.method == "GET" {
+        return serve_download(&request);
+    }
+    if request.method != "POST" || request.path() != telegram::WEBHOOK_PATH {
+        return HttpResponse::text(404, "Not found");
+    }
+    let (principal, message) = match telegram_update(&request) {
+        Ok(update) => update,
+        Err(response) => return response,
+    };
+    let _meter = Meter::start(principal, day_of(clock::now()));
+    let preferences = preferences_of(principal);
+    let language = preferences.language();
+    let text = match telegram::parse_command(&message.text) {
+        Some(Command::Add(description)) => {
+            let id = generate_next_id();
+            let priority = preferences.default_priority.unwrap_or_default();
+            let now = clock::now();
+            USER_STORE.with(|store| UserStoreWrapper { store }.touch(principal, now));
+            match TODO_STORE.with(|store| TodoStoreWrapper { store }.add_todo(principal, id, description, priority, now)) {
+                Ok(change) => {
+                    record_change(change);
+                    Message::Added { id }.render(language)
+                }
+                Err(error) => Message::Error(&error).render(language),
+            }
+        }
+        None => Message::ChatUsage.render(language),
+    };
+    HttpResponse::json(telegram::reply(message.chat_id, &text))
+}
+
+/// Resolves the download a request asks for with a download token.
+///
+/// # Arguments
+///
+/// * `request` - The HTTP request.
+///
+/// # Returns
+///
+/// An Option containing the principal owning the download, the download and the token, or None if the request
+/// does not name a download or carries no valid token.
+fn requested_download(request: &HttpRequest) -> Option<(Principal, Download, &str)> {
+    let download = Download::parse(request.path()).filter(|_| request.method == "GET")?;
+    let token = request.query_param(downloads::TOKEN_PARAMETER)?;
+    let principal = DOWNLOAD_TOKEN_STORE.with(|store| DownloadTokenStoreWrapper { store }.find(token))?;
+    Some((principal, download, token))
+}
+
+/// Serves a download counted against the client. Exports are served as they are; attachments are certified,
+/// so that the following requests are answered by query calls.
+///
+/// # Arguments
+///
+/// * `request` - The HTTP request.
+///
+/// # Returns
+///
+/// The HTTP response, carrying the download.
+fn serve_download(request: &HttpRequest) -> HttpResponse {
+    if let Err(error) = throttle_client(request) {
+        return HttpResponse::text(error.code(), &error.to_string());
+    }
+    requested_download(request)
+        .and_then(|(principal, download, token)| match download {
+            Download::Export => download_response(principal, download),
+            Download::Attachment(_) => certify_download(principal, download, token),
+        })
+        .unwrap_or_else(|| HttpResponse::text(404, "Not found"))
+}
+
+/// Checks a Telegram webhook update and finds the principal it belongs to.
+///
+/// # Arguments
+///
+/// * `request` - The HTTP request, posted to the webhook.
+///
+/// # Returns
+///
+/// A Result containing the principal and the message, or the response ending the request if the update lacks the
+/// secret token, is not a text message or comes from a chat that is not linked.
+fn telegram_update(request: &HttpRequest) -> Result<(Principal, telegram::IncomingMessage), HttpResponse> {
+    let Some(secret) = request.header(telegram::SECRET_HEADER) else {
+        return Err(HttpResponse::text(403, "Missing secret token"));
+    };
+    // Telegram retries updates that are not acknowledged, so unsupported ones are acknowledged too.
+    let Some(message) = telegram::parse_update(&request.body) else {
+        return Err(HttpResponse::text(200, ""));
+    };
+    match telegram_principal(message.chat_id, secret) {
+        Some(principal) => Ok((principal, message)),
+        None => Err(HttpResponse::text(403, "Chat is not linked")),
+    }
+}
+
+/// Serves a request to the API as the principal its API token was issued to.
+///
+/// # Arguments
+///
+/// * `request` - The HTTP request, whose path starts with `/api/`.
+///
+/// # Returns
+///
+/// The HTTP response, carrying JSON on success.
+fn serve_api(request: &HttpRequest) -> HttpResponse {
+    let now = clock::now();
+    let grant = api_tokens::bearer_token(request)
+        .and_then(|token| API_TOKEN_STORE.with(|store| ApiTokenStoreWrapper { store }.authenticate(token, now)));
+    let Some(ApiGrant { principal, scopes }) = grant else {
+        return HttpResponse::text(401, "Missing, expired or revoked API token");
+    };
+    if let Err(error) = ensure_feature(Feature::ApiTokens, principal) {
+        return HttpResponse::text(error.code(), &error.to_string());
+    }
+    let _meter = Meter::start(principal, day_of(now));
+    if request.path() != api_tokens::TODOS_PATH {
+        return HttpResponse::text(404, "Not found");
+    }
+    let scope = match request.method.as_str() {
+        "GET" => ApiScope::Read,
+        "POST" => ApiScope::Write,
+        _ => return HttpResponse::text(405, "Method not allowed"),
+    };
+    if !scopes.contains(&scope) {
+        return HttpResponse::text(403, "The API token does not grant this request");
+    }
+    if let Err(error) = throttle_client(request).and_then(|()| throttle(Caller::Principal(principal))) {
+        return HttpResponse::text(error.code(), &error.to_string());
+    }
+    USER_STORE.with(|store| UserStoreWrapper { store }.touch(principal, now));
+    if scope == ApiScope::Read {
+        return list_api_todos(principal, request);
+    }
+    let request: CreateTodo = match serde_json::from_slice(&request.body) {
+        Ok(request) => request,
+        Err(error) => return HttpResponse::text(400, &format!("Invalid Todo item: {error}")),
+    };
+    match create_requested_todo(principal, request, now) {
+        Ok(id) => HttpResponse {
+            status_code: 201,
+            ..HttpResponse::json(format!("{{\"id\":{id}}}").into_bytes())
+        },
+        Err(error) => HttpResponse::text(error.code(), &error.to_string()),
+    }
+}
+
+/// Lists a page of the Todo items of a principal for `GET /api/todos`, in order of identifier. The page holds at most
+/// `limit` Todo items, capped at `MAX_PAGE_SIZE`, after the identifier `after`; it ends early once the call spent its
+/// instruction budget. The response is `{"todos": [...], "next": <id>}`, where `next` is the `after` of the following
+/// page, or null after the last one.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `request` - The HTTP request, with the optional `limit` and `after` query parameters.
+///
+/// # Returns
+///
+/// The HTTP response, carrying JSON on success.
+fn list_api_todos(principal: Principal, request: &HttpRequest) -> HttpResponse {
+    let limit = match request.query_param("limit").map(str::parse::<u32>).transpose() {
+        Ok(limit) => limit.unwrap_or(MAX_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) as usize,
+        Err(_) => return HttpResponse::text(400, "Invalid limit"),
+    };
+    let after = match request.query_param("after").map(str::parse::<TodoId>).transpose() {
+        Ok(after) => after,
+        Err(_) => return HttpResponse::text(400, "Invalid after"),
+    };
+    let guard = instruction_guard();
+    let (todos, next) = TODO_STORE.with(|store| TodoStoreWrapper { store }.list_todos_after(principal, after, limit, &guard));
+    HttpResponse::json(serde_json::to_vec(&serde_json::json!({ "todos": todos, "next": next })).unwrap_or_default())
+}
+
+/// Creates the Todo item an external service requests with a signed event.
+/// Events naming an item of the service that already became a Todo item are acknowledged without creating another.
+///
+/// # Arguments
+///
+/// * `request` - The HTTP request, whose path names the integration.
+///
+/// # Returns
+///
+/// The HTTP response, carrying the identifier of the created Todo item as JSON.
+fn receive_hook(request: &HttpRequest) -> HttpResponse {
+    let Some((principal, integration)) = INTEGRATION_STORE.with(|store| IntegrationStoreWrapper { store }.find(request.path())) else {
+        return HttpResponse::text(404, "Not found");
+    };
+    let signature = request.header(hooks::SIGNATURE_HEADER).unwrap_or_default();
+    if !hooks::verify_signature(&integration.secret, &request.body, signature) {
+        return HttpResponse::text(401, "Invalid signature");
+    }
+    if let Err(error) = ensure_feature(Feature::Webhooks, principal) {
+        return HttpResponse::text(error.code(), &error.to_string());
+    }
+    let _meter = Meter::start(principal, day_of(clock::now()));
+    let todo = match hooks::todo_for_event(&integration, request.header(hooks::GITHUB_EVENT_HEADER), &request.body) {
+        Ok(Some(todo)) => todo,
+        // Services retry events that are not acknowledged, so events that request nothing are acknowledged too.
+        Ok(None) => return HttpResponse::text(200, "Ignored"),
+        Err(error) => return HttpResponse::text(error.code(), &error.to_string()),
+    };
+    let external_id = match todo.external_id.as_deref().map(validation::external_id).transpose() {
+        Ok(external_id) => external_id,
+        Err(error) => return HttpResponse::text(error.code(), &error.to_string()),
+    };
+    let now = clock::now();
+    USER_STORE.with(|store| UserStoreWrapper { store }.touch(principal, now));
+    let mapped_id = external_id
+        .as_deref()
+        .and_then(|external_id| EXTERNAL_ID_STORE.with(|store| ExternalIdStoreWrapper { store }.get(principal, external_id)))
+        .filter(|id| TODO_STORE.with(|store| TodoStoreWrapper { store }.get_todo(principal, *id)).is_some());
+    if let Some(id) = mapped_id {
+        return HttpResponse::json(format!("{{\"id\":{id}}}").into_bytes());
+    }
+    let request = with_default_priority(principal, todo.request);
+    let id = generate_next_id();
+    match TODO_STORE.with(|store| TodoStoreWrapper { store }.create_todo(principal, id, request, now)) {
+        Ok(change) => {
+            record_change(change);
+            if let Some(external_id) = external_id {
+                EXTERNAL_ID_STORE.with(|store| ExternalIdStoreWrapper { store }.set(principal, external_id, id));
+            }
+            HttpResponse {
+                status_code: 201,
+                ..HttpResponse::json(format!("{{\"id\":{id}}}").into_bytes())
+            }
+        }
+        Err(error) => HttpResponse::text(error.code(), &error.to_string()),
+    }
+}
+
+/// Strips the response of an HTTPS outcall down to its status so that all replicas agree on it.
+///
+/// # Arguments
+///
+/// * `args` - The raw response of the HTTPS outcall.
+///
+/// # Returns
+///
+/// The transformed response.
+#[ic_cdk::query]
+fn transform_outcall_response(args: TransformArgs) -> outcall::HttpResponse {
+    http::strip_response(args.response)
+}
+
+/// Reduces a fetched page to its title and description so that all replicas agree on it.
+///
+/// # Arguments
+///
+/// * `args` - The raw response of the HTTPS outcall.
+///
+/// # Returns
+///
+/// The transformed response.
+#[ic_cdk::query]
+fn transform_link_preview(args: TransformArgs) -> outcall::HttpResponse {
+    link_preview::transform(args.response)
+}
+
+/// Lists the caller's notifications, newest first.
+///
+/// # Arguments
+///
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A vector of notifications.
+#[ic_cdk::query]
+fn list_notifications(paginator: Option<Paginator>) -> Vec<Notification> {
+    let principal = ic_cdk::caller();
+    let page_size = preferences_of(principal).default_page_size;
+    let paginator = paginator.unwrap_or_default().with_default_limit(page_size);
+    NOTIFICATION_STORE.with(|store| NotificationStoreWrapper { store }.list(principal, paginator))
+}
+
+/// Marks one of the caller's notifications as read.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier of the notification.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the notification is not found.
+#[ic_cdk::update]
+fn mark_notification_read(id: NotificationId) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    NOTIFICATION_STORE.with(|store| NotificationStoreWrapper { store }.mark_read(principal, id))
+}
+
+/// Retrieves the number of the caller's unread notifications.
+///
+/// # Returns
+///
+/// The number of unread notifications.
+#[ic_cdk::query]
+fn get_unread_notification_count() -> u64 {
+    let principal = ic_cdk::caller();
+    NOTIFICATION_STORE.with(|store| NotificationStoreWrapper { store }.unread_count(principal))
+}
+
+/// Retrieves the points, level and completion streak of the caller.
+///
+/// # Returns
+///
+/// The score of the caller.
+#[ic_cdk::query]
+fn get_my_score() -> MyScore {
+    let principal = ic_cdk::caller();
+    let score = SCORE_STORE.with(|store| ScoreStoreWrapper { store }.get(principal));
+    MyScore {
+        points: score.points,
+        level: score.level(),
+        current_streak: score.current_streak(day_of(clock::now())),
+        longest_streak: score.longest_streak,
+        on_leaderboard: score.on_leaderboard,
+    }
+}
+
+/// Sets whether the caller is listed on the leaderboard. Principals are not listed unless they opt in.
+///
+/// # Arguments
+///
+/// * `on_leaderboard` - Whether the caller is listed.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if gamification is turned off for the caller.
+#[ic_cdk::update]
+fn set_leaderboard_opt_in(on_leaderboard: bool) -> Result<(), Error> {
+    let (principal, _meter) = active_caller()?;
+    ensure_feature(Feature::Gamification, principal)?;
+    SCORE_STORE.with(|store| ScoreStoreWrapper { store }.set_on_leaderboard(principal, on_leaderboard));
+    Ok(())
+}
+
+/// Lists the principals that opted in to the leaderboard, highest points first.
+///
+/// # Arguments
+///
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A vector of leaderboard entries, empty if gamification is turned off for the caller.
+#[ic_cdk::query]
+fn get_leaderboard(paginator: Option<Paginator>) -> Vec<LeaderboardEntry> {
+    if !feature_enabled(Feature::Gamification, ic_cdk::caller()) {
+        return Vec::new();
+    }
+    let paginator = paginator.unwrap_or_default();
+    SCORE_STORE.with(|store| ScoreStoreWrapper { store }.leaderboard(paginator))
+}
+
+/// Retrieves the resources the caller consumed through update calls on each day.
+///
+/// # Arguments
+///
+/// * `range` - Optional time range, defaulting to the last 30 days.
+///
+/// # Returns
+///
+/// A Result containing the usage, or an Error if the range is invalid.
+#[ic_cdk::query]
+fn get_my_usage(range: Option<StatsRange>) -> Result<MyUsage, Error> {
+    let principal = ic_cdk::caller();
+    let (from, to) = StatsRange::days(range, clock::now())?;
+    Ok(USAGE_STORE.with(|store| UsageStoreWrapper { store }.usage(principal, from, to)))
+}
+
+/// Retrieves the priority changes of a Todo item with who made them, oldest first,
+/// so that Todo items that keep getting deprioritized stand out.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the Todo item.
+///
+/// # Returns
+///
+/// A Result containing the priority changes, or an Error if the Todo item is not found.
+#[ic_cdk::query]
+fn get_priority_history(id: TodoId) -> Result<Vec<PriorityChange>, Error> {
+    let principal = ic_cdk::caller();
+    TODO_STORE.with(|store| TodoStoreWrapper { store }.get_todo(principal, id)).ok_or(Error::NotFound)?;
+    Ok(PRIORITY_HISTORY_STORE.with(|store| PriorityHistoryStoreWrapper { store }.list(principal, id)))
+}
+
+/// Retrieves the number of Todo items the caller completed on each day of a year.
+///
+/// # Arguments
+///
+/// * `year` - The calendar year.
+///
+/// # Returns
+///
+/// A Result containing the heatmap, or an Error if the year is out of range.
+#[ic_cdk::query]
+fn get_completion_heatmap(year: u16) -> Result<CompletionHeatmap, Error> {
+    let principal = ic_cdk::caller();
+    STATS_STORE.with(|store| StatsStoreWrapper { store }.completion_heatmap(principal, year))
+}
+
+/// Retrieves the audit log of the caller, listing every mutation of their Todo items.
+///
+/// # Arguments
+///
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A vector of audit entries, newest first.
+#[ic_cdk::query]
+fn get_audit_log(paginator: Option<Paginator>) -> Vec<AuditEntry> {
+    let principal = ic_cdk::caller();
+    let page_size = preferences_of(principal).default_page_size;
+    let paginator = paginator.unwrap_or_default().with_default_limit(page_size);
+    AUDIT_STORE.with(|store| AuditStoreWrapper { store }.list_entries(principal, paginator))
+}
+
+/// Lists stored Todo records of all principals that can no longer be decoded.
+/// Only admins of the canister may call this endpoint.
+///
+/// # Arguments
+///
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A Result containing the corrupt records, or an Error if the caller is not an admin.
+#[ic_cdk::query]
+fn list_corrupt_todo_items(paginator: Option<Paginator>) -> Result<Vec<CorruptTodo>, Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Administer)?;
+    let paginator = paginator.unwrap_or_default();
+    Ok(TODO_STORE.with(|store| TodoStoreWrapper { store }.list_corrupt_todos(paginator)))
+}
+
+/// Repairs a corrupt Todo record by replacing it with a new Todo item or removing it.
+/// Once a governance canister is configured, only an adopted proposal may perform it; see `validate_repair_corrupt_todo_item`.
+///
+/// # Arguments
+///
+/// * `principal` - The principal owning the record.
+/// * `id` - The identifier the record is stored under.
+/// * `replacement` - The Todo item to store instead, or None to remove the record.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the caller may not perform it, the record is not found or not corrupt.
+#[ic_cdk::update]
+fn repair_corrupt_todo_item(
+    principal: Principal,
+    id: TodoId,
+    replacement: Option<CreateTodo>,
+) -> Result<(), Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Govern)?;
+    let now = clock::now();
+    let todo = TODO_STORE.with(|store| {
+        let wrapper = TodoStoreWrapper { store };
+        wrapper.repair_corrupt_todo(principal, id, replacement, now)?;
+        Ok::<_, Error>(wrapper.get_todo(principal, id))
+    })?;
+    let kind = todo.map_or(TodoEventKind::Removed(id), |todo| TodoEventKind::Put(Box::new(todo)));
+    EVENT_STORE.with(|store| EventStoreWrapper { store }.append(principal, kind, now));
+    Ok(())
+}
+
+/// Renumbers the Todo items of a principal, including archived ones, to the identifiers 1, 2, 3, …
+/// in their current order, so that clients relying on small identifiers can keep using them after mass deletions.
+/// Every store referring to the Todo items follows; the audit log keeps the identifiers it recorded.
+/// This is the one deliberate exception to identifiers never being reused: it announces the renumbering in the
+/// event log, and the counter stays where it is, so new Todo items still get identifiers never handed out before.
+/// Once a governance canister is configured, only an adopted proposal may perform it; see `validate_compact_ids`.
+///
+/// # Arguments
+///
+/// * `principal` - The principal owning the Todo items.
+///
+/// # Returns
+///
+/// A Result containing the number of renumbered Todo items, or an Error if the caller may not perform it.
+#[ic_cdk::update]
+fn compact_ids(principal: Principal) -> Result<u64, Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Govern)?;
+    let mut ids = TODO_STORE.with(|store| TodoStoreWrapper { store }.ids(principal));
+    ids.extend(ARCHIVE_STORE.with(|store| ArchiveStoreWrapper { store }.ids(principal)));
+    let ids = renumbering::dense(ids);
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    TODO_STORE.with(|store| TodoStoreWrapper { store }.renumber(principal, &ids));
+    ARCHIVE_STORE.with(|store| ArchiveStoreWrapper { store }.renumber(principal, &ids));
+    HISTORY_STORE.with(|store| HistoryStoreWrapper { store }.renumber(principal, &ids));
+    EXTERNAL_ID_STORE.with(|store| ExternalIdStoreWrapper { store }.renumber(principal, &ids));
+    ATTACHMENT_STORE.with(|store| AttachmentStoreWrapper { store }.renumber(principal, &ids));
+    BOARD_STORE.with(|store| BoardStoreWrapper { store }.renumber(principal, &ids));
+    MY_DAY_STORE.with(|store| MyDayStoreWrapper { store }.renumber(principal, &ids));
+    NOTIFICATION_STORE.with(|store| NotificationStoreWrapper { store }.renumber(principal, &ids));
+    EMAIL_LOG_STORE.with(|store| EmailLogStoreWrapper { store }.renumber(principal, &ids));
+    POMODORO_STORE.with(|store| PomodoroStoreWrapper { store }.renumber(principal, &ids));
+    MODIFIED_INDEX.with(|store| TodoIndexWrapper::modified(store).renumber(principal, &ids));
+    RANK_INDEX.with(|store| TodoIndexWrapper::rank(store).renumber(principal, &ids));
+    DUE_INDEX.with(|store| TodoIndexWrapper::due(store).renumber(principal, &ids));
+    COMPLETION_INDEX.with(|store| TodoIndexWrapper::completion(store).renumber(principal, &ids));
+    HABIT_STORE.with(|store| HabitStoreWrapper { store }.renumber(principal, &ids));
+    GEO_INDEX.with(|store| GeoIndexWrapper { store }.renumber(principal, &ids));
+    RELATION_STORE.with(|store| RelationStoreWrapper { store }.renumber(principal, &ids));
+    PRIORITY_HISTORY_STORE.with(|store| PriorityHistoryStoreWrapper { store }.renumber(principal, &ids));
+    let pairs = ids.iter().map(|(old, new)| (*old, *new)).collect();
+    EVENT_STORE.with(|store| EventStoreWrapper { store }.append(principal, TodoEventKind::Renumbered(pairs), clock::now()));
+    Ok(ids.len() as u64)
+}
+
+/// Checks a governance proposal to repair a corrupt Todo record before it is put to a vote,
+/// as the validator of the generic proposal calling `repair_corrupt_todo_item`.
+///
+/// # Arguments
+///
+/// * `principal` - The principal owning the record.
+/// * `id` - The identifier the record is stored under.
+/// * `replacement` - The Todo item to store instead, or None to remove the record.
+///
+/// # Returns
+///
+/// A Result containing the rendering of the proposal, or the reason it is rejected.
+#[ic_cdk::query]
+fn validate_repair_corrupt_todo_item(
+    principal: Principal,
+    id: TodoId,
+    replacement: Option<CreateTodo>,
+) -> Result<String, String> {
+    let corrupt = TODO_STORE.with(|store| TodoStoreWrapper { store }.is_corrupt(principal, id));
+    match (corrupt, replacement) {
+        (None, _) => Err(Error::NotFound.to_string()),
+        (Some(false), _) => Err(Error::Conflict(Some("Todo item is not corrupt".to_string())).to_string()),
+        (Some(true), Some(request)) => Ok(format!("Replace the corrupt Todo record {id} of {principal} with \"{}\"", request.description)),
+        (Some(true), None) => Ok(format!("Remove the corrupt Todo record {id} of {principal}")),
+    }
+}
+
+/// Rebuilds the Todo items of a principal from their event log, the source of truth the Todo store is a projection of,
+/// and brings the indexes in line. Statistics, history and the audit log are left as they are, as nothing happened to the Todo items.
+/// Once a governance canister is configured, only an adopted proposal may perform it; see `validate_rebuild_todo_projection`.
+///
+/// # Arguments
+///
+/// * `principal` - The principal owning the Todo items.
+///
+/// # Returns
+///
+/// A Result containing the number of Todo items that were out of line, or an Error if the caller may not perform it.
+#[ic_cdk::update]
+fn rebuild_todo_projection(principal: Principal) -> Result<u64, Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Govern)?;
+    let projection = EVENT_STORE.with(|store| EventStoreWrapper { store }.project(principal));
+    let changes = TODO_STORE.with(|store| TodoStoreWrapper { store }.rebuild(principal, projection));
+    for change in &changes {
+        BOARD_STORE.with(|store| BoardStoreWrapper { store }.record_change(change));
+        TAG_STORE.with(|store| TagStoreWrapper { store }.record_change(change));
+        TODO_COUNT_STORE.with(|store| TodoCountStoreWrapper { store }.record_change(change));
+        if let Some(before) = &change.before {
+            unindex_todo(principal, before);
+        }
+        if let Some(after) = &change.after {
+            index_todo(principal, after);
+        }
+    }
+    Ok(changes.len() as u64)
+}
+
+/// Checks a governance proposal to rebuild the Todo items of a principal from their event log before it is put to a vote,
+/// as the validator of the generic proposal calling `rebuild_todo_projection`.
+///
+/// # Arguments
+///
+/// * `principal` - The principal owning the Todo items.
+///
+/// # Returns
+///
+/// A Result containing the rendering of the proposal, or the reason it is rejected.
+#[ic_cdk::query]
+fn validate_rebuild_todo_projection(principal: Principal) -> Result<String, String> {
+    let projection = EVENT_STORE.with(|store| EventStoreWrapper { store }.project(principal));
+    let todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+    let stale = todos.iter().filter(|todo| projection.get(&todo.id) != Some(*todo)).count()
+        + projection.keys().filter(|id| !todos.iter().any(|todo| todo.id == **id)).count();
+    if stale == 0 {
+        return Err(format!("The Todo items of {principal} match their event log already"));
+    }
+    Ok(format!("Rebuild {stale} Todo items of {principal} from their event log"))
+}
+
+/// Checks a governance proposal to renumber the Todo items of a principal before it is put to a vote,
+/// as the validator of the generic proposal calling `compact_ids`.
+///
+/// # Arguments
+///
+/// * `principal` - The principal owning the Todo items.
+///
+/// # Returns
+///
+/// A Result containing the rendering of the proposal, or the reason it is rejected.
+#[ic_cdk::query]
+fn validate_compact_ids(principal: Principal) -> Result<String, String> {
+    let mut ids = TODO_STORE.with(|store| TodoStoreWrapper { store }.ids(principal));
+    ids.extend(ARCHIVE_STORE.with(|store| ArchiveStoreWrapper { store }.ids(principal)));
+    let count = renumbering::dense(ids).len();
+    if count == 0 {
+        return Err(format!("The Todo items of {principal} are numbered densely already"));
+    }
+    Ok(format!("Renumber {count} Todo items of {principal}"))
+}
+
+/// Turns maintenance mode on or off. While it is on, update calls other than admin operations
+/// return a Maintenance error carrying the message and the periodic jobs pause, while queries keep working.
+/// Only admins of the canister may call this endpoint.
+///
+/// # Arguments
+///
+/// * `on` - Whether the canister is under maintenance.
+/// * `message` - Optional message explaining the maintenance to callers, such as when it ends.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the caller is not an admin or the message is too long.
+#[ic_cdk::update]
+fn set_maintenance_mode(on: bool, message: Option<String>) -> Result<(), Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Administer)?;
+    let mode = MaintenanceMode::new(on, message)?;
+    MAINTENANCE_MODE.with(|cell| cell.borrow_mut().set(mode).unwrap());
+    Ok(())
+}
+
+/// Turns a feature on or off for every principal without an override.
+/// Only admins of the canister may call this endpoint.
+///
+/// # Arguments
+///
+/// * `feature` - The feature.
+/// * `enabled` - Whether the feature is on.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the caller is not an admin.
+#[ic_cdk::update]
+fn set_feature_flag(feature: Feature, enabled: bool) -> Result<(), Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Administer)?;
+    FEATURE_FLAG_STORE.with(|store| FeatureFlagStoreWrapper { store }.set(feature, enabled));
+    Ok(())
+}
+
+/// Turns a feature on or off for a single principal regardless of the global setting, so that it can be rolled out gradually.
+/// Only admins of the canister may call this endpoint.
+///
+/// # Arguments
+///
+/// * `feature` - The feature.
+/// * `principal` - The principal identifier.
+/// * `enabled` - Whether the feature is on for the principal, or None to make it follow the global setting again.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the caller is not an admin or too many principals have an override.
+#[ic_cdk::update]
+fn set_feature_override(feature: Feature, principal: Principal, enabled: Option<bool>) -> Result<(), Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Administer)?;
+    FEATURE_FLAG_STORE.with(|store| FeatureFlagStoreWrapper { store }.set_override(feature, principal, enabled))
+}
+
+/// Lists the flags of every feature with their overrides.
+/// Only admins of the canister may call this endpoint.
+///
+/// # Returns
+///
+/// A Result containing the flags, or an Error if the caller is not an admin.
+#[ic_cdk::query]
+fn list_feature_flags() -> Result<Vec<FeatureFlag>, Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Administer)?;
+    Ok(FEATURE_FLAG_STORE.with(|store| FeatureFlagStoreWrapper { store }.list()))
+}
+
+/// Lists the features that are on for the caller, so that clients can hide the others.
+///
+/// # Returns
+///
+/// A vector of features.
+#[ic_cdk::query]
+fn get_my_features() -> Vec<Feature> {
+    let principal = ic_cdk::caller();
+    Feature::ALL.into_iter().filter(|feature| feature_enabled(*feature, principal)).collect()
+}
+
+/// Returns whether the canister is under maintenance, so that clients can explain why changes are rejected.
+///
+/// # Returns
+///
+/// The maintenance mode.
+#[ic_cdk::query]
+fn get_maintenance_mode() -> MaintenanceMode {
+    maintenance_mode()
+}
+
+/// Sets how many instructions long-running calls, such as bulk updates and exports, may execute before they stop
+/// and return where to continue. Only admins of the canister may call this endpoint.
+///
+/// # Arguments
+///
+/// * `instructions` - The number of instructions, between 100 million and 4 billion.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the caller is not an admin or the budget is out of range.
+#[ic_cdk::update]
+fn set_instruction_budget(instructions: u64) -> Result<(), Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Administer)?;
+    let budget = InstructionBudget::new(instructions)?;
+    INSTRUCTION_BUDGET.with(|cell| cell.borrow_mut().set(budget).unwrap());
+    Ok(())
+}
+
+/// Returns how many instructions long-running calls may execute before they stop.
+/// Only admins of the canister may call this endpoint.
+///
+/// # Returns
+///
+/// A Result containing the instruction budget, or an Error if the caller is not an admin.
+#[ic_cdk::query]
+fn get_instruction_budget() -> Result<InstructionBudget, Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Administer)?;
+    Ok(INSTRUCTION_BUDGET.with(|cell| *cell.borrow().get()))
+}
+
+/// Starts a maintenance job, which runs in slices across many messages until every item is processed.
+/// Only admins of the canister may call this endpoint.
+///
+/// # Arguments
+///
+/// * `kind` - The task the job performs.
+///
+/// # Returns
+///
+/// A Result containing the unique identifier for the job, or an Error if the caller is not an admin or a job of the
+/// same kind is running.
+#[ic_cdk::update]
+fn start_job(kind: JobKind) -> Result<JobId, Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Administer)?;
+    start_maintenance_job(kind)
+}
+
+/// Returns the progress of a maintenance job. Only admins of the canister may call this endpoint.
+///
+/// # Arguments
+///
+/// * `job_id` - The unique identifier for the job.
+///
+/// # Returns
+///
+/// A Result containing the job, or an Error if the caller is not an admin or the job is not found.
+#[ic_cdk::query]
+fn get_job_status(job_id: JobId) -> Result<Job, Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Administer)?;
+    JOB_STORE.with(|store| JobStoreWrapper { store }.get(job_id)).ok_or(Error::NotFound)
+}
+
+/// Returns the governance canister that administers the canister, if any.
+///
+/// # Returns
+///
+/// An Option containing the governance canister, or None if the controllers administer the canister.
+#[ic_cdk::query]
+fn get_governance() -> Option<Principal> {
+    governance_config().governance
+}
+
+/// Dumps the canister state in segments, as raw entries of its stores, so that it can be moved to a new canister
+/// across many messages with `import_segment`. Only admins of the canister may call this endpoint.
+///
+/// # Arguments
+///
+/// * `after_key` - The position returned with the previous segment, or None to start from the beginning.
+/// * `limit` - The maximum number of entries, at most 10000; segments also close before growing too large for a reply.
+///
+/// # Returns
+///
+/// A Result containing the segment, or an Error if the caller is not an admin.
+#[ic_cdk::query]
+fn export_segment(after_key: Option<SegmentKey>, limit: u32) -> Result<Segment, Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Administer)?;
+    Ok(dump::export_segment(after_key, limit, instruction_guard()))
+}
+
+/// Stores raw entries dumped by `export_segment` on another canister, replacing entries with the same keys.
+/// Once a governance canister is configured, only an adopted proposal may perform it.
+///
+/// # Arguments
+///
+/// * `pairs` - The entries, as dumped.
+///
+/// # Returns
+///
+/// A Result containing the number of stored entries, or an Error if the caller may not perform it or an entry names an unknown store.
+#[ic_cdk::update]
+fn import_segment(pairs: Vec<SegmentEntry>) -> Result<u64, Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Govern)?;
+    if let Some(entry) = pairs.iter().find(|entry| !dump::STORES.contains(&entry.store)) {
+        return Err(Error::InvalidInput(format!("Unknown store {}", entry.store)));
+    }
+    let last_id = LAST_TODO_ID.with(|id| *id.borrow().get());
+    for entry in &pairs {
+        dump::with_store(entry.store, |store| store.import(&entry.key, &entry.value));
+    }
+    // The entries bypass the Todo store wrapper, so nothing cached may outlive them.
+    store::forget_cached_reads();
+    // The imported counter may lag behind identifiers in use here or in the import; it never moves back.
+    let last_id = last_id.max(dump::highest_todo_id(&pairs));
+    LAST_TODO_ID.with(|id| id.borrow_mut().set(last_id).unwrap());
+    // Certifications and the schedule timer live on the heap and follow the imported state.
+    reset_certifications();
+    arm_schedule_timer();
+    Ok(pairs.len() as u64)
+}
+
+/// Fills the store with deterministic generated Todo items for local development and load tests.
+/// Only controllers of the canister may call this endpoint.
+///
+/// # Arguments
+///
+/// * `users` - The number of generated principals.
+/// * `todos_per_user` - The number of Todo items generated for each principal.
+///
+/// # Returns
+///
+/// A Result containing the number of created Todo items, or an Error if the caller is not a controller or too many are requested.
+#[ic_cdk::update]
+fn seed_fake_data(users: u32, todos_per_user: u32) -> Result<u64, Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Control)?;
+    seed::check_size(users, todos_per_user)?;
+    let now = clock::now();
+    let mut created = 0;
+    for user in 0..users {
+        let principal = seed::fake_principal(user);
+        USER_STORE.with(|store| UserStoreWrapper { store }.touch(principal, now));
+        let fakes: Vec<_> = (0..todos_per_user).map(|index| (generate_next_id(), seed::fake_todo(user, index, now))).collect();
+        let completed: Vec<TodoId> = fakes.iter().filter(|(_, fake)| fake.completed).map(|(id, _)| *id).collect();
+        let requests = fakes.into_iter().map(|(id, fake)| (id, fake.request)).collect();
+        let changes = TODO_STORE.with(|store| TodoStoreWrapper { store }.create_many(principal, requests, now))?;
+        created += changes.len() as u64;
+        changes.into_iter().for_each(record_change);
+        for id in completed {
+            TODO_STORE
+                .with(|store| TodoStoreWrapper { store }.toggle_todo_complete(principal, id, now))
+                .map(record_change)?;
+        }
+    }
+    Ok(created)
+}
+
+/// Lists the resources each principal consumed through update calls over a time range, heaviest users first.
+/// Only admins of the canister may call this endpoint.
+///
+/// # Arguments
+///
+/// * `range` - Optional time range, defaulting to the last 30 days.
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A Result containing the usage of each principal, or an Error if the caller is not an admin or the range is invalid.
+#[ic_cdk::query]
+fn list_usage(range: Option<StatsRange>, paginator: Option<Paginator>) -> Result<Vec<PrincipalUsage>, Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Administer)?;
+    let (from, to) = StatsRange::days(range, clock::now())?;
+    let paginator = paginator.unwrap_or_default();
+    Ok(USAGE_STORE.with(|store| UsageStoreWrapper { store }.roll_up(from, to, paginator)))
+}
+
+/// Lists the principals using the canister together with their usage statistics.
+/// Only admins of the canister may call this endpoint.
+///
+/// # Arguments
+///
+/// * `paginator` - Optional paginator for controlling the list output.
+///
+/// # Returns
+///
+/// A Result containing the statistics of each principal, or an Error if the caller is not an admin.
+#[ic_cdk::query]
+fn list_users(paginator: Option<Paginator>) -> Result<Vec<UserStats>, Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Administer)?;
+    let paginator = paginator.unwrap_or_default();
+    let users = USER_STORE.with(|store| UserStoreWrapper { store }.list_users(paginator));
+    Ok(TODO_STORE.with(|store| {
+        let wrapper = TodoStoreWrapper { store };
+        users
+            .into_iter()
+            .map(|(principal, activity)| {
+                let (todo_count, storage_bytes) = wrapper.usage(principal);
+                UserStats {
+                    principal,
+                    todo_count,
+                    storage_bytes,
+                    first_seen: activity.first_seen,
+                    last_activity: activity.last_activity,
+                }
+            })
+            .collect()
+    }))
+}
+
+/// Describes the running build, so that deployed frontends can detect version skew and prompt for a refresh.
+///
+/// # Returns
+///
+/// The crate version, git commit, schema version, supported interface versions and Candid interface.
+#[ic_cdk::query]
+fn get_build_info() -> BuildInfo {
+    build_info::current(__export_service())
+}
+
+/// Describes the interface and data layouts of the running build, so that tooling and other canisters
+/// can introspect the API at runtime. The same interface and schema version are embedded as public
+/// metadata of the module.
+///
+/// # Returns
+///
+/// The schema version, account bundle version, supported interface versions and Candid interface.
+#[ic_cdk::query]
+fn get_schema() -> Schema {
+    build_info::schema(__export_service())
+}
+
+/// Returns the Candid interface under the name tools such as the Candid UI look it up by.
+///
+/// # Returns
+///
+/// The Candid description of the public interface.
+#[ic_cdk::query(hidden = true)]
+fn __get_candid_interface_tmp_hack() -> String {
+    __export_service()
+}
+
+/// Lists every error variant together with its stable numeric code.
+///
+/// # Returns
+///
+/// A vector of error variants paired with their codes.
+#[ic_cdk::query]
+fn list_error_codes() -> Vec<(Error, u16)> {
+    Error::variants()
+        .into_iter()
+        .map(|error| {
+            let code = error.code();
+            (error, code)
+        })
+        .collect()
+}
+
+/// Lists the description of every error code in a language, so that frontends can show errors without
+/// translating them on their own.
+///
+/// # Arguments
+///
+/// * `language` - The language of the descriptions, defaulting to the caller's preferred language.
+///
+/// # Returns
+///
+/// A vector of error codes paired with their descriptions.
+#[ic_cdk::query]
+fn list_error_messages(language: Option<Language>) -> Vec<(u16, String)> {
+    let language = language.unwrap_or_else(|| preferences_of(ic_cdk::caller()).language());
+    Error::variants()
+        .iter()
+        .map(|error| (error.code(), Message::Error(error).render(language)))
+        .collect()
+}
+
+/// Keeps the data derived from the Todo store in sync with a change to a Todo item.
+///
+/// # Arguments
+///
+/// * `change` - The change to a Todo item.
+fn record_change(change: TodoChange) {
+    EVENT_STORE.with(|store| EventStoreWrapper { store }.record_change(&change, clock::now()));
+    sync_derived_data(&change);
+    create_next_occurrence(&change);
+}
+
+/// Creates the next occurrence of a recurring Todo item that was just completed. The rule moves to the
+/// new occurrence, so completing the same Todo item again does not create another one. Habits move on
+/// to their next occurrence by checking in instead, so completing one retires it.
+///
+/// # Arguments
+///
+/// * `change` - The change to a Todo item.
+fn create_next_occurrence(change: &TodoChange) {
+    let (Some(before), Some(after)) = (&change.before, &change.after) else {
+        return;
+    };
+    let Some(rule) = after.recurrence.as_deref().filter(|_| !after.habit && !before.is_completed && after.is_completed) else {
+        return;
+    };
+    let Ok(recurrence) = Recurrence::parse(rule) else {
+        return;
+    };
+    let principal = change.principal;
+    let now = clock::now();
+    let due_date = recurrence.next_after(after.due_date.map_or(now, |due_date| due_date.max(now)), preferences_of(principal).utc_offset());
+    let request = CreateTodo {
+        description: after.description.clone(),
+        priority: Some(after.priority),
+        tags: Some(after.tags.clone()),
+        due_date: Some(due_date),
+        project: after.project.clone(),
+        notes: after.notes.clone(),
+        recurrence: Some(rule.to_string()),
+    };
+    let id = generate_next_id();
+    TODO_STORE.with(|store| {
+        let wrapper = TodoStoreWrapper { store };
+        if let Ok(change) = wrapper.create_todo(principal, id, request, now) {
+            record_change(change);
+        }
+        if let Ok(change) = wrapper.set_recurrence(principal, after.id, None, now) {
+            record_change(change);
+        }
+    });
+}
+
+/// Updates the statistics, indexes, history and audit log after a change to a Todo item,
+/// without certifying the export again.
+///
+/// # Arguments
+///
+/// * `change` - The change to a Todo item.
+fn sync_derived_data(change: &TodoChange) {
+    STATS_STORE.with(|store| StatsStoreWrapper { store }.record_change(change));
+    if feature_enabled(Feature::Gamification, change.principal) {
+        SCORE_STORE.with(|store| ScoreStoreWrapper { store }.record_change(change));
+    }
+    BOARD_STORE.with(|store| BoardStoreWrapper { store }.record_change(change));
+    TAG_STORE.with(|store| TagStoreWrapper { store }.record_change(change));
+    TAG_TREND_STORE.with(|store| TagTrendStoreWrapper { store }.record_change(change));
+    TODO_COUNT_STORE.with(|store| TodoCountStoreWrapper { store }.record_change(change));
+    MODIFIED_INDEX.with(|store| TodoIndexWrapper::modified(store).record_change(change));
+    RANK_INDEX.with(|store| TodoIndexWrapper::rank(store).record_change(change));
+    DUE_INDEX.with(|store| TodoIndexWrapper::due(store).record_change(change));
+    COMPLETION_INDEX.with(|store| TodoIndexWrapper::completion(store).record_change(change));
+    GEO_INDEX.with(|store| GeoIndexWrapper { store }.record_change(change));
+    let now = clock::now();
+    let max_revisions = preferences_of(change.principal).max_revisions();
+    HISTORY_STORE.with(|store| HistoryStoreWrapper { store }.record_change(change, now, max_revisions));
+    AUDIT_STORE.with(|store| AuditStoreWrapper { store }.record_change(change, ic_cdk::caller(), now));
+    PRIORITY_HISTORY_STORE.with(|store| PriorityHistoryStoreWrapper { store }.record_change(change, ic_cdk::caller(), now));
+    if let (Some(todo), None) = (&change.before, &change.after) {
+        remove_attachments(change.principal, todo.id);
+        HABIT_STORE.with(|store| HabitStoreWrapper { store }.remove_all(change.principal, todo.id));
+        RELATION_STORE.with(|store| RelationStoreWrapper { store }.remove_all(change.principal, todo.id));
+        PRIORITY_HISTORY_STORE.with(|store| PriorityHistoryStoreWrapper { store }.remove_all(change.principal, todo.id));
+    }
+}
+
+/// Adds a Todo item put back into the Todo store without a recorded change to the indexes.
+///
+/// # Arguments
+///
+/// * `principal` - The principal owning the Todo item.
+/// * `todo` - The Todo item.
+fn index_todo(principal: Principal, todo: &Todo) {
+    MODIFIED_INDEX.with(|store| TodoIndexWrapper::modified(store).insert(principal, todo));
+    RANK_INDEX.with(|store| TodoIndexWrapper::rank(store).insert(principal, todo));
+    DUE_INDEX.with(|store| TodoIndexWrapper::due(store).insert(principal, todo));
+    COMPLETION_INDEX.with(|store| TodoIndexWrapper::completion(store).insert(principal, todo));
+    GEO_INDEX.with(|store| GeoIndexWrapper { store }.insert(principal, todo));
+}
+
+/// Removes a Todo item taken out of the Todo store without a recorded change from the indexes.
+///
+/// # Arguments
+///
+/// * `principal` - The principal owning the Todo item.
+/// * `todo` - The Todo item.
+fn unindex_todo(principal: Principal, todo: &Todo) {
+    MODIFIED_INDEX.with(|store| TodoIndexWrapper::modified(store).remove(principal, todo));
+    RANK_INDEX.with(|store| TodoIndexWrapper::rank(store).remove(principal, todo));
+    DUE_INDEX.with(|store| TodoIndexWrapper::due(store).remove(principal, todo));
+    COMPLETION_INDEX.with(|store| TodoIndexWrapper::completion(store).remove(principal, todo));
+    GEO_INDEX.with(|store| GeoIndexWrapper { store }.remove(principal, todo));
+}
+
+/// Removes the files attached to a Todo item that was deleted.
+///
+/// # Arguments
+///
+/// * `principal` - The principal owning the Todo item.
+/// * `todo_id` - The unique identifier for the Todo item.
+fn remove_attachments(principal: Principal, todo_id: TodoId) {
+    let attachments =
+        ATTACHMENT_STORE.with(|store| AttachmentStoreWrapper { store }.list(principal, todo_id));
+    for attachment in attachments {
+        ATTACHMENT_STORE.with(|store| AttachmentStoreWrapper { store }.remove(principal, attachment.id));
+        CHUNK_STORE.with(|store| ChunkStoreWrapper { store }.remove_all(principal, attachment.id));
+        uncertify_download(principal, Download::Attachment(attachment.id));
+    }
+}
+
+/// Builds the response of a download of a principal.
+///
+/// # Arguments
+///
+/// * `principal` - The principal owning the download.
+/// * `download` - The download.
+///
+/// # Returns
+///
+/// An Option containing the response, or None if the attachment is not found, unfinished or too large
+/// to be served over HTTP.
+fn download_response(principal: Principal, download: Download) -> Option<HttpResponse> {
+    match download {
+        Download::Export => {
+            let todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+            Some(downloads::export_response(&todos))
+        }
+        Download::Attachment(id) => {
+            let attachment = ATTACHMENT_STORE
+                .with(|store| AttachmentStoreWrapper { store }.get(principal, id))
+                .filter(|attachment| attachment.complete && attachment.size <= downloads::MAX_DOWNLOAD_BYTES)?;
+            let body = CHUNK_STORE.with(|store| ChunkStoreWrapper { store }.read(principal, id));
+            Some(downloads::attachment_response(&attachment, body))
+        }
+    }
+}
+
+/// Certifies the current response of a download requested with the download token of its owner.
+///
+/// # Arguments
+///
+/// * `principal` - The principal owning the download.
+/// * `download` - The download.
+/// * `token` - The download token of the principal.
+///
+/// # Returns
+///
+/// An Option containing the certified response, or None if the download no longer exists.
+fn certify_download(principal: Principal, download: Download, token: &str) -> Option<HttpResponse> {
+    let response = download_response(principal, download);
+    CERTIFIER.with(|certifier| {
+        let mut certifier = certifier.borrow_mut();
+        match &response {
+            Some(response) => certifier.certify(principal, download, token, response),
+            None => certifier.uncertify(principal, download),
+        }
+        ic_cdk::api::set_certified_data(&certifier.root_hash());
+    });
+    response
+}
+
+/// Withdraws the certification of a download whose response changed. Building a response can take long, such as
+/// reading the chunks of a large attachment, so the download is certified again only once it is requested.
+///
+/// # Arguments
+///
+/// * `principal` - The principal owning the download.
+/// * `download` - The download.
+fn uncertify_download(principal: Principal, download: Download) {
+    CERTIFIER.with(|certifier| {
+        let mut certifier = certifier.borrow_mut();
+        certifier.uncertify(principal, download);
+        ic_cdk::api::set_certified_data(&certifier.root_hash());
+    });
+}
+
+/// Withdraws the certifications of every download of a principal, such as when its download token changes.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+fn uncertify_downloads(principal: Principal) {
+    CERTIFIER.with(|certifier| {
+        let mut certifier = certifier.borrow_mut();
+        certifier.uncertify_all(principal);
+        ic_cdk::api::set_certified_data(&certifier.root_hash());
+    });
+}
+
+/// Withdraws the certifications of all downloads, leaving only the `Not found` response certified. The certification
+/// tree lives on the heap, so this is all it holds when the canister starts; downloads are certified as they are requested.
+fn reset_certifications() {
+    CERTIFIER.with(|certifier| {
+        let mut certifier = certifier.borrow_mut();
+        *certifier = Certifier::new();
+        ic_cdk::api::set_certified_data(&certifier.root_hash());
+    });
+}
+
+/// Checks that a principal may perform an action on a resource, gathering what the rules in the authorization module
+/// need to know about them.
+///
+/// # Arguments
+///
+/// * `principal` - The principal to be checked.
+/// * `resource` - What the principal acts on.
+/// * `action` - What the principal does.
+///
+/// # Returns
+///
+/// A Result indicating success or an Unauthorized Error.
+fn authorize(principal: Principal, resource: Resource, action: Action) -> Result<(), Error> {
+    let grant = match resource {
+        Resource::Account(owner) if owner != principal => {
+            ACCESS_STORE.with(|store| AccessStoreWrapper { store }.get(owner, principal))
+        }
+        _ => None,
+    };
+    let context = Context {
+        grant,
+        governance: governance_config(),
+        is_controller: ic_cdk::api::is_controller(&principal),
+        is_peer: action == Action::Reference && PEER_STORE.with(|store| PeerStoreWrapper { store }.contains(&principal)),
+    };
+    authorization::check(principal, resource, action, &context)
+}
+
+/// Returns who administers the canister.
+fn governance_config() -> GovernanceConfig {
+    GOVERNANCE_CONFIG.with(|config| config.borrow().get().clone())
+}
+
+/// Returns whether a feature is on for a principal.
+///
+/// # Arguments
+///
+/// * `feature` - The feature.
+/// * `principal` - The principal identifier.
+fn feature_enabled(feature: Feature, principal: Principal) -> bool {
+    FEATURE_FLAG_STORE.with(|store| FeatureFlagStoreWrapper { store }.is_enabled(feature, principal))
+}
+
+/// Checks that a feature is on for a principal.
+///
+/// # Arguments
+///
+/// * `feature` - The feature.
+/// * `principal` - The principal identifier.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the feature is turned off.
+fn ensure_feature(feature: Feature, principal: Principal) -> Result<(), Error> {
+    FEATURE_FLAG_STORE.with(|store| FeatureFlagStoreWrapper { store }.ensure_enabled(feature, principal))
+}
+
+/// Returns whether the canister is under maintenance.
+fn maintenance_mode() -> MaintenanceMode {
+    MAINTENANCE_MODE.with(|mode| mode.borrow().get().clone())
+}
+
+/// Checks how much of each of their quotas a principal used, so that replies to changes can nudge them before
+/// changes are rejected.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `staged` - The Todo items staged by an import, counted as if they were created.
+///
+/// # Returns
+///
+/// A vector of warnings, one for each quota with at least 80% used.
+fn quota_warnings(principal: Principal, staged: u64) -> Vec<Warning> {
+    let count = |len: usize, limit: usize| (len as u64, limit as u64);
+    [
+        (
+            Quota::Todos,
+            (TODO_COUNT_STORE.with(|store| TodoCountStoreWrapper { store }.count(principal)) + staged, todo_counts::MAX_TODOS),
+        ),
+        (
+            Quota::AttachmentBytes,
+            (
+                ATTACHMENT_STORE.with(|store| AttachmentStoreWrapper { store }.used_bytes(principal)),
+                attachments::MAX_BYTES_PER_PRINCIPAL,
+            ),
+        ),
+        (Quota::Labels, count(LABEL_STORE.with(|store| LabelStoreWrapper { store }.list(principal).len()), labels::MAX_LABELS)),
+        (Quota::Goals, count(GOAL_STORE.with(|store| GoalStoreWrapper { store }.list(principal).len()), goals::MAX_GOALS)),
+        (
+            Quota::Checklists,
+            count(CHECKLIST_STORE.with(|store| ChecklistStoreWrapper { store }.list(principal).len()), checklists::MAX_CHECKLISTS),
+        ),
+        (
+            Quota::Namespaces,
+            count(NAMESPACE_STORE.with(|store| NamespaceStoreWrapper { store }.list(principal).len()), namespaces::MAX_NAMESPACES),
+        ),
+        (
+            Quota::ScheduledTodos,
+            count(SCHEDULE_STORE.with(|store| ScheduleStoreWrapper { store }.list(principal).len()), scheduled::MAX_SCHEDULED),
+        ),
+        (
+            Quota::Integrations,
+            count(INTEGRATION_STORE.with(|store| IntegrationStoreWrapper { store }.list(principal).len()), hooks::MAX_INTEGRATIONS),
+        ),
+        (
+            Quota::ApiTokens,
+            count(API_TOKEN_STORE.with(|store| ApiTokenStoreWrapper { store }.list(principal).len()), api_tokens::MAX_API_TOKENS),
+        ),
+        (Quota::AccountGrants, count(ACCESS_STORE.with(|store| AccessStoreWrapper { store }.list(principal).len()), access::MAX_GRANTS)),
+    ]
+    .into_iter()
+    .filter_map(|(quota, (used, limit))| quotas::check(quota, used, limit))
+    .collect()
+}
+
+/// Checks that a namespace was created by a principal.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `namespace` - The namespace, or None for the default space.
+///
+/// # Returns
+///
+/// A Result containing the validated namespace, or an Error if it is invalid or not found.
+fn existing_namespace(principal: Principal, namespace: Option<String>) -> Result<Option<String>, Error> {
+    let Some(namespace) = namespace else {
+        return Ok(None);
+    };
+    let namespace = validation::namespace(&namespace)?;
+    if !NAMESPACE_STORE.with(|store| NamespaceStoreWrapper { store }.exists(principal, &namespace)) {
+        return Err(Error::NotFound);
+    }
+    Ok(Some(namespace))
+}
+
+/// Counts an expensive request against a caller. Only update calls may count, since a query call discards
+/// the counter along with its other state changes.
+///
+/// # Arguments
+///
+/// * `caller` - The caller.
+///
+/// # Returns
+///
+/// A Result indicating success or a RateLimited Error if the caller made too many expensive requests recently.
+fn throttle(caller: Caller) -> Result<(), Error> {
+    THROTTLE.with(|throttle| throttle.borrow_mut().admit(caller, clock::now()))
+}
+
+/// Counts an expensive request served over HTTP by an update call against the IP address of the client,
+/// if the HTTP gateway forwarded it.
+///
+/// # Arguments
+///
+/// * `request` - The HTTP request.
+///
+/// # Returns
+///
+/// A Result indicating success or a RateLimited Error if the client made too many expensive requests recently.
+fn throttle_client(request: &HttpRequest) -> Result<(), Error> {
+    match throttle::client_ip(request.header("X-Forwarded-For"), request.header("X-Real-IP"), throttle::TRUST_REAL_IP) {
+        Some(ip) => throttle(Caller::Ip(ip)),
+        None => Ok(()),
+    }
+}
+
+/// Starts guarding the current call against exceeding the configured instruction budget.
+fn instruction_guard() -> InstructionGuard {
+    INSTRUCTION_BUDGET.with(|cell| cell.borrow().get().guard())
+}
+
+/// Returns the caller of an update call, authorized to change their own account, and records it as active, adding the
+/// onboarding samples on their first call. The call is metered until the returned meter is dropped, so callers keep it until they return.
+///
+/// # Returns
+///
+/// A Result containing the principal of the caller and the meter, or a Maintenance Error while the canister is under maintenance.
+fn active_caller() -> Result<(Principal, Meter), Error> {
+    maintenance_mode().ensure_open()?;
+    let principal = ic_cdk::caller();
+    authorize(principal, Resource::Account(principal), Action::Manage)?;
+    let now = clock::now();
+    let meter = Meter::start(principal, day_of(now));
+    if USER_STORE.with(|store| UserStoreWrapper { store }.touch(principal, now)) {
+        add_samples(principal, now);
+    }
+    Ok((principal, meter))
+}
+
+/// Adds the onboarding samples to the Todo items of a principal.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+///
+/// # Returns
+///
+/// The identifiers of the samples.
+fn add_samples(principal: Principal, now: u64) -> Vec<TodoId> {
+    samples::requests()
+        .into_iter()
+        .filter_map(|request| {
+            let id = generate_next_id();
+            let change = TODO_STORE
+                .with(|store| TodoStoreWrapper { store }.create_sample_todo(principal, id, request, now))
+                .ok()?;
+            record_change(change);
+            Some(id)
+        })
+        .collect()
+}
+
+/// Returns the preferences of a principal.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+///
+/// # Returns
+///
+/// The preferences, or the defaults if the principal never set any.
+fn preferences_of(principal: Principal) -> Preferences {
+    PREFERENCE_STORE.with(|store| PreferenceStoreWrapper { store }.get(principal))
+}
+
+/// Checks that a principal who opted into rejecting duplicates has no open Todo item with a description.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `description` - The description of the new Todo item.
+///
+/// # Returns
+///
+/// A Result indicating success or an AlreadyExists Error naming the open Todo item.
+fn ensure_not_duplicate(principal: Principal, description: &str) -> Result<(), Error> {
+    if preferences_of(principal).reject_duplicates != Some(true) {
+        return Ok(());
+    }
+    let guard = instruction_guard();
+    match TODO_STORE.with(|store| TodoStoreWrapper { store }.find_duplicate(principal, description, &guard)) {
+        Some(id) => Err(Error::AlreadyExists(Some(format!("Todo item {id} has the same description")))),
+        None => Ok(()),
+    }
+}
+
+/// Validates the Todo items of an import, applying the principal's default priority.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `items` - The fields of the Todo items.
+/// * `first` - The position of the first item in the import.
+///
+/// # Returns
+///
+/// A Result containing the validated Todo items, or an InvalidInput Error naming the position of the first invalid one.
+fn validated_import_items(principal: Principal, items: Vec<CreateTodo>, first: u32) -> Result<Vec<CreateTodo>, Error> {
+    items
+        .into_iter()
+        .zip(first..)
+        .map(|(request, position)| {
+            validation::create_request(with_default_priority(principal, request)).map_err(|error| match error {
+                Error::InvalidInput(message) => Error::InvalidInput(format!("Item {position}: {message}")),
+                error => error,
+            })
+        })
+        .collect()
+}
+
+/// Checks that a principal who opted into rejecting duplicates imports no Todo item with the description
+/// of an open Todo item, of an earlier staged item or of another item of the batch.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `earlier` - The items staged before the batch.
+/// * `items` - The batch of items.
+/// * `first` - The position of the first item of the batch in the import.
+///
+/// # Returns
+///
+/// A Result indicating success or an AlreadyExists Error naming the position of the first duplicate.
+fn ensure_no_duplicates(principal: Principal, earlier: &[CreateTodo], items: &[CreateTodo], first: u32) -> Result<(), Error> {
+    if preferences_of(principal).reject_duplicates != Some(true) {
+        return Ok(());
+    }
+    let mut seen: BTreeSet<String> = earlier.iter().map(|item| store::normalized_description(&item.description)).collect();
+    if let Some(position) = items.iter().zip(first..).find_map(|(item, position)| {
+        (!seen.insert(store::normalized_description(&item.description))).then_some(position)
+    }) {
+        return Err(Error::AlreadyExists(Some(format!("Item {position}: another staged item has the same description"))));
+    }
+    let guard = instruction_guard();
+    let descriptions: Vec<&str> = items.iter().map(|item| item.description.as_str()).collect();
+    let duplicates = TODO_STORE.with(|store| TodoStoreWrapper { store }.find_duplicates(principal, &descriptions, &guard));
+    match duplicates.into_iter().zip(first..).find_map(|(id, position)| id.map(|id| (id, position))) {
+        Some((id, position)) => Err(Error::AlreadyExists(Some(format!("Item {position}: Todo item {id} has the same description")))),
+        None => Ok(()),
+    }
+}
+
+/// Creates a Todo item from a creation request on behalf of a principal, rejecting duplicates if they opted to.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `request` - The fields of the new Todo item.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+///
+/// # Returns
+///
+/// A Result containing the unique identifier for the newly created Todo item, or an Error if the input is invalid,
+/// the principal keeps too many Todo items or, for principals rejecting duplicates, an open Todo item has the same description.
+fn create_requested_todo(principal: Principal, request: CreateTodo, now: u64) -> Result<TodoId, Error> {
+    TODO_COUNT_STORE.with(|store| TodoCountStoreWrapper { store }.ensure_room(principal, 1))?;
+    ensure_not_duplicate(principal, &request.description)?;
+    let request = with_default_priority(principal, request);
+    let id = generate_next_id();
+    TODO_STORE
+        .with(|store| TodoStoreWrapper { store }.create_todo(principal, id, request, now))
+        .map(record_change)?;
+    Ok(id)
+}
+
+/// Fills in the default priority of a principal in a creation request that does not set one.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `request` - The creation request.
+///
+/// # Returns
+///
+/// The creation request with a priority.
+fn with_default_priority(principal: Principal, request: CreateTodo) -> CreateTodo {
+    CreateTodo {
+        priority: request.priority.or(preferences_of(principal).default_priority),
+        ..request
+    }
+}
+
+/// Returns the current local day of a principal.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+///
+/// # Returns
+///
+/// The local day, counted in days since the Unix epoch.
+fn local_today_of(principal: Principal) -> Day {
+    local_day_of(clock::now(), preferences_of(principal).utc_offset())
+}
+
+/// Adds a notification to the inbox of a principal, unless they turned off notifications of its kind.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `kind` - What the notification is about.
+/// * `todo_id` - The unique identifier of the Todo item concerned.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+fn notify(principal: Principal, kind: NotificationKind, todo_id: TodoId, now: u64) {
+    if preferences_of(principal).notifications.unwrap_or_default().allows(&kind) {
+        NOTIFICATION_STORE.with(|store| NotificationStoreWrapper { store }.notify(principal, kind, todo_id, now));
+    }
+}
+
+/// Generates the next unique identifier for a Todo item. The counter only ever moves forward, so identifiers of
+/// deleted and archived Todo items are never handed out again.
+///
+/// # Returns
+///
+/// The next unique identifier for a Todo item.
+fn generate_next_id() -> TodoId {
+    LAST_TODO_ID.with(|id| {
+        let mut id = id.borrow_mut();
+        let new_id = *id.get() + 1;
+        id.set(new_id).unwrap()
+    })
+}
+
+/// Generates the next unique identifier for an integration, which is never reused once the integration is deleted.
+///
+/// # Returns
+///
+/// The next unique identifier for an integration.
+fn generate_next_integration_id() -> IntegrationId {
+    LAST_INTEGRATION_ID.with(|id| {
+        let mut id = id.borrow_mut();
+        let new_id = *id.get() + 1;
+        id.set(new_id).unwrap();
+        new_id
+    })
+}
+
+/// Moves open Todo items that were due on the local day that just ended to the same time on the next day,
+/// for principals who opted in. Todo items overdue since earlier days are left alone.
+fn roll_over_unfinished_todos() {
+    let now = clock::now();
+    let principals = PREFERENCE_STORE.with(|store| {
+        PreferenceStoreWrapper { store }.opted_in(|preferences| preferences.roll_over_unfinished.filter(|enabled| *enabled).map(|_| preferences.utc_offset()))
+    });
+    for (principal, utc_offset) in principals {
+        let today_started_at = local_day_start(now, utc_offset);
+        let yesterday_started_at = today_started_at.saturating_sub(NANOS_PER_DAY);
+        let changes = TODO_STORE.with(|store| {
+            TodoStoreWrapper { store }.roll_over_todos(principal, yesterday_started_at, today_started_at, now)
+        });
+        for change in changes {
+            record_change(change);
+        }
+    }
+}
+
+/// Raises the priority of open Todo items left untouched for longer than their owners allow,
+/// reminding the owners of each escalated Todo item.
+fn escalate_stale_todos() {
+    let now = clock::now();
+    let principals = PREFERENCE_STORE.with(|store| {
+        PreferenceStoreWrapper { store }.opted_in(|preferences| preferences.escalate_after_days)
+    });
+    for (principal, days) in principals {
+        let untouched_since = now.saturating_sub(u64::from(days) * NANOS_PER_DAY);
+        let changes = TODO_STORE.with(|store| {
+            TodoStoreWrapper { store }.escalate_stale_todos(principal, untouched_since, now)
+        });
+        for change in changes {
+            if let Some(todo) = &change.after {
+                notify(principal, NotificationKind::Reminder, todo.id, now);
+            }
+            record_change(change);
+        }
+    }
+}
+
+/// Notifies the owners of open Todo items past their due date, at most once per Todo item and local day.
+/// Todo items whose owners muted overdue notifications are skipped.
+fn notify_overdue_todos() {
+    let now = clock::now();
+    let principals = USER_STORE.with(|store| UserStoreWrapper { store }.principals());
+    for principal in principals {
+        let utc_offset = preferences_of(principal).utc_offset();
+        let today_started_at = local_day_start(now, utc_offset);
+        let todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+        for todo in todos {
+            let overdue = todo.due_date.is_some_and(|due_date| due_date <= now);
+            if !overdue || todo.is_completed || todo.overdue_muted {
+                continue;
+            }
+            let notified = NOTIFICATION_STORE.with(|store| {
+                NotificationStoreWrapper { store }.notified_since(principal, &NotificationKind::Overdue, todo.id, today_started_at)
+            });
+            if !notified {
+                notify(principal, NotificationKind::Overdue, todo.id, now);
+            }
+        }
+    }
+}
+
+/// Moves Todo items completed longer ago than their owners allow into the archive.
+/// Archived Todo items keep counting towards statistics, so no change is recorded.
+fn archive_completed_todos() {
+    let now = clock::now();
+    let principals = PREFERENCE_STORE.with(|store| {
+        PreferenceStoreWrapper { store }.opted_in(|preferences| preferences.archive_after_days)
+    });
+    for (principal, days) in principals {
+        let completed_before = now.saturating_sub(u64::from(days) * NANOS_PER_DAY);
+        let todos = TODO_STORE.with(|store| {
+            TodoStoreWrapper { store }.take_completed_todos(principal, completed_before)
+        });
+        todos.iter().for_each(|todo| unindex_todo(principal, todo));
+        EVENT_STORE.with(|store| {
+            let wrapper = EventStoreWrapper { store };
+            todos.iter().for_each(|todo| wrapper.append(principal, TodoEventKind::Removed(todo.id), now));
+        });
+        ARCHIVE_STORE.with(|store| ArchiveStoreWrapper { store }.archive(principal, todos));
+    }
+}
+
+/// Starts a maintenance job and schedules its first slice.
+///
+/// # Arguments
+///
+/// * `kind` - The task the job performs.
+///
+/// # Returns
+///
+/// A Result containing the unique identifier for the job, or an Error if a job of the same kind is running.
+fn start_maintenance_job(kind: JobKind) -> Result<JobId, Error> {
+    let job = JOB_STORE.with(|store| JobStoreWrapper { store }.start(kind, clock::now()))?;
+    schedule_job_slice(job.id);
+    Ok(job.id)
+}
+
+/// Schedules the next slice of a job in a message of its own, so that every slice gets a fresh instruction limit.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the job.
+fn schedule_job_slice(id: JobId) {
+    ic_cdk_timers::set_timer(Duration::ZERO, move || run_job_slice(id));
+}
+
+/// Runs a slice of a job within the instruction budget, persists the progress and schedules the next slice
+/// until the job is done.
+///
+/// # Arguments
+///
+/// * `id` - The unique identifier for the job.
+fn run_job_slice(id: JobId) {
+    let Some(mut job) = JOB_STORE.with(|store| JobStoreWrapper { store }.get(id)) else {
+        return;
+    };
+    let now = clock::now();
+    let guard = instruction_guard();
+    match job.kind {
+        JobKind::Reindex => job.run_slice(|| guard.exhausted(), now, reindex_next),
+        JobKind::Purge => {
+            let principals = principals_with_retention();
+            job.run_slice(|| guard.exhausted(), now, |cursor| {
+                let start = cursor.map_or(Bound::Unbounded, |(principal, _)| Bound::Excluded(principal));
+                let principal = *principals.range((start, Bound::Unbounded)).next()?;
+                purge(principal, now);
+                Some((principal, 0))
+            })
+        }
+        JobKind::Deliver => {
+            let channels: BTreeMap<Principal, EmailChannel> = EMAIL_CHANNEL_STORE.with(|store| EmailChannelStoreWrapper { store }.all()).into_iter().collect();
+            let links: BTreeMap<Principal, TelegramLink> = TELEGRAM_STORE.with(|store| TelegramStoreWrapper { store }.all()).into_iter().collect();
+            let principals: BTreeSet<Principal> = channels.keys().chain(links.keys()).copied().collect();
+            let outcalls = Cell::new(0);
+            let exhausted = || guard.exhausted() || outcalls.get() >= MAX_OUTCALLS_PER_ROUND;
+            job.run_slice(exhausted, now, |cursor| {
+                let start = cursor.map_or(Bound::Unbounded, |(principal, _)| Bound::Excluded(principal));
+                let principal = *principals.range((start, Bound::Unbounded)).next()?;
+                outcalls.set(outcalls.get() + deliver(principal, channels.get(&principal), links.get(&principal), now));
+                Some((principal, 0))
+            })
+        }
+    }
+    // Deliveries carry on with the next maintenance round rather than right away, bounding the outcalls per round.
+    let running = job.state == JobState::Running && job.kind != JobKind::Deliver;
+    JOB_STORE.with(|store| JobStoreWrapper { store }.save(job));
+    if running {
+        schedule_job_slice(id);
+    }
+}
+
+/// Adds the Todo item following a position in the Todo store to the indexes.
+///
+/// # Arguments
+///
+/// * `cursor` - The key of the last Todo item reindexed, or None to start with the first one.
+///
+/// # Returns
+///
+/// The key of the Todo item, or None if no Todo item follows.
+fn reindex_next(cursor: Option<JobCursor>) -> Option<JobCursor> {
+    let (key, todo) = TODO_STORE.with(|store| TodoStoreWrapper { store }.next_record(cursor))?;
+    if let Some(todo) = todo {
+        index_todo(key.0, &todo);
+    }
+    Some(key)
+}
+
+/// Resumes the jobs that were running when the canister was upgraded, whose scheduled slices were dropped.
+/// Deliveries carry on with the next maintenance round.
+fn resume_jobs() {
+    JOB_STORE
+        .with(|store| JobStoreWrapper { store }.running())
+        .into_iter()
+        .filter(|job| job.kind != JobKind::Deliver)
+        .for_each(|job| schedule_job_slice(job.id));
+}
+
+/// Lists the principals with retention settings, whose archived Todo items and previous versions are purged.
+fn principals_with_retention() -> BTreeSet<Principal> {
+    PREFERENCE_STORE.with(|store| {
+        let wrapper = PreferenceStoreWrapper { store };
+        let archive = wrapper.opted_in(|preferences| preferences.archive_retention_days);
+        let revisions = wrapper.opted_in(|preferences| preferences.max_revisions);
+        archive.into_iter().chain(revisions).map(|(principal, _)| principal).collect()
+    })
+}
+
+/// Removes the usage recorded on days that are no longer kept.
+fn prune_usage() {
+    let before = day_of(clock::now()).saturating_sub(USAGE_RETENTION_DAYS);
+    USAGE_STORE.with(|store| UsageStoreWrapper { store }.prune(before));
+}
+
+/// Deletes the archived Todo items and previous versions a principal no longer keeps according to their
+/// retention settings. Purged Todo items are gone for good, along with their attachments.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+///
+/// # Returns
+///
+/// What was removed.
+fn purge(principal: Principal, now: u64) -> PurgeSummary {
+    let preferences = preferences_of(principal);
+    let mut summary = PurgeSummary::default();
+    if let Some(days) = preferences.archive_retention_days {
+        let completed_before = now.saturating_sub(u64::from(days) * NANOS_PER_DAY);
+        let todos = ARCHIVE_STORE.with(|store| ArchiveStoreWrapper { store }.purge(principal, completed_before));
+        summary.archived_todos = todos.len() as u32;
+        for todo in todos {
+            sync_derived_data(&TodoChange { principal, before: Some(todo), after: None });
+        }
+    }
+    if preferences.max_revisions.is_some() {
+        summary.revisions =
+            HISTORY_STORE.with(|store| HistoryStoreWrapper { store }.trim(principal, preferences.max_revisions()));
+    }
+    summary
+}
+
+/// Clears the focus lists of principals for whom a new local day has started.
+fn reset_my_days() {
+    MY_DAY_STORE.with(|store| MyDayStoreWrapper { store }.clear_stale(local_today_of));
+}
+
+/// Sends a principal the email reminders, Telegram announcements and weekly review that are due.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `channel` - The email channel of the principal, if any.
+/// * `link` - The Telegram chat linked by the principal, if any.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+///
+/// # Returns
+///
+/// The number of outcalls made.
+fn deliver(principal: Principal, channel: Option<&EmailChannel>, link: Option<&TelegramLink>, now: u64) -> u32 {
+    let mut outcalls = 0;
+    if let Some(channel) = channel {
+        outcalls += send_email_reminders(principal, channel, now);
+    }
+    if let Some(link) = link {
+        outcalls += send_telegram_announcements(principal, link, now);
+    }
+    outcalls + send_weekly_review(principal, channel, link, now)
+}
+
+/// Emails a principal reminders about their open Todo items due within the next day.
+/// Each due date is reminded about once; the outcome is kept in the delivery log.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `channel` - The email channel of the principal.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+///
+/// # Returns
+///
+/// The number of outcalls made.
+fn send_email_reminders(principal: Principal, channel: &EmailChannel, now: u64) -> u32 {
+    let preferences = preferences_of(principal);
+    if !preferences.notifications.unwrap_or_default().reminders {
+        return 0;
+    }
+    let mut outcalls = 0;
+    let todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+    for todo in todos {
+        let Some(due_date) = todo.due_date else {
+            continue;
+        };
+        if todo.is_completed || due_date <= now || due_date > now + REMINDER_LEAD {
+            continue;
+        }
+        let id = EMAIL_LOG_STORE.with(|store| {
+            let wrapper = EmailLogStoreWrapper { store };
+            (!wrapper.contains(principal, todo.id, due_date))
+                .then(|| wrapper.start(principal, todo.id, due_date, now))
+        });
+        let Some(id) = id else {
+            continue;
+        };
+        let message = email::reminder_message(&todo, due_date, preferences.utc_offset(), preferences.language());
+        let mut request = email::request_for(channel, &message, format!("{principal}-{id}"));
+        request.transform = Some(http::status_only_transform());
+        ic_cdk::spawn(async move {
+            let status = match outcall::http_request(request, OUTCALL_CYCLES).await {
+                Ok((response,)) => email::delivery_status(&response),
+                Err((code, message)) => DeliveryStatus::Failed(format!("{code:?}: {message}")),
+            };
+            EMAIL_LOG_STORE.with(|store| EmailLogStoreWrapper { store }.finish(principal, id, status));
+        });
+        outcalls += 1;
+    }
+    outcalls
+}
+
+/// Announces a principal's Todo items coming due or becoming overdue since the last announcement in their linked Telegram chat.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `link` - The Telegram chat linked by the principal.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+///
+/// # Returns
+///
+/// The number of outcalls made.
+fn send_telegram_announcements(principal: Principal, link: &TelegramLink, now: u64) -> u32 {
+    let preferences = preferences_of(principal);
+    let mut outcalls = 0;
+    if preferences.notifications.unwrap_or_default().reminders {
+        let todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+        let messages = telegram::due_date_messages(&todos, link.announced_until, now, REMINDER_LEAD, preferences.utc_offset(), preferences.language());
+        for text in messages {
+            let mut request = telegram::send_message_request(link, &text);
+            request.transform = Some(http::status_only_transform());
+            ic_cdk::spawn(async move {
+                // Announcements are best effort; a failed message is not retried.
+                let _ = outcall::http_request(request, OUTCALL_CYCLES).await;
+            });
+            outcalls += 1;
+        }
+    }
+    TELEGRAM_STORE.with(|store| TelegramStoreWrapper { store }.set_announced_until(principal, now));
+    outcalls
+}
+
+/// Sends a principal who opted in the review of the seven days ending on their local Sunday, once its evening
+/// begins, by email and to the linked Telegram chat. Each week is reviewed once; principals without either
+/// channel are skipped until they set one up.
+///
+/// # Arguments
+///
+/// * `principal` - The principal identifier.
+/// * `channel` - The email channel of the principal, if any.
+/// * `link` - The Telegram chat linked by the principal, if any.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+///
+/// # Returns
+///
+/// The number of outcalls made.
+fn send_weekly_review(principal: Principal, channel: Option<&EmailChannel>, link: Option<&TelegramLink>, now: u64) -> u32 {
+    let preferences = preferences_of(principal);
+    let utc_offset = preferences.utc_offset();
+    if !preferences.weekly_review.unwrap_or_default() || !review::is_review_time(now, utc_offset) || (channel.is_none() && link.is_none()) {
+        return 0;
+    }
+    let week_start = local_day_of(now, utc_offset).saturating_sub(6);
+    if !REVIEW_LOG_STORE.with(|store| ReviewLogStoreWrapper { store }.mark_sent(principal, week_start)) {
+        return 0;
+    }
+    let todos = TODO_STORE.with(|store| TodoStoreWrapper { store }.all_todos(principal));
+    let message = review::message(&review::build(todos, week_start, utc_offset), preferences.language());
+    let mut requests = Vec::new();
+    if let Some(channel) = channel {
+        requests.push(email::request_for(channel, &message, format!("{principal}-review-{week_start}")));
+    }
+    if let Some(link) = link {
+        requests.push(telegram::send_message_request(link, &format!("{}\n\n{}", message.subject, message.text)));
+    }
+    let outcalls = requests.len() as u32;
+    for mut request in requests {
+        request.transform = Some(http::status_only_transform());
+        ic_cdk::spawn(async move {
+            // Reviews are best effort; a failed delivery is not retried.
+            let _ = outcall::http_request(request, OUTCALL_CYCLES).await;
+        });
+    }
+    outcalls
+}
+
+/// Runs a slice of the delivery job, starting a new one once the previous one delivered to every principal,
+/// so that the principals left over from the previous round are delivered to first.
+fn run_delivery_slice() {
+    let running = JOB_STORE.with(|store| JobStoreWrapper { store }.running()).into_iter().find(|job| job.kind == JobKind::Deliver);
+    let job = running.map_or_else(|| JOB_STORE.with(|store| JobStoreWrapper { store }.start(JobKind::Deliver, clock::now())), Ok);
+    if let Ok(job) = job {
+        run_job_slice(job.id);
+    }
+}
+
+/// Moves the canister clock forward. Only controllers of the canister may call this endpoint,
+/// which exists in builds with the `testing` feature only.
+///
+/// # Arguments
+///
+/// * `nanos` - The amount of time, in nanoseconds.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the caller is not a controller.
+#[cfg(feature = "testing")]
+#[ic_cdk::update]
+fn advance_time(nanos: u64) -> Result<(), Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Control)?;
+    clock::advance(nanos);
+    Ok(())
+}
+
+/// Runs the periodic maintenance jobs right away instead of waiting for the timer. Only controllers
+/// of the canister may call this endpoint, which exists in builds with the `testing` feature only.
+///
+/// # Returns
+///
+/// A Result indicating success or an Error if the caller is not a controller.
+#[cfg(feature = "testing")]
+#[ic_cdk::update]
+fn run_maintenance_now() -> Result<(), Error> {
+    authorize(ic_cdk::caller(), Resource::Canister, Action::Control)?;
+    run_maintenance();
+    Ok(())
+}
+
+/// Creates the scheduled Todo items that are due and arms the timer for the next one.
+/// Under maintenance nothing is created; the periodic jobs pick the due items up once it ends.
+fn create_scheduled_todos() {
+    if maintenance_mode().enabled {
+        return;
+    }
+    let now = clock::now();
+    let due = SCHEDULE_STORE.with(|store| ScheduleStoreWrapper { store }.take_due(now));
+    for (principal, request) in due {
+        // The request was validated when it was scheduled.
+        let id = generate_next_id();
+        if let Ok(change) = TODO_STORE.with(|store| TodoStoreWrapper { store }.create_todo(principal, id, request, now)) {
+            record_change(change);
+        }
+    }
+    arm_schedule_timer();
+}
+
+/// Arms the timer creating the next scheduled Todo item.
+fn arm_schedule_timer() {
+    let next_at = SCHEDULE_STORE.with(|store| ScheduleStoreWrapper { store }.next_at());
+    scheduled::arm_timer(next_at, clock::now(), create_scheduled_todos);
+}
+
+/// Runs the periodic maintenance jobs, which are paused while the canister is under maintenance.
+fn run_maintenance() {
+    if maintenance_mode().enabled {
+        return;
+    }
+    create_scheduled_todos();
+    roll_over_unfinished_todos();
+    escalate_stale_todos();
+    notify_overdue_todos();
+    archive_completed_todos();
+    // A purge still running from the previous round simply carries on.
+    let _ = start_maintenance_job(JobKind::Purge);
+    prune_usage();
+    reset_my_days();
+    run_delivery_slice();
+}
+
+/// Schedules the periodic maintenance jobs and the creation of scheduled Todo items, which do not survive upgrades.
+fn start_timers() {
+    ic_cdk_timers::set_timer_interval(MAINTENANCE_INTERVAL, run_maintenance);
+    arm_schedule_timer();
+}
+
+/// Hands the administration of the canister to the governance canister named in the install arguments, if any.
+/// Upgrading without arguments keeps the current admin.
+///
+/// # Arguments
+///
+/// * `args` - The install arguments.
+fn configure_governance(args: Option<InitArgs>) {
+    if let Some(InitArgs { governance: Some(governance) }) = args {
+        GOVERNANCE_CONFIG.with(|config| config.borrow_mut().set(GovernanceConfig { governance: Some(governance) }).unwrap());
+    }
+}
+
+/// Initializes the canister.
+///
+/// # Arguments
+///
+/// * `args` - Optional arguments naming the governance canister that administers the canister.
+#[ic_cdk::init]
+fn init(args: Option<InitArgs>) {
+    configure_governance(args);
+    migration::record_schema_version();
+    reset_certifications();
+    start_timers();
+}
+
+/// Migrates stored data to the current layout after a canister upgrade.
+///
+/// # Arguments
+///
+/// * `args` - Optional arguments naming the governance canister that administers the canister from now on.
+#[ic_cdk::post_upgrade]
+fn post_upgrade(args: Option<InitArgs>) {
+    configure_governance(args);
+    migration::migrate_legacy_todos();
+    let reindex = migration::backfill_derived_data(migration::stored_schema_version(), clock::now());
+    migration::record_schema_version();
+    reset_certifications();
+    start_timers();
+    resume_jobs();
+    if reindex {
+        // A Reindex job that was already running covers every Todo item too.
+        let _ = start_maintenance_job(JobKind::Reindex);
+    }
+}
 
 ic_cdk::export_candid!();
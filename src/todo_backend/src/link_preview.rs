@@ -0,0 +1,353 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use candid::{CandidType, Deserialize, Nat};
+use ic_cdk::api::management_canister::http_request::{
+    self as outcall, CanisterHttpRequestArgument, HttpHeader, HttpMethod, TransformContext,
+};
+use serde::Serialize;
+use serde_json::json;
+
+/// Name of the query reducing a fetched page to its preview, so that all replicas agree on it.
+const TRANSFORM_METHOD: &str = "transform_link_preview";
+
+/// Maximum size of a fetched page, in bytes; larger pages cannot be previewed.
+const MAX_RESPONSE_BYTES: u64 = 500_000;
+
+/// Maximum number of characters kept of a page title.
+const MAX_TITLE_CHARS: usize = 200;
+
+/// Maximum number of characters kept of a page description.
+const MAX_DESCRIPTION_CHARS: usize = 500;
+
+/// How long a fetched preview is reused before the page is fetched again, in nanoseconds.
+pub(crate) const PREVIEW_TTL: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// How long a page that could not be fetched is not fetched again, in nanoseconds.
+pub(crate) const FAILURE_TTL: u64 = 60 * 60 * 1_000_000_000;
+
+/// Maximum number of URLs remembered as failing; beyond it, URLs whose failure expired are forgotten first.
+const MAX_FAILED_URLS: usize = 10_000;
+
+thread_local! {
+    /// URLs whose page could not be fetched recently, so that requests for them do not pay for another outcall.
+    /// Kept on the heap, so an upgrade forgets them.
+    pub(crate) static FAILED_FETCHES: RefCell<FailedFetches> = RefCell::new(FailedFetches::default());
+}
+
+/// Represents the title and description of the page a Todo item links to, as shown on a link card.
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct LinkPreview {
+    /// The URL the preview was fetched from.
+    pub(crate) url: String,
+    /// The title of the page, if it has one.
+    pub(crate) title: Option<String>,
+    /// The description of the page, if it has one.
+    pub(crate) description: Option<String>,
+    /// Time the page was fetched, in nanoseconds since the Unix epoch.
+    pub(crate) fetched_at: u64,
+}
+
+impl LinkPreview {
+    /// Returns whether the preview belongs to a URL and is recent enough to be reused.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    pub(crate) fn is_fresh(&self, url: &str, now: u64) -> bool {
+        self.url == url && now < self.fetched_at.saturating_add(PREVIEW_TTL)
+    }
+}
+
+/// Remembers the URLs whose page could not be fetched and when.
+#[derive(Default)]
+pub(crate) struct FailedFetches {
+    /// Time of the last failed fetch, in nanoseconds since the Unix epoch, by URL.
+    failed_at: BTreeMap<String, u64>,
+}
+
+impl FailedFetches {
+    /// Records that the page of a URL could not be fetched.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    pub(crate) fn record(&mut self, url: String, now: u64) {
+        if !self.failed_at.contains_key(&url) && self.failed_at.len() >= MAX_FAILED_URLS {
+            self.failed_at
+                .retain(|_, failed_at| now.saturating_sub(*failed_at) < FAILURE_TTL);
+            if self.failed_at.len() >= MAX_FAILED_URLS {
+                self.failed_at.pop_first();
+            }
+        }
+        self.failed_at.insert(url, now);
+    }
+
+    /// Returns whether the page of a URL could not be fetched less than `FAILURE_TTL` ago.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    pub(crate) fn is_failing(&self, url: &str, now: u64) -> bool {
+        self.failed_at
+            .get(url)
+            .is_some_and(|failed_at| now < failed_at.saturating_add(FAILURE_TTL))
+    }
+}
+
+/// Builds the HTTPS outcall fetching a page, with the transform reducing it to its preview.
+/// Only available inside a canister, since the transform refers to the canister id.
+///
+/// # Arguments
+///
+/// * `url` - The validated URL of the page.
+///
+/// # Returns
+///
+/// The HTTP request argument for the management canister.
+pub(crate) fn fetch_request(url: &str) -> CanisterHttpRequestArgument {
+    CanisterHttpRequestArgument {
+        url: url.to_string(),
+        max_response_bytes: Some(MAX_RESPONSE_BYTES),
+        method: HttpMethod::GET,
+        headers: vec![HttpHeader {
+            name: "Accept".to_string(),
+            value: "text/html".to_string(),
+        }],
+        body: None,
+        transform: Some(TransformContext::from_name(
+            TRANSFORM_METHOD.to_string(),
+            Vec::new(),
+        )),
+    }
+}
+
+/// Reduces a fetched page to its status and a JSON body holding its title and description.
+/// Headers such as dates and cookies differ between replicas, so they are dropped.
+///
+/// # Arguments
+///
+/// * `response` - The raw response.
+pub(crate) fn transform(response: outcall::HttpResponse) -> outcall::HttpResponse {
+    let body = if is_success(&response.status) {
+        let (title, description) = parse(&String::from_utf8_lossy(&response.body));
+        json!({ "title": title, "description": description })
+            .to_string()
+            .into_bytes()
+    } else {
+        Vec::new()
+    };
+    outcall::HttpResponse {
+        status: response.status,
+        headers: Vec::new(),
+        body,
+    }
+}
+
+/// Reads the preview out of a transformed response.
+///
+/// # Arguments
+///
+/// * `url` - The URL the page was fetched from.
+/// * `response` - The transformed response.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+///
+/// # Returns
+///
+/// An Option containing the preview, or None if the page could not be fetched.
+pub(crate) fn from_response(
+    url: String,
+    response: &outcall::HttpResponse,
+    now: u64,
+) -> Option<LinkPreview> {
+    if !is_success(&response.status) {
+        return None;
+    }
+    let summary: serde_json::Value = serde_json::from_slice(&response.body).ok()?;
+    let field = |name: &str| summary.get(name)?.as_str().map(str::to_string);
+    Some(LinkPreview {
+        url,
+        title: field("title"),
+        description: field("description"),
+        fetched_at: now,
+    })
+}
+
+/// Returns whether an HTTP status is in the 2xx range.
+fn is_success(status: &Nat) -> bool {
+    *status >= 200_u16 && *status < 300_u16
+}
+
+/// Extracts the title and description of an HTML page, preferring Open Graph tags.
+///
+/// # Arguments
+///
+/// * `html` - The HTML of the page.
+///
+/// # Returns
+///
+/// The title and description, each None if the page does not have one.
+fn parse(html: &str) -> (Option<String>, Option<String>) {
+    let lower = html.to_ascii_lowercase();
+    let head = &html[..lower.find("</head").unwrap_or(html.len())];
+    let mut og_title = None;
+    let mut og_description = None;
+    let mut description = None;
+    for tag in head.split('<').skip(1) {
+        let tag = tag.split('>').next().unwrap_or_default();
+        let is_meta = tag
+            .get(..4)
+            .is_some_and(|name| name.eq_ignore_ascii_case("meta"))
+            && tag[4..].starts_with(char::is_whitespace);
+        if !is_meta {
+            continue;
+        }
+        let key = attribute(tag, "property").or_else(|| attribute(tag, "name"));
+        let slot = match key.map(|key| key.to_ascii_lowercase()).as_deref() {
+            Some("og:title") => &mut og_title,
+            Some("og:description") => &mut og_description,
+            Some("description") => &mut description,
+            _ => continue,
+        };
+        if slot.is_none() {
+            *slot = attribute(tag, "content");
+        }
+    }
+    let title = lower
+        .find("<title")
+        .and_then(|start| Some(start + lower[start..].find('>')? + 1))
+        .and_then(|start| Some(&html[start..start + lower[start..].find("</title")?]));
+    (
+        clean(og_title.as_deref().or(title), MAX_TITLE_CHARS),
+        clean(
+            og_description.or(description).as_deref(),
+            MAX_DESCRIPTION_CHARS,
+        ),
+    )
+}
+
+/// Reads the value of an attribute of an HTML tag, matching its name case-insensitively.
+///
+/// # Arguments
+///
+/// * `tag` - The text of the tag between `<` and `>`.
+/// * `name` - The name of the attribute.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let mut rest = tag;
+    while let Some(equals) = rest.find('=') {
+        let key = rest[..equals].split_whitespace().last().unwrap_or_default();
+        let after = rest[equals + 1..].trim_start();
+        let (value, remainder) = match after.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let end = after[1..].find(quote)? + 1;
+                (&after[1..end], &after[end + 1..])
+            }
+            _ => {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        if key.eq_ignore_ascii_case(name) {
+            return Some(value.to_string());
+        }
+        rest = remainder;
+    }
+    None
+}
+
+/// Decodes common HTML entities, collapses whitespace and truncates a text.
+///
+/// # Arguments
+///
+/// * `text` - The raw text, or None.
+/// * `max_chars` - The maximum number of characters to keep.
+///
+/// # Returns
+///
+/// The cleaned text, or None if it is missing or blank.
+fn clean(text: Option<&str>, max_chars: usize) -> Option<String> {
+    let decoded = text?
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+    let collapsed = decoded.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!collapsed.is_empty()).then(|| collapsed.chars().take(max_chars).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let html = r#"<!DOCTYPE html><html><HEAD>
+            <Title>
+              Fish &amp; Chips
+            </title>
+            <meta name="description" content='Where to eat'>
+            <META
+              property=og:site_name content=Chippy>
+            <meta property="og:description" content="The best &quot;chippy&quot; in town" />
+            </head><body><meta property="og:title" content="Ignored"></body></html>"#;
+        assert_eq!(
+            parse(html),
+            (
+                Some("Fish & Chips".to_string()),
+                Some("The best \"chippy\" in town".to_string())
+            )
+        );
+        assert_eq!(parse("<p>No head</p>"), (None, None));
+    }
+
+    #[test]
+    fn test_transform_and_read_preview() {
+        let response = transform(outcall::HttpResponse {
+            status: Nat::from(200_u16),
+            headers: vec![HttpHeader {
+                name: "Date".to_string(),
+                value: "today".to_string(),
+            }],
+            body: br#"<head><meta property="og:title" content="Docs"></head>"#.to_vec(),
+        });
+        assert!(response.headers.is_empty());
+        let preview = from_response("https://example.com".to_string(), &response, 5).unwrap();
+        assert_eq!(preview.title, Some("Docs".to_string()));
+        assert_eq!(preview.description, None);
+        assert!(preview.is_fresh("https://example.com", 5 + PREVIEW_TTL - 1));
+        assert!(!preview.is_fresh("https://example.com", 5 + PREVIEW_TTL));
+        assert!(!preview.is_fresh("https://example.org", 5));
+
+        let missing = transform(outcall::HttpResponse {
+            status: Nat::from(404_u16),
+            headers: Vec::new(),
+            body: b"<title>Not found</title>".to_vec(),
+        });
+        assert!(missing.body.is_empty());
+        assert_eq!(
+            from_response("https://example.com".to_string(), &missing, 5),
+            None
+        );
+    }
+
+    #[test]
+    fn test_failed_fetches_expire() {
+        let mut failed = FailedFetches::default();
+        failed.record("https://example.com".to_string(), 5);
+
+        assert!(failed.is_failing("https://example.com", 5 + FAILURE_TTL - 1));
+        assert!(!failed.is_failing("https://example.com", 5 + FAILURE_TTL));
+        assert!(!failed.is_failing("https://example.org", 5));
+
+        for i in 0..MAX_FAILED_URLS {
+            failed.record(format!("https://example.com/{i}"), 5 + FAILURE_TTL);
+        }
+        assert_eq!(failed.failed_at.len(), MAX_FAILED_URLS);
+        assert!(!failed.is_failing("https://example.com", 5));
+    }
+}
@@ -0,0 +1,182 @@
+use std::cell::RefCell;
+
+use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::{Memory, StableBTreeMap};
+
+use crate::{
+    errors::Error,
+    todo::{Todo, TodoId},
+};
+
+/// Name of the query peer canisters call to look up a linked Todo item.
+pub(crate) const REFERENCE_METHOD: &str = "get_todo_reference";
+
+/// Maximum number of peer canisters that may look up Todo items.
+pub(crate) const MAX_PEERS: usize = 20;
+
+/// Maximum number of linked Todo items resolved in one query; the rest are reported as unavailable.
+pub(crate) const MAX_RESOLVED_LINKS: usize = 50;
+
+/// Type alias for the PeerStore, which is a StableBTreeMap keyed by the principal of a canister trusted to look up Todo items.
+pub(crate) type PeerStore<M> = StableBTreeMap<Principal, (), M>;
+
+/// Represents what a peer canister learns about a linked Todo item.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct TodoReference {
+    /// Text description of the Todo item.
+    pub(crate) description: String,
+    /// Completion status of the Todo item.
+    pub(crate) is_completed: bool,
+    /// Optional due date, in nanoseconds since the Unix epoch.
+    pub(crate) due_date: Option<u64>,
+}
+
+impl From<Todo> for TodoReference {
+    fn from(todo: Todo) -> Self {
+        Self {
+            description: todo.description,
+            is_completed: todo.is_completed,
+            due_date: todo.due_date,
+        }
+    }
+}
+
+/// Represents the outcome of looking up a linked Todo item.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) enum LinkStatus {
+    /// The linked Todo item was found.
+    Resolved(TodoReference),
+    /// The linked Todo item could not be looked up, for the given reason.
+    Unavailable(String),
+}
+
+/// Represents a Todo item together with the item it links to in another canister.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ResolvedLink {
+    /// The unique identifier for the linking Todo item.
+    pub(crate) todo_id: TodoId,
+    /// The canister holding the linked Todo item.
+    pub(crate) canister: Principal,
+    /// The identifier of the linked Todo item within that canister.
+    pub(crate) linked_id: TodoId,
+    /// The outcome of the lookup.
+    pub(crate) status: LinkStatus,
+}
+
+/// Wrapper around the PeerStore to provide additional functionality.
+pub(crate) struct PeerStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<PeerStore<M>>,
+}
+
+impl<M: Memory> PeerStoreWrapper<'_, M> {
+    /// Replaces the peer canisters trusted to look up Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `peers` - The principals of the peer canisters.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if too many peers are given.
+    pub(crate) fn replace(&self, mut peers: Vec<Principal>) -> Result<(), Error> {
+        peers.sort();
+        peers.dedup();
+        if peers.len() > MAX_PEERS {
+            return Err(Error::QuotaExceeded(Some(format!(
+                "At most {MAX_PEERS} peer canisters can be trusted"
+            ))));
+        }
+        if peers.contains(&Principal::anonymous()) {
+            return Err(Error::InvalidInput(
+                "The anonymous principal cannot be a peer".to_string(),
+            ));
+        }
+        let mut store = self.store.borrow_mut();
+        let existing: Vec<Principal> = store.iter().map(|(peer, _)| peer).collect();
+        for peer in existing {
+            store.remove(&peer);
+        }
+        for peer in peers {
+            store.insert(peer, ());
+        }
+        Ok(())
+    }
+
+    /// Lists the peer canisters trusted to look up Todo items.
+    pub(crate) fn list(&self) -> Vec<Principal> {
+        self.store.borrow().iter().map(|(peer, _)| peer).collect()
+    }
+
+    /// Returns whether a canister is trusted to look up Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal of the caller.
+    pub(crate) fn contains(&self, principal: &Principal) -> bool {
+        self.store.borrow().contains_key(principal)
+    }
+}
+
+/// Turns the result of a call looking up a linked Todo item into its outcome.
+///
+/// # Arguments
+///
+/// * `result` - The result of the call to the canister holding the linked Todo item.
+///
+/// # Returns
+///
+/// The outcome of the lookup.
+pub(crate) fn link_status<E: std::fmt::Debug>(
+    result: Result<(Result<TodoReference, Error>,), (E, String)>,
+) -> LinkStatus {
+    match result {
+        Ok((Ok(reference),)) => LinkStatus::Resolved(reference),
+        Ok((Err(error),)) => LinkStatus::Unavailable(error.to_string()),
+        Err((code, message)) => LinkStatus::Unavailable(format!("{code:?}: {message}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_replace_peers() {
+        let store = RefCell::new(PeerStore::new(DefaultMemoryImpl::default()));
+        let wrapper = PeerStoreWrapper { store: &store };
+        let peer = Principal::management_canister();
+
+        assert!(wrapper.replace(vec![peer, peer]).is_ok());
+        assert_eq!(wrapper.list(), vec![peer]);
+        assert!(wrapper.contains(&peer));
+        assert!(matches!(
+            wrapper.replace(vec![Principal::anonymous()]),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(wrapper.replace(Vec::new()).is_ok());
+        assert!(!wrapper.contains(&peer));
+    }
+
+    #[test]
+    fn test_link_status() {
+        let reference = TodoReference {
+            description: "Ship".to_string(),
+            is_completed: false,
+            due_date: None,
+        };
+        assert_eq!(
+            link_status::<u8>(Ok((Ok(reference.clone()),))),
+            LinkStatus::Resolved(reference)
+        );
+        assert_eq!(
+            link_status::<u8>(Ok((Err(Error::NotFound),))),
+            LinkStatus::Unavailable("Item not found".to_string())
+        );
+        assert_eq!(
+            link_status(Err((5u8, "stopped".to_string()))),
+            LinkStatus::Unavailable("5: stopped".to_string())
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use std::borrow::Cow;
+
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_stable_structures::{storable::Bound, Storable};
+
+use crate::errors::Error;
+
+/// Maximum length of the message shown while the canister is under maintenance, in characters.
+const MAX_MESSAGE_CHARS: usize = 500;
+
+/// Represents whether the canister rejects changes while it is upgraded or migrated.
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct MaintenanceMode {
+    /// Whether update calls are rejected.
+    pub(crate) enabled: bool,
+    /// Optional message explaining the maintenance to callers, such as when it ends.
+    pub(crate) message: Option<String>,
+}
+
+impl Storable for MaintenanceMode {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl MaintenanceMode {
+    /// Creates a maintenance mode, dropping a blank message.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether update calls are rejected.
+    /// * `message` - Optional message explaining the maintenance to callers.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the maintenance mode, or an Error if the message is too long.
+    pub(crate) fn new(enabled: bool, message: Option<String>) -> Result<Self, Error> {
+        let message = message
+            .map(|message| message.trim().to_string())
+            .filter(|message| !message.is_empty());
+        if message
+            .as_ref()
+            .is_some_and(|message| message.chars().count() > MAX_MESSAGE_CHARS)
+        {
+            return Err(Error::InvalidInput(format!(
+                "Maintenance message must not exceed {MAX_MESSAGE_CHARS} characters"
+            )));
+        }
+        Ok(Self { enabled, message })
+    }
+
+    /// Checks that changes are accepted.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or a Maintenance Error carrying the message.
+    pub(crate) fn ensure_open(&self) -> Result<(), Error> {
+        if self.enabled {
+            Err(Error::Maintenance(self.message.clone()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ensure_open() {
+        assert!(MaintenanceMode::default().ensure_open().is_ok());
+        let mode = MaintenanceMode::new(true, Some("  Back at 10:00 ".to_string())).unwrap();
+        assert_eq!(mode.message.as_deref(), Some("Back at 10:00"));
+        assert!(matches!(
+            mode.ensure_open(),
+            Err(Error::Maintenance(Some(message))) if message == "Back at 10:00"
+        ));
+        assert_eq!(
+            MaintenanceMode::new(false, Some(" ".to_string())).unwrap(),
+            MaintenanceMode::default()
+        );
+        assert!(MaintenanceMode::new(true, Some("x".repeat(501))).is_err());
+    }
+}
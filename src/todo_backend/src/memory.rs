@@ -2,19 +2,183 @@ use std::cell::RefCell;
 
 use ic_stable_structures::{
     memory_manager::{MemoryId, MemoryManager, VirtualMemory},
-    DefaultMemoryImpl, StableBTreeMap, StableCell,
+    DefaultMemoryImpl, Memory as _, StableBTreeMap, StableCell,
 };
 
-use crate::{store::TodoStore, todo::TodoId};
+use crate::{
+    access::{AccessStore, SharedAccountIndex}, api_tokens::ApiTokenStore, archive::ArchiveStore, attachments::{AttachmentStore, ChunkStore}, audit::AuditStore, board::BoardStore, budget::InstructionBudget, checklists::ChecklistStore, downloads::DownloadTokenStore, email::{EmailChannelStore, EmailLogStore}, events::EventStore, external_id::ExternalIdStore, features::FeatureFlagStore, geo::GeoIndex, goals::GoalStore, habits::HabitStore, governance::GovernanceConfig, history::HistoryStore, hooks::{IntegrationId, IntegrationStore}, jobs::JobStore, indexes::{CompletionIndex, DueIndex, ModifiedIndex, RankIndex}, labels::LabelStore, links::PeerStore, maintenance::MaintenanceMode, my_day::MyDayStore, namespaces::NamespaceStore, notifications::NotificationStore, pomodoro::PomodoroStore, imports::{ImportStore, StagedTodoStore}, preferences::PreferenceStore, priority_history::PriorityHistoryStore, relations::RelationStore, review::ReviewLogStore, scheduled::ScheduleStore, score::ScoreStore, stats::StatsStore, store::TodoStore, tag_trends::TagTrendStore, tags::TagStore, telegram::{TelegramChatIndex, TelegramStore}, todo::TodoId, todo_counts::TodoCountStore, usage::UsageStore, users::UserStore,
+};
 
 /// Type alias for the virtual memory used in the stable structures.
-type Memory = VirtualMemory<DefaultMemoryImpl>;
+pub(crate) type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+/// Memory ID of the legacy `u32` last Todo ID, only read during migration.
+const LEGACY_LAST_TODO_ID_MEMORY_ID: MemoryId = MemoryId::new(0);
+
+/// Memory ID of the legacy Todo items keyed by `u32` IDs, only read during migration.
+const LEGACY_TODO_STORE_MEMORY_ID: MemoryId = MemoryId::new(1);
 
 /// Memory ID for storing the last Todo ID.
-const LAST_TODO_ID_MEMORY_ID: MemoryId = MemoryId::new(0);
+const LAST_TODO_ID_MEMORY_ID: MemoryId = MemoryId::new(2);
 
 /// Memory ID for storing the Todo items.
-const TODO_STORE_MEMORY_ID: MemoryId = MemoryId::new(1);
+const TODO_STORE_MEMORY_ID: MemoryId = MemoryId::new(3);
+
+/// Memory ID for storing the mapping of external ids to Todo items.
+const EXTERNAL_ID_STORE_MEMORY_ID: MemoryId = MemoryId::new(4);
+
+/// Memory ID for storing the activity of each principal.
+const USER_STORE_MEMORY_ID: MemoryId = MemoryId::new(5);
+
+/// Memory ID for storing the daily activity counts of each principal.
+const STATS_STORE_MEMORY_ID: MemoryId = MemoryId::new(6);
+
+/// Memory ID for storing the audit log of each principal.
+const AUDIT_STORE_MEMORY_ID: MemoryId = MemoryId::new(7);
+
+/// Memory ID for storing the previous versions of Todo items.
+const HISTORY_STORE_MEMORY_ID: MemoryId = MemoryId::new(8);
+
+/// Memory ID for storing the pomodoro sessions of each principal.
+const POMODORO_STORE_MEMORY_ID: MemoryId = MemoryId::new(9);
+
+/// Memory ID for storing the points and streaks of each principal.
+const SCORE_STORE_MEMORY_ID: MemoryId = MemoryId::new(10);
+
+/// Memory ID for storing the preferences of each principal.
+const PREFERENCE_STORE_MEMORY_ID: MemoryId = MemoryId::new(11);
+
+/// Memory ID for storing the archived Todo items.
+const ARCHIVE_STORE_MEMORY_ID: MemoryId = MemoryId::new(12);
+
+/// Memory ID for storing the board columns of each principal.
+const BOARD_STORE_MEMORY_ID: MemoryId = MemoryId::new(13);
+
+/// Memory ID for storing the daily focus list of each principal.
+const MY_DAY_STORE_MEMORY_ID: MemoryId = MemoryId::new(14);
+
+/// Memory ID for storing the notifications of each principal.
+const NOTIFICATION_STORE_MEMORY_ID: MemoryId = MemoryId::new(15);
+
+/// Memory ID for storing the email channel of each principal.
+const EMAIL_CHANNEL_STORE_MEMORY_ID: MemoryId = MemoryId::new(16);
+
+/// Memory ID for storing the log of reminder emails sent to each principal.
+const EMAIL_LOG_STORE_MEMORY_ID: MemoryId = MemoryId::new(17);
+
+/// Memory ID for storing the Telegram chat linked to each principal.
+const TELEGRAM_STORE_MEMORY_ID: MemoryId = MemoryId::new(18);
+
+/// Memory ID for storing the metadata of attachments.
+const ATTACHMENT_STORE_MEMORY_ID: MemoryId = MemoryId::new(19);
+
+/// Memory ID for storing the chunks of attachments.
+const CHUNK_STORE_MEMORY_ID: MemoryId = MemoryId::new(20);
+
+/// Memory ID for storing the download tokens of principals.
+const DOWNLOAD_TOKEN_STORE_MEMORY_ID: MemoryId = MemoryId::new(21);
+
+/// Memory ID for storing the tag registry of each principal.
+const TAG_STORE_MEMORY_ID: MemoryId = MemoryId::new(22);
+
+/// Memory ID for storing the index of Todo items by modification time.
+const MODIFIED_INDEX_MEMORY_ID: MemoryId = MemoryId::new(23);
+
+/// Memory ID for storing the index of open Todo items by rank.
+const RANK_INDEX_MEMORY_ID: MemoryId = MemoryId::new(24);
+
+/// Memory ID for storing the index of Todo items by completion.
+const COMPLETION_INDEX_MEMORY_ID: MemoryId = MemoryId::new(25);
+
+/// Memory ID of the Todo items scheduled for later creation.
+const SCHEDULE_STORE_MEMORY_ID: MemoryId = MemoryId::new(26);
+
+/// Memory ID for storing the labels of each principal.
+const LABEL_STORE_MEMORY_ID: MemoryId = MemoryId::new(27);
+
+/// Memory ID for storing the hashed API tokens of each principal.
+const API_TOKEN_STORE_MEMORY_ID: MemoryId = MemoryId::new(28);
+
+/// Memory ID for storing the integrations of each principal with external services.
+const INTEGRATION_STORE_MEMORY_ID: MemoryId = MemoryId::new(29);
+
+/// Memory ID for storing the peer canisters trusted to look up linked Todo items.
+const PEER_STORE_MEMORY_ID: MemoryId = MemoryId::new(30);
+
+/// Memory ID for storing who administers the canister.
+const GOVERNANCE_CONFIG_MEMORY_ID: MemoryId = MemoryId::new(31);
+
+/// Memory ID for storing whether the canister is under maintenance.
+const MAINTENANCE_MODE_MEMORY_ID: MemoryId = MemoryId::new(32);
+
+/// Memory ID for storing the daily usage of each principal.
+const USAGE_STORE_MEMORY_ID: MemoryId = MemoryId::new(33);
+
+/// Memory ID for storing the feature flags.
+const FEATURE_FLAG_STORE_MEMORY_ID: MemoryId = MemoryId::new(34);
+
+/// Memory ID for storing the last week reviewed for each principal.
+const REVIEW_LOG_STORE_MEMORY_ID: MemoryId = MemoryId::new(35);
+
+/// Memory ID for storing the goals of each principal.
+const GOAL_STORE_MEMORY_ID: MemoryId = MemoryId::new(36);
+
+/// Memory ID for storing the days habits were done on.
+const HABIT_STORE_MEMORY_ID: MemoryId = MemoryId::new(37);
+
+/// Memory ID for the index of open Todo items by the geohash of their location.
+const GEO_INDEX_MEMORY_ID: MemoryId = MemoryId::new(38);
+
+/// Memory ID for storing the relations between Todo items.
+const RELATION_STORE_MEMORY_ID: MemoryId = MemoryId::new(39);
+
+/// Memory ID for storing the checklists of subtasks.
+const CHECKLIST_STORE_MEMORY_ID: MemoryId = MemoryId::new(40);
+
+/// Memory ID for storing the access granted to the accounts of principals.
+const ACCESS_STORE_MEMORY_ID: MemoryId = MemoryId::new(41);
+
+/// Memory ID for storing the event logs the Todo store is a projection of.
+const EVENT_STORE_MEMORY_ID: MemoryId = MemoryId::new(42);
+
+/// Memory ID for storing how many instructions a long-running call may execute.
+const INSTRUCTION_BUDGET_MEMORY_ID: MemoryId = MemoryId::new(43);
+
+/// Memory ID for storing the maintenance jobs and their progress.
+const JOB_STORE_MEMORY_ID: MemoryId = MemoryId::new(44);
+
+/// Memory ID for storing the namespaces of each principal.
+const NAMESPACE_STORE_MEMORY_ID: MemoryId = MemoryId::new(45);
+
+/// Memory ID for storing the priority changes of each Todo item.
+const PRIORITY_HISTORY_STORE_MEMORY_ID: MemoryId = MemoryId::new(46);
+
+/// Memory ID for storing the bulk imports of Todo items.
+const IMPORT_STORE_MEMORY_ID: MemoryId = MemoryId::new(47);
+
+/// Memory ID for storing the Todo items of bulk imports until they are committed.
+const STAGED_TODO_STORE_MEMORY_ID: MemoryId = MemoryId::new(48);
+
+/// Memory ID for storing the weekly counts of each tag.
+const TAG_TREND_STORE_MEMORY_ID: MemoryId = MemoryId::new(49);
+
+/// Memory ID for storing the index of open Todo items by due date.
+const DUE_INDEX_MEMORY_ID: MemoryId = MemoryId::new(50);
+
+/// Memory ID for storing the index of the accounts shared with each principal.
+const SHARED_ACCOUNT_INDEX_MEMORY_ID: MemoryId = MemoryId::new(51);
+
+/// Memory ID for storing the index of the principals linked to each Telegram chat.
+const TELEGRAM_CHAT_INDEX_MEMORY_ID: MemoryId = MemoryId::new(52);
+
+/// Memory ID for storing the schema version the stored data was last migrated to.
+const STORED_SCHEMA_VERSION_MEMORY_ID: MemoryId = MemoryId::new(53);
+
+/// Memory ID for storing the last integration ID.
+const LAST_INTEGRATION_ID_MEMORY_ID: MemoryId = MemoryId::new(54);
+
+/// Memory ID for storing the number of Todo items of each principal.
+const TODO_COUNT_STORE_MEMORY_ID: MemoryId = MemoryId::new(55);
 
 thread_local! {
     /// Global memory manager for stable structures.
@@ -34,4 +198,387 @@ thread_local! {
             GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(TODO_STORE_MEMORY_ID))
         )
     );
+
+    /// Stable BTreeMap for mapping external ids to Todo items.
+    pub(crate) static EXTERNAL_ID_STORE: RefCell<ExternalIdStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(EXTERNAL_ID_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the activity of each principal.
+    pub(crate) static USER_STORE: RefCell<UserStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(USER_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the daily activity counts of each principal.
+    pub(crate) static STATS_STORE: RefCell<StatsStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(STATS_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the audit log of each principal.
+    pub(crate) static AUDIT_STORE: RefCell<AuditStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(AUDIT_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the previous versions of Todo items.
+    pub(crate) static HISTORY_STORE: RefCell<HistoryStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(HISTORY_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the pomodoro sessions of each principal.
+    pub(crate) static POMODORO_STORE: RefCell<PomodoroStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(POMODORO_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the points and streaks of each principal.
+    pub(crate) static SCORE_STORE: RefCell<ScoreStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(SCORE_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the preferences of each principal.
+    pub(crate) static PREFERENCE_STORE: RefCell<PreferenceStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(PREFERENCE_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the archived Todo items.
+    pub(crate) static ARCHIVE_STORE: RefCell<ArchiveStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(ARCHIVE_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the board columns of each principal.
+    pub(crate) static BOARD_STORE: RefCell<BoardStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(BOARD_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the daily focus list of each principal.
+    pub(crate) static MY_DAY_STORE: RefCell<MyDayStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(MY_DAY_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the notifications of each principal.
+    pub(crate) static NOTIFICATION_STORE: RefCell<NotificationStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(NOTIFICATION_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the email channel of each principal.
+    pub(crate) static EMAIL_CHANNEL_STORE: RefCell<EmailChannelStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(EMAIL_CHANNEL_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the log of reminder emails sent to each principal.
+    pub(crate) static EMAIL_LOG_STORE: RefCell<EmailLogStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(EMAIL_LOG_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the Telegram chat linked to each principal.
+    pub(crate) static TELEGRAM_STORE: RefCell<TelegramStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(TELEGRAM_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the metadata of attachments.
+    pub(crate) static ATTACHMENT_STORE: RefCell<AttachmentStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(ATTACHMENT_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the chunks of attachments.
+    pub(crate) static CHUNK_STORE: RefCell<ChunkStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(CHUNK_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the download tokens of principals.
+    pub(crate) static DOWNLOAD_TOKEN_STORE: RefCell<DownloadTokenStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(DOWNLOAD_TOKEN_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the tag registry of each principal.
+    pub(crate) static TAG_STORE: RefCell<TagStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(TAG_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap indexing the Todo items of each principal by modification time.
+    pub(crate) static MODIFIED_INDEX: RefCell<ModifiedIndex<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(MODIFIED_INDEX_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap indexing the open Todo items of each principal by rank.
+    pub(crate) static RANK_INDEX: RefCell<RankIndex<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(RANK_INDEX_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap indexing the Todo items of each principal by completion.
+    pub(crate) static COMPLETION_INDEX: RefCell<CompletionIndex<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(COMPLETION_INDEX_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the Todo items scheduled for later creation.
+    pub(crate) static SCHEDULE_STORE: RefCell<ScheduleStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(SCHEDULE_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the labels of each principal.
+    pub(crate) static LABEL_STORE: RefCell<LabelStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(LABEL_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the hashed API tokens of each principal.
+    pub(crate) static API_TOKEN_STORE: RefCell<ApiTokenStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(API_TOKEN_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the integrations of each principal with external services.
+    pub(crate) static INTEGRATION_STORE: RefCell<IntegrationStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(INTEGRATION_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the peer canisters trusted to look up linked Todo items.
+    pub(crate) static PEER_STORE: RefCell<PeerStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(PEER_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable cell for storing who administers the canister.
+    pub(crate) static GOVERNANCE_CONFIG: RefCell<StableCell<GovernanceConfig, Memory>> = RefCell::new(
+        StableCell::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(GOVERNANCE_CONFIG_MEMORY_ID)), GovernanceConfig::default(),
+        ).unwrap()
+    );
+
+    /// Stable cell for storing whether the canister is under maintenance, which lasts across upgrades.
+    pub(crate) static MAINTENANCE_MODE: RefCell<StableCell<MaintenanceMode, Memory>> = RefCell::new(
+        StableCell::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(MAINTENANCE_MODE_MEMORY_ID)), MaintenanceMode::default(),
+        ).unwrap()
+    );
+
+    /// Stable BTreeMap for storing the daily usage of each principal.
+    pub(crate) static USAGE_STORE: RefCell<UsageStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(USAGE_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the feature flags.
+    pub(crate) static FEATURE_FLAG_STORE: RefCell<FeatureFlagStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(FEATURE_FLAG_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the last week reviewed for each principal.
+    pub(crate) static REVIEW_LOG_STORE: RefCell<ReviewLogStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(REVIEW_LOG_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the goals of each principal.
+    pub(crate) static GOAL_STORE: RefCell<GoalStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(GOAL_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the days habits were done on.
+    pub(crate) static HABIT_STORE: RefCell<HabitStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(HABIT_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap indexing open Todo items by the geohash of their location.
+    pub(crate) static GEO_INDEX: RefCell<GeoIndex<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(GEO_INDEX_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the relations between Todo items.
+    pub(crate) static RELATION_STORE: RefCell<RelationStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(RELATION_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the checklists of subtasks.
+    pub(crate) static CHECKLIST_STORE: RefCell<ChecklistStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(CHECKLIST_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the access granted to the accounts of principals.
+    pub(crate) static ACCESS_STORE: RefCell<AccessStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(ACCESS_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the event logs the Todo store is a projection of.
+    pub(crate) static EVENT_STORE: RefCell<EventStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(EVENT_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable cell for storing the instruction budget of long-running calls, such as bulk updates and exports.
+    pub(crate) static INSTRUCTION_BUDGET: RefCell<StableCell<InstructionBudget, Memory>> = RefCell::new(
+        StableCell::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(INSTRUCTION_BUDGET_MEMORY_ID)), InstructionBudget::default(),
+        ).unwrap()
+    );
+
+    /// Stable BTreeMap for storing the maintenance jobs and their progress, so that they resume after an upgrade.
+    pub(crate) static JOB_STORE: RefCell<JobStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(JOB_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the namespaces of each principal.
+    pub(crate) static NAMESPACE_STORE: RefCell<NamespaceStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(NAMESPACE_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the priority changes of each Todo item.
+    pub(crate) static PRIORITY_HISTORY_STORE: RefCell<PriorityHistoryStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(PRIORITY_HISTORY_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the bulk imports of Todo items.
+    pub(crate) static IMPORT_STORE: RefCell<ImportStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(IMPORT_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the Todo items of bulk imports until they are committed.
+    pub(crate) static STAGED_TODO_STORE: RefCell<StagedTodoStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(STAGED_TODO_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap for storing the weekly counts of each tag.
+    pub(crate) static TAG_TREND_STORE: RefCell<TagTrendStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(TAG_TREND_STORE_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap indexing the open Todo items of each principal by due date.
+    pub(crate) static DUE_INDEX: RefCell<DueIndex<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(DUE_INDEX_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap indexing the accounts shared with each principal.
+    pub(crate) static SHARED_ACCOUNT_INDEX: RefCell<SharedAccountIndex<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(SHARED_ACCOUNT_INDEX_MEMORY_ID))
+        )
+    );
+
+    /// Stable BTreeMap indexing the principals linked to each Telegram chat.
+    pub(crate) static TELEGRAM_CHAT_INDEX: RefCell<TelegramChatIndex<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(TELEGRAM_CHAT_INDEX_MEMORY_ID))
+        )
+    );
+
+    /// Stable cell for storing the schema version the stored data was last migrated to, or 0 if the stored data
+    /// predates recording it.
+    pub(crate) static STORED_SCHEMA_VERSION: RefCell<StableCell<u32, Memory>> = RefCell::new(
+        StableCell::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(STORED_SCHEMA_VERSION_MEMORY_ID)), 0,
+        ).unwrap()
+    );
+
+    /// Stable cell for storing the last integration ID, so that the ID of a deleted integration, and with it
+    /// the path events are posted to, is never given to another integration.
+    pub(crate) static LAST_INTEGRATION_ID: RefCell<StableCell<IntegrationId, Memory>> = RefCell::new(
+        StableCell::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(LAST_INTEGRATION_ID_MEMORY_ID)), 0,
+        ).unwrap()
+    );
+
+    /// Stable BTreeMap counting the Todo items of each principal, archived ones included, so that their quota
+    /// is checked without walking their Todo items.
+    pub(crate) static TODO_COUNT_STORE: RefCell<TodoCountStore<Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            GLOBAL_MEMORY_MANAGER.with(|manager| manager.borrow().get(TODO_COUNT_STORE_MEMORY_ID))
+        )
+    );
+}
+
+/// Returns the memories of the legacy last Todo ID and Todo store.
+///
+/// # Returns
+///
+/// The `(last_todo_id, todo_store)` memories, or None if the legacy store was never written to.
+pub(crate) fn legacy_memories() -> Option<(Memory, Memory)> {
+    GLOBAL_MEMORY_MANAGER.with(|manager| {
+        let manager = manager.borrow();
+        let todo_store = manager.get(LEGACY_TODO_STORE_MEMORY_ID);
+        if todo_store.size() == 0 {
+            return None;
+        }
+        Some((manager.get(LEGACY_LAST_TODO_ID_MEMORY_ID), todo_store))
+    })
 }
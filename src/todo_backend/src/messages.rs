@@ -0,0 +1,250 @@
+use candid::{CandidType, Deserialize};
+
+use crate::{
+    errors::Error,
+    stats::{civil_from_days, NANOS_PER_DAY, NANOS_PER_MINUTE},
+    todo::{Priority, TodoId},
+};
+
+/// Represents a language the user-facing texts of the canister are available in.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum Language {
+    #[default]
+    English,
+    German,
+}
+
+/// Represents a user-facing text of the canister, rendered in the language of its recipient.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Message<'a> {
+    /// The description of an error.
+    Error(&'a Error),
+    /// The subject line of a reminder email.
+    ReminderSubject { description: &'a str },
+    /// The body of a reminder email, without the notes of the Todo item.
+    ReminderText {
+        description: &'a str,
+        due: &'a str,
+        priority: Priority,
+    },
+    /// A chat announcement of a Todo item that became overdue.
+    Overdue { description: &'a str },
+    /// A chat announcement of a Todo item that is due soon.
+    DueSoon { description: &'a str, due: &'a str },
+    /// The chat reply confirming that a Todo item was added.
+    Added { id: TodoId },
+    /// The chat reply explaining the supported commands.
+    ChatUsage,
+    /// The subject line of a weekly review.
+    WeeklyReviewSubject { week: &'a str },
+    /// The summary opening a weekly review.
+    WeeklyReviewText {
+        completed: usize,
+        created: usize,
+        carried_over: usize,
+    },
+    /// A line of a weekly review on the progress of a project.
+    ProjectProgress {
+        project: &'a str,
+        completed: u32,
+        open: u32,
+    },
+}
+
+impl Message<'_> {
+    /// Renders the text in a language.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - The language of the recipient.
+    pub(crate) fn render(&self, language: Language) -> String {
+        match (language, *self) {
+            (Language::English, Message::Error(error)) => error.to_string(),
+            (Language::German, Message::Error(error)) => german_error(error),
+            (Language::English, Message::ReminderSubject { description }) => {
+                format!("Reminder: {description}")
+            }
+            (Language::German, Message::ReminderSubject { description }) => {
+                format!("Erinnerung: {description}")
+            }
+            (
+                Language::English,
+                Message::ReminderText {
+                    description,
+                    due,
+                    priority,
+                },
+            ) => format!("\"{description}\" is due {due}.\nPriority: {priority:?}\n"),
+            (
+                Language::German,
+                Message::ReminderText {
+                    description,
+                    due,
+                    priority,
+                },
+            ) => {
+                let priority = match priority {
+                    Priority::Low => "Niedrig",
+                    Priority::Medium => "Mittel",
+                    Priority::High => "Hoch",
+                };
+                format!("„{description}“ ist fällig {due}.\nPriorität: {priority}\n")
+            }
+            (Language::English, Message::Overdue { description }) => {
+                format!("Overdue: {description}")
+            }
+            (Language::German, Message::Overdue { description }) => {
+                format!("Überfällig: {description}")
+            }
+            (Language::English, Message::DueSoon { description, due }) => {
+                format!("Due {due}: {description}")
+            }
+            (Language::German, Message::DueSoon { description, due }) => {
+                format!("Fällig {due}: {description}")
+            }
+            (Language::English, Message::Added { id }) => format!("Added #{id}"),
+            (Language::German, Message::Added { id }) => format!("#{id} hinzugefügt"),
+            (Language::English, Message::ChatUsage) => {
+                "Send /add followed by a description to add a Todo item.".to_string()
+            }
+            (Language::German, Message::ChatUsage) => {
+                "Sende /add gefolgt von einer Beschreibung, um eine Aufgabe anzulegen.".to_string()
+            }
+            (Language::English, Message::WeeklyReviewSubject { week }) => {
+                format!("Weekly review: week of {week}")
+            }
+            (Language::German, Message::WeeklyReviewSubject { week }) => {
+                format!("Wochenrückblick: Woche ab {week}")
+            }
+            (
+                Language::English,
+                Message::WeeklyReviewText {
+                    completed,
+                    created,
+                    carried_over,
+                },
+            ) => format!("Completed: {completed}\nNew: {created}\nCarried over: {carried_over}\n\n"),
+            (
+                Language::German,
+                Message::WeeklyReviewText {
+                    completed,
+                    created,
+                    carried_over,
+                },
+            ) => format!("Erledigt: {completed}\nNeu: {created}\nÜbertragen: {carried_over}\n\n"),
+            (
+                Language::English,
+                Message::ProjectProgress {
+                    project,
+                    completed,
+                    open,
+                },
+            ) => format!("{project}: {completed} completed, {open} open"),
+            (
+                Language::German,
+                Message::ProjectProgress {
+                    project,
+                    completed,
+                    open,
+                },
+            ) => format!("{project}: {completed} erledigt, {open} offen"),
+        }
+    }
+}
+
+/// Describes an error in German. Details are free text supplied by the canister or the caller and stay as they are.
+///
+/// # Arguments
+///
+/// * `error` - The error.
+fn german_error(error: &Error) -> String {
+    let (text, detail) = match error {
+        Error::NotFound => ("Eintrag nicht gefunden", None),
+        Error::InvalidInput(input) => ("Ungültige Eingabe", Some(input)),
+        Error::Unauthorized(detail) => ("Nicht berechtigt", detail.as_ref()),
+        Error::QuotaExceeded(detail) => ("Kontingent überschritten", detail.as_ref()),
+        Error::AlreadyExists(detail) => ("Eintrag existiert bereits", detail.as_ref()),
+        Error::Conflict(detail) => ("Konflikt", detail.as_ref()),
+        Error::RateLimited(detail) => ("Zu viele Anfragen", detail.as_ref()),
+        Error::DependencyNotMet(detail) => ("Voraussetzung nicht erfüllt", detail.as_ref()),
+        Error::Maintenance(detail) => ("Wartungsarbeiten", detail.as_ref()),
+    };
+    match detail {
+        Some(detail) => format!("{text}: {detail}"),
+        None => text.to_string(),
+    }
+}
+
+/// Formats a timestamp as a local date and time for display to a principal.
+///
+/// # Arguments
+///
+/// * `timestamp` - The timestamp, in nanoseconds since the Unix epoch.
+/// * `utc_offset` - The offset of the time zone from UTC, in nanoseconds.
+/// * `language` - The language of the principal.
+///
+/// # Returns
+///
+/// The local time, e.g. `on 2024-02-29 at 23:30 (UTC-02:00)`.
+pub(crate) fn local_time(timestamp: u64, utc_offset: i64, language: Language) -> String {
+    let local = timestamp.saturating_add_signed(utc_offset);
+    let (year, month, day) = civil_from_days((local / NANOS_PER_DAY) as i64);
+    let minutes = local % NANOS_PER_DAY / NANOS_PER_MINUTE;
+    let (hour, minute) = (minutes / 60, minutes % 60);
+    let offset_minutes = utc_offset / NANOS_PER_MINUTE as i64;
+    let sign = if offset_minutes < 0 { '-' } else { '+' };
+    let offset_minutes = offset_minutes.unsigned_abs();
+    let zone = format!(
+        "UTC{sign}{:02}:{:02}",
+        offset_minutes / 60,
+        offset_minutes % 60
+    );
+    match language {
+        Language::English => {
+            format!("on {year:04}-{month:02}-{day:02} at {hour:02}:{minute:02} ({zone})")
+        }
+        Language::German => {
+            format!("am {day:02}.{month:02}.{year:04} um {hour:02}:{minute:02} ({zone})")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let error = Error::QuotaExceeded(Some("My Day is full".to_string()));
+        assert_eq!(
+            Message::Error(&error).render(Language::English),
+            "Quota exceeded: My Day is full"
+        );
+        assert_eq!(
+            Message::Error(&error).render(Language::German),
+            "Kontingent überschritten: My Day is full"
+        );
+        assert_eq!(
+            Message::Error(&Error::NotFound).render(Language::German),
+            "Eintrag nicht gefunden"
+        );
+        assert_eq!(
+            Message::Added { id: 7 }.render(Language::German),
+            "#7 hinzugefügt"
+        );
+    }
+
+    #[test]
+    fn test_local_time() {
+        let timestamp = 2 * NANOS_PER_DAY - 30 * NANOS_PER_MINUTE;
+        let utc_offset = 60 * NANOS_PER_MINUTE as i64;
+        assert_eq!(
+            local_time(timestamp, utc_offset, Language::English),
+            "on 1970-01-03 at 00:30 (UTC+01:00)"
+        );
+        assert_eq!(
+            local_time(timestamp, -utc_offset, Language::German),
+            "am 02.01.1970 um 22:30 (UTC-01:00)"
+        );
+    }
+}
@@ -0,0 +1,458 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, StableCell, Storable};
+
+use crate::{
+    access::{AccessStore, SharedAccountIndex, SharedAccountIndexWrapper},
+    archive::ArchiveStore,
+    events::{EventStore, EventStoreWrapper, TodoEventKind},
+    hooks::{IntegrationId, IntegrationStore},
+    memory::{legacy_memories, ACCESS_STORE, ARCHIVE_STORE, EVENT_STORE, INTEGRATION_STORE, LAST_INTEGRATION_ID, LAST_TODO_ID, SHARED_ACCOUNT_INDEX, STORED_SCHEMA_VERSION, TAG_STORE, TAG_TREND_STORE, TELEGRAM_CHAT_INDEX, TELEGRAM_STORE, TODO_COUNT_STORE, TODO_STORE},
+    store::TodoStore,
+    tag_trends::{TagTrendStore, TagTrendStoreWrapper},
+    tags::{TagStore, TagStoreWrapper},
+    telegram::{TelegramChatIndex, TelegramChatIndexWrapper, TelegramStore},
+    todo::{Priority, Todo, TodoId, TodoRecord},
+    todo_counts::{TodoCountStore, TodoCountStoreWrapper},
+};
+
+/// Version of the layout of the stored data, raised whenever an upgrade migrates or backfills stored data.
+pub(crate) const SCHEMA_VERSION: u32 = 7;
+
+/// Type alias for the Todo store used before Todo IDs were widened to `u64`.
+type LegacyTodoStore<M> = StableBTreeMap<(Principal, u32), LegacyTodo, M>;
+
+/// Represents a Todo item as it was stored with a `u32` identifier.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+struct LegacyTodo {
+    id: u32,
+    description: String,
+    is_completed: bool,
+    priority: Priority,
+    tags: Vec<String>,
+}
+
+impl Storable for LegacyTodo {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl From<LegacyTodo> for Todo {
+    fn from(legacy: LegacyTodo) -> Self {
+        let mut todo = Todo::new(TodoId::from(legacy.id), legacy.description, legacy.priority);
+        todo.is_completed = legacy.is_completed;
+        todo.tags = legacy.tags;
+        todo
+    }
+}
+
+/// Moves Todo items stored under `u32` IDs into the current store.
+///
+/// Safe to run on every upgrade: once the legacy store has been drained there is nothing left to move.
+pub(crate) fn migrate_legacy_todos() {
+    let Some((last_todo_id_memory, todo_store_memory)) = legacy_memories() else {
+        return;
+    };
+    let legacy_last_id: StableCell<u32, _> = StableCell::init(last_todo_id_memory, 0).unwrap();
+    let legacy_store = RefCell::new(LegacyTodoStore::init(todo_store_memory));
+
+    LAST_TODO_ID.with(|id| {
+        let mut id = id.borrow_mut();
+        let last_id = TodoId::max(*id.get(), TodoId::from(*legacy_last_id.get()));
+        id.set(last_id).unwrap();
+    });
+    TODO_STORE.with(|store| migrate_store(&legacy_store, store));
+}
+
+/// Copies every legacy Todo item into `store` and empties the legacy store.
+///
+/// # Arguments
+///
+/// * `legacy_store` - The store keyed by `u32` Todo IDs.
+/// * `store` - The store keyed by `u64` Todo IDs.
+fn migrate_store<M: Memory>(legacy_store: &RefCell<LegacyTodoStore<M>>, store: &RefCell<TodoStore<M>>) {
+    let mut legacy_store = legacy_store.borrow_mut();
+    let mut store = store.borrow_mut();
+    for ((principal, id), todo) in legacy_store.iter() {
+        let key = (principal, TodoId::from(id));
+        if !store.contains_key(&key) {
+            store.insert(key, Todo::from(todo).into());
+        }
+    }
+    legacy_store.clear_new();
+}
+
+/// Schema version from which every Todo item is in the modification, rank, due date and completion indexes.
+const TODO_INDEXES_VERSION: u32 = 3;
+
+/// Returns the schema version the stored data was last migrated to, or 0 if the stored data predates recording it.
+pub(crate) fn stored_schema_version() -> u32 {
+    STORED_SCHEMA_VERSION.with(|cell| *cell.borrow().get())
+}
+
+/// Records that the stored data matches the current layout, so that later upgrades skip the backfills.
+pub(crate) fn record_schema_version() {
+    STORED_SCHEMA_VERSION.with(|cell| cell.borrow_mut().set(SCHEMA_VERSION).unwrap());
+}
+
+/// Fills the data derived from other stores that was introduced after the schema version the stored data was
+/// last migrated to. Derived data is kept in sync by every change once filled, so each backfill runs once.
+///
+/// The tag registry, the weekly tag counts and the event log are counted rather than keyed, so they are only
+/// filled when empty: stored data that predates recording the schema version may already hold them.
+///
+/// # Arguments
+///
+/// * `stored_version` - The schema version the stored data was last migrated to.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+///
+/// # Returns
+///
+/// Whether the Todo indexes must be filled by a Reindex job, as indexing every Todo item may not fit in an upgrade.
+pub(crate) fn backfill_derived_data(stored_version: u32, now: u64) -> bool {
+    TODO_STORE.with(|todo_store| {
+        ARCHIVE_STORE.with(|archive_store| {
+            if stored_version < 1 {
+                TAG_STORE.with(|tag_store| backfill_tags(todo_store, archive_store, tag_store));
+                EVENT_STORE.with(|events| backfill_events(todo_store, events, now));
+            }
+            if stored_version < 2 {
+                TAG_TREND_STORE.with(|trend_store| backfill_trends(todo_store, archive_store, trend_store));
+            }
+            if stored_version < 7 {
+                TODO_COUNT_STORE.with(|count_store| backfill_todo_counts(todo_store, archive_store, count_store));
+            }
+        })
+    });
+    if stored_version < 4 {
+        ACCESS_STORE.with(|access_store| SHARED_ACCOUNT_INDEX.with(|index| backfill_shared_accounts(access_store, index)));
+    }
+    if stored_version < 5 {
+        TELEGRAM_STORE.with(|telegram_store| TELEGRAM_CHAT_INDEX.with(|index| backfill_telegram_chats(telegram_store, index)));
+    }
+    if stored_version < 6 {
+        INTEGRATION_STORE.with(|store| LAST_INTEGRATION_ID.with(|last_id| backfill_last_integration_id(store, last_id)));
+    }
+    stored_version < TODO_INDEXES_VERSION
+}
+
+/// Passes every entry of a store to `insert`, which adds it to the data derived from the store.
+///
+/// # Arguments
+///
+/// * `store` - The store the data is derived from.
+/// * `insert` - Adds an entry to the derived data.
+fn backfill<K, V, M>(store: &RefCell<StableBTreeMap<K, V, M>>, mut insert: impl FnMut(K, V))
+where
+    K: Storable + Ord + Clone,
+    V: Storable,
+    M: Memory,
+{
+    for (key, value) in store.borrow().iter() {
+        insert(key, value);
+    }
+}
+
+/// Registers the tags of every Todo item in `todo_store` and `archive_store`, unless `tag_store` already holds tags.
+///
+/// # Arguments
+///
+/// * `todo_store` - The store of Todo items.
+/// * `archive_store` - The store of archived Todo items.
+/// * `tag_store` - The tag registry.
+fn backfill_tags<M: Memory>(
+    todo_store: &RefCell<TodoStore<M>>,
+    archive_store: &RefCell<ArchiveStore<M>>,
+    tag_store: &RefCell<TagStore<M>>,
+) {
+    let wrapper = TagStoreWrapper { store: tag_store };
+    if !wrapper.is_empty() {
+        return;
+    }
+    let mut add = |(principal, _), record: TodoRecord| {
+        if let Some(todo) = record.into_todo() {
+            wrapper.add(principal, &todo);
+        }
+    };
+    backfill(todo_store, &mut add);
+    backfill(archive_store, &mut add);
+}
+
+/// Counts the creation and completion of every Todo item in `todo_store` and `archive_store` by tag and week,
+/// unless `trend_store` already holds counts.
+///
+/// # Arguments
+///
+/// * `todo_store` - The store of Todo items.
+/// * `archive_store` - The store of archived Todo items.
+/// * `trend_store` - The weekly tag counts.
+fn backfill_trends<M: Memory>(
+    todo_store: &RefCell<TodoStore<M>>,
+    archive_store: &RefCell<ArchiveStore<M>>,
+    trend_store: &RefCell<TagTrendStore<M>>,
+) {
+    let wrapper = TagTrendStoreWrapper { store: trend_store };
+    if !wrapper.is_empty() {
+        return;
+    }
+    let mut add = |(principal, _), record: TodoRecord| {
+        if let Some(todo) = record.into_todo() {
+            wrapper.add(principal, &todo);
+        }
+    };
+    backfill(todo_store, &mut add);
+    backfill(archive_store, &mut add);
+}
+
+/// Logs every Todo item in `todo_store` as put, unless `events` already holds entries.
+///
+/// # Arguments
+///
+/// * `todo_store` - The store of Todo items.
+/// * `events` - The event log.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+fn backfill_events<M: Memory>(todo_store: &RefCell<TodoStore<M>>, events: &RefCell<EventStore<M>>, now: u64) {
+    let wrapper = EventStoreWrapper { store: events };
+    if !wrapper.is_empty() {
+        return;
+    }
+    backfill(todo_store, |(principal, _), record| {
+        if let Some(todo) = record.into_todo() {
+            wrapper.append(principal, TodoEventKind::Put(Box::new(todo)), now);
+        }
+    });
+}
+
+/// Indexes every grant in `access_store` by grantee.
+///
+/// # Arguments
+///
+/// * `access_store` - The grants to the account of each principal.
+/// * `index` - The index of the accounts shared with each principal.
+fn backfill_shared_accounts<M: Memory>(access_store: &RefCell<AccessStore<M>>, index: &RefCell<SharedAccountIndex<M>>) {
+    let wrapper = SharedAccountIndexWrapper { store: index };
+    backfill(access_store, |(owner, grantee), _| wrapper.insert(owner, grantee));
+}
+
+/// Indexes every link in `telegram_store` by chat.
+///
+/// # Arguments
+///
+/// * `telegram_store` - The Telegram link of each principal.
+/// * `index` - The index of the principals linked to each chat.
+fn backfill_telegram_chats<M: Memory>(telegram_store: &RefCell<TelegramStore<M>>, index: &RefCell<TelegramChatIndex<M>>) {
+    let wrapper = TelegramChatIndexWrapper { store: index };
+    backfill(telegram_store, |principal, link| wrapper.insert(link.chat_id, principal));
+}
+
+/// Raises the last integration ID to the highest ID of the integrations in `integration_store`, which were
+/// numbered per principal before IDs were allocated from a counter.
+///
+/// # Arguments
+///
+/// * `integration_store` - The integrations of each principal.
+/// * `last_id` - The last integration ID.
+fn backfill_last_integration_id<M: Memory>(
+    integration_store: &RefCell<IntegrationStore<M>>,
+    last_id: &RefCell<StableCell<IntegrationId, M>>,
+) {
+    let mut highest = *last_id.borrow().get();
+    backfill(integration_store, |(_, id), _| highest = highest.max(id));
+    last_id.borrow_mut().set(highest).unwrap();
+}
+
+/// Counts the Todo items of every principal in `todo_store` and `archive_store`.
+///
+/// # Arguments
+///
+/// * `todo_store` - The store of Todo items.
+/// * `archive_store` - The store of archived Todo items.
+/// * `count_store` - The number of Todo items of each principal.
+fn backfill_todo_counts<M: Memory>(
+    todo_store: &RefCell<TodoStore<M>>,
+    archive_store: &RefCell<ArchiveStore<M>>,
+    count_store: &RefCell<TodoCountStore<M>>,
+) {
+    let wrapper = TodoCountStoreWrapper { store: count_store };
+    let mut add = |(principal, _), record: TodoRecord| {
+        if record.into_todo().is_some() {
+            wrapper.add(principal, 1);
+        }
+    };
+    backfill(todo_store, &mut add);
+    backfill(archive_store, &mut add);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::{
+        access::AccessStoreWrapper,
+        hooks::{IntegrationKind, IntegrationMapping, IntegrationStoreWrapper},
+        stats::NANOS_PER_DAY,
+        telegram::TelegramStoreWrapper,
+    };
+
+    fn legacy_todo(id: u32) -> LegacyTodo {
+        LegacyTodo {
+            id,
+            description: "Test Todo".to_string(),
+            is_completed: true,
+            priority: Priority::High,
+            tags: vec!["urgent".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_legacy_todo_into_todo() {
+        let todo: Todo = legacy_todo(7).into();
+        assert_eq!(todo.id, 7);
+        assert_eq!(todo.description, "Test Todo");
+        assert!(todo.is_completed);
+        assert_eq!(todo.priority, Priority::High);
+        assert_eq!(todo.tags, vec!["urgent"]);
+    }
+
+    #[test]
+    fn test_migrate_store() {
+        let legacy_store = RefCell::new(LegacyTodoStore::new(DefaultMemoryImpl::default()));
+        let store = RefCell::new(TodoStore::new(DefaultMemoryImpl::default()));
+        let principal = Principal::anonymous();
+        legacy_store.borrow_mut().insert((principal, 1), legacy_todo(1));
+        legacy_store.borrow_mut().insert((principal, u32::MAX), legacy_todo(u32::MAX));
+
+        migrate_store(&legacy_store, &store);
+
+        assert!(legacy_store.borrow().is_empty());
+        assert_eq!(store.borrow().len(), 2);
+        assert_eq!(store.borrow().get(&(principal, 1)).unwrap().into_todo().unwrap().id, 1);
+        assert_eq!(
+            store.borrow().get(&(principal, u32::MAX as TodoId)).unwrap().into_todo().unwrap().id,
+            u32::MAX as TodoId
+        );
+    }
+
+    #[test]
+    fn test_migrate_store_keeps_existing_items() {
+        let legacy_store = RefCell::new(LegacyTodoStore::new(DefaultMemoryImpl::default()));
+        let store = RefCell::new(TodoStore::new(DefaultMemoryImpl::default()));
+        let principal = Principal::anonymous();
+        legacy_store.borrow_mut().insert((principal, 1), legacy_todo(1));
+        let existing = Todo::new(1, "Existing".to_string(), Priority::Low);
+        store.borrow_mut().insert((principal, 1), existing.clone().into());
+
+        migrate_store(&legacy_store, &store);
+
+        assert_eq!(store.borrow().get(&(principal, 1)), Some(existing.into()));
+    }
+
+    #[test]
+    fn test_backfill_tags() {
+        let store = RefCell::new(TodoStore::new(DefaultMemoryImpl::default()));
+        let archive_store = RefCell::new(ArchiveStore::new(DefaultMemoryImpl::default()));
+        let tag_store = RefCell::new(TagStore::new(DefaultMemoryImpl::default()));
+        let principal = Principal::anonymous();
+        store.borrow_mut().insert((principal, 1), Todo::from(legacy_todo(1)).into());
+        archive_store.borrow_mut().insert((principal, 2), Todo::from(legacy_todo(2)).into());
+
+        backfill_tags(&store, &archive_store, &tag_store);
+        backfill_tags(&store, &archive_store, &tag_store);
+
+        let cloud = TagStoreWrapper { store: &tag_store }.cloud(principal);
+        assert_eq!(cloud.len(), 1);
+        assert_eq!((cloud[0].tag.as_str(), cloud[0].completed), ("urgent", 2));
+    }
+
+    #[test]
+    fn test_backfill_trends() {
+        let store = RefCell::new(TodoStore::new(DefaultMemoryImpl::default()));
+        let archive_store = RefCell::new(ArchiveStore::new(DefaultMemoryImpl::default()));
+        let trend_store = RefCell::new(TagTrendStore::new(DefaultMemoryImpl::default()));
+        let principal = Principal::anonymous();
+        let todo = |id| Todo { created_at: NANOS_PER_DAY, completed_at: Some(NANOS_PER_DAY), ..Todo::from(legacy_todo(id)) };
+        store.borrow_mut().insert((principal, 1), todo(1).into());
+        archive_store.borrow_mut().insert((principal, 2), todo(2).into());
+
+        backfill_trends(&store, &archive_store, &trend_store);
+        backfill_trends(&store, &archive_store, &trend_store);
+
+        let trends = TagTrendStoreWrapper { store: &trend_store }.trends(principal, "urgent", 0, 0);
+        assert_eq!((trends.weeks[0].created, trends.weeks[0].completed), (2, 2));
+    }
+
+    #[test]
+    fn test_reindexes_stored_data_older_than_todo_indexes() {
+        assert!(backfill_derived_data(0, 0));
+        assert!(backfill_derived_data(2, 0));
+        assert!(!backfill_derived_data(TODO_INDEXES_VERSION, 0));
+        assert!(!backfill_derived_data(SCHEMA_VERSION, 0));
+    }
+
+    #[test]
+    fn test_backfill_shared_accounts() {
+        let access_store = RefCell::new(AccessStore::new(DefaultMemoryImpl::default()));
+        let index = RefCell::new(SharedAccountIndex::new(DefaultMemoryImpl::default()));
+        let (owner, coach) = (Principal::from_slice(&[1]), Principal::from_slice(&[2]));
+        AccessStoreWrapper { store: &access_store }.grant(owner, coach, Vec::new(), 5).unwrap();
+
+        backfill_shared_accounts(&access_store, &index);
+
+        assert_eq!(SharedAccountIndexWrapper { store: &index }.owners(coach), vec![owner]);
+    }
+
+    #[test]
+    fn test_backfill_last_integration_id() {
+        let integration_store = RefCell::new(IntegrationStore::new(DefaultMemoryImpl::default()));
+        let last_id = RefCell::new(StableCell::new(DefaultMemoryImpl::default(), 0).unwrap());
+        let wrapper = IntegrationStoreWrapper { store: &integration_store };
+        for (principal, id) in [(Principal::from_slice(&[1]), 3), (Principal::from_slice(&[2]), 1)] {
+            wrapper.create(principal, id, IntegrationKind::Generic, IntegrationMapping::default(), &[0]).unwrap();
+        }
+
+        backfill_last_integration_id(&integration_store, &last_id);
+
+        assert_eq!(*last_id.borrow().get(), 3);
+    }
+
+    #[test]
+    fn test_backfill_todo_counts() {
+        let todo_store = RefCell::new(TodoStore::new(DefaultMemoryImpl::default()));
+        let archive_store = RefCell::new(ArchiveStore::new(DefaultMemoryImpl::default()));
+        let count_store = RefCell::new(TodoCountStore::new(DefaultMemoryImpl::default()));
+        let (first, second) = (Principal::from_slice(&[1]), Principal::from_slice(&[2]));
+        for id in 1..=2 {
+            todo_store.borrow_mut().insert((first, id), Todo::new(id, "Task".to_string(), Priority::Low).into());
+        }
+        todo_store.borrow_mut().insert((first, 3), TodoRecord::Corrupt(vec![0xff]));
+        archive_store.borrow_mut().insert((second, 4), Todo::new(4, "Archived".to_string(), Priority::Low).into());
+
+        backfill_todo_counts(&todo_store, &archive_store, &count_store);
+
+        let wrapper = TodoCountStoreWrapper { store: &count_store };
+        assert_eq!(wrapper.count(first), 2);
+        assert_eq!(wrapper.count(second), 1);
+    }
+
+    #[test]
+    fn test_backfill_telegram_chats() {
+        let telegram_store = RefCell::new(TelegramStore::new(DefaultMemoryImpl::default()));
+        let index = RefCell::new(TelegramChatIndex::new(DefaultMemoryImpl::default()));
+        let principal = Principal::from_slice(&[1]);
+        TelegramStoreWrapper { store: &telegram_store }.link(principal, -42, "123:abc".to_string(), 5);
+
+        backfill_telegram_chats(&telegram_store, &index);
+
+        assert_eq!(TelegramChatIndexWrapper { store: &index }.principals(-42), vec![principal]);
+    }
+}
@@ -0,0 +1,178 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{
+    renumbering::{renumbered, Renumbering},
+    stats::Day,
+    todo::TodoId,
+};
+
+/// Maximum number of Todo items in a daily focus list.
+const MAX_MY_DAY_TODOS: usize = 50;
+
+/// Type alias for the MyDayStore, which is a StableBTreeMap keyed by Principal with a value of MyDay.
+pub(crate) type MyDayStore<M> = StableBTreeMap<Principal, MyDay, M>;
+
+/// Represents the Todo items a principal picked to focus on during a single day.
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct MyDay {
+    /// The local day the selection was made on, counted in days since the Unix epoch.
+    pub(crate) day: Day,
+    /// The selected Todo items, in the order they were added.
+    pub(crate) todos: Vec<TodoId>,
+}
+
+impl Storable for MyDay {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Wrapper around the MyDayStore to provide additional functionality.
+///
+/// A selection only counts on the local day it was made on, so it resets at local midnight
+/// even before the maintenance job clears it.
+pub(crate) struct MyDayStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<MyDayStore<M>>,
+}
+
+impl<M: Memory> MyDayStoreWrapper<'_, M> {
+    /// Retrieves the Todo items a principal selected for today.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `today` - The current local day of the principal.
+    ///
+    /// # Returns
+    ///
+    /// The selected Todo items, in the order they were added.
+    pub(crate) fn get(&self, principal: Principal, today: Day) -> Vec<TodoId> {
+        self.store
+            .borrow()
+            .get(&principal)
+            .filter(|my_day| my_day.day == today)
+            .map(|my_day| my_day.todos)
+            .unwrap_or_default()
+    }
+
+    /// Adds a Todo item to the selection of today.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `today` - The current local day of the principal.
+    ///
+    /// # Returns
+    ///
+    /// Whether the Todo item is selected; the selection is full at 50 Todo items.
+    pub(crate) fn add(&self, principal: Principal, id: TodoId, today: Day) -> bool {
+        let mut todos = self.get(principal, today);
+        if !todos.contains(&id) {
+            if todos.len() >= MAX_MY_DAY_TODOS {
+                return false;
+            }
+            todos.push(id);
+        }
+        self.store
+            .borrow_mut()
+            .insert(principal, MyDay { day: today, todos });
+        true
+    }
+
+    /// Removes a Todo item from the selection of today.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `today` - The current local day of the principal.
+    pub(crate) fn remove(&self, principal: Principal, id: TodoId, today: Day) {
+        let mut todos = self.get(principal, today);
+        todos.retain(|todo| *todo != id);
+        self.store
+            .borrow_mut()
+            .insert(principal, MyDay { day: today, todos });
+    }
+
+    /// Removes every selection made before the current local day of its principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `today_of` - Returns the current local day of a principal.
+    pub(crate) fn clear_stale(&self, today_of: impl Fn(Principal) -> Day) {
+        let stale: Vec<Principal> = self
+            .store
+            .borrow()
+            .iter()
+            .filter(|(principal, my_day)| my_day.day != today_of(*principal))
+            .map(|(principal, _)| principal)
+            .collect();
+        let mut store = self.store.borrow_mut();
+        for principal in stale {
+            store.remove(&principal);
+        }
+    }
+
+    /// Points the focus list of a principal to the new identifiers of their Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        let Some(mut my_day) = self.store.borrow().get(&principal) else {
+            return;
+        };
+        for id in &mut my_day.todos {
+            *id = renumbered(ids, *id);
+        }
+        self.store.borrow_mut().insert(principal, my_day);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_selection_resets_on_the_next_day() {
+        let store = RefCell::new(MyDayStore::new(DefaultMemoryImpl::default()));
+        let wrapper = MyDayStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+
+        assert!(wrapper.add(principal, 1, 10));
+        assert!(wrapper.add(principal, 2, 10));
+        assert!(wrapper.add(principal, 1, 10));
+        wrapper.remove(principal, 2, 10);
+        assert_eq!(wrapper.get(principal, 10), vec![1]);
+        assert!(wrapper.get(principal, 11).is_empty());
+
+        wrapper.clear_stale(|_| 10);
+        assert!(store.borrow().contains_key(&principal));
+        wrapper.clear_stale(|_| 11);
+        assert!(!store.borrow().contains_key(&principal));
+    }
+
+    #[test]
+    fn test_selection_is_limited() {
+        let store = RefCell::new(MyDayStore::new(DefaultMemoryImpl::default()));
+        let wrapper = MyDayStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        for id in 0..MAX_MY_DAY_TODOS as TodoId {
+            assert!(wrapper.add(principal, id, 10));
+        }
+        assert!(!wrapper.add(principal, MAX_MY_DAY_TODOS as TodoId, 10));
+    }
+}
@@ -0,0 +1,175 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{bounded_string::BoundedString, errors::Error, validation::MAX_NAMESPACE_LENGTH};
+
+/// Maximum number of namespaces a principal may create.
+pub(crate) const MAX_NAMESPACES: usize = 20;
+
+/// Type alias for the name of a namespace as stored.
+pub(crate) type NamespaceName = BoundedString<{ 4 * MAX_NAMESPACE_LENGTH as u32 }>;
+
+/// Type alias for the NamespaceStore, which is a StableBTreeMap with a tuple key of (Principal, NamespaceName) and value of Namespace.
+pub(crate) type NamespaceStore<M> = StableBTreeMap<(Principal, NamespaceName), Namespace, M>;
+
+/// Represents a separate space of Todo items of a principal, such as "work" or "personal".
+/// Todo items outside of any namespace belong to the default space.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Namespace {
+    /// Name of the namespace, unique per principal.
+    pub(crate) name: String,
+    /// Time the namespace was created, in nanoseconds since the Unix epoch.
+    pub(crate) created_at: u64,
+}
+
+impl Storable for Namespace {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Wrapper around the NamespaceStore to provide additional functionality.
+pub(crate) struct NamespaceStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<NamespaceStore<M>>,
+}
+
+impl<M: Memory> NamespaceStoreWrapper<'_, M> {
+    /// Creates a namespace.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `name` - The validated name of the namespace.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if the namespace exists or too many namespaces exist.
+    pub(crate) fn create(&self, principal: Principal, name: String, now: u64) -> Result<(), Error> {
+        if self.exists(principal, &name) {
+            return Err(Error::AlreadyExists(Some(format!(
+                "Namespace '{name}' already exists"
+            ))));
+        }
+        if self.list(principal).len() >= MAX_NAMESPACES {
+            return Err(Error::QuotaExceeded(Some(format!(
+                "At most {MAX_NAMESPACES} namespaces can be created"
+            ))));
+        }
+        self.store.borrow_mut().insert(
+            (principal, NamespaceName::from(name.as_str())),
+            Namespace {
+                name,
+                created_at: now,
+            },
+        );
+        Ok(())
+    }
+
+    /// Returns whether a principal created a namespace.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `name` - The name of the namespace.
+    pub(crate) fn exists(&self, principal: Principal, name: &str) -> bool {
+        self.store
+            .borrow()
+            .contains_key(&(principal, NamespaceName::from(name)))
+    }
+
+    /// Lists the namespaces of a principal by name.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn list(&self, principal: Principal) -> Vec<Namespace> {
+        self.store
+            .borrow()
+            .range((principal, NamespaceName::from(""))..)
+            .take_while(|((owner, _), _)| *owner == principal)
+            .map(|(_, namespace)| namespace)
+            .collect()
+    }
+
+    /// Puts back the namespaces of a principal from an account bundle.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `namespaces` - The validated namespaces.
+    pub(crate) fn restore(&self, principal: Principal, namespaces: Vec<Namespace>) {
+        let mut store = self.store.borrow_mut();
+        for namespace in namespaces {
+            store.insert(
+                (principal, NamespaceName::from(namespace.name.as_str())),
+                namespace,
+            );
+        }
+    }
+
+    /// Deletes a namespace.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `name` - The name of the namespace.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if the namespace is not found.
+    pub(crate) fn delete(&self, principal: Principal, name: &str) -> Result<(), Error> {
+        self.store
+            .borrow_mut()
+            .remove(&(principal, NamespaceName::from(name)))
+            .map(|_| ())
+            .ok_or(Error::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_namespaces() {
+        let store = RefCell::new(NamespaceStore::new(DefaultMemoryImpl::default()));
+        let wrapper = NamespaceStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let other = Principal::management_canister();
+
+        assert!(wrapper.create(principal, "work".to_string(), 1).is_ok());
+        assert!(wrapper.create(principal, "personal".to_string(), 2).is_ok());
+        assert!(matches!(
+            wrapper.create(principal, "work".to_string(), 3),
+            Err(Error::AlreadyExists(_))
+        ));
+        assert!(wrapper.create(other, "work".to_string(), 3).is_ok());
+        assert_eq!(
+            wrapper
+                .list(principal)
+                .into_iter()
+                .map(|namespace| namespace.name)
+                .collect::<Vec<_>>(),
+            vec!["personal".to_string(), "work".to_string()]
+        );
+
+        assert!(wrapper.delete(principal, "work").is_ok());
+        assert!(!wrapper.exists(principal, "work"));
+        assert!(wrapper.exists(other, "work"));
+        assert!(matches!(
+            wrapper.delete(principal, "work"),
+            Err(Error::NotFound)
+        ));
+    }
+}
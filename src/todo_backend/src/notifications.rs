@@ -0,0 +1,278 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{
+    errors::Error,
+    paginator::Paginator,
+    renumbering::{renumbered, rewrite_entries, Renumbering},
+    todo::TodoId,
+};
+
+/// Maximum number of notifications kept for each principal.
+const MAX_NOTIFICATIONS: usize = 200;
+
+/// Type alias for the identifier of a notification, unique per principal.
+pub(crate) type NotificationId = u64;
+
+/// Type alias for the NotificationStore, which is a StableBTreeMap with a tuple key of (Principal, NotificationId) and value of Notification.
+pub(crate) type NotificationStore<M> = StableBTreeMap<(Principal, NotificationId), Notification, M>;
+
+/// Represents what a notification is about.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) enum NotificationKind {
+    /// A reminder about a Todo item fired.
+    Reminder,
+    /// A Todo item passed its due date without being completed.
+    Overdue,
+    /// A Todo item was shared with the principal.
+    Shared { owner: Principal },
+    /// A comment was added to a Todo item.
+    Comment { author: Principal },
+    /// A Todo item was assigned to the principal.
+    Assigned { by: Principal },
+}
+
+/// Represents an entry in the notification inbox of a principal.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Notification {
+    /// The unique identifier of the notification.
+    pub(crate) id: NotificationId,
+    /// What the notification is about.
+    pub(crate) kind: NotificationKind,
+    /// The unique identifier of the Todo item concerned.
+    pub(crate) todo_id: TodoId,
+    /// Time the notification was created, in nanoseconds since the Unix epoch.
+    pub(crate) created_at: u64,
+    /// Whether the principal has read the notification.
+    pub(crate) read: bool,
+}
+
+impl Storable for Notification {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Wrapper around the NotificationStore to provide additional functionality.
+pub(crate) struct NotificationStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<NotificationStore<M>>,
+}
+
+impl<M: Memory> NotificationStoreWrapper<'_, M> {
+    /// Adds a notification to the inbox of a principal, dropping the oldest ones beyond the limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `kind` - What the notification is about.
+    /// * `todo_id` - The unique identifier of the Todo item concerned.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// The identifier of the new notification.
+    pub(crate) fn notify(
+        &self,
+        principal: Principal,
+        kind: NotificationKind,
+        todo_id: TodoId,
+        now: u64,
+    ) -> NotificationId {
+        let id = self
+            .store
+            .borrow()
+            .iter_upper_bound(&(principal, NotificationId::MAX))
+            .next()
+            .filter(|((owner, _), _)| *owner == principal)
+            .map_or(1, |((_, id), _)| id + 1);
+        let notification = Notification {
+            id,
+            kind,
+            todo_id,
+            created_at: now,
+            read: false,
+        };
+        let mut store = self.store.borrow_mut();
+        store.insert((principal, id), notification);
+        let expired = id.saturating_sub(MAX_NOTIFICATIONS as NotificationId);
+        let expired: Vec<_> = store
+            .range((principal, 0)..=(principal, expired))
+            .map(|(key, _)| key)
+            .collect();
+        for key in expired {
+            store.remove(&key);
+        }
+        id
+    }
+
+    /// Lists the notifications of a principal, newest first.
+    /// Inboxes are bounded, so the whole inbox is read before paging.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `paginator` - The paginator for controlling the list output.
+    ///
+    /// # Returns
+    ///
+    /// A vector of notifications.
+    pub(crate) fn list(&self, principal: Principal, paginator: Paginator) -> Vec<Notification> {
+        let mut notifications: Vec<Notification> = self
+            .store
+            .borrow()
+            .range((principal, 0)..=(principal, NotificationId::MAX))
+            .map(|(_, notification)| notification)
+            .collect();
+        notifications.reverse();
+        notifications
+            .into_iter()
+            .skip(paginator.skip())
+            .take(paginator.limit())
+            .collect()
+    }
+
+    /// Marks a notification as read.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier of the notification.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success, or an Error if the notification is not found.
+    pub(crate) fn mark_read(&self, principal: Principal, id: NotificationId) -> Result<(), Error> {
+        let mut store = self.store.borrow_mut();
+        let notification = store.get(&(principal, id)).ok_or(Error::NotFound)?;
+        if !notification.read {
+            store.insert(
+                (principal, id),
+                Notification {
+                    read: true,
+                    ..notification
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Counts the unread notifications of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    ///
+    /// # Returns
+    ///
+    /// The number of unread notifications.
+    pub(crate) fn unread_count(&self, principal: Principal) -> u64 {
+        self.store
+            .borrow()
+            .range((principal, 0)..=(principal, NotificationId::MAX))
+            .filter(|(_, notification)| !notification.read)
+            .count() as u64
+    }
+
+    /// Returns whether a principal was notified of a kind of event about a Todo item since a point in time.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `kind` - What the notification is about.
+    /// * `todo_id` - The unique identifier of the Todo item concerned.
+    /// * `since` - The point in time, in nanoseconds since the Unix epoch.
+    pub(crate) fn notified_since(
+        &self,
+        principal: Principal,
+        kind: &NotificationKind,
+        todo_id: TodoId,
+        since: u64,
+    ) -> bool {
+        self.store
+            .borrow()
+            .range((principal, 0)..=(principal, NotificationId::MAX))
+            .any(|(_, notification)| {
+                notification.kind == *kind
+                    && notification.todo_id == todo_id
+                    && notification.created_at >= since
+            })
+    }
+
+    /// Points the notifications of a principal to the new identifiers of their Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        rewrite_entries(
+            self.store,
+            (principal, NotificationId::MIN)..=(principal, NotificationId::MAX),
+            |key, mut notification| {
+                notification.todo_id = renumbered(ids, notification.todo_id);
+                (key, notification)
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_notify_list_and_mark_read() {
+        let store = RefCell::new(NotificationStore::new(DefaultMemoryImpl::default()));
+        let wrapper = NotificationStoreWrapper { store: &store };
+        let (principal, other) = (Principal::anonymous(), Principal::management_canister());
+
+        assert_eq!(
+            wrapper.notify(principal, NotificationKind::Reminder, 1, 10),
+            1
+        );
+        let shared = NotificationKind::Shared { owner: other };
+        assert_eq!(wrapper.notify(principal, shared.clone(), 2, 20), 2);
+        wrapper.notify(other, NotificationKind::Reminder, 3, 30);
+
+        let notifications = wrapper.list(principal, Paginator::default());
+        assert_eq!(notifications.len(), 2);
+        assert_eq!(notifications[0].kind, shared);
+        assert_eq!(wrapper.unread_count(principal), 2);
+
+        assert!(wrapper.mark_read(principal, 1).is_ok());
+        assert!(wrapper.mark_read(principal, 1).is_ok());
+        assert_eq!(wrapper.unread_count(principal), 1);
+        assert!(matches!(
+            wrapper.mark_read(principal, 3),
+            Err(Error::NotFound)
+        ));
+
+        assert!(wrapper.notified_since(principal, &NotificationKind::Reminder, 1, 10));
+        assert!(!wrapper.notified_since(principal, &NotificationKind::Reminder, 1, 11));
+        assert!(!wrapper.notified_since(principal, &NotificationKind::Overdue, 1, 0));
+    }
+
+    #[test]
+    fn test_keeps_latest_notifications() {
+        let store = RefCell::new(NotificationStore::new(DefaultMemoryImpl::default()));
+        let wrapper = NotificationStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        for todo_id in 0..MAX_NOTIFICATIONS as TodoId + 5 {
+            wrapper.notify(principal, NotificationKind::Reminder, todo_id, 0);
+        }
+
+        assert_eq!(wrapper.unread_count(principal), MAX_NOTIFICATIONS as u64);
+        assert!(store.borrow().get(&(principal, 5)).is_none());
+        assert!(store.borrow().get(&(principal, 6)).is_some());
+    }
+}
@@ -5,7 +5,17 @@ use serde::Deserialize;
 const DEFAULT_PAGE_SIZE: u32 = 5;
 
 /// Maximum number of items allowed per page.
-const MAX_PAGE_SIZE: u32 = 100;
+pub(crate) const MAX_PAGE_SIZE: u32 = 100;
+
+/// Represents the direction in which a listing is paged through.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum Order {
+    /// From the first item to the last.
+    #[default]
+    Asc,
+    /// From the last item to the first, so page 1 holds the newest items.
+    Desc,
+}
 
 /// Struct to handle pagination logic.
 #[derive(CandidType, Deserialize, Default)]
@@ -17,9 +27,29 @@ pub struct Paginator {
 
     /// Optional limit on the number of items per page.
     limit: Option<u32>,
+
+    /// Optional direction of the listing, ascending by default.
+    order: Option<Order>,
 }
 
 impl Paginator {
+    /// Creates a paginator for the given page, for callers inside the canister such as benchmarks and tests.
+    #[cfg(any(test, feature = "canbench-rs"))]
+    pub fn new(page: u32, limit: Option<u32>) -> Self {
+        Self {
+            page,
+            limit,
+            order: None,
+        }
+    }
+
+    /// Pages through the listing from its last item, for tests.
+    #[cfg(test)]
+    pub fn descending(mut self) -> Self {
+        self.order = Some(Order::Desc);
+        self
+    }
+
     /// Returns the current page number, ensuring it is at least 1.
     /// This method ensures that the page number is always valid and 1-indexed.
     pub fn page(&self) -> u32 {
@@ -34,6 +64,19 @@ impl Paginator {
         u32::min(limit, MAX_PAGE_SIZE) as usize
     }
 
+    /// Uses the given limit if none was set explicitly.
+    /// This lets callers apply a per-user default page size while still honouring an explicit limit.
+    pub fn with_default_limit(mut self, limit: Option<u32>) -> Self {
+        self.limit = self.limit.or(limit);
+        self
+    }
+
+    /// Returns whether the listing is paged through from its last item,
+    /// which reaches the final page without walking over the earlier ones.
+    pub fn is_descending(&self) -> bool {
+        self.order == Some(Order::Desc)
+    }
+
     /// Calculates the number of items to skip based on the current page and limit.
     /// This is useful for database queries or in-memory collections to fetch the correct subset of items.
     pub fn skip(&self) -> usize {
@@ -53,7 +96,7 @@ mod tests {
 
     #[test]
     fn test_custom_page() {
-        let paginator = Paginator { page: 3, limit: None };
+        let paginator = Paginator { page: 3, limit: None, order: None };
         assert_eq!(paginator.page(), 3);
     }
 
@@ -65,25 +108,40 @@ mod tests {
 
     #[test]
     fn test_custom_limit_within_max() {
-        let paginator = Paginator { page: 1, limit: Some(10) };
+        let paginator = Paginator { page: 1, limit: Some(10), order: None };
         assert_eq!(paginator.limit(), 10);
     }
 
     #[test]
     fn test_custom_limit_exceeding_max() {
-        let paginator = Paginator { page: 1, limit: Some(150) };
+        let paginator = Paginator { page: 1, limit: Some(150), order: None };
         assert_eq!(paginator.limit(), MAX_PAGE_SIZE as usize);
     }
 
+    #[test]
+    fn test_with_default_limit() {
+        let paginator = Paginator::default().with_default_limit(Some(20));
+        assert_eq!(paginator.limit(), 20);
+        let paginator = Paginator { page: 1, limit: Some(10), order: None }.with_default_limit(Some(20));
+        assert_eq!(paginator.limit(), 10);
+    }
+
+    #[test]
+    fn test_order() {
+        assert!(!Paginator::default().is_descending());
+        let paginator = Paginator { page: 1, limit: None, order: Some(Order::Desc) };
+        assert!(paginator.is_descending());
+    }
+
     #[test]
     fn test_skip_calculation() {
-        let paginator = Paginator { page: 3, limit: Some(10) };
+        let paginator = Paginator { page: 3, limit: Some(10), order: None };
         assert_eq!(paginator.skip(), 20);
     }
 
     #[test]
     fn test_skip_calculation_with_default_limit() {
-        let paginator = Paginator { page: 3, limit: None };
+        let paginator = Paginator { page: 3, limit: None, order: None };
         assert_eq!(paginator.skip(), 10);
     }
 }
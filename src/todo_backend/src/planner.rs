@@ -0,0 +1,115 @@
+use candid::{CandidType, Deserialize};
+
+use crate::{
+    stats::{local_day_of, Day},
+    todo::Todo,
+};
+
+/// Number of days covered by a week plan.
+const DAYS_PER_WEEK: u32 = 7;
+
+/// Represents the Todo items due on a single day of a week plan.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct PlanDay {
+    /// The local day, counted in days since the Unix epoch.
+    pub(crate) day: Day,
+    /// Todo items due on the day, in order of their due dates.
+    pub(crate) todos: Vec<Todo>,
+}
+
+/// Represents the Todo items of a principal laid out over a week.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct WeekPlan {
+    /// Seven consecutive days, starting on the requested day.
+    pub(crate) days: Vec<PlanDay>,
+    /// Open Todo items without a due date.
+    pub(crate) backlog: Vec<Todo>,
+}
+
+/// Lays out Todo items over the week starting on a given local day.
+///
+/// # Arguments
+///
+/// * `todos` - All Todo items of the principal.
+/// * `start_day` - The first local day of the week, counted in days since the Unix epoch.
+/// * `utc_offset` - The offset of the principal's time zone from UTC, in nanoseconds.
+///
+/// # Returns
+///
+/// The week plan.
+pub(crate) fn week_plan(todos: Vec<Todo>, start_day: Day, utc_offset: i64) -> WeekPlan {
+    let mut days: Vec<PlanDay> = (0..DAYS_PER_WEEK)
+        .map(|offset| PlanDay {
+            day: start_day.saturating_add(offset),
+            todos: Vec::new(),
+        })
+        .collect();
+    let mut backlog = Vec::new();
+    for todo in todos {
+        match todo.due_date {
+            Some(due_date) => {
+                let day = local_day_of(due_date, utc_offset);
+                if let Some(plan_day) = days.iter_mut().find(|plan_day| plan_day.day == day) {
+                    plan_day.todos.push(todo);
+                }
+            }
+            None if !todo.is_completed => backlog.push(todo),
+            None => {}
+        }
+    }
+    for plan_day in &mut days {
+        plan_day.todos.sort_by_key(|todo| todo.due_date);
+    }
+    WeekPlan { days, backlog }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        stats::{NANOS_PER_DAY, NANOS_PER_MINUTE},
+        todo::Priority,
+    };
+
+    fn todo(id: u64, due_date: Option<u64>) -> Todo {
+        Todo {
+            due_date,
+            ..Todo::new(id, format!("Task {id}"), Priority::Medium)
+        }
+    }
+
+    #[test]
+    fn test_week_plan_uses_local_days() {
+        let utc_offset = -60 * NANOS_PER_MINUTE as i64;
+        let todos = vec![
+            todo(1, Some(10 * NANOS_PER_DAY + 30 * NANOS_PER_MINUTE)),
+            todo(2, Some(10 * NANOS_PER_DAY + 2 * 60 * NANOS_PER_MINUTE)),
+            todo(3, Some(20 * NANOS_PER_DAY)),
+            todo(4, None),
+        ];
+
+        let plan = week_plan(todos, 9, utc_offset);
+        assert_eq!(plan.days.len(), 7);
+        assert_eq!(
+            plan.days[0]
+                .todos
+                .iter()
+                .map(|todo| todo.id)
+                .collect::<Vec<_>>(),
+            vec![1]
+        );
+        assert_eq!(
+            plan.days[1]
+                .todos
+                .iter()
+                .map(|todo| todo.id)
+                .collect::<Vec<_>>(),
+            vec![2]
+        );
+        assert_eq!(
+            plan.backlog.iter().map(|todo| todo.id).collect::<Vec<_>>(),
+            vec![4]
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use std::{borrow::Cow, cell::RefCell, collections::BTreeMap};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{
+    errors::Error,
+    renumbering::{renumbered, rewrite_entries, Renumbering},
+    stats::{day_of, Day},
+    todo::TodoId,
+};
+
+/// Maximum length of a single pomodoro session, in minutes.
+const MAX_SESSION_MINUTES: u32 = 120;
+
+/// Type alias for the identifier of a pomodoro session, unique per principal.
+pub(crate) type SessionId = u64;
+
+/// Type alias for the PomodoroStore, which is a StableBTreeMap with a tuple key of (Principal, SessionId) and value of PomodoroSession.
+pub(crate) type PomodoroStore<M> = StableBTreeMap<(Principal, SessionId), PomodoroSession, M>;
+
+/// Represents a focus session spent on a Todo item.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct PomodoroSession {
+    /// The unique identifier of the session.
+    pub(crate) id: SessionId,
+    /// The unique identifier of the Todo item worked on.
+    pub(crate) todo_id: TodoId,
+    /// Planned length of the session, in minutes.
+    pub(crate) minutes: u32,
+    /// Time the session was started, in nanoseconds since the Unix epoch.
+    pub(crate) started_at: u64,
+    /// Time the session was completed, or None while it is running.
+    pub(crate) completed_at: Option<u64>,
+}
+
+impl Storable for PomodoroSession {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Represents the focus time spent on a single Todo item.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct TodoFocus {
+    /// The unique identifier of the Todo item.
+    pub(crate) todo_id: TodoId,
+    /// Number of completed sessions.
+    pub(crate) sessions: u32,
+    /// Total length of the completed sessions, in minutes.
+    pub(crate) minutes: u32,
+}
+
+/// Represents the pomodoro sessions a principal completed on a single day.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct PomodoroSummary {
+    /// The day, counted in days since the Unix epoch.
+    pub(crate) day: Day,
+    /// Number of completed sessions.
+    pub(crate) sessions: u32,
+    /// Total length of the completed sessions, in minutes.
+    pub(crate) minutes: u32,
+    /// Focus time per Todo item, in order of their identifiers.
+    pub(crate) todos: Vec<TodoFocus>,
+}
+
+/// Wrapper around the PomodoroStore to provide additional functionality.
+pub(crate) struct PomodoroStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<PomodoroStore<M>>,
+}
+
+impl<M: Memory> PomodoroStoreWrapper<'_, M> {
+    /// Starts a new pomodoro session for a Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `todo_id` - The unique identifier of the Todo item.
+    /// * `minutes` - The planned length of the session.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the identifier of the new session, or an Error if the length is invalid.
+    pub(crate) fn start(
+        &self,
+        principal: Principal,
+        todo_id: TodoId,
+        minutes: u32,
+        now: u64,
+    ) -> Result<SessionId, Error> {
+        if !(1..=MAX_SESSION_MINUTES).contains(&minutes) {
+            return Err(Error::InvalidInput(format!(
+                "Session length must be between 1 and {MAX_SESSION_MINUTES} minutes"
+            )));
+        }
+        let id = self
+            .store
+            .borrow()
+            .iter_upper_bound(&(principal, SessionId::MAX))
+            .next()
+            .filter(|((owner, _), _)| *owner == principal)
+            .map_or(1, |((_, id), _)| id + 1);
+        let session = PomodoroSession {
+            id,
+            todo_id,
+            minutes,
+            started_at: now,
+            completed_at: None,
+        };
+        self.store.borrow_mut().insert((principal, id), session);
+        Ok(id)
+    }
+
+    /// Marks a running pomodoro session as completed.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier of the session.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success, or an Error if the session is not found or already completed.
+    pub(crate) fn complete(
+        &self,
+        principal: Principal,
+        id: SessionId,
+        now: u64,
+    ) -> Result<(), Error> {
+        let mut store = self.store.borrow_mut();
+        let session = store.get(&(principal, id)).ok_or(Error::NotFound)?;
+        if session.completed_at.is_some() {
+            return Err(Error::Conflict(Some(
+                "Session is already completed".to_string(),
+            )));
+        }
+        store.insert(
+            (principal, id),
+            PomodoroSession {
+                completed_at: Some(now),
+                ..session
+            },
+        );
+        Ok(())
+    }
+
+    /// Summarizes the sessions a principal completed on a given day.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `day` - The day, counted in days since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// The summary of the completed sessions.
+    pub(crate) fn daily_summary(&self, principal: Principal, day: Day) -> PomodoroSummary {
+        let mut summary = PomodoroSummary {
+            day,
+            sessions: 0,
+            minutes: 0,
+            todos: Vec::new(),
+        };
+        let store = self.store.borrow();
+        let sessions = store
+            .range((principal, 0)..=(principal, SessionId::MAX))
+            .map(|(_, session)| session)
+            .filter(|session| session.completed_at.map(day_of) == Some(day));
+        for session in sessions {
+            summary.sessions += 1;
+            summary.minutes += session.minutes;
+            match summary
+                .todos
+                .iter_mut()
+                .find(|focus| focus.todo_id == session.todo_id)
+            {
+                Some(focus) => {
+                    focus.sessions += 1;
+                    focus.minutes += session.minutes;
+                }
+                None => summary.todos.push(TodoFocus {
+                    todo_id: session.todo_id,
+                    sessions: 1,
+                    minutes: session.minutes,
+                }),
+            }
+        }
+        summary.todos.sort_by_key(|focus| focus.todo_id);
+        summary
+    }
+
+    /// Sums the length of the completed sessions of a principal per Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    ///
+    /// # Returns
+    ///
+    /// The minutes of focus time tracked on each Todo item worked on.
+    pub(crate) fn tracked_minutes(&self, principal: Principal) -> BTreeMap<TodoId, u64> {
+        let mut minutes: BTreeMap<TodoId, u64> = BTreeMap::new();
+        self.store
+            .borrow()
+            .range((principal, 0)..=(principal, SessionId::MAX))
+            .map(|(_, session)| session)
+            .filter(|session| session.completed_at.is_some())
+            .for_each(|session| {
+                *minutes.entry(session.todo_id).or_default() += u64::from(session.minutes)
+            });
+        minutes
+    }
+
+    /// Points the pomodoro sessions of a principal to the new identifiers of their Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        rewrite_entries(
+            self.store,
+            (principal, SessionId::MIN)..=(principal, SessionId::MAX),
+            |key, mut session| {
+                session.todo_id = renumbered(ids, session.todo_id);
+                (key, session)
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::stats::NANOS_PER_DAY;
+
+    #[test]
+    fn test_start_and_complete_session() {
+        let store = RefCell::new(PomodoroStore::new(DefaultMemoryImpl::default()));
+        let wrapper = PomodoroStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+
+        assert!(matches!(
+            wrapper.start(principal, 1, 0, 0),
+            Err(Error::InvalidInput(_))
+        ));
+        let first = wrapper.start(principal, 1, 25, 0).unwrap();
+        let second = wrapper.start(principal, 1, 25, 0).unwrap();
+        assert_eq!((first, second), (1, 2));
+
+        assert!(wrapper.complete(principal, first, 10).is_ok());
+        assert!(matches!(
+            wrapper.complete(principal, first, 20),
+            Err(Error::Conflict(_))
+        ));
+        assert!(matches!(
+            wrapper.complete(principal, 3, 20),
+            Err(Error::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_daily_summary() {
+        let store = RefCell::new(PomodoroStore::new(DefaultMemoryImpl::default()));
+        let wrapper = PomodoroStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let today = 5 * NANOS_PER_DAY;
+        for (todo_id, completed_at) in [(2, today), (1, today + 1), (2, today + 2), (1, today - 1)]
+        {
+            let id = wrapper.start(principal, todo_id, 25, completed_at).unwrap();
+            wrapper.complete(principal, id, completed_at).unwrap();
+        }
+        wrapper.start(principal, 3, 25, today).unwrap();
+
+        let summary = wrapper.daily_summary(principal, 5);
+        assert_eq!(summary.sessions, 3);
+        assert_eq!(summary.minutes, 75);
+        assert_eq!(
+            summary.todos,
+            vec![
+                TodoFocus {
+                    todo_id: 1,
+                    sessions: 1,
+                    minutes: 25
+                },
+                TodoFocus {
+                    todo_id: 2,
+                    sessions: 2,
+                    minutes: 50
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,341 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{
+    errors::Error,
+    history::MAX_REVISIONS,
+    messages::Language,
+    notifications::NotificationKind,
+    paginator::MAX_PAGE_SIZE,
+    stats::{Day, NANOS_PER_MINUTE},
+    store::SortOrder,
+    todo::Priority,
+};
+
+/// Maximum number of days a Todo item may be left untouched before it is escalated.
+const MAX_ESCALATION_DAYS: u32 = 365;
+
+/// Maximum number of days a completed Todo item may stay in the active list before it is archived.
+const MAX_ARCHIVE_DAYS: u32 = 3650;
+
+/// Maximum number of days an archived Todo item may be kept after its completion before it is purged.
+const MAX_ARCHIVE_RETENTION_DAYS: u32 = 3650;
+
+/// Number of days a delegated Todo item may be waited on before it needs a follow-up, unless set otherwise.
+const DEFAULT_FOLLOW_UP_DAYS: u32 = 7;
+
+/// Maximum number of days a delegated Todo item may be waited on before it needs a follow-up.
+const MAX_FOLLOW_UP_DAYS: u32 = 365;
+
+/// Range of UTC offsets in use around the world, in minutes.
+const UTC_OFFSET_RANGE: std::ops::RangeInclusive<i32> = -12 * 60..=14 * 60;
+
+/// Type alias for the PreferenceStore, which is a StableBTreeMap keyed by Principal with a value of Preferences.
+pub(crate) type PreferenceStore<M> = StableBTreeMap<Principal, Preferences, M>;
+
+/// Represents a day of the week.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum Weekday {
+    #[default]
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    /// Returns the last day on or before a given day that falls on this weekday.
+    ///
+    /// # Arguments
+    ///
+    /// * `day` - The day, counted in days since the Unix epoch.
+    pub(crate) fn on_or_before(self, day: Day) -> Day {
+        // The Unix epoch fell on a Thursday, the fourth day of a week starting on Monday.
+        let weekday_of_day = (day + 3) % 7;
+        day.saturating_sub((weekday_of_day + 7 - self as u32) % 7)
+    }
+}
+
+/// Represents which events a principal wants to be notified about.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) struct NotificationSettings {
+    /// Whether to notify when a reminder fires or a Todo item becomes overdue.
+    pub(crate) reminders: bool,
+    /// Whether to notify when a Todo item is shared with the principal.
+    pub(crate) shares: bool,
+    /// Whether to notify when a comment is added to a Todo item.
+    pub(crate) comments: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            reminders: true,
+            shares: true,
+            comments: true,
+        }
+    }
+}
+
+impl NotificationSettings {
+    /// Returns whether notifications of the given kind are delivered; assignments are always delivered.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - What the notification is about.
+    pub(crate) fn allows(&self, kind: &NotificationKind) -> bool {
+        match kind {
+            NotificationKind::Reminder | NotificationKind::Overdue => self.reminders,
+            NotificationKind::Shared { .. } => self.shares,
+            NotificationKind::Comment { .. } => self.comments,
+            NotificationKind::Assigned { .. } => true,
+        }
+    }
+}
+
+/// Represents the settings of a principal.
+///
+/// Every field is optional so that settings added later decode as unset for existing principals.
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct Preferences {
+    /// Number of days after which an untouched open Todo item gets its priority raised, or None to never escalate.
+    pub(crate) escalate_after_days: Option<u32>,
+    /// Number of days after completion at which a Todo item is moved to the archive, or None to never archive.
+    pub(crate) archive_after_days: Option<u32>,
+    /// Offset of the local time zone from UTC in minutes, used to find day boundaries; UTC if None.
+    pub(crate) utc_offset_minutes: Option<i32>,
+    /// Priority of new Todo items created without one; `Priority::Medium` if None.
+    pub(crate) default_priority: Option<Priority>,
+    /// Number of items per page when a listing does not set a limit.
+    pub(crate) default_page_size: Option<u32>,
+    /// Order of listed Todo items; in order of creation if None.
+    pub(crate) default_sort: Option<SortOrder>,
+    /// First day of the week in planner views; Monday if None.
+    pub(crate) week_start: Option<Weekday>,
+    /// Events to be notified about; all of them if None.
+    pub(crate) notifications: Option<NotificationSettings>,
+    /// Number of days after which a delegated Todo item needs a follow-up; 7 if None.
+    pub(crate) follow_up_after_days: Option<u32>,
+    /// Number of days after completion at which an archived Todo item is deleted for good, or None to keep it.
+    pub(crate) archive_retention_days: Option<u32>,
+    /// Number of previous versions kept for each Todo item, at most 20; 20 if None.
+    pub(crate) max_revisions: Option<u32>,
+    /// Language of emails, chat messages and error descriptions; English if None.
+    pub(crate) language: Option<Language>,
+    /// Whether open Todo items due on a day move on to the next day once it ends locally; off if None.
+    pub(crate) roll_over_unfinished: Option<bool>,
+    /// Whether a review of the week is sent by email and Telegram on Sunday evenings; off if None.
+    pub(crate) weekly_review: Option<bool>,
+    /// Whether adding a Todo item with the description of an open Todo item is rejected; off if None.
+    pub(crate) reject_duplicates: Option<bool>,
+}
+
+impl Storable for Preferences {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl Preferences {
+    /// Returns the offset of the local time zone from UTC, in nanoseconds.
+    pub(crate) fn utc_offset(&self) -> i64 {
+        i64::from(self.utc_offset_minutes.unwrap_or_default()) * NANOS_PER_MINUTE as i64
+    }
+
+    /// Returns the number of days after which a delegated Todo item needs a follow-up.
+    pub(crate) fn follow_up_after_days(&self) -> u32 {
+        self.follow_up_after_days.unwrap_or(DEFAULT_FOLLOW_UP_DAYS)
+    }
+
+    /// Returns the number of previous versions kept for each Todo item.
+    pub(crate) fn max_revisions(&self) -> usize {
+        self.max_revisions.map_or(MAX_REVISIONS, |max| max as usize)
+    }
+
+    /// Returns the language of the user-facing texts sent to the principal.
+    pub(crate) fn language(&self) -> Language {
+        self.language.unwrap_or_default()
+    }
+
+    /// Checks that every setting is within its allowed range.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an InvalidInput Error naming the offending setting.
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if let Some(days) = self.escalate_after_days {
+            if !(1..=MAX_ESCALATION_DAYS).contains(&days) {
+                return Err(Error::InvalidInput(format!(
+                    "Escalation must happen after 1 to {MAX_ESCALATION_DAYS} days"
+                )));
+            }
+        }
+        if let Some(days) = self.archive_after_days {
+            if !(1..=MAX_ARCHIVE_DAYS).contains(&days) {
+                return Err(Error::InvalidInput(format!(
+                    "Archiving must happen after 1 to {MAX_ARCHIVE_DAYS} days"
+                )));
+            }
+        }
+        if let Some(days) = self.archive_retention_days {
+            if !(1..=MAX_ARCHIVE_RETENTION_DAYS).contains(&days) {
+                return Err(Error::InvalidInput(format!(
+                    "Archived items must be kept for 1 to {MAX_ARCHIVE_RETENTION_DAYS} days"
+                )));
+            }
+        }
+        if let Some(max) = self.max_revisions {
+            if max as usize > MAX_REVISIONS {
+                return Err(Error::InvalidInput(format!(
+                    "At most {MAX_REVISIONS} previous versions can be kept"
+                )));
+            }
+        }
+        if let Some(days) = self.follow_up_after_days {
+            if !(1..=MAX_FOLLOW_UP_DAYS).contains(&days) {
+                return Err(Error::InvalidInput(format!(
+                    "Follow-ups must be due after 1 to {MAX_FOLLOW_UP_DAYS} days"
+                )));
+            }
+        }
+        if let Some(page_size) = self.default_page_size {
+            if !(1..=MAX_PAGE_SIZE).contains(&page_size) {
+                return Err(Error::InvalidInput(format!(
+                    "Default page size must be between 1 and {MAX_PAGE_SIZE}"
+                )));
+            }
+        }
+        if let Some(offset) = self.utc_offset_minutes {
+            if !UTC_OFFSET_RANGE.contains(&offset) {
+                return Err(Error::InvalidInput(
+                    "UTC offset must be between -720 and 840 minutes".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wrapper around the PreferenceStore to provide additional functionality.
+pub(crate) struct PreferenceStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<PreferenceStore<M>>,
+}
+
+impl<M: Memory> PreferenceStoreWrapper<'_, M> {
+    /// Retrieves the preferences of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    ///
+    /// # Returns
+    ///
+    /// The preferences, or the defaults if the principal never set any.
+    pub(crate) fn get(&self, principal: Principal) -> Preferences {
+        self.store.borrow().get(&principal).unwrap_or_default()
+    }
+
+    /// Replaces the preferences of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `preferences` - The new preferences.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if a setting is out of range.
+    pub(crate) fn set(&self, principal: Principal, preferences: Preferences) -> Result<(), Error> {
+        preferences.validate()?;
+        self.store.borrow_mut().insert(principal, preferences);
+        Ok(())
+    }
+
+    /// Lists the principals that enabled a setting.
+    ///
+    /// # Arguments
+    ///
+    /// * `setting` - Reads the setting from the preferences, returning None if it is disabled.
+    ///
+    /// # Returns
+    ///
+    /// A vector of principals paired with the value they configured, such as a number of days.
+    pub(crate) fn opted_in<T>(
+        &self,
+        setting: impl Fn(&Preferences) -> Option<T>,
+    ) -> Vec<(Principal, T)> {
+        self.store
+            .borrow()
+            .iter()
+            .filter_map(|(principal, preferences)| Some((principal, setting(&preferences)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_weekday_on_or_before() {
+        // Day 0 was Thursday 1970-01-01.
+        assert_eq!(Weekday::Thursday.on_or_before(0), 0);
+        assert_eq!(Weekday::Monday.on_or_before(4), 4);
+        assert_eq!(Weekday::Monday.on_or_before(10), 4);
+        assert_eq!(Weekday::Sunday.on_or_before(10), 10);
+        assert_eq!(Weekday::Sunday.on_or_before(9), 3);
+    }
+
+    #[test]
+    fn test_set_validates_preferences() {
+        let store = RefCell::new(PreferenceStore::new(DefaultMemoryImpl::default()));
+        let wrapper = PreferenceStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+
+        let invalid = Preferences {
+            escalate_after_days: Some(0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            wrapper.set(principal, invalid),
+            Err(Error::InvalidInput(_))
+        ));
+        assert_eq!(wrapper.get(principal), Preferences::default());
+        let invalid = Preferences {
+            default_page_size: Some(MAX_PAGE_SIZE + 1),
+            ..Default::default()
+        };
+        assert!(wrapper.set(principal, invalid).is_err());
+        let invalid = Preferences {
+            max_revisions: Some(MAX_REVISIONS as u32 + 1),
+            ..Default::default()
+        };
+        assert!(wrapper.set(principal, invalid).is_err());
+        assert_eq!(wrapper.get(principal).max_revisions(), MAX_REVISIONS);
+
+        let valid = Preferences {
+            escalate_after_days: Some(7),
+            ..Default::default()
+        };
+        wrapper.set(principal, valid.clone()).unwrap();
+        assert_eq!(wrapper.get(principal), valid);
+        assert_eq!(
+            wrapper.opted_in(|p| p.escalate_after_days),
+            vec![(principal, 7)]
+        );
+        assert!(wrapper.opted_in(|p| p.archive_after_days).is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{
+    renumbering::{renumbered, rewrite_entries, Renumbering},
+    store::TodoChange,
+    todo::{Priority, TodoId},
+};
+
+/// Maximum number of priority changes kept for each Todo item; older ones are dropped.
+pub(crate) const MAX_PRIORITY_CHANGES: usize = 100;
+
+/// Type alias for the PriorityHistoryStore, which is a StableBTreeMap with a tuple key of (Principal, TodoId, sequence)
+/// and value of PriorityChange. The sequence increases with every change of a Todo item.
+pub(crate) type PriorityHistoryStore<M> =
+    StableBTreeMap<(Principal, TodoId, u32), PriorityChange, M>;
+
+/// Represents a change of the priority of a Todo item.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) struct PriorityChange {
+    /// Time of the change, in nanoseconds since the Unix epoch.
+    pub(crate) at: u64,
+    /// The principal who made the change; the canister itself for automatic escalations.
+    pub(crate) by: Principal,
+    /// The priority before the change.
+    pub(crate) from: Priority,
+    /// The priority after the change.
+    pub(crate) to: Priority,
+}
+
+impl Storable for PriorityChange {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Wrapper around the PriorityHistoryStore to provide additional functionality.
+pub(crate) struct PriorityHistoryStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<PriorityHistoryStore<M>>,
+}
+
+impl<M: Memory> PriorityHistoryStoreWrapper<'_, M> {
+    /// Records the change of priority a change to a Todo item made, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `change` - The change to a Todo item.
+    /// * `by` - The principal who made the change.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    pub(crate) fn record_change(&self, change: &TodoChange, by: Principal, now: u64) {
+        let (Some(before), Some(after)) = (&change.before, &change.after) else {
+            return;
+        };
+        if before.priority == after.priority {
+            return;
+        }
+        let principal = change.principal;
+        let id = after.id;
+        let history = self.keys(principal, id);
+        let mut store = self.store.borrow_mut();
+        for key in history
+            .iter()
+            .take((history.len() + 1).saturating_sub(MAX_PRIORITY_CHANGES))
+        {
+            store.remove(key);
+        }
+        let sequence = history.last().map_or(1, |(_, _, sequence)| sequence + 1);
+        store.insert(
+            (principal, id, sequence),
+            PriorityChange {
+                at: now,
+                by,
+                from: before.priority,
+                to: after.priority,
+            },
+        );
+    }
+
+    /// Lists the priority changes of a Todo item, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the Todo item.
+    /// * `id` - The unique identifier for the Todo item.
+    pub(crate) fn list(&self, principal: Principal, id: TodoId) -> Vec<PriorityChange> {
+        self.store
+            .borrow()
+            .range((principal, id, u32::MIN)..=(principal, id, u32::MAX))
+            .map(|(_, change)| change)
+            .collect()
+    }
+
+    /// Removes the priority history of a Todo item that was deleted.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the Todo item.
+    /// * `id` - The unique identifier for the Todo item.
+    pub(crate) fn remove_all(&self, principal: Principal, id: TodoId) {
+        let keys = self.keys(principal, id);
+        let mut store = self.store.borrow_mut();
+        for key in keys {
+            store.remove(&key);
+        }
+    }
+
+    /// Moves the priority histories of a principal's Todo items to their new identifiers.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        rewrite_entries(
+            self.store,
+            (principal, TodoId::MIN, u32::MIN)..=(principal, TodoId::MAX, u32::MAX),
+            |(principal, id, sequence), change| {
+                ((principal, renumbered(ids, id), sequence), change)
+            },
+        );
+    }
+
+    /// Returns the keys of the priority changes of a Todo item, oldest first.
+    fn keys(&self, principal: Principal, id: TodoId) -> Vec<(Principal, TodoId, u32)> {
+        self.store
+            .borrow()
+            .range((principal, id, u32::MIN)..=(principal, id, u32::MAX))
+            .map(|(key, _)| key)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::Todo;
+
+    #[test]
+    fn test_records_priority_changes() {
+        let store = RefCell::new(PriorityHistoryStore::new(DefaultMemoryImpl::default()));
+        let wrapper = PriorityHistoryStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let editor = Principal::management_canister();
+        let todo = |priority| Todo::new(1, "Ship".to_string(), priority);
+        let change = |from, to| TodoChange {
+            principal,
+            before: Some(todo(from)),
+            after: Some(todo(to)),
+        };
+
+        wrapper.record_change(&change(Priority::High, Priority::Low), editor, 5);
+        wrapper.record_change(&change(Priority::Low, Priority::Low), editor, 6);
+        wrapper.record_change(&change(Priority::Low, Priority::Medium), principal, 7);
+        assert_eq!(
+            wrapper.list(principal, 1),
+            vec![
+                PriorityChange {
+                    at: 5,
+                    by: editor,
+                    from: Priority::High,
+                    to: Priority::Low
+                },
+                PriorityChange {
+                    at: 7,
+                    by: principal,
+                    from: Priority::Low,
+                    to: Priority::Medium
+                },
+            ]
+        );
+
+        for at in 0..MAX_PRIORITY_CHANGES as u64 {
+            wrapper.record_change(&change(Priority::High, Priority::Low), editor, 10 + at);
+        }
+        let history = wrapper.list(principal, 1);
+        assert_eq!(history.len(), MAX_PRIORITY_CHANGES);
+        assert_eq!(history[0].at, 10);
+
+        wrapper.renumber(principal, &Renumbering::from([(1, 2)]));
+        assert!(wrapper.list(principal, 1).is_empty());
+        wrapper.remove_all(principal, 2);
+        assert!(store.borrow().is_empty());
+    }
+}
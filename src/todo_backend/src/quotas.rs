@@ -0,0 +1,101 @@
+use candid::{CandidType, Deserialize};
+
+use crate::todo::TodoId;
+
+/// Share of a quota, in percent, from which on a warning is given.
+const WARNING_PERCENT: u64 = 80;
+
+/// Represents a limit on what a principal may keep.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Quota {
+    /// Number of Todo items, archived ones included.
+    Todos,
+    /// Total size of the attached files, in bytes.
+    AttachmentBytes,
+    /// Number of labels.
+    Labels,
+    /// Number of goals.
+    Goals,
+    /// Number of checklists.
+    Checklists,
+    /// Number of namespaces.
+    Namespaces,
+    /// Number of Todo items scheduled for later.
+    ScheduledTodos,
+    /// Number of integrations.
+    Integrations,
+    /// Number of API tokens.
+    ApiTokens,
+    /// Number of principals granted access to the account.
+    AccountGrants,
+}
+
+/// Represents a quota a principal is close to exhausting, so that clients can nudge them before changes are rejected.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Warning {
+    /// The quota.
+    pub(crate) quota: Quota,
+    /// How much of the quota is used.
+    pub(crate) used: u64,
+    /// The limit of the quota.
+    pub(crate) limit: u64,
+}
+
+/// Represents a newly created Todo item together with the quotas its owner is close to exhausting.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct CreatedTodo {
+    /// The unique identifier for the Todo item.
+    pub(crate) id: TodoId,
+    /// A warning for each quota with at least 80% used.
+    pub(crate) warnings: Vec<Warning>,
+}
+
+/// Represents a staged chunk of an import together with the quotas the importer is close to exhausting,
+/// counting the staged Todo items as if they were created.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct StagedChunk {
+    /// The number of Todo items staged so far.
+    pub(crate) staged: u32,
+    /// A warning for each quota with at least 80% used.
+    pub(crate) warnings: Vec<Warning>,
+}
+
+/// Checks how much of a quota is used.
+///
+/// # Arguments
+///
+/// * `quota` - The quota.
+/// * `used` - How much of the quota is used.
+/// * `limit` - The limit of the quota.
+///
+/// # Returns
+///
+/// An Option containing a warning if at least 80% of the quota is used, otherwise None.
+pub(crate) fn check(quota: Quota, used: u64, limit: u64) -> Option<Warning> {
+    (used.saturating_mul(100) >= limit.saturating_mul(WARNING_PERCENT)).then_some(Warning {
+        quota,
+        used,
+        limit,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warns_from_80_percent() {
+        assert_eq!(check(Quota::Labels, 79, 100), None);
+        assert_eq!(
+            check(Quota::Labels, 80, 100),
+            Some(Warning {
+                quota: Quota::Labels,
+                used: 80,
+                limit: 100
+            })
+        );
+        assert!(check(Quota::AttachmentBytes, 50_000_000, 50_000_000).is_some());
+        assert_eq!(check(Quota::Todos, 79_999, 100_000), None);
+        assert!(check(Quota::Todos, 80_000, 100_000).is_some());
+    }
+}
@@ -0,0 +1,250 @@
+use std::fmt;
+
+use crate::{
+    errors::Error,
+    stats::{civil_from_days, days_from_civil, local_day_of, NANOS_PER_DAY, NANOS_PER_MINUTE},
+};
+
+/// Time of day an occurrence falls on when a rule does not name one, in minutes after midnight.
+const DEFAULT_MINUTE: u32 = 9 * 60;
+
+/// Names of the days of the week, starting on Monday; the first three letters are accepted as well.
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Number of days searched for the next occurrence; every rule has one at least every other month.
+const SEARCH_DAYS: i64 = 62;
+
+/// Represents when a recurring Todo item falls due, in the local time of its owner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Recurrence {
+    /// On the selected days of every week; bit 0 is Monday.
+    Weekly { weekdays: u8, minute: u32 },
+    /// On a day of every month; months without that day use their last day.
+    Monthly { day: u32, minute: u32 },
+}
+
+impl Recurrence {
+    /// Parses a recurrence rule such as `every day`, `every mon,wed 09:00` or `1st of month`.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The rule, in any letter case.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the recurrence, or an Error if the rule is not understood.
+    pub(crate) fn parse(text: &str) -> Result<Self, Error> {
+        let invalid = || {
+            Error::InvalidInput(format!(
+                "Recurrence must look like \"every day\", \"every mon,wed 09:00\" or \"1st of month\", not \"{text}\""
+            ))
+        };
+        let text = text.to_lowercase();
+        let mut words: Vec<&str> = text.split_whitespace().collect();
+        let minute = match words.last().and_then(|word| parse_time(word)) {
+            Some(minute) => {
+                words.pop();
+                minute
+            }
+            None => DEFAULT_MINUTE,
+        };
+        if words.first() == Some(&"every") {
+            words.remove(0);
+        }
+        match words.as_slice() {
+            [ordinal, "of", "month"] => {
+                let day = parse_ordinal(ordinal).ok_or_else(invalid)?;
+                Ok(Recurrence::Monthly { day, minute })
+            }
+            ["day"] => Ok(Recurrence::Weekly {
+                weekdays: 0b111_1111,
+                minute,
+            }),
+            ["weekday"] => Ok(Recurrence::Weekly {
+                weekdays: 0b001_1111,
+                minute,
+            }),
+            [] => Err(invalid()),
+            days => {
+                let mut weekdays = 0;
+                for name in days.concat().split(',') {
+                    let index = WEEKDAYS
+                        .iter()
+                        .position(|weekday| name == *weekday || name == &weekday[..3])
+                        .ok_or_else(invalid)?;
+                    weekdays |= 1 << index;
+                }
+                Ok(Recurrence::Weekly { weekdays, minute })
+            }
+        }
+    }
+
+    /// Finds the first occurrence strictly after a point in time.
+    ///
+    /// # Arguments
+    ///
+    /// * `after` - The point in time, in nanoseconds since the Unix epoch.
+    /// * `utc_offset` - The offset of the owner's time zone from UTC, in nanoseconds.
+    ///
+    /// # Returns
+    ///
+    /// The time of the next occurrence, in nanoseconds since the Unix epoch.
+    pub(crate) fn next_after(&self, after: u64, utc_offset: i64) -> u64 {
+        let first_day = i64::from(local_day_of(after, utc_offset));
+        (first_day..=first_day + SEARCH_DAYS)
+            .filter(|day| self.falls_on(*day))
+            .map(|day| {
+                let local =
+                    day as u64 * NANOS_PER_DAY + u64::from(self.minute()) * NANOS_PER_MINUTE;
+                local.saturating_add_signed(-utc_offset)
+            })
+            .find(|occurrence| *occurrence > after)
+            .unwrap_or(u64::MAX)
+    }
+
+    /// Returns the time of day of the occurrences, in minutes after midnight.
+    fn minute(&self) -> u32 {
+        match self {
+            Recurrence::Weekly { minute, .. } | Recurrence::Monthly { minute, .. } => *minute,
+        }
+    }
+
+    /// Returns whether an occurrence falls on a day.
+    ///
+    /// # Arguments
+    ///
+    /// * `day` - The local day, counted in days since the Unix epoch.
+    pub(crate) fn falls_on(&self, day: i64) -> bool {
+        match self {
+            // The Unix epoch fell on a Thursday, the fourth day of a week starting on Monday.
+            Recurrence::Weekly { weekdays, .. } => weekdays & (1 << (day + 3).rem_euclid(7)) != 0,
+            Recurrence::Monthly { day: target, .. } => {
+                let (year, month, day_of_month) = civil_from_days(day);
+                let (next_year, next_month) = if month == 12 {
+                    (year + 1, 1)
+                } else {
+                    (year, month + 1)
+                };
+                let days_in_month =
+                    days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1);
+                day_of_month == (*target).min(days_in_month as u32)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recurrence::Weekly {
+                weekdays: 0b111_1111,
+                ..
+            } => write!(f, "every day")?,
+            Recurrence::Weekly { weekdays, .. } => {
+                let names: Vec<&str> = (0..7)
+                    .filter(|index| weekdays & (1 << index) != 0)
+                    .map(|index| &WEEKDAYS[index][..3])
+                    .collect();
+                write!(f, "every {}", names.join(","))?;
+            }
+            Recurrence::Monthly { day, .. } => write!(f, "{day}{} of month", ordinal_suffix(*day))?,
+        }
+        write!(f, " {:02}:{:02}", self.minute() / 60, self.minute() % 60)
+    }
+}
+
+/// Parses a time of day written as `HH:MM`.
+///
+/// # Returns
+///
+/// The minutes after midnight, or None if the text is not a valid time.
+fn parse_time(text: &str) -> Option<u32> {
+    let (hours, minutes) = text.split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60 && text.len() <= 5).then_some(hours * 60 + minutes)
+}
+
+/// Parses a day of the month written as an ordinal such as `1st` or `22nd`.
+///
+/// # Returns
+///
+/// The day, or None if the text is not an ordinal between 1 and 31.
+fn parse_ordinal(text: &str) -> Option<u32> {
+    let digits = text.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let day: u32 = digits.parse().ok()?;
+    ((1..=31).contains(&day) && text[digits.len()..] == *ordinal_suffix(day)).then_some(day)
+}
+
+/// Returns the English ordinal suffix of a number, such as `st` for 1 and `th` for 11.
+fn ordinal_suffix(number: u32) -> &'static str {
+    match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format() {
+        let canonical = |text: &str| Recurrence::parse(text).map(|rule| rule.to_string()).ok();
+        assert_eq!(canonical("Every day"), Some("every day 09:00".to_string()));
+        assert_eq!(
+            canonical("every Monday, wed 18:30"),
+            Some("every mon,wed 18:30".to_string())
+        );
+        assert_eq!(
+            canonical("every weekday 7:05"),
+            Some("every mon,tue,wed,thu,fri 07:05".to_string())
+        );
+        assert_eq!(
+            canonical("1st of month"),
+            Some("1st of month 09:00".to_string())
+        );
+        assert_eq!(
+            canonical("every 22nd of month 00:00"),
+            Some("22nd of month 00:00".to_string())
+        );
+        assert_eq!(canonical("every"), None);
+        assert_eq!(canonical("every funday"), None);
+        assert_eq!(canonical("32nd of month"), None);
+        assert_eq!(canonical("2st of month"), None);
+        assert_eq!(canonical("every day 24:00"), None);
+    }
+
+    #[test]
+    fn test_next_after() {
+        // 1970-01-05 was a Monday.
+        let monday = 4 * NANOS_PER_DAY;
+        let rule = Recurrence::parse("every mon,wed 09:00").unwrap();
+        let nine = 9 * 60 * NANOS_PER_MINUTE;
+        assert_eq!(rule.next_after(monday, 0), monday + nine);
+        assert_eq!(
+            rule.next_after(monday + nine, 0),
+            monday + 2 * NANOS_PER_DAY + nine
+        );
+        let utc_offset = 2 * 60 * NANOS_PER_MINUTE as i64;
+        assert_eq!(
+            rule.next_after(monday, utc_offset),
+            monday + nine - utc_offset as u64
+        );
+
+        // 1970-02-01; February 1970 had 28 days.
+        let february = days_from_civil(1970, 2, 1) as u64 * NANOS_PER_DAY;
+        let rule = Recurrence::parse("31st of month 00:00").unwrap();
+        assert_eq!(rule.next_after(february, 0), february + 27 * NANOS_PER_DAY);
+    }
+}
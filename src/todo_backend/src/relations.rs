@@ -0,0 +1,245 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{
+    errors::Error,
+    renumbering::{renumbered, rewrite_entries, Renumbering},
+    todo::TodoId,
+};
+
+/// Maximum number of Todo items a Todo item may be related to.
+pub(crate) const MAX_RELATIONS: usize = 100;
+
+/// Type alias for the RelationStore, which is a StableBTreeMap with a tuple key of (Principal, TodoId, TodoId) and value of Relation.
+/// Every relation is stored under both of its Todo items, so either one finds it.
+pub(crate) type RelationStore<M> = StableBTreeMap<(Principal, TodoId, TodoId), Relation, M>;
+
+/// Represents how two Todo items are related.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum RelationKind {
+    /// The Todo items concern the same matter.
+    RelatesTo,
+    /// The Todo item repeats the other one.
+    DuplicateOf,
+    /// The Todo item continues the work of the other one.
+    FollowsFrom,
+}
+
+/// Represents a relation as seen from one of its Todo items.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Relation {
+    /// How the Todo items are related.
+    pub(crate) kind: RelationKind,
+    /// Whether the relation starts at this Todo item, as in "this DuplicateOf other", or points at it.
+    pub(crate) outgoing: bool,
+}
+
+impl Storable for Relation {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Represents a Todo item related to another one.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) struct RelatedTodo {
+    /// The unique identifier for the related Todo item.
+    pub(crate) todo_id: TodoId,
+    /// How the Todo items are related.
+    pub(crate) kind: RelationKind,
+    /// Whether the relation starts at the Todo item asked about and points at the related one.
+    pub(crate) outgoing: bool,
+}
+
+/// Wrapper around the RelationStore to provide additional functionality.
+pub(crate) struct RelationStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<RelationStore<M>>,
+}
+
+impl<M: Memory> RelationStoreWrapper<'_, M> {
+    /// Relates a Todo item to another one, replacing any relation between them.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `from` - The unique identifier for the Todo item the relation starts at.
+    /// * `to` - The unique identifier for the Todo item the relation points at.
+    /// * `kind` - How the Todo items are related.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if a Todo item is related to itself or to too many others.
+    pub(crate) fn link(
+        &self,
+        principal: Principal,
+        from: TodoId,
+        to: TodoId,
+        kind: RelationKind,
+    ) -> Result<(), Error> {
+        if from == to {
+            return Err(Error::InvalidInput(
+                "A Todo item cannot be related to itself".to_string(),
+            ));
+        }
+        for (id, other) in [(from, to), (to, from)] {
+            let related = self.related(principal, id);
+            if related.len() >= MAX_RELATIONS && !related.iter().any(|r| r.todo_id == other) {
+                return Err(Error::QuotaExceeded(Some(format!(
+                    "A Todo item can be related to at most {MAX_RELATIONS} others"
+                ))));
+            }
+        }
+        let mut store = self.store.borrow_mut();
+        store.insert(
+            (principal, from, to),
+            Relation {
+                kind,
+                outgoing: true,
+            },
+        );
+        store.insert(
+            (principal, to, from),
+            Relation {
+                kind,
+                outgoing: false,
+            },
+        );
+        Ok(())
+    }
+
+    /// Removes the relation between two Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `a` - The unique identifier for one of the Todo items.
+    /// * `b` - The unique identifier for the other Todo item.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if the Todo items are not related.
+    pub(crate) fn unlink(&self, principal: Principal, a: TodoId, b: TodoId) -> Result<(), Error> {
+        let mut store = self.store.borrow_mut();
+        store.remove(&(principal, b, a));
+        store
+            .remove(&(principal, a, b))
+            .map(|_| ())
+            .ok_or(Error::NotFound)
+    }
+
+    /// Lists the Todo items related to a Todo item, by identifier.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    pub(crate) fn related(&self, principal: Principal, id: TodoId) -> Vec<RelatedTodo> {
+        self.store
+            .borrow()
+            .range((principal, id, TodoId::MIN)..=(principal, id, TodoId::MAX))
+            .map(|((_, _, todo_id), relation)| RelatedTodo {
+                todo_id,
+                kind: relation.kind,
+                outgoing: relation.outgoing,
+            })
+            .collect()
+    }
+
+    /// Removes every relation of a Todo item that was deleted.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    pub(crate) fn remove_all(&self, principal: Principal, id: TodoId) {
+        for related in self.related(principal, id) {
+            let _ = self.unlink(principal, id, related.todo_id);
+        }
+    }
+
+    /// Moves the relations of a principal's Todo items to their new identifiers.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        rewrite_entries(
+            self.store,
+            (principal, TodoId::MIN, TodoId::MIN)..=(principal, TodoId::MAX, TodoId::MAX),
+            |(principal, from, to), relation| {
+                (
+                    (principal, renumbered(ids, from), renumbered(ids, to)),
+                    relation,
+                )
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_link_and_unlink() {
+        let store = RefCell::new(RelationStore::new(DefaultMemoryImpl::default()));
+        let wrapper = RelationStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+
+        assert!(wrapper
+            .link(principal, 2, 1, RelationKind::DuplicateOf)
+            .is_ok());
+        assert!(wrapper
+            .link(principal, 3, 1, RelationKind::FollowsFrom)
+            .is_ok());
+        assert!(matches!(
+            wrapper.link(principal, 1, 1, RelationKind::RelatesTo),
+            Err(Error::InvalidInput(_))
+        ));
+        assert_eq!(
+            wrapper.related(principal, 1),
+            vec![
+                RelatedTodo {
+                    todo_id: 2,
+                    kind: RelationKind::DuplicateOf,
+                    outgoing: false
+                },
+                RelatedTodo {
+                    todo_id: 3,
+                    kind: RelationKind::FollowsFrom,
+                    outgoing: false
+                },
+            ]
+        );
+
+        assert!(wrapper
+            .link(principal, 1, 2, RelationKind::RelatesTo)
+            .is_ok());
+        assert_eq!(
+            wrapper.related(principal, 2)[0].kind,
+            RelationKind::RelatesTo
+        );
+        assert!(!wrapper.related(principal, 2)[0].outgoing);
+
+        wrapper.renumber(principal, &Renumbering::from([(3, 4)]));
+        assert_eq!(wrapper.related(principal, 4)[0].todo_id, 1);
+
+        wrapper.remove_all(principal, 1);
+        assert!(store.borrow().is_empty());
+        assert!(matches!(
+            wrapper.unlink(principal, 1, 2),
+            Err(Error::NotFound)
+        ));
+    }
+}
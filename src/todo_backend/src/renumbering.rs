@@ -0,0 +1,104 @@
+use std::{cell::RefCell, collections::BTreeMap, ops::RangeBounds};
+
+use ic_stable_structures::{Memory, StableBTreeMap, Storable};
+
+use crate::todo::TodoId;
+
+/// Type alias for a renumbering of the Todo items of a principal, mapping old identifiers to new ones.
+pub(crate) type Renumbering = BTreeMap<TodoId, TodoId>;
+
+/// Assigns the identifiers 1, 2, 3, … to Todo items in the order of their current identifiers.
+///
+/// # Arguments
+///
+/// * `ids` - The current identifiers, in any order.
+///
+/// # Returns
+///
+/// The renumbering, only containing the identifiers that change.
+pub(crate) fn dense(mut ids: Vec<TodoId>) -> Renumbering {
+    ids.sort_unstable();
+    ids.dedup();
+    ids.into_iter()
+        .zip(1..)
+        .filter(|(old, new)| old != new)
+        .collect()
+}
+
+/// Returns the new identifier of a Todo item, which is unchanged if the renumbering does not cover it.
+///
+/// # Arguments
+///
+/// * `ids` - The renumbering.
+/// * `id` - The current identifier.
+pub(crate) fn renumbered(ids: &Renumbering, id: TodoId) -> TodoId {
+    ids.get(&id).copied().unwrap_or(id)
+}
+
+/// Rewrites every entry of a stable map within a key range. All entries are taken out before any is
+/// put back, so rewritten keys may collide with keys that are still to be rewritten.
+///
+/// # Arguments
+///
+/// * `store` - The stable map.
+/// * `range` - The keys to rewrite, such as all keys of a principal.
+/// * `rewrite` - Returns the new key and value of an entry.
+pub(crate) fn rewrite_entries<K, V, M>(
+    store: &RefCell<StableBTreeMap<K, V, M>>,
+    range: impl RangeBounds<K>,
+    rewrite: impl Fn(K, V) -> (K, V),
+) where
+    K: Storable + Ord + Clone,
+    V: Storable,
+    M: Memory,
+{
+    let mut store = store.borrow_mut();
+    let keys: Vec<K> = store.range(range).map(|(key, _)| key).collect();
+    let entries: Vec<(K, V)> = keys
+        .into_iter()
+        .filter_map(|key| Some((key.clone(), store.remove(&key)?)))
+        .collect();
+    for (key, value) in entries {
+        let (key, value) = rewrite(key, value);
+        store.insert(key, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_dense() {
+        let ids = dense(vec![9, 1, 4, 4]);
+        assert_eq!(ids, BTreeMap::from([(4, 2), (9, 3)]));
+        assert_eq!(renumbered(&ids, 9), 3);
+        assert_eq!(renumbered(&ids, 1), 1);
+    }
+
+    #[test]
+    fn test_rewrite_entries_allows_overlapping_keys() {
+        let store = RefCell::new(StableBTreeMap::<(u8, TodoId), TodoId, _>::new(
+            DefaultMemoryImpl::default(),
+        ));
+        for (id, value) in [(1, 10), (2, 20), (3, 30)] {
+            store.borrow_mut().insert((0, id), value);
+        }
+        store.borrow_mut().insert((1, 1), 40);
+        let ids = BTreeMap::from([(1, 2), (2, 3), (3, 1)]);
+
+        rewrite_entries(
+            &store,
+            (0, TodoId::MIN)..=(0, TodoId::MAX),
+            |(p, id), value| ((p, renumbered(&ids, id)), value),
+        );
+
+        let entries: Vec<_> = store.borrow().iter().collect();
+        assert_eq!(
+            entries,
+            vec![((0, 1), 30), ((0, 2), 10), ((0, 3), 20), ((1, 1), 40)]
+        );
+    }
+}
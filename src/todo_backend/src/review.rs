@@ -0,0 +1,262 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::{Memory, StableBTreeMap};
+
+use crate::{
+    email::EmailMessage,
+    messages::{Language, Message},
+    stats::{civil_from_days, local_day_of, Day, NANOS_PER_DAY},
+    todo::Todo,
+};
+
+/// Local hour of the Sunday from which the review of the ending week is sent.
+pub(crate) const REVIEW_HOUR: u64 = 18;
+
+/// Type alias for the ReviewLogStore, which is a StableBTreeMap keyed by Principal with value of the first day of the last week reviewed.
+pub(crate) type ReviewLogStore<M> = StableBTreeMap<Principal, Day, M>;
+
+/// Represents how far a project got during a week.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ProjectProgress {
+    /// The project.
+    pub(crate) project: String,
+    /// Number of Todo items of the project completed during the week.
+    pub(crate) completed: u32,
+    /// Number of Todo items of the project still open at the end of the week.
+    pub(crate) open: u32,
+}
+
+/// Represents the look back at a week of a principal's Todo items.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct WeeklyReview {
+    /// The first local day of the week, counted in days since the Unix epoch.
+    pub(crate) week_start: Day,
+    /// Todo items completed during the week.
+    pub(crate) completed: Vec<Todo>,
+    /// Todo items created during the week.
+    pub(crate) created: Vec<Todo>,
+    /// Todo items created before the week that were still open at its end.
+    pub(crate) carried_over: Vec<Todo>,
+    /// Progress of every project with Todo items completed during the week or open at its end, by name.
+    pub(crate) projects: Vec<ProjectProgress>,
+}
+
+/// Assembles the review of a week from the Todo items of a principal.
+///
+/// # Arguments
+///
+/// * `todos` - All Todo items of the principal.
+/// * `week_start` - The first local day of the week.
+/// * `utc_offset` - The offset of the principal's time zone from UTC, in nanoseconds.
+///
+/// # Returns
+///
+/// The weekly review.
+pub(crate) fn build(todos: Vec<Todo>, week_start: Day, utc_offset: i64) -> WeeklyReview {
+    let day_of = |timestamp| local_day_of(timestamp, utc_offset);
+    let week = week_start..week_start.saturating_add(7);
+    let mut review = WeeklyReview {
+        week_start,
+        completed: Vec::new(),
+        created: Vec::new(),
+        carried_over: Vec::new(),
+        projects: Vec::new(),
+    };
+    let mut projects: BTreeMap<String, ProjectProgress> = BTreeMap::new();
+    for todo in todos {
+        let created = day_of(todo.created_at);
+        if created >= week.end {
+            continue;
+        }
+        let completed = todo.completed_at.filter(|_| todo.is_completed).map(day_of);
+        let completed_in_week = completed.is_some_and(|day| week.contains(&day));
+        let open_at_end = completed.is_none_or(|day| day >= week.end);
+        if let Some(project) = &todo.project {
+            if completed_in_week || open_at_end {
+                let progress = projects
+                    .entry(project.clone())
+                    .or_insert_with(|| ProjectProgress {
+                        project: project.clone(),
+                        completed: 0,
+                        open: 0,
+                    });
+                if completed_in_week {
+                    progress.completed += 1;
+                } else {
+                    progress.open += 1;
+                }
+            }
+        }
+        if created >= week.start {
+            review.created.push(todo.clone());
+        } else if open_at_end {
+            review.carried_over.push(todo.clone());
+        }
+        if completed_in_week {
+            review.completed.push(todo);
+        }
+    }
+    review.projects = projects.into_values().collect();
+    review
+}
+
+/// Returns whether a principal's Sunday evening, when the review of the ending week is sent, has begun.
+///
+/// # Arguments
+///
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+/// * `utc_offset` - The offset of the principal's time zone from UTC, in nanoseconds.
+pub(crate) fn is_review_time(now: u64, utc_offset: i64) -> bool {
+    let local = now.saturating_add_signed(utc_offset);
+    // The Unix epoch fell on a Thursday, so day 3 was a Sunday.
+    let sunday = (local / NANOS_PER_DAY) % 7 == 3;
+    sunday && local % NANOS_PER_DAY >= REVIEW_HOUR * NANOS_PER_DAY / 24
+}
+
+/// Builds the message sending a weekly review to its principal.
+///
+/// # Arguments
+///
+/// * `review` - The weekly review.
+/// * `language` - The language of the recipient.
+///
+/// # Returns
+///
+/// The message, usable as an email or, joined, as a chat message.
+pub(crate) fn message(review: &WeeklyReview, language: Language) -> EmailMessage {
+    let (year, month, day) = civil_from_days(i64::from(review.week_start));
+    let mut text = Message::WeeklyReviewText {
+        completed: review.completed.len(),
+        created: review.created.len(),
+        carried_over: review.carried_over.len(),
+    }
+    .render(language);
+    for progress in &review.projects {
+        text.push_str(
+            &Message::ProjectProgress {
+                project: &progress.project,
+                completed: progress.completed,
+                open: progress.open,
+            }
+            .render(language),
+        );
+        text.push('\n');
+    }
+    EmailMessage {
+        subject: Message::WeeklyReviewSubject {
+            week: &format!("{year:04}-{month:02}-{day:02}"),
+        }
+        .render(language),
+        text,
+    }
+}
+
+/// Wrapper around the ReviewLogStore to provide additional functionality.
+pub(crate) struct ReviewLogStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<ReviewLogStore<M>>,
+}
+
+impl<M: Memory> ReviewLogStoreWrapper<'_, M> {
+    /// Records that the review of a week is sent to a principal, unless it already was.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `week_start` - The first local day of the reviewed week.
+    ///
+    /// # Returns
+    ///
+    /// Whether the review still had to be sent.
+    pub(crate) fn mark_sent(&self, principal: Principal, week_start: Day) -> bool {
+        let mut store = self.store.borrow_mut();
+        if store
+            .get(&principal)
+            .is_some_and(|reviewed| reviewed >= week_start)
+        {
+            return false;
+        }
+        store.insert(principal, week_start);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::Priority;
+
+    fn todo(id: u64, project: Option<&str>, created_day: u64, completed_day: Option<u64>) -> Todo {
+        let mut todo = Todo {
+            project: project.map(str::to_string),
+            created_at: created_day * NANOS_PER_DAY,
+            ..Todo::new(id, format!("Task {id}"), Priority::Medium)
+        };
+        if let Some(day) = completed_day {
+            todo.set_completed(true, day * NANOS_PER_DAY);
+        }
+        todo
+    }
+
+    fn ids(todos: &[Todo]) -> Vec<u64> {
+        todos.iter().map(|todo| todo.id).collect()
+    }
+
+    #[test]
+    fn test_build_review() {
+        let todos = vec![
+            todo(1, Some("Work"), 5, Some(11)),
+            todo(2, Some("Work"), 12, None),
+            todo(3, None, 3, None),
+            todo(4, Some("Home"), 2, Some(8)),
+            todo(5, Some("Home"), 11, Some(12)),
+            todo(6, Some("Work"), 18, None),
+            todo(7, Some("Old"), 1, Some(4)),
+            todo(8, None, 6, Some(20)),
+        ];
+
+        let review = build(todos, 10, 0);
+        assert_eq!(ids(&review.completed), vec![1, 5]);
+        assert_eq!(ids(&review.created), vec![2, 5]);
+        assert_eq!(ids(&review.carried_over), vec![3, 8]);
+        assert_eq!(
+            review.projects,
+            vec![
+                ProjectProgress {
+                    project: "Home".to_string(),
+                    completed: 1,
+                    open: 0
+                },
+                ProjectProgress {
+                    project: "Work".to_string(),
+                    completed: 1,
+                    open: 1
+                },
+            ]
+        );
+        let message = message(&review, Language::English);
+        assert_eq!(message.subject, "Weekly review: week of 1970-01-11");
+        assert!(message.text.contains("Work: 1 completed, 1 open"));
+    }
+
+    #[test]
+    fn test_review_time_and_log() {
+        let sunday = 3 * NANOS_PER_DAY;
+        assert!(!is_review_time(sunday + 17 * NANOS_PER_DAY / 24, 0));
+        assert!(is_review_time(sunday + 18 * NANOS_PER_DAY / 24, 0));
+        assert!(!is_review_time(
+            sunday + 18 * NANOS_PER_DAY / 24,
+            8 * NANOS_PER_DAY as i64 / 24
+        ));
+
+        let store = RefCell::new(ReviewLogStore::new(DefaultMemoryImpl::default()));
+        let wrapper = ReviewLogStoreWrapper { store: &store };
+        let tester = Principal::anonymous();
+        assert!(wrapper.mark_sent(tester, 10));
+        assert!(!wrapper.mark_sent(tester, 10));
+        assert!(wrapper.mark_sent(tester, 17));
+    }
+}
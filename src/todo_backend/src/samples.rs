@@ -0,0 +1,67 @@
+use crate::todo::{CreateTodo, Priority};
+
+/// Tag shared by every onboarding sample.
+const SAMPLE_TAG: &str = "getting-started";
+
+/// Returns the creation requests of the onboarding samples, which explain the main features.
+pub(crate) fn requests() -> Vec<CreateTodo> {
+    let sample = |description: &str, priority, tags: &[&str], notes: Option<&str>| CreateTodo {
+        description: description.to_string(),
+        priority: Some(priority),
+        tags: Some(
+            [SAMPLE_TAG]
+                .iter()
+                .chain(tags)
+                .map(|tag| tag.to_string())
+                .collect(),
+        ),
+        notes: notes.map(str::to_string),
+        ..Default::default()
+    };
+    vec![
+        sample(
+            "Welcome! Complete this item to tick off your first Todo",
+            Priority::High,
+            &[],
+            None,
+        ),
+        sample(
+            "Add a Todo item of your own with a priority and a due date",
+            Priority::Medium,
+            &[],
+            Some("Items with a due date show up in your daily digest and week plan."),
+        ),
+        sample(
+            "Group related items with tags like the ones on this item",
+            Priority::Low,
+            &["tags"],
+            None,
+        ),
+        sample(
+            "Pick a few items for My Day to focus on them today",
+            Priority::Medium,
+            &["planning"],
+            Some("My Day starts empty again every morning."),
+        ),
+        sample(
+            "Clear these samples once you are done exploring",
+            Priority::Low,
+            &[],
+            Some("Clearing the samples removes them all, including any you edited."),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::validation;
+
+    #[test]
+    fn test_requests_are_valid() {
+        for request in requests() {
+            assert!(validation::create_request(request).is_ok());
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    time::Duration,
+};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk_timers::TimerId;
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{errors::Error, todo::CreateTodo};
+
+/// Maximum number of Todo items a principal may have scheduled at once.
+pub(crate) const MAX_SCHEDULED: usize = 100;
+
+/// Type alias for the identifier of a scheduled creation, unique per principal.
+pub(crate) type ScheduleId = u64;
+
+/// Type alias for the ScheduleStore, which is a StableBTreeMap with a tuple key of (Principal, ScheduleId) and value of ScheduledTodo.
+pub(crate) type ScheduleStore<M> = StableBTreeMap<(Principal, ScheduleId), ScheduledTodo, M>;
+
+thread_local! {
+    /// The timer creating the next scheduled Todo item. Timers do not survive upgrades, so it is armed again afterwards.
+    static TIMER: Cell<Option<TimerId>> = const { Cell::new(None) };
+}
+
+/// Represents a Todo item that is created at a later time.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub(crate) struct ScheduledTodo {
+    /// The identifier of the scheduled creation.
+    pub(crate) id: ScheduleId,
+    /// Time the Todo item is created at, in nanoseconds since the Unix epoch.
+    pub(crate) create_at: u64,
+    /// The fields of the Todo item.
+    pub(crate) request: CreateTodo,
+}
+
+impl Storable for ScheduledTodo {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Wrapper around the ScheduleStore to provide additional functionality.
+pub(crate) struct ScheduleStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<ScheduleStore<M>>,
+}
+
+impl<M: Memory> ScheduleStoreWrapper<'_, M> {
+    /// Schedules the creation of a Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `request` - The validated fields of the Todo item.
+    /// * `create_at` - Time the Todo item is created at, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the identifier of the scheduled creation, or an Error if too many are scheduled.
+    pub(crate) fn schedule(
+        &self,
+        principal: Principal,
+        request: CreateTodo,
+        create_at: u64,
+    ) -> Result<ScheduleId, Error> {
+        if self.list(principal).len() >= MAX_SCHEDULED {
+            return Err(Error::QuotaExceeded(Some(format!(
+                "At most {MAX_SCHEDULED} Todo items can be scheduled"
+            ))));
+        }
+        let id = self
+            .store
+            .borrow()
+            .iter_upper_bound(&(principal, ScheduleId::MAX))
+            .next()
+            .filter(|((owner, _), _)| *owner == principal)
+            .map_or(1, |((_, id), _)| id + 1);
+        let scheduled = ScheduledTodo {
+            id,
+            create_at,
+            request,
+        };
+        self.store.borrow_mut().insert((principal, id), scheduled);
+        Ok(id)
+    }
+
+    /// Lists the scheduled creations of a principal, earliest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn list(&self, principal: Principal) -> Vec<ScheduledTodo> {
+        let mut scheduled: Vec<ScheduledTodo> = self
+            .store
+            .borrow()
+            .range((principal, ScheduleId::MIN)..=(principal, ScheduleId::MAX))
+            .map(|(_, scheduled)| scheduled)
+            .collect();
+        scheduled.sort_by_key(|scheduled| (scheduled.create_at, scheduled.id));
+        scheduled
+    }
+
+    /// Cancels a scheduled creation.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The identifier of the scheduled creation.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if the scheduled creation is not found.
+    pub(crate) fn cancel(&self, principal: Principal, id: ScheduleId) -> Result<(), Error> {
+        self.store
+            .borrow_mut()
+            .remove(&(principal, id))
+            .map(|_| ())
+            .ok_or(Error::NotFound)
+    }
+
+    /// Returns the earliest time any scheduled Todo item is created at.
+    pub(crate) fn next_at(&self) -> Option<u64> {
+        self.store
+            .borrow()
+            .iter()
+            .map(|(_, scheduled)| scheduled.create_at)
+            .min()
+    }
+
+    /// Removes every scheduled creation that is due.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// The principals and requests of the due Todo items, earliest first.
+    pub(crate) fn take_due(&self, now: u64) -> Vec<(Principal, CreateTodo)> {
+        let mut due: Vec<_> = self
+            .store
+            .borrow()
+            .iter()
+            .filter(|(_, scheduled)| scheduled.create_at <= now)
+            .collect();
+        due.sort_by_key(|(_, scheduled)| scheduled.create_at);
+        let mut store = self.store.borrow_mut();
+        due.into_iter()
+            .map(|(key, scheduled)| {
+                store.remove(&key);
+                (key.0, scheduled.request)
+            })
+            .collect()
+    }
+}
+
+/// Arms the timer creating the next scheduled Todo item, replacing any armed timer.
+///
+/// # Arguments
+///
+/// * `next_at` - The earliest time a scheduled Todo item is created at, or None if nothing is scheduled.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+/// * `callback` - The job creating the due Todo items.
+pub(crate) fn arm_timer(next_at: Option<u64>, now: u64, callback: fn()) {
+    let timer = next_at.map(|next_at| {
+        let delay = Duration::from_nanos(next_at.saturating_sub(now));
+        ic_cdk_timers::set_timer(delay, callback)
+    });
+    if let Some(previous) = TIMER.with(|armed| armed.replace(timer)) {
+        ic_cdk_timers::clear_timer(previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    fn request(description: &str) -> CreateTodo {
+        CreateTodo {
+            description: description.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_schedule_and_take_due() {
+        let store = RefCell::new(ScheduleStore::new(DefaultMemoryImpl::default()));
+        let wrapper = ScheduleStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let other = Principal::management_canister();
+
+        let schedule = |principal, description, create_at| {
+            wrapper
+                .schedule(principal, request(description), create_at)
+                .unwrap()
+        };
+        assert_eq!(schedule(principal, "Later", 30), 1);
+        assert_eq!(schedule(principal, "Sooner", 10), 2);
+        assert_eq!(schedule(other, "Other", 20), 1);
+        assert_eq!(schedule(principal, "Never", 5), 3);
+        assert!(wrapper.cancel(principal, 3).is_ok());
+        assert!(matches!(wrapper.cancel(principal, 3), Err(Error::NotFound)));
+        let ids: Vec<_> = wrapper.list(principal).iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![2, 1]);
+        assert_eq!(wrapper.next_at(), Some(10));
+
+        let due: Vec<_> = wrapper
+            .take_due(20)
+            .into_iter()
+            .map(|(owner, request)| (owner, request.description))
+            .collect();
+        assert_eq!(
+            due,
+            vec![
+                (principal, "Sooner".to_string()),
+                (other, "Other".to_string())
+            ]
+        );
+        assert_eq!(wrapper.next_at(), Some(30));
+    }
+}
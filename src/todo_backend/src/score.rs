@@ -0,0 +1,303 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{
+    paginator::Paginator,
+    stats::{day_of, Day},
+    store::TodoChange,
+    todo::{Priority, Todo},
+};
+
+/// Number of points needed to advance one level.
+const POINTS_PER_LEVEL: u64 = 100;
+
+/// Type alias for the ScoreStore, which is a StableBTreeMap keyed by Principal with a value of Score.
+pub(crate) type ScoreStore<M> = StableBTreeMap<Principal, Score, M>;
+
+/// Represents the points and completion streak of a principal.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Score {
+    /// Total number of points earned.
+    pub(crate) points: u64,
+    /// Number of consecutive days with a completion, ending on `last_completion_day`.
+    pub(crate) streak: u32,
+    /// Longest streak ever reached.
+    pub(crate) longest_streak: u32,
+    /// The last day with a completion, counted in days since the Unix epoch.
+    pub(crate) last_completion_day: Option<Day>,
+    /// Whether the principal is listed on the leaderboard.
+    pub(crate) on_leaderboard: bool,
+}
+
+impl Storable for Score {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl Score {
+    /// Returns the level reached with the earned points, starting at 1.
+    pub(crate) fn level(&self) -> u32 {
+        (self.points / POINTS_PER_LEVEL) as u32 + 1
+    }
+
+    /// Returns the streak as seen on a given day; a streak is lost once a full day passes without a completion.
+    ///
+    /// # Arguments
+    ///
+    /// * `today` - The current day, counted in days since the Unix epoch.
+    pub(crate) fn current_streak(&self, today: Day) -> u32 {
+        match self.last_completion_day {
+            Some(day) if day + 1 >= today => self.streak,
+            _ => 0,
+        }
+    }
+}
+
+/// Represents the score of the caller as reported to them.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct MyScore {
+    /// Total number of points earned.
+    pub(crate) points: u64,
+    /// The level reached, starting at 1.
+    pub(crate) level: u32,
+    /// Number of consecutive days with a completion up to today or yesterday.
+    pub(crate) current_streak: u32,
+    /// Longest streak ever reached.
+    pub(crate) longest_streak: u32,
+    /// Whether the caller is listed on the leaderboard.
+    pub(crate) on_leaderboard: bool,
+}
+
+/// Represents a single entry of the leaderboard.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct LeaderboardEntry {
+    /// The principal identifier.
+    pub(crate) principal: Principal,
+    /// Total number of points earned.
+    pub(crate) points: u64,
+    /// The level reached, starting at 1.
+    pub(crate) level: u32,
+}
+
+/// Returns the points awarded for completing a Todo item.
+/// Higher priorities are worth more, and completing a Todo item by its due date earns half as much again.
+///
+/// # Arguments
+///
+/// * `todo` - The completed Todo item.
+fn points_for(todo: &Todo) -> u64 {
+    let points = match todo.priority {
+        Priority::Low => 5,
+        Priority::Medium => 10,
+        Priority::High => 20,
+    };
+    match (todo.due_date, todo.completed_at) {
+        (Some(due_date), Some(completed_at)) if completed_at <= due_date => points + points / 2,
+        _ => points,
+    }
+}
+
+/// Wrapper around the ScoreStore to provide additional functionality.
+pub(crate) struct ScoreStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<ScoreStore<M>>,
+}
+
+impl<M: Memory> ScoreStoreWrapper<'_, M> {
+    /// Awards points and extends the streak when a Todo item is completed, and
+    /// withdraws the points again when it is reopened.
+    ///
+    /// # Arguments
+    ///
+    /// * `change` - The change to a Todo item.
+    pub(crate) fn record_change(&self, change: &TodoChange) {
+        let (Some(before), Some(after)) = (&change.before, &change.after) else {
+            return;
+        };
+        let mut score = self.get(change.principal);
+        match (before.completed_at, after.completed_at) {
+            (None, Some(completed_at)) => {
+                score.points += points_for(after);
+                let day = day_of(completed_at);
+                score.streak = match score.last_completion_day {
+                    Some(last) if last == day => score.streak,
+                    Some(last) if last + 1 == day => score.streak + 1,
+                    _ => 1,
+                };
+                score.longest_streak = score.longest_streak.max(score.streak);
+                score.last_completion_day = Some(day);
+            }
+            (Some(_), None) => score.points = score.points.saturating_sub(points_for(before)),
+            _ => return,
+        }
+        self.store.borrow_mut().insert(change.principal, score);
+    }
+
+    /// Retrieves the score of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    ///
+    /// # Returns
+    ///
+    /// The score, or an empty score if the principal never completed a Todo item.
+    pub(crate) fn get(&self, principal: Principal) -> Score {
+        self.store.borrow().get(&principal).unwrap_or_default()
+    }
+
+    /// Sets whether a principal is listed on the leaderboard.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `on_leaderboard` - Whether the principal is listed.
+    pub(crate) fn set_on_leaderboard(&self, principal: Principal, on_leaderboard: bool) {
+        let score = Score {
+            on_leaderboard,
+            ..self.get(principal)
+        };
+        self.store.borrow_mut().insert(principal, score);
+    }
+
+    /// Lists the principals that opted in to the leaderboard, highest points first.
+    ///
+    /// # Arguments
+    ///
+    /// * `paginator` - The paginator for controlling the list output.
+    ///
+    /// # Returns
+    ///
+    /// A vector of leaderboard entries.
+    pub(crate) fn leaderboard(&self, paginator: Paginator) -> Vec<LeaderboardEntry> {
+        let mut entries: Vec<LeaderboardEntry> = self
+            .store
+            .borrow()
+            .iter()
+            .filter(|(_, score)| score.on_leaderboard)
+            .map(|(principal, score)| LeaderboardEntry {
+                principal,
+                points: score.points,
+                level: score.level(),
+            })
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.points));
+        entries
+            .into_iter()
+            .skip(paginator.skip())
+            .take(paginator.limit())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::stats::NANOS_PER_DAY;
+
+    fn complete(
+        wrapper: &ScoreStoreWrapper<DefaultMemoryImpl>,
+        principal: Principal,
+        todo: &Todo,
+        now: u64,
+    ) {
+        let mut after = todo.clone();
+        after.set_completed(true, now);
+        wrapper.record_change(&TodoChange {
+            principal,
+            before: Some(todo.clone()),
+            after: Some(after),
+        });
+    }
+
+    #[test]
+    fn test_awards_points_and_tracks_streaks() {
+        let store = RefCell::new(ScoreStore::new(DefaultMemoryImpl::default()));
+        let wrapper = ScoreStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let high = Todo::new(1, "Ship release".to_string(), Priority::High);
+        let on_time = Todo {
+            due_date: Some(NANOS_PER_DAY),
+            ..Todo::new(2, "Pay rent".to_string(), Priority::Medium)
+        };
+
+        complete(&wrapper, principal, &high, 0);
+        complete(&wrapper, principal, &on_time, NANOS_PER_DAY);
+        complete(&wrapper, principal, &high, 3 * NANOS_PER_DAY);
+
+        let score = wrapper.get(principal);
+        assert_eq!(score.points, 20 + 15 + 20);
+        assert_eq!(score.streak, 1);
+        assert_eq!(score.longest_streak, 2);
+        assert_eq!(score.current_streak(4), 1);
+        assert_eq!(score.current_streak(5), 0);
+    }
+
+    #[test]
+    fn test_reopening_withdraws_points() {
+        let store = RefCell::new(ScoreStore::new(DefaultMemoryImpl::default()));
+        let wrapper = ScoreStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let todo = Todo::new(1, "Ship release".to_string(), Priority::High);
+        let mut completed = todo.clone();
+        completed.set_completed(true, 0);
+
+        complete(&wrapper, principal, &todo, 0);
+        wrapper.record_change(&TodoChange {
+            principal,
+            before: Some(completed),
+            after: Some(todo),
+        });
+
+        assert_eq!(wrapper.get(principal).points, 0);
+    }
+
+    #[test]
+    fn test_leaderboard_lists_opted_in_principals() {
+        let store = RefCell::new(ScoreStore::new(DefaultMemoryImpl::default()));
+        let wrapper = ScoreStoreWrapper { store: &store };
+        let (first, second, hidden) = (
+            Principal::anonymous(),
+            Principal::management_canister(),
+            Principal::from_slice(&[1]),
+        );
+        complete(
+            &wrapper,
+            first,
+            &Todo::new(1, "Low".to_string(), Priority::Low),
+            0,
+        );
+        complete(
+            &wrapper,
+            second,
+            &Todo::new(2, "High".to_string(), Priority::High),
+            0,
+        );
+        complete(
+            &wrapper,
+            hidden,
+            &Todo::new(3, "High".to_string(), Priority::High),
+            0,
+        );
+        wrapper.set_on_leaderboard(first, true);
+        wrapper.set_on_leaderboard(second, true);
+
+        let principals: Vec<Principal> = wrapper
+            .leaderboard(Paginator::default())
+            .iter()
+            .map(|entry| entry.principal)
+            .collect();
+        assert_eq!(principals, vec![second, first]);
+    }
+}
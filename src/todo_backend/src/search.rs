@@ -0,0 +1,67 @@
+use candid::{CandidType, Deserialize, Principal};
+
+use crate::{store::TodoFilter, todo::Todo};
+
+/// Represents a search for Todo items by the words they contain.
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub(crate) struct TodoSearch {
+    /// Words the description or notes of a Todo item must all contain, ignoring case.
+    pub(crate) text: String,
+    /// Optional further conditions the Todo items must meet.
+    pub(crate) filter: Option<TodoFilter>,
+    /// Whether the accounts shared with the caller, including their projects, are searched too;
+    /// only the caller's own Todo items if None.
+    pub(crate) include_shared: Option<bool>,
+}
+
+/// Represents a Todo item found by a search, with the account it belongs to.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct SearchHit {
+    /// The principal owning the Todo item; the caller for their own Todo items.
+    pub(crate) owner: Principal,
+    /// The Todo item.
+    pub(crate) todo: Todo,
+}
+
+/// Splits the text of a search into the lowercase words a Todo item must contain.
+///
+/// # Arguments
+///
+/// * `text` - The text of the search.
+pub(crate) fn words(text: &str) -> Vec<String> {
+    text.split_whitespace().map(str::to_lowercase).collect()
+}
+
+/// Returns whether the description or notes of a Todo item contain every word of a search, ignoring case.
+///
+/// # Arguments
+///
+/// * `todo` - The Todo item.
+/// * `words` - The lowercase words of the search.
+pub(crate) fn matches(todo: &Todo, words: &[String]) -> bool {
+    let description = todo.description.to_lowercase();
+    let notes = todo.notes.as_deref().unwrap_or_default().to_lowercase();
+    words
+        .iter()
+        .all(|word| description.contains(word.as_str()) || notes.contains(word.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::todo::Priority;
+
+    #[test]
+    fn test_matches_every_word() {
+        let todo = Todo {
+            notes: Some("Ask Sam about the Q3 numbers".to_string()),
+            ..Todo::new(1, "Write quarterly REPORT".to_string(), Priority::Low)
+        };
+
+        assert!(matches(&todo, &words("report")));
+        assert!(matches(&todo, &words("  Quarterly  q3 ")));
+        assert!(!matches(&todo, &words("report budget")));
+        assert!(matches(&todo, &[]));
+    }
+}
@@ -0,0 +1,149 @@
+use candid::Principal;
+
+use crate::{
+    errors::Error,
+    stats::NANOS_PER_DAY,
+    todo::{CreateTodo, Priority},
+};
+
+/// Maximum number of Todo items generated by a single seeding call, to stay within the instruction limit.
+const MAX_SEED_TODOS: u64 = 10_000;
+
+/// Prefix of the generated principals, which keeps them apart from real ones.
+const PRINCIPAL_PREFIX: &[u8] = b"seed";
+
+/// Verbs the generated descriptions start with.
+const VERBS: [&str; 8] = [
+    "Buy", "Call", "Email", "Fix", "Plan", "Review", "Schedule", "Write",
+];
+
+/// Objects the generated descriptions end with.
+const OBJECTS: [&str; 8] = [
+    "groceries",
+    "the landlord",
+    "the quarterly report",
+    "the bike",
+    "a birthday party",
+    "pull requests",
+    "a dentist appointment",
+    "release notes",
+];
+
+/// Tags assigned to the generated Todo items.
+const TAGS: [&str; 5] = ["home", "work", "errands", "health", "finance"];
+
+/// Projects assigned to the generated Todo items.
+const PROJECTS: [&str; 3] = ["Household", "Launch", "Garden"];
+
+/// Represents a generated Todo item.
+#[derive(Clone, Debug)]
+pub(crate) struct FakeTodo {
+    /// The fields of the Todo item.
+    pub(crate) request: CreateTodo,
+    /// Whether the Todo item is completed after creation.
+    pub(crate) completed: bool,
+}
+
+/// Checks that a seeding call stays within the allowed size.
+///
+/// # Arguments
+///
+/// * `users` - The number of principals to generate.
+/// * `todos_per_user` - The number of Todo items to generate for each principal.
+///
+/// # Returns
+///
+/// A Result indicating success or an InvalidInput Error if too many Todo items are requested.
+pub(crate) fn check_size(users: u32, todos_per_user: u32) -> Result<(), Error> {
+    if u64::from(users) * u64::from(todos_per_user) > MAX_SEED_TODOS {
+        return Err(Error::InvalidInput(format!(
+            "Cannot seed more than {MAX_SEED_TODOS} Todo items at once"
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the generated principal with the given index.
+///
+/// # Arguments
+///
+/// * `user` - The index of the principal.
+pub(crate) fn fake_principal(user: u32) -> Principal {
+    let mut bytes = PRINCIPAL_PREFIX.to_vec();
+    bytes.extend(user.to_be_bytes());
+    Principal::from_slice(&bytes)
+}
+
+/// Generates the Todo item with the given index of a generated principal.
+/// The same indices always yield the same fields, apart from dates relative to `now`.
+///
+/// # Arguments
+///
+/// * `user` - The index of the principal.
+/// * `index` - The index of the Todo item.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+pub(crate) fn fake_todo(user: u32, index: u32, now: u64) -> FakeTodo {
+    let seed = mix((u64::from(user) << 32) | u64::from(index));
+    let pick = |shift: u32, len: usize| (seed >> shift) as usize % len;
+    let priority = [Priority::Low, Priority::Medium, Priority::High][pick(0, 3)];
+    let tags = (0..pick(8, 3))
+        .map(|offset| TAGS[(pick(12, TAGS.len()) + offset) % TAGS.len()].to_string())
+        .collect();
+    let due_date = match pick(16, 4) {
+        0 => None,
+        _ => Some((now + pick(20, 60) as u64 * NANOS_PER_DAY).saturating_sub(30 * NANOS_PER_DAY)),
+    };
+    let project = match pick(28, 6) {
+        project if project < PROJECTS.len() => Some(PROJECTS[project].to_string()),
+        _ => None,
+    };
+    FakeTodo {
+        request: CreateTodo {
+            description: format!(
+                "{} {} #{index}",
+                VERBS[pick(32, VERBS.len())],
+                OBJECTS[pick(40, OBJECTS.len())]
+            ),
+            priority: Some(priority),
+            tags: Some(tags),
+            due_date,
+            project,
+            notes: None,
+            recurrence: None,
+        },
+        completed: pick(48, 4) == 0,
+    }
+}
+
+/// Scrambles the bits of a number, so that consecutive indices yield unrelated Todo items.
+fn mix(value: u64) -> u64 {
+    let mut value = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::validation;
+
+    #[test]
+    fn test_generates_deterministic_valid_todos() {
+        for index in 0..100 {
+            let first = fake_todo(1, index, NANOS_PER_DAY * 100);
+            let second = fake_todo(1, index, NANOS_PER_DAY * 100);
+            assert_eq!(first.request.description, second.request.description);
+            assert_eq!(first.request.tags, second.request.tags);
+            assert!(validation::create_request(first.request).is_ok());
+        }
+        assert_ne!(fake_principal(1), fake_principal(2));
+    }
+
+    #[test]
+    fn test_check_size() {
+        assert!(check_size(10, 1_000).is_ok());
+        assert!(matches!(check_size(10, 1_001), Err(Error::InvalidInput(_))));
+    }
+}
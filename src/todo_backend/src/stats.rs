@@ -0,0 +1,642 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{errors::Error, store::TodoChange, todo::Todo};
+
+/// Number of nanoseconds in a minute.
+pub(crate) const NANOS_PER_MINUTE: u64 = 60 * 1_000_000_000;
+
+/// Number of nanoseconds in a day.
+pub(crate) const NANOS_PER_DAY: u64 = 1_440 * NANOS_PER_MINUTE;
+
+/// Number of days covered by the statistics if no range is given.
+const DEFAULT_RANGE_DAYS: u32 = 30;
+
+/// Maximum number of days covered by a single statistics request.
+const MAX_RANGE_DAYS: u32 = 366;
+
+/// Type alias for a day, counted in days since the Unix epoch.
+pub(crate) type Day = u32;
+
+/// Type alias for the StatsStore, which is a StableBTreeMap with a tuple key of (Principal, Day) and value of DailyCounts.
+pub(crate) type StatsStore<M> = StableBTreeMap<(Principal, Day), DailyCounts, M>;
+
+/// Returns the day a timestamp falls on.
+///
+/// # Arguments
+///
+/// * `timestamp` - The timestamp, in nanoseconds since the Unix epoch.
+pub(crate) fn day_of(timestamp: u64) -> Day {
+    (timestamp / NANOS_PER_DAY) as Day
+}
+
+/// Returns the local day a timestamp falls on in a time zone.
+///
+/// # Arguments
+///
+/// * `timestamp` - The timestamp, in nanoseconds since the Unix epoch.
+/// * `utc_offset` - The offset of the time zone from UTC, in nanoseconds.
+pub(crate) fn local_day_of(timestamp: u64, utc_offset: i64) -> Day {
+    day_of(timestamp.saturating_add_signed(utc_offset))
+}
+
+/// Returns the time the local day a timestamp falls on started at in a time zone.
+///
+/// # Arguments
+///
+/// * `timestamp` - The timestamp, in nanoseconds since the Unix epoch.
+/// * `utc_offset` - The offset of the time zone from UTC, in nanoseconds.
+///
+/// # Returns
+///
+/// The local midnight, in nanoseconds since the Unix epoch.
+pub(crate) fn local_day_start(timestamp: u64, utc_offset: i64) -> u64 {
+    (u64::from(local_day_of(timestamp, utc_offset)) * NANOS_PER_DAY).saturating_add_signed(-utc_offset)
+}
+
+/// Returns the day of a calendar date in the proleptic Gregorian calendar.
+///
+/// # Arguments
+///
+/// * `year` - The year.
+/// * `month` - The month, from 1 to 12.
+/// * `day` - The day of the month, from 1 to 31.
+///
+/// # Returns
+///
+/// The number of days since the Unix epoch.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns the calendar date of a day in the proleptic Gregorian calendar.
+///
+/// # Arguments
+///
+/// * `days` - The number of days since the Unix epoch.
+///
+/// # Returns
+///
+/// The `(year, month, day)` of the date.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Represents the aggregated activity of a principal on a single day.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct DailyCounts {
+    /// Number of Todo items created.
+    pub(crate) created: u32,
+    /// Number of Todo items completed.
+    pub(crate) completed: u32,
+    /// Sum of the time between creation and completion of the completed items, in nanoseconds.
+    pub(crate) completion_latency_total: u64,
+    /// Number of completed items with a known creation time.
+    pub(crate) completion_latency_samples: u32,
+    /// Number of unfinished Todo items moved on to the next day; None in counts recorded before rollovers existed.
+    pub(crate) rolled_over: Option<u32>,
+}
+
+impl Storable for DailyCounts {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Represents the time range covered by a statistics request.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug)]
+pub(crate) struct StatsRange {
+    /// Start of the range, in nanoseconds since the Unix epoch.
+    pub(crate) from: u64,
+    /// End of the range (inclusive), in nanoseconds since the Unix epoch.
+    pub(crate) to: u64,
+}
+
+impl StatsRange {
+    /// Resolves an optional range into an inclusive range of days.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The requested range, defaulting to the last `DEFAULT_RANGE_DAYS` days.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the first and last day, or an Error if the range is invalid or too long.
+    pub(crate) fn days(range: Option<StatsRange>, now: u64) -> Result<(Day, Day), Error> {
+        let (from, to) = match range {
+            Some(range) => (day_of(range.from), day_of(range.to)),
+            None => {
+                let today = day_of(now);
+                (today.saturating_sub(DEFAULT_RANGE_DAYS - 1), today)
+            }
+        };
+        if from > to {
+            return Err(Error::InvalidInput(
+                "Range start must not be after its end".to_string(),
+            ));
+        }
+        if to - from >= MAX_RANGE_DAYS {
+            return Err(Error::InvalidInput(format!(
+                "Range cannot span more than {MAX_RANGE_DAYS} days"
+            )));
+        }
+        Ok((from, to))
+    }
+}
+
+/// Represents the activity of a principal on a single day.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct DayStats {
+    /// The day, counted in days since the Unix epoch.
+    pub(crate) day: Day,
+    /// Number of Todo items created.
+    pub(crate) created: u32,
+    /// Number of Todo items completed.
+    pub(crate) completed: u32,
+    /// Number of unfinished Todo items moved on to the next day.
+    pub(crate) rolled_over: u32,
+}
+
+/// Represents how often a tag was used.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct TagCount {
+    /// The tag.
+    pub(crate) tag: String,
+    /// Number of Todo items carrying the tag.
+    pub(crate) count: u32,
+}
+
+/// Represents the productivity statistics of a principal over a range of days.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct MyStats {
+    /// Activity per day, only including days with any activity.
+    pub(crate) days: Vec<DayStats>,
+    /// Number of consecutive days, up to today, on which at least one Todo item was completed.
+    pub(crate) current_streak: u32,
+    /// Average time between creation and completion, in nanoseconds.
+    pub(crate) average_completion_latency: Option<u64>,
+    /// Most used tags among the Todo items created in the range.
+    pub(crate) busiest_tags: Vec<TagCount>,
+}
+
+/// Represents the number of completions on each day of a year.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct CompletionHeatmap {
+    /// The year covered by the heatmap.
+    pub(crate) year: u16,
+    /// The day of January 1st, counted in days since the Unix epoch.
+    pub(crate) first_day: Day,
+    /// Number of completions on each day of the year, starting on January 1st.
+    pub(crate) counts: Vec<u32>,
+}
+
+/// Wrapper around the StatsStore to provide additional functionality.
+pub(crate) struct StatsStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<StatsStore<M>>,
+}
+
+impl<M: Memory> StatsStoreWrapper<'_, M> {
+    /// Updates the daily counts affected by a change to a Todo item.
+    ///
+    /// Removing a Todo item keeps its history; reopening a completed item withdraws its completion.
+    ///
+    /// # Arguments
+    ///
+    /// * `change` - The change to a Todo item.
+    pub(crate) fn record_change(&self, change: &TodoChange) {
+        let (before, after) = (change.before.as_ref(), change.after.as_ref());
+        if let (None, Some(todo)) = (before, after) {
+            self.update(change.principal, day_of(todo.created_at), |counts| {
+                counts.created += 1
+            });
+        }
+        let completed_at = |todo: Option<&Todo>| todo.and_then(|todo| todo.completed_at);
+        match (completed_at(before), completed_at(after)) {
+            (None, Some(completed_at)) => {
+                let created_at = after.map(|todo| todo.created_at).unwrap_or_default();
+                self.update(change.principal, day_of(completed_at), |counts| {
+                    counts.completed += 1;
+                    if created_at > 0 {
+                        counts.completion_latency_total += completed_at.saturating_sub(created_at);
+                        counts.completion_latency_samples += 1;
+                    }
+                });
+            }
+            (Some(completed_at), None) if after.is_some() => {
+                let created_at = before.map(|todo| todo.created_at).unwrap_or_default();
+                self.update(change.principal, day_of(completed_at), |counts| {
+                    counts.completed = counts.completed.saturating_sub(1);
+                    if created_at > 0 {
+                        counts.completion_latency_total = counts
+                            .completion_latency_total
+                            .saturating_sub(completed_at.saturating_sub(created_at));
+                        counts.completion_latency_samples =
+                            counts.completion_latency_samples.saturating_sub(1);
+                    }
+                });
+            }
+            _ => {}
+        }
+        if let (Some(before), Some(todo)) = (before, after) {
+            if todo.rollover_count > before.rollover_count {
+                self.update(change.principal, day_of(todo.updated_at), |counts| {
+                    let rolled_over = counts.rolled_over.unwrap_or_default();
+                    counts.rolled_over = Some(rolled_over + todo.rollover_count - before.rollover_count);
+                });
+            }
+        }
+    }
+
+    /// Lists the daily counts of a principal within a range of days.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `from` - The first day of the range.
+    /// * `to` - The last day of the range (inclusive).
+    ///
+    /// # Returns
+    ///
+    /// A vector of days with activity, paired with their counts.
+    pub(crate) fn daily_counts(
+        &self,
+        principal: Principal,
+        from: Day,
+        to: Day,
+    ) -> Vec<(Day, DailyCounts)> {
+        self.store
+            .borrow()
+            .range((principal, from)..=(principal, to))
+            .map(|((_, day), counts)| (day, counts))
+            .collect()
+    }
+
+    /// Builds the completion heatmap of a principal for a calendar year.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `year` - The calendar year, not earlier than 1970.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the heatmap, or an Error if the year is out of range.
+    pub(crate) fn completion_heatmap(
+        &self,
+        principal: Principal,
+        year: u16,
+    ) -> Result<CompletionHeatmap, Error> {
+        if !(1970..=9999).contains(&year) {
+            return Err(Error::InvalidInput(
+                "Year must be between 1970 and 9999".to_string(),
+            ));
+        }
+        let first_day = days_from_civil(i64::from(year), 1, 1) as Day;
+        let last_day = days_from_civil(i64::from(year) + 1, 1, 1) as Day - 1;
+        let mut counts = vec![0; (last_day - first_day + 1) as usize];
+        for (day, daily) in self.daily_counts(principal, first_day, last_day) {
+            counts[(day - first_day) as usize] = daily.completed;
+        }
+        Ok(CompletionHeatmap {
+            year,
+            first_day,
+            counts,
+        })
+    }
+
+    /// Computes the number of consecutive days with at least one completion, ending today.
+    ///
+    /// A streak that ended yesterday is still current, since today may not be over yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `today` - The current day.
+    ///
+    /// # Returns
+    ///
+    /// The length of the current streak in days.
+    pub(crate) fn current_streak(&self, principal: Principal, today: Day) -> u32 {
+        let store = self.store.borrow();
+        let has_completion = |day: Day| {
+            store
+                .get(&(principal, day))
+                .is_some_and(|counts| counts.completed > 0)
+        };
+        let mut day = if has_completion(today) {
+            today
+        } else {
+            today.saturating_sub(1)
+        };
+        let mut streak = 0;
+        while has_completion(day) {
+            streak += 1;
+            if day == 0 {
+                break;
+            }
+            day -= 1;
+        }
+        streak
+    }
+
+    /// Applies an update to the counts of a principal on a day.
+    fn update(&self, principal: Principal, day: Day, update: impl FnOnce(&mut DailyCounts)) {
+        let mut store = self.store.borrow_mut();
+        let mut counts = store.get(&(principal, day)).unwrap_or_default();
+        update(&mut counts);
+        store.insert((principal, day), counts);
+    }
+}
+
+/// Assembles the statistics of a principal from its daily counts.
+///
+/// # Arguments
+///
+/// * `days` - The days with activity, paired with their counts.
+/// * `current_streak` - The length of the current completion streak.
+/// * `busiest_tags` - The most used tags.
+///
+/// # Returns
+///
+/// The statistics of the principal.
+pub(crate) fn summarize(
+    days: Vec<(Day, DailyCounts)>,
+    current_streak: u32,
+    busiest_tags: Vec<TagCount>,
+) -> MyStats {
+    let (latency_total, latency_samples) =
+        days.iter()
+            .fold((0u64, 0u64), |(total, samples), (_, counts)| {
+                (
+                    total + counts.completion_latency_total,
+                    samples + u64::from(counts.completion_latency_samples),
+                )
+            });
+    MyStats {
+        days: days
+            .into_iter()
+            .map(|(day, counts)| DayStats {
+                day,
+                created: counts.created,
+                completed: counts.completed,
+                rolled_over: counts.rolled_over.unwrap_or_default(),
+            })
+            .collect(),
+        current_streak,
+        average_completion_latency: latency_total.checked_div(latency_samples),
+        busiest_tags,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::Priority;
+
+    fn todo(created_at: u64, completed_at: Option<u64>) -> Todo {
+        Todo {
+            created_at,
+            is_completed: completed_at.is_some(),
+            completed_at,
+            ..Todo::new(1, "Test Todo".to_string(), Priority::Medium)
+        }
+    }
+
+    #[test]
+    fn test_record_change() {
+        let store = RefCell::new(StatsStore::new(DefaultMemoryImpl::default()));
+        let wrapper = StatsStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let created_at = 10 * NANOS_PER_DAY;
+        let completed_at = 12 * NANOS_PER_DAY;
+
+        wrapper.record_change(&TodoChange {
+            principal,
+            before: None,
+            after: Some(todo(created_at, None)),
+        });
+        wrapper.record_change(&TodoChange {
+            principal,
+            before: Some(todo(created_at, None)),
+            after: Some(todo(created_at, Some(completed_at))),
+        });
+
+        let days = wrapper.daily_counts(principal, 10, 12);
+        assert_eq!(days.len(), 2);
+        assert_eq!(
+            days[0],
+            (
+                10,
+                DailyCounts {
+                    created: 1,
+                    ..Default::default()
+                }
+            )
+        );
+        assert_eq!(
+            days[1],
+            (
+                12,
+                DailyCounts {
+                    completed: 1,
+                    completion_latency_total: 2 * NANOS_PER_DAY,
+                    completion_latency_samples: 1,
+                    ..Default::default()
+                }
+            )
+        );
+
+        wrapper.record_change(&TodoChange {
+            principal,
+            before: Some(todo(created_at, Some(completed_at))),
+            after: Some(todo(created_at, None)),
+        });
+        assert_eq!(
+            wrapper.daily_counts(principal, 12, 12)[0].1,
+            DailyCounts::default()
+        );
+
+        let rolled_over = Todo {
+            rollover_count: 1,
+            updated_at: 13 * NANOS_PER_DAY,
+            ..todo(created_at, None)
+        };
+        wrapper.record_change(&TodoChange {
+            principal,
+            before: Some(todo(created_at, None)),
+            after: Some(rolled_over),
+        });
+        assert_eq!(wrapper.daily_counts(principal, 13, 13)[0].1.rolled_over, Some(1));
+    }
+
+    #[test]
+    fn test_daily_counts_decode_without_rollovers() {
+        #[derive(CandidType)]
+        struct Recorded {
+            created: u32,
+            completed: u32,
+            completion_latency_total: u64,
+            completion_latency_samples: u32,
+        }
+        let recorded = Recorded {
+            created: 1,
+            completed: 2,
+            completion_latency_total: 3,
+            completion_latency_samples: 2,
+        };
+        let counts = DailyCounts::from_bytes(Cow::Owned(Encode!(&recorded).unwrap()));
+        assert_eq!(counts.completed, 2);
+        assert_eq!(counts.rolled_over, None);
+    }
+
+    #[test]
+    fn test_current_streak() {
+        let store = RefCell::new(StatsStore::new(DefaultMemoryImpl::default()));
+        let wrapper = StatsStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        for day in [3, 5, 6, 7] {
+            let counts = DailyCounts {
+                completed: 1,
+                ..Default::default()
+            };
+            store.borrow_mut().insert((principal, day), counts);
+        }
+
+        assert_eq!(wrapper.current_streak(principal, 7), 3);
+        assert_eq!(wrapper.current_streak(principal, 8), 3);
+        assert_eq!(wrapper.current_streak(principal, 9), 0);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let days = vec![
+            (
+                1,
+                DailyCounts {
+                    created: 2,
+                    completed: 1,
+                    completion_latency_total: 10,
+                    completion_latency_samples: 1,
+                    rolled_over: None,
+                },
+            ),
+            (
+                2,
+                DailyCounts {
+                    created: 0,
+                    completed: 1,
+                    completion_latency_total: 30,
+                    completion_latency_samples: 1,
+                    rolled_over: Some(1),
+                },
+            ),
+        ];
+        let stats = summarize(days, 2, vec![]);
+        assert_eq!(
+            stats.days[0],
+            DayStats {
+                day: 1,
+                created: 2,
+                completed: 1,
+                rolled_over: 0
+            }
+        );
+        assert_eq!(stats.average_completion_latency, Some(20));
+        assert_eq!(stats.current_streak, 2);
+    }
+
+    #[test]
+    fn test_local_day_start() {
+        let utc_offset = 60 * NANOS_PER_MINUTE as i64;
+        assert_eq!(local_day_start(NANOS_PER_DAY + 1, 0), NANOS_PER_DAY);
+        assert_eq!(
+            local_day_start(2 * NANOS_PER_DAY - 30 * NANOS_PER_MINUTE, utc_offset),
+            2 * NANOS_PER_DAY - 60 * NANOS_PER_MINUTE
+        );
+    }
+
+    #[test]
+    fn test_days_from_civil() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(2024, 12, 31), 20_088);
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(20_088), (2024, 12, 31));
+    }
+
+    #[test]
+    fn test_completion_heatmap() {
+        let store = RefCell::new(StatsStore::new(DefaultMemoryImpl::default()));
+        let wrapper = StatsStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let first_day = days_from_civil(2024, 1, 1) as Day;
+        let counts = DailyCounts {
+            completed: 4,
+            ..Default::default()
+        };
+        store
+            .borrow_mut()
+            .insert((principal, first_day + 59), counts);
+        store
+            .borrow_mut()
+            .insert((principal, first_day + 366), counts);
+
+        let heatmap = wrapper.completion_heatmap(principal, 2024).unwrap();
+        assert_eq!(heatmap.first_day, first_day);
+        assert_eq!(heatmap.counts.len(), 366);
+        assert_eq!(heatmap.counts[59], 4);
+        assert_eq!(heatmap.counts.iter().sum::<u32>(), 4);
+        assert!(matches!(
+            wrapper.completion_heatmap(principal, 1969),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_range_days() {
+        assert_eq!(
+            StatsRange::days(None, 40 * NANOS_PER_DAY).unwrap(),
+            (11, 40)
+        );
+        let range = StatsRange {
+            from: 5 * NANOS_PER_DAY,
+            to: 4 * NANOS_PER_DAY,
+        };
+        assert!(matches!(
+            StatsRange::days(Some(range), 0),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+}
@@ -1,16 +1,275 @@
-use std::cell::RefCell;
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    ops::Bound,
+};
 
-use candid::Principal;
-use ic_stable_structures::{Memory, StableBTreeMap};
+use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::{Memory, StableBTreeMap, Storable};
 
 use crate::{
+    board::ColumnId,
+    budget::InstructionGuard,
+    checklists::MAX_CHECKLIST_ITEMS,
     errors::Error,
-    paginator::Paginator,
-    todo::{Priority, Todo, TodoId},
+    geo::GeoPoint,
+    goals::GoalId,
+    labels::LabelId,
+    link_preview::LinkPreview,
+    paginator::{Order, Paginator},
+    renumbering::{renumbered, rewrite_entries, Renumbering},
+    stats::{TagCount, NANOS_PER_DAY},
+    tags,
+    todo::{CreateTodo, Energy, PatchTodo, Priority, Subtask, Todo, TodoId, TodoRecord},
+    validation,
 };
 
-/// Type alias for the TodoStore, which is a StableBTreeMap with a tuple key of (Principal, TodoId) and value of Todo.
-pub(crate) type TodoStore<M> = StableBTreeMap<(Principal, TodoId), Todo, M>;
+/// Type alias for the TodoStore, which is a StableBTreeMap with a tuple key of (Principal, TodoId) and value of TodoRecord.
+pub(crate) type TodoStore<M> = StableBTreeMap<(Principal, TodoId), TodoRecord, M>;
+
+/// Represents a stored Todo record that could not be decoded.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct CorruptTodo {
+    /// The principal owning the record.
+    pub(crate) principal: Principal,
+    /// The identifier the record is stored under.
+    pub(crate) id: TodoId,
+    /// The raw bytes of the record.
+    pub(crate) bytes: Vec<u8>,
+}
+
+/// Represents the effect of a mutation on a single Todo item.
+///
+/// Returned by every mutating method of the wrapper so that data derived from the store can be kept in sync.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct TodoChange {
+    /// The principal owning the Todo item.
+    pub(crate) principal: Principal,
+    /// The Todo item before the mutation, or None if it was created.
+    pub(crate) before: Option<Todo>,
+    /// The Todo item after the mutation, or None if it was removed.
+    pub(crate) after: Option<Todo>,
+}
+
+/// Represents the order in which Todo items are listed.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum SortOrder {
+    /// In order of creation.
+    #[default]
+    Created,
+    /// Soonest due date first; Todo items without a due date come last.
+    DueDate,
+    /// Highest priority first.
+    Priority,
+}
+
+impl SortOrder {
+    /// Returns the sort keys equivalent to the order.
+    pub(crate) fn keys(self) -> Vec<SortKey> {
+        match self {
+            SortOrder::Created => vec![SortKey { field: SortField::Created, direction: Order::Asc }],
+            SortOrder::DueDate => vec![SortKey { field: SortField::DueDate, direction: Order::Asc }],
+            SortOrder::Priority => vec![SortKey { field: SortField::Priority, direction: Order::Desc }],
+        }
+    }
+}
+
+/// Represents conditions a Todo item must all meet to be listed; unset conditions match every item.
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub(crate) struct TodoFilter {
+    /// Whether the Todo item is completed.
+    pub(crate) completed: Option<bool>,
+    /// A tag the Todo item carries, or one nested in it such as `work/projectX` for `work`.
+    pub(crate) tag: Option<String>,
+    /// The project the Todo item belongs to.
+    pub(crate) project: Option<String>,
+    /// The GTD context of the Todo item.
+    pub(crate) context: Option<String>,
+    /// A label attached to the Todo item.
+    pub(crate) label_id: Option<LabelId>,
+    /// Whether the Todo item is open and past its due date.
+    pub(crate) overdue: Option<bool>,
+}
+
+impl TodoFilter {
+    /// Returns whether a Todo item meets every condition of the filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `todo` - The Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    pub(crate) fn matches(&self, todo: &Todo, now: u64) -> bool {
+        let is_overdue = !todo.is_completed && todo.due_date.is_some_and(|due_date| due_date < now);
+        self.completed.is_none_or(|completed| todo.is_completed == completed)
+            && self.overdue.is_none_or(|overdue| is_overdue == overdue)
+            && self.tag.as_ref().is_none_or(|tag| todo.tags.iter().any(|carried| tags::is_within(carried, tag)))
+            && self.project.as_ref().is_none_or(|project| todo.project.as_ref() == Some(project))
+            && self.context.as_ref().is_none_or(|context| todo.context.as_ref() == Some(context))
+            && self.label_id.is_none_or(|label_id| todo.label_ids.contains(&label_id))
+    }
+}
+
+/// Represents the identifier of a Todo item with its version, which changes whenever the Todo item does.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) struct TodoVersion {
+    /// The unique identifier of the Todo item.
+    pub(crate) id: TodoId,
+    /// The time of the last change or creation, in nanoseconds since the Unix epoch.
+    pub(crate) version: u64,
+}
+
+/// Maximum number of Todo items changed by a single bulk update, to stay within the instruction limit.
+const MAX_BULK_UPDATES: usize = 500;
+
+/// Represents the outcome of a bulk update.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) struct BulkUpdate {
+    /// Number of Todo items changed.
+    pub(crate) updated: u32,
+    /// Whether Todo items are left to visit because the cap was reached or the instruction budget was spent.
+    pub(crate) remaining: bool,
+    /// The identifier to continue after, or None once every Todo item was visited.
+    pub(crate) next: Option<TodoId>,
+}
+
+/// Maximum number of keys a listing can be sorted by.
+const MAX_SORT_KEYS: usize = 4;
+
+/// Represents a page of Todo items sorted by several keys.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct SortedPage {
+    /// The Todo items of the page when every Todo item was scanned in one call. When the scan was split across calls,
+    /// the best `skip + limit` Todo items among those scanned by this call, in order, from which the client cuts the
+    /// page after merging them with those of the other calls.
+    pub(crate) todos: Vec<Todo>,
+    /// The identifier to continue after, or None once every Todo item was scanned.
+    pub(crate) next: Option<TodoId>,
+}
+
+/// Represents a field Todo items can be sorted by.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum SortField {
+    /// The order of creation.
+    Created,
+    /// The due date; Todo items without a due date come last in either direction.
+    DueDate,
+    /// The priority, from lowest to highest.
+    Priority,
+    /// The time of the last change.
+    Updated,
+}
+
+/// Represents one key of a multi-key sort, applied when the previous keys tie.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) struct SortKey {
+    /// The field to sort by.
+    pub(crate) field: SortField,
+    /// The direction to sort in.
+    pub(crate) direction: Order,
+}
+
+impl SortKey {
+    /// Compares two Todo items by the key.
+    fn compare(&self, a: &Todo, b: &Todo) -> Ordering {
+        let ordering = match self.field {
+            // Identifiers grow with creation, unlike `created_at`, which is 0 for old items.
+            SortField::Created => a.id.cmp(&b.id),
+            SortField::DueDate => match (a.due_date, b.due_date) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (a, b) => return a.is_none().cmp(&b.is_none()),
+            },
+            SortField::Priority => b.priority.rank().cmp(&a.priority.rank()),
+            SortField::Updated => a.last_touched().cmp(&b.last_touched()),
+        };
+        match self.direction {
+            Order::Asc => ordering,
+            Order::Desc => ordering.reverse(),
+        }
+    }
+}
+
+/// Puts a description in the form duplicates are compared in: lowercase, with runs of whitespace collapsed.
+pub(crate) fn normalized_description(description: &str) -> String {
+    description.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Maximum number of Todo items a call keeps decoded; beyond it, the earliest keys are dropped first.
+pub(crate) const MAX_CACHED_TODOS: usize = 64;
+
+thread_local! {
+    /// Todo items decoded during the current message, while it caches its reads.
+    /// Kept on the heap, and emptied once the last open scope ends.
+    static READ_CACHE: RefCell<ReadCache> = const { RefCell::new(ReadCache { scopes: 0, todos: BTreeMap::new() }) };
+}
+
+/// Represents the Todo items decoded during a call, so that reading the same key several times within a message
+/// decodes its bytes once. The wrapper writes through it, so it never differs from the store.
+struct ReadCache {
+    /// Number of open scopes; reads are only cached while at least one is open.
+    scopes: usize,
+    /// The decoded Todo items, or None where no valid Todo item is stored, by store address and key.
+    todos: BTreeMap<(usize, Principal, TodoId), Option<Todo>>,
+}
+
+impl ReadCache {
+    /// Remembers the Todo item stored under a key, if reads are cached.
+    fn remember(&mut self, key: (usize, Principal, TodoId), todo: Option<Todo>) {
+        if self.scopes == 0 {
+            return;
+        }
+        if self.todos.len() >= MAX_CACHED_TODOS && !self.todos.contains_key(&key) {
+            self.todos.pop_first();
+        }
+        self.todos.insert(key, todo);
+    }
+}
+
+/// Caches reads of Todo items until dropped.
+struct ReadScope(());
+
+/// Starts caching reads of Todo items.
+///
+/// # Returns
+///
+/// The scope, which ends caching once it and any other open scope are dropped.
+fn cache_reads() -> ReadScope {
+    READ_CACHE.with(|cache| cache.borrow_mut().scopes += 1);
+    ReadScope(())
+}
+
+/// Caches reads of Todo items while a function runs, so that reading the same key again decodes it once.
+/// The function cannot await, so the cache never outlives the message: another message running while a call
+/// awaits could change the store without the wrapper, and nothing it cached would be trusted afterwards.
+///
+/// # Arguments
+///
+/// * `f` - The function, which writes to the store through the wrapper only or calls `forget_cached_reads` after.
+///
+/// # Returns
+///
+/// What the function returns.
+pub(crate) fn with_cached_reads<R>(f: impl FnOnce() -> R) -> R {
+    let _scope = cache_reads();
+    f()
+}
+
+/// Drops every cached Todo item, after the store was written to without the wrapper.
+pub(crate) fn forget_cached_reads() {
+    READ_CACHE.with(|cache| cache.borrow_mut().todos.clear());
+}
+
+impl Drop for ReadScope {
+    fn drop(&mut self) {
+        READ_CACHE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            cache.scopes -= 1;
+            if cache.scopes == 0 {
+                cache.todos.clear();
+            }
+        });
+    }
+}
 
 /// Wrapper around the TodoStore to provide additional functionality.
 pub(crate) struct TodoStoreWrapper<'a, M: Memory> {
@@ -22,197 +281,1681 @@ impl<'a, M: Memory> TodoStoreWrapper<'a, M> {
     //     Self { store }
     // }
 
-    /// Adds a new Todo item to the store.
+    /// Adds a new Todo item to the store.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `text` - The text description of the Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the input is invalid.
+    pub(crate) fn add_todo(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        description: String,
+        priority: Priority,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let description = validation::description(&description)?;
+        let todo = Todo { created_at: now, ..Todo::new(id, description, priority) };
+        Ok(self.insert(principal, None, todo))
+    }
+
+    /// Finds an open Todo item of a principal with the same description, ignoring case and spacing,
+    /// so that a task captured twice can be caught. The search stops once the call spent its instruction budget.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `description` - The description of the new Todo item.
+    /// * `guard` - The instruction budget of the call.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the unique identifier for the open Todo item, or None if there is none among
+    /// the Todo items searched.
+    pub(crate) fn find_duplicate(&self, principal: Principal, description: &str, guard: &InstructionGuard) -> Option<TodoId> {
+        let normalized = normalized_description(description);
+        self.find_todos(principal, 1, guard, |todo| {
+            !todo.is_completed && normalized_description(&todo.description) == normalized
+        })
+        .first()
+        .map(|todo| todo.id)
+    }
+
+    /// Finds, for each of a batch of descriptions, an open Todo item of a principal with the same description,
+    /// scanning the Todo items once for the whole batch. The search stops once the call spent its instruction budget.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `descriptions` - The descriptions of the new Todo items.
+    /// * `guard` - The instruction budget of the call.
+    ///
+    /// # Returns
+    ///
+    /// A vector holding, in the order of the descriptions, the unique identifier for the first open Todo item
+    /// with the description, or None if there is none among the Todo items searched.
+    pub(crate) fn find_duplicates(
+        &self,
+        principal: Principal,
+        descriptions: &[&str],
+        guard: &InstructionGuard,
+    ) -> Vec<Option<TodoId>> {
+        let normalized: Vec<String> = descriptions.iter().map(|description| normalized_description(description)).collect();
+        let wanted: BTreeSet<&str> = normalized.iter().map(String::as_str).collect();
+        let mut open = BTreeMap::new();
+        for todo in self.find_todos(principal, usize::MAX, guard, |todo| {
+            !todo.is_completed && wanted.contains(normalized_description(&todo.description).as_str())
+        }) {
+            open.entry(normalized_description(&todo.description)).or_insert(todo.id);
+        }
+        normalized.iter().map(|description| open.get(description).copied()).collect()
+    }
+
+    /// Lists the first Todo items of a principal that meet a condition, in order of identifier, without decoding
+    /// the Todo items after them. The search stops once the call spent its instruction budget.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `count` - The maximum number of Todo items to list.
+    /// * `guard` - The instruction budget of the call.
+    /// * `condition` - The condition the Todo items must meet.
+    ///
+    /// # Returns
+    ///
+    /// A vector of at most `count` Todo items.
+    pub(crate) fn find_todos(
+        &self,
+        principal: Principal,
+        count: usize,
+        guard: &InstructionGuard,
+        condition: impl Fn(&Todo) -> bool,
+    ) -> Vec<Todo> {
+        self.store
+            .borrow()
+            .range((principal, TodoId::MIN)..)
+            .take_while(|((p, _), _)| p == &principal && !guard.exhausted())
+            .filter_map(|(_, record)| record.into_todo())
+            .filter(|todo| condition(todo))
+            .take(count)
+            .collect()
+    }
+
+    /// Adds a new Todo item built from a creation request to the store.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `request` - The fields of the new Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the input is invalid.
+    pub(crate) fn create_todo(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        request: CreateTodo,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let request = validation::create_request(request)?;
+        let todo = Todo { created_at: now, ..Todo::from_request(id, request) };
+        Ok(self.insert(principal, None, todo))
+    }
+
+    /// Adds a new onboarding sample Todo item built from a creation request to the store.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `request` - The fields of the sample Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the input is invalid.
+    pub(crate) fn create_sample_todo(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        request: CreateTodo,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let request = validation::create_request(request)?;
+        let todo = Todo { created_at: now, sample: true, ..Todo::from_request(id, request) };
+        Ok(self.insert(principal, None, todo))
+    }
+
+    /// Replaces the fields of an existing Todo item with those of a creation request.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `request` - The new fields of the Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found or the input is invalid.
+    pub(crate) fn replace_todo(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        request: CreateTodo,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let request = validation::create_request(request)?;
+        self.modify(principal, id, now, |todo| todo.replace_with_request(request))
+    }
+
+    /// Restores an existing Todo item to a previous version of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `previous` - The previous version of the Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn restore_todo(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        previous: Todo,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| *todo = Todo { id, ..previous })
+    }
+
+    /// Adds the Todo items restored from an account bundle to the store as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `todos` - The validated Todo items, with fresh identifiers.
+    ///
+    /// # Returns
+    ///
+    /// The changes describing the writes.
+    pub(crate) fn restore_from_bundle(&self, principal: Principal, todos: Vec<Todo>) -> Vec<TodoChange> {
+        self.insert_many(principal, todos.into_iter().map(|todo| (None, todo)).collect())
+    }
+
+    /// Adds a batch of new Todo items built from creation requests to the store. Every request is validated
+    /// before anything is stored, so either the whole batch is added or none of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `requests` - The unique identifier and fields of each new Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the changes, in the order of the requests, or an Error if any request is invalid.
+    pub(crate) fn create_many(
+        &self,
+        principal: Principal,
+        requests: Vec<(TodoId, CreateTodo)>,
+        now: u64,
+    ) -> Result<Vec<TodoChange>, Error> {
+        let writes = requests
+            .into_iter()
+            .map(|(id, request)| {
+                let request = validation::create_request(request)?;
+                Ok((None, Todo { created_at: now, ..Todo::from_request(id, request) }))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(self.insert_many(principal, writes))
+    }
+
+    /// Stores a batch of Todo items, replacing any previous versions. The map is borrowed once for the whole
+    /// batch instead of once per item, which matters for bulk endpoints and importers.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `writes` - The previous version, if any, and the Todo item to be stored, for each Todo item.
+    ///
+    /// # Returns
+    ///
+    /// The changes describing the writes, in order.
+    pub(crate) fn insert_many(&self, principal: Principal, writes: Vec<(Option<Todo>, Todo)>) -> Vec<TodoChange> {
+        let mut store = self.store.borrow_mut();
+        writes
+            .into_iter()
+            .map(|(before, after)| {
+                store.insert((principal, after.id), after.clone().into());
+                self.forget(principal, after.id);
+                TodoChange { principal, before, after: Some(after) }
+            })
+            .collect()
+    }
+
+    /// Retrieves a Todo item from the store, decoding it once per call while reads are cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the Todo item if found, otherwise None.
+    pub(crate) fn get_todo(&self, principal: Principal, id: TodoId) -> Option<Todo> {
+        let key = self.cache_key(principal, id);
+        if let Some(todo) = READ_CACHE.with(|cache| cache.borrow().todos.get(&key).cloned()) {
+            return todo;
+        }
+        let todo = self.store.borrow().get(&(principal, id)).and_then(TodoRecord::into_todo);
+        READ_CACHE.with(|cache| cache.borrow_mut().remember(key, todo.clone()));
+        todo
+    }
+
+    /// Lists Todo items for a given principal with pagination.
+    /// Records that cannot be decoded are skipped. A descending paginator lists the Todo items in reverse,
+    /// walking the principal's keys backwards in creation order. Other orders rank only the Todo items scanned
+    /// before the call spent its instruction budget; `list_todos_sorted` tells where to continue.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `paginator` - The paginator for controlling the list output.
+    /// * `sort` - The order in which the Todo items are listed.
+    /// * `guard` - The instruction budget of the call.
+    ///
+    /// # Returns
+    ///
+    /// A vector of Todo items.
+    pub(crate) fn list_todos(
+        &self,
+        principal: Principal,
+        paginator: Paginator,
+        sort: SortOrder,
+        guard: &InstructionGuard,
+    ) -> Vec<Todo> {
+        if sort == SortOrder::Created {
+            let store = self.store.borrow();
+            let records = store.range((principal, TodoId::MIN)..=(principal, TodoId::MAX));
+            let records: Box<dyn Iterator<Item = _>> = if paginator.is_descending() {
+                Box::new(records.rev())
+            } else {
+                Box::new(records)
+            };
+            return records
+                .filter_map(|(_, record)| record.into_todo())
+                .skip(paginator.skip())
+                .take(paginator.limit())
+                .collect();
+        }
+        self.list_todos_sorted(principal, paginator, &sort.keys(), None, guard)
+            .map(|page| page.todos)
+            .unwrap_or_default()
+    }
+
+    /// Lists Todo items for a given principal with pagination, sorted by several keys.
+    /// The Todo items are scanned in order of identifier and only the best `skip + limit` of them are kept, so the cost
+    /// of a page grows with its depth rather than with a full sort. Ties left by the keys are broken by creation order,
+    /// and a descending paginator reverses the whole order. The scan stops once the call spent its instruction budget,
+    /// returning where to continue.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `paginator` - The paginator for controlling the list output.
+    /// * `sort` - The sort keys, most significant first.
+    /// * `after` - The identifier returned by the previous call to continue after, or None to start from the first Todo item.
+    /// * `guard` - The instruction budget of the call.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the sorted page, or an InvalidInput Error if there are too many sort keys.
+    pub(crate) fn list_todos_sorted(
+        &self,
+        principal: Principal,
+        paginator: Paginator,
+        sort: &[SortKey],
+        after: Option<TodoId>,
+        guard: &InstructionGuard,
+    ) -> Result<SortedPage, Error> {
+        if sort.len() > MAX_SORT_KEYS {
+            return Err(Error::InvalidInput(format!(
+                "Cannot sort by more than {MAX_SORT_KEYS} keys"
+            )));
+        }
+        let descending = paginator.is_descending();
+        let compare = |a: &Todo, b: &Todo| {
+            let ordering = sort
+                .iter()
+                .map(|key| key.compare(a, b))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| a.id.cmp(&b.id));
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        };
+        let end = paginator.skip() + paginator.limit();
+        let start = after.map_or(Bound::Included((principal, TodoId::MIN)), |id| Bound::Excluded((principal, id)));
+        let mut todos = Vec::new();
+        let mut cursor = after;
+        let mut next = None;
+        for ((_, id), record) in self.store.borrow().range((start, Bound::Included((principal, TodoId::MAX)))) {
+            // At least one record is read per call, so that paging through always progresses.
+            if cursor != after && guard.exhausted() {
+                next = cursor;
+                break;
+            }
+            todos.extend(record.into_todo());
+            cursor = Some(id);
+            // Only the best `end` Todo items can reach the page, so the others are dropped as the scan goes.
+            if todos.len() > 2 * end {
+                todos.select_nth_unstable_by(end, compare);
+                todos.truncate(end);
+            }
+        }
+        if end < todos.len() {
+            todos.select_nth_unstable_by(end, compare);
+            todos.truncate(end);
+        }
+        todos.sort_unstable_by(compare);
+        // A page can only be cut out of a scan that covered every Todo item; otherwise the candidates are returned
+        // for the client to merge with those of the other calls.
+        let skip = if after.is_none() && next.is_none() { paginator.skip() } else { 0 };
+        Ok(SortedPage { todos: todos.into_iter().skip(skip).collect(), next })
+    }
+
+    /// Lists all Todo items of a given principal.
+    /// Records that cannot be decoded are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    ///
+    /// # Returns
+    ///
+    /// A vector of Todo items, ordered by their identifiers.
+    pub(crate) fn all_todos(&self, principal: Principal) -> Vec<Todo> {
+        self.store
+            .borrow()
+            .range((principal, TodoId::MIN)..)
+            .take_while(|((p, _), _)| p == &principal)
+            .filter_map(|(_, record)| record.into_todo())
+            .collect()
+    }
+
+    /// Lists the Todo items of a principal after an identifier, in order of identifier. Records that cannot be decoded
+    /// are skipped. The listing stops at `limit` Todo items or once the call spent its instruction budget, returning
+    /// where to continue.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `after` - The identifier returned by the previous call to continue after, or None to start from the first Todo item.
+    /// * `limit` - The maximum number of Todo items to list.
+    /// * `guard` - The instruction budget of the call.
+    ///
+    /// # Returns
+    ///
+    /// The Todo items and the identifier to continue after, or None if no Todo item follows.
+    pub(crate) fn list_todos_after(
+        &self,
+        principal: Principal,
+        after: Option<TodoId>,
+        limit: usize,
+        guard: &InstructionGuard,
+    ) -> (Vec<Todo>, Option<TodoId>) {
+        let start = after.map_or(Bound::Included((principal, TodoId::MIN)), |id| Bound::Excluded((principal, id)));
+        let mut todos = Vec::new();
+        let mut cursor = after;
+        for ((_, id), record) in self.store.borrow().range((start, Bound::Included((principal, TodoId::MAX)))) {
+            // At least one record is read per call, so that paging through always progresses.
+            if todos.len() == limit || (cursor != after && guard.exhausted()) {
+                return (todos, cursor);
+            }
+            todos.extend(record.into_todo());
+            cursor = Some(id);
+        }
+        (todos, None)
+    }
+
+    /// Retrieves the Todo record following a position in the store, across principals, so that jobs can walk
+    /// every Todo item in slices.
+    ///
+    /// # Arguments
+    ///
+    /// * `after` - The key of the record to continue after, or None to start with the first record.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the key of the record and its Todo item, if it could be decoded, or None if no record follows.
+    pub(crate) fn next_record(&self, after: Option<(Principal, TodoId)>) -> Option<((Principal, TodoId), Option<Todo>)> {
+        let start = after.map_or(Bound::Unbounded, Bound::Excluded);
+        self.store.borrow().range((start, Bound::Unbounded)).next().map(|(key, record)| (key, record.into_todo()))
+    }
+
+    /// Lists the identifiers and versions of the Todo items of a principal that match a filter,
+    /// so that clients can tell which cached Todo items changed without fetching them.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `filter` - The validated filter.
+    ///
+    /// # Returns
+    ///
+    /// A vector of identifiers with versions, ordered by identifier.
+    pub(crate) fn todo_versions(&self, principal: Principal, filter: &TodoFilter, now: u64) -> Vec<TodoVersion> {
+        self.all_todos(principal)
+            .iter()
+            .filter(|todo| filter.matches(todo, now))
+            .map(|todo| TodoVersion { id: todo.id, version: todo.last_touched() })
+            .collect()
+    }
+
+    /// Applies a partial update to every Todo item of a principal that matches a filter, in order of identifier.
+    /// Todo items the update would leave unchanged are skipped and do not count towards the cap.
+    /// The update stops at the cap or once the instruction budget is spent, returning where to continue.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `filter` - The conditions the Todo items must meet.
+    /// * `patch` - The fields to be changed.
+    /// * `after` - The identifier returned by the previous call to continue after, or None to start from the first Todo item.
+    /// * `guard` - The instruction budget of the call.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the outcome with the changes, or an Error if the input is invalid.
+    pub(crate) fn bulk_update(
+        &self,
+        principal: Principal,
+        filter: &TodoFilter,
+        patch: PatchTodo,
+        after: Option<TodoId>,
+        guard: &InstructionGuard,
+        now: u64,
+    ) -> Result<(BulkUpdate, Vec<TodoChange>), Error> {
+        let patch = validation::patch(patch)?;
+        let mut writes = Vec::new();
+        let mut cursor = after;
+        let next = loop {
+            let start = cursor.map_or(Bound::Included((principal, TodoId::MIN)), |id| Bound::Excluded((principal, id)));
+            let record = self.store.borrow().range((start, Bound::Unbounded)).next().filter(|((p, _), _)| p == &principal);
+            let Some(((_, id), record)) = record else {
+                break None;
+            };
+            if let Some(todo) = record.into_todo().filter(|todo| filter.matches(todo, now)) {
+                let mut patched = todo.clone();
+                patched.apply_patch(patch.clone(), now);
+                if patched != todo {
+                    if writes.len() == MAX_BULK_UPDATES {
+                        break cursor;
+                    }
+                    patched.updated_at = now;
+                    writes.push((Some(todo), patched));
+                }
+            }
+            cursor = Some(id);
+            if guard.exhausted() {
+                break cursor;
+            }
+        };
+        let outcome = BulkUpdate { updated: writes.len() as u32, remaining: next.is_some(), next };
+        Ok((outcome, self.insert_many(principal, writes)))
+    }
+
+    /// Measures how much of the store a principal uses.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    ///
+    /// # Returns
+    ///
+    /// The number of records owned by the principal and their total size in bytes.
+    pub(crate) fn usage(&self, principal: Principal) -> (u64, u64) {
+        self.store
+            .borrow()
+            .range((principal, TodoId::MIN)..)
+            .take_while(|((p, _), _)| p == &principal)
+            .fold((0, 0), |(count, bytes), (_, record)| {
+                (count + 1, bytes + record.to_bytes().len() as u64)
+            })
+    }
+
+    /// Counts the tags of the Todo items a principal created within a time range.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `from` - Start of the range, in nanoseconds since the Unix epoch.
+    /// * `to` - End of the range (inclusive), in nanoseconds since the Unix epoch.
+    /// * `limit` - The maximum number of tags to return.
+    ///
+    /// # Returns
+    ///
+    /// A vector of the most used tags, ordered by descending count.
+    pub(crate) fn busiest_tags(&self, principal: Principal, from: u64, to: u64, limit: usize) -> Vec<TagCount> {
+        let mut counts: BTreeMap<String, u32> = BTreeMap::new();
+        self.store
+            .borrow()
+            .range((principal, TodoId::MIN)..)
+            .take_while(|((p, _), _)| p == &principal)
+            .filter_map(|(_, record)| record.into_todo())
+            .filter(|todo| (from..=to).contains(&todo.created_at))
+            .flat_map(|todo| todo.tags)
+            .for_each(|tag| *counts.entry(tag).or_default() += 1);
+        let mut tags: Vec<TagCount> = counts
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect();
+        tags.sort_by_key(|tag| std::cmp::Reverse(tag.count));
+        tags.truncate(limit);
+        tags
+    }
+
+    /// Updates the text of an existing Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `text` - The new text description of the Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found or the input is invalid.
+    pub(crate) fn update_todo(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        text: String,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let text = validation::description(&text)?;
+        self.modify(principal, id, now, |todo| todo.description = text)
+    }
+
+    /// Applies a partial update to a Todo item in a single write.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `patch` - The fields to be changed.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found or the input is invalid.
+    pub(crate) fn patch_todo(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        patch: PatchTodo,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let patch = validation::patch(patch)?;
+        self.modify(principal, id, now, |todo| todo.apply_patch(patch, now))
+    }
+
+    /// Removes a Todo item from the store.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the change if a Todo item was removed, otherwise None.
+    pub(crate) fn remove_todo(&self, principal: Principal, id: TodoId) -> Option<TodoChange> {
+        let record = self.store.borrow_mut().remove(&(principal, id));
+        self.forget(principal, id);
+        let before = record?.into_todo()?;
+        Some(TodoChange { principal, before: Some(before), after: None })
+    }
+
+    /// Toggles the completion status of a Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn toggle_todo_complete(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| todo.set_completed(!todo.is_completed, now))
+    }
+
+    /// Marks a Todo item as complete, leaving it as it is if it already is.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn complete_todo(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| todo.set_completed(true, now))
+    }
+
+    /// Modifies the priority of an existing Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `priority` - The new priority for the Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn modify_todo_priority(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        priority: Priority,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| todo.set_priority(priority))
+    }
+
+    /// Places a Todo item in a board column, moving it to the project the column belongs to.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `column_id` - The unique identifier for the column.
+    /// * `project` - The project the column belongs to.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn move_todo_to_column(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        column_id: ColumnId,
+        project: String,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| {
+            todo.project = Some(project);
+            todo.column_id = Some(column_id);
+        })
+    }
+
+    /// Sets or clears the GTD context of a Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `context` - The new context, or None to clear it.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found or the context is invalid.
+    pub(crate) fn set_context(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        context: Option<String>,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let context = context.as_deref().map(validation::context).transpose()?;
+        self.modify(principal, id, now, |todo| todo.context = context)
+    }
+
+    /// Sets or clears the recurrence rule of a Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `recurrence` - The new recurrence rule, or None to stop recurring.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found or the rule is invalid.
+    pub(crate) fn set_recurrence(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        recurrence: Option<String>,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let recurrence = recurrence.as_deref().map(validation::recurrence).transpose()?;
+        self.modify(principal, id, now, |todo| todo.recurrence = recurrence)
+    }
+
+    /// Sets whether the owner is notified once a Todo item is overdue.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `muted` - Whether overdue notifications are turned off.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn set_overdue_muted(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        muted: bool,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| todo.overdue_muted = muted)
+    }
+
+    /// Sets or clears the Todo item in another canister a Todo item refers to.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `linked` - The canister holding the linked Todo item and its identifier there, or None to remove the link.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn set_linked(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        linked: Option<(Principal, TodoId)>,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| todo.linked = linked)
+    }
+
+    /// Sets or clears the place a Todo item is done at.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `location` - The validated location, or None to clear it.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn set_location(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        location: Option<GeoPoint>,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| todo.location = location)
+    }
+
+    /// Appends subtasks to a Todo item, such as the items of a checklist.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `titles` - The validated titles of the subtasks.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found or would have too many subtasks.
+    pub(crate) fn add_subtasks(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        titles: Vec<String>,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let todo = self.get_todo(principal, id).ok_or(Error::NotFound)?;
+        if todo.subtasks.len() + titles.len() > MAX_CHECKLIST_ITEMS {
+            return Err(Error::QuotaExceeded(Some(format!(
+                "A Todo item can have at most {MAX_CHECKLIST_ITEMS} subtasks"
+            ))));
+        }
+        self.modify(principal, id, now, |todo| {
+            todo.subtasks.extend(titles.into_iter().map(|title| Subtask { title, done: false }))
+        })
+    }
+
+    /// Ticks off a subtask of a Todo item, or reopens it.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `index` - The position of the subtask, starting at 0.
+    /// * `done` - Whether the subtask is done.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item or subtask is not found.
+    pub(crate) fn set_subtask_done(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        index: u32,
+        done: bool,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let todo = self.get_todo(principal, id).ok_or(Error::NotFound)?;
+        if index as usize >= todo.subtasks.len() {
+            return Err(Error::NotFound);
+        }
+        self.modify(principal, id, now, |todo| todo.subtasks[index as usize].done = done)
+    }
+
+    /// Starts or stops tracking a Todo item as a habit.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `habit` - Whether the Todo item is tracked as a habit.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found, or is to become a habit without recurring.
+    pub(crate) fn set_habit(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        habit: bool,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let todo = self.get_todo(principal, id).ok_or(Error::NotFound)?;
+        if habit && todo.recurrence.is_none() {
+            return Err(Error::DependencyNotMet(Some(
+                "Only recurring Todo items can be tracked as habits".to_string(),
+            )));
+        }
+        self.modify(principal, id, now, |todo| todo.habit = habit)
+    }
+
+    /// Moves a habit on to its next occurrence.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `due_date` - The time of the next occurrence, in nanoseconds since the Unix epoch.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn advance_habit(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        due_date: u64,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| todo.due_date = Some(due_date))
+    }
+
+    /// Attaches a Todo item to a goal, or detaches it.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `goal_id` - The unique identifier for an existing goal, or None to detach the Todo item.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn set_goal(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        goal_id: Option<GoalId>,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| todo.goal_id = goal_id)
+    }
+
+    /// Detaches every Todo item of a principal from a deleted goal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `goal_id` - The unique identifier for the goal.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A vector of the changes to the Todo items that were attached to the goal.
+    pub(crate) fn remove_goal_from_todos(
+        &self,
+        principal: Principal,
+        goal_id: GoalId,
+        now: u64,
+    ) -> Vec<TodoChange> {
+        self.all_todos(principal)
+            .into_iter()
+            .filter(|todo| todo.goal_id == Some(goal_id))
+            .filter_map(|todo| self.set_goal(principal, todo.id, None, now).ok())
+            .collect()
+    }
+
+    /// Lists the distinct GTD contexts of the open Todo items of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    ///
+    /// # Returns
+    ///
+    /// A vector of contexts, in alphabetical order.
+    pub(crate) fn contexts(&self, principal: Principal) -> Vec<String> {
+        self.all_todos(principal)
+            .into_iter()
+            .filter(|todo| !todo.is_completed)
+            .filter_map(|todo| todo.context)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Lists the open Todo items of a principal in a GTD context with pagination.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `context` - The sanitized context.
+    /// * `paginator` - The paginator for controlling the list output.
+    ///
+    /// # Returns
+    ///
+    /// A vector of Todo items, ordered by their identifiers.
+    pub(crate) fn list_todos_in_context(
+        &self,
+        principal: Principal,
+        context: &str,
+        paginator: Paginator,
+    ) -> Vec<Todo> {
+        self.all_todos(principal)
+            .into_iter()
+            .filter(|todo| !todo.is_completed && todo.context.as_deref() == Some(context))
+            .skip(paginator.skip())
+            .take(paginator.limit())
+            .collect()
+    }
+
+    /// Lists the Todo items of a principal in a namespace with pagination.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `namespace` - The namespace, or None for the default space.
+    /// * `paginator` - The paginator for controlling the list output.
+    pub(crate) fn list_todos_in_namespace(
+        &self,
+        principal: Principal,
+        namespace: Option<&str>,
+        paginator: Paginator,
+    ) -> Vec<Todo> {
+        self.all_todos(principal)
+            .into_iter()
+            .filter(|todo| todo.namespace.as_deref() == namespace)
+            .skip(paginator.skip())
+            .take(paginator.limit())
+            .collect()
+    }
+
+    /// Moves a Todo item to another namespace.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `namespace` - The existing namespace, or None for the default space.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn set_namespace(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        namespace: Option<String>,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| todo.namespace = namespace)
+    }
+
+    /// Returns whether a principal has Todo items in a namespace.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `namespace` - The namespace.
+    pub(crate) fn namespace_in_use(&self, principal: Principal, namespace: &str) -> bool {
+        self.all_todos(principal).iter().any(|todo| todo.namespace.as_deref() == Some(namespace))
+    }
+
+    /// Sets or clears the energy level of a Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `energy` - The new energy level, or None to clear it.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn set_energy(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        energy: Option<Energy>,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| todo.energy = energy)
+    }
+
+    /// Sets or clears the estimate of how long a Todo item takes.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `minutes` - The validated estimate, in minutes, or None to clear it.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn set_estimate(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        minutes: Option<u32>,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| todo.estimated_minutes = minutes)
+    }
+
+    /// Lists the open Todo items of a principal that take at most the given energy level, with pagination.
+    /// Todo items without an energy level are left out.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `level` - The highest energy level to include.
+    /// * `paginator` - The paginator for controlling the list output.
+    ///
+    /// # Returns
+    ///
+    /// A vector of Todo items, highest priority first, then soonest due date.
+    pub(crate) fn list_todos_by_energy(
+        &self,
+        principal: Principal,
+        level: Energy,
+        paginator: Paginator,
+    ) -> Vec<Todo> {
+        let mut todos: Vec<Todo> = self
+            .all_todos(principal)
+            .into_iter()
+            .filter(|todo| !todo.is_completed && todo.energy.is_some_and(|energy| energy <= level))
+            .collect();
+        todos.sort_by_key(|todo| (todo.priority.rank(), todo.due_date.unwrap_or(u64::MAX), todo.id));
+        todos.into_iter().skip(paginator.skip()).take(paginator.limit()).collect()
+    }
+
+    /// Marks a Todo item as waiting on someone, or clears the wait.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `who` - The person or party waited on, or None if the Todo item is no longer waiting.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found or the name is invalid.
+    pub(crate) fn mark_waiting(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        who: Option<String>,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let who = who.as_deref().map(validation::waiting_on).transpose()?;
+        self.modify(principal, id, now, |todo| todo.set_waiting_on(who, now))
+    }
+
+    /// Sets or clears the web page a Todo item refers to.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `url` - The new URL, or None to clear it.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found or the URL is invalid.
+    pub(crate) fn set_url(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        url: Option<String>,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let url = url.as_deref().map(validation::https_url).transpose()?;
+        self.modify(principal, id, now, |todo| todo.set_url(url))
+    }
+
+    /// Caches the preview of the page a Todo item refers to.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `preview` - The fetched preview.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found
+    /// or its URL changed while the page was fetched.
+    pub(crate) fn set_link_preview(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        preview: LinkPreview,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let todo = self.get_todo(principal, id).ok_or(Error::NotFound)?;
+        if todo.url.as_ref() != Some(&preview.url) {
+            return Err(Error::Conflict(Some("The URL changed while the page was fetched".to_string())));
+        }
+        self.modify(principal, id, now, |todo| todo.link_preview = Some(preview))
+    }
+
+    /// Adds a tag to a Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `tag` - The tag to be added.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found or the tag is invalid.
+    pub(crate) fn add_tag_to_todo(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        tag: String,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        let tag = validation::tag(&tag)?;
+        self.modify(principal, id, now, |todo| todo.add_tag(tag))
+    }
+
+    /// Removes a tag from a Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `tag` - The tag to be removed.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn remove_tag_from_todo(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        tag: &str,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| todo.remove_tag(tag))
+    }
+
+    /// Attaches a label to a Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `label_id` - The unique identifier for an existing label.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn add_label_to_todo(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        label_id: LabelId,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| todo.add_label(label_id))
+    }
+
+    /// Detaches a label from a Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `label_id` - The unique identifier for the label.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the change or an Error if the Todo item is not found.
+    pub(crate) fn remove_label_from_todo(
+        &self,
+        principal: Principal,
+        id: TodoId,
+        label_id: LabelId,
+        now: u64,
+    ) -> Result<TodoChange, Error> {
+        self.modify(principal, id, now, |todo| todo.remove_label(label_id))
+    }
+
+    /// Detaches a deleted label from every Todo item of a principal carrying it.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `label_id` - The unique identifier for the label.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A vector of the changes to the Todo items that carried the label.
+    pub(crate) fn remove_label_from_todos(
+        &self,
+        principal: Principal,
+        label_id: LabelId,
+        now: u64,
+    ) -> Vec<TodoChange> {
+        self.all_todos(principal)
+            .into_iter()
+            .filter(|todo| todo.label_ids.contains(&label_id))
+            .filter_map(|todo| self.remove_label_from_todo(principal, todo.id, label_id, now).ok())
+            .collect()
+    }
+
+    /// Raises the priority of every open Todo item of a principal left untouched since a given time.
+    /// Todo items whose last change is unknown are left alone.
     ///
     /// # Arguments
     ///
     /// * `principal` - The principal identifier.
-    /// * `id` - The unique identifier for the Todo item.
-    /// * `text` - The text description of the Todo item.
-    pub(crate) fn add_todo(&self, principal: Principal, id: TodoId, description: String, priority: Priority) {
-        let todo = Todo::new(id, description,priority);
-        self.store.borrow_mut().insert((principal, id), todo);
+    /// * `untouched_since` - Todo items last changed before this time are escalated, in nanoseconds since the Unix epoch.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A vector of the changes to the escalated Todo items.
+    pub(crate) fn escalate_stale_todos(
+        &self,
+        principal: Principal,
+        untouched_since: u64,
+        now: u64,
+    ) -> Vec<TodoChange> {
+        self.all_todos(principal)
+            .into_iter()
+            .filter(|todo| !todo.is_completed && todo.priority.raised().is_some())
+            .filter(|todo| (1..untouched_since).contains(&todo.last_touched()))
+            .filter_map(|todo| {
+                self.modify(principal, todo.id, now, |todo| {
+                    todo.escalate();
+                })
+                .ok()
+            })
+            .collect()
     }
 
-    /// Retrieves a Todo item from the store.
+    /// Moves the open Todo items of a principal that were due within a period to the same time a day later.
     ///
     /// # Arguments
     ///
     /// * `principal` - The principal identifier.
-    /// * `id` - The unique identifier for the Todo item.
+    /// * `from` - The start of the period, in nanoseconds since the Unix epoch.
+    /// * `until` - The end of the period (exclusive), in nanoseconds since the Unix epoch.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
     ///
     /// # Returns
     ///
-    /// An Option containing the Todo item if found, otherwise None.
-    pub(crate) fn get_todo(&self, principal: Principal, id: TodoId) -> Option<Todo> {
-        self.store.borrow().get(&(principal, id))
+    /// A vector of the changes to the moved Todo items.
+    pub(crate) fn roll_over_todos(
+        &self,
+        principal: Principal,
+        from: u64,
+        until: u64,
+        now: u64,
+    ) -> Vec<TodoChange> {
+        self.all_todos(principal)
+            .into_iter()
+            .filter(|todo| !todo.is_completed)
+            .filter(|todo| todo.due_date.is_some_and(|due_date| (from..until).contains(&due_date)))
+            .filter_map(|todo| {
+                self.modify(principal, todo.id, now, |todo| todo.roll_over(NANOS_PER_DAY))
+                    .ok()
+            })
+            .collect()
     }
 
-    /// Lists Todo items for a given principal with pagination.
+    /// Removes every onboarding sample Todo item of a principal, including samples they edited.
     ///
     /// # Arguments
     ///
     /// * `principal` - The principal identifier.
-    /// * `paginator` - The paginator for controlling the list output.
     ///
     /// # Returns
     ///
-    /// A vector of Todo items.
-    pub(crate) fn list_todos(&self, principal: Principal, paginator: Paginator) -> Vec<Todo> {
-        self.store
-            .borrow()
-            .range((principal, TodoId::MIN)..)
-            .skip(paginator.skip())
-            .take_while(|((p, _), _)| p == &principal)
-            .take(paginator.limit())
-            .map(|((_, _), todo)| todo.clone())
+    /// A vector of the changes to the removed Todo items.
+    pub(crate) fn remove_sample_todos(&self, principal: Principal) -> Vec<TodoChange> {
+        self.all_todos(principal)
+            .into_iter()
+            .filter(|todo| todo.sample)
+            .filter_map(|todo| self.remove_todo(principal, todo.id))
             .collect()
     }
 
-    /// Updates the text of an existing Todo item.
+    /// Removes the Todo items of a principal that were completed before a given time.
+    ///
+    /// The removal is not reported as a change, since the Todo items are expected to be moved elsewhere.
     ///
     /// # Arguments
     ///
     /// * `principal` - The principal identifier.
-    /// * `id` - The unique identifier for the Todo item.
-    /// * `text` - The new text description of the Todo item.
+    /// * `completed_before` - Todo items completed before this time are removed, in nanoseconds since the Unix epoch.
     ///
     /// # Returns
     ///
-    /// A Result indicating success or an Error if the Todo item is not found or the input is invalid.
-    pub(crate) fn update_todo(
-        &self,
-        principal: Principal,
-        id: TodoId,
-        text: String,
-    ) -> Result<(), Error> {
-        if text.is_empty() {
-            return Err(Error::InvalidInput("Text cannot be empty".to_string()));
-        }
-        match self.get_todo(principal, id) {
-            Some(mut todo) => {
-                todo.description = text;
-                self.store.borrow_mut().insert((principal, id), todo);
-                Ok(())
-            }
-            None => Err(Error::NotFound),
+    /// A vector of the removed Todo items.
+    pub(crate) fn take_completed_todos(&self, principal: Principal, completed_before: u64) -> Vec<Todo> {
+        let todos: Vec<Todo> = self
+            .all_todos(principal)
+            .into_iter()
+            .filter(|todo| todo.completed_at.is_some_and(|completed_at| completed_at < completed_before))
+            .collect();
+        let mut store = self.store.borrow_mut();
+        for todo in &todos {
+            store.remove(&(principal, todo.id));
+            self.forget(principal, todo.id);
         }
+        todos
     }
 
-    /// Removes a Todo item from the store.
+    /// Puts back a Todo item that was previously taken out of the store, without reporting a change.
     ///
     /// # Arguments
     ///
     /// * `principal` - The principal identifier.
-    /// * `id` - The unique identifier for the Todo item.
-    pub(crate) fn remove_todo(&self, principal: Principal, id: TodoId) {
-        self.store.borrow_mut().remove(&(principal, id));
+    /// * `todo` - The Todo item to be put back.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an AlreadyExists Error if the identifier is in use.
+    pub(crate) fn put_back_todo(&self, principal: Principal, todo: Todo) -> Result<(), Error> {
+        let mut store = self.store.borrow_mut();
+        if store.contains_key(&(principal, todo.id)) {
+            return Err(Error::AlreadyExists(None));
+        }
+        self.forget(principal, todo.id);
+        store.insert((principal, todo.id), todo.into());
+        Ok(())
     }
 
-    /// Toggles the completion status of a Todo item.
+    /// Lists the records of all principals that cannot be decoded, with pagination.
     ///
     /// # Arguments
     ///
-    /// * `principal` - The principal identifier.
-    /// * `id` - The unique identifier for the Todo item.
+    /// * `paginator` - The paginator for controlling the list output.
     ///
     /// # Returns
     ///
-    /// A Result indicating success or an Error if the Todo item is not found.
-    pub(crate) fn toggle_todo_complete(
+    /// A vector of corrupt records.
+    pub(crate) fn list_corrupt_todos(&self, paginator: Paginator) -> Vec<CorruptTodo> {
+        self.store
+            .borrow()
+            .iter()
+            .filter_map(|((principal, id), record)| match record {
+                TodoRecord::Valid(_) => None,
+                TodoRecord::Corrupt(bytes) => Some(CorruptTodo { principal, id, bytes }),
+            })
+            .skip(paginator.skip())
+            .take(paginator.limit())
+            .collect()
+    }
+
+    /// Returns whether the record stored for a principal under an identifier cannot be decoded.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the record.
+    /// * `id` - The identifier the record is stored under.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing whether the record is corrupt, or None if no record is stored.
+    pub(crate) fn is_corrupt(&self, principal: Principal, id: TodoId) -> Option<bool> {
+        self.store
+            .borrow()
+            .get(&(principal, id))
+            .map(|record| matches!(record, TodoRecord::Corrupt(_)))
+    }
+
+    /// Repairs a corrupt record by replacing it with a new Todo item or removing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the record.
+    /// * `id` - The identifier the record is stored under.
+    /// * `replacement` - The Todo item to store instead, or None to remove the record.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an Error if the record is not found, is not corrupt or the replacement is invalid.
+    pub(crate) fn repair_corrupt_todo(
         &self,
         principal: Principal,
         id: TodoId,
+        replacement: Option<CreateTodo>,
+        now: u64,
     ) -> Result<(), Error> {
-        match self.get_todo(principal, id) {
-            Some(mut todo) => {
-                todo.is_completed = !todo.is_completed;
-                self.store.borrow_mut().insert((principal, id), todo);
+        match self.store.borrow().get(&(principal, id)) {
+            Some(TodoRecord::Corrupt(_)) => {}
+            Some(TodoRecord::Valid(_)) => {
+                return Err(Error::Conflict(Some("Todo item is not corrupt".to_string())))
+            }
+            None => return Err(Error::NotFound),
+        }
+        match replacement {
+            Some(request) => self.create_todo(principal, id, request, now).map(|_| ()),
+            None => {
+                self.store.borrow_mut().remove(&(principal, id));
+                self.forget(principal, id);
                 Ok(())
             }
-            None => Err(Error::NotFound),
         }
     }
 
-    /// Modifies the priority of an existing Todo item.
+    /// Makes the Todo items of a principal match their projection from the event log. Corrupt records the
+    /// projection does not cover are left for `repair_corrupt_todo`.
     ///
     /// # Arguments
     ///
     /// * `principal` - The principal identifier.
-    /// * `id` - The unique identifier for the Todo item.
-    /// * `priority` - The new priority for the Todo item.
+    /// * `projection` - The Todo items replayed from the event log, by identifier.
     ///
     /// # Returns
     ///
-    /// A Result indicating success or an Error if the Todo item is not found.
-    pub(crate) fn modify_todo_priority(
-        &self,
-        principal: Principal,
-        id: TodoId,
-        priority: Priority,
-    ) -> Result<(), Error> {
-        match self.get_todo(principal, id) {
-            Some(mut todo) => {
-                todo.priority = priority;
-                self.store.borrow_mut().insert((principal, id), todo);
-                Ok(())
+    /// The changes made to bring the store in line, to be applied to the indexes but not reported as new events.
+    pub(crate) fn rebuild(&self, principal: Principal, mut projection: BTreeMap<TodoId, Todo>) -> Vec<TodoChange> {
+        let mut changes = Vec::new();
+        for id in self.ids(principal) {
+            let before = self.get_todo(principal, id);
+            match (before, projection.remove(&id)) {
+                (before, Some(after)) if before.as_ref() != Some(&after) => changes.push(self.insert(principal, before, after)),
+                (Some(_), None) => changes.extend(self.remove_todo(principal, id)),
+                _ => {}
             }
-            None => Err(Error::NotFound),
         }
+        for after in projection.into_values() {
+            changes.push(self.insert(principal, None, after));
+        }
+        changes
     }
-    
-    /// Adds a tag to a Todo item.
+
+    /// Stores a Todo item, replacing any previous version.
     ///
     /// # Arguments
     ///
     /// * `principal` - The principal identifier.
-    /// * `id` - The unique identifier for the Todo item.
-    /// * `tag` - The tag to be added.
+    /// * `before` - The previous version of the Todo item, if any.
+    /// * `after` - The Todo item to be stored.
     ///
     /// # Returns
     ///
-    /// A Result indicating success or an Error if the Todo item is not found.
-    pub(crate) fn add_tag_to_todo(
-        &self,
-        principal: Principal,
-        id: TodoId,
-        tag: String,
-    ) -> Result<(), Error> {
-        match self.get_todo(principal, id) {
-            Some(mut todo) => {
-                todo.add_tag(tag);
-                self.store.borrow_mut().insert((principal, id), todo);
-                Ok(())
-            }
-            None => Err(Error::NotFound),
-        }
+    /// The change describing the write.
+    fn insert(&self, principal: Principal, before: Option<Todo>, after: Todo) -> TodoChange {
+        self.store.borrow_mut().insert((principal, after.id), after.clone().into());
+        let key = self.cache_key(principal, after.id);
+        READ_CACHE.with(|cache| cache.borrow_mut().remember(key, Some(after.clone())));
+        TodoChange { principal, before, after: Some(after) }
     }
 
-    /// Removes a tag from a Todo item.
+    /// Reads a Todo item, applies a modification and writes it back, recording the time of the change.
     ///
     /// # Arguments
     ///
     /// * `principal` - The principal identifier.
     /// * `id` - The unique identifier for the Todo item.
-    /// * `tag` - The tag to be removed.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    /// * `modification` - The modification to be applied.
     ///
     /// # Returns
     ///
-    /// A Result indicating success or an Error if the Todo item is not found.
-    pub(crate) fn remove_tag_from_todo(
+    /// A Result containing the change or an Error if the Todo item is not found.
+    fn modify(
         &self,
         principal: Principal,
         id: TodoId,
-        tag: &str,
-    ) -> Result<(), Error> {
-        match self.get_todo(principal, id) {
-            Some(mut todo) => {
-                todo.remove_tag(tag);
-                self.store.borrow_mut().insert((principal, id), todo);
-                Ok(())
-            }
-            None => Err(Error::NotFound),
+        now: u64,
+        modification: impl FnOnce(&mut Todo),
+    ) -> Result<TodoChange, Error> {
+        let before = self.get_todo(principal, id).ok_or(Error::NotFound)?;
+        let mut after = before.clone();
+        modification(&mut after);
+        if after != before {
+            after.updated_at = now;
         }
+        Ok(self.insert(principal, Some(before), after))
+    }
+
+    /// Lists the identifiers of all records of a principal, including records that cannot be decoded.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn ids(&self, principal: Principal) -> Vec<TodoId> {
+        self.store
+            .borrow()
+            .range((principal, TodoId::MIN)..=(principal, TodoId::MAX))
+            .map(|((_, id), _)| id)
+            .collect()
+    }
+
+    /// Moves the records of a principal to their new identifiers, without reporting changes.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `ids` - The renumbering of the principal's Todo items.
+    pub(crate) fn renumber(&self, principal: Principal, ids: &Renumbering) {
+        rewrite_entries(
+            self.store,
+            (principal, TodoId::MIN)..=(principal, TodoId::MAX),
+            |(principal, id), record| {
+                let id = renumbered(ids, id);
+                ((principal, id), record.with_id(id))
+            },
+        );
+        READ_CACHE.with(|cache| cache.borrow_mut().todos.retain(|(_, owner, _), _| *owner != principal));
+    }
+
+    /// Returns the key a Todo item is cached under, which tells stores apart by their address.
+    fn cache_key(&self, principal: Principal, id: TodoId) -> (usize, Principal, TodoId) {
+        (self.store.as_ptr() as usize, principal, id)
+    }
+
+    /// Drops a Todo item from the cache after its record was removed or replaced outside `insert`.
+    fn forget(&self, principal: Principal, id: TodoId) {
+        let key = self.cache_key(principal, id);
+        READ_CACHE.with(|cache| cache.borrow_mut().todos.remove(&key));
     }
 }
 
@@ -224,6 +1967,8 @@ mod tests {
     use std::cell::RefCell;
     use std::collections::HashMap;
 
+    use ic_stable_structures::DefaultMemoryImpl;
+
     struct Store {
         store: RefCell<HashMap<(Principal, TodoId), Todo>>,
     }
@@ -327,4 +2072,524 @@ mod tests {
             Err(Error::NotFound)
         );
     }
-}
\ No newline at end of file
+
+    fn new_todo_store() -> RefCell<TodoStore<DefaultMemoryImpl>> {
+        RefCell::new(TodoStore::new(DefaultMemoryImpl::default()))
+    }
+
+    #[test]
+    fn test_list_todos_skips_corrupt_records() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        wrapper.add_todo(principal, 1, "First".to_string(), Priority::Low, 0).unwrap();
+        store.borrow_mut().insert((principal, 2), TodoRecord::Corrupt(vec![0xff]));
+        wrapper.add_todo(principal, 3, "Third".to_string(), Priority::Low, 0).unwrap();
+
+        let ids: Vec<TodoId> = wrapper
+            .list_todos(principal, Paginator::default(), SortOrder::Created, &InstructionGuard::with_counter(u64::MAX, || 0))
+            .iter()
+            .map(|todo| todo.id)
+            .collect();
+        assert_eq!(ids, vec![1, 3]);
+        assert_eq!(wrapper.get_todo(principal, 2), None);
+    }
+
+    #[test]
+    fn test_cached_reads_follow_writes() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        wrapper.add_todo(principal, 1, "First".to_string(), Priority::Low, 0).unwrap();
+
+        let scope = cache_reads();
+        assert_eq!(wrapper.get_todo(principal, 1).unwrap().description, "First");
+        // A write that bypasses the wrapper is not seen until the scope ends, showing the read was cached.
+        store.borrow_mut().insert((principal, 1), TodoRecord::Corrupt(vec![0xff]));
+        assert_eq!(wrapper.get_todo(principal, 1).unwrap().description, "First");
+        wrapper.update_todo(principal, 1, "Changed".to_string(), 1).unwrap();
+        assert_eq!(wrapper.get_todo(principal, 1).unwrap().description, "Changed");
+        wrapper.remove_todo(principal, 1);
+        assert_eq!(wrapper.get_todo(principal, 1), None);
+
+        let other = new_todo_store();
+        let other_wrapper = TodoStoreWrapper { store: &other };
+        other_wrapper.add_todo(principal, 2, "Elsewhere".to_string(), Priority::Low, 0).unwrap();
+        assert_eq!(wrapper.get_todo(principal, 2), None);
+        assert!(other_wrapper.get_todo(principal, 2).is_some());
+
+        store.borrow_mut().insert((principal, 2), TodoRecord::Corrupt(vec![0xff]));
+        drop(scope);
+        assert!(READ_CACHE.with(|cache| cache.borrow().todos.is_empty()));
+        wrapper.get_todo(principal, 2);
+        assert!(READ_CACHE.with(|cache| cache.borrow().todos.is_empty()));
+    }
+
+    #[test]
+    fn test_interleaved_scopes_and_raw_writes() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        wrapper.add_todo(principal, 1, "First".to_string(), Priority::Low, 0).unwrap();
+
+        let first = cache_reads();
+        let second = cache_reads();
+        wrapper.get_todo(principal, 1);
+        // Scopes may end in any order; caching lasts until the last one ends.
+        drop(first);
+        store.borrow_mut().insert((principal, 1), Todo::new(1, "Raw".to_string(), Priority::Low).into());
+        assert_eq!(wrapper.get_todo(principal, 1).unwrap().description, "First");
+        forget_cached_reads();
+        assert_eq!(wrapper.get_todo(principal, 1).unwrap().description, "Raw");
+        drop(second);
+        assert!(READ_CACHE.with(|cache| cache.borrow().todos.is_empty()));
+
+        let description = with_cached_reads(|| {
+            wrapper.get_todo(principal, 1);
+            assert!(READ_CACHE.with(|cache| !cache.borrow().todos.is_empty()));
+            wrapper.get_todo(principal, 1).unwrap().description
+        });
+        assert_eq!(description, "Raw");
+        assert!(READ_CACHE.with(|cache| cache.borrow().todos.is_empty()));
+    }
+
+    #[test]
+    fn test_repair_corrupt_todo() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        store.borrow_mut().insert((principal, 1), TodoRecord::Corrupt(vec![0xff]));
+        store.borrow_mut().insert((principal, 2), TodoRecord::Corrupt(vec![0xfe]));
+
+        let corrupt = wrapper.list_corrupt_todos(Paginator::default());
+        assert_eq!(corrupt.len(), 2);
+        assert_eq!(corrupt[0], CorruptTodo { principal, id: 1, bytes: vec![0xff] });
+
+        let replacement = CreateTodo { description: "Restored".to_string(), ..Default::default() };
+        assert!(wrapper.repair_corrupt_todo(principal, 1, Some(replacement), 0).is_ok());
+        assert!(wrapper.repair_corrupt_todo(principal, 2, None, 0).is_ok());
+        assert_eq!(wrapper.get_todo(principal, 1).unwrap().description, "Restored");
+        assert!(!store.borrow().contains_key(&(principal, 2)));
+        assert!(matches!(
+            wrapper.repair_corrupt_todo(principal, 1, None, 0),
+            Err(crate::errors::Error::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn test_escalate_stale_todos() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        wrapper.add_todo(principal, 1, "Stale".to_string(), Priority::Low, 10).unwrap();
+        wrapper.add_todo(principal, 2, "Touched".to_string(), Priority::Low, 10).unwrap();
+        wrapper.add_todo(principal, 3, "Highest".to_string(), Priority::High, 10).unwrap();
+        wrapper.update_todo(principal, 2, "Touched again".to_string(), 30).unwrap();
+
+        let changes = wrapper.escalate_stale_todos(principal, 20, 40);
+        assert_eq!(changes.len(), 1);
+        let escalated = wrapper.get_todo(principal, 1).unwrap();
+        assert_eq!(escalated.priority, Priority::Medium);
+        assert!(escalated.escalated);
+        assert_eq!(escalated.updated_at, 40);
+
+        wrapper.modify_todo_priority(principal, 1, Priority::Low, 50).unwrap();
+        assert!(!wrapper.get_todo(principal, 1).unwrap().escalated);
+    }
+
+    #[test]
+    fn test_roll_over_todos() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        for (id, due_date) in [(1, 5), (2, 15), (3, 15), (4, 25)] {
+            let request = CreateTodo {
+                description: format!("Todo {id}"),
+                due_date: Some(due_date),
+                ..Default::default()
+            };
+            wrapper.create_todo(principal, id, request, 0).unwrap();
+        }
+        wrapper.toggle_todo_complete(principal, 3, 16).unwrap();
+
+        let changes = wrapper.roll_over_todos(principal, 10, 20, 30);
+        assert_eq!(changes.len(), 1);
+        let rolled_over = wrapper.get_todo(principal, 2).unwrap();
+        assert_eq!(rolled_over.due_date, Some(15 + NANOS_PER_DAY));
+        assert_eq!(rolled_over.rollover_count, 1);
+        assert_eq!(wrapper.get_todo(principal, 1).unwrap().due_date, Some(5));
+        assert_eq!(wrapper.get_todo(principal, 3).unwrap().rollover_count, 0);
+    }
+
+    #[test]
+    fn test_take_completed_todos() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        wrapper.add_todo(principal, 1, "Old".to_string(), Priority::Low, 0).unwrap();
+        wrapper.add_todo(principal, 2, "Recent".to_string(), Priority::Low, 0).unwrap();
+        wrapper.add_todo(principal, 3, "Open".to_string(), Priority::Low, 0).unwrap();
+        wrapper.toggle_todo_complete(principal, 1, 10).unwrap();
+        wrapper.toggle_todo_complete(principal, 2, 30).unwrap();
+
+        let taken = wrapper.take_completed_todos(principal, 20);
+        assert_eq!(taken.iter().map(|todo| todo.id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(wrapper.get_todo(principal, 1), None);
+
+        let todo = taken.into_iter().next().unwrap();
+        assert!(wrapper.put_back_todo(principal, todo.clone()).is_ok());
+        assert!(matches!(
+            wrapper.put_back_todo(principal, todo),
+            Err(crate::errors::Error::AlreadyExists(_))
+        ));
+    }
+
+    #[test]
+    fn test_remove_sample_todos() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let sample = CreateTodo { description: "Sample".to_string(), ..Default::default() };
+        wrapper.create_sample_todo(principal, 1, sample, 0).unwrap();
+        wrapper.add_todo(principal, 2, "Own".to_string(), Priority::Low, 0).unwrap();
+
+        let removed = wrapper.remove_sample_todos(principal);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(wrapper.all_todos(principal).iter().map(|todo| todo.id).collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn test_list_todos_sorted() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        wrapper.add_todo(principal, 1, "Low".to_string(), Priority::Low, 0).unwrap();
+        wrapper.add_todo(principal, 2, "High".to_string(), Priority::High, 0).unwrap();
+        wrapper.add_todo(principal, 3, "Medium".to_string(), Priority::Medium, 0).unwrap();
+        let patch = PatchTodo { due_date: Some(Some(5)), ..Default::default() };
+        wrapper.patch_todo(principal, 3, patch, 0).unwrap();
+
+        let unlimited = InstructionGuard::with_counter(u64::MAX, || 0);
+        let ids = |sort| -> Vec<TodoId> {
+            wrapper.list_todos(principal, Paginator::default(), sort, &unlimited).iter().map(|todo| todo.id).collect()
+        };
+        assert_eq!(ids(SortOrder::Created), vec![1, 2, 3]);
+        assert_eq!(ids(SortOrder::DueDate), vec![3, 1, 2]);
+        assert_eq!(ids(SortOrder::Priority), vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn test_contexts() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        for id in 1..=3 {
+            wrapper.add_todo(principal, id, format!("Task {id}"), Priority::Medium, 0).unwrap();
+        }
+        wrapper.set_context(principal, 1, Some("home".to_string()), 0).unwrap();
+        wrapper.set_context(principal, 2, Some("@errands".to_string()), 0).unwrap();
+        wrapper.set_context(principal, 3, Some("@home".to_string()), 0).unwrap();
+        wrapper.toggle_todo_complete(principal, 2, 0).unwrap();
+
+        assert_eq!(wrapper.contexts(principal), vec!["@home".to_string()]);
+        let ids: Vec<TodoId> = wrapper
+            .list_todos_in_context(principal, "@home", Paginator::default())
+            .iter()
+            .map(|todo| todo.id)
+            .collect();
+        assert_eq!(ids, vec![1, 3]);
+        assert!(wrapper.set_context(principal, 4, None, 0).is_err());
+    }
+
+    #[test]
+    fn test_find_duplicate() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        wrapper.add_todo(principal, 1, "Buy  milk".to_string(), Priority::Medium, 0).unwrap();
+        wrapper.add_todo(principal, 2, "Call mom".to_string(), Priority::Medium, 0).unwrap();
+        wrapper.toggle_todo_complete(principal, 2, 0).unwrap();
+
+        let unlimited = InstructionGuard::with_counter(u64::MAX, || 0);
+        assert_eq!(wrapper.find_duplicate(principal, " buy Milk", &unlimited), Some(1));
+        assert_eq!(wrapper.find_duplicate(principal, "Call mom", &unlimited), None);
+        assert_eq!(wrapper.find_duplicate(Principal::management_canister(), "Buy milk", &unlimited), None);
+        let exhausted = InstructionGuard::with_counter(0, || 1);
+        assert_eq!(wrapper.find_duplicate(principal, " buy Milk", &exhausted), None);
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        wrapper.add_todo(principal, 1, "Buy  milk".to_string(), Priority::Medium, 0).unwrap();
+        wrapper.add_todo(principal, 2, "buy milk".to_string(), Priority::Medium, 0).unwrap();
+        wrapper.add_todo(principal, 3, "Call mom".to_string(), Priority::Medium, 0).unwrap();
+        wrapper.toggle_todo_complete(principal, 3, 0).unwrap();
+
+        let unlimited = InstructionGuard::with_counter(u64::MAX, || 0);
+        let descriptions = ["Call mom", " BUY milk", "Walk the dog"];
+        assert_eq!(wrapper.find_duplicates(principal, &descriptions, &unlimited), vec![None, Some(1), None]);
+        let exhausted = InstructionGuard::with_counter(0, || 1);
+        assert_eq!(wrapper.find_duplicates(principal, &descriptions, &exhausted), vec![None, None, None]);
+    }
+
+    #[test]
+    fn test_list_todos_after() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        for id in 1..=3 {
+            wrapper.add_todo(principal, id, format!("Todo {id}"), Priority::Medium, 0).unwrap();
+        }
+        wrapper.add_todo(Principal::management_canister(), 4, "Other".to_string(), Priority::Medium, 0).unwrap();
+
+        let unlimited = InstructionGuard::with_counter(u64::MAX, || 0);
+        let ids = |(todos, next): (Vec<Todo>, Option<TodoId>)| (todos.iter().map(|todo| todo.id).collect::<Vec<_>>(), next);
+        assert_eq!(ids(wrapper.list_todos_after(principal, None, 2, &unlimited)), (vec![1, 2], Some(2)));
+        assert_eq!(ids(wrapper.list_todos_after(principal, Some(2), 2, &unlimited)), (vec![3], None));
+        let exhausted = InstructionGuard::with_counter(0, || 1);
+        assert_eq!(ids(wrapper.list_todos_after(principal, Some(1), 2, &exhausted)), (vec![2], Some(2)));
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        for id in 1..=3 {
+            wrapper.add_todo(principal, id, format!("Task {id}"), Priority::Medium, 0).unwrap();
+        }
+        wrapper.set_namespace(principal, 2, Some("work".to_string()), 0).unwrap();
+
+        let ids = |namespace| -> Vec<TodoId> {
+            wrapper.list_todos_in_namespace(principal, namespace, Paginator::default()).iter().map(|todo| todo.id).collect()
+        };
+        assert_eq!(ids(None), vec![1, 3]);
+        assert_eq!(ids(Some("work")), vec![2]);
+        assert!(wrapper.namespace_in_use(principal, "work"));
+        assert!(!wrapper.namespace_in_use(principal, "personal"));
+    }
+
+    #[test]
+    fn test_list_todos_by_energy() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        wrapper.add_todo(principal, 1, "Deep work".to_string(), Priority::High, 0).unwrap();
+        wrapper.add_todo(principal, 2, "Water plants".to_string(), Priority::Low, 0).unwrap();
+        wrapper.add_todo(principal, 3, "Reply to email".to_string(), Priority::High, 0).unwrap();
+        wrapper.add_todo(principal, 4, "Unrated".to_string(), Priority::High, 0).unwrap();
+        wrapper.set_energy(principal, 1, Some(Energy::High), 0).unwrap();
+        wrapper.set_energy(principal, 2, Some(Energy::Low), 0).unwrap();
+        wrapper.set_energy(principal, 3, Some(Energy::Medium), 0).unwrap();
+
+        let ids = |level| -> Vec<TodoId> {
+            wrapper
+                .list_todos_by_energy(principal, level, Paginator::default())
+                .iter()
+                .map(|todo| todo.id)
+                .collect()
+        };
+        assert_eq!(ids(Energy::Low), vec![2]);
+        assert_eq!(ids(Energy::Medium), vec![3, 2]);
+        assert_eq!(ids(Energy::High), vec![1, 3, 2]);
+    }
+
+    #[test]
+    fn test_list_todos_sorted_by_several_keys() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let todos = [
+            (Priority::High, Some(20)),
+            (Priority::Low, Some(10)),
+            (Priority::High, None),
+            (Priority::High, Some(10)),
+            (Priority::Low, None),
+        ];
+        for (id, (priority, due_date)) in (1..).zip(todos) {
+            let request = CreateTodo {
+                description: format!("Task {id}"),
+                priority: Some(priority),
+                due_date,
+                ..CreateTodo::default()
+            };
+            wrapper.create_todo(principal, id, request, 0).unwrap();
+        }
+        let sort = [
+            SortKey { field: SortField::Priority, direction: Order::Desc },
+            SortKey { field: SortField::DueDate, direction: Order::Desc },
+        ];
+
+        let unlimited = InstructionGuard::with_counter(u64::MAX, || 0);
+        let ids = |page, limit| -> Vec<TodoId> {
+            let paginator = Paginator::new(page, Some(limit));
+            wrapper
+                .list_todos_sorted(principal, paginator, &sort, None, &unlimited)
+                .unwrap()
+                .todos
+                .iter()
+                .map(|todo| todo.id)
+                .collect()
+        };
+        assert_eq!(ids(1, 10), vec![1, 4, 3, 2, 5]);
+        assert_eq!(ids(2, 2), vec![3, 2]);
+        assert_eq!(ids(4, 2), Vec::<TodoId>::new());
+        let reversed: Vec<TodoId> = wrapper
+            .list_todos_sorted(principal, Paginator::new(1, Some(2)).descending(), &sort, None, &unlimited)
+            .unwrap()
+            .todos
+            .iter()
+            .map(|todo| todo.id)
+            .collect();
+        assert_eq!(reversed, vec![5, 2]);
+        let newest: Vec<TodoId> = wrapper
+            .list_todos(principal, Paginator::new(2, Some(2)).descending(), SortOrder::Created, &unlimited)
+            .iter()
+            .map(|todo| todo.id)
+            .collect();
+        assert_eq!(newest, vec![3, 2]);
+        assert!(wrapper.list_todos_sorted(principal, Paginator::default(), &[sort[0]; 5], None, &unlimited).is_err());
+
+        // Out of budget, each call ranks one more Todo item, and merging the candidates of every call gives the page.
+        let exhausted = InstructionGuard::with_counter(0, || 1);
+        let mut candidates = Vec::new();
+        let mut after = None;
+        loop {
+            let page = wrapper.list_todos_sorted(principal, Paginator::new(2, Some(2)), &sort, after, &exhausted).unwrap();
+            assert_eq!(page.todos.len(), 1);
+            candidates.extend(page.todos);
+            match page.next {
+                Some(next) => after = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(after, Some(4));
+        let compare = |a: &Todo, b: &Todo| sort.iter().map(|key| key.compare(a, b)).find(|o| o.is_ne()).unwrap();
+        candidates.sort_by(compare);
+        let merged: Vec<TodoId> = candidates.iter().skip(2).take(2).map(|todo| todo.id).collect();
+        assert_eq!(merged, ids(2, 2));
+    }
+
+    #[test]
+    fn test_todo_versions() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        for id in 1..=3 {
+            wrapper.add_todo(principal, id, format!("Task {id}"), Priority::Medium, 10).unwrap();
+        }
+        wrapper.add_tag_to_todo(principal, 2, "home".to_string(), 20).unwrap();
+        wrapper.toggle_todo_complete(principal, 3, 30).unwrap();
+
+        let versions = wrapper.todo_versions(principal, &TodoFilter::default(), 30);
+        assert_eq!(
+            versions,
+            vec![
+                TodoVersion { id: 1, version: 10 },
+                TodoVersion { id: 2, version: 20 },
+                TodoVersion { id: 3, version: 30 },
+            ]
+        );
+        let filter = TodoFilter { completed: Some(false), ..TodoFilter::default() };
+        assert_eq!(wrapper.todo_versions(principal, &filter, 30).len(), 2);
+        let filter = TodoFilter { tag: Some("home".to_string()), ..TodoFilter::default() };
+        assert_eq!(wrapper.todo_versions(principal, &filter, 30), vec![TodoVersion { id: 2, version: 20 }]);
+    }
+
+    #[test]
+    fn test_bulk_update() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        for id in 1..=4 {
+            let request = CreateTodo {
+                description: format!("Task {id}"),
+                priority: None,
+                tags: Some(vec![if id == 4 { "home" } else { "work" }.to_string()]),
+                due_date: Some(if id == 3 { 100 } else { 10 }),
+                project: None,
+                notes: None,
+                recurrence: None,
+            };
+            wrapper.create_todo(principal, id, request, 0).unwrap();
+        }
+        wrapper.toggle_todo_complete(principal, 2, 5).unwrap();
+        let filter = TodoFilter { tag: Some("work".to_string()), overdue: Some(true), ..TodoFilter::default() };
+        let patch = PatchTodo { priority: Some(Priority::High), ..PatchTodo::default() };
+
+        let unlimited = InstructionGuard::with_counter(u64::MAX, || 0);
+        let (outcome, changes) = wrapper.bulk_update(principal, &filter, patch.clone(), None, &unlimited, 50).unwrap();
+        assert_eq!(outcome, BulkUpdate { updated: 1, remaining: false, next: None });
+        assert_eq!(changes[0].after.as_ref().unwrap().id, 1);
+        assert_eq!(wrapper.get_todo(principal, 1).unwrap().priority, Priority::High);
+        assert_eq!(wrapper.get_todo(principal, 4).unwrap().priority, Priority::Medium);
+
+        let (outcome, _) = wrapper.bulk_update(principal, &filter, patch, None, &unlimited, 50).unwrap();
+        assert_eq!(outcome.updated, 0);
+
+        // A spent budget stops after every Todo item, and the calls continue where the previous one stopped.
+        let exhausted = InstructionGuard::with_counter(0, || 1);
+        let patch = PatchTodo { priority: Some(Priority::Low), ..PatchTodo::default() };
+        let filter = TodoFilter::default();
+        let mut after = None;
+        let mut updated = 0;
+        for expected in [Some(1), Some(2), Some(3), Some(4), None] {
+            let (outcome, _) = wrapper.bulk_update(principal, &filter, patch.clone(), after, &exhausted, 50).unwrap();
+            updated += outcome.updated;
+            after = outcome.next;
+            if after.is_none() {
+                assert!(!outcome.remaining);
+                break;
+            }
+            assert_eq!(after, expected);
+        }
+        assert_eq!(updated, 4);
+    }
+
+    #[test]
+    fn test_create_many_stores_all_or_nothing() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let request = |description: &str| CreateTodo { description: description.to_string(), ..Default::default() };
+
+        assert!(matches!(
+            wrapper.create_many(principal, vec![(1, request("First")), (2, request(" "))], 0),
+            Err(crate::errors::Error::InvalidInput(_))
+        ));
+        assert!(store.borrow().is_empty());
+
+        let changes = wrapper.create_many(principal, vec![(1, request("First")), (2, request("Second"))], 7).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert!(changes.iter().all(|change| change.before.is_none()));
+        assert_eq!(wrapper.get_todo(principal, 2).unwrap().created_at, 7);
+        assert_eq!(wrapper.all_todos(principal).len(), 2);
+    }
+
+    #[test]
+    fn test_link_preview_follows_url() {
+        let store = new_todo_store();
+        let wrapper = TodoStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        wrapper.add_todo(principal, 1, "Read docs".to_string(), Priority::Medium, 0).unwrap();
+        assert!(wrapper.set_url(principal, 1, Some("http://example.com".to_string()), 0).is_err());
+        wrapper.set_url(principal, 1, Some("https://example.com".to_string()), 0).unwrap();
+
+        let preview = |url: &str| LinkPreview {
+            url: url.to_string(),
+            title: Some("Example".to_string()),
+            ..LinkPreview::default()
+        };
+        assert!(matches!(
+            wrapper.set_link_preview(principal, 1, preview("https://example.org"), 0),
+            Err(crate::errors::Error::Conflict(_))
+        ));
+        wrapper.set_link_preview(principal, 1, preview("https://example.com"), 0).unwrap();
+        assert!(wrapper.get_todo(principal, 1).unwrap().link_preview.is_some());
+        wrapper.set_url(principal, 1, Some("https://example.org".to_string()), 0).unwrap();
+        assert_eq!(wrapper.get_todo(principal, 1).unwrap().link_preview, None);
+    }
+}
@@ -0,0 +1,280 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{
+    stats::{day_of, Day, NANOS_PER_DAY},
+    store::TodoChange,
+    tags::TagName,
+    todo::Todo,
+    validation::MAX_TAG_LENGTH,
+};
+
+/// Type alias for a week, counted in weeks since the Monday before the Unix epoch.
+pub(crate) type Week = u32;
+
+/// Number of weeks covered by a trend when no range is requested.
+pub(crate) const DEFAULT_TREND_WEEKS: Week = 12;
+
+/// Type alias for the TagTrendStore, which is a StableBTreeMap with a tuple key of (Principal, TagName, Week)
+/// and value of WeeklyTagCounts.
+pub(crate) type TagTrendStore<M> = StableBTreeMap<(Principal, TagName, Week), WeeklyTagCounts, M>;
+
+/// Represents how many Todo items carrying a tag a principal created and completed in a week.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct WeeklyTagCounts {
+    /// Number of Todo items created.
+    pub(crate) created: u32,
+    /// Number of Todo items completed.
+    pub(crate) completed: u32,
+}
+
+impl Storable for WeeklyTagCounts {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Represents the activity of a tag in a single week.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct TagWeek {
+    /// Start of the week, a Monday, in nanoseconds since the Unix epoch.
+    pub(crate) week_start: u64,
+    /// Number of Todo items carrying the tag that were created in the week.
+    pub(crate) created: u32,
+    /// Number of Todo items carrying the tag that were completed in the week.
+    pub(crate) completed: u32,
+}
+
+/// Represents how the use of a tag developed over a range of weeks.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct TagTrends {
+    /// The tag.
+    pub(crate) tag: String,
+    /// Every week of the range, oldest first, including weeks without activity.
+    pub(crate) weeks: Vec<TagWeek>,
+}
+
+/// Returns the week a day falls in. Weeks start on Monday; the Unix epoch was a Thursday.
+///
+/// # Arguments
+///
+/// * `day` - The day, counted in days since the Unix epoch.
+pub(crate) fn week_of(day: Day) -> Week {
+    (day + 3) / 7
+}
+
+/// Returns the start of a week, in nanoseconds since the Unix epoch; the first week starts at the epoch.
+///
+/// # Arguments
+///
+/// * `week` - The week.
+pub(crate) fn week_start(week: Week) -> u64 {
+    u64::from((week * 7).saturating_sub(3)) * NANOS_PER_DAY
+}
+
+/// Wrapper around the TagTrendStore to provide additional functionality.
+pub(crate) struct TagTrendStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<TagTrendStore<M>>,
+}
+
+impl<M: Memory> TagTrendStoreWrapper<'_, M> {
+    /// Updates the weekly counts of the tags affected by a change to a Todo item.
+    ///
+    /// Removing a Todo item keeps its history; reopening a completed item withdraws its completion.
+    ///
+    /// # Arguments
+    ///
+    /// * `change` - The change to a Todo item.
+    pub(crate) fn record_change(&self, change: &TodoChange) {
+        let (before, after) = (change.before.as_ref(), change.after.as_ref());
+        if let (None, Some(todo)) = (before, after) {
+            self.count_creation(change.principal, todo);
+        }
+        let completed_at = |todo: Option<&Todo>| todo.and_then(|todo| todo.completed_at);
+        match (completed_at(before), completed_at(after), before, after) {
+            (None, Some(_), _, Some(todo)) => self.count_completion(change.principal, todo),
+            (Some(completed_at), None, Some(todo), Some(_)) => {
+                self.update(change.principal, todo, completed_at, |counts| {
+                    counts.completed = counts.completed.saturating_sub(1)
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Counts the creation and completion of a Todo item stored before the trends existed.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the Todo item.
+    /// * `todo` - The Todo item.
+    pub(crate) fn add(&self, principal: Principal, todo: &Todo) {
+        if todo.created_at > 0 {
+            self.count_creation(principal, todo);
+        }
+        self.count_completion(principal, todo);
+    }
+
+    /// Returns whether no counts are stored for any principal.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.store.borrow().is_empty()
+    }
+
+    /// Lists the weekly counts of a tag of a principal within a range of weeks.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `tag` - The tag.
+    /// * `from` - The first week of the range.
+    /// * `to` - The last week of the range (inclusive).
+    ///
+    /// # Returns
+    ///
+    /// The trends of the tag, with every week of the range.
+    pub(crate) fn trends(
+        &self,
+        principal: Principal,
+        tag: &str,
+        from: Week,
+        to: Week,
+    ) -> TagTrends {
+        let store = self.store.borrow();
+        let name = TagName::from(tag);
+        let weeks = (from..=to)
+            .map(|week| {
+                let counts = store
+                    .get(&(principal, name.clone(), week))
+                    .unwrap_or_default();
+                TagWeek {
+                    week_start: week_start(week),
+                    created: counts.created,
+                    completed: counts.completed,
+                }
+            })
+            .collect();
+        TagTrends {
+            tag: tag.to_string(),
+            weeks,
+        }
+    }
+
+    /// Counts the creation of a Todo item for each of its tags.
+    fn count_creation(&self, principal: Principal, todo: &Todo) {
+        self.update(principal, todo, todo.created_at, |counts| {
+            counts.created += 1
+        });
+    }
+
+    /// Counts the completion of a Todo item for each of its tags, if it is completed.
+    fn count_completion(&self, principal: Principal, todo: &Todo) {
+        if let Some(completed_at) = todo.completed_at {
+            self.update(principal, todo, completed_at, |counts| {
+                counts.completed += 1
+            });
+        }
+    }
+
+    /// Updates the counts of every tag of a Todo item in the week of a point in time.
+    fn update(
+        &self,
+        principal: Principal,
+        todo: &Todo,
+        at: u64,
+        update: impl Fn(&mut WeeklyTagCounts),
+    ) {
+        let week = week_of(day_of(at));
+        let mut store = self.store.borrow_mut();
+        for tag in &todo.tags {
+            if tag.len() > 4 * MAX_TAG_LENGTH {
+                continue;
+            }
+            let key = (principal, TagName::from(tag.as_str()), week);
+            let mut counts = store.get(&key).unwrap_or_default();
+            update(&mut counts);
+            if counts == WeeklyTagCounts::default() {
+                store.remove(&key);
+            } else {
+                store.insert(key, counts);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::Priority;
+
+    #[test]
+    fn test_weeks_start_on_monday() {
+        // 1970-01-05 was the first Monday after the epoch.
+        assert_eq!(week_of(0), 0);
+        assert_eq!(week_of(3), 0);
+        assert_eq!(week_of(4), 1);
+        assert_eq!(week_start(1), 4 * NANOS_PER_DAY);
+        assert_eq!(week_start(0), 0);
+    }
+
+    #[test]
+    fn test_records_weekly_tag_counts() {
+        let store = RefCell::new(TagTrendStore::new(DefaultMemoryImpl::default()));
+        let wrapper = TagTrendStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let week = 100;
+        let created = Todo {
+            tags: vec!["home".to_string(), "garden".to_string()],
+            created_at: week_start(week),
+            ..Todo::new(1, "Mow".to_string(), Priority::Low)
+        };
+        let completed = Todo {
+            is_completed: true,
+            completed_at: Some(week_start(week + 1) + NANOS_PER_DAY),
+            ..created.clone()
+        };
+        let change = |before: Option<&Todo>, after: Option<&Todo>| TodoChange {
+            principal,
+            before: before.cloned(),
+            after: after.cloned(),
+        };
+
+        wrapper.record_change(&change(None, Some(&created)));
+        wrapper.record_change(&change(Some(&created), Some(&completed)));
+        let trends = wrapper.trends(principal, "home", week, week + 2);
+        assert_eq!(
+            trends
+                .weeks
+                .iter()
+                .map(|week| (week.created, week.completed))
+                .collect::<Vec<_>>(),
+            vec![(1, 0), (0, 1), (0, 0)]
+        );
+        assert_eq!(trends.weeks[1].week_start, week_start(week + 1));
+
+        wrapper.record_change(&change(Some(&completed), Some(&created)));
+        wrapper.record_change(&change(Some(&created), None));
+        let trends = wrapper.trends(principal, "garden", week, week + 1);
+        assert_eq!(trends.weeks[0].created, 1);
+        assert_eq!(trends.weeks[1].completed, 0);
+        assert_eq!(store.borrow().len(), 2);
+
+        let other = RefCell::new(TagTrendStore::new(DefaultMemoryImpl::default()));
+        let other = TagTrendStoreWrapper { store: &other };
+        other.add(principal, &completed);
+        assert_eq!(
+            other.trends(principal, "home", week, week + 1).weeks[1].completed,
+            1
+        );
+    }
+}
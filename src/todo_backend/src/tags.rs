@@ -0,0 +1,344 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{
+    bounded_string::BoundedString, store::TodoChange, todo::Todo, validation::MAX_TAG_LENGTH,
+};
+
+/// Separates the levels of a nested tag, as in `work/projectX`.
+pub(crate) const TAG_SEPARATOR: char = '/';
+
+/// Type alias for a tag as stored in the registry.
+pub(crate) type TagName = BoundedString<{ 4 * MAX_TAG_LENGTH as u32 }>;
+
+/// Type alias for the TagStore, which is a StableBTreeMap with a tuple key of (Principal, TagName) and value of TagTally.
+pub(crate) type TagStore<M> = StableBTreeMap<(Principal, TagName), TagTally, M>;
+
+/// Represents how many Todo items of a principal carry a tag.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct TagTally {
+    /// Number of open Todo items carrying the tag.
+    pub(crate) open: u32,
+    /// Number of completed Todo items carrying the tag.
+    pub(crate) completed: u32,
+}
+
+impl Storable for TagTally {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Represents a single tag of the tag cloud.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct TagCloudEntry {
+    /// The tag.
+    pub(crate) tag: String,
+    /// Number of Todo items carrying the tag.
+    pub(crate) count: u32,
+    /// Number of open Todo items carrying the tag.
+    pub(crate) open: u32,
+    /// Number of completed Todo items carrying the tag.
+    pub(crate) completed: u32,
+}
+
+/// Represents a tag with the tags nested in it.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct TagNode {
+    /// The last level of the tag, such as `projectX`.
+    pub(crate) name: String,
+    /// The full tag, such as `work/projectX`.
+    pub(crate) tag: String,
+    /// Number of open Todo items carrying exactly this tag.
+    pub(crate) open: u32,
+    /// Number of completed Todo items carrying exactly this tag.
+    pub(crate) completed: u32,
+    /// The tags one level below, in alphabetical order.
+    pub(crate) children: Vec<TagNode>,
+}
+
+impl TagNode {
+    /// Adds a tag below this node, creating the levels in between that no Todo item carries.
+    ///
+    /// # Arguments
+    ///
+    /// * `levels` - The levels of the tag below this node.
+    /// * `tally` - The tally of the tag.
+    fn insert(&mut self, levels: &[&str], tally: TagTally) {
+        let Some((name, rest)) = levels.split_first() else {
+            self.open = tally.open;
+            self.completed = tally.completed;
+            return;
+        };
+        let index = match self.children.iter().position(|child| child.name == *name) {
+            Some(index) => index,
+            None => {
+                let tag = if self.tag.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}{TAG_SEPARATOR}{name}", self.tag)
+                };
+                self.children.push(TagNode {
+                    name: name.to_string(),
+                    tag,
+                    open: 0,
+                    completed: 0,
+                    children: Vec::new(),
+                });
+                self.children.len() - 1
+            }
+        };
+        self.children[index].insert(rest, tally);
+    }
+
+    /// Sorts the children of this node and of every node below it by name.
+    fn sort(&mut self) {
+        self.children.sort_by(|a, b| a.name.cmp(&b.name));
+        self.children.iter_mut().for_each(TagNode::sort);
+    }
+}
+
+/// Returns whether a tag is another tag or nested in it, so that `work` covers `work/projectX`.
+///
+/// # Arguments
+///
+/// * `tag` - The tag carried by a Todo item.
+/// * `ancestor` - The tag searched for.
+pub(crate) fn is_within(tag: &str, ancestor: &str) -> bool {
+    tag.strip_prefix(ancestor)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(TAG_SEPARATOR))
+}
+
+/// Wrapper around the TagStore to provide additional functionality.
+pub(crate) struct TagStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<TagStore<M>>,
+}
+
+impl<M: Memory> TagStoreWrapper<'_, M> {
+    /// Updates the tallies of the tags carried by a Todo item before and after a change.
+    ///
+    /// Archived Todo items keep counting, as archiving does not record a change.
+    ///
+    /// # Arguments
+    ///
+    /// * `change` - The change to a Todo item.
+    pub(crate) fn record_change(&self, change: &TodoChange) {
+        if let Some(before) = &change.before {
+            self.tally(change.principal, before, false);
+        }
+        if let Some(after) = &change.after {
+            self.tally(change.principal, after, true);
+        }
+    }
+
+    /// Counts the tags of Todo items stored before the registry existed.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the Todo item.
+    /// * `todo` - The Todo item.
+    pub(crate) fn add(&self, principal: Principal, todo: &Todo) {
+        self.tally(principal, todo, true);
+    }
+
+    /// Returns whether no tag is registered for any principal.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.store.borrow().is_empty()
+    }
+
+    /// Lists every tag a principal uses, in alphabetical order.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    ///
+    /// # Returns
+    ///
+    /// A vector of tags with the number of open and completed Todo items carrying them.
+    pub(crate) fn cloud(&self, principal: Principal) -> Vec<TagCloudEntry> {
+        self.store
+            .borrow()
+            .range((principal, TagName::from(""))..)
+            .take_while(|((p, _), _)| p == &principal)
+            .map(|((_, tag), tally)| TagCloudEntry {
+                tag: tag.0,
+                count: tally.open + tally.completed,
+                open: tally.open,
+                completed: tally.completed,
+            })
+            .collect()
+    }
+
+    /// Arranges every tag a principal uses by nesting.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    ///
+    /// # Returns
+    ///
+    /// A vector of the top-level tags, each with the tags nested in it, in alphabetical order.
+    pub(crate) fn tree(&self, principal: Principal) -> Vec<TagNode> {
+        let mut root = TagNode {
+            name: String::new(),
+            tag: String::new(),
+            open: 0,
+            completed: 0,
+            children: Vec::new(),
+        };
+        for entry in self.cloud(principal) {
+            let levels: Vec<&str> = entry.tag.split(TAG_SEPARATOR).collect();
+            let tally = TagTally {
+                open: entry.open,
+                completed: entry.completed,
+            };
+            root.insert(&levels, tally);
+        }
+        root.sort();
+        root.children
+    }
+
+    /// Adds a Todo item to, or withdraws it from, the tallies of its tags.
+    /// Tags longer than a valid tag cannot be keyed and are left out.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the Todo item.
+    /// * `todo` - The Todo item.
+    /// * `add` - Whether the Todo item is added rather than withdrawn.
+    fn tally(&self, principal: Principal, todo: &Todo, add: bool) {
+        let mut store = self.store.borrow_mut();
+        for tag in &todo.tags {
+            if tag.len() > 4 * MAX_TAG_LENGTH {
+                continue;
+            }
+            let key = (principal, TagName::from(tag.as_str()));
+            let mut tally = store.get(&key).unwrap_or_default();
+            let count = if todo.is_completed {
+                &mut tally.completed
+            } else {
+                &mut tally.open
+            };
+            *count = if add {
+                *count + 1
+            } else {
+                count.saturating_sub(1)
+            };
+            if tally == TagTally::default() {
+                store.remove(&key);
+            } else {
+                store.insert(key, tally);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::Priority;
+
+    #[test]
+    fn test_tallies_follow_changes() {
+        let store = RefCell::new(TagStore::new(DefaultMemoryImpl::default()));
+        let wrapper = TagStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let todo = Todo {
+            tags: vec!["home".to_string(), "work".to_string()],
+            ..Todo::new(1, "Tidy desk".to_string(), Priority::Low)
+        };
+        let other = Todo {
+            tags: vec!["work".to_string()],
+            ..Todo::new(2, "Email boss".to_string(), Priority::Low)
+        };
+        let mut completed = todo.clone();
+        completed.set_completed(true, 1);
+        let change = |before: Option<&Todo>, after: Option<&Todo>| TodoChange {
+            principal,
+            before: before.cloned(),
+            after: after.cloned(),
+        };
+
+        wrapper.record_change(&change(None, Some(&todo)));
+        wrapper.add(principal, &other);
+        wrapper.record_change(&change(Some(&todo), Some(&completed)));
+        let entry = |tag: &str, open, completed| TagCloudEntry {
+            tag: tag.to_string(),
+            count: open + completed,
+            open,
+            completed,
+        };
+        assert_eq!(
+            wrapper.cloud(principal),
+            vec![entry("home", 0, 1), entry("work", 1, 1)]
+        );
+
+        wrapper.record_change(&change(Some(&completed), None));
+        assert_eq!(wrapper.cloud(principal), vec![entry("work", 1, 0)]);
+        assert_eq!(wrapper.cloud(Principal::management_canister()), vec![]);
+    }
+
+    #[test]
+    fn test_tree() {
+        let store = RefCell::new(TagStore::new(DefaultMemoryImpl::default()));
+        let wrapper = TagStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let todo = Todo {
+            tags: ["work/projectX/docs", "work", "work-life", "home/garden"]
+                .map(String::from)
+                .to_vec(),
+            ..Todo::new(1, "Plan".to_string(), Priority::Low)
+        };
+        wrapper.add(principal, &todo);
+
+        let node = |name: &str, tag: &str, open, children| TagNode {
+            name: name.to_string(),
+            tag: tag.to_string(),
+            open,
+            completed: 0,
+            children,
+        };
+        assert_eq!(
+            wrapper.tree(principal),
+            vec![
+                node(
+                    "home",
+                    "home",
+                    0,
+                    vec![node("garden", "home/garden", 1, vec![])]
+                ),
+                node(
+                    "work",
+                    "work",
+                    1,
+                    vec![node(
+                        "projectX",
+                        "work/projectX",
+                        0,
+                        vec![node("docs", "work/projectX/docs", 1, vec![])]
+                    )]
+                ),
+                node("work-life", "work-life", 1, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_within() {
+        assert!(is_within("work", "work"));
+        assert!(is_within("work/projectX", "work"));
+        assert!(!is_within("work-life", "work"));
+        assert!(!is_within("work", "work/projectX"));
+    }
+}
@@ -0,0 +1,414 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_cdk::api::management_canister::http_request::{
+    CanisterHttpRequestArgument, HttpHeader, HttpMethod,
+};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    messages::{local_time, Language, Message},
+    todo::Todo,
+};
+
+/// Path of the route receiving updates from the Telegram bot.
+pub(crate) const WEBHOOK_PATH: &str = "/telegram/webhook";
+
+/// Header carrying the secret token Telegram was configured to send with every update.
+pub(crate) const SECRET_HEADER: &str = "X-Telegram-Bot-Api-Secret-Token";
+
+/// Base URL of the Telegram Bot API.
+const API_URL: &str = "https://api.telegram.org";
+
+/// Maximum size of the response accepted from the Telegram Bot API, in bytes.
+const MAX_RESPONSE_BYTES: u64 = 2_048;
+
+/// Type alias for the TelegramStore, which is a StableBTreeMap keyed by Principal with a value of TelegramLink.
+pub(crate) type TelegramStore<M> = StableBTreeMap<Principal, TelegramLink, M>;
+
+/// Type alias for the TelegramChatIndex, which is a StableBTreeMap keyed by (chat, principal) for every link
+/// in the TelegramStore, so that the principal a webhook update belongs to is found without a full scan.
+/// The chat identifier is stored as the bits of its `i64`.
+pub(crate) type TelegramChatIndex<M> = StableBTreeMap<(u64, Principal), (), M>;
+
+/// Represents the Telegram chat a principal receives notifications in and adds Todo items from.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct TelegramLink {
+    /// The identifier of the Telegram chat.
+    pub(crate) chat_id: i64,
+    /// The token of the bot sending the messages.
+    pub(crate) bot_token: String,
+    /// The secret token the bot sends with every webhook update.
+    pub(crate) secret: String,
+    /// Time up to which due dates have been announced, in nanoseconds since the Unix epoch.
+    pub(crate) announced_until: u64,
+}
+
+impl Storable for TelegramLink {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Represents a text message received by the bot.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct IncomingMessage {
+    /// The identifier of the chat the message was sent in.
+    pub(crate) chat_id: i64,
+    /// The text of the message.
+    pub(crate) text: String,
+}
+
+/// Represents a command sent to the bot.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Command {
+    /// Adds a Todo item with the given description.
+    Add(String),
+}
+
+/// Derives the webhook secret token of a bot, so that only Telegram, which knows it, can post updates.
+///
+/// # Arguments
+///
+/// * `bot_token` - The token of the bot.
+///
+/// # Returns
+///
+/// The secret token, to be passed as `secret_token` when setting the webhook of the bot.
+pub(crate) fn webhook_secret(bot_token: &str) -> String {
+    Sha256::digest(format!("webhook:{bot_token}"))
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Extracts the text message from a webhook update.
+///
+/// # Arguments
+///
+/// * `body` - The JSON body of the update.
+///
+/// # Returns
+///
+/// An Option containing the message, or None if the update is not a text message.
+pub(crate) fn parse_update(body: &[u8]) -> Option<IncomingMessage> {
+    let update: serde_json::Value = serde_json::from_slice(body).ok()?;
+    let message = update.get("message")?;
+    Some(IncomingMessage {
+        chat_id: message.get("chat")?.get("id")?.as_i64()?,
+        text: message.get("text")?.as_str()?.to_string(),
+    })
+}
+
+/// Parses a command sent to the bot, such as `/add buy milk`.
+///
+/// # Arguments
+///
+/// * `text` - The text of the message.
+///
+/// # Returns
+///
+/// An Option containing the command, or None if the text is not a known command.
+pub(crate) fn parse_command(text: &str) -> Option<Command> {
+    let (command, argument) = text.trim().split_once(' ').unwrap_or((text.trim(), ""));
+    // Commands in group chats may be addressed to a bot, as in `/add@todo_bot`.
+    match command.split('@').next()? {
+        "/add" => Some(Command::Add(argument.trim().to_string())),
+        _ => None,
+    }
+}
+
+/// Serializes a reply to a webhook update, which Telegram sends as a message without an outcall.
+///
+/// # Arguments
+///
+/// * `chat_id` - The identifier of the chat.
+/// * `text` - The text of the reply.
+pub(crate) fn reply(chat_id: i64, text: &str) -> Vec<u8> {
+    json!({ "method": "sendMessage", "chat_id": chat_id, "text": text })
+        .to_string()
+        .into_bytes()
+}
+
+/// Builds the HTTPS outcall sending a message to a chat.
+///
+/// Telegram does not deduplicate messages, and every replica of the subnet sends the request,
+/// so the message may arrive more than once. The caller attaches the transform.
+///
+/// # Arguments
+///
+/// * `link` - The Telegram link of the recipient.
+/// * `text` - The text of the message.
+///
+/// # Returns
+///
+/// The HTTP request argument for the management canister.
+pub(crate) fn send_message_request(link: &TelegramLink, text: &str) -> CanisterHttpRequestArgument {
+    let body = json!({ "chat_id": link.chat_id, "text": text });
+    CanisterHttpRequestArgument {
+        url: format!("{API_URL}/bot{}/sendMessage", link.bot_token),
+        max_response_bytes: Some(MAX_RESPONSE_BYTES),
+        method: HttpMethod::POST,
+        headers: vec![HttpHeader {
+            name: "Content-Type".to_string(),
+            value: "application/json".to_string(),
+        }],
+        body: Some(body.to_string().into_bytes()),
+        transform: None,
+    }
+}
+
+/// Lists the messages announcing due dates that came up in a period.
+/// A Todo item is announced `lead` before its due date, and again once it is overdue unless overdue alerts are muted for it.
+///
+/// # Arguments
+///
+/// * `todos` - All Todo items of the principal.
+/// * `since` - The end of the previously announced period, exclusive.
+/// * `until` - The end of the period to announce, inclusive.
+/// * `lead` - How long before its due date a Todo item is announced, in nanoseconds.
+/// * `utc_offset` - The offset of the principal's time zone from UTC, in nanoseconds.
+/// * `language` - The language of the principal.
+///
+/// # Returns
+///
+/// The texts of the messages to send.
+pub(crate) fn due_date_messages(
+    todos: &[Todo],
+    since: u64,
+    until: u64,
+    lead: u64,
+    utc_offset: i64,
+    language: Language,
+) -> Vec<String> {
+    let mut messages = Vec::new();
+    for todo in todos.iter().filter(|todo| !todo.is_completed) {
+        let Some(due_date) = todo.due_date else {
+            continue;
+        };
+        if since < due_date && due_date <= until {
+            if todo.overdue_muted {
+                continue;
+            }
+            let message = Message::Overdue {
+                description: &todo.description,
+            };
+            messages.push(message.render(language));
+        } else if since + lead < due_date && due_date <= until + lead {
+            let message = Message::DueSoon {
+                description: &todo.description,
+                due: &local_time(due_date, utc_offset, language),
+            };
+            messages.push(message.render(language));
+        }
+    }
+    messages
+}
+
+/// Wrapper around the TelegramStore to provide additional functionality.
+pub(crate) struct TelegramStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<TelegramStore<M>>,
+}
+
+impl<M: Memory> TelegramStoreWrapper<'_, M> {
+    /// Links a principal to a Telegram chat, replacing any previous link.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `chat_id` - The identifier of the Telegram chat.
+    /// * `bot_token` - The validated token of the bot.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// The secret token of the webhook.
+    pub(crate) fn link(
+        &self,
+        principal: Principal,
+        chat_id: i64,
+        bot_token: String,
+        now: u64,
+    ) -> String {
+        let secret = webhook_secret(&bot_token);
+        let link = TelegramLink {
+            chat_id,
+            bot_token,
+            secret: secret.clone(),
+            announced_until: now,
+        };
+        self.store.borrow_mut().insert(principal, link);
+        secret
+    }
+
+    /// Removes the Telegram link of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn unlink(&self, principal: Principal) {
+        self.store.borrow_mut().remove(&principal);
+    }
+
+    /// Retrieves the Telegram link of a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    ///
+    /// # Returns
+    ///
+    /// An Option containing the link if the principal linked a chat, otherwise None.
+    pub(crate) fn get(&self, principal: Principal) -> Option<TelegramLink> {
+        self.store.borrow().get(&principal)
+    }
+
+    /// Lists every linked principal.
+    ///
+    /// # Returns
+    ///
+    /// A vector of principals and their Telegram links.
+    pub(crate) fn all(&self) -> Vec<(Principal, TelegramLink)> {
+        self.store.borrow().iter().collect()
+    }
+
+    /// Records the time up to which due dates have been announced to a principal.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `announced_until` - The end of the announced period.
+    pub(crate) fn set_announced_until(&self, principal: Principal, announced_until: u64) {
+        let mut store = self.store.borrow_mut();
+        if let Some(link) = store.get(&principal) {
+            store.insert(
+                principal,
+                TelegramLink {
+                    announced_until,
+                    ..link
+                },
+            );
+        }
+    }
+}
+
+/// Wrapper around the TelegramChatIndex to provide additional functionality.
+pub(crate) struct TelegramChatIndexWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<TelegramChatIndex<M>>,
+}
+
+impl<M: Memory> TelegramChatIndexWrapper<'_, M> {
+    /// Records that a principal linked a chat.
+    ///
+    /// # Arguments
+    ///
+    /// * `chat_id` - The identifier of the Telegram chat.
+    /// * `principal` - The principal identifier.
+    pub(crate) fn insert(&self, chat_id: i64, principal: Principal) {
+        self.store
+            .borrow_mut()
+            .insert((chat_id as u64, principal), ());
+    }
+
+    /// Records that a principal no longer links a chat.
+    ///
+    /// # Arguments
+    ///
+    /// * `chat_id` - The identifier of the Telegram chat.
+    /// * `principal` - The principal identifier.
+    pub(crate) fn remove(&self, chat_id: i64, principal: Principal) {
+        self.store.borrow_mut().remove(&(chat_id as u64, principal));
+    }
+
+    /// Lists the principals linked to a chat, each possibly by a bot of their own.
+    ///
+    /// # Arguments
+    ///
+    /// * `chat_id` - The identifier of the Telegram chat.
+    pub(crate) fn principals(&self, chat_id: i64) -> Vec<Principal> {
+        self.store
+            .borrow()
+            .range((chat_id as u64, Principal::management_canister())..)
+            .take_while(|((chat, _), _)| *chat == chat_id as u64)
+            .map(|((_, principal), _)| principal)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::{stats::NANOS_PER_DAY, todo::Priority};
+
+    #[test]
+    fn test_parse_update_and_command() {
+        let body =
+            br#"{"update_id":1,"message":{"chat":{"id":-42},"text":"/add@todo_bot  buy milk "}}"#;
+        let message = parse_update(body).unwrap();
+        assert_eq!(message.chat_id, -42);
+        assert_eq!(
+            parse_command(&message.text),
+            Some(Command::Add("buy milk".to_string()))
+        );
+        assert_eq!(parse_command("/start"), None);
+        assert_eq!(parse_update(br#"{"update_id":2}"#), None);
+    }
+
+    #[test]
+    fn test_due_date_messages() {
+        let due = |id, due_date| Todo {
+            due_date: Some(due_date),
+            ..Todo::new(id, format!("Task {id}"), Priority::Medium)
+        };
+        let todos = vec![
+            due(1, 10),
+            due(2, 20 + NANOS_PER_DAY),
+            due(3, 30 + NANOS_PER_DAY),
+            due(4, 5),
+        ];
+
+        let messages = due_date_messages(&todos, 5, 20, NANOS_PER_DAY, 0, Language::English);
+        assert_eq!(
+            messages,
+            vec![
+                "Overdue: Task 1".to_string(),
+                "Due on 1970-01-02 at 00:00 (UTC+00:00): Task 2".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_link_and_index_chats() {
+        let store = RefCell::new(TelegramStore::new(DefaultMemoryImpl::default()));
+        let wrapper = TelegramStoreWrapper { store: &store };
+        let index = RefCell::new(TelegramChatIndex::new(DefaultMemoryImpl::default()));
+        let index = TelegramChatIndexWrapper { store: &index };
+        let principal = Principal::anonymous();
+        let other = Principal::from_slice(&[1]);
+
+        let secret = wrapper.link(principal, -42, "123:abc".to_string(), 0);
+        assert_eq!(wrapper.get(principal).unwrap().secret, secret);
+        index.insert(-42, principal);
+        index.insert(-42, other);
+        index.insert(42, other);
+        assert_eq!(index.principals(-42), vec![other, principal]);
+        assert_eq!(index.principals(7), vec![]);
+
+        wrapper.unlink(principal);
+        index.remove(-42, principal);
+        assert_eq!(wrapper.get(principal), None);
+        assert_eq!(index.principals(-42), vec![other]);
+        assert_eq!(index.principals(42), vec![other]);
+    }
+}
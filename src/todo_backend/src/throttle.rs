@@ -0,0 +1,147 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use candid::Principal;
+
+use crate::errors::Error;
+
+/// Length of a throttling window, in nanoseconds.
+pub(crate) const WINDOW_NANOS: u64 = 60 * 1_000_000_000;
+
+/// Maximum number of expensive requests, such as reads of the HTTP API, per caller and window.
+pub(crate) const MAX_READS_PER_WINDOW: u32 = 30;
+
+/// Maximum number of callers tracked at once; beyond it, callers whose window ended are forgotten first.
+pub(crate) const MAX_TRACKED_CALLERS: usize = 10_000;
+
+/// Whether the HTTP gateway in front of the canister sets `X-Real-IP` itself. The boundary nodes of the Internet
+/// Computer do not, so a client could choose the value and the header is ignored.
+pub(crate) const TRUST_REAL_IP: bool = false;
+
+thread_local! {
+    /// Expensive requests per caller in the current window.
+    /// Kept on the heap, so an upgrade resets it. Query calls discard their state changes, so only update
+    /// calls are throttled; queries bound their work with the instruction budget instead.
+    pub(crate) static THROTTLE: RefCell<Throttle> = RefCell::new(Throttle::default());
+}
+
+/// Represents who an expensive request is counted against.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Caller {
+    /// An authenticated principal.
+    Principal(Principal),
+    /// A client of the HTTP gateway, by the IP address the gateway forwarded.
+    Ip(String),
+}
+
+/// Counts expensive requests per caller in fixed windows.
+#[derive(Default)]
+pub(crate) struct Throttle {
+    /// Start of the current window and number of requests in it, by caller.
+    windows: BTreeMap<Caller, (u64, u32)>,
+}
+
+impl Throttle {
+    /// Counts an expensive request of a caller, refusing it once the caller reached the limit of the window.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - The caller.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or a RateLimited Error.
+    pub(crate) fn admit(&mut self, caller: Caller, now: u64) -> Result<(), Error> {
+        if !self.windows.contains_key(&caller) && self.windows.len() >= MAX_TRACKED_CALLERS {
+            self.windows
+                .retain(|_, (start, _)| now.saturating_sub(*start) < WINDOW_NANOS);
+            if self.windows.len() >= MAX_TRACKED_CALLERS {
+                return Err(Error::RateLimited(Some(
+                    "Too many callers; try again later".to_string(),
+                )));
+            }
+        }
+        let (start, reads) = self.windows.entry(caller).or_insert((now, 0));
+        if now.saturating_sub(*start) >= WINDOW_NANOS {
+            *start = now;
+            *reads = 0;
+        }
+        if *reads >= MAX_READS_PER_WINDOW {
+            let seconds = (*start + WINDOW_NANOS - now).div_ceil(1_000_000_000);
+            return Err(Error::RateLimited(Some(format!(
+                "Too many requests; try again in {seconds} s"
+            ))));
+        }
+        *reads += 1;
+        Ok(())
+    }
+}
+
+/// Returns the IP address of the client of an HTTP request, as forwarded by the HTTP gateway. Clients may send
+/// an `X-Forwarded-For` header of their own, which the gateway appends the address it saw to, so only the last
+/// hop is the gateway's.
+///
+/// # Arguments
+///
+/// * `forwarded_for` - The value of the `X-Forwarded-For` header.
+/// * `real_ip` - The value of the `X-Real-IP` header.
+/// * `trust_real_ip` - Whether the gateway sets `X-Real-IP` itself, replacing any value sent by the client.
+///
+/// # Returns
+///
+/// The IP address, or None if the gateway forwarded none.
+pub(crate) fn client_ip(
+    forwarded_for: Option<&str>,
+    real_ip: Option<&str>,
+    trust_real_ip: bool,
+) -> Option<String> {
+    forwarded_for
+        .and_then(|value| value.rsplit(',').next())
+        .or(real_ip.filter(|_| trust_real_ip))
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admit_limits_reads_per_window() {
+        let mut throttle = Throttle::default();
+        let caller = Caller::Principal(Principal::anonymous());
+        for _ in 0..MAX_READS_PER_WINDOW {
+            assert!(throttle.admit(caller.clone(), 10).is_ok());
+        }
+        assert!(matches!(
+            throttle.admit(caller.clone(), WINDOW_NANOS),
+            Err(Error::RateLimited(_))
+        ));
+        assert!(throttle
+            .admit(Caller::Ip("1.2.3.4".to_string()), 20)
+            .is_ok());
+        assert!(throttle.admit(caller, 10 + WINDOW_NANOS).is_ok());
+    }
+
+    #[test]
+    fn test_client_ip() {
+        assert_eq!(
+            client_ip(Some("1.2.3.4, 10.0.0.1"), Some("10.0.0.1"), true),
+            Some("10.0.0.1".to_string())
+        );
+        assert_eq!(
+            client_ip(None, Some(" 5.6.7.8 "), true),
+            Some("5.6.7.8".to_string())
+        );
+        assert_eq!(client_ip(None, Some("5.6.7.8"), false), None);
+        assert_eq!(client_ip(Some(""), None, true), None);
+    }
+
+    #[test]
+    fn test_client_ip_ignores_spoofed_first_hop() {
+        let key = client_ip(Some("9.9.9.9"), None, false);
+        assert_eq!(client_ip(Some("1.1.1.1, 9.9.9.9"), None, false), key);
+        assert_eq!(client_ip(Some("2.2.2.2, 9.9.9.9"), None, false), key);
+    }
+}
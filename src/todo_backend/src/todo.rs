@@ -1,28 +1,62 @@
 use std::borrow::Cow;
 
-
-use candid::{CandidType, Decode, Deserialize, Encode};
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
 use ic_stable_structures::{storable::Bound, Storable};
+use serde::Serialize;
+
+use crate::{geo::GeoPoint, goals::GoalId, labels::LabelId, link_preview::LinkPreview};
 
 /// Type alias for the unique identifier of a Todo item.
-pub(crate) type TodoId = u32;
+pub(crate) type TodoId = u64;
 
 /// Represents the priority level of a Todo item.
-#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[derive(CandidType, Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
 pub(crate) enum Priority {
     Low,
+    #[default]
     Medium,
     High,
 }
 
-impl Default for Priority {
-    fn default() -> Self {
-        Priority::Medium
+impl Priority {
+    /// Returns the rank of the priority, where more important priorities rank lower.
+    pub(crate) fn rank(self) -> u8 {
+        match self {
+            Priority::High => 0,
+            Priority::Medium => 1,
+            Priority::Low => 2,
+        }
+    }
+
+    /// Returns the next higher priority, or None if the priority is already the highest.
+    pub(crate) fn raised(self) -> Option<Priority> {
+        match self {
+            Priority::Low => Some(Priority::Medium),
+            Priority::Medium => Some(Priority::High),
+            Priority::High => None,
+        }
     }
 }
 
+/// Represents how much energy or focus a Todo item takes, ordered from least to most.
+#[derive(CandidType, Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Energy {
+    Low,
+    Medium,
+    High,
+}
+
+/// Represents a step of a Todo item, ticked off on its own.
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub(crate) struct Subtask {
+    /// Title of the subtask.
+    pub(crate) title: String,
+    /// Whether the subtask is done.
+    pub(crate) done: bool,
+}
+
 /// Represents a Todo item with an ID, text description, and completion status.
-#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)] // Add PartialEq trait
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq)] // Add PartialEq trait
 pub(crate) struct Todo {
     /// Unique identifier for the Todo item.
     pub(crate) id: TodoId,
@@ -34,6 +68,97 @@ pub(crate) struct Todo {
     pub(crate) priority: Priority,
     /// Tags associated with the Todo item.
     pub(crate) tags: Vec<String>,
+    /// Optional due date, in nanoseconds since the Unix epoch.
+    pub(crate) due_date: Option<u64>,
+    /// Optional project the Todo item belongs to.
+    pub(crate) project: Option<String>,
+    /// Optional GTD context the Todo item can be done in, such as `@home`, kept apart from tags.
+    pub(crate) context: Option<String>,
+    /// Optional energy level the Todo item takes.
+    pub(crate) energy: Option<Energy>,
+    /// Optional person or party the Todo item was delegated to and is waiting on.
+    pub(crate) waiting_on: Option<String>,
+    /// Time since which the Todo item is waiting on `waiting_on`, in nanoseconds since the Unix epoch.
+    pub(crate) waiting_since: Option<u64>,
+    /// Optional web page the Todo item refers to.
+    pub(crate) url: Option<String>,
+    /// Title and description of the page at `url`, once fetched.
+    pub(crate) link_preview: Option<LinkPreview>,
+    /// Optional free-form notes on the Todo item.
+    pub(crate) notes: Option<String>,
+    /// Creation time, in nanoseconds since the Unix epoch; 0 for items created before it was recorded.
+    pub(crate) created_at: u64,
+    /// Completion time, in nanoseconds since the Unix epoch, if the Todo item is completed.
+    pub(crate) completed_at: Option<u64>,
+    /// Time of the last change, in nanoseconds since the Unix epoch; 0 if never changed since it was recorded.
+    pub(crate) updated_at: u64,
+    /// Whether the priority was raised automatically because the Todo item was left untouched.
+    pub(crate) escalated: bool,
+    /// Optional board column the Todo item is placed in, within the board of its project.
+    pub(crate) column_id: Option<u64>,
+    /// Whether the Todo item is an onboarding sample, removed together with the other samples on request.
+    pub(crate) sample: bool,
+    /// Optional rule by which the next occurrence is created once the Todo item is completed, such as `every mon 09:00`.
+    pub(crate) recurrence: Option<String>,
+    /// Whether the owner opted out of being notified that the Todo item is overdue.
+    pub(crate) overdue_muted: bool,
+    /// Number of times the Todo item was left unfinished on the day it was due and moved to the next day.
+    pub(crate) rollover_count: u32,
+    /// Labels attached to the Todo item, in the order they were attached.
+    pub(crate) label_ids: Vec<LabelId>,
+    /// Optional Todo item in another canister this one refers to, as the canister and the identifier within it.
+    pub(crate) linked: Option<(Principal, TodoId)>,
+    /// Optional goal the Todo item contributes to.
+    pub(crate) goal_id: Option<GoalId>,
+    /// Whether the recurring Todo item is tracked as a habit: checking in records the occurrence and moves
+    /// the Todo item on to the next one, and completing it retires the habit without creating another.
+    pub(crate) habit: bool,
+    /// Optional place the Todo item is done at, such as a shop for an errand.
+    pub(crate) location: Option<GeoPoint>,
+    /// Steps of the Todo item, in order.
+    pub(crate) subtasks: Vec<Subtask>,
+    /// Optional namespace the Todo item belongs to, or None for the default space.
+    pub(crate) namespace: Option<String>,
+    /// Optional estimate of how long the Todo item takes, in minutes.
+    pub(crate) estimated_minutes: Option<u32>,
+}
+
+/// Represents a request to create a fully populated Todo item in a single call.
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub(crate) struct CreateTodo {
+    /// Text description of the Todo item.
+    pub(crate) description: String,
+    /// Priority level of the Todo item, defaults to `Priority::Medium`.
+    pub(crate) priority: Option<Priority>,
+    /// Tags associated with the Todo item.
+    pub(crate) tags: Option<Vec<String>>,
+    /// Optional due date, in nanoseconds since the Unix epoch.
+    pub(crate) due_date: Option<u64>,
+    /// Optional project the Todo item belongs to.
+    pub(crate) project: Option<String>,
+    /// Optional free-form notes on the Todo item.
+    pub(crate) notes: Option<String>,
+    /// Optional recurrence rule, such as `every day`, `every mon,wed 09:00` or `1st of month`.
+    pub(crate) recurrence: Option<String>,
+}
+
+/// Represents a partial update of a Todo item; only the provided fields are changed.
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub(crate) struct PatchTodo {
+    /// New text description of the Todo item.
+    pub(crate) description: Option<String>,
+    /// New priority level of the Todo item.
+    pub(crate) priority: Option<Priority>,
+    /// New due date, in nanoseconds since the Unix epoch, or Some(None) to clear it.
+    pub(crate) due_date: Option<Option<u64>>,
+    /// New completion status of the Todo item.
+    pub(crate) is_completed: Option<bool>,
+    /// New tags, replacing the existing ones.
+    pub(crate) tags: Option<Vec<String>>,
+    /// New project the Todo item belongs to, or Some(None) to take it out of its project.
+    pub(crate) project: Option<Option<String>>,
+    /// New free-form notes on the Todo item, or Some(None) to clear them.
+    pub(crate) notes: Option<Option<String>>,
 }
 
 impl Todo {
@@ -53,11 +178,158 @@ impl Todo {
             id,
             description,
             is_completed: false,
-            priority: priority,
+            priority,
             tags: Vec::new(),
+            due_date: None,
+            project: None,
+            context: None,
+            energy: None,
+            waiting_on: None,
+            waiting_since: None,
+            url: None,
+            link_preview: None,
+            notes: None,
+            created_at: 0,
+            completed_at: None,
+            updated_at: 0,
+            escalated: false,
+            column_id: None,
+            sample: false,
+            recurrence: None,
+            overdue_muted: false,
+            rollover_count: 0,
+            label_ids: Vec::new(),
+            linked: None,
+            goal_id: None,
+            habit: false,
+            location: None,
+            subtasks: Vec::new(),
+            namespace: None,
+            estimated_minutes: None,
+        }
+    }
+
+    /// Creates a new Todo item from a creation request.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The unique identifier for the Todo item.
+    /// * `request` - The fields of the new Todo item.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `Todo`.
+    pub(crate) fn from_request(id: TodoId, request: CreateTodo) -> Self {
+        Self {
+            tags: request.tags.unwrap_or_default(),
+            due_date: request.due_date,
+            project: request.project,
+            notes: request.notes,
+            recurrence: request.recurrence,
+            ..Self::new(id, request.description, request.priority.unwrap_or_default())
+        }
+    }
+
+    /// Replaces every field covered by a creation request, keeping the identity and completion status.
+    /// A Todo item moved to another project leaves its board column.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The new fields of the Todo item.
+    pub(crate) fn replace_with_request(&mut self, request: CreateTodo) {
+        let Todo { description, priority, tags, due_date, project, notes, recurrence, .. } =
+            Todo::from_request(self.id, request);
+        self.description = description;
+        self.set_priority(priority);
+        self.tags = tags;
+        self.due_date = due_date;
+        self.notes = notes;
+        self.recurrence = recurrence;
+        if self.project != project {
+            self.project = project;
+            self.column_id = None;
+        }
+    }
+
+    /// Sets the completion status of the Todo item, recording when it was completed.
+    ///
+    /// # Arguments
+    ///
+    /// * `is_completed` - The new completion status.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    pub(crate) fn set_completed(&mut self, is_completed: bool, now: u64) {
+        if self.is_completed != is_completed {
+            self.is_completed = is_completed;
+            self.completed_at = is_completed.then_some(now);
+        }
+    }
+
+    /// Sets the priority of the Todo item, clearing any automatic escalation.
+    ///
+    /// # Arguments
+    ///
+    /// * `priority` - The new priority.
+    pub(crate) fn set_priority(&mut self, priority: Priority) {
+        self.priority = priority;
+        self.escalated = false;
+    }
+
+    /// Raises the priority of the Todo item by one level and marks it as escalated.
+    ///
+    /// # Returns
+    ///
+    /// Whether the priority was raised; the highest priority cannot be raised further.
+    pub(crate) fn escalate(&mut self) -> bool {
+        let Some(priority) = self.priority.raised() else {
+            return false;
+        };
+        self.priority = priority;
+        self.escalated = true;
+        true
+    }
+
+    /// Moves the due date of the Todo item ahead by a day and counts the rollover.
+    ///
+    /// # Arguments
+    ///
+    /// * `day` - The length of a day, in nanoseconds.
+    pub(crate) fn roll_over(&mut self, day: u64) {
+        if let Some(due_date) = self.due_date.as_mut() {
+            *due_date += day;
+            self.rollover_count += 1;
+        }
+    }
+
+    /// Marks the Todo item as waiting on someone, keeping the start of the wait if they stay the same.
+    ///
+    /// # Arguments
+    ///
+    /// * `who` - The person or party waited on, or None if the Todo item is no longer waiting.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    pub(crate) fn set_waiting_on(&mut self, who: Option<String>, now: u64) {
+        if self.waiting_on != who {
+            self.waiting_since = who.is_some().then_some(now);
+            self.waiting_on = who;
+        }
+    }
+
+    /// Sets the web page the Todo item refers to, dropping the preview of a previous page.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page, or None to clear it.
+    pub(crate) fn set_url(&mut self, url: Option<String>) {
+        if self.url != url {
+            self.link_preview = None;
+            self.url = url;
         }
     }
 
+    /// Returns the time the Todo item was last changed or created, or 0 if unknown.
+    pub(crate) fn last_touched(&self) -> u64 {
+        self.updated_at.max(self.created_at)
+    }
+
     /// Adds a tag to the Todo item.
     ///
     /// # Arguments
@@ -67,6 +339,26 @@ impl Todo {
         self.tags.push(tag);
     }
 
+    /// Attaches a label to the Todo item unless it is attached already.
+    ///
+    /// # Arguments
+    ///
+    /// * `label_id` - The unique identifier for the label.
+    pub(crate) fn add_label(&mut self, label_id: LabelId) {
+        if !self.label_ids.contains(&label_id) {
+            self.label_ids.push(label_id);
+        }
+    }
+
+    /// Detaches a label from the Todo item.
+    ///
+    /// # Arguments
+    ///
+    /// * `label_id` - The unique identifier for the label.
+    pub(crate) fn remove_label(&mut self, label_id: LabelId) {
+        self.label_ids.retain(|id| *id != label_id);
+    }
+
     /// Removes a tag from the Todo item.
     ///
     /// # Arguments
@@ -76,9 +368,151 @@ impl Todo {
         self.tags.retain(|t| t != tag);
     }
 
-    
+    /// Applies a partial update to the Todo item. A Todo item moved to another project leaves its board column.
+    ///
+    /// # Arguments
+    ///
+    /// * `patch` - The fields to be changed.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    pub(crate) fn apply_patch(&mut self, patch: PatchTodo, now: u64) {
+        if let Some(description) = patch.description {
+            self.description = description;
+        }
+        if let Some(priority) = patch.priority {
+            self.set_priority(priority);
+        }
+        if let Some(due_date) = patch.due_date {
+            self.due_date = due_date;
+        }
+        if let Some(is_completed) = patch.is_completed {
+            self.set_completed(is_completed, now);
+        }
+        if let Some(tags) = patch.tags {
+            self.tags = tags;
+        }
+        if let Some(project) = patch.project {
+            if self.project != project {
+                self.project = project;
+                self.column_id = None;
+            }
+        }
+        if let Some(notes) = patch.notes {
+            self.notes = notes;
+        }
+    }
+}
+
+/// Version byte prefixed to every encoded Todo record.
+const TODO_RECORD_VERSION: u8 = 1;
 
-    
+/// Magic bytes that start every Candid message, used to recognize records written before versioning.
+const CANDID_MAGIC: &[u8] = b"DIDL";
+
+/// Lenient view of a stored Todo item, used when a record no longer matches the current schema.
+/// Records that embed a Todo item, such as revisions and audit entries, fall back to it as well.
+///
+/// Every field is optional so that missing or mistyped fields decode as `None` instead of failing.
+#[derive(CandidType, Deserialize)]
+pub(crate) struct LenientTodo {
+    id: Option<TodoId>,
+    description: Option<String>,
+    is_completed: Option<bool>,
+    priority: Option<Priority>,
+    tags: Option<Vec<String>>,
+    due_date: Option<u64>,
+    project: Option<String>,
+    context: Option<String>,
+    energy: Option<Energy>,
+    waiting_on: Option<String>,
+    waiting_since: Option<u64>,
+    url: Option<String>,
+    link_preview: Option<LinkPreview>,
+    notes: Option<String>,
+    created_at: Option<u64>,
+    completed_at: Option<u64>,
+    updated_at: Option<u64>,
+    escalated: Option<bool>,
+    column_id: Option<u64>,
+    sample: Option<bool>,
+    recurrence: Option<String>,
+    overdue_muted: Option<bool>,
+    rollover_count: Option<u32>,
+    label_ids: Option<Vec<LabelId>>,
+    linked: Option<(Principal, TodoId)>,
+    goal_id: Option<GoalId>,
+    habit: Option<bool>,
+    location: Option<GeoPoint>,
+    subtasks: Option<Vec<Subtask>>,
+    namespace: Option<String>,
+    estimated_minutes: Option<u32>,
+}
+
+impl LenientTodo {
+    /// Converts the lenient view into a Todo item, filling in defaults for missing fields.
+    ///
+    /// # Returns
+    ///
+    /// The Todo item, or None if the identifier or description is missing.
+    pub(crate) fn into_todo(self) -> Option<Todo> {
+        Some(Todo {
+            is_completed: self.is_completed.unwrap_or_default(),
+            tags: self.tags.unwrap_or_default(),
+            due_date: self.due_date,
+            project: self.project,
+            context: self.context,
+            energy: self.energy,
+            waiting_on: self.waiting_on,
+            waiting_since: self.waiting_since,
+            url: self.url,
+            link_preview: self.link_preview,
+            notes: self.notes,
+            created_at: self.created_at.unwrap_or_default(),
+            completed_at: self.completed_at,
+            updated_at: self.updated_at.unwrap_or_default(),
+            escalated: self.escalated.unwrap_or_default(),
+            column_id: self.column_id,
+            sample: self.sample.unwrap_or_default(),
+            recurrence: self.recurrence,
+            overdue_muted: self.overdue_muted.unwrap_or_default(),
+            rollover_count: self.rollover_count.unwrap_or_default(),
+            label_ids: self.label_ids.unwrap_or_default(),
+            linked: self.linked,
+            goal_id: self.goal_id,
+            habit: self.habit.unwrap_or_default(),
+            location: self.location,
+            subtasks: self.subtasks.unwrap_or_default(),
+            namespace: self.namespace,
+            estimated_minutes: self.estimated_minutes,
+            ..Todo::new(self.id?, self.description?, self.priority.unwrap_or_default())
+        })
+    }
+}
+
+impl Todo {
+    /// Encodes the Todo item as a versioned record.
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![TODO_RECORD_VERSION];
+        bytes.extend(Encode!(self).unwrap());
+        bytes
+    }
+
+    /// Decodes a versioned or unversioned Todo record.
+    ///
+    /// Records that do not match the current schema are decoded leniently, with defaults for missing fields.
+    ///
+    /// # Returns
+    ///
+    /// The decoded Todo item, or None if the record is corrupt.
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let candid = match bytes.split_first() {
+            Some((&TODO_RECORD_VERSION, candid)) => candid,
+            _ if bytes.starts_with(CANDID_MAGIC) => bytes,
+            _ => return None,
+        };
+        Decode!(candid, Self)
+            .ok()
+            .or_else(|| Decode!(candid, LenientTodo).ok()?.into_todo())
+    }
 }
 
 impl Storable for Todo {
@@ -89,8 +523,8 @@ impl Storable for Todo {
     /// # Returns
     ///
     /// A `Cow<[u8]>` containing the byte representation of the `Todo` instance.
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(Encode!(self).unwrap())
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(self.encode())
     }
 
     /// Creates a `Todo` instance from a byte array.
@@ -103,10 +537,66 @@ impl Storable for Todo {
     ///
     /// A `Todo` instance.
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        Decode!(bytes.as_ref(), Self).unwrap()
+        Self::decode(&bytes).expect("Corrupt Todo record")
     }
 }
 
+/// Represents a Todo record in stable memory, which may fail to decode.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TodoRecord {
+    /// A record that decoded into a Todo item, boxed since it is much larger than a corrupt record.
+    Valid(Box<Todo>),
+    /// A record that could not be decoded, kept as raw bytes.
+    Corrupt(Vec<u8>),
+}
+
+impl TodoRecord {
+    /// Returns the Todo item if the record is valid.
+    pub(crate) fn into_todo(self) -> Option<Todo> {
+        match self {
+            TodoRecord::Valid(todo) => Some(*todo),
+            TodoRecord::Corrupt(_) => None,
+        }
+    }
+
+    /// Returns the record with the identifier of a valid Todo item replaced; corrupt records are unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The new identifier.
+    pub(crate) fn with_id(self, id: TodoId) -> Self {
+        match self {
+            TodoRecord::Valid(todo) => Todo { id, ..*todo }.into(),
+            corrupt => corrupt,
+        }
+    }
+}
+
+impl From<Todo> for TodoRecord {
+    fn from(todo: Todo) -> Self {
+        TodoRecord::Valid(Box::new(todo))
+    }
+}
+
+impl Storable for TodoRecord {
+    const BOUND: Bound = Bound::Unbounded;
+
+    /// Converts the record to a byte array, leaving corrupt bytes untouched.
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        match self {
+            TodoRecord::Valid(todo) => Cow::Owned(todo.encode()),
+            TodoRecord::Corrupt(bytes) => Cow::Borrowed(bytes),
+        }
+    }
+
+    /// Creates a record from a byte array without ever trapping.
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        match Todo::decode(&bytes) {
+            Some(todo) => TodoRecord::Valid(Box::new(todo)),
+            None => TodoRecord::Corrupt(bytes.into_owned()),
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -117,11 +607,51 @@ mod tests {
         let todo = Todo::new(1, "Test Todo".to_string(), Priority::High);
         assert_eq!(todo.id, 1);
         assert_eq!(todo.description, "Test Todo");
-        assert_eq!(todo.is_completed, false);
+        assert!(!todo.is_completed);
         assert_eq!(todo.priority, Priority::High);
         assert!(todo.tags.is_empty());
     }
 
+    #[test]
+    fn test_todo_from_request() {
+        let todo = Todo::from_request(
+            1,
+            CreateTodo {
+                description: "Test Todo".to_string(),
+                tags: Some(vec!["urgent".to_string()]),
+                due_date: Some(42),
+                project: Some("home".to_string()),
+                notes: Some("Some notes".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(todo.id, 1);
+        assert_eq!(todo.description, "Test Todo");
+        assert_eq!(todo.priority, Priority::Medium);
+        assert_eq!(todo.tags, vec!["urgent"]);
+        assert_eq!(todo.due_date, Some(42));
+        assert_eq!(todo.project.as_deref(), Some("home"));
+        assert_eq!(todo.notes.as_deref(), Some("Some notes"));
+    }
+
+    #[test]
+    fn test_replace_with_request() {
+        let mut todo = Todo::new(1, "Test Todo".to_string(), Priority::High);
+        todo.is_completed = true;
+        todo.add_tag("urgent".to_string());
+        todo.replace_with_request(CreateTodo {
+            description: "Synced Todo".to_string(),
+            notes: Some("From elsewhere".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(todo.id, 1);
+        assert!(todo.is_completed);
+        assert_eq!(todo.description, "Synced Todo");
+        assert_eq!(todo.priority, Priority::Medium);
+        assert!(todo.tags.is_empty());
+        assert_eq!(todo.notes.as_deref(), Some("From elsewhere"));
+    }
+
     #[test]
     fn test_add_tag() {
         let mut todo = Todo::new(1, "Test Todo".to_string(), Priority::Medium);
@@ -138,6 +668,77 @@ mod tests {
         assert_eq!(todo.tags, vec!["home"]);
     }
 
+    #[test]
+    fn test_apply_patch() {
+        let mut todo = Todo::new(1, "Test Todo".to_string(), Priority::Medium);
+        todo.add_tag("urgent".to_string());
+        todo.apply_patch(
+            PatchTodo {
+                priority: Some(Priority::High),
+                due_date: Some(Some(42)),
+                is_completed: Some(true),
+                notes: Some(Some("Call first".to_string())),
+                ..Default::default()
+            },
+            7,
+        );
+        assert_eq!(todo.description, "Test Todo");
+        assert_eq!(todo.priority, Priority::High);
+        assert_eq!(todo.due_date, Some(42));
+        assert!(todo.is_completed);
+        assert_eq!(todo.completed_at, Some(7));
+        assert_eq!(todo.tags, vec!["urgent"]);
+        assert_eq!(todo.notes.as_deref(), Some("Call first"));
+    }
+
+    #[test]
+    fn test_apply_patch_leaves_or_clears_optional_fields() {
+        let mut todo = Todo {
+            due_date: Some(42),
+            project: Some("Home".to_string()),
+            column_id: Some(3),
+            notes: Some("Call first".to_string()),
+            ..Todo::new(1, "Test Todo".to_string(), Priority::Medium)
+        };
+        todo.apply_patch(PatchTodo { description: Some("Renamed".to_string()), ..Default::default() }, 7);
+        assert_eq!(todo.due_date, Some(42));
+        assert_eq!(todo.project.as_deref(), Some("Home"));
+        assert_eq!(todo.column_id, Some(3));
+        assert_eq!(todo.notes.as_deref(), Some("Call first"));
+
+        todo.apply_patch(
+            PatchTodo { due_date: Some(None), project: Some(None), notes: Some(None), ..Default::default() },
+            8,
+        );
+        assert_eq!(todo.due_date, None);
+        assert_eq!(todo.project, None);
+        assert_eq!(todo.column_id, None);
+        assert_eq!(todo.notes, None);
+    }
+
+    #[test]
+    fn test_escalate() {
+        let mut todo = Todo::new(1, "Test Todo".to_string(), Priority::Medium);
+        assert!(todo.escalate());
+        assert_eq!(todo.priority, Priority::High);
+        assert!(todo.escalated);
+        assert!(!todo.escalate());
+
+        todo.set_priority(Priority::Low);
+        assert!(!todo.escalated);
+    }
+
+    #[test]
+    fn test_set_completed() {
+        let mut todo = Todo::new(1, "Test Todo".to_string(), Priority::Medium);
+        todo.set_completed(true, 5);
+        todo.set_completed(true, 9);
+        assert_eq!(todo.completed_at, Some(5));
+        todo.set_completed(false, 12);
+        assert!(!todo.is_completed);
+        assert_eq!(todo.completed_at, None);
+    }
+
     #[test]
     fn test_to_bytes_and_from_bytes() {
         let todo = Todo::new(1, "Test Todo".to_string(), Priority::Low);
@@ -145,4 +746,31 @@ mod tests {
         let decoded_todo = Todo::from_bytes(bytes);
         assert_eq!(todo, decoded_todo);
     }
+
+    #[test]
+    fn test_decode_unversioned_record() {
+        let todo = Todo::new(1, "Test Todo".to_string(), Priority::Low);
+        let bytes = Encode!(&todo).unwrap();
+        assert_eq!(Todo::decode(&bytes), Some(todo));
+    }
+
+    #[test]
+    fn test_decode_record_with_missing_fields() {
+        #[derive(CandidType)]
+        struct OldTodo {
+            id: TodoId,
+            description: String,
+        }
+        let bytes = Encode!(&OldTodo { id: 1, description: "Test Todo".to_string() }).unwrap();
+        let todo = Todo::decode(&bytes).unwrap();
+        assert_eq!(todo, Todo::new(1, "Test Todo".to_string(), Priority::Medium));
+    }
+
+    #[test]
+    fn test_corrupt_record() {
+        let record = TodoRecord::from_bytes(Cow::Borrowed(b"garbage"));
+        assert_eq!(record, TodoRecord::Corrupt(b"garbage".to_vec()));
+        assert_eq!(record.to_bytes().as_ref(), b"garbage");
+        assert_eq!(record.into_todo(), None);
+    }
 }
\ No newline at end of file
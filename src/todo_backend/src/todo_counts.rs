@@ -0,0 +1,140 @@
+use std::cell::RefCell;
+
+use candid::Principal;
+use ic_stable_structures::{Memory, StableBTreeMap};
+
+use crate::{errors::Error, store::TodoChange};
+
+/// Maximum number of Todo items a principal may keep, archived ones included.
+pub(crate) const MAX_TODOS: u64 = 100_000;
+
+/// Type alias for the TodoCountStore, which is a StableBTreeMap keyed by principal holding how many Todo items they keep.
+pub(crate) type TodoCountStore<M> = StableBTreeMap<Principal, u64, M>;
+
+/// Wrapper around the TodoCountStore to provide additional functionality.
+pub(crate) struct TodoCountStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<TodoCountStore<M>>,
+}
+
+impl<M: Memory> TodoCountStoreWrapper<'_, M> {
+    /// Counts a Todo item that was created or removes one that was deleted.
+    ///
+    /// Archived Todo items keep counting, as archiving does not record a change.
+    ///
+    /// # Arguments
+    ///
+    /// * `change` - The change to a Todo item.
+    pub(crate) fn record_change(&self, change: &TodoChange) {
+        match (&change.before, &change.after) {
+            (None, Some(_)) => self.add(change.principal, 1),
+            (Some(_), None) => self.subtract(change.principal, 1),
+            _ => {}
+        }
+    }
+
+    /// Counts Todo items stored without a recorded change.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the Todo items.
+    /// * `count` - The number of Todo items.
+    pub(crate) fn add(&self, principal: Principal, count: u64) {
+        let mut store = self.store.borrow_mut();
+        let total = store.get(&principal).unwrap_or_default() + count;
+        store.insert(principal, total);
+    }
+
+    /// Returns how many Todo items a principal keeps.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    pub(crate) fn count(&self, principal: Principal) -> u64 {
+        self.store.borrow().get(&principal).unwrap_or_default()
+    }
+
+    /// Checks that a principal may add Todo items.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `count` - The number of Todo items to be added.
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or a QuotaExceeded Error if the principal would keep more than 100,000 Todo items.
+    pub(crate) fn ensure_room(&self, principal: Principal, count: u64) -> Result<(), Error> {
+        if self.count(principal).saturating_add(count) > MAX_TODOS {
+            return Err(Error::QuotaExceeded(Some(format!(
+                "A principal can keep at most {MAX_TODOS} Todo items"
+            ))));
+        }
+        Ok(())
+    }
+
+    /// Stops counting a Todo item that was deleted.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal owning the Todo items.
+    /// * `count` - The number of Todo items.
+    fn subtract(&self, principal: Principal, count: u64) {
+        let mut store = self.store.borrow_mut();
+        let total = store
+            .get(&principal)
+            .unwrap_or_default()
+            .saturating_sub(count);
+        if total == 0 {
+            store.remove(&principal);
+        } else {
+            store.insert(principal, total);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    use crate::todo::{Priority, Todo};
+
+    #[test]
+    fn test_counts_created_and_deleted_todos() {
+        let store = RefCell::new(TodoCountStore::new(DefaultMemoryImpl::default()));
+        let wrapper = TodoCountStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+        let todo = Todo::new(1, "Task".to_string(), Priority::Low);
+        let created = TodoChange {
+            principal,
+            before: None,
+            after: Some(todo.clone()),
+        };
+        let updated = TodoChange {
+            principal,
+            before: Some(todo.clone()),
+            after: Some(todo.clone()),
+        };
+        let deleted = TodoChange {
+            principal,
+            before: Some(todo),
+            after: None,
+        };
+
+        wrapper.record_change(&created);
+        wrapper.record_change(&updated);
+        assert_eq!(wrapper.count(principal), 1);
+        wrapper.add(principal, 2);
+        assert_eq!(wrapper.count(principal), 3);
+        wrapper.record_change(&deleted);
+        assert_eq!(wrapper.count(principal), 2);
+
+        wrapper.add(principal, MAX_TODOS - 3);
+        assert!(wrapper.ensure_room(principal, 1).is_ok());
+        assert!(matches!(
+            wrapper.ensure_room(principal, 2),
+            Err(Error::QuotaExceeded(_))
+        ));
+    }
+}
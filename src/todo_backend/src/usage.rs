@@ -0,0 +1,242 @@
+use std::{borrow::Cow, cell::RefCell, collections::BTreeMap};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::{memory::USAGE_STORE, paginator::Paginator, stats::Day};
+
+/// Number of days the usage of a principal is kept for.
+pub(crate) const USAGE_RETENTION_DAYS: Day = 400;
+
+/// Type alias for the UsageStore, which is a StableBTreeMap with a tuple key of (Principal, Day) and value of Usage.
+pub(crate) type UsageStore<M> = StableBTreeMap<(Principal, Day), Usage, M>;
+
+/// Represents the resources a principal consumed through update calls.
+/// Queries cannot change the state of the canister, so they are not metered.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Usage {
+    /// Number of update calls.
+    pub(crate) update_calls: u64,
+    /// Number of bytes of arguments sent with the update calls, which bounds what they stored.
+    pub(crate) bytes_written: u64,
+    /// Number of instructions the update calls executed.
+    pub(crate) instructions: u64,
+}
+
+impl Storable for Usage {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl Usage {
+    /// Adds the resources of another usage to this one.
+    fn add(&mut self, other: &Usage) {
+        self.update_calls += other.update_calls;
+        self.bytes_written += other.bytes_written;
+        self.instructions += other.instructions;
+    }
+}
+
+/// Represents the usage of a principal on a single day.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct DailyUsage {
+    /// The day, counted in days since the Unix epoch.
+    pub(crate) day: Day,
+    /// The resources consumed on the day.
+    pub(crate) usage: Usage,
+}
+
+/// Represents the usage of the caller over a range of days.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct MyUsage {
+    /// The days with usage, oldest first.
+    pub(crate) days: Vec<DailyUsage>,
+    /// The resources consumed over the whole range.
+    pub(crate) total: Usage,
+}
+
+/// Represents the usage of a principal over a range of days, as seen by an admin.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct PrincipalUsage {
+    /// The principal identifier.
+    pub(crate) principal: Principal,
+    /// The resources consumed over the range.
+    pub(crate) total: Usage,
+}
+
+/// Wrapper around the UsageStore to provide additional functionality.
+pub(crate) struct UsageStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<UsageStore<M>>,
+}
+
+impl<M: Memory> UsageStoreWrapper<'_, M> {
+    /// Adds the resources of an update call to the usage of a principal on a day.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `day` - The day the call was made on.
+    /// * `usage` - The resources the call consumed.
+    pub(crate) fn record(&self, principal: Principal, day: Day, usage: &Usage) {
+        let mut store = self.store.borrow_mut();
+        let mut total = store.get(&(principal, day)).unwrap_or_default();
+        total.add(usage);
+        store.insert((principal, day), total);
+    }
+
+    /// Retrieves the usage of a principal over a range of days.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `from` - The first day of the range.
+    /// * `to` - The last day of the range (inclusive).
+    pub(crate) fn usage(&self, principal: Principal, from: Day, to: Day) -> MyUsage {
+        let days: Vec<DailyUsage> = self
+            .store
+            .borrow()
+            .range((principal, from)..=(principal, to))
+            .map(|((_, day), usage)| DailyUsage { day, usage })
+            .collect();
+        let mut total = Usage::default();
+        for day in &days {
+            total.add(&day.usage);
+        }
+        MyUsage { days, total }
+    }
+
+    /// Sums up the usage of every principal over a range of days, heaviest users first by instructions.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The first day of the range.
+    /// * `to` - The last day of the range (inclusive).
+    /// * `paginator` - The paginator for controlling the list output.
+    pub(crate) fn roll_up(&self, from: Day, to: Day, paginator: Paginator) -> Vec<PrincipalUsage> {
+        let mut totals: BTreeMap<Principal, Usage> = BTreeMap::new();
+        for ((principal, day), usage) in self.store.borrow().iter() {
+            if (from..=to).contains(&day) {
+                totals.entry(principal).or_default().add(&usage);
+            }
+        }
+        let mut entries: Vec<PrincipalUsage> = totals
+            .into_iter()
+            .map(|(principal, total)| PrincipalUsage { principal, total })
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.total.instructions));
+        entries
+            .into_iter()
+            .skip(paginator.skip())
+            .take(paginator.limit())
+            .collect()
+    }
+
+    /// Removes the usage recorded before a day.
+    ///
+    /// # Arguments
+    ///
+    /// * `before` - The first day that is kept.
+    pub(crate) fn prune(&self, before: Day) {
+        let expired: Vec<(Principal, Day)> = self
+            .store
+            .borrow()
+            .iter()
+            .filter(|((_, day), _)| *day < before)
+            .map(|(key, _)| key)
+            .collect();
+        let mut store = self.store.borrow_mut();
+        for key in expired {
+            store.remove(&key);
+        }
+    }
+}
+
+/// Meters an update call of a principal; the usage is recorded once the meter is dropped at the end of the call.
+pub(crate) struct Meter {
+    principal: Principal,
+    day: Day,
+    bytes_written: u64,
+}
+
+impl Meter {
+    /// Starts metering the current update call. Must be called before the call awaits anything.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The caller.
+    /// * `day` - The day the call is made on.
+    pub(crate) fn start(principal: Principal, day: Day) -> Self {
+        Self {
+            principal,
+            day,
+            bytes_written: ic_cdk::api::call::arg_data_raw_size() as u64,
+        }
+    }
+}
+
+impl Drop for Meter {
+    fn drop(&mut self) {
+        let usage = Usage {
+            update_calls: 1,
+            bytes_written: self.bytes_written,
+            // Counter 1 counts the instructions of the whole call, across awaits.
+            instructions: ic_cdk::api::performance_counter(1),
+        };
+        USAGE_STORE
+            .with(|store| UsageStoreWrapper { store }.record(self.principal, self.day, &usage));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_record_and_roll_up() {
+        let store = RefCell::new(UsageStore::new(DefaultMemoryImpl::default()));
+        let wrapper = UsageStoreWrapper { store: &store };
+        let light = Principal::anonymous();
+        let heavy = Principal::management_canister();
+        let call = |instructions| Usage {
+            update_calls: 1,
+            bytes_written: 10,
+            instructions,
+        };
+
+        wrapper.record(light, 1, &call(100));
+        wrapper.record(light, 1, &call(100));
+        wrapper.record(light, 3, &call(100));
+        wrapper.record(heavy, 2, &call(1_000));
+
+        let usage = wrapper.usage(light, 1, 2);
+        assert_eq!(usage.days.len(), 1);
+        assert_eq!(
+            usage.total,
+            Usage {
+                update_calls: 2,
+                bytes_written: 20,
+                instructions: 200
+            }
+        );
+
+        let principals: Vec<_> = wrapper
+            .roll_up(1, 3, Paginator::default())
+            .into_iter()
+            .map(|entry| (entry.principal, entry.total.update_calls))
+            .collect();
+        assert_eq!(principals, vec![(heavy, 1), (light, 3)]);
+
+        wrapper.prune(3);
+        assert_eq!(wrapper.usage(light, 0, 10).total.update_calls, 1);
+        assert!(wrapper.usage(heavy, 0, 10).days.is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, Storable};
+
+use crate::paginator::Paginator;
+
+/// Type alias for the UserStore, which is a StableBTreeMap keyed by Principal with a value of UserActivity.
+pub(crate) type UserStore<M> = StableBTreeMap<Principal, UserActivity, M>;
+
+/// Represents when a principal first and last changed any data in the canister.
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) struct UserActivity {
+    /// Time of the first update call, in nanoseconds since the Unix epoch.
+    pub(crate) first_seen: u64,
+    /// Time of the latest update call, in nanoseconds since the Unix epoch.
+    pub(crate) last_activity: u64,
+}
+
+impl Storable for UserActivity {
+    const BOUND: Bound = Bound::Unbounded;
+
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+/// Represents the usage of the canister by a single principal, as reported to the operator.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct UserStats {
+    /// The principal identifier.
+    pub(crate) principal: Principal,
+    /// Number of Todo items owned by the principal.
+    pub(crate) todo_count: u64,
+    /// Number of bytes the Todo items of the principal occupy in stable memory.
+    pub(crate) storage_bytes: u64,
+    /// Time of the first update call, in nanoseconds since the Unix epoch.
+    pub(crate) first_seen: u64,
+    /// Time of the latest update call, in nanoseconds since the Unix epoch.
+    pub(crate) last_activity: u64,
+}
+
+/// Wrapper around the UserStore to provide additional functionality.
+pub(crate) struct UserStoreWrapper<'a, M: Memory> {
+    pub store: &'a RefCell<UserStore<M>>,
+}
+
+impl<M: Memory> UserStoreWrapper<'_, M> {
+    /// Records activity of a principal, registering it on first use.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The principal identifier.
+    /// * `now` - The current time, in nanoseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// Whether this is the first activity of the principal.
+    pub(crate) fn touch(&self, principal: Principal, now: u64) -> bool {
+        let mut store = self.store.borrow_mut();
+        let (activity, is_new) = match store.get(&principal) {
+            Some(activity) => (UserActivity { last_activity: now, ..activity }, false),
+            None => (UserActivity { first_seen: now, last_activity: now }, true),
+        };
+        store.insert(principal, activity);
+        is_new
+    }
+
+    /// Lists every known principal.
+    pub(crate) fn principals(&self) -> Vec<Principal> {
+        self.store.borrow().iter().map(|(principal, _)| principal).collect()
+    }
+
+    /// Lists known principals and their activity with pagination.
+    ///
+    /// # Arguments
+    ///
+    /// * `paginator` - The paginator for controlling the list output.
+    ///
+    /// # Returns
+    ///
+    /// A vector of principals paired with their activity.
+    pub(crate) fn list_users(&self, paginator: Paginator) -> Vec<(Principal, UserActivity)> {
+        self.store
+            .borrow()
+            .iter()
+            .skip(paginator.skip())
+            .take(paginator.limit())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ic_stable_structures::DefaultMemoryImpl;
+
+    #[test]
+    fn test_touch_keeps_first_seen() {
+        let store = RefCell::new(UserStore::new(DefaultMemoryImpl::default()));
+        let wrapper = UserStoreWrapper { store: &store };
+        let principal = Principal::anonymous();
+
+        assert!(wrapper.touch(principal, 10));
+        assert!(!wrapper.touch(principal, 20));
+
+        let users = wrapper.list_users(Paginator::default());
+        assert_eq!(
+            users,
+            vec![(principal, UserActivity { first_seen: 10, last_activity: 20 })]
+        );
+    }
+}
@@ -0,0 +1,465 @@
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{
+    checklists::MAX_CHECKLIST_ITEMS,
+    email::EmailChannel,
+    hooks::IntegrationMapping,
+    errors::Error,
+    geo::GeoPoint,
+    goals::{GoalTarget, MAX_TARGET_COUNT},
+    recurrence::Recurrence,
+    store::TodoFilter,
+    tags::TAG_SEPARATOR,
+    todo::{CreateTodo, PatchTodo, TodoId},
+};
+
+/// Maximum number of Todo items a batch call can select.
+const MAX_BATCH_SIZE: usize = 100;
+
+/// Maximum number of characters in a Todo description.
+const MAX_DESCRIPTION_LENGTH: usize = 1_000;
+
+/// Maximum number of characters in the notes of a Todo item.
+const MAX_NOTES_LENGTH: usize = 10_000;
+
+/// Maximum number of characters in a tag.
+pub(crate) const MAX_TAG_LENGTH: usize = 50;
+
+/// Maximum number of characters in a project name.
+const MAX_PROJECT_LENGTH: usize = 100;
+
+/// Maximum number of characters in the name of a namespace.
+pub(crate) const MAX_NAMESPACE_LENGTH: usize = 50;
+
+/// Maximum number of characters in a GTD context, including its leading `@`.
+const MAX_CONTEXT_LENGTH: usize = 50;
+
+/// Maximum number of characters in the name of the person a Todo item is waiting on.
+const MAX_WAITING_ON_LENGTH: usize = 100;
+
+/// Maximum number of characters in the file name of an attachment.
+const MAX_FILE_NAME_LENGTH: usize = 255;
+
+/// Maximum number of characters in a MIME type.
+const MAX_MIME_TYPE_LENGTH: usize = 100;
+
+/// Maximum number of characters in the name of a board column.
+const MAX_COLUMN_NAME_LENGTH: usize = 50;
+
+/// Maximum number of characters in the name of a label.
+const MAX_LABEL_NAME_LENGTH: usize = 50;
+
+/// Maximum number of characters in the label of a location.
+const MAX_LOCATION_LABEL_LENGTH: usize = 100;
+
+/// Maximum number of characters in the title of a goal.
+const MAX_GOAL_TITLE_LENGTH: usize = 200;
+
+/// Maximum number of characters in the name of a checklist.
+const MAX_CHECKLIST_NAME_LENGTH: usize = 100;
+
+/// Maximum number of characters in the title of a subtask.
+const MAX_SUBTASK_TITLE_LENGTH: usize = 200;
+
+/// Longest estimate of a Todo item, in minutes: 30 days.
+const MAX_ESTIMATED_MINUTES: u32 = 30 * 24 * 60;
+
+/// Maximum number of characters in an external identifier.
+pub(crate) const MAX_EXTERNAL_ID_LENGTH: usize = 200;
+
+/// Maximum number of characters in the URL of an external endpoint.
+const MAX_URL_LENGTH: usize = 2_048;
+
+/// Maximum number of characters in an API key of an external service.
+const MAX_API_KEY_LENGTH: usize = 200;
+
+/// Maximum number of characters in an email address.
+const MAX_EMAIL_ADDRESS_LENGTH: usize = 254;
+
+/// Maximum number of characters in the token of a Telegram bot.
+const MAX_BOT_TOKEN_LENGTH: usize = 100;
+
+/// Invisible characters that carry no meaning in user text and are silently removed.
+/// Zero-width joiners are kept since they are part of emoji sequences.
+const ZERO_WIDTH_CHARS: [char; 3] = ['\u{200B}', '\u{2060}', '\u{FEFF}'];
+
+/// Normalizes a piece of user text and checks it against the given constraints.
+///
+/// The text is normalized to NFC, stripped of zero-width characters and trimmed.
+///
+/// # Arguments
+///
+/// * `field` - The name of the field, used in error messages.
+/// * `text` - The text to be sanitized.
+/// * `max_length` - The maximum number of characters allowed.
+/// * `multiline` - Whether line breaks and tabs are allowed.
+///
+/// # Returns
+///
+/// A Result containing the sanitized text, or an Error if it is empty, too long or contains control characters.
+fn sanitize(field: &str, text: &str, max_length: usize, multiline: bool) -> Result<String, Error> {
+    let text: String = text.nfc().filter(|c| !ZERO_WIDTH_CHARS.contains(c)).collect();
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(Error::InvalidInput(format!("{field} cannot be empty")));
+    }
+    if text.chars().count() > max_length {
+        return Err(Error::InvalidInput(format!(
+            "{field} cannot be longer than {max_length} characters"
+        )));
+    }
+    let is_allowed = |c: char| !c.is_control() || (multiline && matches!(c, '\n' | '\r' | '\t'));
+    if !text.chars().all(is_allowed) {
+        return Err(Error::InvalidInput(format!("{field} cannot contain control characters")));
+    }
+    Ok(text.to_string())
+}
+
+/// Sanitizes the description of a Todo item.
+pub(crate) fn description(text: &str) -> Result<String, Error> {
+    sanitize("Description", text, MAX_DESCRIPTION_LENGTH, false)
+}
+
+/// Sanitizes the notes of a Todo item, which may span multiple lines.
+pub(crate) fn notes(text: &str) -> Result<String, Error> {
+    sanitize("Notes", text, MAX_NOTES_LENGTH, true)
+}
+
+/// Sanitizes the text of a search.
+pub(crate) fn search_text(text: &str) -> Result<String, Error> {
+    sanitize("Search text", text, MAX_DESCRIPTION_LENGTH, false)
+}
+
+/// Sanitizes a single tag, trimming each level of a nested tag such as `work / projectX`.
+pub(crate) fn tag(text: &str) -> Result<String, Error> {
+    let tag = sanitize("Tag", text, MAX_TAG_LENGTH, false)?;
+    let levels: Vec<&str> = tag.split(TAG_SEPARATOR).map(str::trim).collect();
+    if levels.iter().any(|level| level.is_empty()) {
+        return Err(Error::InvalidInput(format!("Tag cannot have an empty level between '{TAG_SEPARATOR}'")));
+    }
+    Ok(levels.join(&TAG_SEPARATOR.to_string()))
+}
+
+/// Sanitizes a project name.
+pub(crate) fn project(text: &str) -> Result<String, Error> {
+    sanitize("Project", text, MAX_PROJECT_LENGTH, false)
+}
+
+/// Sanitizes the name of a namespace.
+pub(crate) fn namespace(text: &str) -> Result<String, Error> {
+    sanitize("Namespace", text, MAX_NAMESPACE_LENGTH, false)
+}
+
+/// Sanitizes a GTD context, adding the leading `@` if it is missing so that `home` and `@home` match.
+pub(crate) fn context(text: &str) -> Result<String, Error> {
+    let context = sanitize("Context", text, MAX_CONTEXT_LENGTH, false)?;
+    if context.starts_with('@') {
+        return Ok(context);
+    }
+    sanitize("Context", &format!("@{context}"), MAX_CONTEXT_LENGTH, false)
+}
+
+/// Checks a recurrence rule and writes it in its canonical form, such as `every mon,wed 09:00`.
+pub(crate) fn recurrence(text: &str) -> Result<String, Error> {
+    Recurrence::parse(text).map(|recurrence| recurrence.to_string())
+}
+
+/// Sanitizes the name of the person or party a Todo item is waiting on.
+pub(crate) fn waiting_on(text: &str) -> Result<String, Error> {
+    sanitize("Waiting on", text, MAX_WAITING_ON_LENGTH, false)
+}
+
+/// Sanitizes the file name of an attachment, which cannot contain path separators.
+pub(crate) fn file_name(text: &str) -> Result<String, Error> {
+    let name = sanitize("File name", text, MAX_FILE_NAME_LENGTH, false)?;
+    if name.contains(['/', '\\']) {
+        return Err(Error::InvalidInput("File name cannot contain path separators".to_string()));
+    }
+    Ok(name)
+}
+
+/// Sanitizes a MIME type such as `image/png`, lowercasing it.
+pub(crate) fn mime_type(text: &str) -> Result<String, Error> {
+    let mime = sanitize("MIME type", text, MAX_MIME_TYPE_LENGTH, false)?.to_ascii_lowercase();
+    let is_token = |part: &str| {
+        !part.is_empty()
+            && part.chars().all(|c| c.is_ascii_alphanumeric() || "!#$&^_.+-".contains(c))
+    };
+    match mime.split_once('/') {
+        Some((kind, subtype)) if is_token(kind) && is_token(subtype) => Ok(mime),
+        _ => Err(Error::InvalidInput("MIME type must look like type/subtype".to_string())),
+    }
+}
+
+/// Sanitizes the name of a board column.
+pub(crate) fn column_name(text: &str) -> Result<String, Error> {
+    sanitize("Column name", text, MAX_COLUMN_NAME_LENGTH, false)
+}
+
+/// Sanitizes the name of a label.
+pub(crate) fn label_name(text: &str) -> Result<String, Error> {
+    sanitize("Label name", text, MAX_LABEL_NAME_LENGTH, false)
+}
+
+/// Checks that a location lies on Earth and sanitizes its label.
+pub(crate) fn location(location: GeoPoint) -> Result<GeoPoint, Error> {
+    if !(-90.0..=90.0).contains(&location.lat) || !(-180.0..=180.0).contains(&location.lon) {
+        return Err(Error::InvalidInput(
+            "Latitude must lie between -90 and 90 degrees and longitude between -180 and 180 degrees".to_string(),
+        ));
+    }
+    let label = location.label.as_deref().map(|label| sanitize("Location label", label, MAX_LOCATION_LABEL_LENGTH, false)).transpose()?;
+    Ok(GeoPoint { label, ..location })
+}
+
+/// Sanitizes the title of a goal.
+pub(crate) fn goal_title(text: &str) -> Result<String, Error> {
+    sanitize("Goal title", text, MAX_GOAL_TITLE_LENGTH, false)
+}
+
+/// Sanitizes the name of a checklist.
+pub(crate) fn checklist_name(text: &str) -> Result<String, Error> {
+    sanitize("Checklist name", text, MAX_CHECKLIST_NAME_LENGTH, false)
+}
+
+/// Sanitizes the subtask titles of a checklist, of which there must be at least one and at most `MAX_CHECKLIST_ITEMS`.
+pub(crate) fn checklist_items(items: Vec<String>) -> Result<Vec<String>, Error> {
+    if !(1..=MAX_CHECKLIST_ITEMS).contains(&items.len()) {
+        return Err(Error::InvalidInput(format!(
+            "Checklist must have 1 to {MAX_CHECKLIST_ITEMS} items"
+        )));
+    }
+    items.iter().map(|item| sanitize("Subtask title", item, MAX_SUBTASK_TITLE_LENGTH, false)).collect()
+}
+
+/// Checks that a goal targets at least one and at most `MAX_TARGET_COUNT` completions.
+pub(crate) fn goal_target(target: GoalTarget) -> Result<GoalTarget, Error> {
+    match target {
+        GoalTarget::Count(count) if !(1..=MAX_TARGET_COUNT).contains(&count) => Err(Error::InvalidInput(format!(
+            "Goal must target 1 to {MAX_TARGET_COUNT} completed Todo items"
+        ))),
+        target => Ok(target),
+    }
+}
+
+/// Checks that an estimate of a Todo item is at least one minute and at most `MAX_ESTIMATED_MINUTES`.
+pub(crate) fn estimated_minutes(minutes: u32) -> Result<u32, Error> {
+    if !(1..=MAX_ESTIMATED_MINUTES).contains(&minutes) {
+        return Err(Error::InvalidInput(format!(
+            "Estimate must be between 1 and {MAX_ESTIMATED_MINUTES} minutes"
+        )));
+    }
+    Ok(minutes)
+}
+
+/// Checks a color written as `#rrggbb` and writes it in lower case.
+pub(crate) fn color(text: &str) -> Result<String, Error> {
+    let color = text.trim().to_ascii_lowercase();
+    match color.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => Ok(color),
+        _ => Err(Error::InvalidInput("Color must look like #rrggbb".to_string())),
+    }
+}
+
+/// Sanitizes an identifier assigned to a Todo item by an external system.
+pub(crate) fn external_id(text: &str) -> Result<String, Error> {
+    sanitize("External ID", text, MAX_EXTERNAL_ID_LENGTH, false)
+}
+
+/// Sanitizes the URL of an external endpoint or web page, which must use HTTPS and name a host.
+pub(crate) fn https_url(text: &str) -> Result<String, Error> {
+    let url = sanitize("URL", text, MAX_URL_LENGTH, false)?;
+    let host = url
+        .strip_prefix("https://")
+        .and_then(|rest| rest.split(['/', '?', '#']).next())
+        .unwrap_or_default();
+    if host.is_empty() || url.contains(char::is_whitespace) {
+        return Err(Error::InvalidInput("URL must be a valid HTTPS URL".to_string()));
+    }
+    Ok(url)
+}
+
+/// Sanitizes an email address.
+pub(crate) fn email_address(text: &str) -> Result<String, Error> {
+    let address = sanitize("Email address", text, MAX_EMAIL_ADDRESS_LENGTH, false)?;
+    if !address.contains('@') {
+        return Err(Error::InvalidInput("Email address must contain an @".to_string()));
+    }
+    Ok(address)
+}
+
+/// Sanitizes every field of an email channel.
+pub(crate) fn email_channel(channel: EmailChannel) -> Result<EmailChannel, Error> {
+    Ok(EmailChannel {
+        endpoint: https_url(&channel.endpoint)?,
+        api_key: sanitize("API key", &channel.api_key, MAX_API_KEY_LENGTH, false)?,
+        from: email_address(&channel.from)?,
+        to: email_address(&channel.to)?,
+    })
+}
+
+/// Sanitizes the token of a Telegram bot, which becomes part of the Bot API URL.
+pub(crate) fn bot_token(text: &str) -> Result<String, Error> {
+    let token = sanitize("Bot token", text, MAX_BOT_TOKEN_LENGTH, false)?;
+    if !token.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '_' | '-')) {
+        return Err(Error::InvalidInput(
+            "Bot token can only contain letters, digits, ':', '_' and '-'".to_string(),
+        ));
+    }
+    Ok(token)
+}
+
+/// Sanitizes the project and tags an integration files its Todo items under.
+pub(crate) fn integration_mapping(mapping: IntegrationMapping) -> Result<IntegrationMapping, Error> {
+    Ok(IntegrationMapping {
+        project: mapping.project.as_deref().map(project).transpose()?,
+        tags: tags(mapping.tags)?,
+        ..mapping
+    })
+}
+
+/// Sanitizes a list of tags.
+pub(crate) fn tags(tags: Vec<String>) -> Result<Vec<String>, Error> {
+    tags.iter().map(|t| tag(t)).collect()
+}
+
+/// Sanitizes every text field of a creation request.
+pub(crate) fn create_request(request: CreateTodo) -> Result<CreateTodo, Error> {
+    Ok(CreateTodo {
+        description: description(&request.description)?,
+        tags: request.tags.map(tags).transpose()?,
+        project: request.project.as_deref().map(project).transpose()?,
+        notes: request.notes.as_deref().map(notes).transpose()?,
+        recurrence: request.recurrence.as_deref().map(recurrence).transpose()?,
+        ..request
+    })
+}
+
+/// Sanitizes every text field provided in a partial update.
+pub(crate) fn patch(patch: PatchTodo) -> Result<PatchTodo, Error> {
+    Ok(PatchTodo {
+        description: patch.description.as_deref().map(description).transpose()?,
+        tags: patch.tags.map(tags).transpose()?,
+        project: patch.project.map(|value| value.as_deref().map(project).transpose()).transpose()?,
+        notes: patch.notes.map(|value| value.as_deref().map(notes).transpose()).transpose()?,
+        ..patch
+    })
+}
+
+/// Checks that a batch call selects no more Todo items than allowed.
+pub(crate) fn batch(ids: &[TodoId]) -> Result<(), Error> {
+    if ids.len() > MAX_BATCH_SIZE {
+        return Err(Error::InvalidInput(format!(
+            "Cannot select more than {MAX_BATCH_SIZE} Todo items at once"
+        )));
+    }
+    Ok(())
+}
+
+/// Sanitizes the text conditions of a filter, so that they compare equal to the stored values.
+pub(crate) fn filter(filter: TodoFilter) -> Result<TodoFilter, Error> {
+    Ok(TodoFilter {
+        tag: filter.tag.as_deref().map(tag).transpose()?,
+        project: filter.project.as_deref().map(project).transpose()?,
+        context: filter.context.as_deref().map(context).transpose()?,
+        ..filter
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trims_whitespace() {
+        assert_eq!(description("  Buy milk \n").unwrap(), "Buy milk");
+    }
+
+    #[test]
+    fn test_rejects_empty_text() {
+        assert!(matches!(description("   "), Err(Error::InvalidInput(_))));
+        assert!(matches!(tag("\u{200B}"), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_removes_zero_width_characters() {
+        assert_eq!(tag("ur\u{200B}gent\u{FEFF}").unwrap(), "urgent");
+    }
+
+    #[test]
+    fn test_nested_tags() {
+        assert_eq!(tag(" work / projectX ").unwrap(), "work/projectX");
+        assert!(matches!(tag("work//projectX"), Err(Error::InvalidInput(_))));
+        assert!(matches!(tag("/work"), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_color() {
+        assert_eq!(color(" #FF8800 ").unwrap(), "#ff8800");
+        assert!(matches!(color("ff8800"), Err(Error::InvalidInput(_))));
+        assert!(matches!(color("#ff880"), Err(Error::InvalidInput(_))));
+        assert!(matches!(color("#gg8800"), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_normalizes_to_nfc() {
+        assert_eq!(description("Cafe\u{301}").unwrap(), "Caf\u{E9}");
+    }
+
+    #[test]
+    fn test_rejects_control_characters() {
+        assert!(matches!(description("Buy\u{7}milk"), Err(Error::InvalidInput(_))));
+        assert!(matches!(description("Buy\nmilk"), Err(Error::InvalidInput(_))));
+        assert_eq!(notes("Buy\nmilk").unwrap(), "Buy\nmilk");
+    }
+
+    #[test]
+    fn test_rejects_text_exceeding_max_length() {
+        assert!(tag(&"a".repeat(MAX_TAG_LENGTH)).is_ok());
+        assert!(matches!(tag(&"a".repeat(MAX_TAG_LENGTH + 1)), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_context() {
+        assert_eq!(context(" home ").unwrap(), "@home");
+        assert_eq!(context("@errands").unwrap(), "@errands");
+        assert!(matches!(context(&"a".repeat(MAX_CONTEXT_LENGTH)), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_attachment_metadata() {
+        assert_eq!(file_name(" receipt.pdf ").unwrap(), "receipt.pdf");
+        assert!(matches!(file_name("../etc/passwd"), Err(Error::InvalidInput(_))));
+        assert_eq!(mime_type("Image/PNG").unwrap(), "image/png");
+        assert!(matches!(mime_type("image"), Err(Error::InvalidInput(_))));
+        assert!(matches!(mime_type("text/html; charset=utf-8"), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_https_url() {
+        assert!(https_url(" https://api.mailgun.net/v3/example.com/messages ").is_ok());
+        assert!(matches!(https_url("http://example.com"), Err(Error::InvalidInput(_))));
+        assert!(matches!(https_url("https:///path"), Err(Error::InvalidInput(_))));
+        assert!(matches!(email_address("example.com"), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_create_request() {
+        let request = create_request(CreateTodo {
+            description: " Buy milk ".to_string(),
+            tags: Some(vec![" home ".to_string()]),
+            notes: Some("".to_string()),
+            ..Default::default()
+        });
+        assert!(matches!(request, Err(Error::InvalidInput(_))));
+
+        let request = create_request(CreateTodo {
+            description: " Buy milk ".to_string(),
+            tags: Some(vec![" home ".to_string()]),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(request.description, "Buy milk");
+        assert_eq!(request.tags, Some(vec!["home".to_string()]));
+    }
+}
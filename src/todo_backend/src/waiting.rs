@@ -0,0 +1,81 @@
+use candid::{CandidType, Deserialize};
+
+use crate::{stats::NANOS_PER_DAY, todo::Todo};
+
+/// Represents an open Todo item that is waiting on someone.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct WaitingTodo {
+    /// The Todo item.
+    pub(crate) todo: Todo,
+    /// Number of whole days the Todo item has been waiting.
+    pub(crate) waiting_days: u32,
+    /// Whether the Todo item has been waiting long enough to follow up on.
+    pub(crate) needs_follow_up: bool,
+}
+
+/// Lists the open Todo items that are waiting on someone, longest waiting first.
+///
+/// # Arguments
+///
+/// * `todos` - All Todo items of the principal.
+/// * `now` - The current time, in nanoseconds since the Unix epoch.
+/// * `follow_up_after_days` - Number of days after which a Todo item needs a follow-up.
+///
+/// # Returns
+///
+/// A vector of waiting Todo items.
+pub(crate) fn list(todos: Vec<Todo>, now: u64, follow_up_after_days: u32) -> Vec<WaitingTodo> {
+    let mut waiting: Vec<WaitingTodo> = todos
+        .into_iter()
+        .filter(|todo| !todo.is_completed && todo.waiting_on.is_some())
+        .map(|todo| {
+            let since = todo.waiting_since.unwrap_or(now);
+            let waiting_days =
+                u32::try_from(now.saturating_sub(since) / NANOS_PER_DAY).unwrap_or(u32::MAX);
+            WaitingTodo {
+                todo,
+                waiting_days,
+                needs_follow_up: waiting_days >= follow_up_after_days,
+            }
+        })
+        .collect();
+    waiting.sort_by_key(|waiting| (waiting.todo.waiting_since, waiting.todo.id));
+    waiting
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::todo::Priority;
+
+    #[test]
+    fn test_list_waiting() {
+        let waiting = |id, who: Option<&str>, since| {
+            let mut todo = Todo::new(id, format!("Task {id}"), Priority::Medium);
+            todo.set_waiting_on(who.map(str::to_string), since);
+            todo
+        };
+        let mut completed = waiting(4, Some("Dana"), 0);
+        completed.set_completed(true, NANOS_PER_DAY);
+        let todos = vec![
+            waiting(1, Some("Alex"), 8 * NANOS_PER_DAY),
+            waiting(2, Some("Sam"), NANOS_PER_DAY),
+            waiting(3, None, 0),
+            completed,
+        ];
+
+        let list = list(todos, 10 * NANOS_PER_DAY, 7);
+        let summary: Vec<(u64, u32, bool)> = list
+            .iter()
+            .map(|waiting| {
+                (
+                    waiting.todo.id,
+                    waiting.waiting_days,
+                    waiting.needs_follow_up,
+                )
+            })
+            .collect();
+        assert_eq!(summary, vec![(2, 9, true), (1, 2, false)]);
+    }
+}